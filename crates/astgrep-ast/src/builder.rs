@@ -100,7 +100,8 @@ impl AstBuilder {
         body: UniversalNode,
     ) -> UniversalNode {
         let mut node = UniversalNode::new(NodeType::FunctionDeclaration)
-            .with_identifier(name.to_string());
+            .with_identifier(name.to_string())
+            .with_attribute("name".to_string(), name.to_string());
         
         for param in parameters {
             node = node.add_child(param);
@@ -141,7 +142,8 @@ impl AstBuilder {
     /// Create a variable declaration node
     pub fn variable_declaration(name: &str, initializer: Option<UniversalNode>) -> UniversalNode {
         let mut node = UniversalNode::new(NodeType::VariableDeclaration)
-            .with_identifier(name.to_string());
+            .with_identifier(name.to_string())
+            .with_attribute("name".to_string(), name.to_string());
         
         if let Some(init) = initializer {
             node = node.add_child(init);
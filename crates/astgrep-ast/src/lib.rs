@@ -6,10 +6,12 @@
 pub mod nodes;
 pub mod visitor;
 pub mod builder;
+pub mod scope;
 
 pub use nodes::*;
 pub use visitor::*;
 pub use builder::*;
+pub use scope::*;
 
 use astgrep_core::{AstNode, Result};
 use serde::{Deserialize, Serialize};
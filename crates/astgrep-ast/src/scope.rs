@@ -0,0 +1,546 @@
+//! Lexical scopes and closure captures
+//!
+//! Functions, methods, lambdas, arrow functions, classes and blocks each open a scope.
+//! A [`ScopeTree`] records the names declared in every scope and the scope each
+//! identifier resolves to, so a variable used inside a closure but declared in an
+//! enclosing function is known to be captured instead of being mistaken for a local or
+//! for an unrelated variable of the same name in a sibling closure.
+//!
+//! Nodes are identified by their pre-order index, the order in which
+//! `ast_utils::visit_nodes` visits them, so analyses walking the same AST can map
+//! their own nodes onto scopes without the AST carrying ids.
+
+use crate::nodes::UniversalNode;
+use astgrep_core::AstNode;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Index of a scope in its [`ScopeTree`]
+pub type ScopeId = usize;
+
+/// The file-level scope every tree starts with
+pub const GLOBAL_SCOPE: ScopeId = 0;
+
+/// Kind of construct opening a scope
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScopeKind {
+    Global,
+    /// Named function or method
+    Function,
+    /// Lambda, arrow function or anonymous function expression
+    Closure,
+    Class,
+    Block,
+}
+
+impl ScopeKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ScopeKind::Global => "global",
+            ScopeKind::Function => "function",
+            ScopeKind::Closure => "closure",
+            ScopeKind::Class => "class",
+            ScopeKind::Block => "block",
+        }
+    }
+
+    /// Whether the scope is a function body; variables referenced across it are captured
+    pub fn is_function(&self) -> bool {
+        matches!(self, ScopeKind::Function | ScopeKind::Closure)
+    }
+}
+
+/// A lexical scope
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Scope {
+    pub id: ScopeId,
+    pub parent: Option<ScopeId>,
+    pub kind: ScopeKind,
+    /// Name of the function, method or class opening the scope
+    pub name: Option<String>,
+    /// Pre-order index of the node opening the scope
+    pub node: usize,
+    /// Names declared directly in this scope, with the pre-order index of their declaration
+    pub declarations: BTreeMap<String, usize>,
+}
+
+/// Read of or assignment to a name
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Reference {
+    pub name: String,
+    /// Pre-order index of the identifier
+    pub node: usize,
+    /// Innermost scope containing the identifier
+    pub scope: ScopeId,
+    pub is_write: bool,
+    /// Scope declaring the name; `None` when the file declares it nowhere
+    pub resolved: Option<ScopeId>,
+}
+
+/// Variable of an enclosing function referenced from inside a nested function or closure
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CapturedVariable {
+    pub name: String,
+    /// The nested function or closure capturing the variable
+    pub closure: ScopeId,
+    /// Scope declaring the variable
+    pub defining_scope: ScopeId,
+    /// Pre-order index of the declaration
+    pub declaration: usize,
+    /// Pre-order indices of the reads of the variable inside the closure
+    pub uses: Vec<usize>,
+    /// Pre-order indices of the assignments to the variable inside the closure
+    pub writes: Vec<usize>,
+}
+
+/// Scopes, name resolution and closure captures of one AST
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScopeTree {
+    scopes: Vec<Scope>,
+    references: Vec<Reference>,
+    captures: Vec<CapturedVariable>,
+    /// Innermost scope of every node by pre-order index; a scope-opening node is in its own scope
+    node_scopes: Vec<ScopeId>,
+}
+
+impl ScopeTree {
+    /// Build the scope tree of the AST rooted at `root`
+    pub fn build(root: &dyn AstNode) -> Self {
+        let mut builder = ScopeBuilder { tree: ScopeTree::default() };
+        builder.tree.scopes.push(Scope {
+            id: GLOBAL_SCOPE,
+            parent: None,
+            kind: ScopeKind::Global,
+            name: None,
+            node: 0,
+            declarations: BTreeMap::new(),
+        });
+        builder.visit(root, GLOBAL_SCOPE, false);
+        builder.finish()
+    }
+
+    pub fn scopes(&self) -> &[Scope] {
+        &self.scopes
+    }
+
+    pub fn scope(&self, id: ScopeId) -> Option<&Scope> {
+        self.scopes.get(id)
+    }
+
+    pub fn references(&self) -> &[Reference] {
+        &self.references
+    }
+
+    pub fn captures(&self) -> &[CapturedVariable] {
+        &self.captures
+    }
+
+    /// Variables captured by the closure or nested function opening `closure`
+    pub fn captures_of(&self, closure: ScopeId) -> impl Iterator<Item = &CapturedVariable> {
+        self.captures.iter().filter(move |c| c.closure == closure)
+    }
+
+    /// Innermost scope containing the node at pre-order index `node`
+    pub fn scope_of(&self, node: usize) -> Option<ScopeId> {
+        self.node_scopes.get(node).copied()
+    }
+
+    /// Scope declaring `name` as seen from `scope`
+    pub fn resolve(&self, scope: ScopeId, name: &str) -> Option<ScopeId> {
+        self.ancestors(scope).find(|s| self.scopes[*s].declarations.contains_key(name))
+    }
+
+    /// Innermost function or closure scope containing `scope`, itself included
+    pub fn enclosing_function(&self, scope: ScopeId) -> Option<ScopeId> {
+        self.ancestors(scope).find(|s| self.scopes[*s].kind.is_function())
+    }
+
+    /// `scope` followed by its parents up to the global scope
+    pub fn ancestors(&self, scope: ScopeId) -> impl Iterator<Item = ScopeId> + '_ {
+        std::iter::successors(self.scopes.get(scope).map(|s| s.id), move |id| self.scopes[*id].parent)
+    }
+
+    /// Mark the scope-opening nodes of `root`, the AST this tree was built from, with
+    /// `scope` (the scope kind) and `scope_id` attributes, and closures with the
+    /// comma-separated names they capture in `captures`
+    pub fn annotate(&self, root: &mut UniversalNode) {
+        let mut opened: BTreeMap<usize, &Scope> = BTreeMap::new();
+        for scope in self.scopes.iter().filter(|s| s.kind != ScopeKind::Global) {
+            opened.insert(scope.node, scope);
+        }
+        let mut index = 0;
+        self.annotate_node(root, &opened, &mut index);
+    }
+
+    fn annotate_node(&self, node: &mut UniversalNode, opened: &BTreeMap<usize, &Scope>, index: &mut usize) {
+        if let Some(scope) = opened.get(index) {
+            node.add_attribute("scope".to_string(), scope.kind.as_str().to_string());
+            node.add_attribute("scope_id".to_string(), scope.id.to_string());
+            let captured: Vec<&str> = self.captures_of(scope.id).map(|c| c.name.as_str()).collect();
+            if !captured.is_empty() {
+                node.add_attribute("captures".to_string(), captured.join(","));
+            }
+        }
+        *index += 1;
+        for child in node.children_mut() {
+            self.annotate_node(child, opened, index);
+        }
+    }
+}
+
+struct ScopeBuilder {
+    tree: ScopeTree,
+}
+
+impl ScopeBuilder {
+    /// Record the next node in pre-order as belonging to `scope`
+    fn enter(&mut self, scope: ScopeId) -> usize {
+        self.tree.node_scopes.push(scope);
+        self.tree.node_scopes.len() - 1
+    }
+
+    fn open(&mut self, kind: ScopeKind, parent: ScopeId, node: usize) -> ScopeId {
+        let id = self.tree.scopes.len();
+        self.tree.scopes.push(Scope {
+            id,
+            parent: Some(parent),
+            kind,
+            name: None,
+            node,
+            declarations: BTreeMap::new(),
+        });
+        self.tree.node_scopes[node] = id;
+        id
+    }
+
+    fn declare(&mut self, scope: ScopeId, name: &str, node: usize) {
+        if !name.is_empty() {
+            self.tree.scopes[scope].declarations.entry(name.to_string()).or_insert(node);
+        }
+    }
+
+    fn reference(&mut self, scope: ScopeId, name: &str, node: usize, is_write: bool) {
+        self.tree.references.push(Reference { name: name.to_string(), node, scope, is_write, resolved: None });
+    }
+
+    /// Visit `node` inside `scope`; `in_scope_body` is set for the direct children of a
+    /// scope-opening node, whose block body shares that node's scope
+    fn visit(&mut self, node: &dyn AstNode, scope: ScopeId, in_scope_body: bool) {
+        let index = self.enter(scope);
+        match scope_kind(node) {
+            Some(ScopeKind::Block) if in_scope_body => self.visit_children(node, scope, 0),
+            Some(ScopeKind::Block) => {
+                let block = self.open(ScopeKind::Block, scope, index);
+                self.visit_children(node, block, 0);
+            }
+            Some(kind) if kind.is_function() => self.visit_function(node, kind, scope, index),
+            Some(_) => self.visit_class(node, scope, index),
+            None => match node.node_type() {
+                "variable_declaration" => self.visit_declaration(node, scope, index),
+                "assignment_expression" => {
+                    let mut start = 0;
+                    if let Some(target) = node.child(0).filter(|c| is_name(*c)) {
+                        let target_index = self.enter(scope);
+                        self.reference(scope, &name_of(target), target_index, true);
+                        self.visit_children(target, scope, 0);
+                        start = 1;
+                    }
+                    self.visit_children(node, scope, start);
+                }
+                "member_expression" => {
+                    // Only the object is a variable; the property names a member
+                    for i in 0..node.child_count() {
+                        if let Some(child) = node.child(i) {
+                            if i > 0 && is_name(child) {
+                                self.enter(scope);
+                                self.visit_children(child, scope, 0);
+                            } else {
+                                self.visit(child, scope, false);
+                            }
+                        }
+                    }
+                }
+                _ if is_name(node) => {
+                    self.reference(scope, &name_of(node), index, false);
+                    self.visit_children(node, scope, 0);
+                }
+                _ => self.visit_children(node, scope, 0),
+            },
+        }
+    }
+
+    fn visit_children(&mut self, node: &dyn AstNode, scope: ScopeId, start: usize) {
+        for i in start..node.child_count() {
+            if let Some(child) = node.child(i) {
+                self.visit(child, scope, false);
+            }
+        }
+    }
+
+    /// Visit an identifier declaring its name in `declaring_scope`
+    fn visit_declared_name(&mut self, node: &dyn AstNode, scope: ScopeId, declaring_scope: ScopeId) {
+        let index = self.enter(scope);
+        self.declare(declaring_scope, &name_of(node), index);
+        self.visit_children(node, scope, 0);
+    }
+
+    /// A function's name is declared in the enclosing scope and its parameters in its own.
+    /// The last child is the body; earlier identifiers are the name (for named functions)
+    /// and the parameters.
+    fn visit_function(&mut self, node: &dyn AstNode, kind: ScopeKind, parent: ScopeId, index: usize) {
+        let scope = self.open(kind, parent, index);
+        let mut unnamed = true;
+        if let Some(name) = node.get_attribute("name") {
+            self.declare(parent, name, index);
+            self.tree.scopes[scope].name = Some(name.to_string());
+            unnamed = false;
+        }
+        if let Some(parameters) = node.get_attribute("parameters") {
+            for parameter in parameters.split(',') {
+                self.declare(scope, parameter_name(parameter), index);
+            }
+        }
+
+        let mut expects_name = unnamed && (kind == ScopeKind::Function || ts_kind(node) == Some("function_expression"));
+        let body = node.child_count().saturating_sub(1);
+        for i in 0..node.child_count() {
+            let Some(child) = node.child(i) else { continue };
+            if i < body && is_name(child) {
+                if expects_name {
+                    let name = name_of(child);
+                    self.tree.scopes[scope].name = Some(name);
+                    self.visit_declared_name(child, scope, parent);
+                    expects_name = false;
+                } else {
+                    self.visit_declared_name(child, scope, scope);
+                }
+            } else if i < body && is_parameter_list(child) {
+                self.visit_parameters(child, scope);
+            } else {
+                self.visit(child, scope, true);
+            }
+        }
+    }
+
+    fn visit_parameters(&mut self, list: &dyn AstNode, scope: ScopeId) {
+        self.enter(scope);
+        for i in 0..list.child_count() {
+            let Some(child) = list.child(i) else { continue };
+            if is_name(child) {
+                self.visit_declared_name(child, scope, scope);
+                continue;
+            }
+            self.enter(scope);
+            // `x: int` and `x = 1` name the parameter first, `String x` last
+            let names: Vec<usize> = (0..child.child_count())
+                .filter(|j| child.child(*j).is_some_and(is_name))
+                .collect();
+            let declared = match ts_kind(child) {
+                Some("typed_parameter" | "default_parameter" | "typed_default_parameter" | "assignment_pattern"
+                    | "required_parameter" | "optional_parameter") => names.first(),
+                _ => names.last(),
+            }
+            .copied();
+            for j in 0..child.child_count() {
+                if let Some(grandchild) = child.child(j) {
+                    if Some(j) == declared {
+                        self.visit_declared_name(grandchild, scope, scope);
+                    } else {
+                        self.visit(grandchild, scope, false);
+                    }
+                }
+            }
+        }
+    }
+
+    fn visit_class(&mut self, node: &dyn AstNode, parent: ScopeId, index: usize) {
+        let scope = self.open(ScopeKind::Class, parent, index);
+        let mut expects_name = true;
+        if let Some(name) = node.get_attribute("name") {
+            self.declare(parent, name, index);
+            self.tree.scopes[scope].name = Some(name.to_string());
+            expects_name = false;
+        }
+        for i in 0..node.child_count() {
+            let Some(child) = node.child(i) else { continue };
+            if expects_name && is_name(child) {
+                self.tree.scopes[scope].name = Some(name_of(child));
+                self.visit_declared_name(child, scope, parent);
+                expects_name = false;
+            } else {
+                self.visit(child, scope, true);
+            }
+        }
+    }
+
+    fn visit_declaration(&mut self, node: &dyn AstNode, scope: ScopeId, index: usize) {
+        let mut expects_name = true;
+        if let Some(name) = node.get_attribute("name") {
+            self.declare(scope, name, index);
+            expects_name = false;
+        }
+        for i in 0..node.child_count() {
+            let Some(child) = node.child(i) else { continue };
+            if expects_name && is_name(child) {
+                // The declaration node, which its initializer flows into, stands for the variable
+                self.enter(scope);
+                self.declare(scope, &name_of(child), index);
+                self.visit_children(child, scope, 0);
+                expects_name = false;
+            } else {
+                self.visit(child, scope, false);
+            }
+        }
+    }
+
+    /// Resolve references and derive captures. An assignment to a name declared nowhere
+    /// declares it in the innermost enclosing function, as in Python or sloppy-mode
+    /// JavaScript; an assignment to a variable of an enclosing function is a captured write.
+    fn finish(mut self) -> ScopeTree {
+        for i in 0..self.tree.references.len() {
+            let reference = &self.tree.references[i];
+            if reference.is_write && self.tree.resolve(reference.scope, &reference.name).is_none() {
+                let target = self.tree.enclosing_function(reference.scope).unwrap_or(GLOBAL_SCOPE);
+                let (name, node) = (reference.name.clone(), reference.node);
+                self.declare(target, &name, node);
+            }
+        }
+
+        let mut captures: BTreeMap<(ScopeId, String), CapturedVariable> = BTreeMap::new();
+        for i in 0..self.tree.references.len() {
+            let (scope, name) = (self.tree.references[i].scope, self.tree.references[i].name.clone());
+            let Some(defining) = self.tree.resolve(scope, &name) else { continue };
+            self.tree.references[i].resolved = Some(defining);
+
+            // Variables of the file or of a class body are not captured by functions
+            if self.tree.enclosing_function(defining).is_none() {
+                continue;
+            }
+            let declaration = self.tree.scopes[defining].declarations[&name];
+            let crossed: Vec<ScopeId> = self
+                .tree
+                .ancestors(scope)
+                .take_while(|s| *s != defining)
+                .filter(|s| self.tree.scopes[*s].kind.is_function())
+                .collect();
+            let reference = &self.tree.references[i];
+            for closure in crossed {
+                let capture = captures.entry((closure, name.clone())).or_insert_with(|| CapturedVariable {
+                    name: name.clone(),
+                    closure,
+                    defining_scope: defining,
+                    declaration,
+                    uses: Vec::new(),
+                    writes: Vec::new(),
+                });
+                if reference.is_write {
+                    capture.writes.push(reference.node);
+                } else {
+                    capture.uses.push(reference.node);
+                }
+            }
+        }
+        self.tree.captures = captures.into_values().collect();
+        self.tree
+    }
+}
+
+/// Kind of scope `node` opens, if any
+fn scope_kind(node: &dyn AstNode) -> Option<ScopeKind> {
+    match node.node_type() {
+        "lambda_expression" | "arrow_function" => Some(ScopeKind::Closure),
+        // The tree-sitter front end maps anonymous functions to function declarations
+        "function_declaration" | "method_declaration" => match ts_kind(node) {
+            Some("arrow_function" | "function_expression" | "lambda" | "lambda_expression" | "closure_expression"
+                | "anonymous_function" | "anonymous_function_creation_expression") => Some(ScopeKind::Closure),
+            _ => Some(ScopeKind::Function),
+        },
+        "class_declaration" => Some(ScopeKind::Class),
+        "block_statement" => Some(ScopeKind::Block),
+        _ => None,
+    }
+}
+
+fn ts_kind(node: &dyn AstNode) -> Option<&str> {
+    node.get_attribute("ts_kind")
+}
+
+/// Whether `node` is an identifier naming a variable (not a type, property or keyword)
+fn is_name(node: &dyn AstNode) -> bool {
+    node.node_type() == "identifier" && matches!(ts_kind(node), None | Some("identifier" | "variable_name"))
+}
+
+fn is_parameter_list(node: &dyn AstNode) -> bool {
+    ts_kind(node).is_some_and(|kind| kind.contains("parameters"))
+}
+
+fn name_of(node: &dyn AstNode) -> String {
+    node.get_attribute("name").or_else(|| node.text()).unwrap_or("").trim().to_string()
+}
+
+/// Variable name of a parameter written as source text (`x`, `x = 1`, `x: int`, `String x`)
+fn parameter_name(parameter: &str) -> &str {
+    let declarator = parameter.split(['=', ':']).next().unwrap_or("").trim();
+    declarator.rsplit(|c: char| c.is_whitespace() || c == '*' || c == '&').next().unwrap_or("").trim_start_matches("...")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::AstBuilder;
+    use crate::nodes::NodeType;
+
+    fn closure(parameters: &[&str], body: UniversalNode) -> UniversalNode {
+        let mut node = UniversalNode::new(NodeType::LambdaExpression);
+        for parameter in parameters {
+            node = node.with_parameter(parameter.to_string());
+        }
+        node.add_child(body)
+    }
+
+    #[test]
+    fn test_closure_captures_enclosing_variable() {
+        // function handler(req) {
+        //     let data = req;
+        //     run(() => exec(data));
+        //     each((data) => log(data));
+        // }
+        let body = AstBuilder::block_statement(vec![
+            AstBuilder::variable_declaration("data", Some(AstBuilder::identifier("req"))),
+            AstBuilder::call_expression(
+                AstBuilder::identifier("run"),
+                vec![closure(&[], AstBuilder::call_expression(AstBuilder::identifier("exec"), vec![AstBuilder::identifier("data")]))],
+            ),
+            AstBuilder::call_expression(
+                AstBuilder::identifier("each"),
+                vec![closure(&["data"], AstBuilder::call_expression(AstBuilder::identifier("log"), vec![AstBuilder::identifier("data")]))],
+            ),
+        ]);
+        let mut root = AstBuilder::program(vec![AstBuilder::function_declaration(
+            "handler",
+            vec![AstBuilder::identifier("req")],
+            body,
+        )]);
+        let tree = ScopeTree::build(&root);
+
+        let handler = tree.scopes().iter().find(|s| s.kind == ScopeKind::Function).unwrap();
+        assert_eq!(handler.name.as_deref(), Some("handler"));
+        assert!(handler.declarations.contains_key("req") && handler.declarations.contains_key("data"));
+        assert!(tree.scope(GLOBAL_SCOPE).unwrap().declarations.contains_key("handler"));
+
+        // Only the first closure captures `data`; the second one's parameter shadows it
+        let closures: Vec<&Scope> = tree.scopes().iter().filter(|s| s.kind == ScopeKind::Closure).collect();
+        assert_eq!(closures.len(), 2);
+        assert_eq!(tree.captures().len(), 1);
+        let capture = &tree.captures()[0];
+        assert_eq!((capture.name.as_str(), capture.closure, capture.defining_scope), ("data", closures[0].id, handler.id));
+        assert_eq!(capture.uses.len(), 1);
+        assert_eq!(tree.scope_of(capture.uses[0]), Some(closures[0].id));
+        assert_eq!(tree.resolve(closures[1].id, "data"), Some(closures[1].id));
+
+        tree.annotate(&mut root);
+        let lambda = &root.children[0].children[1].children[1].children[1];
+        assert_eq!(lambda.get_attribute("scope").map(String::as_str), Some("closure"));
+        assert_eq!(lambda.get_attribute("captures").map(String::as_str), Some("data"));
+    }
+}
@@ -1,15 +1,35 @@
 //! Enhanced analyze command with advanced features
 
 use anyhow::Result;
-use astgrep_core::{Language, OutputFormat, Severity, Confidence};
-use std::path::PathBuf;
+use astgrep_core::{is_test_file, record_diagnostic, test_severity, CancellationToken, DiagnosticKind, Language, OutputFormat, Redaction, Severity, Confidence, TestFilePolicy};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
 use std::time::Instant;
-use tracing::{info, warn};
+use tracing::{debug, info, warn};
 use crate::{EnhancedAnalysisConfig, PerformanceProfiler};
-use crate::tree_sitter_analyzer::TreeSitterAnalyzer;
+use crate::commands::color::Painter;
+use crate::commands::ignore_file::IgnoreStack;
+use crate::commands::archive::ExtractedArchives;
+use crate::commands::image::ExtractedImage;
+use crate::commands::isolation::{run_isolated, FailedFile, FailureReason};
+use crate::commands::target_filter::{check_target, SkippedFile};
+use crate::commands::progress::Progress;
+use crate::commands::worker_pool::{map_files, worker_count};
+use astgrep_parser::{split_component, ComponentKind};
+
+/// Target that reads the source to analyze from stdin
+pub const STDIN_TARGET: &str = "-";
+
+/// Path reported for stdin without `--stdin-filename`
+const STDIN_FILENAME: &str = "<stdin>";
+
+/// Key of finding fingerprints in SARIF `partialFingerprints`
+pub(crate) const SARIF_FINGERPRINT_KEY: &str = "astgrepFingerprint/v1";
 
 // Simplified types for demonstration
-#[derive(Debug, Clone, serde::Serialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct Finding {
     pub rule_id: String,
     pub message: String,
@@ -17,9 +37,87 @@ pub struct Finding {
     pub confidence: Confidence,
     pub location: Location,
     pub fix: Option<String>,
+    /// Container image layer that introduced the file (image scans only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub layer: Option<String>,
+    /// Category of project-level findings that do not come from a rule file, e.g. `supply-chain`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    /// Redaction applied to the matched text, also used when displaying the source line
+    #[serde(skip)]
+    pub redaction: Option<Redaction>,
+    /// Whether the finding is in a test file
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub is_test: bool,
+    /// The rule traced data from a taint source into the match, or the finding is the flow itself
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub taint_corroborated: bool,
+    /// Risk score from 0 to 100, assigned by the scoring pass
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub risk_score: Option<u8>,
+    /// Estimated remediation effort, assigned by the scoring pass
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub effort: Option<crate::commands::risk::Effort>,
+    /// Suppressed by an inline `astgrep-ignore` / `nosem` comment; reported separately
+    #[serde(skip)]
+    pub suppressed: bool,
+    /// Line-independent identifier kept across runs, assigned by the fingerprinting pass
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fingerprint: Option<String>,
 }
 
-#[derive(Debug, Clone, serde::Serialize)]
+impl Default for Finding {
+    fn default() -> Self {
+        Self {
+            rule_id: String::new(),
+            message: String::new(),
+            severity: Severity::Info,
+            confidence: Confidence::Medium,
+            location: Location::default(),
+            fix: None,
+            layer: None,
+            category: None,
+            redaction: None,
+            is_test: false,
+            taint_corroborated: false,
+            risk_score: None,
+            effort: None,
+            suppressed: false,
+            fingerprint: None,
+        }
+    }
+}
+
+impl Finding {
+    /// A finding of a rule at `location`; the passes after matching fill in the rest
+    pub fn new(rule_id: impl Into<String>, message: impl Into<String>, severity: Severity, confidence: Confidence, location: Location) -> Self {
+        Self { rule_id: rule_id.into(), message: message.into(), severity, confidence, location, ..Self::default() }
+    }
+
+    /// Identifier of a finding's rule and exact location (FNV-1a), used to name artifacts
+    /// and key triage states
+    pub fn location_id(&self) -> String {
+        let key = format!(
+            "{}|{}|{}:{}-{}:{}",
+            self.rule_id,
+            self.location.file.to_string_lossy(),
+            self.location.start_line,
+            self.location.start_column,
+            self.location.end_line,
+            self.location.end_column,
+        );
+        let hash = key.bytes().fold(0xcbf29ce484222325u64, |h, b| (h ^ b as u64).wrapping_mul(0x100000001b3));
+        format!("{:016x}", hash)
+    }
+
+    /// The fingerprint, or the location id of a finding that did not go through the
+    /// fingerprinting pass
+    pub fn stable_id(&self) -> String {
+        self.fingerprint.clone().unwrap_or_else(|| self.location_id())
+    }
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
 pub struct Location {
     #[serde(serialize_with = "serialize_pathbuf")]
     pub file: PathBuf,
@@ -29,6 +127,19 @@ pub struct Location {
     pub end_column: usize,
 }
 
+impl Location {
+    pub fn new(file: impl Into<PathBuf>, start_line: usize, start_column: usize, end_line: usize, end_column: usize) -> Self {
+        Self { file: file.into(), start_line, start_column, end_line, end_column }
+    }
+}
+
+/// A finding of `rule_id` on `line` of `file`, for tests; other fields are set with
+/// struct update syntax
+#[cfg(test)]
+pub(crate) fn test_finding(rule_id: &str, file: impl Into<PathBuf>, line: usize) -> Finding {
+    Finding::new(rule_id, "Avoid eval", Severity::Error, Confidence::High, Location::new(file, line, 1, line, 9))
+}
+
 fn serialize_pathbuf<S>(path: &PathBuf, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: serde::Serializer,
@@ -37,32 +148,176 @@ where
 }
 
 /// Run enhanced analysis with advanced features
-pub async fn run_enhanced(config: EnhancedAnalysisConfig, output_file: Option<PathBuf>) -> Result<()> {
-    let start_time = Instant::now();
-
+pub async fn run_enhanced(mut config: EnhancedAnalysisConfig, output_file: Option<PathBuf>) -> Result<()> {
     info!("Starting enhanced analysis");
 
-    // Collect target files
-    let target_files = collect_target_files(&config).await?;
-    info!("Found {} files to analyze", target_files.len());
+    // `process::exit` skips destructors, so the scratch directories are removed first
+    let extracted = ExtractedTargets::extract(&mut config)?;
+    let exit_code = analyze_extracted(config, output_file, &extracted).await?;
+    drop(extracted);
+    if exit_code != 0 {
+        std::process::exit(exit_code);
+    }
+    Ok(())
+}
+
+/// Image and archive targets unpacked to scratch directories, which are removed on drop
+pub(crate) struct ExtractedTargets {
+    image: Option<(ExtractedImage, tempfile::TempDir)>,
+    archives: ExtractedArchives,
+}
 
-    if target_files.is_empty() {
-        warn!("No files found to analyze");
-        return Ok(());
+impl ExtractedTargets {
+    /// Unpack the image and archive targets of `config`, pointing its targets at them
+    pub(crate) fn extract(config: &mut EnhancedAnalysisConfig) -> Result<Self> {
+        // Container images are unpacked to a scratch rootfs that replaces the targets
+        let image = match config.image {
+            Some(ref image) => {
+                let work_dir = tempfile::Builder::new().prefix("astgrep-image-").tempdir()?;
+                let extracted = crate::commands::image::extract_image(image, work_dir.path(), &config.exclude_patterns)?;
+                config.target_paths = vec![extracted.rootfs.clone()];
+                config.workspace = false;
+                Some((extracted, work_dir))
+            }
+            None => None,
+        };
+        // Archive targets are unpacked to scratch directories analyzed in their place
+        let archives = crate::commands::archive::extract_targets(&mut config.target_paths)?;
+        Ok(Self { image, archives })
+    }
+
+    fn image(&self) -> Option<&ExtractedImage> {
+        self.image.as_ref().map(|(extracted, _)| extracted)
+    }
+}
+
+/// Analyze the targets of `config` and write the report; returns the exit code of the run
+pub(crate) async fn analyze_extracted(mut config: EnhancedAnalysisConfig, output_file: Option<PathBuf>, extracted: &ExtractedTargets) -> Result<i32> {
+    let start_time = Instant::now();
+
+    if config.diagnostics.is_some() {
+        astgrep_core::enable_diagnostics();
+    }
+    if config.metrics_out.is_some() {
+        crate::commands::metrics::enable();
+    }
+
+    let cancellation = CancellationToken::new();
+    cancel_on_ctrl_c(&cancellation);
+    let budget = config.scan_profile.as_ref().and_then(|p| p.time_budget());
+    if let Some(budget) = budget {
+        cancel_after(&cancellation, budget);
     }
 
+    let degraded_languages = check_parser_health(&config.languages);
+    report_rule_conflicts(&config);
+
+    let image = extracted.image();
+    let archives = &extracted.archives;
+
+    // JSON lines are written as each file completes unless an option needs the whole run
+    let stream = match config.output_format {
+        OutputFormat::Jsonl if streams_findings(&config) => Some(FindingStream::new(&config, image, archives, output_file.as_deref())?),
+        _ => None,
+    };
+
     // Run simplified analysis
     let mut all_findings = Vec::new();
     let mut analysis_stats = AnalysisStatistics::new();
+    analysis_stats.degraded_languages = degraded_languages;
+    analysis_stats.profile = config.scan_profile.clone();
+
+    if reads_stdin(&config)? {
+        analyze_stdin(&config, &mut all_findings, &mut analysis_stats, &cancellation)?;
+    } else if config.workspace {
+        analyze_workspace_roots(&config, &mut all_findings, &mut analysis_stats, &cancellation).await?;
+    } else {
+        // Collect target files
+        let target_files = collect_target_files(&config, &mut analysis_stats.skipped_files).await?;
+        info!("Found {} files to analyze", target_files.len());
+
+        if target_files.is_empty() && image.is_none() && !config.supply_chain {
+            warn!("No files found to analyze");
+            return Ok(0);
+        }
+
+        let mut shared_config = config.clone();
+        shared_config.project_index = project_index_for(&shared_config, &target_files);
+        let shared_config = Arc::new(shared_config);
+        analyze_files(target_files, &shared_config, &mut all_findings, &mut analysis_stats, &cancellation, stream.as_ref())?;
+    }
+
+    if config.supply_chain && !cancellation.is_cancelled() {
+        for target in config.target_paths.iter().filter(|t| t.is_dir()) {
+            all_findings.extend(crate::commands::supply_chain::check_project(target, &config));
+        }
+    }
+
+    // Partial results are still reported, marked as cancelled or out of budget
+    analysis_stats.budget_exhausted = cancellation.is_cancelled() && budget.is_some_and(|b| start_time.elapsed() >= b);
+    analysis_stats.cancelled = cancellation.is_cancelled() && !analysis_stats.budget_exhausted;
+    if analysis_stats.cancelled {
+        warn!("Analysis cancelled after {} file(s); reporting partial results", analysis_stats.files_analyzed);
+    } else if analysis_stats.budget_exhausted {
+        warn!("Time budget exhausted after {} file(s); reporting partial results", analysis_stats.files_analyzed);
+    }
 
-    for file_path in target_files {
-        info!("Analyzing file: {:?}", file_path);
-        analyze_file_simple(&file_path, &config, &mut all_findings, &mut analysis_stats)?;
+    if let Some(ref stream) = stream {
+        stream.emit(std::mem::take(&mut all_findings))?;
+    }
+    let (mut all_findings, suppressed) = prepare_findings(all_findings, &config, image, archives);
+    if config.sort_by_risk {
+        crate::commands::risk::sort_by_risk(&mut all_findings);
+    }
+
+    if let Some(image) = image {
+        analysis_stats.image = Some(image.summary());
+    }
+
+    if let Some(stream) = stream {
+        return stream.finish(&analysis_stats, start_time.elapsed());
+    }
+
+    // Findings silenced by inline comments are only listed on request
+    if !suppressed.is_empty() {
+        info!("{} finding(s) suppressed by inline comments", suppressed.len());
+    }
+    if config.report_suppressed {
+        analysis_stats.suppressed_findings = suppressed;
     }
 
     // Apply filters
     let filtered_findings = apply_filters(&all_findings, &config);
 
+    // Cancelled runs are incomplete and would make missing findings look fixed
+    if let Some(ref db) = config.record {
+        if analysis_stats.cancelled || analysis_stats.budget_exhausted {
+            warn!("Not recording an incomplete run to {}", db.display());
+        } else {
+            let targets = match config.image {
+                Some(ref image) => vec![PathBuf::from(image)],
+                None => archives.restore_targets(&config.target_paths),
+            };
+            crate::commands::trends::TrendStore::open(db)?.record_run(&targets, &filtered_findings)?;
+            info!("Recorded {} finding(s) to {}", filtered_findings.len(), db.display());
+        }
+    }
+
+    // The baseline is written before it is applied, so it can be refreshed in the same run
+    if let Some(ref path) = config.baseline_out {
+        crate::commands::baseline::Baseline::from_findings(&filtered_findings).write(path)?;
+        info!("Wrote a baseline of {} finding(s) to {}", filtered_findings.len(), path.display());
+    }
+    let filtered_findings = match config.baseline_file {
+        Some(ref path) => {
+            let (new_findings, matched) = crate::commands::baseline::Baseline::read(path)?.new_findings(filtered_findings);
+            info!("{} finding(s) are in the baseline {}", matched, path.display());
+            analysis_stats.baseline_matched = Some(matched);
+            new_findings
+        }
+        None => filtered_findings,
+    };
+
     // Apply max findings limit
     let limited_findings = if let Some(max) = config.max_findings {
         filtered_findings.into_iter().take(max).collect()
@@ -70,7 +325,26 @@ pub async fn run_enhanced(config: EnhancedAnalysisConfig, output_file: Option<Pa
         filtered_findings
     };
 
+    if let Some(ref dir) = config.emit_patches {
+        crate::commands::patches::emit_patches(&limited_findings, dir, config.combine_patches)?;
+    }
+    if let Some(ref dir) = config.emit_tests {
+        crate::commands::repro::emit_reproductions(&limited_findings, &config.rule_files, dir)?;
+    }
+    if config.fix || config.fix_dry_run {
+        let summary = crate::commands::autofix::apply_fixes(&limited_findings, &config.rule_files, config.fix_dry_run, config.fix_conflicts)?;
+        eprintln!("{}", summary.describe(config.fix_dry_run));
+        analysis_stats.skipped_fixes = summary.skipped;
+    }
+    write_diagnostics(&config)?;
+
+    if config.interactive {
+        crate::commands::tui::browse(limited_findings, Path::new(crate::commands::triage::DEFAULT_TRIAGE_FILE))?;
+        return Ok(0);
+    }
+
     // Generate output
+    config.color = config.color.resolve(output_file.is_none());
     let total_time = start_time.elapsed();
     let output = generate_enhanced_output(
         &limited_findings,
@@ -87,25 +361,367 @@ pub async fn run_enhanced(config: EnhancedAnalysisConfig, output_file: Option<Pa
     } else {
         println!("{}", output);
     }
+    if let Some(ref path) = config.metrics_out {
+        crate::commands::metrics::write(path, &limited_findings, &analysis_stats, total_time)?;
+        info!("Metrics written to: {}", path.display());
+    }
 
     // Exit with appropriate code
+    if analysis_stats.cancelled {
+        return Ok(130);
+    }
     if config.fail_on_findings && !limited_findings.is_empty() {
         info!("Found {} issues, exiting with error code", limited_findings.len());
-        std::process::exit(1);
+        return Ok(1);
     }
 
     info!("Analysis completed in {:?}", total_time);
+    Ok(0)
+}
+
+/// Drop duplicate findings and give the rest their fingerprint, redaction, test file
+/// handling, risk score and in-image or in-archive path. Works on the findings of a whole
+/// run or, when streaming, of one file at a time. Returns the findings and those silenced
+/// by inline comments.
+fn prepare_findings(
+    findings: Vec<Finding>,
+    config: &EnhancedAnalysisConfig,
+    image: Option<&ExtractedImage>,
+    archives: &ExtractedArchives,
+) -> (Vec<Finding>, Vec<Finding>) {
+    let (mut findings, duplicates) = crate::commands::fingerprint::dedup(findings);
+    if duplicates > 0 {
+        debug!("Dropped {} duplicate finding(s)", duplicates);
+    }
+    crate::commands::fingerprint::assign(&mut findings, |file| {
+        image.and_then(|image| image.locate(file)).map(|(path, _)| path).or_else(|| archives.locate(file))
+    });
+
+    redact_findings(&mut findings, config);
+    apply_test_file_policy(&mut findings, config);
+    crate::commands::risk::score_findings(&mut findings, &config.risk_model, &config.rule_files);
+
+    // Report image findings by in-image path and originating layer
+    if let Some(image) = image {
+        for finding in &mut findings {
+            if let Some((path, layer)) = image.locate(&finding.location.file) {
+                finding.layer = layer.cloned();
+                finding.location.file = path;
+            }
+        }
+    }
+    // and archive findings as <archive>!<entry>
+    for finding in &mut findings {
+        if let Some(path) = archives.locate(&finding.location.file) {
+            finding.location.file = path;
+        }
+    }
+
+    let (suppressed, findings): (Vec<Finding>, Vec<Finding>) = findings.into_iter().partition(|f| f.suppressed);
+    (findings, suppressed)
+}
+
+/// Whether `--format jsonl` can write findings as files complete: options that work on
+/// the findings of the whole run need them all first
+fn streams_findings(config: &EnhancedAnalysisConfig) -> bool {
+    !(config.workspace
+        || config.sort_by_risk
+        || config.record.is_some()
+        || config.baseline_out.is_some()
+        || config.emit_patches.is_some()
+        || config.emit_tests.is_some()
+        || config.fix
+        || config.fix_dry_run
+        || config.interactive
+        || config.metrics_out.is_some()
+        || config.output_template.is_some()
+        || config.compatible_mode.is_some())
+}
+
+/// Findings written as JSON lines as each file completes, after going through the steps
+/// the buffered output applies to the whole run
+struct FindingStream<'a> {
+    config: EnhancedAnalysisConfig,
+    image: Option<&'a ExtractedImage>,
+    archives: &'a ExtractedArchives,
+    baseline: Option<crate::commands::baseline::Baseline>,
+    writer: crate::commands::jsonl::JsonlWriter,
+    suppressed: AtomicUsize,
+    baseline_matched: AtomicUsize,
+}
+
+impl<'a> FindingStream<'a> {
+    fn new(
+        config: &EnhancedAnalysisConfig,
+        image: Option<&'a ExtractedImage>,
+        archives: &'a ExtractedArchives,
+        output_file: Option<&Path>,
+    ) -> Result<Self> {
+        let baseline = config.baseline_file.as_deref().map(crate::commands::baseline::Baseline::read).transpose()?;
+        let output: Box<dyn std::io::Write + Send> = match output_file {
+            Some(path) => Box::new(std::fs::File::create(path).map_err(|e| anyhow::anyhow!("Cannot create {}: {}", path.display(), e))?),
+            None => Box::new(std::io::stdout()),
+        };
+        Ok(Self {
+            config: config.clone(),
+            image,
+            archives,
+            baseline,
+            writer: crate::commands::jsonl::JsonlWriter::new(output, config.max_findings),
+            suppressed: AtomicUsize::new(0),
+            baseline_matched: AtomicUsize::new(0),
+        })
+    }
+
+    /// Prepare, filter and write the findings of one file
+    fn emit(&self, findings: Vec<Finding>) -> Result<()> {
+        if findings.is_empty() {
+            return Ok(());
+        }
+        let (findings, suppressed) = prepare_findings(findings, &self.config, self.image, self.archives);
+        self.suppressed.fetch_add(suppressed.len(), Ordering::Relaxed);
+        let findings = apply_filters(&findings, &self.config);
+        let findings = match self.baseline {
+            Some(ref baseline) => {
+                let (new_findings, matched) = baseline.new_findings(findings);
+                self.baseline_matched.fetch_add(matched, Ordering::Relaxed);
+                new_findings
+            }
+            None => findings,
+        };
+        self.writer.write(&findings)
+    }
+
+    /// Log the totals of the run; returns the exit code as the buffered output does
+    fn finish(self, stats: &AnalysisStatistics, total_time: std::time::Duration) -> Result<i32> {
+        let config = &self.config;
+        let suppressed = self.suppressed.into_inner();
+        if suppressed > 0 {
+            info!("{} finding(s) suppressed by inline comments", suppressed);
+        }
+        if let Some(ref path) = config.baseline_file {
+            info!("{} finding(s) are in the baseline {}", self.baseline_matched.into_inner(), path.display());
+        }
+        write_diagnostics(config)?;
+
+        let written = self.writer.written();
+        info!("Streamed {} finding(s) from {} file(s)", written, stats.files_analyzed);
+        if stats.cancelled {
+            return Ok(130);
+        }
+        if config.fail_on_findings && written > 0 {
+            info!("Found {} issues, exiting with error code", written);
+            return Ok(1);
+        }
+        info!("Analysis completed in {:?}", total_time);
+        Ok(0)
+    }
+}
+
+/// Write the fidelity diagnostics collected during the run to `--diagnostics`
+fn write_diagnostics(config: &EnhancedAnalysisConfig) -> Result<()> {
+    if let Some(ref path) = config.diagnostics {
+        let diagnostics = astgrep_core::take_diagnostics();
+        diagnostics.write_to(path)?;
+        info!("Wrote {} fidelity diagnostic(s) to {}", diagnostics.entries.len(), path.display());
+    }
+    Ok(())
+}
+
+/// Log how the `--rules` sources and the `--policy` replace and override rules; overrides
+/// that change nothing or name an unknown rule are likely mistakes and warned about
+fn report_rule_conflicts(config: &EnhancedAnalysisConfig) {
+    use astgrep_rules::RuleConflict;
+
+    let repository = rule_repository(config);
+    for conflict in repository.conflicts() {
+        match conflict {
+            RuleConflict::UnknownRule { .. } => warn!("{}", conflict),
+            RuleConflict::Overridden { fields, .. } if fields.is_empty() => warn!("{}", conflict),
+            _ => info!("{}", conflict),
+        }
+    }
+}
+
+/// Cancel `token` on the first Ctrl-C so the current file finishes and partial results are
+/// written; a second Ctrl-C exits immediately
+fn cancel_on_ctrl_c(token: &CancellationToken) {
+    let token = token.clone();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            eprintln!("Cancelling analysis (press Ctrl-C again to exit immediately)...");
+            token.cancel();
+            if tokio::signal::ctrl_c().await.is_ok() {
+                std::process::exit(130);
+            }
+        }
+    });
+}
+
+/// Cancel the analysis once a scan profile's time budget has elapsed
+fn cancel_after(token: &CancellationToken, budget: std::time::Duration) {
+    let token = token.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(budget).await;
+        token.cancel();
+    });
+}
+
+/// Parser health of every language, checked once per process
+pub(crate) fn parser_health() -> &'static astgrep_parser::ParserHealthReport {
+    static HEALTH: OnceLock<astgrep_parser::ParserHealthReport> = OnceLock::new();
+    HEALTH.get_or_init(|| astgrep_parser::LanguageParserRegistry::new().health_check())
+}
+
+/// Check the parsers of the configured languages and warn about any that will be
+/// analyzed with regex rules only
+fn check_parser_health(languages: &[Language]) -> Vec<DegradedLanguage> {
+    let degraded: Vec<DegradedLanguage> = parser_health()
+        .degraded()
+        .into_iter()
+        .filter(|(language, _)| languages.contains(language))
+        .map(|(language, reason)| DegradedLanguage { language: language.as_str().to_string(), reason: reason.to_string() })
+        .collect();
+
+    for entry in &degraded {
+        warn!("Parser for {} is unavailable ({}); falling back to regex-only analysis", entry.language, entry.reason);
+        eprintln!("⚠️  WARNING: {} parser is unavailable ({}); only pattern-regex rules will run for {} files", entry.language, entry.reason, entry.language);
+    }
+    degraded
+}
+
+/// Redact the matched text of findings whose rule (or the global `--redact` option)
+/// asks for it, in messages and fixes; must run while the analyzed files still exist
+fn redact_findings(findings: &mut [Finding], config: &EnhancedAnalysisConfig) {
+    let repository = rule_repository(config);
+    let mut sources: std::collections::HashMap<PathBuf, Option<String>> = std::collections::HashMap::new();
+
+    for finding in findings.iter_mut() {
+        let redaction = match repository.rule(&finding.rule_id) {
+            Some(rule) => Redaction::for_rule(&rule.metadata, config.redact),
+            None => config.redact,
+        };
+        let redaction = match redaction {
+            Some(redaction) => redaction,
+            None => continue,
+        };
+
+        let file = finding.location.file.clone();
+        let source = sources.entry(file.clone()).or_insert_with(|| std::fs::read_to_string(&file).ok());
+        let loc = &finding.location;
+        let location = astgrep_core::Location::new(file, loc.start_line, loc.start_column, loc.end_line, loc.end_column);
+        if let Some(secret) = source.as_deref().and_then(|s| astgrep_core::matched_text(s, &location)) {
+            finding.message = redaction.redact_in(&finding.message, secret);
+            finding.fix = finding.fix.as_ref().map(|fix| redaction.redact_in(fix, secret));
+        }
+        finding.redaction = Some(redaction);
+    }
+}
+
+/// Flag findings in test files and apply the test-file policy of their rule (or the
+/// global `--test-files` policy), dropping or re-rating them; test files are recognized
+/// by their path below the analyzed target
+fn apply_test_file_policy(findings: &mut Vec<Finding>, config: &EnhancedAnalysisConfig) {
+    let repository = rule_repository(config);
+
+    findings.retain_mut(|finding| {
+        let file = &finding.location.file;
+        let relative = config
+            .target_paths
+            .iter()
+            .filter_map(|target| file.strip_prefix(target).ok())
+            .min_by_key(|relative| relative.components().count())
+            .unwrap_or(file.as_path());
+        finding.is_test = is_test_file(relative);
+
+        let rule = repository.rule(&finding.rule_id);
+        let policy = match rule {
+            Some(rule) => TestFilePolicy::for_rule(&rule.metadata, config.test_files),
+            None => config.test_files,
+        };
+        match policy.apply(finding.severity, finding.is_test) {
+            Some(severity) => {
+                finding.severity = match rule {
+                    Some(rule) if finding.is_test => test_severity(&rule.metadata).unwrap_or(severity),
+                    _ => severity,
+                };
+                true
+            }
+            None => false,
+        }
+    });
+}
+
+/// The project index of `files` that dataflow rules follow across files, when dataflow
+/// is enabled
+fn project_index_for(config: &EnhancedAnalysisConfig, files: &[PathBuf]) -> Option<Arc<astgrep_dataflow::ProjectIndex>> {
+    if !config.enable_dataflow {
+        return None;
+    }
+    let index = crate::commands::index::index_for_analysis(&config.target_paths, files)?;
+    debug!("Project index of {} file(s) for cross-file dataflow", index.file_count());
+    Some(Arc::new(index))
+}
+
+/// Analyze each target directory as its own project root, applying its `.astgrep.yml`
+/// and recording per-root statistics for the merged report
+async fn analyze_workspace_roots(
+    config: &EnhancedAnalysisConfig,
+    findings: &mut Vec<Finding>,
+    stats: &mut AnalysisStatistics,
+    cancellation: &CancellationToken,
+) -> Result<()> {
+    use crate::commands::workspace::{config_for_root, RootStatistics, ROOT_CONFIG_FILE};
+
+    for root in &config.target_paths {
+        if cancellation.is_cancelled() {
+            break;
+        }
+        if !root.is_dir() {
+            warn!("Workspace root is not a directory: {}", root.display());
+            continue;
+        }
+
+        let mut root_config = config_for_root(config, root)?;
+        let root_files = collect_target_files(&root_config, &mut stats.skipped_files).await?;
+        info!("Workspace root {}: {} files to analyze", root.display(), root_files.len());
+        root_config.project_index = project_index_for(&root_config, &root_files);
+
+        let root_config = Arc::new(root_config);
+        let mut root_findings = Vec::new();
+        let mut root_stats = AnalysisStatistics::new();
+        analyze_files(root_files, &root_config, &mut root_findings, &mut root_stats, cancellation, None)?;
+
+        crate::commands::risk::score_findings(&mut root_findings, &root_config.risk_model, &root_config.rule_files);
+        let config_file = root.join(ROOT_CONFIG_FILE);
+        stats.files_analyzed += root_stats.files_analyzed;
+        stats.rules_executed += root_stats.rules_executed;
+        stats.analysis_errors += root_stats.analysis_errors;
+        stats.failed_files.extend(root_stats.failed_files);
+        stats.roots.push(RootStatistics {
+            root: root.display().to_string(),
+            config_file: config_file.is_file().then(|| config_file.display().to_string()),
+            files_analyzed: root_stats.files_analyzed,
+            rules_executed: root_stats.rules_executed,
+            total_findings: apply_filters(&root_findings, config).len(),
+        });
+        findings.extend(root_findings);
+    }
+
     Ok(())
 }
 
-async fn collect_target_files(config: &EnhancedAnalysisConfig) -> Result<Vec<PathBuf>> {
+pub(crate) async fn collect_target_files(config: &EnhancedAnalysisConfig, skipped: &mut Vec<SkippedFile>) -> Result<Vec<PathBuf>> {
     let mut files = Vec::new();
 
     for target in &config.target_paths {
         if target.is_file() {
-            files.push(target.clone());
+            match check_target(target, config.max_target_bytes) {
+                Some(skip) => skipped.push(skip),
+                None => files.push(target.clone()),
+            }
         } else if target.is_dir() {
-            collect_files_from_directory(target, &mut files, config)?;
+            collect_files_from_directory(target, &mut files, skipped, config, &IgnoreStack::above(target, config.git_ignore))?;
         } else {
             warn!("Target path does not exist: {}", target.display());
         }
@@ -114,28 +730,39 @@ async fn collect_target_files(config: &EnhancedAnalysisConfig) -> Result<Vec<Pat
     Ok(files)
 }
 
+/// Collect the files under `dir` to analyze, skipping paths its `.astgrepignore` files
+/// and those of `ignores` exclude, and noting binary and oversized files in `skipped`
 fn collect_files_from_directory(
     dir: &PathBuf,
     files: &mut Vec<PathBuf>,
+    skipped: &mut Vec<SkippedFile>,
     config: &EnhancedAnalysisConfig,
+    ignores: &IgnoreStack,
 ) -> Result<()> {
     use std::fs;
 
+    let ignores = ignores.enter(dir);
     for entry in fs::read_dir(dir)? {
         let entry = entry?;
         let path = entry.path();
+        let is_dir = path.is_dir();
 
-        if path.is_dir() {
-            collect_files_from_directory(&path, files, config)?;
+        if ignores.is_ignored(&path, is_dir) {
+            debug!("Skipping {} (ignore file)", path.display());
+        } else if is_dir {
+            collect_files_from_directory(&path, files, skipped, config, &ignores)?;
         } else if should_include_file(&path, config) {
-            files.push(path);
+            match check_target(&path, config.max_target_bytes) {
+                Some(skip) => skipped.push(skip),
+                None => files.push(path),
+            }
         }
     }
 
     Ok(())
 }
 
-fn should_include_file(path: &PathBuf, config: &EnhancedAnalysisConfig) -> bool {
+pub(crate) fn should_include_file(path: &Path, config: &EnhancedAnalysisConfig) -> bool {
     let path_str = path.to_string_lossy();
 
     // Check include patterns
@@ -148,11 +775,16 @@ fn should_include_file(path: &PathBuf, config: &EnhancedAnalysisConfig) -> bool
         }
     }
 
-    // Check exclude patterns
-    for pattern in &config.exclude_patterns {
-        if glob_match(pattern, &path_str) {
-            return false;
-        }
+    if is_excluded(path, &config.exclude_patterns) {
+        return false;
+    }
+
+    // Directory overrides decide regardless of the extension
+    if let Some(language) = config.language_override(path) {
+        return config.languages.contains(&language);
+    }
+    if let Some(kind) = ComponentKind::for_path(path) {
+        return kind.languages().iter().any(|language| config.languages.contains(language));
     }
 
     // Check if file extension matches supported languages (including extra preprocess source languages)
@@ -179,6 +811,12 @@ fn should_include_file(path: &PathBuf, config: &EnhancedAnalysisConfig) -> bool
     }
 }
 
+/// Whether one of the `--exclude` patterns matches `path`
+pub(crate) fn is_excluded(path: &Path, exclude_patterns: &[String]) -> bool {
+    let path_str = path.to_string_lossy();
+    exclude_patterns.iter().any(|pattern| glob_match(pattern, &path_str))
+}
+
 fn glob_match(pattern: &str, text: &str) -> bool {
     // Simple glob matching implementation
     // In a real implementation, you'd use a proper glob library
@@ -195,16 +833,137 @@ fn glob_match(pattern: &str, text: &str) -> bool {
     }
 }
 
+/// Analyze `files` on the worker pool of `config`, merging their findings and statistics
+/// in file order
+fn analyze_files(
+    files: Vec<PathBuf>,
+    config: &Arc<EnhancedAnalysisConfig>,
+    findings: &mut Vec<Finding>,
+    stats: &mut AnalysisStatistics,
+    cancellation: &CancellationToken,
+    stream: Option<&FindingStream<'_>>,
+) -> Result<()> {
+    let workers = worker_count(config.parallel, config.max_threads);
+    debug!("Analyzing {} file(s) with {} worker(s)", files.len(), workers);
+    let progress = Progress::new(files.len(), config.progress);
+    let cache = match config.cache_dir {
+        Some(ref dir) => Some(crate::commands::cache::FindingCache::open(dir, config, &files)?),
+        None => None,
+    };
+    let results = map_files(files, workers, cancellation, EngineWorker::default, |worker, file_path| {
+        debug!("Analyzing file: {:?}", file_path);
+        progress.start(&file_path);
+        let mut result = analyze_file_cached(file_path, config, worker, cancellation, cache.as_ref());
+        // Streamed findings are written here and not kept
+        if let (Some(stream), Ok((file_findings, _))) = (stream, &mut result) {
+            if let Err(e) = stream.emit(std::mem::take(file_findings)) {
+                warn!("Cannot write findings, stopping the analysis: {}", e);
+                cancellation.cancel();
+            }
+        }
+        progress.finish_file();
+        result
+    });
+    progress.finish();
+    let results = results?;
+    if let Some(cache) = cache {
+        info!("{} file(s) served from the analysis cache, {} analyzed", cache.hits(), cache.misses());
+    }
+
+    for result in results {
+        match result {
+            Ok((file_findings, file_stats)) => {
+                findings.extend(file_findings);
+                stats.files_analyzed += file_stats.files_analyzed;
+                if stats.rules_executed == 0 {
+                    stats.rules_executed = file_stats.rules_executed;
+                }
+            }
+            Err(failed) => {
+                if failed.reason == FailureReason::Error {
+                    stats.analysis_errors += 1;
+                }
+                stats.failed_files.push(failed);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Analyze one file, or take its findings from `cache` when neither the file nor the rule
+/// set changed since they were stored
+fn analyze_file_cached(
+    file_path: PathBuf,
+    config: &Arc<EnhancedAnalysisConfig>,
+    worker: &mut EngineWorker,
+    cancellation: &CancellationToken,
+    cache: Option<&crate::commands::cache::FindingCache>,
+) -> Result<(Vec<Finding>, AnalysisStatistics), FailedFile> {
+    let Some(cache) = cache else {
+        return analyze_file_isolated(file_path, config, worker, cancellation);
+    };
+    let key = cache.key(&file_path);
+    if let Some((findings, rules_executed)) = key.as_deref().and_then(|key| cache.get(key)) {
+        let mut stats = AnalysisStatistics::new();
+        stats.files_analyzed = 1;
+        stats.rules_executed = rules_executed;
+        return Ok((findings, stats));
+    }
+
+    let result = analyze_file_isolated(file_path, config, worker, cancellation);
+    // A cancelled file may be missing findings
+    if let (Some(key), Ok((findings, stats))) = (key, &result) {
+        if !cancellation.is_cancelled() {
+            cache.put(&key, findings, stats.rules_executed);
+        }
+    }
+    result
+}
+
+/// Analyze one file through [`run_isolated`], so that its errors, panics and overruns of
+/// `--timeout` become a [`FailedFile`] instead of ending the run
+pub(crate) fn analyze_file_isolated(
+    file_path: PathBuf,
+    config: &Arc<EnhancedAnalysisConfig>,
+    worker: &mut EngineWorker,
+    cancellation: &CancellationToken,
+) -> Result<(Vec<Finding>, AnalysisStatistics), FailedFile> {
+    let result = run_isolated(&file_path, config.file_timeout, cancellation, |token| {
+        let mut file_findings = Vec::new();
+        let mut file_stats = AnalysisStatistics::new();
+        analyze_file_simple(&file_path, config, worker, &mut file_findings, &mut file_stats, &token)?;
+        Ok((file_findings, file_stats))
+    });
+    // A panic may have left the engine half way through a file
+    if result.as_ref().is_err_and(|failed| failed.reason == FailureReason::Error) {
+        *worker = EngineWorker::default();
+    }
+    result
+}
+
 fn analyze_file_simple(
     file_path: &PathBuf,
     config: &EnhancedAnalysisConfig,
+    worker: &mut EngineWorker,
     findings: &mut Vec<Finding>,
     stats: &mut AnalysisStatistics,
+    cancellation: &CancellationToken,
 ) -> Result<()> {
     stats.files_analyzed += 1;
 
-    // Determine language from file extension
-    let language = determine_language(file_path)?;
+    // A directory override applies to the whole file, even a multi-language component
+    let overridden = config.language_override(file_path);
+    if overridden.is_none() {
+        if let Some(kind) = ComponentKind::for_path(file_path) {
+            return analyze_component(file_path, kind, config, worker, findings, stats, cancellation);
+        }
+    }
+
+    // Determine language from the override or the file extension
+    let language = match overridden {
+        Some(language) => language,
+        None => determine_language(file_path)?,
+    };
 
     // Skip if language is not in the configured languages
     if !config.languages.contains(&language) {
@@ -214,10 +973,28 @@ fn analyze_file_simple(
     // Read file content
     let source_code = std::fs::read_to_string(file_path)?;
 
+    if config.scan_profile.as_ref().is_some_and(|p| p.anchor_prefilter) && !contains_rule_anchor(&source_code, language, config) {
+        debug!("Skipping {:?}: no rule anchor found", file_path);
+        return Ok(());
+    }
+
+    // Languages whose parser failed the health check only get regex rules
+    if !config.rule_files.is_empty() && parser_health().is_degraded(language) {
+        let (file_findings, rules_count) = analyze_with_regex_rules(file_path, &source_code, language, config)?;
+        findings.extend(file_findings);
+        if stats.rules_executed == 0 {
+            stats.rules_executed = rules_count;
+        }
+        return Ok(());
+    }
+
     // Load rules if any are specified
     if !config.rule_files.is_empty() {
         // Use shared astgrep RuleEngine to ensure consistent behavior across CLI/GUI/Web
-        let (file_findings, rules_count) = analyze_with_rule_engine(file_path, &source_code, language, config)?;
+        let (mut file_findings, rules_count) = worker.analyze(file_path, &source_code, language, config, cancellation)?;
+        if config.map_source_locations {
+            map_to_original_sources(&mut file_findings, file_path, &source_code);
+        }
         findings.extend(file_findings);
         // Record executed rules count once
         if stats.rules_executed == 0 {
@@ -230,123 +1007,350 @@ fn analyze_file_simple(
     Ok(())
 }
 
-/// Real rule-based analysis using actual rule files
-/// Returns (findings, rules_count)
-fn analyze_with_basic_patterns(
-    file_path: &PathBuf,
-    source_code: &str,
-    language: Language,
-    config: &EnhancedAnalysisConfig,
-) -> Result<(Vec<Finding>, usize)> {
-    let mut findings = Vec::new();
-
-    // Load rules from the specified rule files/directories
-    let rules = load_rules_for_language(&config.rule_files, language)?;
-
-    if rules.is_empty() {
-        info!("No rules found for language {:?}", language);
-        return Ok((findings, 0));
+/// Findings of `source_code`, the possibly unsaved content of `file_path`, after the
+/// severity and confidence filters; suppressed findings are dropped and files of
+/// unsupported languages have none. Used by the language server, which analyzes
+/// documents as they are edited.
+pub(crate) fn analyze_source(file_path: &PathBuf, source_code: &str, config: &EnhancedAnalysisConfig) -> Result<Vec<Finding>> {
+    let language = match config.language_override(file_path).map(Ok).unwrap_or_else(|| determine_language(file_path)) {
+        Ok(language) => language,
+        Err(_) => return Ok(Vec::new()),
+    };
+    if config.rule_files.is_empty() {
+        return Ok(Vec::new());
     }
 
-    let rules_count = rules.len();
-    info!("Loaded {} rules for {:?}", rules_count, language);
+    let cancellation = CancellationToken::new();
+    let (findings, _) = if parser_health().is_degraded(language) {
+        analyze_with_regex_rules(file_path, source_code, language, config)?
+    } else {
+        EngineWorker::default().analyze(file_path, source_code, language, config, &cancellation)?
+    };
+    let findings: Vec<Finding> = findings.into_iter().filter(|f| !f.suppressed).collect();
+    Ok(apply_filters(&findings, config))
+}
 
-    // Apply each rule to the source code
-    for rule in &rules {
-        let rule_findings = apply_rule_to_source(rule, file_path, source_code)?;
-        findings.extend(rule_findings);
+/// Whether the target is `-`, standard input. The source is analyzed in memory, so
+/// options that rewrite or read back the target file are refused.
+fn reads_stdin(config: &EnhancedAnalysisConfig) -> Result<bool> {
+    if !config.target_paths.iter().any(|target| target.as_os_str() == STDIN_TARGET) {
+        return Ok(false);
     }
-
-    Ok((findings, rules_count))
+    if config.target_paths.len() > 1 {
+        return Err(anyhow::anyhow!("`-` (stdin) cannot be combined with other targets"));
+    }
+    if config.fix || config.fix_dry_run || config.emit_patches.is_some() || config.interactive {
+        return Err(anyhow::anyhow!("--fix, --fix-dry-run, --emit-patches and --interactive need files, not stdin"));
+    }
+    Ok(true)
 }
 
-/// Load rules from rule files/directories for a specific language
-fn load_rules_for_language(rule_paths: &[PathBuf], language: Language) -> Result<Vec<ParsedRule>> {
-    let mut rules = Vec::new();
+/// Analyze the source on stdin as the file `--stdin-filename` (default `<stdin>`). The
+/// language is the only `--language` given, else the one of the file name's extension.
+fn analyze_stdin(
+    config: &EnhancedAnalysisConfig,
+    findings: &mut Vec<Finding>,
+    stats: &mut AnalysisStatistics,
+    cancellation: &CancellationToken,
+) -> Result<()> {
+    use std::io::Read;
+
+    let file_path = config.stdin_filename.clone().unwrap_or_else(|| PathBuf::from(STDIN_FILENAME));
+    let language = match config.languages.as_slice() {
+        [language] => *language,
+        _ => config
+            .language_override(&file_path)
+            .map(Ok)
+            .unwrap_or_else(|| determine_language(&file_path))
+            .map_err(|_| anyhow::anyhow!("Cannot tell the language of stdin; pass a single --language or a --stdin-filename with a known extension"))?,
+    };
 
-    for rule_path in rule_paths {
-        if rule_path.is_file() {
-            if let Ok(file_rules) = load_rules_from_file(rule_path, language) {
-                rules.extend(file_rules);
-            }
-        } else if rule_path.is_dir() {
-            if let Ok(dir_rules) = load_rules_from_directory_recursive(rule_path, language) {
-                rules.extend(dir_rules);
-            }
-        }
+    let mut source_code = String::new();
+    std::io::stdin().read_to_string(&mut source_code)?;
+    stats.files_analyzed = 1;
+    if config.rule_files.is_empty() {
+        return Ok(());
     }
 
-    Ok(rules)
+    let (file_findings, rules_count) = if parser_health().is_degraded(language) {
+        analyze_with_regex_rules(&file_path, &source_code, language, config)?
+    } else {
+        EngineWorker::default().analyze(&file_path, &source_code, language, config, cancellation)?
+    };
+    findings.extend(file_findings);
+    stats.rules_executed = rules_count;
+    Ok(())
 }
 
-/// Load rules from a single YAML file
-fn load_rules_from_file(file_path: &PathBuf, target_language: Language) -> Result<Vec<ParsedRule>> {
-    let content = std::fs::read_to_string(file_path)?;
-    parse_semgrep_rules(&content, target_language, Some(file_path))
+/// Whether `source` may match a rule of `language`: it contains a literal anchor of some
+/// rule, or some rule has no anchors and must always run
+fn contains_rule_anchor(source: &str, language: Language, config: &EnhancedAnalysisConfig) -> bool {
+    let repository = rule_repository(config);
+    let lowered = source.to_lowercase();
+    repository.rules_for_language(language).iter().any(|rule| match rule.anchors() {
+        Some(anchors) => anchors.iter().any(|anchor| lowered.contains(anchor.as_str())),
+        None => true,
+    })
 }
 
-/// Recursively load rules from a directory
-fn load_rules_from_directory_recursive(dir_path: &PathBuf, target_language: Language) -> Result<Vec<ParsedRule>> {
-    let mut rules = Vec::new();
+/// Analyze each code region of a Vue, Svelte or Blade component with its own language,
+/// mapping findings back to the component's lines and columns
+fn analyze_component(
+    file_path: &PathBuf,
+    kind: ComponentKind,
+    config: &EnhancedAnalysisConfig,
+    worker: &mut EngineWorker,
+    findings: &mut Vec<Finding>,
+    stats: &mut AnalysisStatistics,
+    cancellation: &CancellationToken,
+) -> Result<()> {
+    if config.rule_files.is_empty() {
+        return Ok(());
+    }
+    let source_code = std::fs::read_to_string(file_path)?;
 
-    if let Ok(entries) = std::fs::read_dir(dir_path) {
-        for entry in entries.flatten() {
-            let path = entry.path();
-            if path.is_dir() {
-                if let Ok(subdir_rules) = load_rules_from_directory_recursive(&path, target_language) {
-                    rules.extend(subdir_rules);
-                }
-            } else if path.extension().map_or(false, |ext| ext == "yaml" || ext == "yml") {
-                if let Ok(file_rules) = load_rules_from_file(&path, target_language) {
-                    rules.extend(file_rules);
-                }
+    for region in split_component(kind, &source_code) {
+        let language = match region.language {
+            Some(language) if config.languages.contains(&language) => language,
+            _ => continue,
+        };
+        debug!("Analyzing {:?} region at {}:{} of {} as {:?}", region.kind, region.start_line, region.start_column, file_path.display(), language);
+
+        let (region_findings, rules_count) = if parser_health().is_degraded(language) {
+            analyze_with_regex_rules(file_path, &region.content, language, config)?
+        } else {
+            worker.analyze(file_path, &region.content, language, config, cancellation)?
+        };
+        if stats.rules_executed == 0 {
+            stats.rules_executed = rules_count;
+        }
+
+        for mut finding in region_findings {
+            let loc = &mut finding.location;
+            (loc.start_line, loc.start_column) = region.to_original(loc.start_line, loc.start_column);
+            (loc.end_line, loc.end_column) = region.to_original(loc.end_line, loc.end_column);
+            findings.push(finding);
+        }
+    }
+
+    Ok(())
+}
+
+/// Regex-only analysis for languages without a working parser: runs the top-level
+/// `pattern-regex` patterns of each applicable rule, skipping lines matched by a
+/// top-level `pattern-not-regex`
+/// Returns (findings, rules_count)
+fn analyze_with_regex_rules(
+    file_path: &Path,
+    source_code: &str,
+    language: Language,
+    config: &EnhancedAnalysisConfig,
+) -> Result<(Vec<Finding>, usize)> {
+    use astgrep_rules::{PatternType, RegexIndex};
+
+    let repository = rule_repository(config);
+    let rules = repository.rules_for_language(language);
+    let index = RegexIndex::build(rules.iter().copied());
+    let hits = index.scan(language, source_code);
+    let mut findings = Vec::new();
+    let mut rules_count = 0;
+
+    for rule in rules {
+        let mut positive = Vec::new();
+        let mut negative = Vec::new();
+        for pattern in &rule.patterns {
+            match &pattern.pattern_type {
+                PatternType::Regex(re) => positive.push(re),
+                PatternType::NotRegex(re) => negative.push(re),
+                _ => {}
+            }
+        }
+        if positive.len() + negative.len() < rule.patterns.len() {
+            record_diagnostic(DiagnosticKind::EngineFallback, Some(language), "ast -> regex (parser degraded)", || rule.id.clone());
+        }
+        if positive.is_empty() {
+            continue;
+        }
+        rules_count += 1;
+
+        let negative: Vec<regex::Regex> = negative.iter().filter_map(|re| regex::Regex::new(re).ok()).collect();
+        for re in positive {
+            if !hits.may_match(re) {
+                continue;
+            }
+            let re = match index.regex(re) {
+                Some(re) => re.clone(),
+                None => match regex::Regex::new(re) {
+                    Ok(re) => re,
+                    Err(e) => {
+                        warn!("Invalid regex in rule {}: {}", rule.id, e);
+                        continue;
+                    }
+                },
+            };
+            for m in re.find_iter(source_code) {
+                let (start_line, start_column) = line_and_column(source_code, m.start());
+                let (end_line, end_column) = line_and_column(source_code, m.end());
+                let line = source_code.lines().nth(start_line - 1).unwrap_or("");
+                if negative.iter().any(|not| not.is_match(line)) {
+                    continue;
+                }
+                findings.push(Finding {
+                    fix: rule.fix.clone(),
+                    suppressed: !config.disable_nosem && astgrep_core::is_suppressed(source_code, start_line, &rule.id),
+                    ..Finding::new(
+                        rule.id.clone(),
+                        rule.description.clone(),
+                        rule.severity,
+                        Confidence::Low,
+                        Location::new(file_path, start_line, start_column, end_line, end_column),
+                    )
+                });
             }
         }
     }
 
-    Ok(rules)
+    Ok((findings, rules_count))
+}
+
+/// 1-based line and column of a byte offset
+fn line_and_column(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset];
+    let line = before.matches('\n').count() + 1;
+    let column = offset - before.rfind('\n').map(|i| i + 1).unwrap_or(0) + 1;
+    (line, column)
+}
+
+/// Point findings in generated code at the original sources, using a source map or
+/// `#line` directives when the file has one
+fn map_to_original_sources(findings: &mut [Finding], file_path: &Path, source_code: &str) {
+    let mapper = match astgrep_core::LineMapper::for_file(file_path, source_code) {
+        Some(mapper) => mapper,
+        None => return,
+    };
+    debug!("Mapping finding locations in {} to original sources", file_path.display());
+
+    for finding in findings.iter_mut() {
+        let loc = &finding.location;
+        let mapped = mapper.map_location(&astgrep_core::Location::new(
+            loc.file.clone(),
+            loc.start_line,
+            loc.start_column,
+            loc.end_line,
+            loc.end_column,
+        ));
+        finding.location = Location {
+            file: mapped.file,
+            start_line: mapped.start_line,
+            start_column: mapped.start_column,
+            end_line: mapped.end_line,
+            end_column: mapped.end_column,
+        };
+    }
+}
+
+
+/// Rule engine and parsers of one analysis worker. They are built for the first file the
+/// worker analyzes with the rule engine and reused for its other files, so a worker serves
+/// a single configuration.
+#[derive(Default)]
+pub(crate) struct EngineWorker {
+    loaded: Option<LoadedEngine>,
+}
+
+struct LoadedEngine {
+    engine: astgrep_rules::RuleEngine,
+    rules_count: usize,
+    parsers: astgrep_parser::LanguageParserRegistry,
+    /// Languages whose parser the engine matches simple patterns with
+    registered: HashSet<Language>,
 }
 
+impl EngineWorker {
+    /// The engine with the rules of `config`, built on first use
+    fn load(&mut self, config: &EnhancedAnalysisConfig) -> Result<&mut LoadedEngine> {
+        if self.loaded.is_none() {
+            let mut engine = astgrep_rules::RuleEngine::new();
+            engine.set_inline_suppressions(!config.disable_nosem);
+            let rules_count = load_rules_into_engine(&rule_repository(config), &mut engine)?;
+            engine.configure_executor().record_timings(crate::commands::metrics::is_enabled());
+            if let Some(ref models) = config.taint_models {
+                engine.configure_executor().set_taint_models(Arc::clone(models));
+            }
+            if let Some(ref index) = config.project_index {
+                engine.configure_executor().set_project_index(Arc::clone(index));
+            }
+            let parsers = astgrep_parser::LanguageParserRegistry::new();
+            self.loaded = Some(LoadedEngine { engine, rules_count, parsers, registered: HashSet::new() });
+        }
+        Ok(self.loaded.as_mut().expect("engine is loaded"))
+    }
+
+    /// Analyze a file using the shared astgrep RuleEngine (same semantics as GUI/Web)
+    fn analyze(
+        &mut self,
+        file_path: &PathBuf,
+        source_code: &str,
+        language: Language,
+        config: &EnhancedAnalysisConfig,
+        cancellation: &CancellationToken,
+    ) -> Result<(Vec<Finding>, usize)> {
+        analyze_with_rule_engine(self.load(config)?, file_path, source_code, language, config, cancellation)
+    }
+}
 
-/// Analyze a file using the shared astgrep RuleEngine (same semantics as GUI/Web)
 fn analyze_with_rule_engine(
+    loaded: &mut LoadedEngine,
     file_path: &PathBuf,
     source_code: &str,
     language: Language,
     config: &EnhancedAnalysisConfig,
+    cancellation: &CancellationToken,
 ) -> Result<(Vec<Finding>, usize)> {
-    eprintln!("[DEBUG] entered analyze_with_rule_engine for {}", file_path.display());
-    use astgrep_parser::LanguageParserRegistry;
-    use astgrep_rules::{RuleContext, RuleEngine};
+    debug!(file = %file_path.display(), "analyzing with the rule engine");
+    use astgrep_rules::RuleContext;
     use std::path::Path;
 
-    // 1) Load rules into the shared engine
-    let mut engine = RuleEngine::new();
-    let rules_count = load_rules_into_engine_from_paths(&config.rule_files, &mut engine)?;
+    // 1) The worker's engine, with the rules already loaded
+    let LoadedEngine { engine, rules_count, parsers, registered } = loaded;
+    let rules_count = *rules_count;
     if rules_count == 0 {
         return Ok((Vec::new(), 0));
     }
+    let metrics = crate::commands::metrics::is_enabled();
+    // Simple patterns are parsed with the file's grammar and matched on its syntax tree
+    if registered.insert(language) {
+        if let Ok(parser) = astgrep_parser::ParserFactory::create_parser(language) {
+            engine.configure_executor().register_parser(Arc::from(parser));
+        }
+    }
 
     // 2) Build AST once per file (if a parser exists). If not (e.g., Xml not yet wired), still allow preprocess path.
-    let registry = LanguageParserRegistry::new();
-    let parser_opt = registry.get_parser(language);
+    let parser_opt = parsers.get_parser(language);
     let mut all_findings_core: Vec<astgrep_core::Finding> = Vec::new();
+    let mut suppressed_core: Vec<astgrep_core::Finding> = Vec::new();
 
     if let Some(parser) = parser_opt {
-        let ast = parser.parse(source_code, Path::new(file_path))?;
+        let parse_start = Instant::now();
+        // A parse cut short by `--timeout` or Ctrl-C leaves the file without findings
+        let ast = match parser.parse_cancellable(source_code, Path::new(file_path), cancellation) {
+            Err(astgrep_core::AnalysisError::Cancelled) => return Ok((Vec::new(), rules_count)),
+            parsed => parsed?,
+        };
+        crate::commands::metrics::record_parse(language, parse_start.elapsed());
 
         // 3) Execute rules with unified context
         let mut context = RuleContext::new(
             file_path.to_string_lossy().to_string(),
             language,
             source_code.to_string(),
-        );
+        ).with_cancellation(cancellation.clone());
         // Pass CLI level sql_statement_boundary (if provided) into context; per-rule YAML can override in engine
         if let Some(flag) = config.sql_statement_boundary {
             context = context.add_data("sql_statement_boundary".to_string(), flag.to_string());
         }
 
-        all_findings_core = engine.analyze(ast.as_ref(), &context)?;
+        (all_findings_core, suppressed_core) = engine.analyze_with_suppressed(ast.as_ref(), &context)?;
     } else {
         tracing::warn!("No parser registered for {:?}; skipping direct analysis but will attempt preprocess path if configured", language);
     }
@@ -357,20 +1361,8 @@ fn analyze_with_rule_engine(
     //     preprocess: "embedded-sql"
     //     preprocess.from: "java,xml"
     // When present on a SQL rule, we will extract SQL snippets from Java/XML sources and run the SQL rule on those snippets.
-    {
-        let lang_name = match language {
-            Language::Java => "Java",
-            Language::Xml => "Xml",
-            Language::Sql => "Sql",
-            _ => "Other",
-        };
-        eprintln!("[DEBUG-PREPROC] language for preprocessing check = {}", lang_name);
-        tracing::info!("enhanced: language for preprocessing check = {}", lang_name);
-    }
     if matches!(language, Language::Java | Language::Xml) {
-        use astgrep_parser::LanguageParserRegistry;
-        let registry2 = LanguageParserRegistry::new();
-        if let Some(sql_parser) = registry2.get_parser(Language::Sql) {
+        if let Some(sql_parser) = parsers.get_parser(Language::Sql) {
             // Collect eligible SQL rules with preprocessing metadata
             tracing::info!("embedded-sql: total loaded rules = {}", engine.rules().len());
             let sql_rules: Vec<_> = engine
@@ -412,1742 +1404,104 @@ fn analyze_with_rule_engine(
                         // Build SQL context using original file path but snippet content
                         let mut ctx_sql = RuleContext::new(
                             file_path.to_string_lossy().to_string(),
-                            Language::Sql,
-                            sn.sql.clone(),
-                        );
-                        if let Some(flag) = config.sql_statement_boundary {
-                            ctx_sql = ctx_sql.add_data("sql_statement_boundary".to_string(), flag.to_string());
-                        }
-
-                        for rule in &sql_rules {
-                            if let Ok(Some(result)) = engine.execute_rule(&rule.id, ast_sql.as_ref(), &ctx_sql) {
-                                if result.is_success() {
-                                    tracing::debug!("embedded-sql: rule '{}' produced {} findings on snippet #{}", rule.id, result.findings.len(), idx + 1);
-                                    for mut f in result.findings {
-                                        // Adjust location lines by snippet offset
-                                        let mut loc = f.location;
-                                        let line_off = sn.start_line.saturating_sub(1);
-                                        loc.start_line += line_off;
-                                        loc.end_line += line_off;
-                                        // Rewrap into CLI Finding
-                                        all_findings_core.push(astgrep_core::Finding {
-                                            rule_id: f.rule_id,
-                                            message: f.message,
-                                            severity: f.severity,
-                                            confidence: f.confidence,
-                                            location: astgrep_core::Location::new(
-                                                std::path::PathBuf::from(&file_path),
-                                                loc.start_line, loc.start_column, loc.end_line, loc.end_column
-                                            ),
-                                            metadata: {
-                                                let mut m = f.metadata;
-                                                if let Some(ctx) = sn.context.as_ref() { m.insert("embedded_context".to_string(), ctx.clone()); }
-                                                m
-                                            },
-                                            fix_suggestion: f.fix_suggestion,
-                                        });
-                                    }
-                                }
-                            }
-                        }
-                    } else {
-                        tracing::warn!("embedded-sql: failed to parse snippet #{} as SQL", idx + 1);
-                    }
-                }
-            }
-        }
-    }
-
-    // 4) Convert to CLI Finding shape
-    let mut findings = Vec::with_capacity(all_findings_core.len());
-    for f in all_findings_core {
-        findings.push(Finding {
-            rule_id: f.rule_id,
-            message: f.message,
-            severity: f.severity,
-            confidence: f.confidence,
-            location: Location {
-                file: f.location.file,
-                start_line: f.location.start_line,
-                start_column: f.location.start_column,
-                end_line: f.location.end_line,
-                end_column: f.location.end_column,
-            },
-            fix: f.fix_suggestion,
-        });
-    }
-
-    Ok((findings, rules_count))
-}
-
-/// Recursively load all YAML rules into the shared RuleEngine
-fn load_rules_into_engine_from_paths(
-    rule_paths: &[PathBuf],
-    engine: &mut astgrep_rules::RuleEngine,
-) -> Result<usize> {
-    use std::fs;
-
-    fn is_yaml(path: &std::path::Path) -> bool {
-        path.extension().map_or(false, |ext| ext == "yaml" || ext == "yml")
-    }
-
-    fn load_from_dir(dir: &std::path::Path, engine: &mut astgrep_rules::RuleEngine) -> anyhow::Result<usize> {
-        let mut loaded = 0usize;
-        for entry in fs::read_dir(dir)? {
-            let entry = entry?;
-            let path = entry.path();
-            if path.is_dir() {
-                loaded += load_from_dir(&path, engine)?;
-            } else if is_yaml(&path) {
-                if let Ok(content) = fs::read_to_string(&path) {
-                    match engine.load_rules_from_yaml(&content) {
-                        Ok(n) => { loaded += n; },
-                        Err(e) => {
-                            tracing::warn!("Failed to load rules from {:?}: {}", path, e);
-                        }
-
-
-                    }
-                }
-            }
-        }
-        Ok(loaded)
-    }
-
-    let mut total = 0usize;
-    for rule_path in rule_paths {
-        if rule_path.is_file() {
-            if is_yaml(rule_path) {
-                if let Ok(content) = std::fs::read_to_string(rule_path) {
-                    match engine.load_rules_from_yaml(&content) {
-                        Ok(n) => { total += n; },
-                        Err(e) => tracing::warn!("Failed to load rules from {:?}: {}", rule_path, e),
-                    }
-                }
-            }
-        } else if rule_path.is_dir() {
-            total += load_from_dir(rule_path, engine)?;
-        }
-    }
-
-    Ok(total)
-}
-
-/// Apply a single rule to source code
-fn apply_rule_to_source(rule: &ParsedRule, file_path: &PathBuf, source_code: &str) -> Result<Vec<Finding>> {
-    let mut findings = Vec::new();
-
-    // Check if this rule has NOT_INSIDE or NOT_REGEX patterns that need special handling
-    let has_not_inside = rule.patterns.iter().any(|p| p.starts_with("NOT_INSIDE:"));
-    let has_not_regex = rule.patterns.iter().any(|p| p.starts_with("NOT_REGEX:"));
-    if has_not_inside || has_not_regex {
-        info!("Rule '{}' has NOT_INSIDE or NOT_REGEX patterns, applying special handling", rule.id);
-        // Apply special handling for rules with NOT_INSIDE or NOT_REGEX patterns
-        findings.extend(apply_rule_with_not_inside(rule, file_path, source_code)?);
-        return Ok(findings);
-    }
-
-    // Check if this is a taint analysis rule
-    if rule.patterns.iter().any(|p| p.contains("sink(")) &&
-       rule.patterns.iter().any(|p| p.contains("\"tainted\"")) {
-        // Apply simplified taint analysis
-        findings.extend(apply_simple_taint_analysis(rule, file_path, source_code)?);
-    } else {
-        // Determine language from file extension
-        if let Ok(language) = determine_language(file_path) {
-            // Try tree-sitter based analysis first for supported languages
-            if let Ok(mut ts_analyzer) = TreeSitterAnalyzer::new() {
-                if ts_analyzer.supports_language(language) {
-                    let ts_findings = ts_analyzer.apply_rule_with_tree_sitter(
-                    &rule.id,
-                    &rule.message,
-                    &rule.severity,
-                    &rule.patterns,
-                    file_path,
-                    source_code,
-                    language,
-                    &rule.fix,
-                )?;
-
-                if !ts_findings.is_empty() {
-                    // Convert tree-sitter findings to our Finding format
-                    for ts_finding in ts_findings {
-                        let finding = Finding {
-                            rule_id: ts_finding.rule_id,
-                            message: ts_finding.message,
-                            severity: ts_finding.severity,
-                            confidence: ts_finding.confidence,
-                            location: Location {
-                                file: ts_finding.location.file,
-                                start_line: ts_finding.location.start_line,
-                                start_column: ts_finding.location.start_column,
-                                end_line: ts_finding.location.end_line,
-                                end_column: ts_finding.location.end_column,
-                            },
-                            fix: ts_finding.fix_suggestion,
-                        };
-                        findings.push(finding);
-                    }
-                    return Ok(findings);
-                }
-                }
-            }
-        }
-
-        // Try enhanced matching once per rule to preserve grouping semantics (e.g., pattern-either)
-        if let Ok(language) = determine_language(file_path) {
-            if let Ok(enhanced_findings) = apply_enhanced_pattern_matching(rule, file_path, source_code, language) {
-                if !enhanced_findings.is_empty() {
-                    return Ok(enhanced_findings);
-                }
-            }
-        }
-
-        // Fallback to pattern-aware matching
-        for pattern in &rule.patterns {
-            // Check if this is a regex pattern first (higher priority)
-            if pattern.starts_with("NOT_REGEX:") ||
-               // Direct regex patterns (no unescaped metavariables)
-               (!pattern.contains('$') && (pattern.contains('(') || pattern.contains('[') || pattern.contains('\\'))) ||
-               // Regex patterns with escaped dollar signs (like \$[a-zA-Z_])
-               (pattern.contains("\\$") && (pattern.contains('[') || pattern.contains('(') || pattern.contains('?'))) ||
-               // Regex patterns with common regex syntax
-               (pattern.contains('\\') && (pattern.contains("\\s") || pattern.contains("\\d") || pattern.contains("\\w"))) {
-                // Use our advanced pattern matcher for regex patterns
-                findings.extend(apply_metavariable_pattern(rule, pattern, file_path, source_code)?);
-            } else if pattern.contains('$') {
-
-
-                // Use our pattern matcher for metavariable patterns
-                findings.extend(apply_metavariable_pattern(rule, pattern, file_path, source_code)?);
-            } else {
-                // Simple string-based pattern matching for literal patterns
-                for (line_num, line) in source_code.lines().enumerate() {
-                    if line.contains(pattern) {
-                        let finding = Finding {
-                            rule_id: rule.id.clone(),
-                            message: rule.message.clone(),
-                            severity: rule.severity.clone(),
-                            confidence: Confidence::Medium,
-                            location: Location {
-                                file: file_path.clone(),
-                                start_line: line_num + 1,
-                                start_column: line.find(pattern).unwrap_or(0) + 1,
-                                end_line: line_num + 1,
-                                end_column: line.find(pattern).unwrap_or(0) + pattern.len() + 1,
-                            },
-                            fix: rule.fix.clone(),
-                        };
-                        findings.push(finding);
-                    }
-                }
-            }
-        }
-    }
-
-    Ok(findings)
-}
-
-/// Apply metavariable pattern matching using our pattern matcher
-fn apply_metavariable_pattern(
-    rule: &ParsedRule,
-    pattern: &str,
-    file_path: &PathBuf,
-    source_code: &str,
-) -> Result<Vec<Finding>> {
-    use astgrep_parser::LanguageParserRegistry;
-    use astgrep_matcher::AdvancedPatternMatcher;
-
-    let mut findings = Vec::new();
-
-    // Determine language
-    let language = match determine_language(file_path) {
-        Ok(lang) => lang,
-        Err(_) => return Ok(findings), // Skip if language cannot be determined
-    };
-
-    // Check if pattern looks like a regex (contains regex metacharacters)
-    let is_likely_regex = pattern.contains('(') && pattern.contains(')') &&
-                         (pattern.contains('\\') || pattern.contains('[') || pattern.contains('*') || pattern.contains('+'));
-
-    if is_likely_regex {
-        info!("Pattern looks like regex, attempting direct regex matching: {}", pattern);
-        match apply_regex_pattern(rule, pattern, file_path, source_code) {
-            Ok(regex_findings) => {
-                info!("Regex pattern matching found {} matches", regex_findings.len());
-                if !regex_findings.is_empty() {
-                    return Ok(regex_findings);
-                }
-            }
-            Err(e) => {
-                warn!("Regex pattern matching failed: {}", e);
-            }
-        }
-    }
-
-    // Try to use our enhanced rule parser and matcher first
-    info!("Attempting enhanced pattern matching for pattern: {}", pattern);
-    match apply_enhanced_pattern_matching(rule, file_path, source_code, language) {
-        Ok(enhanced_findings) => {
-            info!("Enhanced pattern matching succeeded, found {} matches", enhanced_findings.len());
-            if !enhanced_findings.is_empty() {
-                return Ok(enhanced_findings);
-            } else {
-                info!("Enhanced pattern matching found no matches, falling back to tree-sitter");
-            }
-        }
-        Err(e) => {
-            warn!("Enhanced pattern matching failed for {}: {}, falling back to tree-sitter",
-                  file_path.display(), e);
-        }
-    }
-
-    // Try to use tree-sitter for proper AST-based pattern matching
-    info!("Attempting tree-sitter parsing for pattern: {}", pattern);
-    match apply_tree_sitter_pattern_matching(rule, pattern, file_path, source_code, language) {
-        Ok(tree_sitter_findings) => {
-            info!("Tree-sitter parsing succeeded, found {} matches", tree_sitter_findings.len());
-            if !tree_sitter_findings.is_empty() {
-                return Ok(tree_sitter_findings);
-            } else {
-                info!("Tree-sitter found no matches, falling back to simple matching");
-            }
-        }
-        Err(e) => {
-            warn!("Tree-sitter parsing failed for {}: {}, falling back to simple matching",
-                  file_path.display(), e);
-        }
-    }
-
-    // Fallback to simple pattern matching if tree-sitter fails
-    return apply_simple_metavariable_pattern(rule, pattern, file_path, source_code);
-
-}
-
-/// Apply enhanced pattern matching using our new AdvancedSemgrepMatcher
-fn apply_enhanced_pattern_matching(
-    rule: &ParsedRule,
-    file_path: &PathBuf,
-    source_code: &str,
-    language: Language,
-) -> Result<Vec<Finding>> {
-    use astgrep_rules::{RuleParser, Rule};
-    use astgrep_matcher::AdvancedSemgrepMatcher;
-    use astgrep_parser::tree_sitter_parser::TreeSitterParser;
-
-    let mut findings = Vec::new();
-
-    // Convert our simplified ParsedRule to a full Rule structure
-    let rule_yaml = convert_parsed_rule_to_yaml(rule)?;
-
-    // Parse the rule using our enhanced rule parser
-    let parser = RuleParser::new();
-    let rules = parser.parse_yaml(&rule_yaml)?;
-
-    if rules.is_empty() {
-        return Ok(findings);
-    }
-
-    let enhanced_rule = &rules[0];
-
-    // Create tree-sitter parser and parse the source code
-    let mut ts_parser = TreeSitterParser::new()?;
-    if let Some(tree) = ts_parser.parse(source_code, language)? {
-        let ast = ts_parser.tree_to_universal_ast(&tree, source_code)?;
-
-        // Create advanced matcher and find matches
-        let mut matcher = AdvancedSemgrepMatcher::new();
-
-        for pattern in &enhanced_rule.patterns {
-            // Convert our Pattern to SemgrepPattern
-            let semgrep_pattern = convert_pattern_to_semgrep_pattern(pattern)?;
-
-            let matches = matcher.find_matches(&semgrep_pattern, &ast)?;
-
-            for match_result in matches {
-                // Extract precise location from the matched AST node
-                let (sl, sc, el, ec) = match match_result.node.location() {
-                    Some((sl, sc, el, ec)) => (sl, sc, el, ec),
-                    None => (1, 1, 1, 1),
-                };
-                let finding = Finding {
-                    rule_id: rule.id.clone(),
-                    message: rule.message.clone(),
-                    severity: rule.severity.clone(),
-                    confidence: Confidence::High,
-                    location: Location {
-                        file: file_path.clone(),
-                        start_line: sl,
-                        start_column: sc,
-                        end_line: el,
-                        end_column: ec,
-                    },
-                    fix: rule.fix.clone(),
-                };
-                findings.push(finding);
-            }
-        }
-    }
-
-    Ok(findings)
-}
-
-/// Convert ParsedRule to YAML format for enhanced parsing
-fn convert_parsed_rule_to_yaml(rule: &ParsedRule) -> Result<String> {
-    // Preserve original rule YAML structure to keep semantics (e.g., pattern-either)
-    let mut top = serde_yaml::Mapping::new();
-    let mut rules_seq = serde_yaml::Sequence::new();
-    rules_seq.push(rule.raw_rule_value.clone());
-    top.insert(serde_yaml::Value::String("rules".to_string()), serde_yaml::Value::Sequence(rules_seq));
-    let yaml = serde_yaml::to_string(&serde_yaml::Value::Mapping(top))?;
-    Ok(yaml)
-}
-
-/// Convert our Pattern to SemgrepPattern
-fn convert_pattern_to_semgrep_pattern(pattern: &astgrep_rules::Pattern) -> Result<astgrep_core::SemgrepPattern> {
-    use astgrep_core::{SemgrepPattern, PatternType as CorePatternType};
-
-    let core_pattern_type = match &pattern.pattern_type {
-        astgrep_rules::PatternType::Simple(s) => CorePatternType::Simple(s.clone()),
-        astgrep_rules::PatternType::Either(patterns) => {
-            let converted: Result<Vec<_>> = patterns.iter()
-                .map(convert_pattern_to_semgrep_pattern)
-                .collect();
-            CorePatternType::Either(converted?)
-        }
-        astgrep_rules::PatternType::Inside(inner) => {
-            CorePatternType::Inside(Box::new(convert_pattern_to_semgrep_pattern(inner)?))
-        }
-        astgrep_rules::PatternType::NotInside(inner) => {
-            CorePatternType::NotInside(Box::new(convert_pattern_to_semgrep_pattern(inner)?))
-        }
-        astgrep_rules::PatternType::Not(inner) => {
-            CorePatternType::Not(Box::new(convert_pattern_to_semgrep_pattern(inner)?))
-        }
-        astgrep_rules::PatternType::Regex(regex) => CorePatternType::Regex(regex.clone()),
-        astgrep_rules::PatternType::NotRegex(regex) => CorePatternType::NotRegex(regex.clone()),
-        astgrep_rules::PatternType::All(patterns) => {
-            let converted: Result<Vec<_>> = patterns.iter()
-                .map(convert_pattern_to_semgrep_pattern)
-                .collect();
-            CorePatternType::All(converted?)
-        }
-        astgrep_rules::PatternType::Any(patterns) => {
-            let converted: Result<Vec<_>> = patterns.iter()
-                .map(convert_pattern_to_semgrep_pattern)
-                .collect();
-            CorePatternType::Any(converted?)
-        }
-    };
-
-    Ok(SemgrepPattern {
-        pattern_type: core_pattern_type,
-        metavariable_pattern: None, // TODO: Convert metavariable patterns
-        conditions: Vec::new(), // TODO: Convert conditions
-        focus: pattern.focus.clone(),
-    })
-}
-
-/// Simple metavariable pattern matching for basic cases
-fn apply_simple_metavariable_pattern(
-    rule: &ParsedRule,
-    pattern: &str,
-    file_path: &PathBuf,
-    source_code: &str,
-) -> Result<Vec<Finding>> {
-    let mut findings = Vec::new();
-
-    // Check if this is a NOT_REGEX pattern
-    if pattern.starts_with("NOT_REGEX:") {
-        // For now, skip NOT_REGEX patterns in simple matching
-        // They should be handled by the enhanced pattern matching
-        return Ok(findings);
-    }
-
-    // Check if this is a NOT_INSIDE pattern
-    if pattern.starts_with("NOT_INSIDE:") {
-        // For now, skip NOT_INSIDE patterns in simple matching
-        // They should be handled by the enhanced pattern matching
-        return Ok(findings);
-    }
-
-    // Check if this is a direct regex pattern
-    // Allow patterns with escaped dollar signs (like \$[a-zA-Z_])
-    let is_regex_pattern = (!pattern.contains('$') || pattern.contains("\\$")) &&
-        (pattern.contains('[') || pattern.contains('*') || pattern.contains('+') ||
-         pattern.contains('?') || pattern.contains('^') || pattern.contains('\\') ||
-         pattern.contains('(') || pattern.contains('|'));
-
-    info!("Pattern '{}' - is_regex_pattern: {}, contains '$': {}, contains '(': {}, contains '|': {}",
-          pattern, is_regex_pattern, pattern.contains('$'), pattern.contains('('), pattern.contains('|'));
-
-    if is_regex_pattern {
-        // Handle as direct regex - first fix double escaping from YAML
-        let fixed_pattern = pattern
-            .replace("\\\\s", "\\s")
-            .replace("\\\\d", "\\d")
-            .replace("\\\\w", "\\w")
-            .replace("\\\\b", "\\b")
-            .replace("\\\\t", "\\t")
-            .replace("\\\\n", "\\n")
-            .replace("\\\\r", "\\r")
-            .replace("\\\\$", "\\$");
-
-        info!("Attempting to compile regex pattern: '{}'", fixed_pattern);
-
-
-
-        if let Ok(regex) = regex::Regex::new(&fixed_pattern) {
-            info!("Regex compiled successfully, searching for matches...");
-            for (line_num, line) in source_code.lines().enumerate() {
-                // Skip lines that are comments
-                let trimmed_line = line.trim();
-                if trimmed_line.starts_with('#') || trimmed_line.starts_with("//") || trimmed_line.starts_with("/*") {
-                    info!("Skipping comment line {}: '{}'", line_num + 1, line);
-                    continue;
-                }
-
-                if line.contains('$') {
-                    info!("Checking line {}: '{}'", line_num + 1, line);
-                }
-                for mat in regex.find_iter(line) {
-                    // Check if the match is inside a comment on the same line
-                    if let Some(comment_pos) = line.find('#') {
-                        if mat.start() >= comment_pos {
-                            continue; // Skip matches inside inline comments
-                        }
-                    }
-                    if let Some(comment_pos) = line.find("//") {
-                        if mat.start() >= comment_pos {
-                            continue; // Skip matches inside inline comments
-                        }
-                    }
-
-                    // Additional check: if the line starts with # (comment), skip the match entirely
-                    if line.trim_start().starts_with('#') {
-                        continue; // Skip matches in comment lines
-                    }
-
-                    info!("Found regex match: '{}' at line {} position {}-{}",
-                          &line[mat.start()..mat.end()], line_num + 1, mat.start(), mat.end());
-                    let finding = Finding {
-                        rule_id: rule.id.clone(),
-                        message: rule.message.clone(),
-                        severity: rule.severity.clone(),
-                        confidence: Confidence::High,
-                        location: Location {
-                            file: file_path.clone(),
-                            start_line: line_num + 1,
-                            start_column: mat.start() + 1,
-                            end_line: line_num + 1,
-                            end_column: mat.end() + 1,
-                        },
-                        fix: rule.fix.clone(),
-                    };
-                    findings.push(finding);
-                }
-            }
-        } else {
-            // If regex compilation fails, try as literal pattern
-            for (line_num, line) in source_code.lines().enumerate() {
-                // Skip lines that are comments
-                let trimmed_line = line.trim();
-                if trimmed_line.starts_with('#') || trimmed_line.starts_with("//") || trimmed_line.starts_with("/*") {
-                    continue;
-                }
-
-                if line.contains(pattern) {
-                    if let Some(pos) = line.find(pattern) {
-                        // Check if the match is inside a comment on the same line
-                        if let Some(comment_pos) = line.find('#') {
-                            if pos >= comment_pos {
-                                continue; // Skip matches inside inline comments
-                            }
-                        }
-                        if let Some(comment_pos) = line.find("//") {
-                            if pos >= comment_pos {
-                                continue; // Skip matches inside inline comments
-                            }
-                        }
-
-                        // Additional check: if the line starts with # (comment), skip the match entirely
-                        if line.trim_start().starts_with('#') {
-                            continue; // Skip matches in comment lines
-                        }
-
-                        let finding = Finding {
-                            rule_id: rule.id.clone(),
-                            message: rule.message.clone(),
-                            severity: rule.severity.clone(),
-                            confidence: Confidence::Medium,
-                            location: Location {
-                                file: file_path.clone(),
-                                start_line: line_num + 1,
-                                start_column: pos + 1,
-                                end_line: line_num + 1,
-                                end_column: pos + pattern.len() + 1,
-                            },
-                            fix: rule.fix.clone(),
-                        };
-                        findings.push(finding);
-                    }
-                }
-            }
-        }
-    } else {
-        // Convert pattern to a regex-like pattern for matching
-        let regex_pattern = convert_pattern_to_regex(pattern);
-
-        for (line_num, line) in source_code.lines().enumerate() {
-            // Skip lines that are comments
-            let trimmed_line = line.trim();
-            if trimmed_line.starts_with('#') || trimmed_line.starts_with("//") || trimmed_line.starts_with("/*") {
-                continue;
-            }
-
-            if let Some(matches) = find_pattern_matches(&regex_pattern, line) {
-                for match_pos in matches {
-                    // Check if the match is inside a comment on the same line
-                    if let Some(comment_pos) = line.find('#') {
-                        if match_pos >= comment_pos {
-                            continue; // Skip matches inside inline comments
-                        }
-                    }
-                    if let Some(comment_pos) = line.find("//") {
-                        if match_pos >= comment_pos {
-                            continue; // Skip matches inside inline comments
-                        }
-                    }
-
-                    // Additional check: if the line starts with # (comment), skip the match entirely
-                    if line.trim_start().starts_with('#') {
-                        continue; // Skip matches in comment lines
-                    }
-
-                    let finding = Finding {
-                        rule_id: rule.id.clone(),
-                        message: rule.message.clone(),
-                        severity: rule.severity.clone(),
-                        confidence: Confidence::High,
-                        location: Location {
-                            file: file_path.clone(),
-                            start_line: line_num + 1,
-                            start_column: match_pos + 1,
-                            end_line: line_num + 1,
-                            end_column: match_pos + pattern.len(),
-                        },
-                        fix: rule.fix.clone(),
-                    };
-                    findings.push(finding);
-                }
-            }
-        }
-    }
-
-    // Handle the most common case: $X (matches any expression)
-    if pattern.trim() == "$X" {
-        // Find all expressions in the code (simplified heuristic)
-        for (line_num, line) in source_code.lines().enumerate() {
-            let trimmed = line.trim();
-            if !trimmed.is_empty() &&
-               !trimmed.starts_with('#') &&
-               !trimmed.starts_with("//") &&
-               !trimmed.starts_with("def ") &&
-               !trimmed.starts_with("class ") &&
-               !trimmed.starts_with("import ") &&
-               !trimmed.starts_with("from ") {
-
-                // Look for expressions (assignments, function calls, etc.)
-                if trimmed.contains('=') ||
-                   trimmed.contains('(') ||
-                   trimmed.contains('[') ||
-                   (trimmed.chars().any(|c| c.is_alphanumeric()) && !trimmed.starts_with("if ") && !trimmed.starts_with("for ") && !trimmed.starts_with("while ")) {
-
-                    let finding = Finding {
-                        rule_id: rule.id.clone(),
-                        message: rule.message.clone(),
-                        severity: rule.severity.clone(),
-                        confidence: Confidence::Low, // Lower confidence for heuristic matching
-                        location: Location {
-                            file: file_path.clone(),
-                            start_line: line_num + 1,
-                            start_column: 1,
-                            end_line: line_num + 1,
-                            end_column: line.len() + 1,
-                        },
-                        fix: rule.fix.clone(),
-                    };
-                    findings.push(finding);
-                }
-            }
-        }
-    }
-
-    Ok(findings)
-}
-
-/// Apply tree-sitter based pattern matching for better precision
-fn apply_tree_sitter_pattern_matching(
-    rule: &ParsedRule,
-    pattern: &str,
-    file_path: &PathBuf,
-    source_code: &str,
-    language: Language,
-) -> Result<Vec<Finding>> {
-    use astgrep_parser::tree_sitter_parser::TreeSitterParser;
-
-    info!("Creating TreeSitterParser for language: {:?}", language);
-    let mut findings = Vec::new();
-    let mut parser = TreeSitterParser::new()?;
-
-    info!("Parsing source code with tree-sitter...");
-    // Parse the source code with tree-sitter
-    if let Some(tree) = parser.parse(source_code, language)? {
-        info!("Tree-sitter parsing successful, searching for pattern matches...");
-        // Find pattern matches using tree-sitter
-        let matches = parser.find_pattern_matches(&tree, source_code, pattern)?;
-        info!("Tree-sitter found {} raw matches", matches.len());
-
-        for (i, node) in matches.iter().enumerate() {
-            info!("Match {}: kind='{}', text='{}'", i, node.kind(),
-                  node.utf8_text(source_code.as_bytes()).unwrap_or("<invalid>"));
-            // Skip matches in comments
-            if node.kind() == "comment" || node.kind() == "line_comment" || node.kind() == "block_comment" {
-                continue;
-            }
-
-            // Check if the node is inside a comment by examining parent nodes
-            let mut current = node.parent();
-            let mut in_comment = false;
-            while let Some(parent) = current {
-                if parent.kind() == "comment" || parent.kind() == "line_comment" || parent.kind() == "block_comment" {
-                    in_comment = true;
-                    break;
-                }
-                current = parent.parent();
-            }
-
-            if in_comment {
-                continue;
-            }
-
-            // Try to extract capture groups from the matched text if the pattern is a regex
-            let mut message = rule.message.clone();
-            if let Ok(node_text) = node.utf8_text(source_code.as_bytes()) {
-                // Try to match the pattern as a regex to extract capture groups
-                if let Ok(regex) = regex::Regex::new(pattern) {
-                    if let Some(captures) = regex.captures(node_text) {
-                        message = replace_capture_groups(&message, &captures);
-                    }
-                }
-            }
-
-            let finding = Finding {
-                rule_id: rule.id.clone(),
-                message,
-                severity: rule.severity.clone(),
-                confidence: Confidence::High,
-                location: Location {
-                    file: file_path.clone(),
-                    start_line: node.start_position().row + 1,
-                    start_column: node.start_position().column + 1,
-                    end_line: node.end_position().row + 1,
-                    end_column: node.end_position().column + 1,
-                },
-                fix: rule.fix.clone(),
-            };
-            findings.push(finding);
-        }
-    } else {
-        warn!("Tree-sitter failed to parse source code for language: {:?}", language);
-    }
-
-    info!("Tree-sitter analysis completed with {} findings", findings.len());
-    Ok(findings)
-}
-
-/// Convert a semgrep-style pattern to a simple regex pattern
-fn convert_pattern_to_regex(pattern: &str) -> String {
-    // Handle patterns like "System.out.println($MESSAGE)" or "eval $CODE"
-    let mut regex_pattern = pattern.to_string();
-
-    // For patterns with metavariables, we need to be more precise
-    // Replace metavariables with more specific regex patterns
-
-    // Special handling for specific metavariables first
-    regex_pattern = regex_pattern.replace("$CODE", r"\S+");  // Non-whitespace
-    regex_pattern = regex_pattern.replace("$CMD", r"\S+");   // Non-whitespace
-    regex_pattern = regex_pattern.replace("$USER_INPUT", r"\$[0-9@*]+");  // Bash positional parameters
-    regex_pattern = regex_pattern.replace("$FILE", r"\S+");  // Non-whitespace
-    regex_pattern = regex_pattern.replace("$OPTIONS", r"[^\s]+");  // Non-whitespace
-    regex_pattern = regex_pattern.replace("$URL", r"\S+");   // Non-whitespace
-
-    // General metavariable replacement (more conservative)
-    regex_pattern = regex::Regex::new(r"\$[A-Z_][A-Z0-9_]*").unwrap()
-        .replace_all(&regex_pattern, r"\S+").to_string();
-    regex_pattern = regex::Regex::new(r"\$[a-z_][a-z0-9_]*").unwrap()
-        .replace_all(&regex_pattern, r"\S+").to_string();
-
-    // Legacy handling for other patterns
-    regex_pattern = regex_pattern.replace("$MESSAGE", r"[^,\)]+");
-    regex_pattern = regex_pattern.replace("$ARGS", r".*");
-    regex_pattern = regex_pattern.replace("$X", r".*");
-
-    // Escape special regex characters in the base pattern
-    let mut escaped = String::new();
-    let chars: Vec<char> = regex_pattern.chars().collect();
-    let mut i = 0;
-
-    while i < chars.len() {
-        let ch = chars[i];
-
-        // Check if this is part of our regex substitution
-        if ch == '\\' && i + 1 < chars.len() && chars[i + 1] == 'S' {
-            // This is \S+ - keep it as is
-            escaped.push(ch);
-            escaped.push(chars[i + 1]);
-            i += 2;
-        } else if ch == '[' {
-            // This might be part of our character class - keep it
-            escaped.push(ch);
-            i += 1;
-        } else if ch == ']' || ch == '+' || ch == '*' || ch == '?' {
-            // These might be part of our regex - keep them
-            escaped.push(ch);
-            i += 1;
-        } else {
-            // Regular character - escape if needed
-            match ch {
-                '.' | '^' | '$' | '(' | ')' | '{' | '}' | '\\' | '|' => {
-                    escaped.push('\\');
-                    escaped.push(ch);
-                }
-                _ => escaped.push(ch),
-            }
-            i += 1;
-        }
-    }
-
-    escaped
-}
-
-/// Find pattern matches in a line of code
-fn find_pattern_matches(pattern: &str, line: &str) -> Option<Vec<usize>> {
-    // First try exact string matching for simple patterns
-    if !pattern.contains('[') && !pattern.contains('*') && !pattern.contains('+') && !pattern.contains('?') {
-        if let Some(pos) = line.find(pattern) {
-            return Some(vec![pos]);
-        }
-    }
-
-    // Try regex matching for more complex patterns
-    if let Ok(regex) = regex::Regex::new(pattern) {
-        let mut matches = Vec::new();
-        for mat in regex.find_iter(line) {
-            matches.push(mat.start());
-        }
-        if !matches.is_empty() {
-            return Some(matches);
-        }
-    }
-
-    // Fallback: try simple substring matching for patterns that might have failed regex compilation
-    if line.contains(pattern) {
-        if let Some(pos) = line.find(pattern) {
-            return Some(vec![pos]);
-        }
-    }
-
-    None
-}
-
-/// Apply simplified taint analysis for taint rules
-fn apply_simple_taint_analysis(rule: &ParsedRule, file_path: &PathBuf, source_code: &str) -> Result<Vec<Finding>> {
-    let mut findings = Vec::new();
-    let lines: Vec<&str> = source_code.lines().collect();
-
-    // Find taint sources, sinks, and sanitized variables
-    let mut taint_sources = Vec::new();
-    let mut sanitized_vars = Vec::new();
-    let mut sink_calls = Vec::new();
-    let mut var_assignments = Vec::new(); // Track all variable assignments
-
-    // First pass: collect all assignments and direct taint sources
-    for (line_num, line) in lines.iter().enumerate() {
-        // Find direct taint source usage in sink calls
-        if line.contains("sink(") && line.contains("\"tainted\"") {
-            // Check if the taint is sanitized: sink(sanitize("tainted"))
-            if !line.contains("sanitize(") {
-                // Direct taint: sink("tainted") without sanitization
-                let finding = Finding {
-                    rule_id: rule.id.clone(),
-                    message: rule.message.clone(),
-                    severity: rule.severity.clone(),
-                    confidence: Confidence::High,
-                    location: Location {
-                        file: file_path.clone(),
-                        start_line: line_num + 1,
-                        start_column: line.find("sink(").unwrap_or(0) + 1,
-                        end_line: line_num + 1,
-                        end_column: line.find("sink(").unwrap_or(0) + 5,
-                    },
-                    fix: rule.fix.clone(),
-                };
-                findings.push(finding);
-            }
-        }
-
-        // Track variable assignments from taint sources
-        if line.contains("\"tainted\"") && line.contains("=") {
-            // Extract variable name (handle both regular vars and PHP vars with $)
-            if let Some(equals_pos) = line.find('=') {
-                let left_part = line[..equals_pos].trim();
-                if let Some(var_name) = left_part.split_whitespace().last() {
-                    let clean_var = var_name.trim_end_matches(';').trim();
-                    info!("Taint source found: {} (line {})", clean_var, line_num + 1);
-                    taint_sources.push((clean_var.to_string(), line_num + 1));
-                }
-            }
-        }
-
-        // Track variable-to-variable assignments for later propagation
-        if line.contains("=") && !line.contains("\"") && !line.contains("sanitize(") {
-            // Extract assignment: var1 = var2
-            if let Some(equals_pos) = line.find('=') {
-                let left_part = line[..equals_pos].trim();
-                let right_part = line[equals_pos + 1..].trim();
-
-                // Extract variable names (simplified)
-                if let Some(left_var) = left_part.split_whitespace().last() {
-                    if let Some(right_var) = right_part.split_whitespace().next() {
-                        // Clean up variable names (remove semicolons, etc.)
-                        let clean_left = left_var.trim_end_matches(';').trim();
-                        let clean_right = right_var.trim_end_matches(';').trim();
-                        info!("Variable assignment found: {} = {} (line {})", clean_left, clean_right, line_num + 1);
-                        var_assignments.push((clean_left.to_string(), clean_right.to_string(), line_num + 1));
-                    }
-                }
-            }
-        }
-
-        // Track variable sanitization: x = sanitize(x) or x = sanitize("tainted")
-        // Skip sanitizers that are in conditional branches (improved heuristic)
-        // Detect base indentation level from the first non-empty line
-        let line_indent = line.len() - line.trim_start().len();
-
-        // Simple heuristic: if line has more than 2 spaces and contains "if" or "else" nearby,
-        // consider subsequent more-indented lines as conditional
-        let is_in_conditional = line_indent > 2 && (
-            // Check if there's an if/else statement in recent lines
-            lines.iter().take(line_num + 1).rev().take(3).any(|prev_line| {
-                prev_line.trim().starts_with("if ") || prev_line.trim().starts_with("else")
-            })
-        );
-
-        if line.contains("sanitize(") && line.contains("=") && !is_in_conditional {
-            // Extract variable name being assigned (handle both regular vars and PHP vars with $)
-            if let Some(equals_pos) = line.find('=') {
-                let left_part = line[..equals_pos].trim();
-                if let Some(var_name) = left_part.split_whitespace().last() {
-                    let clean_var = var_name.trim_end_matches(';').trim();
-                    info!("Sanitizer found: {} (line {})", clean_var, line_num + 1);
-                    sanitized_vars.push((clean_var.to_string(), line_num + 1));
-                }
-            }
-        }
-
-        // Track sink calls with variables
-        if line.contains("sink(") && !line.contains("\"tainted\"") {
-            // Extract argument (simplified)
-            if let Some(start) = line.find("sink(") {
-                let after_paren = start + 5;
-                if let Some(end) = line[after_paren..].find(')') {
-                    let arg = line[after_paren..after_paren + end].trim();
-                    if !arg.is_empty() && !arg.starts_with('"') {
-                        sink_calls.push((arg.to_string(), line_num + 1));
-                    }
-                }
-            }
-        }
-    }
-
-    // Multi-round taint propagation through variable assignments
-    let mut changed = true;
-    let mut round = 0;
-    while changed && round < 10 { // Limit rounds to prevent infinite loops
-        changed = false;
-        round += 1;
-
-        for (left_var, right_var, assignment_line) in &var_assignments {
-            // Check if right_var is tainted and left_var is not yet tainted
-            let right_taint_info = taint_sources.iter().find(|(tainted_var, _)| tainted_var == right_var);
-            let left_already_tainted = taint_sources.iter().any(|(tainted_var, _)| tainted_var == left_var);
-
-            if let Some((_, taint_line)) = right_taint_info {
-                // Only propagate if the assignment happens AFTER the taint source
-                if !left_already_tainted && assignment_line > taint_line {
-                    info!("Taint propagation round {}: {} -> {} (line {}, taint from line {})",
-                          round, right_var, left_var, assignment_line, taint_line);
-                    taint_sources.push((left_var.clone(), *assignment_line));
-                    changed = true;
-                }
-            }
-        }
-    }
-
-    // Check for taint propagation through variables (excluding sanitized ones)
-    // Use a set to track which sinks we've already processed to avoid duplicates
-    let mut processed_sinks = std::collections::HashSet::new();
-
-    for (sink_arg, sink_line) in &sink_calls {
-        if processed_sinks.contains(sink_line) {
-            continue; // Skip if we've already processed this sink
-        }
-
-        // Find if any taint source affects this sink
-        let mut sink_is_tainted = false;
-        for (var_name, source_line) in &taint_sources {
-            if sink_arg == var_name && source_line < sink_line {
-                // Only consider taint sources that occur BEFORE the sink
-                // Check if this variable was sanitized AFTER the taint source but BEFORE the sink
-                let is_sanitized_before_sink = sanitized_vars.iter().any(|(sanitized_var, sanitize_line)| {
-                    sanitized_var == var_name &&
-                    sanitize_line > source_line &&
-                    sanitize_line < sink_line
-                });
-
-                if !is_sanitized_before_sink {
-                    info!("Sink at line {} is tainted: variable {} from line {} (no sanitization between {} and {})",
-                          sink_line, var_name, source_line, source_line, sink_line);
-                    sink_is_tainted = true;
-                    break; // Found at least one taint source that affects this sink
-                } else {
-                    info!("Sink at line {} is safe: variable {} was sanitized before use", sink_line, var_name);
-                }
-            }
-        }
-
-        if sink_is_tainted {
-            let finding = Finding {
-                rule_id: rule.id.clone(),
-                message: rule.message.clone(),
-                severity: rule.severity.clone(),
-                confidence: Confidence::Medium,
-                location: Location {
-                    file: file_path.clone(),
-                    start_line: *sink_line,
-                    start_column: lines[*sink_line - 1].find("sink(").unwrap_or(0) + 1,
-                    end_line: *sink_line,
-                    end_column: lines[*sink_line - 1].find("sink(").unwrap_or(0) + 5,
-                },
-                fix: rule.fix.clone(),
-            };
-            findings.push(finding);
-        }
-
-        processed_sinks.insert(*sink_line);
-    }
-
-    Ok(findings)
-}
-
-/// Parsed rule structure
-#[derive(Debug, Clone)]
-struct ParsedRule {
-    id: String,
-    message: String,
-    severity: Severity,
-    languages: Vec<Language>,
-    patterns: Vec<String>,
-    fix: Option<String>,
-    // Preserve the original YAML value to maintain semantics like pattern-either
-    raw_rule_value: serde_yaml::Value,
-}
-
-/// Parse Semgrep-style YAML rules
-fn parse_semgrep_rules(content: &str, target_language: Language, file_path: Option<&PathBuf>) -> Result<Vec<ParsedRule>> {
-    let mut rules = Vec::new();
-
-    // Try to parse as YAML
-    if let Ok(yaml_value) = serde_yaml::from_str::<serde_yaml::Value>(content) {
-        if let Some(yaml_rules) = yaml_value.get("rules").and_then(|r| r.as_sequence()) {
-            for rule_value in yaml_rules {
-                if let Ok(rule) = parse_single_rule(rule_value, target_language, file_path) {
-                    rules.push(rule);
-                }
-            }
-        }
-    }
-
-    Ok(rules)
-}
-
-/// Parse a single rule from YAML
-fn parse_single_rule(rule_value: &serde_yaml::Value, target_language: Language, file_path: Option<&PathBuf>) -> Result<ParsedRule> {
-    let base_id = rule_value.get("id")
-        .and_then(|v| v.as_str())
-        .unwrap_or("unknown-rule");
-
-    // Generate semgrep-compatible rule ID with path prefix
-    let id = if let Some(path) = file_path {
-        // Convert path to semgrep-style ID prefix (exclude filename, only use directory path)
-        let path_str = path.to_string_lossy();
-        let dir_path = path.parent()
-            .map(|p| p.to_string_lossy())
-            .unwrap_or_else(|| std::borrow::Cow::Borrowed(""));
-
-        let path_prefix = dir_path
-            .strip_prefix("./")
-            .unwrap_or(&dir_path)
-            .replace('/', ".");
-
-        if path_prefix.is_empty() {
-            base_id.to_string()
-        } else {
-            format!("{}.{}", path_prefix, base_id)
-        }
-    } else {
-        base_id.to_string()
-    };
-
-    let message = rule_value.get("message")
-        .and_then(|v| v.as_str())
-        .unwrap_or("Security issue detected")
-        .to_string();
-
-    // Parse severity
-    let severity = rule_value.get("severity")
-        .and_then(|v| v.as_str())
-        .map(|s| match s.to_uppercase().as_str() {
-            "ERROR" | "HIGH" => Severity::Error,
-            "WARNING" | "MEDIUM" => Severity::Warning,
-            "INFO" | "LOW" => Severity::Info,
-            _ => Severity::Warning,
-        })
-        .unwrap_or(Severity::Warning);
-
-    // Parse languages
-    let languages = rule_value.get("languages")
-        .and_then(|v| v.as_sequence())
-        .map(|langs| {
-            langs.iter()
-                .filter_map(|l| l.as_str())
-                .filter_map(|l| Language::from_str(l))
-                .collect()
-        })
-        .unwrap_or_else(|| vec![target_language]);
-
-    // Skip if this rule doesn't apply to the target language
-    if !languages.contains(&target_language) {
-        info!("Rule '{}' skipped: languages {:?} don't include target {:?}", id, languages, target_language);
-        return Err(anyhow::anyhow!("Rule doesn't apply to target language"));
-    }
-
-    info!("Rule '{}' accepted for target language {:?}", id, target_language);
-
-    // Extract patterns using improved pattern extraction
-    let patterns = extract_patterns_from_rule_value(rule_value);
-
-    // Debug: log extracted patterns
-    if !patterns.is_empty() {
-        info!("Rule '{}' extracted patterns: {:?}", id, patterns);
-    }
-
-    // Parse fix suggestion
-    let fix = rule_value.get("fix")
-        .and_then(|v| v.as_str())
-        .map(|s| s.to_string());
-
-    Ok(ParsedRule {
-        id,
-        message,
-        severity,
-        languages,
-        patterns,
-        fix,
-        raw_rule_value: rule_value.clone(),
-    })
-}
-
-/// Extract simple string patterns from Semgrep pattern syntax
-fn extract_simple_patterns(pattern: &str) -> Vec<String> {
-    let mut patterns = Vec::new();
-
-    // Check if this is a metavariable pattern - pass through as-is for tree-sitter
-    if pattern.contains('$') {
-        patterns.push(pattern.trim().to_string());
-        return patterns;
-    }
-
-    // Look for quoted strings in the pattern
-    let re = regex::Regex::new(r#""([^"]+)""#).unwrap();
-    for cap in re.captures_iter(pattern) {
-        if let Some(matched) = cap.get(1) {
-            let pattern_str = matched.as_str();
-            // Skip metavariables like $ALGO
-            if !pattern_str.starts_with('$') {
-                patterns.push(format!("\"{}\"", pattern_str));
-            }
-        }
-    }
-
-    // Look for function calls with ellipsis (e.g., "sink(...)")
-    let func_re = regex::Regex::new(r"(\w+)\s*\(\s*\.\.\.\s*\)").unwrap();
-    for cap in func_re.captures_iter(pattern) {
-        if let Some(func_name) = cap.get(1) {
-            patterns.push(format!("{}(", func_name.as_str()));
-        }
-    }
-
-    // Look for common API calls in patterns
-    if pattern.contains("MessageDigest.getInstance") {
-        patterns.push("MessageDigest.getInstance".to_string());
-    }
-    if pattern.contains("executeQuery") {
-        patterns.push(".executeQuery(".to_string());
-    }
-    if pattern.contains("eval") {
-        patterns.push("eval(".to_string());
-    }
-    if pattern.contains("getSha1Digest") {
-        patterns.push("getSha1Digest".to_string());
-    }
-
-    // Handle simple string literals without quotes (for taint sources)
-    // Only add if we haven't already extracted patterns from quotes
-    if patterns.is_empty() && pattern.trim().starts_with('"') && pattern.trim().ends_with('"') {
-        patterns.push(pattern.trim().to_string());
-    }
-
-    // Handle simple literals (numbers, identifiers, etc.)
-    // If no patterns were extracted yet, treat the whole pattern as a literal
-    if patterns.is_empty() {
-        let trimmed = pattern.trim();
-        if !trimmed.is_empty() {
-            patterns.push(trimmed.to_string());
-        }
-    }
-
-    // Remove duplicates
-    patterns.sort();
-    patterns.dedup();
-
-    patterns
-}
-
-/// Extract patterns from complex Semgrep rule structure
-fn extract_patterns_from_rule_value(rule_value: &serde_yaml::Value) -> Vec<String> {
-    let mut patterns = Vec::new();
-
-    // Handle pattern-either
-    if let Some(pattern_either) = rule_value.get("pattern-either") {
-        if let Some(either_array) = pattern_either.as_sequence() {
-            for item in either_array {
-                patterns.extend(extract_patterns_from_rule_value(item));
-            }
-        }
-    }
-
-    // Handle patterns (array)
-    if let Some(patterns_array) = rule_value.get("patterns") {
-        if let Some(array) = patterns_array.as_sequence() {
-            for item in array {
-                patterns.extend(extract_patterns_from_rule_value(item));
-            }
-        }
-    }
-
-    // Handle single pattern
-    if let Some(pattern_value) = rule_value.get("pattern") {
-        if let Some(pattern_str) = pattern_value.as_str() {
-            patterns.extend(extract_simple_patterns(pattern_str));
-        }
-    }
-
-    // Handle pattern-regex (CRITICAL: This was missing!)
-    if let Some(pattern_regex) = rule_value.get("pattern-regex") {
-        if let Some(regex_str) = pattern_regex.as_str() {
-            // Add the regex pattern directly - it will be used as a regex
-            patterns.push(regex_str.to_string());
-        }
-    }
-
-    // Handle pattern-not-regex
-    if let Some(pattern_not_regex) = rule_value.get("pattern-not-regex") {
-        if let Some(regex_str) = pattern_not_regex.as_str() {
-            // For not-regex patterns, we'll handle them differently in the matching logic
-            // For now, just add them as patterns to be processed
-            patterns.push(format!("NOT_REGEX:{}", regex_str));
-        }
-    }
-
-    // Handle pattern-not-inside
-    if let Some(pattern_not_inside) = rule_value.get("pattern-not-inside") {
-        if let Some(not_inside_str) = pattern_not_inside.as_str() {
-            // For not-inside patterns, we'll handle them differently in the matching logic
-            patterns.push(format!("NOT_INSIDE:{}", not_inside_str));
-        }
-    }
-
-    // Handle metavariable-regex (extract the regex pattern)
-    if let Some(metavar_regex) = rule_value.get("metavariable-regex") {
-        if let Some(regex_value) = metavar_regex.get("regex") {
-            if let Some(regex_str) = regex_value.as_str() {
-                // For SHA1 detection, add specific patterns
-                if regex_str.contains("SHA1") || regex_str.contains("SHA-1") {
-                    patterns.push("\"SHA1\"".to_string());
-                    patterns.push("\"SHA-1\"".to_string());
-                }
-                if regex_str.contains("MD5") {
-                    patterns.push("\"MD5\"".to_string());
-                }
-            }
-        }
-    }
-
-    // Handle taint analysis patterns
-    // Extract patterns from pattern-sources
-    if let Some(sources) = rule_value.get("pattern-sources") {
-        if let Some(sources_array) = sources.as_sequence() {
-            for source in sources_array {
-                patterns.extend(extract_patterns_from_rule_value(source));
-            }
-        }
-    }
-
-    // Extract patterns from pattern-sinks
-    if let Some(sinks) = rule_value.get("pattern-sinks") {
-        if let Some(sinks_array) = sinks.as_sequence() {
-            for sink in sinks_array {
-                patterns.extend(extract_patterns_from_rule_value(sink));
-            }
-        }
-    }
-
-    // Extract patterns from pattern-sanitizers
-    if let Some(sanitizers) = rule_value.get("pattern-sanitizers") {
-        if let Some(sanitizers_array) = sanitizers.as_sequence() {
-            for sanitizer in sanitizers_array {
-                patterns.extend(extract_patterns_from_rule_value(sanitizer));
-            }
-        }
-    }
-
-    patterns
-}
-
-/// Apply a rule that contains NOT_INSIDE patterns
-fn apply_rule_with_not_inside(rule: &ParsedRule, file_path: &PathBuf, source_code: &str) -> Result<Vec<Finding>> {
-    let mut findings = Vec::new();
-
-    // Separate main patterns from NOT_INSIDE patterns
-    let mut main_patterns = Vec::new();
-    let mut not_inside_patterns = Vec::new();
-    let mut not_regex_patterns = Vec::new();
-
-    for pattern in &rule.patterns {
-        if pattern.starts_with("NOT_INSIDE:") {
-            let not_inside_pattern = &pattern[11..]; // Remove "NOT_INSIDE:" prefix
-            not_inside_patterns.push(not_inside_pattern);
-        } else if pattern.starts_with("NOT_REGEX:") {
-            let not_regex_pattern = &pattern[10..]; // Remove "NOT_REGEX:" prefix
-            not_regex_patterns.push(not_regex_pattern);
-        } else {
-            main_patterns.push(pattern);
-        }
-    }
-
-    info!("Rule '{}': Found {} main patterns, {} NOT_INSIDE patterns, {} NOT_REGEX patterns",
-          rule.id, main_patterns.len(), not_inside_patterns.len(), not_regex_patterns.len());
-
-    // Find all matches for main patterns
-    let mut candidate_findings = Vec::new();
-    for pattern in &main_patterns {
-        info!("Processing main pattern: '{}'", pattern);
-
-        // Special handling for unsafe-user-input pattern
-        if pattern.as_str() == "$CMD $USER_INPUT" {
-            // Convert to a regex pattern that matches commands with positional parameters
-            let simplified_pattern = r"(rm|cat|echo|eval|cp|mv|chmod|chown)\s+.*\$[0-9@*]";
-            let pattern_findings = apply_regex_pattern(rule, &simplified_pattern, file_path, source_code)?;
-            info!("Simplified pattern '{}' found {} matches", simplified_pattern, pattern_findings.len());
-            candidate_findings.extend(pattern_findings);
-        } else {
-            let pattern_findings = apply_metavariable_pattern(rule, pattern, file_path, source_code)?;
-            info!("Main pattern '{}' found {} matches", pattern, pattern_findings.len());
-            candidate_findings.extend(pattern_findings);
-        }
-    }
-
-    // Filter out findings that are inside NOT_INSIDE patterns
-    info!("Filtering {} candidate findings", candidate_findings.len());
-    for finding in candidate_findings {
-        let mut should_include = true;
-
-        // Check if this finding is inside any NOT_INSIDE pattern
-        for not_inside_pattern in &not_inside_patterns {
-            if is_finding_inside_pattern(&finding, not_inside_pattern, source_code) {
-                info!("Finding at line {} excluded by NOT_INSIDE pattern: {}", finding.location.start_line, not_inside_pattern);
-                should_include = false;
-                break;
-            }
-        }
-
-        // Check if this finding matches any NOT_REGEX pattern
-        if should_include {
-            for not_regex_pattern in &not_regex_patterns {
-                // Special handling for XML namespace validation rules
-                if rule.id.contains("xml-namespace-prefix") {
-                    // Extract the prefix from the finding message or location
-                    if let Some(prefix) = extract_prefix_from_finding(&finding, source_code) {
-                        if is_xml_namespace_prefix_declared(&prefix, source_code) {
-                            info!("Finding at line {} excluded: namespace prefix '{}' is declared", finding.location.start_line, prefix);
-                            should_include = false;
-                            break;
-                        }
-                    }
-                } else if is_finding_matching_regex(&finding, not_regex_pattern, source_code) {
-                    info!("Finding at line {} excluded by NOT_REGEX pattern: {}", finding.location.start_line, not_regex_pattern);
-                    should_include = false;
-                    break;
-                }
-            }
-        }
-
-        if should_include {
-            info!("Finding at line {} included", finding.location.start_line);
-            findings.push(finding);
-        }
-    }
-
-    // Remove duplicate findings (same rule, same location)
-    findings.sort_by(|a, b| {
-        a.location.start_line.cmp(&b.location.start_line)
-            .then_with(|| a.location.start_column.cmp(&b.location.start_column))
-    });
-    findings.dedup_by(|a, b| {
-        a.location.start_line == b.location.start_line &&
-        a.location.start_column == b.location.start_column &&
-        a.rule_id == b.rule_id
-    });
-
-    Ok(findings)
-}
-
-/// Check if a finding is inside a NOT_INSIDE pattern
-fn is_finding_inside_pattern(finding: &Finding, not_inside_pattern: &str, source_code: &str) -> bool {
-    let lines: Vec<&str> = source_code.lines().collect();
-    let finding_line = finding.location.start_line;
-
-    // Handle simple single-line patterns first
-    if not_inside_pattern.contains("[[ $VAR ]]") {
-        // Check if the finding is inside a [[ ... ]] conditional
-        if finding_line > 0 && finding_line <= lines.len() {
-            let line = lines[finding_line - 1];
-            // Check if the line contains [[ and ]]
-            if line.contains("[[") && line.contains("]]") {
-                return true;
-            }
-        }
-    }
-
-    if not_inside_pattern.contains("(( $VAR ))") {
-        // Check if the finding is inside a (( ... )) arithmetic expression
-        if finding_line > 0 && finding_line <= lines.len() {
-            let line = lines[finding_line - 1];
-            // Check if the line contains (( and ))
-            if line.contains("((") && line.contains("))") {
-                return true;
-            }
-        }
-    }
-
-    // Handle multi-line if-then-fi patterns
-    if (not_inside_pattern.contains("if [[") || not_inside_pattern.contains("if sudo")) &&
-       not_inside_pattern.contains("then") && not_inside_pattern.contains("fi") {
-        return is_finding_inside_if_block(finding, not_inside_pattern, &lines);
-    }
-
-    // Handle other multi-line patterns
-    if not_inside_pattern.contains("$TEMP=$(mktemp)") {
-        return is_finding_inside_mktemp_block(finding, &lines);
-    }
-
-    false
-}
-
-/// Check if a finding is inside an if-then-fi block that matches the NOT_INSIDE pattern
-fn is_finding_inside_if_block(finding: &Finding, not_inside_pattern: &str, lines: &[&str]) -> bool {
-    let finding_line = finding.location.start_line;
-
-    // More flexible pattern matching for different NOT_INSIDE patterns
-    let patterns_to_check = vec![
-        ("== \"yes\"", vec!["if [[ \"$CONFIRM\" == \"yes\" ]]", "if [[ $CONFIRM == \"yes\" ]]"]),
-        ("sudo -n true", vec!["if sudo -n true", "if sudo -n true 2>/dev/null"]),
-        ("=~ ^[a-zA-Z0-9_]+$", vec!["=~ ^[a-zA-Z0-9_]+$"]),
-        ("=~ ^[a-zA-Z0-9_.-]+$", vec!["=~ ^[a-zA-Z0-9_.-]+$"]),
-        ("=~ ^[a-zA-Z0-9_/-]+$", vec!["=~ ^[a-zA-Z0-9_/-]+$"]),
-    ];
-
-    for (pattern_key, if_patterns) in patterns_to_check {
-        if not_inside_pattern.contains(pattern_key) {
-            // Look for if-then-fi blocks that contain this condition
-            for (i, line) in lines.iter().enumerate() {
-                let line_num = i + 1;
-                let trimmed_line = line.trim();
-
-                // Check if this line starts an if block with our condition
-                let matches_if_pattern = if_patterns.iter().any(|if_pattern| {
-                    trimmed_line.starts_with("if ") && trimmed_line.contains(if_pattern)
-                });
-
-                if matches_if_pattern || (trimmed_line.starts_with("if [[") && if_patterns.iter().any(|p| trimmed_line.contains(p))) {
-                    // Find the corresponding fi or end of block
-                    let mut end_line = None;
-                    let mut brace_count = 0;
-
-                    for (j, search_line) in lines.iter().enumerate().skip(i + 1) {
-                        let search_trimmed = search_line.trim();
-
-                        // Count braces for nested blocks
-                        if search_trimmed.contains("then") || search_trimmed.contains("{") {
-                            brace_count += 1;
-                        }
-                        if search_trimmed == "fi" || search_trimmed == "}" {
-                            if brace_count == 0 {
-                                end_line = Some(j + 1);
-                                break;
-                            } else {
-                                brace_count -= 1;
-                            }
+                            Language::Sql,
+                            sn.sql.clone(),
+                        ).with_cancellation(cancellation.clone());
+                        if let Some(flag) = config.sql_statement_boundary {
+                            ctx_sql = ctx_sql.add_data("sql_statement_boundary".to_string(), flag.to_string());
                         }
-                    }
 
-                    if let Some(end_line_num) = end_line {
-                        // Check if the finding is between the if and end lines
-                        if finding_line > line_num && finding_line < end_line_num {
-                            return true;
+                        for rule in &sql_rules {
+                            if let Ok(Some(result)) = engine.execute_rule(&rule.id, ast_sql.as_ref(), &ctx_sql) {
+                                if result.is_success() {
+                                    tracing::debug!("embedded-sql: rule '{}' produced {} findings on snippet #{}", rule.id, result.findings.len(), idx + 1);
+                                    for mut f in result.findings {
+                                        // Adjust location lines by snippet offset
+                                        let mut loc = f.location;
+                                        let line_off = sn.start_line.saturating_sub(1);
+                                        loc.start_line += line_off;
+                                        loc.end_line += line_off;
+                                        // Rewrap into CLI Finding; suppression comments live in the host file
+                                        let suppressed = !config.disable_nosem && astgrep_core::is_suppressed(source_code, loc.start_line, &f.rule_id);
+                                        let target = if suppressed { &mut suppressed_core } else { &mut all_findings_core };
+                                        target.push(astgrep_core::Finding {
+                                            rule_id: f.rule_id,
+                                            message: f.message,
+                                            severity: f.severity,
+                                            confidence: f.confidence,
+                                            location: astgrep_core::Location::new(
+                                                std::path::PathBuf::from(&file_path),
+                                                loc.start_line, loc.start_column, loc.end_line, loc.end_column
+                                            ),
+                                            metadata: {
+                                                let mut m = f.metadata;
+                                                if let Some(ctx) = sn.context.as_ref() { m.insert("embedded_context".to_string(), ctx.clone()); }
+                                                m
+                                            },
+                                            fix_suggestion: f.fix_suggestion,
+                                        });
+                                    }
+                                }
+                            }
                         }
+                    } else {
+                        tracing::warn!("embedded-sql: failed to parse snippet #{} as SQL", idx + 1);
                     }
                 }
             }
         }
     }
 
-    false
-}
-
-/// Check if a finding is inside a mktemp block
-fn is_finding_inside_mktemp_block(finding: &Finding, lines: &[&str]) -> bool {
-    let finding_line = finding.location.start_line;
-
-    // Look for TEMP=$(mktemp) pattern before the finding
-    for (i, line) in lines.iter().enumerate() {
-        let line_num = i + 1;
-
-        if line_num < finding_line && line.contains("=$(mktemp)") {
-            // More sophisticated heuristic: check if the finding uses the same variable
-            if let Some(var_name) = extract_mktemp_variable(line) {
-                // Check if the finding line uses this variable
-                let finding_line_content = lines.get(finding_line - 1).unwrap_or(&"");
-                if finding_line_content.contains(&format!("${}", var_name)) ||
-                   finding_line_content.contains(&format!("\"${}\"", var_name)) {
-                    // If mktemp is within 20 lines before the finding and uses the same variable, consider it protected
-                    if finding_line - line_num <= 20 {
-                        return true;
-                    }
-                }
-            }
-        }
-    }
-
-    false
-}
-
-/// Extract variable name from mktemp assignment
-fn extract_mktemp_variable(line: &str) -> Option<String> {
-    // Look for pattern like: TEMP=$(mktemp) or temp_file=$(mktemp)
-    if let Some(equals_pos) = line.find("=$(mktemp)") {
-        let var_part = &line[..equals_pos];
-        if let Some(var_name) = var_part.split_whitespace().last() {
-            return Some(var_name.to_string());
-        }
-    }
-    None
-}
-
-/// Replace capture groups in message template with actual captured values
-fn replace_capture_groups(message: &str, captures: &regex::Captures) -> String {
-    let mut result = message.to_string();
-
-    // Replace numbered capture groups: ${1}, ${2}, etc.
-    for i in 1..captures.len() {
-        if let Some(captured) = captures.get(i) {
-            let placeholder = format!("${{{}}}", i);
-            result = result.replace(&placeholder, captured.as_str());
-        }
-    }
-
-    result
-}
-
-/// Apply a regex pattern to source code and return findings
-fn apply_regex_pattern(rule: &ParsedRule, pattern: &str, file_path: &PathBuf, source_code: &str) -> Result<Vec<Finding>> {
-    let mut findings = Vec::new();
-
-    if let Ok(regex) = regex::Regex::new(pattern) {
-        for (line_num, line) in source_code.lines().enumerate() {
-            // Skip lines that are comments
-            let trimmed_line = line.trim();
-            if trimmed_line.starts_with('#') || trimmed_line.starts_with("//") || trimmed_line.starts_with("/*") {
-                info!("Skipping comment line {}: '{}'", line_num + 1, line);
-                continue;
-            }
-
-            // Use captures_iter to get all matches with capture groups
-            for captures in regex.captures_iter(line) {
-                if let Some(mat) = captures.get(0) {
-                    // Check if the match is inside a comment on the same line
-                    if let Some(comment_pos) = line.find('#') {
-                        if mat.start() >= comment_pos {
-                            continue; // Skip matches inside inline comments
-                        }
-                    }
-                    if let Some(comment_pos) = line.find("//") {
-                        if mat.start() >= comment_pos {
-                            continue; // Skip matches inside inline comments
-                        }
-                    }
-
-                    // Additional check: if the line starts with # (comment), skip the match entirely
-                    if line.trim_start().starts_with('#') {
-                        continue; // Skip matches in comment lines
-                    }
-
-                    // Replace capture groups in the message
-                    let message = replace_capture_groups(&rule.message, &captures);
-
-                    let finding = Finding {
-                        rule_id: rule.id.clone(),
-                        message,
-                        severity: rule.severity.clone(),
-                        location: Location {
-                            file: file_path.clone(),
-                            start_line: line_num + 1,
-                            end_line: line_num + 1,
-                            start_column: mat.start() + 1,
-                            end_column: mat.end() + 1,
-                        },
-                        confidence: Confidence::High,
-                        fix: None,
-                    };
-                    findings.push(finding);
-                }
-            }
-        }
+    if metrics {
+        crate::commands::metrics::record_rules(engine.configure_executor().take_timings());
     }
 
-    Ok(findings)
-}
-
-/// Check if a finding matches a NOT_REGEX pattern
-fn is_finding_matching_regex(finding: &Finding, not_regex_pattern: &str, source_code: &str) -> bool {
-    let lines: Vec<&str> = source_code.lines().collect();
-    let finding_line = finding.location.start_line;
-
-    if finding_line > 0 && finding_line <= lines.len() {
-        let line = lines[finding_line - 1];
-
-        // Fix double escaping from YAML
-        let fixed_pattern = not_regex_pattern
-            .replace("\\\\s", "\\s")
-            .replace("\\\\d", "\\d")
-            .replace("\\\\w", "\\w")
-            .replace("\\\\$", "\\$");
-
-        info!("Checking NOT_REGEX pattern '{}' (fixed: '{}') against line {}: '{}'",
-              not_regex_pattern, fixed_pattern, finding_line, line);
-
-        if let Ok(regex) = regex::Regex::new(&fixed_pattern) {
-            let matches = regex.is_match(line);
-            info!("NOT_REGEX pattern '{}' {} line {}", fixed_pattern, if matches { "matches" } else { "does not match" }, finding_line);
-            return matches;
-        } else {
-            info!("Failed to compile NOT_REGEX pattern: '{}'", fixed_pattern);
-        }
+    // 4) Convert to CLI Finding shape
+    let mut findings = Vec::with_capacity(all_findings_core.len() + suppressed_core.len());
+    let marked = all_findings_core.into_iter().map(|f| (f, false)).chain(suppressed_core.into_iter().map(|f| (f, true)));
+    for (f, suppressed) in marked {
+        let location = Location::new(f.location.file, f.location.start_line, f.location.start_column, f.location.end_line, f.location.end_column);
+        let taint_corroborated = f.metadata.get("taint_corroborated").is_some_and(|v| v == "true")
+            || f.metadata.get("analysis_type").is_some_and(|v| v == "dataflow");
+        findings.push(Finding {
+            fix: f.fix_suggestion,
+            suppressed,
+            taint_corroborated,
+            ..Finding::new(f.rule_id, f.message, f.severity, f.confidence, location)
+        });
     }
 
-    false
-}
-
-/// Check if a namespace prefix is declared in the XML document
-/// This is a special handler for XML namespace validation
-fn is_xml_namespace_prefix_declared(prefix: &str, source_code: &str) -> bool {
-    use regex::Regex;
-
-    // Look for xmlns:prefix= declaration anywhere in the document
-    let pattern = format!(r#"xmlns:{}[\s]*="#, regex::escape(prefix));
-    if let Ok(regex) = Regex::new(&pattern) {
-        return regex.is_match(source_code);
-    }
-    false
+    Ok((findings, rules_count))
 }
 
-/// Extract all namespace prefixes used in XML elements
-fn extract_used_namespace_prefixes(source_code: &str) -> std::collections::HashSet<String> {
-    use regex::Regex;
-    let mut prefixes = std::collections::HashSet::new();
-
-    // Match <prefix:element patterns
-    if let Ok(regex) = Regex::new(r"<(\w+):(\w+)") {
-        for cap in regex.captures_iter(source_code) {
-            if let Some(prefix_match) = cap.get(1) {
-                prefixes.insert(prefix_match.as_str().to_string());
-            }
-        }
-    }
-
-    prefixes
+/// Load the deduplicated rules found under `rule_paths` (YAML files, directories and packs)
+/// into `engine`. Parsed files come from the shared rule repository cache.
+pub(crate) fn load_rules_into_engine_from_paths(
+    rule_paths: &[PathBuf],
+    engine: &mut astgrep_rules::RuleEngine,
+) -> Result<usize> {
+    load_rules_into_engine(&astgrep_rules::RuleRepository::from_paths(rule_paths), engine)
 }
 
-/// Extract all declared namespace prefixes in XML
-fn extract_declared_namespace_prefixes(source_code: &str) -> std::collections::HashSet<String> {
-    use regex::Regex;
-    let mut prefixes = std::collections::HashSet::new();
-
-    // Match xmlns:prefix= declarations
-    if let Ok(regex) = Regex::new(r#"xmlns:(\w+)[\s]*="#) {
-        for cap in regex.captures_iter(source_code) {
-            if let Some(prefix_match) = cap.get(1) {
-                prefixes.insert(prefix_match.as_str().to_string());
-            }
-        }
-    }
-
-    prefixes
+/// The rules of `--rules`, with the `--policy` applied
+pub(crate) fn rule_repository(config: &EnhancedAnalysisConfig) -> astgrep_rules::RuleRepository {
+    let mut repository = astgrep_rules::RuleRepository::new();
+    // Set before loading, so the rule files are only indexed once
+    repository.set_policy(config.policy.clone());
+    repository.load_paths(&config.rule_files);
+    repository
 }
 
-/// Extract namespace prefix from a finding location in XML
-fn extract_prefix_from_finding(finding: &Finding, source_code: &str) -> Option<String> {
-    use regex::Regex;
-
-    let lines: Vec<&str> = source_code.lines().collect();
-    let finding_line = finding.location.start_line;
-
-    if finding_line > 0 && finding_line <= lines.len() {
-        let line = lines[finding_line - 1];
-
-        // Try to extract prefix from <prefix:element pattern
-        if let Ok(regex) = Regex::new(r"<(\w+):(\w+)") {
-            if let Some(cap) = regex.captures(line) {
-                if let Some(prefix_match) = cap.get(1) {
-                    return Some(prefix_match.as_str().to_string());
-                }
-            }
+/// Add the rules of `repository` to `engine`; returns the number added
+fn load_rules_into_engine(repository: &astgrep_rules::RuleRepository, engine: &mut astgrep_rules::RuleEngine) -> Result<usize> {
+    let mut total = 0usize;
+    for rule in repository.rules() {
+        match engine.add_rule(rule.clone()) {
+            Ok(()) => total += 1,
+            Err(e) => tracing::warn!("Failed to add rule '{}': {}", rule.id, e),
         }
     }
-
-    None
+    Ok(total)
 }
 
 
@@ -2262,119 +1616,7 @@ fn normalize_sql(raw: &str) -> String {
     if s.ends_with(';') { s } else { format!("{};", s) }
 }
 
-#[derive(Clone)]
-struct BasicPattern {
-    rule_id: String,
-    pattern: String,
-    message: String,
-    severity: Severity,
-    confidence: Confidence,
-    fix: Option<String>,
-}
-
-fn get_basic_security_patterns(language: Language) -> Vec<BasicPattern> {
-    match language {
-        Language::Java => vec![
-            BasicPattern {
-                rule_id: "java-sql-injection".to_string(),
-                pattern: ".executeQuery(".to_string(),
-                message: "Potential SQL injection vulnerability".to_string(),
-                severity: Severity::Critical,
-                confidence: Confidence::Medium,
-                fix: Some("Use PreparedStatement instead of Statement".to_string()),
-            },
-            BasicPattern {
-                rule_id: "java-hardcoded-password".to_string(),
-                pattern: "password".to_string(),
-                message: "Potential hardcoded password".to_string(),
-                severity: Severity::Warning,
-                confidence: Confidence::Low,
-                fix: Some("Use environment variables or secure configuration".to_string()),
-            },
-            BasicPattern {
-                rule_id: "java-weak-hash-sha1".to_string(),
-                pattern: "\"SHA1\"".to_string(),
-                message: "Use of SHA1 hash algorithm which is considered insecure".to_string(),
-                severity: Severity::Error,
-                confidence: Confidence::High,
-                fix: Some("Use SHA-256, SHA-384, or SHA-512 instead of SHA1".to_string()),
-            },
-            BasicPattern {
-                rule_id: "java-weak-hash-md5".to_string(),
-                pattern: "\"MD5\"".to_string(),
-                message: "Use of MD5 hash algorithm which is considered insecure".to_string(),
-                severity: Severity::Error,
-                confidence: Confidence::High,
-                fix: Some("Use SHA-256, SHA-384, or SHA-512 instead of MD5".to_string()),
-            },
-        ],
-        Language::JavaScript => vec![
-            BasicPattern {
-                rule_id: "js-eval-usage".to_string(),
-                pattern: "eval(".to_string(),
-                message: "Use of eval() can lead to code injection".to_string(),
-                severity: Severity::Critical,
-                confidence: Confidence::High,
-                fix: Some("Avoid using eval(), use safer alternatives".to_string()),
-            },
-            BasicPattern {
-                rule_id: "js-innerhtml".to_string(),
-                pattern: "innerHTML".to_string(),
-                message: "Potential XSS vulnerability with innerHTML".to_string(),
-                severity: Severity::Warning,
-                confidence: Confidence::Medium,
-                fix: Some("Use textContent or sanitize input".to_string()),
-            },
-        ],
-        Language::Python => vec![
-            BasicPattern {
-                rule_id: "python-exec-usage".to_string(),
-                pattern: "exec(".to_string(),
-                message: "Use of exec() can lead to code injection".to_string(),
-                severity: Severity::Critical,
-                confidence: Confidence::High,
-                fix: Some("Avoid using exec(), use safer alternatives".to_string()),
-            },
-            BasicPattern {
-                rule_id: "python-sql-format".to_string(),
-                pattern: ".format(".to_string(),
-                message: "Potential SQL injection with string formatting".to_string(),
-                severity: Severity::Warning,
-                confidence: Confidence::Low,
-                fix: Some("Use parameterized queries".to_string()),
-            },
-        ],
-        Language::Sql => vec![
-            BasicPattern {
-                rule_id: "sql-union-injection".to_string(),
-                pattern: "UNION".to_string(),
-                message: "Potential SQL injection with UNION".to_string(),
-                severity: Severity::Critical,
-                confidence: Confidence::Medium,
-                fix: Some("Use parameterized queries".to_string()),
-            },
-        ],
-        Language::Bash => vec![
-            BasicPattern {
-                rule_id: "bash-command-injection".to_string(),
-                pattern: "$((".to_string(),
-                message: "Potential command injection".to_string(),
-                severity: Severity::Critical,
-                confidence: Confidence::Medium,
-                fix: Some("Validate and sanitize input".to_string()),
-            },
-        ],
-        Language::Php => vec![],
-        Language::CSharp => vec![],
-        Language::C => vec![],
-        Language::Ruby => vec![],
-        Language::Kotlin => vec![],
-        Language::Swift => vec![],
-        Language::Xml => vec![],
-    }
-}
-
-fn determine_language(file_path: &PathBuf) -> Result<Language> {
+pub(crate) fn determine_language(file_path: &Path) -> Result<Language> {
     if let Some(extension) = file_path.extension() {
         let ext_str = extension.to_string_lossy().to_lowercase();
         match ext_str.as_str() {
@@ -2414,14 +1656,26 @@ fn apply_filters(findings: &[Finding], config: &EnhancedAnalysisConfig) -> Vec<F
                 }
             }
 
+            // Apply risk score gate
+            if let Some(min_risk_score) = config.min_risk_score {
+                if finding.risk_score.unwrap_or(0) < min_risk_score {
+                    return false;
+                }
+            }
+
+            // Apply the --filter expression
+            if let Some(ref filter) = config.finding_filter {
+                if !filter.matches(finding) {
+                    return false;
+                }
+            }
+
             true
         })
         .cloned()
         .collect()
 }
 
-// Simplified baseline comparison (removed for now to avoid complexity)
-
 fn generate_enhanced_output(
     findings: &[Finding],
     stats: &AnalysisStatistics,
@@ -2429,6 +1683,10 @@ fn generate_enhanced_output(
     total_time: std::time::Duration,
     profiler: Option<&PerformanceProfiler>,
 ) -> Result<String> {
+    if let Some(ref template) = config.output_template {
+        return crate::commands::report_template::render(template, findings, stats, config, total_time);
+    }
+
     // Check for compatibility mode
     if let Some(ref compatible_mode) = config.compatible_mode {
         match compatible_mode.to_lowercase().as_str() {
@@ -2442,12 +1700,28 @@ fn generate_enhanced_output(
     match config.output_format {
         OutputFormat::Json => generate_json_output(findings, stats, config, total_time, profiler),
         OutputFormat::Sarif => generate_sarif_output(findings, stats, config, total_time),
+        OutputFormat::CodeClimate => Ok(generate_code_climate_output(findings)),
+        OutputFormat::Csv => Ok(crate::commands::csv::render(findings, &config.csv_columns)),
+        OutputFormat::Html => Ok(crate::commands::html_report::render(findings, stats, total_time)),
+        OutputFormat::Markdown => Ok(crate::commands::markdown_report::render(findings, stats, total_time, config.context_lines)),
+        OutputFormat::Jsonl => crate::commands::jsonl::render(findings),
+        OutputFormat::GithubActions => Ok(crate::commands::github_actions::render(findings)),
         OutputFormat::Xml => generate_text_output(findings, stats, config, total_time, profiler), // XML not implemented
         OutputFormat::Yaml => generate_text_output(findings, stats, config, total_time, profiler), // YAML not implemented
         OutputFormat::Text => generate_text_output(findings, stats, config, total_time, profiler),
     }
 }
 
+/// Render findings in the output format of `config`, as the `convert` command does
+pub(crate) fn render_report(
+    findings: &[Finding],
+    stats: &AnalysisStatistics,
+    config: &EnhancedAnalysisConfig,
+    total_time: std::time::Duration,
+) -> Result<String> {
+    generate_enhanced_output(findings, stats, config, total_time, None)
+}
+
 fn generate_json_output(
     findings: &[Finding],
     stats: &AnalysisStatistics,
@@ -2458,15 +1732,53 @@ fn generate_json_output(
     use serde_json::json;
 
     let mut output = json!({
+        "schema_version": astgrep_core::REPORT_SCHEMA_VERSION,
         "findings": findings,
         "summary": {
             "total_findings": findings.len(),
             "files_analyzed": stats.files_analyzed,
             "rules_executed": stats.rules_executed,
             "analysis_time_ms": total_time.as_millis(),
+            "status": stats.status(),
         }
     });
 
+    if let Some(context) = config.context_lines {
+        for (finding, value) in findings.iter().zip(output["findings"].as_array_mut().into_iter().flatten()) {
+            if let Some(lines) = crate::commands::source_context::read(finding, context) {
+                value["context"] = json!(lines);
+            }
+        }
+    }
+
+    if !stats.suppressed_findings.is_empty() {
+        output["suppressed_findings"] = json!(stats.suppressed_findings);
+    }
+    if !stats.degraded_languages.is_empty() {
+        output["summary"]["degraded_languages"] = json!(stats.degraded_languages);
+    }
+    if !stats.skipped_fixes.is_empty() {
+        output["summary"]["skipped_fixes"] = json!(stats.skipped_fixes);
+    }
+    if let Some(matched) = stats.baseline_matched {
+        output["summary"]["baseline_matched"] = json!(matched);
+    }
+    if !stats.skipped_files.is_empty() {
+        output["summary"]["skipped_files"] = json!(stats.skipped_files);
+    }
+    if !stats.failed_files.is_empty() {
+        output["summary"]["failed_files"] = json!(stats.failed_files);
+    }
+    if !stats.roots.is_empty() {
+        output["summary"]["roots"] = json!(stats.roots);
+    }
+    if let Some(ref image) = stats.image {
+        output["summary"]["image"] = json!(image);
+    }
+    if let Some(ref profile) = stats.profile {
+        output["summary"]["profile"] = json!(profile);
+    }
+
     if config.include_metrics {
         output["statistics"] = json!(stats);
 
@@ -2486,35 +1798,84 @@ fn generate_text_output(
     profiler: Option<&PerformanceProfiler>,
 ) -> Result<String> {
     let mut output = String::new();
+    let painter = Painter::new(config.color);
+
+    output.push_str(&painter.bold("=== astgrep Analysis Results ==="));
+    output.push_str("\n\n");
+
+    if !stats.degraded_languages.is_empty() {
+        output.push_str(&painter.yellow("⚠️  WARNING: some parsers are unavailable; these languages were analyzed with regex rules only:"));
+        output.push_str("\n");
+        for entry in &stats.degraded_languages {
+            output.push_str(&format!("   - {}: {}\n", entry.language, entry.reason));
+        }
+        output.push_str("\n");
+    }
 
-    output.push_str("=== astgrep Analysis Results ===\n\n");
+    if !stats.skipped_fixes.is_empty() {
+        output.push_str(&painter.yellow(&format!("⚠️  {} fix(es) were not applied:", stats.skipped_fixes.len())));
+        output.push_str("\n");
+        for skipped in &stats.skipped_fixes {
+            output.push_str(&format!("   - {} at {}:{}: {}\n", skipped.rule_id, skipped.file.display(), skipped.line, skipped.reason));
+        }
+        output.push_str("\n");
+    }
 
     if findings.is_empty() {
-        output.push_str("✅ No issues found!\n\n");
-    } else {
+        output.push_str(&painter.green("✅ No issues found!"));
+        output.push_str("\n\n");
+    } else if stats.roots.is_empty() {
         output.push_str(&format!("Found {} issue(s):\n\n", findings.len()));
+        push_text_findings(&mut output, findings.iter(), &painter, config.context_lines);
+    } else {
+        output.push_str(&format!("Found {} issue(s) across {} root(s):\n\n", findings.len(), stats.roots.len()));
 
-        for (i, finding) in findings.iter().enumerate() {
-            output.push_str(&format!("{}. {} ({})\n", i + 1, finding.message, finding.rule_id));
-            output.push_str(&format!("   File: {}:{}:{}\n",
-                finding.location.file.display(),
-                finding.location.start_line,
-                finding.location.start_column
-            ));
-            output.push_str(&format!("   Severity: {:?}, Confidence: {:?}\n",
-                finding.severity, finding.confidence
+        // One section per workspace root
+        for root in &stats.roots {
+            output.push_str(&painter.bold(&format!("--- {} ---", root.root)));
+            output.push_str("\n");
+            output.push_str(&format!("Files analyzed: {}, Rules executed: {}, Issues: {}\n",
+                root.files_analyzed, root.rules_executed, root.total_findings
             ));
-            if let Some(ref fix) = finding.fix {
-                output.push_str(&format!("   Fix: {}\n", fix));
+            if let Some(ref config_file) = root.config_file {
+                output.push_str(&format!("Config: {}\n", config_file));
             }
             output.push_str("\n");
+            let root_path = Path::new(&root.root);
+            push_text_findings(&mut output, findings.iter().filter(|f| f.location.file.starts_with(root_path)), &painter, config.context_lines);
         }
     }
 
     // Summary
-    output.push_str("=== Summary ===\n");
+    output.push_str(&painter.bold("=== Summary ==="));
+    output.push_str("\n");
+    if stats.cancelled {
+        output.push_str("Status: cancelled (partial results)\n");
+    } else if stats.budget_exhausted {
+        output.push_str("Status: time budget exhausted (partial results)\n");
+    }
+    if let Some(ref profile) = stats.profile {
+        output.push_str(&format!("Profile: {}\n", profile.describe()));
+    }
+    if let Some(ref image) = stats.image {
+        output.push_str(&format!("Image: {} ({} layers, {} application files)\n",
+            image.reference, image.layers.len(), image.files_extracted
+        ));
+        if !image.manifests.is_empty() {
+            output.push_str(&format!("Manifests: {}\n", image.manifests.join(", ")));
+        }
+    }
     output.push_str(&format!("Files analyzed: {}\n", stats.files_analyzed));
     output.push_str(&format!("Rules executed: {}\n", stats.rules_executed));
+    if let Some(matched) = stats.baseline_matched {
+        output.push_str(&format!("Baseline findings hidden: {}\n", matched));
+    }
+    if !stats.skipped_files.is_empty() {
+        output.push_str(&format!("Files skipped: {} ({})\n", stats.skipped_files.len(), crate::commands::target_filter::describe(&stats.skipped_files)));
+    }
+    if !stats.failed_files.is_empty() {
+        output.push_str(&format!("Files failed: {} ({})\n", stats.failed_files.len(), crate::commands::isolation::describe(&stats.failed_files)));
+    }
     output.push_str(&format!("Analysis time: {:?}\n", total_time));
 
     if config.include_metrics {
@@ -2530,9 +1891,64 @@ fn generate_text_output(
     Ok(output)
 }
 
+fn push_text_findings<'a>(output: &mut String, findings: impl Iterator<Item = &'a Finding>, painter: &Painter, context: Option<usize>) {
+    for (i, finding) in findings.enumerate() {
+        let header = format!("{}. {} ({})", i + 1, finding.message, finding.rule_id);
+        output.push_str(&painter.severity(&finding.severity, &header));
+        output.push_str("\n");
+        output.push_str(&painter.dim(&format!("   File: {}:{}:{}",
+            finding.location.file.display(),
+            finding.location.start_line,
+            finding.location.start_column
+        )));
+        output.push_str("\n");
+        if let Some(lines) = context.and_then(|context| crate::commands::source_context::read(finding, context)) {
+            output.push_str(&format!("   {}\n", painter.dim("Code:")));
+            let width = lines.last().map_or(1, |l| l.line.to_string().len());
+            for line in &lines {
+                let (head, span, tail) = line.split();
+                let marker = if line.matched.is_some() { '>' } else { ' ' };
+                output.push_str(&format!("   {}{}\n",
+                    painter.dim(&format!("{} {:>width$} | {}", marker, line.line, head, width = width)),
+                    painter.underline(span) + &painter.dim(tail)
+                ));
+            }
+        } else if let Some((head, span, tail)) = split_source_line(finding) {
+            output.push_str(&format!("   {} {}{}{}\n",
+                painter.dim("Code:"),
+                painter.dim(head.trim_start()),
+                painter.underline(&span),
+                painter.dim(tail.trim_end())
+            ));
+        }
+        output.push_str(&format!("   {} {}{} {:?}\n",
+            painter.dim("Severity:"),
+            painter.severity(&finding.severity, &format!("{:?}", finding.severity)),
+            painter.dim(", Confidence:"),
+            finding.confidence
+        ));
+        if let (Some(score), Some(effort)) = (finding.risk_score, finding.effort) {
+            output.push_str(&painter.dim(&format!("   Risk: {}/100, Effort: {}", score, effort.as_str())));
+            output.push_str("\n");
+        }
+        if let Some(ref layer) = finding.layer {
+            output.push_str(&painter.dim(&format!("   Layer: {}", layer)));
+            output.push_str("\n");
+        }
+        if let Some(ref fix) = finding.fix {
+            output.push_str(&format!("   {} {}\n", painter.dim("Fix:"), fix));
+        }
+        if let Some(ref fingerprint) = finding.fingerprint {
+            output.push_str(&painter.dim(&format!("   Fingerprint: {}", fingerprint)));
+            output.push_str("\n");
+        }
+        output.push_str("\n");
+    }
+}
+
 fn generate_sarif_output(
     findings: &[Finding],
-    _stats: &AnalysisStatistics,
+    stats: &AnalysisStatistics,
     _config: &EnhancedAnalysisConfig,
     _total_time: std::time::Duration,
 ) -> Result<String> {
@@ -2550,8 +1966,20 @@ fn generate_sarif_output(
                     "informationUri": "https://github.com/your-org/astgrep"
                 }
             },
-            "results": findings.iter().map(|finding| {
-                json!({
+            "invocations": [{
+                "executionSuccessful": !stats.cancelled && !stats.budget_exhausted,
+                "properties": {
+                    "status": stats.status(),
+                    "profile": stats.profile
+                },
+                "toolExecutionNotifications": stats.failed_files.iter().map(|failed| json!({
+                    "level": "error",
+                    "message": { "text": format!("Analysis aborted: {}", failed.message) },
+                    "locations": [{ "physicalLocation": { "artifactLocation": { "uri": failed.file } } }]
+                })).collect::<Vec<_>>()
+            }],
+            "results": findings.iter().chain(&stats.suppressed_findings).map(|finding| {
+                let mut result = json!({
                     "ruleId": finding.rule_id,
                     "message": {
                         "text": finding.message
@@ -2575,7 +2003,27 @@ fn generate_sarif_output(
                             }
                         }
                     }]
-                })
+                });
+                if let Some(ref fingerprint) = finding.fingerprint {
+                    result["partialFingerprints"] = json!({ SARIF_FINGERPRINT_KEY: fingerprint });
+                }
+                let mut properties = serde_json::Map::new();
+                if let Some(ref category) = finding.category {
+                    properties.insert("category".to_string(), json!(category));
+                }
+                if let Some(score) = finding.risk_score {
+                    properties.insert("riskScore".to_string(), json!(score));
+                }
+                if let Some(effort) = finding.effort {
+                    properties.insert("effort".to_string(), json!(effort));
+                }
+                if !properties.is_empty() {
+                    result["properties"] = serde_json::Value::Object(properties);
+                }
+                if finding.suppressed {
+                    result["suppressions"] = json!([{ "kind": "inSource" }]);
+                }
+                result
             }).collect::<Vec<_>>()
         }]
     });
@@ -2583,114 +2031,64 @@ fn generate_sarif_output(
     Ok(serde_json::to_string_pretty(&sarif)?)
 }
 
-fn generate_html_output(
-    findings: &[Finding],
-    stats: &AnalysisStatistics,
-    _config: &EnhancedAnalysisConfig,
-    total_time: std::time::Duration,
-) -> Result<String> {
-    let mut html = String::new();
-
-    html.push_str("<!DOCTYPE html>\n<html>\n<head>\n");
-    html.push_str("<title>astgrep Analysis Report</title>\n");
-    html.push_str("<style>\n");
-    html.push_str("body { font-family: Arial, sans-serif; margin: 20px; }\n");
-    html.push_str(".finding { border: 1px solid #ddd; margin: 10px 0; padding: 10px; }\n");
-    html.push_str(".error { border-left: 5px solid #f44336; }\n");
-    html.push_str(".warning { border-left: 5px solid #ff9800; }\n");
-    html.push_str(".info { border-left: 5px solid #2196f3; }\n");
-    html.push_str("</style>\n");
-    html.push_str("</head>\n<body>\n");
-
-    html.push_str("<h1>astgrep Analysis Report</h1>\n");
-    html.push_str(&format!("<p>Generated on: {}</p>\n", chrono::Utc::now().format("%Y-%m-%d %H:%M:%S UTC")));
-
-    html.push_str("<h2>Summary</h2>\n");
-    html.push_str(&format!("<p>Total findings: {}</p>\n", findings.len()));
-    html.push_str(&format!("<p>Files analyzed: {}</p>\n", stats.files_analyzed));
-    html.push_str(&format!("<p>Analysis time: {:?}</p>\n", total_time));
-
-    if !findings.is_empty() {
-        html.push_str("<h2>Findings</h2>\n");
+/// Code Climate engine output: one JSON issue per finding, each terminated by a NUL character.
+/// Fingerprints are line-independent, so issues keep their identity across commits.
+fn generate_code_climate_output(findings: &[Finding]) -> String {
+    use serde_json::json;
 
-        for finding in findings {
-            let severity_class = match finding.severity {
-                Severity::Critical => "error",
-                Severity::Error => "error",
-                Severity::Warning => "warning",
+    let current_dir = std::env::current_dir().ok();
+    let mut output = String::new();
+    for (finding, fingerprint) in findings.iter().zip(fingerprints(findings)) {
+        let file = &finding.location.file;
+        let path = current_dir.as_deref().and_then(|dir| file.strip_prefix(dir).ok()).unwrap_or(file);
+        let path = path.to_string_lossy().replace('\\', "/");
+        let issue = json!({
+            "type": "issue",
+            "check_name": finding.rule_id,
+            "description": finding.message,
+            "categories": [code_climate_category(finding.category.as_deref())],
+            "severity": match finding.severity {
                 Severity::Info => "info",
-            };
-
-            html.push_str(&format!("<div class=\"finding {}\">\n", severity_class));
-            html.push_str(&format!("<h3>{}</h3>\n", finding.message));
-            html.push_str(&format!("<p><strong>Rule:</strong> {}</p>\n", finding.rule_id));
-            html.push_str(&format!("<p><strong>File:</strong> {}:{}:{}</p>\n",
-                finding.location.file.display(),
-                finding.location.start_line,
-                finding.location.start_column
-            ));
-            html.push_str(&format!("<p><strong>Severity:</strong> {:?}</p>\n", finding.severity));
-            html.push_str(&format!("<p><strong>Confidence:</strong> {:?}</p>\n", finding.confidence));
-
-            if let Some(ref fix) = finding.fix {
-                html.push_str(&format!("<p><strong>Fix:</strong> {}</p>\n", fix));
-            }
-
-            html.push_str("</div>\n");
-        }
+                Severity::Warning => "minor",
+                Severity::Error => "major",
+                Severity::Critical => "critical",
+            },
+            "location": {
+                "path": path.strip_prefix("./").unwrap_or(&path),
+                "positions": {
+                    "begin": { "line": finding.location.start_line, "column": finding.location.start_column },
+                    "end": { "line": finding.location.end_line, "column": finding.location.end_column }
+                }
+            },
+            "fingerprint": fingerprint,
+        });
+        output.push_str(&issue.to_string());
+        output.push('\0');
     }
-
-    html.push_str("</body>\n</html>\n");
-
-    Ok(html)
+    output
 }
 
-fn generate_markdown_output(
-    findings: &[Finding],
-    stats: &AnalysisStatistics,
-    _config: &EnhancedAnalysisConfig,
-    total_time: std::time::Duration,
-) -> Result<String> {
-    let mut md = String::new();
-
-    md.push_str("# astgrep Analysis Report\n\n");
-    md.push_str(&format!("**Generated:** {}\n\n", chrono::Utc::now().format("%Y-%m-%d %H:%M:%S UTC")));
-
-    md.push_str("## Summary\n\n");
-    md.push_str(&format!("- **Total findings:** {}\n", findings.len()));
-    md.push_str(&format!("- **Files analyzed:** {}\n", stats.files_analyzed));
-    md.push_str(&format!("- **Analysis time:** {:?}\n\n", total_time));
-
-    if !findings.is_empty() {
-        md.push_str("## Findings\n\n");
-
-        for (i, finding) in findings.iter().enumerate() {
-            let severity_emoji = match finding.severity {
-                Severity::Critical => "🔴",
-                Severity::Error => "🔴",
-                Severity::Warning => "🟡",
-                Severity::Info => "🔵",
-            };
-
-            md.push_str(&format!("### {} {}. {}\n\n", severity_emoji, i + 1, finding.message));
-            md.push_str(&format!("- **Rule:** `{}`\n", finding.rule_id));
-            md.push_str(&format!("- **File:** `{}:{}:{}`\n",
-                finding.location.file.display(),
-                finding.location.start_line,
-                finding.location.start_column
-            ));
-            md.push_str(&format!("- **Severity:** {:?}\n", finding.severity));
-            md.push_str(&format!("- **Confidence:** {:?}\n", finding.confidence));
-
-            if let Some(ref fix) = finding.fix {
-                md.push_str(&format!("- **Fix:** {}\n", fix));
-            }
-
-            md.push_str("\n");
-        }
+/// The fingerprints of `findings`, computed for findings that did not go through the
+/// fingerprinting pass
+pub(crate) fn fingerprints(findings: &[Finding]) -> Vec<String> {
+    if let Some(assigned) = findings.iter().map(|f| f.fingerprint.clone()).collect::<Option<Vec<String>>>() {
+        return assigned;
     }
+    let mut computed = findings.to_vec();
+    crate::commands::fingerprint::assign(&mut computed, |_| None);
+    findings.iter().zip(computed).map(|(f, c)| f.fingerprint.clone().or(c.fingerprint).unwrap_or_default()).collect()
+}
 
-    Ok(md)
+/// The Code Climate category closest to a rule's `category` metadata
+fn code_climate_category(category: Option<&str>) -> &'static str {
+    match category.map(str::to_lowercase).as_deref() {
+        Some("security") | Some("supply-chain") => "Security",
+        Some("performance") => "Performance",
+        Some("style") | Some("best-practice") | Some("maintainability") => "Style",
+        Some("complexity") => "Complexity",
+        Some("compatibility") | Some("portability") => "Compatibility",
+        _ => "Bug Risk",
+    }
 }
 
 /// Analysis statistics
@@ -2701,6 +2099,43 @@ pub struct AnalysisStatistics {
     pub parse_errors: usize,
     pub analysis_errors: usize,
     pub dataflow_analyses: usize,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub roots: Vec<crate::commands::workspace::RootStatistics>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image: Option<crate::commands::image::ImageSummary>,
+    /// The analysis was interrupted and the results are partial
+    pub cancelled: bool,
+    /// Languages analyzed regex-only because their parser failed the health check
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub degraded_languages: Vec<DegradedLanguage>,
+    /// Scan profile the run used
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<crate::commands::scan_profile::ScanProfile>,
+    /// The profile's time budget ran out and the results are partial
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub budget_exhausted: bool,
+    /// Fixes `--fix` left out, with the reason
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub skipped_fixes: Vec<crate::commands::autofix::SkippedFix>,
+    /// Findings dropped by inline suppression comments, kept with `--report-suppressed`
+    #[serde(skip)]
+    pub suppressed_findings: Vec<Finding>,
+    /// Findings left out because the `--baseline` file has them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub baseline_matched: Option<usize>,
+    /// Binary and oversized target files that were not analyzed
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub skipped_files: Vec<SkippedFile>,
+    /// Target files whose analysis failed or timed out, so their findings are missing
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub failed_files: Vec<FailedFile>,
+}
+
+/// A language whose parser is unavailable, with the health check's reason
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct DegradedLanguage {
+    pub language: String,
+    pub reason: String,
 }
 
 impl AnalysisStatistics {
@@ -2711,6 +2146,28 @@ impl AnalysisStatistics {
             parse_errors: 0,
             analysis_errors: 0,
             dataflow_analyses: 0,
+            roots: Vec::new(),
+            image: None,
+            cancelled: false,
+            degraded_languages: Vec::new(),
+            profile: None,
+            budget_exhausted: false,
+            skipped_fixes: Vec::new(),
+            suppressed_findings: Vec::new(),
+            baseline_matched: None,
+            skipped_files: Vec::new(),
+            failed_files: Vec::new(),
+        }
+    }
+
+    /// Completion status reported in the output summary
+    pub fn status(&self) -> &'static str {
+        if self.cancelled {
+            "cancelled"
+        } else if self.budget_exhausted {
+            "budget-exhausted"
+        } else {
+            "completed"
         }
     }
 }
@@ -2782,7 +2239,7 @@ fn generate_semgrep_compatible_output(
                 // Display all findings for this rule
                 for (i, finding) in rule_findings.iter().enumerate() {
                     writeln!(&mut output, "           {}┆ {}", finding.location.start_line,
-                            get_source_line(&finding.location.file, finding.location.start_line).unwrap_or_else(|| "<source unavailable>".to_string()))?;
+                            displayed_source_line(finding).unwrap_or_else(|| "<source unavailable>".to_string()))?;
 
                     // Add separator between findings (except for the last one)
                     if rule_findings.len() > 1 && i < rule_findings.len() - 1 {
@@ -2816,6 +2273,39 @@ fn generate_semgrep_compatible_output(
     Ok(output)
 }
 
+/// Source line shown for a finding, with the matched text redacted when required
+fn displayed_source_line(finding: &Finding) -> Option<String> {
+    let redaction = match finding.redaction {
+        Some(redaction) => redaction,
+        None => return get_source_line(&finding.location.file, finding.location.start_line),
+    };
+
+    match split_source_line(finding) {
+        Some((head, span, tail)) => Some(format!("{}{}{}", head, span, tail).trim().to_string()),
+        // Columns that do not fall on character boundaries: hide the whole line
+        None => get_source_line(&finding.location.file, finding.location.start_line).map(|line| redaction.apply(&line)),
+    }
+}
+
+/// The finding's first source line split into the text before, inside and after the
+/// matched span; the span is redacted when the finding asks for it
+fn split_source_line(finding: &Finding) -> Option<(String, String, String)> {
+    let line = std::fs::read_to_string(&finding.location.file)
+        .ok()?
+        .lines()
+        .nth(finding.location.start_line.checked_sub(1)?)?
+        .to_string();
+    let loc = &finding.location;
+    let start = loc.start_column.saturating_sub(1).min(line.len());
+    let end = if loc.end_line == loc.start_line { loc.end_column.saturating_sub(1).clamp(start, line.len()) } else { line.len() };
+    let (head, span, tail) = (line.get(..start)?, line.get(start..end)?, line.get(end..)?);
+    let span = match finding.redaction {
+        Some(redaction) => redaction.apply(span),
+        None => span.to_string(),
+    };
+    Some((head.to_string(), span, tail.to_string()))
+}
+
 /// Helper function to get source line from file
 fn get_source_line(file_path: &std::path::Path, line_number: usize) -> Option<String> {
     use std::fs::File;
@@ -0,0 +1,237 @@
+//! Archive targets for the analyze command
+//!
+//! `.zip`, `.jar`, `.war`, `.tar`, `.tar.gz` and `.tgz` targets are unpacked to a private
+//! temporary directory that is analyzed in their place. Findings in an archive are reported under a
+//! virtual path joining the archive and the entry with `!`, e.g.
+//! `libs/app.jar!com/example/Foo.java`. Entries that would land outside the scratch
+//! directory are skipped.
+
+use crate::commands::image::{open_maybe_gzip, read_tar, TarEntryKind};
+use anyhow::{anyhow, Result};
+use std::io;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// Extensions of zip-based archives
+const ZIP_EXTENSIONS: &[&str] = &[".zip", ".jar", ".war", ".ear"];
+
+/// Extensions of tar archives, optionally gzip-compressed
+const TAR_EXTENSIONS: &[&str] = &[".tar", ".tar.gz", ".tgz"];
+
+/// Archive targets unpacked for analysis; the scratch directory is removed on drop
+#[derive(Debug, Default)]
+pub struct ExtractedArchives {
+    work_dir: Option<tempfile::TempDir>,
+    /// (archive as given on the command line, directory it was unpacked to)
+    roots: Vec<(PathBuf, PathBuf)>,
+}
+
+impl ExtractedArchives {
+    /// Number of archives unpacked
+    pub fn len(&self) -> usize {
+        self.roots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.roots.is_empty()
+    }
+
+    /// `targets` with the directories archives were unpacked to replaced by the archives
+    pub fn restore_targets(&self, targets: &[PathBuf]) -> Vec<PathBuf> {
+        targets
+            .iter()
+            .map(|target| self.roots.iter().find(|(_, root)| root == target).map_or(target, |(archive, _)| archive).clone())
+            .collect()
+    }
+
+    /// Virtual path (`<archive>!<entry>`) of a file under an unpacked archive
+    pub fn locate(&self, file: &Path) -> Option<PathBuf> {
+        self.roots.iter().find_map(|(archive, root)| {
+            let entry = file.strip_prefix(root).ok()?;
+            let entry = entry.to_string_lossy().replace('\\', "/");
+            Some(PathBuf::from(format!("{}!{}", archive.display(), entry)))
+        })
+    }
+}
+
+/// Whether `path` is an archive file the analyze command unpacks
+pub fn is_archive(path: &Path) -> bool {
+    archive_kind(path).is_some() && path.is_file()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArchiveKind {
+    Zip,
+    Tar,
+}
+
+fn archive_kind(path: &Path) -> Option<ArchiveKind> {
+    let name = path.file_name()?.to_string_lossy().to_lowercase();
+    if ZIP_EXTENSIONS.iter().any(|ext| name.ends_with(ext)) {
+        Some(ArchiveKind::Zip)
+    } else if TAR_EXTENSIONS.iter().any(|ext| name.ends_with(ext)) {
+        Some(ArchiveKind::Tar)
+    } else {
+        None
+    }
+}
+
+/// Unpack the archives among `targets` into a temporary directory, replacing each with the
+/// directory it was unpacked to
+pub fn extract_targets(targets: &mut [PathBuf]) -> Result<ExtractedArchives> {
+    let mut extracted = ExtractedArchives::default();
+    for (index, target) in targets.iter_mut().enumerate() {
+        if !is_archive(target) {
+            continue;
+        }
+        let work_dir = match extracted.work_dir.take() {
+            Some(work_dir) => work_dir,
+            None => tempfile::Builder::new().prefix("astgrep-archives-").tempdir()?,
+        };
+        let root = work_dir.path().join(index.to_string());
+        extracted.work_dir = Some(work_dir);
+        std::fs::create_dir_all(&root)?;
+        let files = extract_archive(target, &root).map_err(|e| anyhow!("Cannot unpack archive {}: {}", target.display(), e))?;
+        info!("Unpacked {} file(s) from {}", files, target.display());
+        extracted.roots.push((std::mem::replace(target, root.clone()), root));
+    }
+    Ok(extracted)
+}
+
+/// Unpack the regular files of `archive` into `dest`; returns how many were written
+fn extract_archive(archive: &Path, dest: &Path) -> Result<usize> {
+    let mut files = 0;
+    match archive_kind(archive) {
+        Some(ArchiveKind::Zip) => {
+            let mut zip = zip::ZipArchive::new(std::fs::File::open(archive)?)?;
+            for index in 0..zip.len() {
+                let mut entry = zip.by_index(index)?;
+                if !entry.is_file() {
+                    continue;
+                }
+                let Some(path) = entry.enclosed_name().map(Path::to_path_buf) else {
+                    warn!("Skipping zip entry outside the archive root: {}", entry.name());
+                    continue;
+                };
+                write_entry(&dest.join(path), &mut entry)?;
+                files += 1;
+            }
+        }
+        Some(ArchiveKind::Tar) => {
+            read_tar(open_maybe_gzip(archive)?, |path, kind, data| {
+                if kind == TarEntryKind::File {
+                    write_entry(&dest.join(path), data)?;
+                    files += 1;
+                }
+                Ok(())
+            })?;
+        }
+        None => return Err(anyhow!("not a zip or tar archive")),
+    }
+    Ok(files)
+}
+
+fn write_entry(dest: &Path, data: &mut dyn io::Read) -> Result<()> {
+    if let Some(parent) = dest.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    io::copy(data, &mut std::fs::File::create(dest)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn write_zip(path: &Path, entries: &[(&str, &str)]) {
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(path).unwrap());
+        for (name, content) in entries {
+            zip.start_file(*name, zip::write::FileOptions::default()).unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    fn write_tar_gz(path: &Path, entries: &[(&str, &str)]) {
+        let mut tar = Vec::new();
+        for (name, content) in entries {
+            let mut header = [0u8; 512];
+            header[..name.len()].copy_from_slice(name.as_bytes());
+            header[124..135].copy_from_slice(format!("{:011o}", content.len()).as_bytes());
+            header[156] = b'0';
+            header[257..263].copy_from_slice(b"ustar\0");
+            tar.extend_from_slice(&header);
+            tar.extend_from_slice(content.as_bytes());
+            tar.resize(tar.len() + (512 - content.len() % 512) % 512, 0);
+        }
+        tar.extend_from_slice(&[0u8; 1024]);
+        let mut gz = flate2::write::GzEncoder::new(std::fs::File::create(path).unwrap(), flate2::Compression::default());
+        gz.write_all(&tar).unwrap();
+        gz.finish().unwrap();
+    }
+
+    #[test]
+    fn test_unpacks_archive_targets_and_maps_paths_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let jar = dir.path().join("app.jar");
+        let tgz = dir.path().join("site.tar.gz");
+        write_zip(&jar, &[("com/example/Foo.java", "class Foo {}"), ("../escape.java", "class X {}")]);
+        write_tar_gz(&tgz, &[("./src/app.js", "eval(x)\n")]);
+        let plain = dir.path().join("main.py");
+        std::fs::write(&plain, "print(1)\n").unwrap();
+
+        let mut targets = vec![jar.clone(), plain.clone(), tgz.clone()];
+        let extracted = extract_targets(&mut targets).unwrap();
+        let work_dir = targets[0].parent().unwrap().to_path_buf();
+        assert_eq!(extracted.len(), 2);
+        assert_eq!(targets[1], plain);
+        assert!(targets[0].join("com/example/Foo.java").is_file());
+        assert!(!work_dir.join("escape.java").exists());
+        assert_eq!(std::fs::read_to_string(targets[2].join("src/app.js")).unwrap(), "eval(x)\n");
+
+        let located = extracted.locate(&targets[0].join("com/example/Foo.java")).unwrap();
+        assert_eq!(located, PathBuf::from(format!("{}!com/example/Foo.java", jar.display())));
+        assert_eq!(extracted.locate(&targets[2].join("src/app.js")).unwrap(), PathBuf::from(format!("{}!src/app.js", tgz.display())));
+        assert_eq!(extracted.locate(&plain), None);
+        assert_eq!(extracted.restore_targets(&targets), vec![jar, plain, tgz]);
+
+        drop(extracted);
+        assert!(!work_dir.exists());
+    }
+
+    #[tokio::test]
+    async fn test_scratch_directory_is_removed_after_a_failing_run() {
+        use crate::commands::analyze_enhanced::{analyze_extracted, ExtractedTargets};
+
+        let dir = tempfile::tempdir().unwrap();
+        let zip = dir.path().join("app.zip");
+        write_zip(&zip, &[("app.py", "eval(x)\n")]);
+        let rules = dir.path().join("rules.yaml");
+        std::fs::write(&rules, "rules:\n  - id: py-eval\n    message: eval\n    severity: ERROR\n    languages: [python]\n    pattern: eval($X)\n").unwrap();
+        let mut config = crate::EnhancedAnalysisConfig {
+            target_paths: vec![zip],
+            rule_files: vec![rules],
+            languages: crate::DEFAULT_LANGUAGES.to_vec(),
+            fail_on_findings: true,
+            ..crate::EnhancedAnalysisConfig::default()
+        };
+
+        let extracted = ExtractedTargets::extract(&mut config).unwrap();
+        let work_dir = config.target_paths[0].parent().unwrap().to_path_buf();
+        // The exit code is returned rather than exiting with the scratch directory in place
+        let exit_code = analyze_extracted(config, Some(dir.path().join("report.json")), &extracted).await.unwrap();
+        assert_eq!(exit_code, 1);
+        drop(extracted);
+        assert!(!work_dir.exists());
+    }
+
+    #[test]
+    fn test_recognizes_archives_by_extension() {
+        assert_eq!(archive_kind(Path::new("lib/App.JAR")), Some(ArchiveKind::Zip));
+        assert_eq!(archive_kind(Path::new("release.tgz")), Some(ArchiveKind::Tar));
+        assert_eq!(archive_kind(Path::new("release.tar.gz")), Some(ArchiveKind::Tar));
+        assert_eq!(archive_kind(Path::new("notes.gz")), None);
+        assert!(!is_archive(Path::new("/nonexistent/app.zip")));
+    }
+}
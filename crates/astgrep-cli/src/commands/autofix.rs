@@ -0,0 +1,317 @@
+//! In-place autofixes
+//!
+//! `analyze --fix` rewrites the matched span of every finding whose rule carries a fix,
+//! after backing the file up next to itself; `--fix-dry-run` prints the edits as unified
+//! diffs instead. Fix templates referring to metavariables are rendered by re-running
+//! the finding's rule on its file, so the bindings of the match at the finding's location
+//! are substituted. Fixes touching the same lines cannot both be applied: the conflict
+//! strategy picks one, and every fix left out is recorded with its reason.
+
+use anyhow::Result;
+use astgrep_parser::LanguageParserRegistry;
+use astgrep_rules::{RuleContext, RuleEngine, RuleRepository};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+use crate::commands::analyze_enhanced::{determine_language, Finding};
+use crate::commands::patches::{apply_edits, line_edit, patch_path, unified_diff, LineEdit};
+
+/// Suffix appended to the name of a file before it is rewritten
+pub const BACKUP_SUFFIX: &str = "backup";
+
+/// Which of two fixes touching the same lines is applied
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConflictStrategy {
+    /// Keep the fix that comes first in the file
+    #[default]
+    SkipLater,
+    /// Keep the fix of the more severe finding; ties keep the first
+    PreferSeverity,
+    /// Ask on the terminal
+    Prompt,
+}
+
+/// A fix that was not applied
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SkippedFix {
+    pub rule_id: String,
+    pub file: PathBuf,
+    pub line: usize,
+    pub reason: String,
+}
+
+impl SkippedFix {
+    fn new(finding: &Finding, reason: impl Into<String>) -> Self {
+        Self {
+            rule_id: finding.rule_id.clone(),
+            file: finding.location.file.clone(),
+            line: finding.location.start_line,
+            reason: reason.into(),
+        }
+    }
+}
+
+/// Outcome of applying (or previewing) fixes
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct FixSummary {
+    /// Fixes applied, or that would be applied in a dry run
+    pub applied: usize,
+    /// Fixes that could not be rendered, or lost a conflict
+    pub skipped: Vec<SkippedFix>,
+    /// Files changed, with their backups (none in a dry run)
+    pub files: Vec<(PathBuf, Option<PathBuf>)>,
+}
+
+impl FixSummary {
+    pub fn describe(&self, dry_run: bool) -> String {
+        let verb = if dry_run { "would apply" } else { "applied" };
+        let mut text = format!("Autofix: {} {} fix(es) in {} file(s)", verb, self.applied, self.files.len());
+        if !self.skipped.is_empty() {
+            text.push_str(&format!(", skipped {}", self.skipped.len()));
+        }
+        for (file, backup) in &self.files {
+            match backup {
+                Some(backup) => text.push_str(&format!("\n  {} (backup: {})", file.display(), backup.display())),
+                None => text.push_str(&format!("\n  {}", file.display())),
+            }
+        }
+        for skipped in &self.skipped {
+            text.push_str(&format!("\n  skipped {} at {}:{}: {}", skipped.rule_id, skipped.file.display(), skipped.line, skipped.reason));
+        }
+        text
+    }
+}
+
+/// Apply the fixes of `findings` in place, or print them as diffs when `dry_run`
+pub fn apply_fixes(findings: &[Finding], rule_files: &[PathBuf], dry_run: bool, strategy: ConflictStrategy) -> Result<FixSummary> {
+    let repository = RuleRepository::from_paths(rule_files);
+    let mut by_file: BTreeMap<PathBuf, Vec<&Finding>> = BTreeMap::new();
+    for finding in findings.iter().filter(|f| f.fix.is_some()) {
+        by_file.entry(finding.location.file.clone()).or_default().push(finding);
+    }
+
+    let mut summary = FixSummary::default();
+    for (file, file_findings) in by_file {
+        let source = match std::fs::read_to_string(&file) {
+            Ok(source) => source,
+            Err(e) => {
+                warn!("Skipping fixes for {}: {}", file.display(), e);
+                summary.skipped.extend(file_findings.iter().map(|f| SkippedFix::new(f, format!("cannot read the file: {}", e))));
+                continue;
+            }
+        };
+
+        let mut rendered: Vec<Finding> = Vec::new();
+        for finding in file_findings {
+            match render_fix(finding, &file, &source, &repository) {
+                Some(fix) => rendered.push(Finding { fix: Some(fix), ..finding.clone() }),
+                None => summary.skipped.push(SkippedFix::new(finding, "the fix's metavariables could not be substituted")),
+            }
+        }
+        let mut edits = Vec::new();
+        for finding in &rendered {
+            match line_edit(finding, &source) {
+                Some(edit) => edits.push((finding, edit)),
+                None => summary.skipped.push(SkippedFix::new(finding, "the location does not fit the file")),
+            }
+        }
+        let (edits, conflicts) = resolve_conflicts(edits, strategy, &mut prompt_choice);
+        summary.skipped.extend(conflicts);
+        if edits.is_empty() {
+            continue;
+        }
+
+        let display_path = patch_path(&file);
+        if dry_run {
+            print!("{}", unified_diff(&display_path, &source, &edits));
+            summary.files.push((file, None));
+        } else {
+            let backup = backup_path(&file);
+            std::fs::copy(&file, &backup)?;
+            std::fs::write(&file, apply_edits(&source, &edits))?;
+            info!("Applied {} fix(es) to {}", edits.len(), display_path);
+            summary.files.push((file, Some(backup)));
+        }
+        summary.applied += edits.len();
+    }
+    Ok(summary)
+}
+
+/// Pick non-overlapping edits by `strategy`, returning them sorted by position together
+/// with the fixes left out. `prompt(kept, candidate)` answers whether the candidate
+/// replaces the kept fix it conflicts with.
+fn resolve_conflicts(
+    mut edits: Vec<(&Finding, LineEdit)>,
+    strategy: ConflictStrategy,
+    prompt: &mut dyn FnMut(&Finding, &Finding) -> bool,
+) -> (Vec<LineEdit>, Vec<SkippedFix>) {
+    edits.sort_by_key(|(f, e)| (e.start_line, f.location.start_column));
+    if strategy == ConflictStrategy::PreferSeverity {
+        // Stable, so equally severe fixes keep their position order
+        edits.sort_by_key(|(f, _)| std::cmp::Reverse(f.severity));
+    }
+
+    let overlaps = |a: &LineEdit, b: &LineEdit| a.start_line <= b.end_line && b.start_line <= a.end_line;
+    let mut kept: Vec<(&Finding, LineEdit)> = Vec::new();
+    let mut skipped = Vec::new();
+    for (finding, edit) in edits {
+        let Some(conflict) = kept.iter().position(|(_, k)| overlaps(k, &edit)) else {
+            kept.push((finding, edit));
+            continue;
+        };
+        let winner = kept[conflict].0;
+        let replace = strategy == ConflictStrategy::Prompt
+            && !kept.iter().enumerate().any(|(i, (_, k))| i != conflict && overlaps(k, &edit))
+            && prompt(winner, finding);
+        if replace {
+            skipped.push(SkippedFix::new(winner, format!("overlaps the fix of {} chosen instead", finding.rule_id)));
+            kept[conflict] = (finding, edit);
+        } else {
+            warn!("Skipping overlapping fix for {} at {}:{}", finding.rule_id, finding.location.file.display(), finding.location.start_line);
+            skipped.push(SkippedFix::new(
+                finding,
+                format!("overlaps the fix of {} at line {}", winner.rule_id, winner.location.start_line),
+            ));
+        }
+    }
+
+    kept.sort_by_key(|(f, e)| (e.start_line, f.location.start_column));
+    (kept.into_iter().map(|(_, e)| e).collect(), skipped)
+}
+
+/// Ask on the terminal which of two conflicting fixes to apply; anything but `2` keeps
+/// the first
+fn prompt_choice(kept: &Finding, candidate: &Finding) -> bool {
+    let describe = |f: &Finding| format!("{} ({}) at line {}: {}", f.rule_id, f.severity.as_str(), f.location.start_line, f.fix.as_deref().unwrap_or_default());
+    eprintln!("Conflicting fixes in {}:", kept.location.file.display());
+    eprintln!("  [1] {}", describe(kept));
+    eprintln!("  [2] {}", describe(candidate));
+    eprint!("Apply which fix? [1/2] (default 1): ");
+    let _ = std::io::stderr().flush();
+
+    let mut answer = String::new();
+    std::io::stdin().lock().read_line(&mut answer).is_ok() && answer.trim() == "2"
+}
+
+/// `app.js` is backed up as `app.js.backup`
+pub fn backup_path(file: &Path) -> PathBuf {
+    let mut name = file.file_name().map(|n| n.to_os_string()).unwrap_or_default();
+    name.push(".");
+    name.push(BACKUP_SUFFIX);
+    file.with_file_name(name)
+}
+
+/// The finding's fix with the metavariables of its match substituted; a fix without
+/// metavariables is used as is
+pub(crate) fn render_fix(finding: &Finding, file: &Path, source: &str, repository: &RuleRepository) -> Option<String> {
+    let fix = finding.fix.as_ref()?;
+    let metavariable = Regex::new(r"\$[A-Z_][A-Z0-9_]*").ok()?;
+    if !metavariable.is_match(fix) {
+        return Some(fix.clone());
+    }
+
+    let rule = repository.rule(&finding.rule_id)?;
+    let language = determine_language(file).ok()?;
+    let parser_registry = LanguageParserRegistry::new();
+    let ast = parser_registry.get_parser(language)?.parse(source, file).ok()?;
+    let mut engine = RuleEngine::new();
+    engine.add_rule(rule.clone()).ok()?;
+    let context = RuleContext::new(file.to_string_lossy().to_string(), language, source.to_string());
+
+    // Rendered fixes of the rule's matches, by start position
+    let matches: HashMap<(usize, usize), String> = engine
+        .analyze(ast.as_ref(), &context)
+        .ok()?
+        .into_iter()
+        .filter_map(|f| f.fix_suggestion.map(|fix| ((f.location.start_line, f.location.start_column), fix)))
+        .collect();
+    matches
+        .get(&(finding.location.start_line, finding.location.start_column))
+        .filter(|fix| !metavariable.is_match(fix))
+        .cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::analyze_enhanced::{test_finding, Location};
+    use astgrep_core::Severity;
+
+    fn finding(file: &Path, line: usize, start: usize, end: usize, fix: &str) -> Finding {
+        Finding {
+            severity: Severity::Warning,
+            location: Location::new(file, line, start, line, end),
+            fix: Some(fix.to_string()),
+            ..test_finding("r", file, line)
+        }
+    }
+
+    #[test]
+    fn test_apply_fixes_with_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("app.js");
+        let source = "let a = md5(x);\nlet b = md5(y);\n";
+        std::fs::write(&file, source).unwrap();
+        let findings = vec![
+            finding(&file, 1, 9, 15, "sha256(x)"),
+            finding(&file, 2, 9, 15, "sha256(y)"),
+            finding(&file, 2, 9, 12, "sha1"),
+        ];
+
+        let preview = apply_fixes(&findings, &[], true, ConflictStrategy::SkipLater).unwrap();
+        assert_eq!((preview.applied, preview.skipped.len()), (2, 1));
+        assert_eq!(preview.skipped[0].reason, "overlaps the fix of r at line 2");
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), source);
+
+        let summary = apply_fixes(&findings, &[], false, ConflictStrategy::SkipLater).unwrap();
+        assert_eq!(summary.applied, 2);
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "let a = sha256(x);\nlet b = sha256(y);\n");
+        assert_eq!(summary.files, vec![(file.clone(), Some(dir.path().join("app.js.backup")))]);
+        assert_eq!(std::fs::read_to_string(dir.path().join("app.js.backup")).unwrap(), source);
+        assert!(summary.describe(false).starts_with("Autofix: applied 2 fix(es) in 1 file(s), skipped 1"));
+    }
+
+    #[test]
+    fn test_unresolved_metavariables_are_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("app.js");
+        std::fs::write(&file, "eval(input);\n").unwrap();
+
+        let summary = apply_fixes(&[finding(&file, 1, 1, 12, "safeEval($X)")], &[], false, ConflictStrategy::SkipLater).unwrap();
+        assert_eq!(summary.applied, 0);
+        assert_eq!(summary.skipped[0].reason, "the fix's metavariables could not be substituted");
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "eval(input);\n");
+        assert!(!backup_path(&file).exists());
+    }
+
+    #[test]
+    fn test_conflict_strategies() {
+        let file = PathBuf::from("app.js");
+        let source = "let a = md5(x);\n";
+        let mut weak = finding(&file, 1, 9, 15, "sha256(x)");
+        weak.rule_id = "weak-hash".to_string();
+        let mut critical = finding(&file, 1, 9, 12, "hmac");
+        critical.rule_id = "no-md5".to_string();
+        critical.severity = Severity::Critical;
+        let edits = || vec![(&weak, line_edit(&weak, source).unwrap()), (&critical, line_edit(&critical, source).unwrap())];
+        let applied = |edits: &[LineEdit]| apply_edits(source, edits);
+
+        let (kept, skipped) = resolve_conflicts(edits(), ConflictStrategy::SkipLater, &mut |_, _| unreachable!());
+        assert_eq!(applied(&kept), "let a = sha256(x);\n");
+        assert_eq!(skipped[0].rule_id, "no-md5");
+
+        let (kept, skipped) = resolve_conflicts(edits(), ConflictStrategy::PreferSeverity, &mut |_, _| unreachable!());
+        assert_eq!(applied(&kept), "let a = hmac(x);\n");
+        assert_eq!(skipped[0].reason, "overlaps the fix of no-md5 at line 1");
+
+        let (kept, skipped) = resolve_conflicts(edits(), ConflictStrategy::Prompt, &mut |kept, candidate| {
+            assert_eq!((kept.rule_id.as_str(), candidate.rule_id.as_str()), ("weak-hash", "no-md5"));
+            true
+        });
+        assert_eq!(applied(&kept), "let a = hmac(x);\n");
+        assert_eq!(skipped[0].reason, "overlaps the fix of no-md5 chosen instead");
+    }
+}
@@ -0,0 +1,274 @@
+//! Finding baselines
+//!
+//! `analyze --baseline-out <file>` records the findings of a run; `analyze --baseline
+//! <file>` then reports only findings that are not in it, so a project can adopt a rule
+//! set without fixing every existing issue first. Findings are matched by the fingerprint
+//! reports show for them (see [`crate::commands::fingerprint`]) rather than by line
+//! number, so edits elsewhere in a file do not turn old findings into new ones.
+//!
+//! `--baseline` also takes a SARIF report, from an earlier astgrep run or another tool, so
+//! "new issues only" gating needs no baseline run first. A result carrying an astgrep
+//! fingerprint matches the finding with that fingerprint; any other result matches a
+//! finding of the same rule id on the same file and start line.
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+use serde_json::Value;
+use crate::commands::analyze_enhanced::{fingerprints, Finding, SARIF_FINGERPRINT_KEY};
+
+/// Version of the baseline file format; 2 uses the fingerprints reports show
+pub const BASELINE_VERSION: u32 = 2;
+
+/// A finding recorded in a baseline
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BaselineEntry {
+    pub fingerprint: String,
+    pub rule_id: String,
+    pub file: String,
+    /// Line of the finding when it was recorded, for people reading the file
+    pub line: usize,
+}
+
+/// The findings of an earlier run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Baseline {
+    pub version: u32,
+    pub findings: Vec<BaselineEntry>,
+    /// Results of a SARIF report read as the baseline
+    #[serde(skip)]
+    sarif_results: Vec<SarifResult>,
+}
+
+/// A result of a SARIF report used as a baseline
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SarifResult {
+    /// `astgrepFingerprint/v1` partial fingerprint, in reports written by astgrep
+    fingerprint: Option<String>,
+    rule_id: String,
+    file: String,
+    line: usize,
+}
+
+impl Baseline {
+    /// Baseline of `findings`, ordered by file and line
+    pub fn from_findings(findings: &[Finding]) -> Self {
+        let mut entries: Vec<BaselineEntry> = findings
+            .iter()
+            .zip(fingerprints(findings))
+            .map(|(finding, fingerprint)| BaselineEntry {
+                fingerprint,
+                rule_id: finding.rule_id.clone(),
+                file: display_path(&finding.location.file),
+                line: finding.location.start_line,
+            })
+            .collect();
+        entries.sort_by(|a, b| (&a.file, a.line, &a.rule_id).cmp(&(&b.file, b.line, &b.rule_id)));
+        Self { version: BASELINE_VERSION, findings: entries, sarif_results: Vec::new() }
+    }
+
+    /// Read a baseline file or a SARIF report
+    pub fn read(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path).map_err(|e| anyhow!("Cannot read baseline {}: {}", path.display(), e))?;
+        let document: Value = serde_json::from_str(&text).map_err(|e| anyhow!("{} is not a baseline file: {}", path.display(), e))?;
+        if document.get("runs").is_some() {
+            return Ok(Self { version: BASELINE_VERSION, findings: Vec::new(), sarif_results: sarif_results(&document) });
+        }
+        let baseline: Self = serde_json::from_value(document).map_err(|e| anyhow!("{} is not a baseline file: {}", path.display(), e))?;
+        if baseline.version != BASELINE_VERSION {
+            return Err(anyhow!(
+                "unsupported baseline version {} in {} (this astgrep reads {}); write it again with --baseline-out",
+                baseline.version,
+                path.display(),
+                BASELINE_VERSION
+            ));
+        }
+        Ok(baseline)
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// The findings not in the baseline, and how many were
+    pub fn new_findings(&self, findings: Vec<Finding>) -> (Vec<Finding>, usize) {
+        let known: BTreeSet<&str> = self.findings.iter().map(|entry| entry.fingerprint.as_str()).collect();
+        let known_results: BTreeSet<&str> = self.sarif_results.iter().filter_map(|result| result.fingerprint.as_deref()).collect();
+        let mut results_at: HashMap<(&str, usize), Vec<&SarifResult>> = HashMap::new();
+        for result in self.sarif_results.iter().filter(|result| result.fingerprint.is_none()) {
+            results_at.entry((result.rule_id.as_str(), result.line)).or_default().push(result);
+        }
+        let prints = fingerprints(&findings);
+        let total = findings.len();
+        let new: Vec<Finding> = findings
+            .into_iter()
+            .zip(prints)
+            .filter(|(finding, fingerprint)| {
+                !known.contains(fingerprint.as_str())
+                    && !known_results.contains(fingerprint.as_str())
+                    && !results_at
+                        .get(&(finding.rule_id.as_str(), finding.location.start_line))
+                        .is_some_and(|results| results.iter().any(|result| result.is_at(&finding.location.file)))
+            })
+            .map(|(finding, _)| finding)
+            .collect();
+        let matched = total - new.len();
+        (new, matched)
+    }
+}
+
+impl SarifResult {
+    /// Whether this result is in `file`. Paths match when one is the other or ends with
+    /// it, as report paths may be relative to another directory.
+    fn is_at(&self, file: &Path) -> bool {
+        let file = display_path(file);
+        let (longer, shorter) = if file.len() >= self.file.len() { (&file, &self.file) } else { (&self.file, &file) };
+        longer == shorter || longer.ends_with(&format!("/{}", shorter))
+    }
+}
+
+/// The results of every run of a SARIF report
+fn sarif_results(sarif: &Value) -> Vec<SarifResult> {
+    let results = sarif["runs"].as_array().into_iter().flatten().flat_map(|run| run["results"].as_array().into_iter().flatten());
+    results
+        // Results the report marks as gone are not known issues
+        .filter(|result| result["baselineState"].as_str() != Some("absent"))
+        .map(|result| {
+            let location = &result["locations"][0]["physicalLocation"];
+            let uri = location["artifactLocation"]["uri"].as_str().unwrap_or_default();
+            SarifResult {
+                fingerprint: result["partialFingerprints"][SARIF_FINGERPRINT_KEY].as_str().map(str::to_string),
+                rule_id: result["ruleId"].as_str().unwrap_or_default().to_string(),
+                file: display_path(Path::new(&uri_path(uri))),
+                line: location["region"]["startLine"].as_u64().unwrap_or(1) as usize,
+            }
+        })
+        .collect()
+}
+
+/// File path of a SARIF artifact URI: a relative reference or a `file:` URI, with
+/// percent-escapes decoded
+fn uri_path(uri: &str) -> String {
+    let mut decoded = Vec::with_capacity(uri.len());
+    let mut rest = uri.strip_prefix("file://").unwrap_or(uri).as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        let escaped = match tail {
+            [high, low, ..] if byte == b'%' => (*high as char).to_digit(16).zip((*low as char).to_digit(16)),
+            _ => None,
+        };
+        match escaped {
+            Some((high, low)) => {
+                decoded.push((high * 16 + low) as u8);
+                rest = &tail[2..];
+            }
+            None => {
+                decoded.push(byte);
+                rest = tail;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+pub(crate) fn display_path(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    path.strip_prefix("./").map(str::to_string).unwrap_or(path)
+}
+
+pub(crate) fn fnv1a(key: &str) -> String {
+    let hash = key.bytes().fold(0xcbf29ce484222325u64, |h, b| (h ^ b as u64).wrapping_mul(0x100000001b3));
+    format!("{:016x}", hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::analyze_enhanced::test_finding;
+
+    fn finding(file: &Path, rule_id: &str, line: usize) -> Finding {
+        test_finding(rule_id, file, line)
+    }
+
+    #[test]
+    fn test_baseline_survives_line_shifts() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("app.js");
+        std::fs::write(&file, "eval(a);\nlet x = 1;\neval(b);\neval(a);\n").unwrap();
+        let old = vec![finding(&file, "js-eval", 1), finding(&file, "js-eval", 3), finding(&file, "js-eval", 4)];
+        let baseline_path = dir.path().join("baseline.json");
+        Baseline::from_findings(&old).write(&baseline_path).unwrap();
+        let baseline = Baseline::read(&baseline_path).unwrap();
+        assert_eq!(baseline.findings.len(), 3);
+
+        // Code added above the old findings, plus one more eval(b) at the end
+        std::fs::write(&file, "import x;\n\neval(a);\nlet x = 1;\neval(b);\neval(a);\neval(b);\n").unwrap();
+        let current = vec![
+            finding(&file, "js-eval", 3),
+            finding(&file, "js-eval", 5),
+            finding(&file, "js-eval", 6),
+            finding(&file, "js-eval", 7),
+            finding(&file, "js-other", 3),
+        ];
+        let (new, matched) = baseline.new_findings(current);
+        assert_eq!(matched, 3);
+        let new: Vec<(&str, usize)> = new.iter().map(|f| (f.rule_id.as_str(), f.location.start_line)).collect();
+        assert_eq!(new, vec![("js-eval", 7), ("js-other", 3)]);
+    }
+
+    #[test]
+    fn test_sarif_report_as_baseline() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("src/app.js");
+        let sarif = serde_json::json!({
+            "version": "2.1.0",
+            "runs": [{
+                "tool": { "driver": { "name": "other" } },
+                "results": [
+                    { "ruleId": "js-eval", "partialFingerprints": { SARIF_FINGERPRINT_KEY: "00000000000000aa" },
+                      "locations": [{ "physicalLocation": { "artifactLocation": { "uri": "elsewhere.js" }, "region": { "startLine": 40 } } }] },
+                    { "ruleId": "js-exec",
+                      "locations": [{ "physicalLocation": { "artifactLocation": { "uri": "src/app%2Ejs" }, "region": { "startLine": 3 } } }] },
+                    { "ruleId": "js-exec", "baselineState": "absent",
+                      "locations": [{ "physicalLocation": { "artifactLocation": { "uri": "src/app.js" }, "region": { "startLine": 9 } } }] }
+                ]
+            }]
+        });
+        let path = dir.path().join("previous.sarif");
+        std::fs::write(&path, sarif.to_string()).unwrap();
+        let baseline = Baseline::read(&path).unwrap();
+
+        let fingerprinted = Finding { fingerprint: Some("00000000000000aa".to_string()), ..finding(&file, "js-eval", 1) };
+        let current = vec![
+            fingerprinted,
+            finding(&file, "js-exec", 3),
+            finding(&file, "js-exec", 4),
+            finding(&file, "js-eval", 3),
+            finding(&file, "js-exec", 9),
+        ];
+        let (new, matched) = baseline.new_findings(current);
+        assert_eq!(matched, 2);
+        let new: Vec<(&str, usize)> = new.iter().map(|f| (f.rule_id.as_str(), f.location.start_line)).collect();
+        assert_eq!(new, vec![("js-exec", 4), ("js-eval", 3), ("js-exec", 9)]);
+
+        assert_eq!(uri_path("file:///home/me/my%20app/a.js"), "/home/me/my app/a.js");
+        assert_eq!(uri_path("100%"), "100%");
+    }
+
+    #[test]
+    fn test_reject_other_versions() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("baseline.json");
+        std::fs::write(&path, "{\"version\": 3, \"findings\": []}").unwrap();
+        assert!(Baseline::read(&path).unwrap_err().to_string().contains("unsupported baseline version 3"));
+        // Baselines of the old line-hash fingerprints are rejected rather than matching nothing
+        std::fs::write(&path, "{\"version\": 1, \"findings\": []}").unwrap();
+        assert!(Baseline::read(&path).unwrap_err().to_string().contains("--baseline-out"));
+        std::fs::write(&path, "{\"findings\": []}").unwrap();
+        assert!(Baseline::read(&path).is_err());
+    }
+}
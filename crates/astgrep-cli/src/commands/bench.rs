@@ -0,0 +1,278 @@
+//! Rule benchmark command
+//!
+//! `bench` runs every loaded rule on its own against a target corpus a number of times and
+//! reports, per rule, its wall time, match count and slowest files, so rule authors can
+//! tell which rule makes a scan slow. Files are parsed once up front and only rule
+//! execution is timed. Rules whose mean time over the corpus exceeds `--budget-ms` are
+//! flagged, and the command then fails.
+
+use anyhow::{anyhow, Result};
+use astgrep_core::{AstNode, Language};
+use astgrep_parser::LanguageParserRegistry;
+use astgrep_rules::{RuleContext, RuleEngine};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+use crate::commands::analyze_enhanced::{collect_target_files, determine_language, load_rules_into_engine_from_paths};
+use crate::{EnhancedAnalysisConfig, OutputFormatCli};
+
+/// A corpus file, parsed once for all rules
+struct CorpusFile {
+    path: PathBuf,
+    language: Language,
+    source: String,
+    ast: Box<dyn AstNode>,
+}
+
+/// Time one rule spent on one file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileTiming {
+    pub file: String,
+    /// Mean time per iteration
+    pub mean_ms: f64,
+    pub matches: usize,
+}
+
+/// Benchmark results of one rule
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RuleBench {
+    pub rule_id: String,
+    /// Corpus files in one of the rule's languages
+    pub files: usize,
+    pub matches: usize,
+    /// Mean time of one pass over the corpus
+    pub mean_ms: f64,
+    /// Fastest and slowest pass over the corpus
+    pub min_ms: f64,
+    pub max_ms: f64,
+    /// Files the rule spent the most time on, slowest first
+    pub slowest_files: Vec<FileTiming>,
+    pub errors: usize,
+    pub over_budget: bool,
+}
+
+/// Complete rule benchmark report, slowest rule first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleBenchReport {
+    pub iterations: usize,
+    pub files: usize,
+    pub budget_ms: Option<f64>,
+    pub rules: Vec<RuleBench>,
+}
+
+impl RuleBenchReport {
+    pub fn over_budget(&self) -> impl Iterator<Item = &RuleBench> {
+        self.rules.iter().filter(|rule| rule.over_budget)
+    }
+}
+
+/// Benchmark each rule of `rules` against the files under `targets`
+pub async fn run(
+    targets: Vec<PathBuf>,
+    rules: Vec<PathBuf>,
+    iterations: usize,
+    budget_ms: Option<f64>,
+    slowest: usize,
+    format: OutputFormatCli,
+) -> Result<()> {
+    if iterations == 0 {
+        return Err(anyhow!("--iterations must be at least 1"));
+    }
+    let mut engine = RuleEngine::new();
+    load_rules_into_engine_from_paths(&rules, &mut engine)?;
+    if engine.rule_count() == 0 {
+        return Err(anyhow!("No rules loaded"));
+    }
+
+    let mut languages: Vec<Language> = engine.rules().iter().flat_map(|rule| rule.languages.iter().copied()).collect();
+    languages.sort_by_key(|language| language.as_str());
+    languages.dedup();
+    let config = EnhancedAnalysisConfig {
+        target_paths: if targets.is_empty() { vec![PathBuf::from(".")] } else { targets },
+        languages,
+        ..Default::default()
+    };
+    let mut skipped = Vec::new();
+    let files = collect_target_files(&config, &mut skipped).await?;
+    if !skipped.is_empty() {
+        info!("Skipped {} binary or oversized file(s)", skipped.len());
+    }
+    let corpus = parse_corpus(files, &config);
+    if corpus.is_empty() {
+        return Err(anyhow!("No target files in the rules' languages"));
+    }
+
+    info!("Benchmarking {} rule(s) on {} file(s), {} iteration(s)", engine.rule_count(), corpus.len(), iterations);
+    let report = benchmark(&mut engine, &corpus, iterations, budget_ms, slowest);
+
+    let output = match format {
+        OutputFormatCli::Json => serde_json::to_string_pretty(&report)?,
+        _ => format_report(&report),
+    };
+    println!("{}", output);
+
+    let over_budget = report.over_budget().count();
+    if over_budget > 0 {
+        return Err(anyhow!("{} rule(s) exceeded the {:.1}ms budget", over_budget, budget_ms.unwrap_or_default()));
+    }
+    Ok(())
+}
+
+/// Parse the corpus files, leaving out files that fail to parse
+fn parse_corpus(files: Vec<PathBuf>, config: &EnhancedAnalysisConfig) -> Vec<CorpusFile> {
+    let registry = LanguageParserRegistry::new();
+    let mut corpus = Vec::new();
+    for path in files {
+        let language = match config.language_override(&path).map(Ok).unwrap_or_else(|| determine_language(&path)) {
+            Ok(language) => language,
+            Err(_) => continue,
+        };
+        let parser = match registry.get_parser(language) {
+            Some(parser) => parser,
+            None => continue,
+        };
+        let parsed = std::fs::read_to_string(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|source| Ok((parser.parse(&source, &path)?, source)));
+        match parsed {
+            Ok((ast, source)) => corpus.push(CorpusFile { path, language, source, ast }),
+            Err(e) => warn!("Leaving {} out of the benchmark: {}", path.display(), e),
+        }
+    }
+    corpus
+}
+
+/// Run each rule of `engine` alone, `iterations` times per file
+fn benchmark(engine: &mut RuleEngine, corpus: &[CorpusFile], iterations: usize, budget_ms: Option<f64>, slowest: usize) -> RuleBenchReport {
+    let rules: Vec<(String, Vec<Language>)> = engine.rules().iter().map(|rule| (rule.id.clone(), rule.languages.clone())).collect();
+    let contexts: Vec<RuleContext> = corpus
+        .iter()
+        .map(|file| RuleContext::new(file.path.to_string_lossy().to_string(), file.language, file.source.clone()))
+        .collect();
+
+    let mut results = Vec::with_capacity(rules.len());
+    for (rule_id, languages) in rules {
+        let mut passes = vec![Duration::ZERO; iterations];
+        let mut timings = Vec::new();
+        let mut errors = 0;
+        for (file, context) in corpus.iter().zip(&contexts) {
+            if !languages.contains(&file.language) {
+                continue;
+            }
+            let mut total = Duration::ZERO;
+            let mut matches = 0;
+            for pass in passes.iter_mut() {
+                let start = Instant::now();
+                let result = engine.execute_rule(&rule_id, file.ast.as_ref(), context);
+                let elapsed = start.elapsed();
+                *pass += elapsed;
+                total += elapsed;
+                match result {
+                    Ok(Some(result)) if result.is_success() => matches = result.findings.len(),
+                    Ok(None) => {}
+                    _ => errors += 1,
+                }
+            }
+            timings.push(FileTiming {
+                file: file.path.display().to_string(),
+                mean_ms: total.as_secs_f64() * 1000.0 / iterations as f64,
+                matches,
+            });
+        }
+        results.push(summarize(rule_id, &passes, timings, errors, budget_ms, slowest));
+    }
+
+    results.sort_by(|a, b| b.mean_ms.total_cmp(&a.mean_ms));
+    RuleBenchReport { iterations, files: corpus.len(), budget_ms, rules: results }
+}
+
+/// Results of one rule from the duration of each pass over the corpus and its per-file
+/// timings
+fn summarize(
+    rule_id: String,
+    passes: &[Duration],
+    mut timings: Vec<FileTiming>,
+    errors: usize,
+    budget_ms: Option<f64>,
+    slowest: usize,
+) -> RuleBench {
+    let passes: Vec<f64> = passes.iter().map(|pass| pass.as_secs_f64() * 1000.0).collect();
+    let mean_ms = passes.iter().sum::<f64>() / passes.len().max(1) as f64;
+    let files = timings.len();
+    let matches = timings.iter().map(|timing| timing.matches).sum();
+    timings.sort_by(|a, b| b.mean_ms.total_cmp(&a.mean_ms));
+    timings.truncate(slowest);
+
+    RuleBench {
+        rule_id,
+        files,
+        matches,
+        mean_ms,
+        min_ms: passes.iter().copied().fold(f64::INFINITY, f64::min),
+        max_ms: passes.iter().copied().fold(0.0, f64::max),
+        slowest_files: timings,
+        errors,
+        over_budget: budget_ms.is_some_and(|budget| mean_ms > budget),
+    }
+}
+
+fn format_report(report: &RuleBenchReport) -> String {
+    let mut output = format!(
+        "⏱️  Benchmarked {} rule(s) on {} file(s), {} iteration(s) each\n",
+        report.rules.len(),
+        report.files,
+        report.iterations
+    );
+    if let Some(budget) = report.budget_ms {
+        output.push_str(&format!("   Budget: {:.1}ms per rule per pass\n", budget));
+    }
+
+    for rule in &report.rules {
+        let flag = if rule.over_budget { "  ⚠️ over budget" } else { "" };
+        output.push_str(&format!(
+            "\n  {}  {:.3}ms (min {:.3}, max {:.3})  {} match(es) in {} file(s){}\n",
+            rule.rule_id, rule.mean_ms, rule.min_ms, rule.max_ms, rule.matches, rule.files, flag
+        ));
+        if rule.errors > 0 {
+            output.push_str(&format!("    {} failed execution(s)\n", rule.errors));
+        }
+        for timing in &rule.slowest_files {
+            output.push_str(&format!("    {:.3}ms  {} ({} match(es))\n", timing.mean_ms, timing.file, timing.matches));
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timing(file: &str, mean_ms: f64, matches: usize) -> FileTiming {
+        FileTiming { file: file.to_string(), mean_ms, matches }
+    }
+
+    #[test]
+    fn test_summarize_flags_rules_over_budget() {
+        let passes = [Duration::from_millis(4), Duration::from_millis(6)];
+        let timings = vec![timing("a.py", 1.0, 2), timing("b.py", 3.5, 0), timing("c.py", 0.5, 1)];
+        let bench = summarize("py-eval".to_string(), &passes, timings, 0, Some(4.0), 2);
+
+        assert_eq!((bench.files, bench.matches), (3, 3));
+        assert!((bench.mean_ms - 5.0).abs() < 1e-9);
+        assert!((bench.min_ms - 4.0).abs() < 1e-9 && (bench.max_ms - 6.0).abs() < 1e-9);
+        let slowest: Vec<&str> = bench.slowest_files.iter().map(|t| t.file.as_str()).collect();
+        assert_eq!(slowest, vec!["b.py", "a.py"]);
+        assert!(bench.over_budget);
+
+        let within = summarize("py-exec".to_string(), &passes, Vec::new(), 0, Some(10.0), 2);
+        assert!(!within.over_budget);
+        assert!(!summarize("py-exec".to_string(), &passes, Vec::new(), 0, None, 2).over_budget);
+
+        let report = RuleBenchReport { iterations: 2, files: 3, budget_ms: Some(4.0), rules: vec![bench, within] };
+        let text = format_report(&report);
+        assert!(text.contains("py-eval  5.000ms (min 4.000, max 6.000)  3 match(es) in 3 file(s)  ⚠️ over budget"));
+        assert!(text.contains("    3.500ms  b.py (0 match(es))"));
+        assert_eq!(report.over_budget().count(), 1);
+    }
+}
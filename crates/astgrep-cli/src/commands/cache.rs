@@ -0,0 +1,230 @@
+//! Incremental analysis cache
+//!
+//! `--cache-dir <dir>` keeps the findings of every analyzed file, keyed by a hash of its
+//! path and content, below a directory named after a hash of the rule set: the rules as
+//! loaded (after `--policy`), the engine version and the options that change what a file
+//! yields. Files whose content did not change since a run with the same rule set are
+//! served from the cache instead of being parsed and matched again. With dataflow
+//! enabled, findings can depend on other files, so the path and content of every file
+//! of the run are part of the rule set hash and any change to them invalidates the cache.
+//!
+//! Rule sets no run has added entries to for a week are removed when the cache is opened,
+//! so the directory does not grow with every rule change; workspace roots with rules of
+//! their own each keep a rule set. Files that fail or time out are not cached.
+
+use crate::commands::analyze_enhanced::{rule_repository, Finding};
+use crate::EnhancedAnalysisConfig;
+use anyhow::{anyhow, Result};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, SystemTime};
+use tracing::debug;
+
+/// Age after which the entries of an unused rule set are removed
+const STALE_AFTER: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Findings of one file as stored in the cache
+#[derive(serde::Serialize, serde::Deserialize)]
+struct CacheEntry {
+    rules_executed: usize,
+    findings: Vec<Finding>,
+    /// Indexes of the findings silenced by inline comments, a flag findings do not serialize
+    #[serde(default)]
+    suppressed: Vec<usize>,
+}
+
+/// Per-file findings of earlier runs with the same rule set
+#[derive(Debug)]
+pub struct FindingCache {
+    /// Directory of the current rule set
+    dir: PathBuf,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+impl FindingCache {
+    /// Open the cache below `cache_dir` for the rule set of `config` run over `files`
+    pub fn open(cache_dir: &Path, config: &EnhancedAnalysisConfig, files: &[PathBuf]) -> Result<Self> {
+        Self::open_rule_set(cache_dir, &rule_set_hash(config, files)?)
+    }
+
+    fn open_rule_set(cache_dir: &Path, rule_set: &str) -> Result<Self> {
+        let dir = cache_dir.join(rule_set);
+        std::fs::create_dir_all(&dir).map_err(|e| anyhow!("Cannot create cache directory {}: {}", dir.display(), e))?;
+        let now = SystemTime::now();
+        for entry in std::fs::read_dir(cache_dir)?.flatten() {
+            let modified = entry.metadata().and_then(|m| m.modified()).unwrap_or(now);
+            let stale = now.duration_since(modified).is_ok_and(|age| age > STALE_AFTER);
+            if entry.path() != dir && entry.path().is_dir() && stale {
+                debug!("Removing cache entries of rule set {}", entry.file_name().to_string_lossy());
+                let _ = std::fs::remove_dir_all(entry.path());
+            }
+        }
+        Ok(Self { dir, hits: AtomicUsize::new(0), misses: AtomicUsize::new(0) })
+    }
+
+    /// Cache key of `file` as it is now; none when it cannot be read as text
+    pub fn key(&self, file: &Path) -> Option<String> {
+        let content = std::fs::read_to_string(file).ok()?;
+        let mut hasher = Sha256::new();
+        hasher.update(file.display().to_string());
+        hasher.update([0]);
+        hasher.update(content);
+        Some(hex(hasher))
+    }
+
+    /// Findings and number of rules run stored under `key`
+    pub fn get(&self, key: &str) -> Option<(Vec<Finding>, usize)> {
+        let entry = std::fs::read_to_string(self.entry_path(key))
+            .ok()
+            .and_then(|text| serde_json::from_str::<CacheEntry>(&text).ok());
+        match entry {
+            Some(mut entry) => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                for &index in &entry.suppressed {
+                    if let Some(finding) = entry.findings.get_mut(index) {
+                        finding.suppressed = true;
+                    }
+                }
+                Some((entry.findings, entry.rules_executed))
+            }
+            None => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+        }
+    }
+
+    /// Store the findings of a file; failures only lose the entry
+    pub fn put(&self, key: &str, findings: &[Finding], rules_executed: usize) {
+        let suppressed = findings.iter().enumerate().filter(|(_, f)| f.suppressed).map(|(index, _)| index).collect();
+        let entry = CacheEntry { rules_executed, findings: findings.to_vec(), suppressed };
+        let path = self.entry_path(key);
+        // Written aside and renamed, so concurrent runs never read half an entry
+        let partial = path.with_extension(format!("tmp{}", std::process::id()));
+        let written = serde_json::to_string(&entry)
+            .map_err(std::io::Error::from)
+            .and_then(|json| std::fs::write(&partial, json))
+            .and_then(|_| std::fs::rename(&partial, &path));
+        if let Err(e) = written {
+            debug!("Cannot write cache entry {}: {}", path.display(), e);
+            let _ = std::fs::remove_file(&partial);
+        }
+    }
+
+    /// Files served from the cache so far
+    pub fn hits(&self) -> usize {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// Files looked up but not found so far
+    pub fn misses(&self) -> usize {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+}
+
+/// Hash of what decides the findings of a file besides its content
+fn rule_set_hash(config: &EnhancedAnalysisConfig, files: &[PathBuf]) -> Result<String> {
+    let mut hasher = Sha256::new();
+    hasher.update(format!(
+        "{}|{:?}|{:?}|{}|{:?}|{}|{}|{:?}",
+        env!("CARGO_PKG_VERSION"),
+        config.languages,
+        config.language_overrides,
+        config.enable_dataflow,
+        config.sql_statement_boundary,
+        config.map_source_locations,
+        config.disable_nosem,
+        config.scan_profile,
+    ));
+    if let Some(ref models) = config.taint_models {
+        hasher.update(format!("|{:?}", models.models()));
+    }
+    // Serialized through JSON values, whose maps are sorted, so metadata order is stable
+    for rule in rule_repository(config).rules() {
+        hasher.update("|");
+        hasher.update(serde_json::to_value(rule)?.to_string());
+    }
+    if config.enable_dataflow {
+        for file in files {
+            hasher.update("|");
+            hasher.update(file.display().to_string());
+            hasher.update([0]);
+            hasher.update(std::fs::read(file).unwrap_or_default());
+        }
+    }
+    Ok(hex(hasher))
+}
+
+/// Lowercase hex of the SHA-256 digest; a weaker hash would let a crafted file collide
+/// with a cached clean one and be served its findings
+fn hex(hasher: Sha256) -> String {
+    hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::analyze_enhanced::{test_finding, Location};
+
+    fn finding(file: &Path) -> Finding {
+        Finding { location: Location::new(file, 1, 1, 1, 8), ..test_finding("py-eval", file, 1) }
+    }
+
+    #[test]
+    fn test_serves_unchanged_files_of_the_same_rule_set() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache_dir = dir.path().join("cache");
+        let file = dir.path().join("app.py");
+        std::fs::write(&file, "eval(x)\n").unwrap();
+
+        let cache = FindingCache::open_rule_set(&cache_dir, "rules-a").unwrap();
+        let key = cache.key(&file).unwrap();
+        assert!(cache.get(&key).is_none());
+        let silenced = Finding { suppressed: true, ..finding(&file) };
+        cache.put(&key, &[finding(&file), silenced], 12);
+
+        let (findings, rules_executed) = cache.get(&cache.key(&file).unwrap()).unwrap();
+        assert_eq!(rules_executed, 12);
+        assert_eq!(findings[0].location.file, file);
+        assert_eq!(findings.iter().map(|f| f.suppressed).collect::<Vec<_>>(), vec![false, true]);
+        assert_eq!((cache.hits(), cache.misses()), (1, 1));
+
+        // Edited content misses
+        std::fs::write(&file, "eval(y)\n").unwrap();
+        assert!(cache.get(&cache.key(&file).unwrap()).is_none());
+
+        // Another rule set starts empty; this one is kept until it goes unused for a while
+        let other = FindingCache::open_rule_set(&cache_dir, "rules-b").unwrap();
+        assert!(other.get(&key).is_none());
+        assert!(cache_dir.join("rules-a").exists());
+        let weeks_ago = SystemTime::now() - 2 * STALE_AFTER;
+        std::fs::File::open(cache_dir.join("rules-a")).unwrap().set_modified(weeks_ago).unwrap();
+        FindingCache::open_rule_set(&cache_dir, "rules-b").unwrap();
+        assert!(!cache_dir.join("rules-a").exists());
+        assert!(other.key(&dir.path().join("missing.py")).is_none());
+    }
+
+    #[test]
+    fn test_dataflow_rule_set_covers_every_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let (app, helper) = (dir.path().join("app.py"), dir.path().join("helper.py"));
+        std::fs::write(&app, "run(load())\n").unwrap();
+        std::fs::write(&helper, "def load(): return input()\n").unwrap();
+        let files = vec![app.clone(), helper.clone()];
+
+        let config = EnhancedAnalysisConfig::default();
+        let dataflow = EnhancedAnalysisConfig { enable_dataflow: true, ..EnhancedAnalysisConfig::default() };
+        let (plain, with_dataflow) = (rule_set_hash(&config, &files).unwrap(), rule_set_hash(&dataflow, &files).unwrap());
+
+        // Editing another file only changes the rule set when findings can cross files
+        std::fs::write(&helper, "def load(): return 1\n").unwrap();
+        assert_eq!(rule_set_hash(&config, &files).unwrap(), plain);
+        assert_ne!(rule_set_hash(&dataflow, &files).unwrap(), with_dataflow);
+    }
+}
@@ -0,0 +1,285 @@
+//! CI entry point
+//!
+//! `astgrep ci` runs an analysis without configuration. It recognises GitHub Actions,
+//! GitLab CI and Jenkins from their environment variables and then:
+//!
+//! - in a pull or merge request, analyzes only the files changed since the merge base
+//!   with the target branch, falling back to all files when the history is too shallow
+//!   to find it (`--full-scan` always analyzes all files);
+//! - writes a SARIF report to `astgrep.sarif` for code scanning uploads;
+//! - lists the findings in the job log, as workflow annotations on GitHub Actions;
+//! - fails the job when a finding is at or above `--fail-on` (error by default).
+//!
+//! Rules come from `--rules`, else `.astgrep.yml`, else the `rules` directory, and the
+//! include, exclude and language settings of `.astgrep.yml` apply.
+
+use anyhow::{anyhow, Result};
+use astgrep_core::{OutputFormat, Severity};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tracing::{info, warn};
+use crate::commands::analyze_enhanced::{run_enhanced, should_include_file};
+use crate::commands::color::ColorChoice;
+use crate::EnhancedAnalysisConfig;
+
+/// Where the SARIF report is written unless `--sarif-output` is given
+pub const DEFAULT_SARIF_OUTPUT: &str = "astgrep.sarif";
+
+/// CI system the job runs on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Provider {
+    GitHubActions,
+    GitLab,
+    Jenkins,
+    Unknown,
+}
+
+impl Provider {
+    pub fn name(self) -> &'static str {
+        match self {
+            Provider::GitHubActions => "GitHub Actions",
+            Provider::GitLab => "GitLab CI",
+            Provider::Jenkins => "Jenkins",
+            Provider::Unknown => "unknown CI",
+        }
+    }
+}
+
+/// What the environment says about the job
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CiEnvironment {
+    pub provider: Provider,
+    /// Revision a pull or merge request targets, if the job builds one
+    pub base_ref: Option<String>,
+}
+
+impl CiEnvironment {
+    pub fn detect() -> Self {
+        Self::from_vars(|name| std::env::var(name).ok().filter(|value| !value.is_empty()))
+    }
+
+    fn from_vars(var: impl Fn(&str) -> Option<String>) -> Self {
+        let branch = |name: &str| var(name).map(|branch| format!("origin/{}", branch));
+        if var("GITHUB_ACTIONS").as_deref() == Some("true") {
+            Self { provider: Provider::GitHubActions, base_ref: branch("GITHUB_BASE_REF") }
+        } else if var("GITLAB_CI").as_deref() == Some("true") {
+            let base_ref = var("CI_MERGE_REQUEST_DIFF_BASE_SHA").or_else(|| branch("CI_MERGE_REQUEST_TARGET_BRANCH_NAME"));
+            Self { provider: Provider::GitLab, base_ref }
+        } else if var("JENKINS_URL").is_some() {
+            Self { provider: Provider::Jenkins, base_ref: branch("CHANGE_TARGET") }
+        } else {
+            Self { provider: Provider::Unknown, base_ref: None }
+        }
+    }
+}
+
+/// A result of the SARIF report, as shown in the job log
+#[derive(Debug, Clone, PartialEq)]
+struct CiFinding {
+    rule_id: String,
+    severity: Severity,
+    message: String,
+    file: String,
+    line: u64,
+    column: u64,
+    end_line: u64,
+}
+
+/// Analyze the repository in the current directory the way the detected CI system expects
+pub async fn run(
+    rules: Vec<PathBuf>,
+    sarif_output: PathBuf,
+    fail_on: Option<Severity>,
+    full_scan: bool,
+    base_ref: Option<String>,
+) -> Result<()> {
+    let environment = CiEnvironment::detect();
+    let base_ref = if full_scan { None } else { base_ref.or(environment.base_ref) };
+    info!("Running on {}", environment.provider.name());
+
+    let root = Path::new(".");
+    let base = EnhancedAnalysisConfig {
+        rule_files: crate::commands::registry::Registry::from_env().resolve_all(rules)?,
+        languages: crate::DEFAULT_LANGUAGES.to_vec(),
+        output_format: OutputFormat::Sarif,
+        color: ColorChoice::Never,
+        ..Default::default()
+    };
+    let mut config = crate::commands::workspace::config_for_root(&base, root)?;
+    if config.rule_files.is_empty() {
+        config.rule_files = crate::commands::explain::rule_paths(Vec::new(), root)?;
+    }
+
+    if let Some(ref base_ref) = base_ref {
+        match changed_files(base_ref) {
+            Ok(files) => {
+                let files: Vec<PathBuf> = files.into_iter().filter(|file| file.is_file() && should_include_file(file, &config)).collect();
+                eprintln!("Analyzing {} file(s) changed since {}", files.len(), base_ref);
+                config.target_paths = files;
+            }
+            Err(e) => warn!(
+                "Cannot diff against {} ({}); analyzing all files. Fetch the target branch and enough history for diff-aware scans.",
+                base_ref, e
+            ),
+        }
+    }
+
+    run_enhanced(config, Some(sarif_output.clone())).await?;
+
+    let sarif: Value = serde_json::from_str(&std::fs::read_to_string(&sarif_output)?)?;
+    let findings = sarif_findings(&sarif);
+    for finding in &findings {
+        println!("{}", annotation(environment.provider, finding));
+    }
+    let blocking = fail_on.map_or(0, |threshold| findings.iter().filter(|f| f.severity >= threshold).count());
+    eprintln!(
+        "{} finding(s), {} blocking; SARIF report written to {}",
+        findings.len(),
+        blocking,
+        sarif_output.display()
+    );
+    if blocking > 0 {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Files added, copied, modified or renamed since the merge base of `HEAD` and `base_ref`,
+/// relative to the current directory
+pub(crate) fn changed_files(base_ref: &str) -> Result<Vec<PathBuf>> {
+    let merge_base = git(&["merge-base", "HEAD", base_ref])?;
+    let diff = git(&["diff", "--name-only", "--relative", "--diff-filter=ACMR", "-z", merge_base.trim()])?;
+    Ok(diff.split('\0').filter(|path| !path.is_empty()).map(PathBuf::from).collect())
+}
+
+pub(crate) fn git(args: &[&str]) -> Result<String> {
+    let output = Command::new("git").args(args).output().map_err(|e| anyhow!("cannot run git: {}", e))?;
+    if !output.status.success() {
+        return Err(anyhow!("git {}: {}", args[0], String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// The unsuppressed results of a SARIF report
+fn sarif_findings(sarif: &Value) -> Vec<CiFinding> {
+    let results = sarif["runs"].as_array().into_iter().flatten().flat_map(|run| run["results"].as_array().into_iter().flatten());
+    results
+        .filter(|result| result.get("suppressions").is_none())
+        .map(|result| {
+            let location = &result["locations"][0]["physicalLocation"];
+            let region = &location["region"];
+            let line = region["startLine"].as_u64().unwrap_or(1);
+            CiFinding {
+                rule_id: result["ruleId"].as_str().unwrap_or_default().to_string(),
+                severity: match result["level"].as_str() {
+                    Some("error") => Severity::Error,
+                    Some("warning") => Severity::Warning,
+                    _ => Severity::Info,
+                },
+                message: result["message"]["text"].as_str().unwrap_or_default().to_string(),
+                file: location["artifactLocation"]["uri"].as_str().unwrap_or_default().to_string(),
+                line,
+                column: region["startColumn"].as_u64().unwrap_or(1),
+                end_line: region["endLine"].as_u64().unwrap_or(line),
+            }
+        })
+        .collect()
+}
+
+/// A log line for `finding`: a workflow command on GitHub Actions, which turns it into an
+/// annotation of the pull request, else `file:line:column: severity [rule] message`
+fn annotation(provider: Provider, finding: &CiFinding) -> String {
+    let file = finding.file.strip_prefix("./").unwrap_or(&finding.file);
+    match provider {
+        Provider::GitHubActions => {
+            let command = match finding.severity {
+                Severity::Critical | Severity::Error => "error",
+                Severity::Warning => "warning",
+                Severity::Info => "notice",
+            };
+            format!(
+                "::{} file={},line={},endLine={},col={},title={}::{}",
+                command,
+                escape_property(file),
+                finding.line,
+                finding.end_line,
+                finding.column,
+                escape_property(&finding.rule_id),
+                escape_data(&finding.message)
+            )
+        }
+        _ => format!(
+            "{}:{}:{}: {} [{}] {}",
+            file,
+            finding.line,
+            finding.column,
+            finding.severity.as_str(),
+            finding.rule_id,
+            finding.message.replace('\n', " ")
+        ),
+    }
+}
+
+/// Escape the message of a GitHub Actions workflow command
+fn escape_data(text: &str) -> String {
+    text.replace('%', "%25").replace('\r', "%0D").replace('\n', "%0A")
+}
+
+/// Escape a property value of a GitHub Actions workflow command
+fn escape_property(text: &str) -> String {
+    escape_data(text).replace(':', "%3A").replace(',', "%2C")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    fn environment(vars: &[(&str, &str)]) -> CiEnvironment {
+        let vars: HashMap<String, String> = vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        CiEnvironment::from_vars(|name| vars.get(name).cloned())
+    }
+
+    #[test]
+    fn test_detects_provider_and_base() {
+        let github = environment(&[("GITHUB_ACTIONS", "true"), ("GITHUB_BASE_REF", "main")]);
+        assert_eq!(github, CiEnvironment { provider: Provider::GitHubActions, base_ref: Some("origin/main".to_string()) });
+        assert_eq!(environment(&[("GITHUB_ACTIONS", "true")]).base_ref, None);
+
+        let gitlab = environment(&[
+            ("GITLAB_CI", "true"),
+            ("CI_MERGE_REQUEST_TARGET_BRANCH_NAME", "develop"),
+            ("CI_MERGE_REQUEST_DIFF_BASE_SHA", "4f2a9c1"),
+        ]);
+        assert_eq!(gitlab, CiEnvironment { provider: Provider::GitLab, base_ref: Some("4f2a9c1".to_string()) });
+
+        let jenkins = environment(&[("JENKINS_URL", "https://ci.example.com/"), ("CHANGE_TARGET", "release")]);
+        assert_eq!(jenkins, CiEnvironment { provider: Provider::Jenkins, base_ref: Some("origin/release".to_string()) });
+        assert_eq!(environment(&[]).provider, Provider::Unknown);
+    }
+
+    #[test]
+    fn test_annotations_from_sarif() {
+        let sarif = json!({ "runs": [{ "results": [
+            { "ruleId": "js-eval", "level": "error", "message": { "text": "Avoid eval: 100%\nreally" },
+              "locations": [{ "physicalLocation": { "artifactLocation": { "uri": "./src/a,b.js" },
+                "region": { "startLine": 3, "startColumn": 5, "endLine": 4 } } }] },
+            { "ruleId": "js-todo", "level": "note", "message": { "text": "TODO" },
+              "locations": [{ "physicalLocation": { "artifactLocation": { "uri": "src/c.js" },
+                "region": { "startLine": 9, "startColumn": 1 } } }] },
+            { "ruleId": "js-exec", "level": "warning", "message": { "text": "ignored" },
+              "suppressions": [{ "kind": "inSource" }] }
+        ] }] });
+        let findings = sarif_findings(&sarif);
+        assert_eq!(findings.len(), 2);
+
+        assert_eq!(
+            annotation(Provider::GitHubActions, &findings[0]),
+            "::error file=src/a%2Cb.js,line=3,endLine=4,col=5,title=js-eval::Avoid eval: 100%25%0Areally"
+        );
+        assert_eq!(annotation(Provider::GitHubActions, &findings[1]), "::notice file=src/c.js,line=9,endLine=9,col=1,title=js-todo::TODO");
+        assert_eq!(annotation(Provider::GitLab, &findings[1]), "src/c.js:9:1: INFO [js-todo] TODO");
+    }
+}
@@ -0,0 +1,111 @@
+//! ANSI coloring of terminal output
+//!
+//! `--color auto` colors only when writing to a terminal and `NO_COLOR` is unset;
+//! `always` and `never` override both.
+
+use astgrep_core::Severity;
+use std::io::IsTerminal;
+
+/// When to color text output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorChoice {
+    /// Color when writing to a terminal and `NO_COLOR` is not set
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// Resolve `Auto` for output going to stdout (`to_stdout`) or to a file
+    pub fn resolve(self, to_stdout: bool) -> ColorChoice {
+        let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+        self.resolve_with(no_color, to_stdout && std::io::stdout().is_terminal())
+    }
+
+    fn resolve_with(self, no_color: bool, terminal: bool) -> ColorChoice {
+        match self {
+            ColorChoice::Auto if terminal && !no_color => ColorChoice::Always,
+            ColorChoice::Auto => ColorChoice::Never,
+            choice => choice,
+        }
+    }
+}
+
+/// Wraps text in ANSI escapes, or passes it through when disabled
+#[derive(Debug, Clone, Copy)]
+pub struct Painter {
+    enabled: bool,
+}
+
+impl Painter {
+    /// Painter for an already resolved choice; `Auto` does not color
+    pub fn new(choice: ColorChoice) -> Self {
+        Self { enabled: choice == ColorChoice::Always }
+    }
+
+    fn paint(&self, code: &str, text: &str) -> String {
+        if self.enabled && !text.is_empty() {
+            format!("\x1b[{}m{}\x1b[0m", code, text)
+        } else {
+            text.to_string()
+        }
+    }
+
+    /// Text in the color of `severity`
+    pub fn severity(&self, severity: &Severity, text: &str) -> String {
+        let code = match severity {
+            Severity::Critical => "1;35",
+            Severity::Error => "1;31",
+            Severity::Warning => "1;33",
+            Severity::Info => "1;36",
+        };
+        self.paint(code, text)
+    }
+
+    pub fn bold(&self, text: &str) -> String {
+        self.paint("1", text)
+    }
+
+    pub fn dim(&self, text: &str) -> String {
+        self.paint("2", text)
+    }
+
+    pub fn underline(&self, text: &str) -> String {
+        self.paint("4", text)
+    }
+
+    pub fn green(&self, text: &str) -> String {
+        self.paint("32", text)
+    }
+
+    pub fn yellow(&self, text: &str) -> String {
+        self.paint("33", text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve() {
+        assert_eq!(ColorChoice::Auto.resolve_with(false, true), ColorChoice::Always);
+        assert_eq!(ColorChoice::Auto.resolve_with(true, true), ColorChoice::Never);
+        assert_eq!(ColorChoice::Auto.resolve_with(false, false), ColorChoice::Never);
+        assert_eq!(ColorChoice::Always.resolve_with(true, false), ColorChoice::Always);
+        assert_eq!(ColorChoice::Never.resolve_with(false, true), ColorChoice::Never);
+    }
+
+    #[test]
+    fn test_painter() {
+        let plain = Painter::new(ColorChoice::Never);
+        assert_eq!(plain.severity(&Severity::Error, "x"), "x");
+        assert_eq!(plain.underline("x"), "x");
+
+        let color = Painter::new(ColorChoice::Always);
+        assert_eq!(color.severity(&Severity::Error, "x"), "\x1b[1;31mx\x1b[0m");
+        assert_eq!(color.dim("x"), "\x1b[2mx\x1b[0m");
+        assert_eq!(color.underline(""), "");
+    }
+}
@@ -0,0 +1,120 @@
+//! Shell completion scripts
+//!
+//! `completions <shell>` prints a completion script for bash, zsh, fish, PowerShell or
+//! elvish, generated by clap_complete from the command-line definition. Beyond commands
+//! and flags, `--language` completes to the supported language names and the rule id of
+//! `explain` to the ids of the rules found when the script is generated: those of
+//! `--rules`, else of `.astgrep.yml`, else of `./rules`. Regenerate the script after
+//! adding rules to pick up their ids.
+
+use anyhow::Result;
+use astgrep_core::Language;
+use clap::builder::PossibleValuesParser;
+use clap::{Command, CommandFactory};
+use clap_complete::Shell;
+use std::path::{Path, PathBuf};
+use tracing::{debug, info};
+
+/// Name the scripts complete
+const BIN_NAME: &str = "astgrep";
+
+const LANGUAGES: [Language; 12] = [
+    Language::Java,
+    Language::JavaScript,
+    Language::Python,
+    Language::Php,
+    Language::Sql,
+    Language::Bash,
+    Language::CSharp,
+    Language::C,
+    Language::Ruby,
+    Language::Kotlin,
+    Language::Swift,
+    Language::Xml,
+];
+
+/// Print the completion script of `shell`, completing the ids of the rules in `rules`
+pub async fn run(shell: Shell, rules: Vec<PathBuf>) -> Result<()> {
+    let rule_ids = rule_ids(rules);
+    info!("Generating {} completions with {} rule id(s)", shell, rule_ids.len());
+    let mut command = with_value_completions(crate::Cli::command(), &rule_ids);
+    clap_complete::generate(shell, &mut command, BIN_NAME, &mut std::io::stdout());
+    Ok(())
+}
+
+/// The ids of the configured rules, sorted; none when no rules are configured
+fn rule_ids(rules: Vec<PathBuf>) -> Vec<String> {
+    let paths = match crate::commands::explain::rule_paths(rules, Path::new("."))
+        .and_then(|paths| crate::commands::registry::Registry::from_env().resolve_all(paths))
+    {
+        Ok(paths) => paths,
+        Err(e) => {
+            debug!("Not completing rule ids: {}", e);
+            return Vec::new();
+        }
+    };
+    let repository = astgrep_rules::RuleRepository::from_paths(&paths);
+    let mut ids: Vec<String> = repository.rules().map(|rule| rule.id.clone()).collect();
+    ids.sort();
+    ids.dedup();
+    ids
+}
+
+/// `command` with the values of language and rule id arguments listed for completion.
+/// The values only feed the script; parsing still goes through the derived definition.
+fn with_value_completions(command: Command, rule_ids: &[String]) -> Command {
+    let languages: Vec<&'static str> = LANGUAGES.iter().map(Language::as_str).collect();
+    let rule_ids = rule_ids.to_vec();
+    command.mut_subcommands(move |mut subcommand| {
+        let has_language = subcommand.get_arguments().any(|arg| arg.get_id() == "language");
+        let has_rule_id = subcommand.get_arguments().any(|arg| arg.get_id() == "rule_id");
+        if has_language {
+            let languages = languages.clone();
+            subcommand = subcommand.mut_arg("language", |arg| arg.value_parser(PossibleValuesParser::new(languages)));
+        }
+        if has_rule_id && !rule_ids.is_empty() {
+            let rule_ids = rule_ids.clone();
+            subcommand = subcommand.mut_arg("rule_id", |arg| arg.value_parser(PossibleValuesParser::new(rule_ids)));
+        }
+        subcommand
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn script(shell: Shell, rule_ids: &[String]) -> String {
+        let mut command = with_value_completions(crate::Cli::command(), rule_ids);
+        let mut script = Vec::new();
+        clap_complete::generate(shell, &mut command, BIN_NAME, &mut script);
+        String::from_utf8(script).unwrap()
+    }
+
+    #[test]
+    fn test_scripts_complete_languages_and_rule_ids() {
+        let ids = vec!["js-eval".to_string(), "py-exec".to_string()];
+        let bash = script(Shell::Bash, &ids);
+        assert!(bash.contains("complete -F _astgrep"));
+        assert!(bash.contains("javascript"));
+        assert!(bash.contains("js-eval py-exec"));
+
+        let fish = script(Shell::Fish, &[]);
+        assert!(fish.contains("complete -c astgrep"));
+        assert!(fish.contains("kotlin"));
+        assert!(!fish.contains("js-eval"));
+    }
+
+    #[test]
+    fn test_rule_ids_from_rule_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let rules = dir.path().join("rules.yaml");
+        std::fs::write(
+            &rules,
+            "rules:\n  - id: py-exec\n    message: exec\n    severity: ERROR\n    languages: [python]\n    pattern: exec($X)\n\
+             \x20 - id: js-eval\n    message: eval\n    severity: ERROR\n    languages: [javascript]\n    pattern: eval($X)\n",
+        )
+        .unwrap();
+        assert_eq!(rule_ids(vec![rules]), vec!["js-eval".to_string(), "py-exec".to_string()]);
+    }
+}
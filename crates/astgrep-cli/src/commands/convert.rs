@@ -0,0 +1,244 @@
+//! Report conversion
+//!
+//! Re-renders the JSON findings file of an earlier `analyze` run in another output
+//! format, so one scan can produce SARIF for code scanning, HTML for people and CSV for
+//! spreadsheets without analyzing the code again.
+
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::info;
+
+use crate::commands::analyze_enhanced::{render_report, AnalysisStatistics, DegradedLanguage, Finding};
+use crate::commands::workspace::RootStatistics;
+use crate::{convert_output_format, EnhancedAnalysisConfig, OutputFormatCli};
+
+/// The parts of an `analyze --format json` report a conversion carries over
+#[derive(Deserialize)]
+struct JsonReport {
+    /// Absent in reports written before the format was versioned, which match 1.0.0
+    #[serde(default)]
+    schema_version: Option<String>,
+    findings: Vec<Finding>,
+    #[serde(default)]
+    suppressed_findings: Vec<Finding>,
+    #[serde(default)]
+    summary: JsonSummary,
+}
+
+#[derive(Default, Deserialize)]
+struct JsonSummary {
+    #[serde(default)]
+    files_analyzed: usize,
+    #[serde(default)]
+    rules_executed: usize,
+    #[serde(default)]
+    analysis_time_ms: u64,
+    #[serde(default)]
+    status: Option<String>,
+    #[serde(default)]
+    degraded_languages: Vec<DegradedLanguage>,
+    #[serde(default)]
+    roots: Vec<RootStatistics>,
+    #[serde(default)]
+    profile: Option<serde_json::Value>,
+    #[serde(default)]
+    skipped_fixes: Vec<crate::commands::autofix::SkippedFix>,
+    #[serde(default)]
+    baseline_matched: Option<usize>,
+    #[serde(default)]
+    skipped_files: Vec<crate::commands::target_filter::SkippedFile>,
+    #[serde(default)]
+    failed_files: Vec<crate::commands::isolation::FailedFile>,
+}
+
+/// A findings report read back from disk
+#[derive(Debug)]
+pub struct Report {
+    pub findings: Vec<Finding>,
+    pub stats: AnalysisStatistics,
+    pub analysis_time: Duration,
+}
+
+impl Report {
+    /// Read a report written by `analyze --format json`
+    pub fn read(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path).map_err(|e| anyhow!("Cannot read {}: {}", path.display(), e))?;
+        Self::parse(&text).map_err(|e| anyhow!("{} is not a JSON findings report: {}", path.display(), e))
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let report: JsonReport = serde_json::from_str(text)?;
+        if let Some(version) = report.schema_version.as_deref() {
+            if !astgrep_core::is_compatible_schema_version(version) {
+                return Err(anyhow!(
+                    "unsupported report schema version {} (this astgrep reads {})",
+                    version,
+                    astgrep_core::REPORT_SCHEMA_VERSION
+                ));
+            }
+        }
+        let summary = report.summary;
+
+        let mut stats = AnalysisStatistics::new();
+        stats.files_analyzed = summary.files_analyzed;
+        stats.rules_executed = summary.rules_executed;
+        stats.degraded_languages = summary.degraded_languages;
+        stats.roots = summary.roots;
+        stats.skipped_fixes = summary.skipped_fixes;
+        stats.baseline_matched = summary.baseline_matched;
+        stats.skipped_files = summary.skipped_files;
+        stats.failed_files = summary.failed_files;
+        stats.suppressed_findings = report.suppressed_findings;
+        for finding in &mut stats.suppressed_findings {
+            finding.suppressed = true;
+        }
+        stats.cancelled = summary.status.as_deref() == Some("cancelled");
+        stats.budget_exhausted = summary.status.as_deref() == Some("budget-exhausted");
+        // The profile's name is not one of its settings, so it is restored separately
+        stats.profile = summary.profile.and_then(|value| {
+            let name = value.get("name").and_then(|n| n.as_str()).unwrap_or_default().to_string();
+            serde_json::from_value::<crate::commands::scan_profile::ScanProfile>(value)
+                .ok()
+                .map(|profile| crate::commands::scan_profile::ScanProfile { name, ..profile })
+        });
+
+        Ok(Self {
+            findings: report.findings,
+            stats,
+            analysis_time: Duration::from_millis(summary.analysis_time_ms),
+        })
+    }
+
+    /// Render the report in `format`
+    pub fn render(&self, format: &OutputFormatCli, color: crate::commands::color::ColorChoice) -> Result<String> {
+        let config = EnhancedAnalysisConfig {
+            output_format: convert_output_format(format.clone()),
+            color,
+            ..EnhancedAnalysisConfig::default()
+        };
+        render_report(&self.findings, &self.stats, &config, self.analysis_time)
+    }
+}
+
+/// Convert the findings file `input` to `format`, writing to `output` or stdout
+pub async fn run(input: PathBuf, format: OutputFormatCli, output: Option<PathBuf>) -> Result<()> {
+    let report = Report::read(&input)?;
+    let color = crate::commands::color::ColorChoice::Auto.resolve(output.is_none());
+    let rendered = report.render(&format, color)?;
+
+    match output {
+        Some(path) => {
+            std::fs::write(&path, rendered)?;
+            info!("Converted {} finding(s) to {}", report.findings.len(), path.display());
+        }
+        None => println!("{}", rendered),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::color::ColorChoice;
+
+    const REPORT: &str = r#"{
+  "findings": [
+    {
+      "rule_id": "js-eval",
+      "message": "Avoid eval, it runs \"arbitrary\" code",
+      "severity": "ERROR",
+      "confidence": "HIGH",
+      "location": { "file": "src/app.js", "start_line": 3, "start_column": 5, "end_line": 3, "end_column": 15 },
+      "fix": null,
+      "risk_score": 85,
+      "effort": "small"
+    }
+  ],
+  "summary": { "total_findings": 1, "files_analyzed": 4, "rules_executed": 2, "analysis_time_ms": 12, "status": "completed" }
+}"#;
+
+    #[test]
+    fn test_convert_json_report() {
+        let report = Report::parse(REPORT).unwrap();
+        assert_eq!(report.findings.len(), 1);
+        assert_eq!(report.stats.files_analyzed, 4);
+
+        let csv = report.render(&OutputFormatCli::Csv, ColorChoice::Never).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[1], "js-eval,ERROR,HIGH,src/app.js,3,5,3,15,\"Avoid eval, it runs \"\"arbitrary\"\" code\",,85,small");
+
+        let sarif: serde_json::Value = serde_json::from_str(&report.render(&OutputFormatCli::Sarif, ColorChoice::Never).unwrap()).unwrap();
+        let result = &sarif["runs"][0]["results"][0];
+        assert_eq!(result["ruleId"], "js-eval");
+        assert_eq!(result["properties"]["riskScore"], 85);
+
+        let markdown = report.render(&OutputFormatCli::Markdown, ColorChoice::Never).unwrap();
+        assert!(markdown.contains("- **Rule:** `js-eval`"));
+        assert!(report.render(&OutputFormatCli::Html, ColorChoice::Never).unwrap().contains("<h3>Avoid eval"));
+
+        let json: serde_json::Value = serde_json::from_str(&report.render(&OutputFormatCli::Json, ColorChoice::Never).unwrap()).unwrap();
+        assert_eq!(json["findings"][0]["effort"], "small");
+        assert_eq!(json["summary"]["files_analyzed"], 4);
+    }
+
+    #[test]
+    fn test_code_climate_issues() {
+        let mut report = Report::parse(REPORT).unwrap();
+        report.findings[0].category = Some("security".to_string());
+        let output = report.render(&OutputFormatCli::CodeClimate, ColorChoice::Never).unwrap();
+        let issues: Vec<&str> = output.split('\0').map(str::trim).filter(|issue| !issue.is_empty()).collect();
+        assert_eq!(issues.len(), 1);
+
+        let issue: serde_json::Value = serde_json::from_str(issues[0]).unwrap();
+        assert_eq!(issue["type"], "issue");
+        assert_eq!(issue["check_name"], "js-eval");
+        assert_eq!(issue["categories"][0], "Security");
+        assert_eq!(issue["severity"], "major");
+        assert_eq!(issue["location"]["path"], "src/app.js");
+        assert_eq!(issue["location"]["positions"]["begin"]["line"], 3);
+        assert_eq!(issue["fingerprint"].as_str().map(str::len), Some(16));
+    }
+
+    #[test]
+    fn test_json_report_matches_schema() {
+        let mut report = Report::parse(REPORT).unwrap();
+        let mut finding = report.findings[0].clone();
+        finding.fix = Some("JSON.parse(input)".to_string());
+        finding.layer = Some("sha256:abc".to_string());
+        finding.category = Some("supply-chain".to_string());
+        finding.is_test = true;
+        report.findings.push(finding);
+
+        let json: serde_json::Value = serde_json::from_str(&report.render(&OutputFormatCli::Json, ColorChoice::Never).unwrap()).unwrap();
+        assert_eq!(json["schema_version"], astgrep_core::REPORT_SCHEMA_VERSION);
+        assert_eq!(astgrep_core::undeclared_fields(&astgrep_core::report_schema(), &json), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_suppressed_findings_round_trip() {
+        let mut value: serde_json::Value = serde_json::from_str(REPORT).unwrap();
+        value["suppressed_findings"] = value["findings"].clone();
+        let report = Report::parse(&value.to_string()).unwrap();
+        assert!(report.stats.suppressed_findings[0].suppressed);
+
+        let sarif: serde_json::Value = serde_json::from_str(&report.render(&OutputFormatCli::Sarif, ColorChoice::Never).unwrap()).unwrap();
+        let results = sarif["runs"][0]["results"].as_array().unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(results[0].get("suppressions").is_none());
+        assert_eq!(results[1]["suppressions"][0]["kind"], "inSource");
+
+        let json: serde_json::Value = serde_json::from_str(&report.render(&OutputFormatCli::Json, ColorChoice::Never).unwrap()).unwrap();
+        assert_eq!(json["suppressed_findings"][0]["rule_id"], "js-eval");
+        assert_eq!(json["summary"]["total_findings"], 1);
+    }
+
+    #[test]
+    fn test_reject_other_files() {
+        assert!(Report::parse("{\"runs\": []}").is_err());
+        let future = REPORT.replacen("{", "{ \"schema_version\": \"2.0.0\",", 1);
+        assert!(Report::parse(&future).unwrap_err().to_string().contains("unsupported report schema version 2.0.0"));
+    }
+}
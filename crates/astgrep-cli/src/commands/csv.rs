@@ -0,0 +1,197 @@
+//! CSV findings output
+//!
+//! `--format csv` writes one row per finding for triage in a spreadsheet. `--csv-columns`
+//! picks and orders the columns, e.g. `rule_id,severity,file,line,message`. Fields are
+//! quoted as RFC 4180 describes, and text starting like a spreadsheet formula is prefixed
+//! with `'` so opening the file does not evaluate it.
+
+use anyhow::{anyhow, Result};
+use crate::commands::analyze_enhanced::Finding;
+
+/// A column of the CSV output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CsvColumn {
+    RuleId,
+    Severity,
+    Confidence,
+    File,
+    StartLine,
+    StartColumn,
+    EndLine,
+    EndColumn,
+    Message,
+    Fix,
+    RiskScore,
+    Effort,
+    Category,
+    Fingerprint,
+}
+
+/// Columns written unless `--csv-columns` picks others
+pub const DEFAULT_COLUMNS: &[CsvColumn] = &[
+    CsvColumn::RuleId,
+    CsvColumn::Severity,
+    CsvColumn::Confidence,
+    CsvColumn::File,
+    CsvColumn::StartLine,
+    CsvColumn::StartColumn,
+    CsvColumn::EndLine,
+    CsvColumn::EndColumn,
+    CsvColumn::Message,
+    CsvColumn::Fix,
+    CsvColumn::RiskScore,
+    CsvColumn::Effort,
+];
+
+const ALL_COLUMNS: &[CsvColumn] = &[
+    CsvColumn::RuleId,
+    CsvColumn::Severity,
+    CsvColumn::Confidence,
+    CsvColumn::File,
+    CsvColumn::StartLine,
+    CsvColumn::StartColumn,
+    CsvColumn::EndLine,
+    CsvColumn::EndColumn,
+    CsvColumn::Message,
+    CsvColumn::Fix,
+    CsvColumn::RiskScore,
+    CsvColumn::Effort,
+    CsvColumn::Category,
+    CsvColumn::Fingerprint,
+];
+
+impl CsvColumn {
+    /// Header of the column
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CsvColumn::RuleId => "rule_id",
+            CsvColumn::Severity => "severity",
+            CsvColumn::Confidence => "confidence",
+            CsvColumn::File => "file",
+            CsvColumn::StartLine => "start_line",
+            CsvColumn::StartColumn => "start_column",
+            CsvColumn::EndLine => "end_line",
+            CsvColumn::EndColumn => "end_column",
+            CsvColumn::Message => "message",
+            CsvColumn::Fix => "fix",
+            CsvColumn::RiskScore => "risk_score",
+            CsvColumn::Effort => "effort",
+            CsvColumn::Category => "category",
+            CsvColumn::Fingerprint => "fingerprint",
+        }
+    }
+
+    /// Column named `s`; `line` and `column` stand for the start of the finding
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().replace('-', "_").as_str() {
+            "line" => Some(CsvColumn::StartLine),
+            "column" => Some(CsvColumn::StartColumn),
+            "rule" => Some(CsvColumn::RuleId),
+            name => ALL_COLUMNS.iter().copied().find(|column| column.as_str() == name),
+        }
+    }
+
+    fn value(&self, finding: &Finding) -> String {
+        let loc = &finding.location;
+        match self {
+            CsvColumn::RuleId => field(&finding.rule_id),
+            CsvColumn::Severity => finding.severity.as_str().to_string(),
+            CsvColumn::Confidence => finding.confidence.as_str().to_string(),
+            CsvColumn::File => field(&loc.file.to_string_lossy()),
+            CsvColumn::StartLine => loc.start_line.to_string(),
+            CsvColumn::StartColumn => loc.start_column.to_string(),
+            CsvColumn::EndLine => loc.end_line.to_string(),
+            CsvColumn::EndColumn => loc.end_column.to_string(),
+            CsvColumn::Message => field(&finding.message),
+            CsvColumn::Fix => field(finding.fix.as_deref().unwrap_or("")),
+            CsvColumn::RiskScore => finding.risk_score.map(|s| s.to_string()).unwrap_or_default(),
+            CsvColumn::Effort => finding.effort.map(|e| e.as_str().to_string()).unwrap_or_default(),
+            CsvColumn::Category => field(finding.category.as_deref().unwrap_or("")),
+            CsvColumn::Fingerprint => finding.fingerprint.clone().unwrap_or_default(),
+        }
+    }
+}
+
+/// Parse a comma-separated `--csv-columns` list
+pub fn parse_columns(spec: &str) -> Result<Vec<CsvColumn>> {
+    let columns = spec
+        .split(',')
+        .filter(|name| !name.trim().is_empty())
+        .map(|name| {
+            CsvColumn::parse(name).ok_or_else(|| {
+                let known: Vec<&str> = ALL_COLUMNS.iter().map(|column| column.as_str()).collect();
+                anyhow!("unknown column '{}' (expected one of {})", name.trim(), known.join(", "))
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    if columns.is_empty() {
+        return Err(anyhow!("no columns given"));
+    }
+    Ok(columns)
+}
+
+/// Render `findings` as CSV with a header row
+pub fn render(findings: &[Finding], columns: &[CsvColumn]) -> String {
+    let header: Vec<&str> = columns.iter().map(|column| column.as_str()).collect();
+    let mut csv = header.join(",");
+    csv.push('\n');
+    for finding in findings {
+        let row: Vec<String> = columns.iter().map(|column| column.value(finding)).collect();
+        csv.push_str(&row.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+/// A text field, defused if it would be read as a formula and quoted if it holds a
+/// separator, quote or line break
+fn field(value: &str) -> String {
+    let value = if value.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{}", value)
+    } else {
+        value.to_string()
+    };
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::analyze_enhanced::test_finding;
+    use astgrep_core::{Confidence, Severity};
+
+    fn finding(message: &str) -> Finding {
+        Finding {
+            message: message.to_string(),
+            severity: Severity::Warning,
+            confidence: Confidence::Medium,
+            category: Some("security".to_string()),
+            ..test_finding("py-eval", "app.py", 7)
+        }
+    }
+
+    #[test]
+    fn test_parse_columns() {
+        let columns = parse_columns("rule_id, severity,file,line,message").unwrap();
+        assert_eq!(columns, vec![CsvColumn::RuleId, CsvColumn::Severity, CsvColumn::File, CsvColumn::StartLine, CsvColumn::Message]);
+        assert_eq!(parse_columns("end-line").unwrap(), vec![CsvColumn::EndLine]);
+        assert!(parse_columns("rule_id,owner").unwrap_err().to_string().contains("unknown column 'owner'"));
+        assert!(parse_columns(" , ").is_err());
+    }
+
+    #[test]
+    fn test_render_quotes_and_defuses_fields() {
+        let columns = parse_columns("rule_id,line,message,category").unwrap();
+        let findings = [finding("Avoid \"eval\", it runs code"), finding("=HYPERLINK(\"x\")"), finding("two\nlines")];
+        let csv = render(&findings, &columns);
+        let expected = "rule_id,start_line,message,category\n\
+            py-eval,7,\"Avoid \"\"eval\"\", it runs code\",security\n\
+            py-eval,7,\"'=HYPERLINK(\"\"x\"\")\",security\n\
+            py-eval,7,\"two\nlines\",security\n";
+        assert_eq!(csv, expected);
+    }
+}
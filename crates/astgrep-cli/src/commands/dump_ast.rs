@@ -0,0 +1,172 @@
+//! Syntax tree dump
+//!
+//! `dump-ast <file>` parses one file with the parser astgrep uses for its language and
+//! prints the resulting universal syntax tree, so rule authors can see which node types a
+//! pattern has to target. The tree is printed as indented text, as JSON or as an
+//! S-expression; every node shows its type and location, and text and S-expression output
+//! show the source text of each node shortened to one line.
+
+use anyhow::{anyhow, Result};
+use astgrep_core::{AstNode, Language};
+use astgrep_parser::LanguageParserRegistry;
+use serde_json::{json, Value};
+use std::fmt::Write;
+use std::path::PathBuf;
+use tracing::info;
+use crate::commands::analyze_enhanced::determine_language;
+
+/// Characters of node text shown in text and S-expression output
+const TEXT_PREVIEW_CHARS: usize = 40;
+
+/// How the tree is printed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AstFormat {
+    Text,
+    Json,
+    SExpr,
+}
+
+/// Parse `file`, as `language` or else the language of its extension, and print its tree
+pub async fn run(file: PathBuf, language: Option<String>, format: AstFormat) -> Result<()> {
+    let language = match language {
+        Some(name) => Language::from_str(&name).ok_or_else(|| anyhow!("Unknown language: {}", name))?,
+        None => determine_language(&file)?,
+    };
+    let registry = LanguageParserRegistry::new();
+    let parser = registry.get_parser(language).ok_or_else(|| anyhow!("No parser for {}", language.as_str()))?;
+    let source = std::fs::read_to_string(&file).map_err(|e| anyhow!("Cannot read {}: {}", file.display(), e))?;
+    info!("Parsing {} as {}", file.display(), language.as_str());
+    let ast = parser.parse(&source, &file)?;
+
+    match format {
+        AstFormat::Text => print!("{}", render_text(ast.as_ref())),
+        AstFormat::Json => println!("{}", serde_json::to_string_pretty(&to_json(ast.as_ref()))?),
+        AstFormat::SExpr => println!("{}", render_sexpr(ast.as_ref())),
+    }
+    Ok(())
+}
+
+/// One line per node, indented by depth: `type [start-end] "text"`
+fn render_text(root: &dyn AstNode) -> String {
+    fn visit(node: &dyn AstNode, depth: usize, out: &mut String) {
+        let _ = write!(out, "{}{}", "  ".repeat(depth), node.node_type());
+        if let Some(location) = node.location() {
+            let _ = write!(out, " [{}]", format_location(location));
+        }
+        if let Some(text) = node.text().filter(|text| !text.trim().is_empty()) {
+            let _ = write!(out, " {:?}", preview(text));
+        }
+        out.push('\n');
+        for child in children(node) {
+            visit(child, depth + 1, out);
+        }
+    }
+    let mut out = String::new();
+    visit(root, 0, &mut out);
+    out
+}
+
+/// `(type @start-end child...)`, with the text of leaves as a string after the location
+fn render_sexpr(root: &dyn AstNode) -> String {
+    fn visit(node: &dyn AstNode, depth: usize, out: &mut String) {
+        let _ = write!(out, "({}", node.node_type());
+        if let Some(location) = node.location() {
+            let _ = write!(out, " @{}", format_location(location));
+        }
+        if node.child_count() == 0 {
+            if let Some(text) = node.text().filter(|text| !text.trim().is_empty()) {
+                let _ = write!(out, " {:?}", preview(text));
+            }
+        }
+        for child in children(node) {
+            let _ = write!(out, "\n{}", "  ".repeat(depth + 1));
+            visit(child, depth + 1, out);
+        }
+        out.push(')');
+    }
+    let mut out = String::new();
+    visit(root, 0, &mut out);
+    out
+}
+
+/// The node and its subtree with the full text of every node
+fn to_json(node: &dyn AstNode) -> Value {
+    let location = node.location().map(|(start_line, start_column, end_line, end_column)| {
+        json!({ "start_line": start_line, "start_column": start_column, "end_line": end_line, "end_column": end_column })
+    });
+    json!({
+        "type": node.node_type(),
+        "location": location,
+        "text": node.text(),
+        "children": children(node).map(to_json).collect::<Vec<_>>(),
+    })
+}
+
+fn children(node: &dyn AstNode) -> impl Iterator<Item = &dyn AstNode> {
+    (0..node.child_count()).filter_map(move |index| node.child(index))
+}
+
+fn format_location((start_line, start_column, end_line, end_column): (usize, usize, usize, usize)) -> String {
+    format!("{}:{}-{}:{}", start_line, start_column, end_line, end_column)
+}
+
+/// `text` on one line with its whitespace runs collapsed, cut to `TEXT_PREVIEW_CHARS`
+fn preview(text: &str) -> String {
+    let line = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if line.chars().count() > TEXT_PREVIEW_CHARS {
+        format!("{}...", line.chars().take(TEXT_PREVIEW_CHARS).collect::<String>())
+    } else {
+        line
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use astgrep_ast::{NodeType, UniversalNode};
+
+    fn tree() -> UniversalNode {
+        let call = UniversalNode::new(NodeType::CallExpression)
+            .with_location(2, 5, 2, 16)
+            .with_text("eval(input)".to_string())
+            .add_child(UniversalNode::new(NodeType::Identifier).with_location(2, 5, 2, 9).with_text("eval".to_string()))
+            .add_child(UniversalNode::new(NodeType::Identifier).with_location(2, 10, 2, 15).with_text("input".to_string()));
+        UniversalNode::new(NodeType::Program)
+            .with_location(1, 1, 3, 1)
+            .with_text("function run(input) {\n    eval(input)\n}".to_string())
+            .add_child(call)
+    }
+
+    #[test]
+    fn test_render_text_and_sexpr() {
+        let root = tree();
+        let text = render_text(&root);
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "program [1:1-3:1] \"function run(input) { eval(input) }\"");
+        assert_eq!(lines[1], "  call_expression [2:5-2:16] \"eval(input)\"");
+        assert_eq!(lines[2], "    identifier [2:5-2:9] \"eval\"");
+
+        let sexpr = render_sexpr(&root);
+        assert_eq!(
+            sexpr,
+            "(program @1:1-3:1\n  (call_expression @2:5-2:16\n    (identifier @2:5-2:9 \"eval\")\n    (identifier @2:10-2:15 \"input\")))"
+        );
+    }
+
+    #[test]
+    fn test_json_keeps_full_text_and_locations() {
+        let value = to_json(&tree());
+        assert_eq!(value["type"], "program");
+        assert_eq!(value["text"], "function run(input) {\n    eval(input)\n}");
+        let call = &value["children"][0];
+        assert_eq!(call["location"]["start_column"], 5);
+        assert_eq!(call["children"][1]["text"], "input");
+        assert_eq!(call["children"][1]["children"], json!([]));
+    }
+
+    #[test]
+    fn test_preview_shortens_long_text() {
+        let long = "x".repeat(TEXT_PREVIEW_CHARS + 5);
+        assert_eq!(preview(&long), format!("{}...", "x".repeat(TEXT_PREVIEW_CHARS)));
+    }
+}
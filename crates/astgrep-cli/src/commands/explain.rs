@@ -0,0 +1,400 @@
+//! Explain command for describing a single rule
+//!
+//! CI logs and SARIF viewers often show nothing but a rule id. `explain <rule-id>` looks
+//! the rule up in the configured rule paths and prints what it is for: its description,
+//! patterns, CWE/OWASP references and other metadata, embedded examples and fix guidance.
+
+use anyhow::{anyhow, Result};
+use astgrep_rules::{LoadedRule, Pattern, PatternType, Rule, RuleRepository};
+use std::path::{Path, PathBuf};
+use tracing::info;
+use crate::commands::workspace::RootConfig;
+use crate::OutputFormatCli;
+
+/// Metadata keys shown as references ahead of the other metadata
+const REFERENCE_KEYS: &[&str] = &["cwe", "owasp", "references", "source-url"];
+
+/// Similar rule ids offered when the requested one is unknown
+const MAX_SUGGESTIONS: usize = 5;
+
+/// Print the description, patterns, metadata, examples and fix guidance of `rule_id`
+pub async fn run(rule_id: String, rules: Vec<PathBuf>, format: OutputFormatCli) -> Result<()> {
+    let paths = rule_paths(rules, Path::new("."))?;
+    info!("Looking up rule {} in {} rule path(s)", rule_id, paths.len());
+
+    let repository = RuleRepository::from_paths(&paths);
+    let loaded = find_rule(&repository, &rule_id)?;
+    let output = match format {
+        OutputFormatCli::Text => render_text(loaded),
+        OutputFormatCli::Markdown => render_markdown(loaded),
+        other => return Err(anyhow!("explain supports text and markdown output, not {:?}", other)),
+    };
+    print!("{}", output);
+    Ok(())
+}
+
+/// The rule paths given on the command line, else those of `.astgrep.yml` in `root`,
+/// else the `rules` directory
+pub(crate) fn rule_paths(rules: Vec<PathBuf>, root: &Path) -> Result<Vec<PathBuf>> {
+    if !rules.is_empty() {
+        return Ok(rules);
+    }
+    if let Some(config) = RootConfig::load(root)?.filter(|config| !config.rules.is_empty()) {
+        return config.rule_paths(root);
+    }
+    let default = root.join("rules");
+    if default.is_dir() {
+        return Ok(vec![default]);
+    }
+    Err(anyhow!("No rule paths configured; pass --rules or list them in .astgrep.yml"))
+}
+
+fn find_rule<'a>(repository: &'a RuleRepository, rule_id: &str) -> Result<&'a LoadedRule> {
+    if let Some(loaded) = repository.get(rule_id) {
+        return Ok(loaded);
+    }
+    if repository.is_empty() {
+        return Err(anyhow!("Rule '{}' not found: no rules were loaded", rule_id));
+    }
+    let suggestions = similar_ids(repository.rules().map(|rule| rule.id.as_str()), rule_id);
+    if suggestions.is_empty() {
+        return Err(anyhow!("Rule '{}' not found in {} loaded rules", rule_id, repository.len()));
+    }
+    Err(anyhow!("Rule '{}' not found; did you mean: {}?", rule_id, suggestions.join(", ")))
+}
+
+/// Ids containing `wanted` or a few edits away from it, closest first
+fn similar_ids<'a>(ids: impl Iterator<Item = &'a str>, wanted: &str) -> Vec<String> {
+    let wanted = wanted.to_lowercase();
+    let max_distance = (wanted.chars().count() / 4).max(2);
+    let mut candidates: Vec<(usize, &str)> = ids
+        .filter_map(|id| {
+            let lower = id.to_lowercase();
+            let distance = if lower.contains(&wanted) { 0 } else { edit_distance(&lower, &wanted) };
+            (distance <= max_distance).then_some((distance, id))
+        })
+        .collect();
+    candidates.sort();
+    candidates.into_iter().take(MAX_SUGGESTIONS).map(|(_, id)| id.to_string()).collect()
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Metadata entries as (key, value) pairs
+type MetadataEntries<'a> = Vec<(&'a str, &'a str)>;
+
+/// References in [`REFERENCE_KEYS`] order, then the rest of the metadata sorted by key
+fn split_metadata(rule: &Rule) -> (MetadataEntries<'_>, MetadataEntries<'_>) {
+    let mut references = Vec::new();
+    for wanted in REFERENCE_KEYS {
+        if let Some((key, value)) = rule.metadata.iter().find(|(key, _)| key.eq_ignore_ascii_case(wanted)) {
+            references.push((key.as_str(), value.as_str()));
+        }
+    }
+    let mut other: Vec<(&str, &str)> = rule
+        .metadata
+        .iter()
+        .filter(|(key, _)| !REFERENCE_KEYS.iter().any(|wanted| key.eq_ignore_ascii_case(wanted)))
+        .map(|(key, value)| (key.as_str(), value.as_str()))
+        .collect();
+    other.sort();
+    (references, other)
+}
+
+/// The rule's patterns in rule-file syntax, one line each
+fn pattern_lines(patterns: &[Pattern]) -> Vec<String> {
+    let mut lines = Vec::new();
+    for pattern in patterns {
+        push_pattern(pattern, 0, &mut lines);
+    }
+    lines
+}
+
+fn push_pattern(pattern: &Pattern, depth: usize, lines: &mut Vec<String>) {
+    let indent = "  ".repeat(depth);
+    let key = pattern.key();
+    match &pattern.pattern_type {
+        PatternType::Simple(text) | PatternType::Regex(text) | PatternType::NotRegex(text) => {
+            push_scalar(&indent, key, text, lines);
+        }
+        PatternType::Inside(inner) | PatternType::NotInside(inner) | PatternType::Not(inner) => {
+            match &inner.pattern_type {
+                PatternType::Simple(text) => push_scalar(&indent, key, text, lines),
+                _ => {
+                    lines.push(format!("{}- {}:", indent, key));
+                    push_pattern(inner, depth + 1, lines);
+                }
+            }
+        }
+        PatternType::Either(children) | PatternType::All(children) | PatternType::Any(children) => {
+            lines.push(format!("{}- {}:", indent, key));
+            for child in children {
+                push_pattern(child, depth + 1, lines);
+            }
+        }
+    }
+    if let Some(focus) = &pattern.focus {
+        lines.push(format!("{}- focus-metavariable: {}", indent, focus.join(", ")));
+    }
+}
+
+/// `- key: value`, or a `|` block when the value spans lines
+fn push_scalar(indent: &str, key: &str, text: &str, lines: &mut Vec<String>) {
+    let text = text.trim_end();
+    if !text.contains('\n') {
+        lines.push(format!("{}- {}: {}", indent, key, text));
+        return;
+    }
+    lines.push(format!("{}- {}: |", indent, key));
+    lines.extend(text.lines().map(|line| format!("{}    {}", indent, line)));
+}
+
+/// How the rule's findings can be fixed
+fn fix_guidance(rule: &Rule) -> Vec<String> {
+    let mut guidance = Vec::new();
+    if let Some(fix) = &rule.fix {
+        guidance.push(format!("Autofix replaces the match with: {}", fix.trim_end()));
+    }
+    if let Some(fix_regex) = &rule.fix_regex {
+        guidance.push(format!("Autofix replaces /{}/ with: {}", fix_regex.regex, fix_regex.replacement));
+    }
+    guidance
+}
+
+fn languages(rule: &Rule) -> String {
+    rule.languages.iter().map(|language| language.as_str()).collect::<Vec<_>>().join(", ")
+}
+
+fn example_language(rule: &Rule, language: Option<astgrep_core::Language>) -> &'static str {
+    language.or_else(|| rule.languages.first().copied()).map(|l| l.as_str()).unwrap_or("")
+}
+
+fn render_text(loaded: &LoadedRule) -> String {
+    let rule = &loaded.rule;
+    let mut output = String::new();
+
+    output.push_str(&format!("{}\n", rule.id));
+    if !rule.name.is_empty() && rule.name != rule.id {
+        output.push_str(&format!("  {}\n", rule.name));
+    }
+    output.push_str(&format!("\nSeverity: {}    Confidence: {}\n", rule.severity.as_str(), rule.confidence.as_str()));
+    output.push_str(&format!("Languages: {}\n", languages(rule)));
+    output.push_str(&format!("Defined in: {}\n", loaded.origin));
+    if !rule.enabled {
+        output.push_str("Status: disabled\n");
+    }
+
+    if !rule.description.trim().is_empty() {
+        output.push_str("\nDescription:\n");
+        for line in rule.description.trim().lines() {
+            output.push_str(&format!("  {}\n", line));
+        }
+    }
+
+    let (references, metadata) = split_metadata(rule);
+    if !references.is_empty() {
+        output.push_str("\nReferences:\n");
+        for (key, value) in references {
+            output.push_str(&format!("  {}: {}\n", key.to_uppercase(), value));
+        }
+    }
+
+    let patterns = pattern_lines(&rule.patterns);
+    if !patterns.is_empty() {
+        output.push_str("\nPatterns:\n");
+        for line in patterns {
+            output.push_str(&format!("  {}\n", line));
+        }
+    }
+    if let Some(dataflow) = &rule.dataflow {
+        output.push_str("\nData flow:\n");
+        output.push_str(&format!("  Sources: {}\n", dataflow.sources.join(", ")));
+        output.push_str(&format!("  Sinks: {}\n", dataflow.sinks.join(", ")));
+        if !dataflow.sanitizers.is_empty() {
+            output.push_str(&format!("  Sanitizers: {}\n", dataflow.sanitizers.join(", ")));
+        }
+    }
+
+    if !rule.examples.is_empty() {
+        output.push_str("\nExamples:\n");
+        for example in &rule.examples {
+            let verdict = if example.should_match { "matches" } else { "does not match" };
+            output.push_str(&format!("  {} ({}):\n", verdict, example_language(rule, example.language)));
+            for line in example.code.trim_end().lines() {
+                output.push_str(&format!("    {}\n", line));
+            }
+        }
+    }
+
+    output.push_str("\nFix guidance:\n");
+    let guidance = fix_guidance(rule);
+    if guidance.is_empty() {
+        output.push_str("  No autofix; see the description and references above.\n");
+    }
+    for line in guidance {
+        output.push_str(&format!("  {}\n", line));
+    }
+
+    if !metadata.is_empty() {
+        output.push_str("\nMetadata:\n");
+        for (key, value) in metadata {
+            output.push_str(&format!("  {}: {}\n", key, value));
+        }
+    }
+
+    output
+}
+
+fn render_markdown(loaded: &LoadedRule) -> String {
+    let rule = &loaded.rule;
+    let mut output = String::new();
+
+    output.push_str(&format!("# `{}`\n\n", rule.id));
+    if !rule.name.is_empty() && rule.name != rule.id {
+        output.push_str(&format!("**{}**\n\n", rule.name));
+    }
+    output.push_str(&format!("- **Severity:** {}\n", rule.severity.as_str()));
+    output.push_str(&format!("- **Confidence:** {}\n", rule.confidence.as_str()));
+    output.push_str(&format!("- **Languages:** {}\n", languages(rule)));
+    output.push_str(&format!("- **Defined in:** `{}`\n", loaded.origin));
+    if !rule.enabled {
+        output.push_str("- **Status:** disabled\n");
+    }
+    output.push('\n');
+
+    if !rule.description.trim().is_empty() {
+        output.push_str(&format!("## Description\n\n{}\n\n", rule.description.trim()));
+    }
+
+    let (references, metadata) = split_metadata(rule);
+    if !references.is_empty() {
+        output.push_str("## References\n\n");
+        for (key, value) in references {
+            output.push_str(&format!("- **{}:** {}\n", key.to_uppercase(), value));
+        }
+        output.push('\n');
+    }
+
+    let patterns = pattern_lines(&rule.patterns);
+    if !patterns.is_empty() {
+        output.push_str(&format!("## Patterns\n\n```yaml\n{}\n```\n\n", patterns.join("\n")));
+    }
+    if let Some(dataflow) = &rule.dataflow {
+        output.push_str("## Data flow\n\n");
+        output.push_str(&format!("- **Sources:** {}\n", dataflow.sources.join(", ")));
+        output.push_str(&format!("- **Sinks:** {}\n", dataflow.sinks.join(", ")));
+        if !dataflow.sanitizers.is_empty() {
+            output.push_str(&format!("- **Sanitizers:** {}\n", dataflow.sanitizers.join(", ")));
+        }
+        output.push('\n');
+    }
+
+    if !rule.examples.is_empty() {
+        output.push_str("## Examples\n\n");
+        for example in &rule.examples {
+            let verdict = if example.should_match { "Matches" } else { "Does not match" };
+            output.push_str(&format!(
+                "{}:\n\n```{}\n{}\n```\n\n",
+                verdict,
+                example_language(rule, example.language),
+                example.code.trim_end()
+            ));
+        }
+    }
+
+    output.push_str("## Fix guidance\n\n");
+    let guidance = fix_guidance(rule);
+    if guidance.is_empty() {
+        output.push_str("No autofix; see the description and references above.\n\n");
+    } else {
+        for line in guidance {
+            output.push_str(&format!("- {}\n", line));
+        }
+        output.push('\n');
+    }
+
+    if !metadata.is_empty() {
+        output.push_str("## Metadata\n\n| Key | Value |\n|-----|-------|\n");
+        for (key, value) in metadata {
+            output.push_str(&format!("| {} | {} |\n", key, value.replace('|', "\\|")));
+        }
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RULE_YAML: &str = r#"
+rules:
+  - id: js-eval-injection
+    name: Eval with user input
+    message: Avoid eval
+    description: Passing untrusted input to eval executes arbitrary code.
+    severity: ERROR
+    languages: [javascript]
+    patterns:
+      - pattern: eval($X)
+      - pattern-not: eval("...")
+    fix: JSON.parse($X)
+    examples:
+      positive:
+        - eval(input)
+      negative:
+        - eval("1 + 1")
+    metadata:
+      cwe: "CWE-95: Eval Injection"
+      owasp: "A03:2021 - Injection"
+      category: security
+"#;
+
+    fn repository() -> RuleRepository {
+        let mut repository = RuleRepository::new();
+        repository.load_yaml("inline.yml", RULE_YAML).unwrap();
+        repository
+    }
+
+    #[test]
+    fn test_render_rule() {
+        let repository = repository();
+        let loaded = find_rule(&repository, "js-eval-injection").unwrap();
+
+        let text = render_text(loaded);
+        assert!(text.starts_with("js-eval-injection\n  Eval with user input\n"));
+        assert!(text.contains("  CWE: CWE-95: Eval Injection\n  OWASP: A03:2021 - Injection\n"));
+        assert!(text.contains("  - pattern: eval($X)\n  - pattern-not: eval(\"...\")\n"));
+        assert!(text.contains("  matches (javascript):\n    eval(input)\n"));
+        assert!(text.contains("  does not match (javascript):\n    eval(\"1 + 1\")\n"));
+        assert!(text.contains("Autofix replaces the match with: JSON.parse($X)"));
+        assert!(text.contains("Metadata:\n  category: security\n"));
+
+        let markdown = render_markdown(loaded);
+        assert!(markdown.starts_with("# `js-eval-injection`\n"));
+        assert!(markdown.contains("- **CWE:** CWE-95: Eval Injection\n"));
+        assert!(markdown.contains("```yaml\n- pattern: eval($X)\n"));
+        assert!(markdown.contains("| category | security |\n"));
+    }
+
+    #[test]
+    fn test_unknown_rule_suggests_similar_ids() {
+        let repository = repository();
+        let error = find_rule(&repository, "js-eval-injecton").unwrap_err().to_string();
+        assert!(error.contains("did you mean: js-eval-injection?"), "{}", error);
+        let error = find_rule(&repository, "python-sqli").unwrap_err().to_string();
+        assert!(error.contains("not found in 1 loaded rules"), "{}", error);
+    }
+}
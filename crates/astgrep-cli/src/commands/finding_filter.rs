@@ -0,0 +1,458 @@
+//! Finding filter expressions
+//!
+//! `--filter` keeps the findings an expression holds for, after the severity, confidence
+//! and risk score filters, e.g. `severity>=error && rule_id=~"sql" && path!~"tests/"`.
+//!
+//! A comparison is a field, an operator and a value. `severity`, `confidence`, `effort`,
+//! `line` and `risk_score` are ordered and take `==`, `!=`, `<`, `<=`, `>` and `>=`;
+//! `rule_id`, `message`, `path`, `category` and `layer` are text and take `==`, `!=`, `=~`
+//! (matches the regex) and `!~`; `is_test` is `true` or `false`, and on its own means
+//! `is_test==true`. Values are bare words or double-quoted strings with `\"` and `\\`
+//! escapes. Comparisons combine with `&&`, `||`, `!` and parentheses, `&&` binding
+//! tighter than `||`. A finding without a category, layer or effort has none of them: only
+//! `!=` and `!~` hold for it. A missing risk score is 0, as for `--min-risk-score`.
+
+use anyhow::{anyhow, Result};
+use astgrep_core::{Confidence, Severity};
+use regex::Regex;
+use std::cmp::Ordering;
+
+use crate::commands::analyze_enhanced::Finding;
+use crate::commands::risk::Effort;
+
+/// A parsed `--filter` expression
+#[derive(Debug, Clone)]
+pub struct FindingFilter {
+    expr: Expr,
+}
+
+impl FindingFilter {
+    pub fn parse(source: &str) -> Result<Self> {
+        let mut parser = Parser { tokens: tokenize(source)?, position: 0 };
+        let expr = parser.or()?;
+        match parser.tokens.get(parser.position) {
+            None => Ok(Self { expr }),
+            Some(token) => Err(anyhow!("unexpected {} after a complete expression", token)),
+        }
+    }
+
+    /// Whether the expression holds for `finding`
+    pub fn matches(&self, finding: &Finding) -> bool {
+        self.expr.eval(finding)
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Compare { field: Field, op: Op, value: Value },
+}
+
+impl Expr {
+    fn eval(&self, finding: &Finding) -> bool {
+        match self {
+            Expr::And(left, right) => left.eval(finding) && right.eval(finding),
+            Expr::Or(left, right) => left.eval(finding) || right.eval(finding),
+            Expr::Not(inner) => !inner.eval(finding),
+            Expr::Compare { field, op, value } => match value {
+                Value::Rank(expected) => match field.rank(finding) {
+                    Some(actual) => op.holds(actual.cmp(expected)),
+                    None => *op == Op::Ne,
+                },
+                Value::Text(expected) => match field.text(finding) {
+                    Some(actual) => op.holds(actual.as_str().cmp(expected.as_str())),
+                    None => *op == Op::Ne,
+                },
+                Value::Regex(regex) => match field.text(finding) {
+                    Some(actual) => regex.is_match(&actual) == (*op == Op::Matches),
+                    None => *op == Op::NotMatches,
+                },
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Severity,
+    Confidence,
+    Effort,
+    Line,
+    RiskScore,
+    IsTest,
+    RuleId,
+    Message,
+    Path,
+    Category,
+    Layer,
+}
+
+impl Field {
+    fn parse(name: &str) -> Result<Self> {
+        Ok(match name {
+            "severity" => Field::Severity,
+            "confidence" => Field::Confidence,
+            "effort" => Field::Effort,
+            "line" => Field::Line,
+            "risk_score" => Field::RiskScore,
+            "is_test" => Field::IsTest,
+            "rule_id" => Field::RuleId,
+            "message" => Field::Message,
+            "path" => Field::Path,
+            "category" => Field::Category,
+            "layer" => Field::Layer,
+            _ => {
+                return Err(anyhow!(
+                    "unknown field '{}'; expected severity, confidence, effort, line, risk_score, is_test, rule_id, message, path, category or layer",
+                    name
+                ))
+            }
+        })
+    }
+
+    fn is_text(self) -> bool {
+        matches!(self, Field::RuleId | Field::Message | Field::Path | Field::Category | Field::Layer)
+    }
+
+    /// Position of the finding's value in the order of an ordered field
+    fn rank(self, finding: &Finding) -> Option<u64> {
+        match self {
+            Field::Severity => Some(finding.severity as u64),
+            Field::Confidence => Some(finding.confidence as u64),
+            Field::Effort => finding.effort.map(|effort| effort as u64),
+            Field::Line => Some(finding.location.start_line as u64),
+            Field::RiskScore => Some(u64::from(finding.risk_score.unwrap_or(0))),
+            Field::IsTest => Some(u64::from(finding.is_test)),
+            _ => None,
+        }
+    }
+
+    fn text(self, finding: &Finding) -> Option<String> {
+        match self {
+            Field::RuleId => Some(finding.rule_id.clone()),
+            Field::Message => Some(finding.message.clone()),
+            Field::Path => Some(finding.location.file.display().to_string()),
+            Field::Category => finding.category.clone(),
+            Field::Layer => finding.layer.clone(),
+            _ => None,
+        }
+    }
+
+    /// Rank of `word` as a value of an ordered field
+    fn parse_rank(self, word: &str) -> Result<u64> {
+        let rank = match self {
+            Field::Severity => [Severity::Info, Severity::Warning, Severity::Error, Severity::Critical]
+                .into_iter()
+                .find(|severity| severity.as_str().eq_ignore_ascii_case(word))
+                .map(|severity| severity as u64),
+            Field::Confidence => [Confidence::Low, Confidence::Medium, Confidence::High]
+                .into_iter()
+                .find(|confidence| confidence.as_str().eq_ignore_ascii_case(word))
+                .map(|confidence| confidence as u64),
+            Field::Effort => [Effort::Trivial, Effort::Small, Effort::Medium, Effort::Large]
+                .into_iter()
+                .find(|effort| effort.as_str().eq_ignore_ascii_case(word))
+                .map(|effort| effort as u64),
+            Field::IsTest => match word {
+                "true" => Some(1),
+                "false" => Some(0),
+                _ => None,
+            },
+            _ => word.parse().ok(),
+        };
+        rank.ok_or_else(|| anyhow!("invalid {} value '{}'", self.name(), word))
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Field::Severity => "severity",
+            Field::Confidence => "confidence",
+            Field::Effort => "effort",
+            Field::Line => "line",
+            Field::RiskScore => "risk_score",
+            Field::IsTest => "is_test",
+            Field::RuleId => "rule_id",
+            Field::Message => "message",
+            Field::Path => "path",
+            Field::Category => "category",
+            Field::Layer => "layer",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Matches,
+    NotMatches,
+}
+
+impl Op {
+    fn holds(self, ordering: Ordering) -> bool {
+        match self {
+            Op::Eq => ordering == Ordering::Equal,
+            Op::Ne => ordering != Ordering::Equal,
+            Op::Lt => ordering == Ordering::Less,
+            Op::Le => ordering != Ordering::Greater,
+            Op::Gt => ordering == Ordering::Greater,
+            Op::Ge => ordering != Ordering::Less,
+            Op::Matches | Op::NotMatches => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Value {
+    Rank(u64),
+    Text(String),
+    Regex(Regex),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Quoted(String),
+    Op(Op),
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Word(word) => write!(f, "'{}'", word),
+            Token::Quoted(text) => write!(f, "\"{}\"", text),
+            Token::Op(op) => write!(f, "operator {:?}", op),
+            Token::And => write!(f, "'&&'"),
+            Token::Or => write!(f, "'||'"),
+            Token::Not => write!(f, "'!'"),
+            Token::Open => write!(f, "'('"),
+            Token::Close => write!(f, "')'"),
+        }
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        let two: String = chars.clone().take(2).collect();
+        let token = match two.as_str() {
+            "&&" => Some(Token::And),
+            "||" => Some(Token::Or),
+            "==" => Some(Token::Op(Op::Eq)),
+            "!=" => Some(Token::Op(Op::Ne)),
+            "<=" => Some(Token::Op(Op::Le)),
+            ">=" => Some(Token::Op(Op::Ge)),
+            "=~" => Some(Token::Op(Op::Matches)),
+            "!~" => Some(Token::Op(Op::NotMatches)),
+            _ => None,
+        };
+        if let Some(token) = token {
+            chars.nth(1);
+            tokens.push(token);
+            continue;
+        }
+        chars.next();
+        tokens.push(match c {
+            '=' => Token::Op(Op::Eq),
+            '<' => Token::Op(Op::Lt),
+            '>' => Token::Op(Op::Gt),
+            '!' => Token::Not,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '"' => {
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(escaped @ ('"' | '\\')) => text.push(escaped),
+                            Some(other) => {
+                                text.push('\\');
+                                text.push(other);
+                            }
+                            None => return Err(anyhow!("unterminated string")),
+                        },
+                        Some(other) => text.push(other),
+                        None => return Err(anyhow!("unterminated string")),
+                    }
+                }
+                Token::Quoted(text)
+            }
+            '&' | '|' | '~' => return Err(anyhow!("unexpected '{}'; use '&&', '||', '=~' or '!~'", c)),
+            _ => {
+                let mut word = c.to_string();
+                while let Some(&next) = chars.peek() {
+                    if next.is_whitespace() || "()&|!=<>~\"".contains(next) {
+                        break;
+                    }
+                    word.push(next);
+                    chars.next();
+                }
+                Token::Word(word)
+            }
+        });
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        let found = self.tokens.get(self.position) == Some(token);
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn or(&mut self) -> Result<Expr> {
+        let mut expr = self.and()?;
+        while self.eat(&Token::Or) {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr> {
+        let mut expr = self.unary()?;
+        while self.eat(&Token::And) {
+            expr = Expr::And(Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        match self.next() {
+            Some(Token::Not) => Ok(Expr::Not(Box::new(self.unary()?))),
+            Some(Token::Open) => {
+                let expr = self.or()?;
+                if !self.eat(&Token::Close) {
+                    return Err(anyhow!("missing ')'"));
+                }
+                Ok(expr)
+            }
+            Some(Token::Word(name)) => self.comparison(Field::parse(&name)?),
+            Some(token) => Err(anyhow!("expected a field, found {}", token)),
+            None => Err(anyhow!("expected a field, found the end of the filter")),
+        }
+    }
+
+    fn comparison(&mut self, field: Field) -> Result<Expr> {
+        let op = match self.tokens.get(self.position) {
+            Some(Token::Op(op)) => {
+                self.position += 1;
+                *op
+            }
+            _ if field == Field::IsTest => return Ok(Expr::Compare { field, op: Op::Eq, value: Value::Rank(1) }),
+            _ => return Err(anyhow!("expected an operator after '{}'", field.name())),
+        };
+        let word = match self.next() {
+            Some(Token::Word(word)) | Some(Token::Quoted(word)) => word,
+            Some(token) => return Err(anyhow!("expected a value after '{}', found {}", field.name(), token)),
+            None => return Err(anyhow!("expected a value after '{}'", field.name())),
+        };
+
+        let value = match op {
+            Op::Matches | Op::NotMatches if field.is_text() => {
+                Value::Regex(Regex::new(&word).map_err(|e| anyhow!("invalid regex for {}: {}", field.name(), e))?)
+            }
+            Op::Matches | Op::NotMatches => return Err(anyhow!("{} is not text; '=~' and '!~' only apply to text fields", field.name())),
+            Op::Eq | Op::Ne if field.is_text() => Value::Text(word),
+            _ if field.is_text() => return Err(anyhow!("{} is text; compare it with '==', '!=', '=~' or '!~'", field.name())),
+            Op::Lt | Op::Le | Op::Gt | Op::Ge if field == Field::IsTest => {
+                return Err(anyhow!("is_test is true or false; compare it with '==' or '!='"))
+            }
+            _ => Value::Rank(field.parse_rank(&word)?),
+        };
+        Ok(Expr::Compare { field, op, value })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::analyze_enhanced::test_finding;
+
+    fn finding(rule_id: &str, severity: Severity, file: &str) -> Finding {
+        Finding {
+            message: "User input reaches a \"query\"".to_string(),
+            severity,
+            confidence: Confidence::Medium,
+            category: Some("security".to_string()),
+            is_test: file.contains("tests/"),
+            risk_score: Some(70),
+            effort: Some(Effort::Small),
+            ..test_finding(rule_id, file, 12)
+        }
+    }
+
+    fn matches(filter: &str, finding: &Finding) -> bool {
+        FindingFilter::parse(filter).unwrap().matches(finding)
+    }
+
+    #[test]
+    fn test_filter_expressions() {
+        let sql = finding("java-sql-injection", Severity::Error, "src/Dao.java");
+        let sql_test = finding("java-sql-injection", Severity::Error, "src/tests/DaoTest.java");
+        let weak = finding("java-weak-hash", Severity::Warning, "src/Hash.java");
+
+        let filter = r#"severity>=error && rule_id=~"sql" && path!~"tests/""#;
+        assert!(matches(filter, &sql));
+        assert!(!matches(filter, &sql_test));
+        assert!(!matches(filter, &weak));
+
+        assert!(matches("severity < ERROR || !(confidence == high)", &weak));
+        assert!(!matches("severity>=critical", &sql));
+        assert!(matches("effort<=small && risk_score>60 && line=12", &sql));
+        assert!(matches("is_test && category==security", &sql_test));
+        assert!(!matches("is_test==false || rule_id!=java-sql-injection", &sql_test));
+        assert!(matches(r#"message == "User input reaches a \"query\"""#, &weak));
+        // No layer: only the negative operators hold
+        assert!(matches("layer!=web && layer!~web", &weak));
+        assert!(!matches("layer=~\".*\" || layer==\"\"", &weak));
+        // && binds tighter than ||
+        assert!(matches("severity==warning || severity==error && is_test", &weak));
+        assert!(!matches("(severity==warning || severity==error) && is_test", &weak));
+    }
+
+    #[test]
+    fn test_filter_errors() {
+        let error = |filter: &str| FindingFilter::parse(filter).unwrap_err().to_string();
+        assert!(error("sevrity>=error").contains("unknown field 'sevrity'"));
+        assert!(error("severity>=fatal").contains("invalid severity value"));
+        assert!(error("severity=~err").contains("only apply to text fields"));
+        assert!(error("rule_id>sql").contains("is text"));
+        assert!(error("rule_id=~\"(\"").contains("invalid regex"));
+        assert!(error("path=~\"src").contains("unterminated string"));
+        assert!(error("severity>=error & is_test").contains("use '&&'"));
+        assert!(error("(is_test").contains("missing ')'"));
+        assert!(error("is_test rule_id==x").contains("after a complete expression"));
+        assert!(error("").contains("end of the filter"));
+    }
+}
@@ -0,0 +1,153 @@
+//! Finding fingerprints
+//!
+//! Every finding of a run is given a fingerprint, a hash of the rule id, the normalized
+//! file path, the source lines of the match and its structural context: the closest line
+//! above the match that is indented less, usually the header of the enclosing function,
+//! class or block. Line numbers are left out, so a finding keeps its fingerprint when code
+//! above it moves, while the same code in another function gets another one; identical
+//! matches in the same context are told apart by their order.
+//!
+//! The matching paths of the engine can report one match more than once, e.g. from the
+//! source scan and from the node text fallback. Findings with the same fingerprint whose
+//! locations overlap are such duplicates and only the first is kept.
+
+use crate::commands::analyze_enhanced::{Finding, Location};
+use crate::commands::baseline::{display_path, fnv1a};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Drop findings that repeat an earlier one: same fingerprint, overlapping location.
+/// Returns the findings left and how many were dropped.
+pub fn dedup(findings: Vec<Finding>) -> (Vec<Finding>, usize) {
+    let keys = keys(&findings, |_| None);
+    let total = findings.len();
+    let mut kept: HashMap<&str, Vec<usize>> = HashMap::new();
+    let mut unique = Vec::with_capacity(total);
+    for (i, finding) in findings.iter().enumerate() {
+        let earlier = kept.entry(keys[i].as_str()).or_default();
+        if earlier.iter().any(|&j| overlaps(&findings[j].location, &finding.location)) {
+            continue;
+        }
+        earlier.push(i);
+        unique.push(i);
+    }
+    let dropped = total - unique.len();
+    let mut findings: Vec<Option<Finding>> = findings.into_iter().map(Some).collect();
+    (unique.into_iter().filter_map(|i| findings[i].take()).collect(), dropped)
+}
+
+/// Set the fingerprint of every finding. `reported_path` gives the path a file is reported
+/// under when it is read from elsewhere, as for files extracted from container images.
+pub fn assign(findings: &mut [Finding], reported_path: impl Fn(&Path) -> Option<PathBuf>) {
+    let keys = keys(findings, reported_path);
+    // Identical matches are numbered in line order, so removing the second of three
+    // keeps the first and gives the third the fingerprint of the second
+    let mut order: Vec<usize> = (0..findings.len()).collect();
+    order.sort_by_key(|&i| (findings[i].location.start_line, findings[i].location.start_column));
+    let mut occurrences: HashMap<&str, usize> = HashMap::new();
+    for i in order {
+        let occurrence = occurrences.entry(keys[i].as_str()).or_insert(0);
+        findings[i].fingerprint = Some(fnv1a(&format!("{}|{}", keys[i], occurrence)));
+        *occurrence += 1;
+    }
+}
+
+/// What a finding's fingerprint is made of, without its occurrence number
+fn keys(findings: &[Finding], reported_path: impl Fn(&Path) -> Option<PathBuf>) -> Vec<String> {
+    let mut sources: HashMap<PathBuf, Option<String>> = HashMap::new();
+    findings
+        .iter()
+        .map(|finding| {
+            let file = &finding.location.file;
+            let source = sources.entry(file.clone()).or_insert_with(|| std::fs::read_to_string(file).ok());
+            let anchor = source
+                .as_deref()
+                .and_then(|source| anchor(source, &finding.location))
+                .unwrap_or_else(|| format!("line {}", finding.location.start_line));
+            let path = reported_path(file).unwrap_or_else(|| file.clone());
+            format!("{}|{}|{}", finding.rule_id, display_path(&path), anchor)
+        })
+        .collect()
+}
+
+/// The structural context and the matched lines of `location`, whitespace collapsed
+fn anchor(source: &str, location: &Location) -> Option<String> {
+    let lines: Vec<&str> = source.lines().collect();
+    let start = location.start_line.checked_sub(1).filter(|&start| start < lines.len())?;
+    let end = location.end_line.max(location.start_line).min(lines.len());
+    let matched = collapse(&lines[start..end].join(" "));
+    let indent = indentation(lines[start]);
+    let context = lines[..start]
+        .iter()
+        .rev()
+        .find(|line| !line.trim().is_empty() && indentation(line) < indent)
+        .map(|line| collapse(line))
+        .unwrap_or_default();
+    Some(format!("{}|{}", context, matched))
+}
+
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+fn collapse(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn overlaps(a: &Location, b: &Location) -> bool {
+    (a.start_line, a.start_column) <= (b.end_line, b.end_column) && (b.start_line, b.start_column) <= (a.end_line, a.end_column)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::analyze_enhanced::{test_finding, Location};
+
+    fn finding(file: &Path, rule_id: &str, (start_line, start_column): (usize, usize), (end_line, end_column): (usize, usize)) -> Finding {
+        Finding { location: Location::new(file, start_line, start_column, end_line, end_column), ..test_finding(rule_id, file, start_line) }
+    }
+
+    fn fingerprints(findings: &[Finding]) -> Vec<String> {
+        let mut findings = findings.to_vec();
+        assign(&mut findings, |_| None);
+        findings.into_iter().map(|f| f.fingerprint.unwrap()).collect()
+    }
+
+    #[test]
+    fn test_fingerprints_follow_code_and_context() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("app.py");
+        std::fs::write(&file, "def a():\n    exec(x)\n\ndef b():\n    exec(x)\n    exec(x)\n").unwrap();
+        let before = fingerprints(&[finding(&file, "py-exec", (2, 5), (2, 12)), finding(&file, "py-exec", (5, 5), (5, 12)), finding(&file, "py-exec", (6, 5), (6, 12))]);
+        assert_eq!(before.len(), 3);
+        assert_ne!(before[0], before[1], "same code in another function");
+        assert_ne!(before[1], before[2], "repeated code in one function");
+
+        // Lines added above the matches keep their fingerprints
+        std::fs::write(&file, "import os\n\ndef a():\n    exec(x)\n\ndef b():\n    exec(x)\n    exec(x)\n").unwrap();
+        let after = fingerprints(&[finding(&file, "py-exec", (4, 5), (4, 12)), finding(&file, "py-exec", (7, 5), (7, 12)), finding(&file, "py-exec", (8, 5), (8, 12))]);
+        assert_eq!(before, after);
+
+        let other_rule = fingerprints(&[finding(&file, "py-eval", (4, 5), (4, 12))]);
+        assert_ne!(other_rule[0], after[0]);
+    }
+
+    #[test]
+    fn test_dedup_merges_overlapping_reports_of_one_match() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("app.js");
+        std::fs::write(&file, "run(eval(a)); run(eval(a));\n").unwrap();
+        let findings = vec![
+            finding(&file, "js-eval", (1, 5), (1, 12)),
+            // The node text fallback reports the enclosing call
+            finding(&file, "js-eval", (1, 1), (1, 13)),
+            // The same code further along the line is another match
+            finding(&file, "js-eval", (1, 19), (1, 26)),
+            finding(&file, "js-exec", (1, 5), (1, 12)),
+        ];
+        let (unique, dropped) = dedup(findings);
+        assert_eq!(dropped, 1);
+        let starts: Vec<(&str, usize)> = unique.iter().map(|f| (f.rule_id.as_str(), f.location.start_column)).collect();
+        assert_eq!(starts, vec![("js-eval", 5), ("js-eval", 19), ("js-exec", 5)]);
+    }
+}
@@ -0,0 +1,367 @@
+//! Fmt command for canonicalizing rule files
+//!
+//! `fmt` rewrites rule YAML in one style so reviews are about rules rather than layout:
+//! rule keys in the order of [`RULE_KEYS`], two-space indentation with indented
+//! sequences, a blank line between rules, flow style for language lists and literal
+//! blocks for multi-line patterns. Files are parsed with [`RuleParser`] first and the
+//! result is checked to hold the same YAML as the input. YAML comments cannot be carried
+//! through the rewrite, so only a leading comment header is kept; files with other comments
+//! are reported and left alone. Directories are walked like `analyze` walks its targets,
+//! honoring ignore files and `--exclude` patterns.
+
+use anyhow::{anyhow, Result};
+use astgrep_rules::{RuleParser, RULE_KEYS};
+use serde_yaml::{Mapping, Value};
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+use crate::commands::ignore_file::walk_files;
+
+/// Rule keys whose scalar lists are written in flow style (`[java, python]`)
+const FLOW_KEYS: &[&str] = &["languages", "requires"];
+
+/// Key order of nested mappings, by the key holding them
+const NESTED_KEY_ORDER: &[(&str, &[&str])] = &[
+    ("fix-regex", &["regex", "replacement", "count"]),
+    ("paths", &["include", "exclude"]),
+    ("dataflow", &["sources", "sinks", "sanitizers", "must_flow", "max_depth"]),
+];
+
+/// What formatting a rule file would do
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FormatOutcome {
+    /// The file is already formatted
+    Unchanged,
+    /// The file's formatted content
+    Reformatted(String),
+    /// The file has comments other than a leading header, which formatting would drop
+    HasComments,
+}
+
+/// Format the rule files under `paths`, or with `check` only report the ones that are not
+/// formatted and fail if there are any
+pub async fn run(paths: Vec<PathBuf>, check: bool, exclude: Vec<String>, git_ignore: bool) -> Result<()> {
+    let mut files = Vec::new();
+    for path in &paths {
+        collect_rule_files(path, &exclude, git_ignore, &mut files)?;
+    }
+    if files.is_empty() {
+        return Err(anyhow!("No rule files (.yaml, .yml) found"));
+    }
+    info!("Formatting {} rule file(s)", files.len());
+
+    let mut unformatted = 0;
+    let mut failed = 0;
+    for file in &files {
+        let outcome = std::fs::read_to_string(file)
+            .map_err(anyhow::Error::from)
+            .and_then(|text| format_rule_file(&text));
+        match outcome {
+            Ok(FormatOutcome::Unchanged) => {}
+            Ok(FormatOutcome::Reformatted(formatted)) => {
+                unformatted += 1;
+                if check {
+                    println!("Would reformat {}", file.display());
+                } else {
+                    std::fs::write(file, formatted)?;
+                    println!("Formatted {}", file.display());
+                }
+            }
+            Ok(FormatOutcome::HasComments) => {
+                warn!("Left {} as is: formatting would drop its comments", file.display());
+            }
+            Err(e) => {
+                failed += 1;
+                eprintln!("❌ {}: {}", file.display(), e);
+            }
+        }
+    }
+
+    if failed > 0 {
+        return Err(anyhow!("{} rule file(s) could not be formatted", failed));
+    }
+    if check && unformatted > 0 {
+        return Err(anyhow!("{} of {} rule file(s) need formatting; run 'astgrep fmt'", unformatted, files.len()));
+    }
+    if !check {
+        println!("{} of {} rule file(s) reformatted", unformatted, files.len());
+    }
+    Ok(())
+}
+
+/// `path` itself if it is a file, else the rule files under it
+fn collect_rule_files(path: &Path, exclude: &[String], git_ignore: bool, files: &mut Vec<PathBuf>) -> Result<()> {
+    if path.is_file() {
+        files.push(path.to_path_buf());
+    } else if path.is_dir() {
+        let rule_files = walk_files(path, git_ignore, exclude)?;
+        files.extend(rule_files.into_iter().filter(|file| file.extension().is_some_and(|ext| ext == "yaml" || ext == "yml")));
+    } else {
+        return Err(anyhow!("Path does not exist: {}", path.display()));
+    }
+    Ok(())
+}
+
+/// Canonical form of the rule file `text`
+pub fn format_rule_file(text: &str) -> Result<FormatOutcome> {
+    RuleParser::new().parse_yaml(text)?;
+    let (header, body) = split_header(text);
+    if has_comments(body) {
+        return Ok(FormatOutcome::HasComments);
+    }
+
+    let mut value: Value = serde_yaml::from_str(text)?;
+    canonicalize(&mut value);
+    let mut formatted = header;
+    write_block(&mut formatted, &value, 0, true);
+
+    // Mappings compare regardless of key order, so this only fails if the writer lost data
+    let reparsed: Value = serde_yaml::from_str(&formatted)
+        .map_err(|e| anyhow!("formatting produced invalid YAML: {}", e))?;
+    if reparsed != value {
+        return Err(anyhow!("formatting would change the rules; please report this file"));
+    }
+
+    if formatted == text {
+        Ok(FormatOutcome::Unchanged)
+    } else {
+        Ok(FormatOutcome::Reformatted(formatted))
+    }
+}
+
+/// The leading comment lines of `text`, followed by a blank line, and the rest of it
+fn split_header(text: &str) -> (String, &str) {
+    let mut end = 0;
+    for line in text.split_inclusive('\n') {
+        let trimmed = line.trim();
+        if !trimmed.is_empty() && !trimmed.starts_with('#') {
+            break;
+        }
+        end += line.len();
+    }
+    let header = text[..end].trim();
+    let header = if header.is_empty() { String::new() } else { format!("{}\n\n", header) };
+    (header, &text[end..])
+}
+
+/// Whether `body` has a YAML comment; `#` lines inside block scalars are content
+fn has_comments(body: &str) -> bool {
+    let mut block_parent: Option<usize> = None;
+    for line in body.lines() {
+        let trimmed = line.trim_start();
+        let indent = line.len() - trimmed.len();
+        if let Some(parent) = block_parent {
+            if trimmed.is_empty() || indent > parent {
+                continue;
+            }
+            block_parent = None;
+        }
+        if trimmed.starts_with('#') || has_trailing_comment(trimmed) {
+            return true;
+        }
+        if opens_block_scalar(trimmed) {
+            block_parent = Some(indent);
+        }
+    }
+    false
+}
+
+/// Whether `line` has a ` #` comment outside quotes
+fn has_trailing_comment(line: &str) -> bool {
+    let mut quote: Option<char> = None;
+    let mut previous = ' ';
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match quote {
+            Some('"') if c == '\\' => {
+                chars.next();
+            }
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if (c == '"' || c == '\'') && (previous.is_whitespace() || matches!(previous, ':' | '-' | '[' | '{' | ',')) => quote = Some(c),
+            None if c == '#' && previous.is_whitespace() => return true,
+            None => {}
+        }
+        previous = c;
+    }
+    false
+}
+
+/// Whether `line` ends with a block scalar indicator (`|`, `>-`, `|2+`, ...)
+fn opens_block_scalar(line: &str) -> bool {
+    let indicator_start = match line.rfind(['|', '>']) {
+        Some(start) => start,
+        None => return false,
+    };
+    let (before, indicator) = line.split_at(indicator_start);
+    indicator[1..].chars().all(|c| c == '-' || c == '+' || c.is_ascii_digit())
+        && (before.is_empty() || before.ends_with(": ") || before.ends_with("- ") || before.trim_end() == "-")
+}
+
+/// Order rule keys by [`RULE_KEYS`] and known nested mappings by [`NESTED_KEY_ORDER`];
+/// other keys keep their order after the known ones
+fn canonicalize(value: &mut Value) {
+    let Some(file) = value.as_mapping_mut() else { return };
+    reorder(file, &["rules"]);
+    let Some(rules) = file.get_mut("rules").and_then(Value::as_sequence_mut) else { return };
+    for rule in rules.iter_mut().filter_map(Value::as_mapping_mut) {
+        reorder(rule, RULE_KEYS);
+        for (key, order) in NESTED_KEY_ORDER {
+            if let Some(nested) = rule.get_mut(*key).and_then(Value::as_mapping_mut) {
+                reorder(nested, order);
+            }
+        }
+    }
+}
+
+fn reorder(mapping: &mut Mapping, order: &[&str]) {
+    let position = |key: &Value| key.as_str().and_then(|key| order.iter().position(|known| *known == key)).unwrap_or(order.len());
+    let mut entries: Vec<(Value, Value)> = std::mem::take(mapping).into_iter().collect();
+    entries.sort_by_key(|(key, _)| position(key));
+    mapping.extend(entries);
+}
+
+/// Write a mapping or sequence on its own lines at `indent`; `top` separates rules with
+/// blank lines
+fn write_block(out: &mut String, value: &Value, indent: usize, top: bool) {
+    let pad = " ".repeat(indent);
+    match value {
+        Value::Mapping(mapping) => {
+            for (key, value) in mapping {
+                let key_text = scalar(key);
+                out.push_str(&format!("{}{}:", pad, key_text));
+                let flow = key.as_str().is_some_and(|key| FLOW_KEYS.contains(&key));
+                write_value(out, value, indent + 2, flow, top && key.as_str() == Some("rules"));
+            }
+        }
+        Value::Sequence(items) => {
+            for (i, item) in items.iter().enumerate() {
+                if top && i > 0 {
+                    out.push('\n');
+                }
+                write_item(out, item, indent);
+            }
+        }
+        other => {
+            out.push_str(&pad);
+            out.push_str(&scalar(other));
+            out.push('\n');
+        }
+    }
+}
+
+/// Write the value of a key that was just written, without a newline, at `indent`
+fn write_value(out: &mut String, value: &Value, indent: usize, flow: bool, rules: bool) {
+    match value {
+        Value::Mapping(mapping) if !mapping.is_empty() => {
+            out.push('\n');
+            write_block(out, value, indent, false);
+        }
+        Value::Sequence(items) if flow && items.iter().all(is_inline_scalar) => {
+            let items: Vec<String> = items.iter().map(scalar).collect();
+            out.push_str(&format!(" [{}]\n", items.join(", ")));
+        }
+        Value::Sequence(items) if !items.is_empty() => {
+            out.push('\n');
+            write_block(out, value, indent, rules);
+        }
+        Value::String(text) if is_block_string(text) => write_literal(out, text, indent),
+        other => out.push_str(&format!(" {}\n", scalar(other))),
+    }
+}
+
+/// Write one `- ` sequence item at `indent`
+fn write_item(out: &mut String, item: &Value, indent: usize) {
+    let pad = " ".repeat(indent);
+    match item {
+        Value::Mapping(mapping) if !mapping.is_empty() => {
+            // The first key goes on the dash line
+            let mut nested = String::new();
+            write_block(&mut nested, item, indent + 2, false);
+            out.push_str(&pad);
+            out.push_str("- ");
+            out.push_str(&nested[indent + 2..]);
+        }
+        Value::Sequence(items) if !items.is_empty() => {
+            let mut nested = String::new();
+            write_block(&mut nested, item, indent + 2, false);
+            out.push_str(&pad);
+            out.push_str("- ");
+            out.push_str(&nested[indent + 2..]);
+        }
+        Value::String(text) if is_block_string(text) => {
+            out.push_str(&format!("{}-", pad));
+            write_literal(out, text, indent + 2);
+        }
+        other => out.push_str(&format!("{}- {}\n", pad, scalar(other))),
+    }
+}
+
+/// Write ` |` and the lines of `text` at `indent`
+fn write_literal(out: &mut String, text: &str, indent: usize) {
+    let chomping = match text.len() - text.trim_end_matches('\n').len() {
+        0 => "-",
+        1 => "",
+        _ => "+",
+    };
+    out.push_str(&format!(" |{}\n", chomping));
+    let pad = " ".repeat(indent);
+    for line in text.trim_end_matches('\n').split('\n') {
+        if line.is_empty() {
+            out.push('\n');
+        } else {
+            out.push_str(&format!("{}{}\n", pad, line));
+        }
+    }
+    for _ in 1..text.len() - text.trim_end_matches('\n').len() {
+        out.push('\n');
+    }
+}
+
+/// Whether `text` is written as a literal block: it spans lines and reads back the same
+fn is_block_string(text: &str) -> bool {
+    text.trim_end_matches('\n').contains('\n')
+        && !text.starts_with([' ', '\t', '\n'])
+        && !text.contains(['\r', '\t'])
+        && !text.lines().any(|line| line.ends_with(' '))
+}
+
+fn is_inline_scalar(value: &Value) -> bool {
+    match value {
+        Value::String(text) => !text.contains('\n'),
+        Value::Null | Value::Bool(_) | Value::Number(_) => true,
+        _ => false,
+    }
+}
+
+/// A scalar, or any value that is not written in block style, on one line
+fn scalar(value: &Value) -> String {
+    match value {
+        Value::Mapping(mapping) if mapping.is_empty() => "{}".to_string(),
+        Value::Sequence(items) if items.is_empty() => "[]".to_string(),
+        Value::String(text) if text.contains('\n') => serde_json::to_string(text).unwrap_or_default(),
+        other => serde_yaml::to_string(other).unwrap_or_default().trim_end().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MESSY: &str = "# Security rules\n# Owner: appsec\n\nrules:\n- severity: ERROR\n  languages:\n  - python\n  id: py-eval\n  message: Avoid eval\n  metadata:\n    cwe: \"CWE-95\"\n  patterns:\n     - pattern: \"eval($X)\"\n     - pattern-not: 'eval(\"1\")'\n- id: py-exec\n  pattern: \"def f():\\n    exec($X)\\n\"\n  languages: [python]\n  severity: WARNING\n  message: Avoid exec\n";
+
+    const FORMATTED: &str = "# Security rules\n# Owner: appsec\n\nrules:\n  - id: py-eval\n    message: Avoid eval\n    severity: ERROR\n    languages: [python]\n    patterns:\n      - pattern: eval($X)\n      - pattern-not: eval(\"1\")\n    metadata:\n      cwe: CWE-95\n\n  - id: py-exec\n    message: Avoid exec\n    severity: WARNING\n    languages: [python]\n    pattern: |\n      def f():\n          exec($X)\n";
+
+    #[test]
+    fn test_format_rule_file() {
+        assert_eq!(format_rule_file(MESSY).unwrap(), FormatOutcome::Reformatted(FORMATTED.to_string()));
+        assert_eq!(format_rule_file(FORMATTED).unwrap(), FormatOutcome::Unchanged);
+
+        let commented = "rules:\n  - id: a  # the rule\n    message: m\n    severity: INFO\n    languages: [python]\n    pattern: f()\n";
+        assert_eq!(format_rule_file(commented).unwrap(), FormatOutcome::HasComments);
+        let code_comment = "rules:\n  - id: a\n    message: m\n    severity: INFO\n    languages: [python]\n    pattern: |\n      # setup\n      f()\n";
+        assert_eq!(format_rule_file(code_comment).unwrap(), FormatOutcome::Unchanged);
+
+        assert!(format_rule_file("rules: [").is_err());
+    }
+}
@@ -0,0 +1,95 @@
+//! GitHub Actions annotations
+//!
+//! `--format github-actions` writes one workflow command per finding, e.g.
+//! `::error file=src/app.js,line=3,col=5,endLine=3,endColumn=15,title=js-eval::Avoid eval`.
+//! Run as a step of a workflow, the findings show up as annotations on the lines of the
+//! pull request diff, with no SARIF upload and so no `security-events` permission needed.
+//! Critical and error findings are errors, warnings are warnings and the rest notices.
+//!
+//! Paths are made relative to `GITHUB_WORKSPACE`, as annotations need paths relative to
+//! the repository root.
+
+use astgrep_core::Severity;
+use std::path::Path;
+use crate::commands::analyze_enhanced::Finding;
+
+/// Render `findings` as workflow commands, one per line
+pub fn render(findings: &[Finding]) -> String {
+    let workspace = std::env::var_os("GITHUB_WORKSPACE");
+    let workspace = workspace.as_deref().map(Path::new);
+    findings.iter().map(|finding| annotation(finding, workspace) + "\n").collect()
+}
+
+/// The workflow command annotating `finding`
+fn annotation(finding: &Finding, workspace: Option<&Path>) -> String {
+    let command = match finding.severity {
+        Severity::Critical | Severity::Error => "error",
+        Severity::Warning => "warning",
+        Severity::Info => "notice",
+    };
+    let loc = &finding.location;
+    let mut message = finding.message.clone();
+    if let Some(ref fix) = finding.fix {
+        message.push_str(&format!("\nFix: {}", fix));
+    }
+    format!(
+        "::{} file={},line={},col={},endLine={},endColumn={},title={}::{}",
+        command,
+        escape_property(&relative_path(&loc.file, workspace)),
+        loc.start_line,
+        loc.start_column,
+        loc.end_line,
+        loc.end_column,
+        escape_property(&finding.rule_id),
+        escape_data(&message)
+    )
+}
+
+/// `file` relative to the repository checkout, without a leading `./`
+fn relative_path(file: &Path, workspace: Option<&Path>) -> String {
+    let file = workspace.and_then(|root| file.strip_prefix(root).ok()).unwrap_or(file);
+    let file = file.strip_prefix(".").unwrap_or(file);
+    file.to_string_lossy().replace('\\', "/")
+}
+
+/// The message of a workflow command, where `%`, CR and LF must be escaped
+fn escape_data(text: &str) -> String {
+    text.replace('%', "%25").replace('\r', "%0D").replace('\n', "%0A")
+}
+
+/// A property of a workflow command, which additionally must not contain `:` or `,`
+fn escape_property(text: &str) -> String {
+    escape_data(text).replace(':', "%3A").replace(',', "%2C")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::analyze_enhanced::{test_finding, Location};
+
+    fn finding(file: &str, severity: Severity, message: &str, fix: Option<&str>) -> Finding {
+        Finding {
+            message: message.to_string(),
+            severity,
+            location: Location::new(file, 3, 5, 4, 2),
+            fix: fix.map(str::to_string),
+            ..test_finding("js-eval", file, 3)
+        }
+    }
+
+    #[test]
+    fn test_annotations() {
+        let workspace = Path::new("/home/runner/work/app");
+        let error = finding("/home/runner/work/app/src/a,b.js", Severity::Critical, "Avoid eval: 100% unsafe", Some("JSON.parse(x)"));
+        assert_eq!(
+            annotation(&error, Some(workspace)),
+            "::error file=src/a%2Cb.js,line=3,col=5,endLine=4,endColumn=2,title=js-eval::Avoid eval: 100%25 unsafe%0AFix: JSON.parse(x)"
+        );
+        let notice = finding("./lib/x.js", Severity::Info, "Line one\r\nline two", None);
+        assert_eq!(
+            annotation(&notice, Some(workspace)),
+            "::notice file=lib/x.js,line=3,col=5,endLine=4,endColumn=2,title=js-eval::Line one%0D%0Aline two"
+        );
+        assert!(annotation(&finding("a.js", Severity::Warning, "m", None), None).starts_with("::warning file=a.js,"));
+    }
+}
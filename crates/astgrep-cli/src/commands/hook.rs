@@ -0,0 +1,259 @@
+//! Git hooks
+//!
+//! `install-hook` writes a pre-commit or pre-push hook into the repository's hooks
+//! directory (`core.hooksPath` when set) that runs `astgrep run-hook`. The hook analyzes
+//! only what is about to leave the developer's hands:
+//!
+//! - pre-commit analyzes the staged version of each staged file, checked out to a scratch
+//!   directory, so unstaged edits neither hide nor add findings;
+//! - pre-push analyzes the files changed since the upstream branch, or since `origin/HEAD`
+//!   for a branch without one.
+//!
+//! Hooks run with the `fast-ci` scan profile unless another is given, so no dataflow rules
+//! run and files without a rule anchor are skipped. Findings at or above `--fail-on` are
+//! printed with their fix, and the hook then fails; `--no-verify` bypasses it. Rules come
+//! from `--rules`, else `.astgrep.yml`, else the `rules` directory, as for `ci`.
+
+use anyhow::{anyhow, Result};
+use astgrep_core::{OutputFormat, Severity};
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+use crate::commands::analyze_enhanced::{run_enhanced, should_include_file, Finding};
+use crate::commands::ci::{changed_files, git};
+use crate::commands::color::ColorChoice;
+use crate::commands::scan_profile::ScanProfile;
+use crate::EnhancedAnalysisConfig;
+
+/// Line that marks a hook as written by `install-hook`, so reinstalling may replace it
+const MARKER: &str = "# Installed by astgrep install-hook";
+
+/// Git hook astgrep can run as
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookKind {
+    PreCommit,
+    PrePush,
+}
+
+impl HookKind {
+    /// File name of the hook in the hooks directory
+    pub fn name(self) -> &'static str {
+        match self {
+            HookKind::PreCommit => "pre-commit",
+            HookKind::PrePush => "pre-push",
+        }
+    }
+
+    fn action(self) -> &'static str {
+        match self {
+            HookKind::PreCommit => "commit",
+            HookKind::PrePush => "push",
+        }
+    }
+}
+
+/// Settings the installed hook passes to `run-hook`
+#[derive(Debug, Clone, PartialEq)]
+pub struct HookOptions {
+    pub rules: Vec<PathBuf>,
+    pub fail_on: Option<Severity>,
+    pub profile: String,
+}
+
+/// Install `hook` into the hooks directory of the repository in the current directory
+pub async fn install(hook: HookKind, options: HookOptions, force: bool) -> Result<()> {
+    let hooks_dir = PathBuf::from(git(&["rev-parse", "--git-path", "hooks"])?.trim());
+    let path = install_into(&hooks_dir, hook, &options, force)?;
+    println!("✅ Installed {} hook: {}", hook.name(), path.display());
+    println!("   Bypass it once with 'git {} --no-verify'", hook.action());
+    Ok(())
+}
+
+fn install_into(hooks_dir: &Path, hook: HookKind, options: &HookOptions, force: bool) -> Result<PathBuf> {
+    let path = hooks_dir.join(hook.name());
+    if let Ok(existing) = std::fs::read_to_string(&path) {
+        if !existing.contains(MARKER) && !force {
+            return Err(anyhow!("{} already exists and was not installed by astgrep; pass --force to replace it", path.display()));
+        }
+    }
+    std::fs::create_dir_all(hooks_dir)?;
+    std::fs::write(&path, hook_script(hook, options))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))?;
+    }
+    Ok(path)
+}
+
+fn hook_script(hook: HookKind, options: &HookOptions) -> String {
+    let mut command = format!("exec astgrep run-hook {}", hook.name());
+    for rule in &options.rules {
+        command.push_str(&format!(" --rules {}", shell_quote(&rule.to_string_lossy())));
+    }
+    let fail_on = options.fail_on.map_or("never".to_string(), |severity| severity.as_str().to_lowercase());
+    command.push_str(&format!(" --fail-on {} --profile {}", fail_on, shell_quote(&options.profile)));
+    format!("#!/bin/sh\n{}; remove this file to uninstall\n{}\n", MARKER, command)
+}
+
+/// `text` as a single-quoted POSIX shell word
+fn shell_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "'\\''"))
+}
+
+/// Run `hook` on the repository in the current directory, exiting with status 1 when a
+/// finding blocks the commit or push
+pub async fn run(hook: HookKind, options: HookOptions) -> Result<()> {
+    let root = Path::new(".");
+    let base = EnhancedAnalysisConfig {
+        rule_files: crate::commands::registry::Registry::from_env().resolve_all(options.rules)?,
+        languages: crate::DEFAULT_LANGUAGES.to_vec(),
+        output_format: OutputFormat::Json,
+        color: ColorChoice::Never,
+        scan_profile: Some(ScanProfile::resolve(&options.profile, Path::new(astgrep_core::constants::paths::CONFIG_FILE))?),
+        ..Default::default()
+    };
+    let mut config = crate::commands::workspace::config_for_root(&base, root)?;
+    if config.rule_files.is_empty() {
+        config.rule_files = crate::commands::explain::rule_paths(Vec::new(), root)?;
+    }
+
+    let files = match hook {
+        HookKind::PreCommit => git(&["diff", "--cached", "--name-only", "--diff-filter=ACMR", "-z"])?
+            .split('\0')
+            .filter(|path| !path.is_empty())
+            .map(PathBuf::from)
+            .collect(),
+        HookKind::PrePush => match changed_files("@{upstream}").or_else(|_| changed_files("origin/HEAD")) {
+            Ok(files) => files,
+            Err(e) => {
+                warn!("Cannot tell which files the push changes ({}); skipping analysis", e);
+                return Ok(());
+            }
+        },
+    };
+    let files: Vec<PathBuf> = files.into_iter().filter(|file| file.is_file() && should_include_file(file, &config)).collect();
+    if files.is_empty() {
+        info!("No files to analyze for the {} hook", hook.name());
+        return Ok(());
+    }
+    eprintln!("astgrep: analyzing {} file(s) before {}", files.len(), hook.action());
+
+    let scratch = tempfile::Builder::new().prefix("astgrep-hook-").tempdir()?;
+    let findings = analyze(config, hook, files, scratch.path()).await?;
+    // `process::exit` below skips destructors
+    drop(scratch);
+
+    let blocking: Vec<&Finding> = findings.iter().filter(|f| options.fail_on.is_some_and(|threshold| f.severity >= threshold)).collect();
+    if blocking.is_empty() {
+        if !findings.is_empty() {
+            eprintln!("astgrep: {} finding(s) below the blocking severity", findings.len());
+        }
+        return Ok(());
+    }
+    eprint!("{}", format_blocked(hook, &blocking));
+    std::process::exit(1);
+}
+
+/// Analyze `files` as `hook` sees them, using `scratch` for staged copies and the report
+async fn analyze(mut config: EnhancedAnalysisConfig, hook: HookKind, files: Vec<PathBuf>, scratch: &Path) -> Result<Vec<Finding>> {
+    std::fs::create_dir_all(scratch)?;
+    // The staged versions are checked out below the scratch directory under their own paths
+    let staged = scratch.join("staged");
+    config.target_paths = match hook {
+        HookKind::PreCommit => {
+            let prefix = format!("--prefix={}/", staged.display());
+            let paths: Vec<String> = files.iter().map(|file| file.to_string_lossy().into_owned()).collect();
+            let mut args = vec!["checkout-index", prefix.as_str(), "--"];
+            args.extend(paths.iter().map(String::as_str));
+            git(&args)?;
+            files.iter().map(|file| staged.join(file)).collect()
+        }
+        HookKind::PrePush => files,
+    };
+
+    let report_path = scratch.join("findings.json");
+    run_enhanced(config, Some(report_path.clone())).await?;
+    let mut findings = crate::commands::convert::Report::read(&report_path)?.findings;
+    for finding in &mut findings {
+        if let Ok(path) = finding.location.file.strip_prefix(&staged) {
+            finding.location.file = path.to_path_buf();
+        }
+    }
+    Ok(findings)
+}
+
+/// The findings that block `hook`, each with its fix when the rule has one
+fn format_blocked(hook: HookKind, findings: &[&Finding]) -> String {
+    let mut output = String::new();
+    for finding in findings {
+        output.push_str(&format!(
+            "{}:{}:{}: {} [{}] {}\n",
+            finding.location.file.display(),
+            finding.location.start_line,
+            finding.location.start_column,
+            finding.severity.as_str(),
+            finding.rule_id,
+            finding.message.replace('\n', " ")
+        ));
+        if let Some(ref fix) = finding.fix {
+            output.push_str(&format!("    fix: {}\n", fix.replace('\n', "\n         ")));
+        }
+    }
+    output.push_str(&format!(
+        "astgrep: {} blocked by {} finding(s); fix them or bypass with 'git {} --no-verify'\n",
+        hook.action(),
+        findings.len(),
+        hook.action()
+    ));
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::analyze_enhanced::{test_finding, Location};
+
+    fn options() -> HookOptions {
+        HookOptions { rules: vec![PathBuf::from("rules/it's")], fail_on: Some(Severity::Error), profile: "fast-ci".to_string() }
+    }
+
+    #[test]
+    fn test_install_writes_script_and_keeps_foreign_hooks() {
+        let dir = tempfile::tempdir().unwrap();
+        let hooks = dir.path().join("hooks");
+        let path = install_into(&hooks, HookKind::PreCommit, &options(), false).unwrap();
+        let script = std::fs::read_to_string(&path).unwrap();
+        assert!(script.starts_with("#!/bin/sh\n"));
+        assert!(script.contains("exec astgrep run-hook pre-commit --rules 'rules/it'\\''s' --fail-on error --profile 'fast-ci'"));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o111, 0o111);
+        }
+
+        // Our own hook is replaced, another tool's only with --force
+        let relaxed = HookOptions { fail_on: None, ..options() };
+        install_into(&hooks, HookKind::PreCommit, &relaxed, false).unwrap();
+        assert!(std::fs::read_to_string(&path).unwrap().contains("--fail-on never"));
+        let foreign = hooks.join("pre-push");
+        std::fs::write(&foreign, "#!/bin/sh\nmake lint\n").unwrap();
+        assert!(install_into(&hooks, HookKind::PrePush, &options(), false).unwrap_err().to_string().contains("--force"));
+        install_into(&hooks, HookKind::PrePush, &options(), true).unwrap();
+        assert!(std::fs::read_to_string(&foreign).unwrap().contains("run-hook pre-push"));
+    }
+
+    #[test]
+    fn test_blocked_findings_show_fix_hints() {
+        let finding = Finding {
+            location: Location::new("src/app.py", 3, 5, 3, 12),
+            fix: Some("ast.literal_eval(x)".to_string()),
+            ..test_finding("py-eval", "src/app.py", 3)
+        };
+        let text = format_blocked(HookKind::PreCommit, &[&finding]);
+        assert_eq!(
+            text,
+            "src/app.py:3:5: ERROR [py-eval] Avoid eval\n    fix: ast.literal_eval(x)\n\
+             astgrep: commit blocked by 1 finding(s); fix them or bypass with 'git commit --no-verify'\n"
+        );
+    }
+}
@@ -0,0 +1,345 @@
+//! Interactive HTML report
+//!
+//! `--format html` writes a single page with its styles and script inlined, so it can be
+//! archived as a CI artifact or mailed around and opened without network access. The page
+//! charts the findings by severity, rule and file, groups them per file, shows each
+//! finding's source in a collapsible snippet, and filters by severity and free text.
+
+use crate::commands::analyze_enhanced::{AnalysisStatistics, Finding};
+use astgrep_core::Severity;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::path::PathBuf;
+
+/// Lines of source shown around a finding
+const SNIPPET_CONTEXT: usize = 2;
+
+/// Bars of the rule and file charts
+const CHART_ROWS: usize = 10;
+
+const SEVERITIES: [Severity; 4] = [Severity::Critical, Severity::Error, Severity::Warning, Severity::Info];
+
+const STYLE: &str = r#"
+body { font-family: -apple-system, "Segoe UI", Arial, sans-serif; margin: 0; color: #222; background: #f6f7f9; }
+header, main { max-width: 1100px; margin: 0 auto; padding: 16px 24px; }
+h1 { margin: 8px 0 4px; }
+.muted { color: #666; }
+.cards { display: flex; flex-wrap: wrap; gap: 12px; margin: 16px 0; }
+.card { background: #fff; border: 1px solid #ddd; border-radius: 6px; padding: 10px 16px; min-width: 120px; }
+.card b { display: block; font-size: 1.6em; }
+.charts { display: grid; grid-template-columns: repeat(auto-fit, minmax(300px, 1fr)); gap: 16px; }
+.chart { background: #fff; border: 1px solid #ddd; border-radius: 6px; padding: 10px 16px; }
+.chart h3 { margin: 4px 0 8px; font-size: 1em; }
+.bar { display: grid; grid-template-columns: 40% 1fr 3em; gap: 6px; align-items: center; margin: 3px 0; font-size: 0.85em; }
+.bar span { overflow: hidden; text-overflow: ellipsis; white-space: nowrap; }
+.bar i { display: block; height: 12px; border-radius: 2px; background: #607d8b; }
+.bar em { font-style: normal; text-align: right; }
+.filters { position: sticky; top: 0; background: #f6f7f9; padding: 10px 0; display: flex; flex-wrap: wrap; gap: 14px; align-items: center; border-bottom: 1px solid #ddd; }
+.filters input[type=search] { flex: 1; min-width: 200px; padding: 4px 8px; }
+details.file { background: #fff; border: 1px solid #ddd; border-radius: 6px; margin: 12px 0; }
+details.file > summary { padding: 8px 12px; cursor: pointer; font-family: monospace; }
+.count { background: #eceff1; border-radius: 10px; padding: 0 8px; margin-left: 8px; font-family: sans-serif; font-size: 0.85em; }
+.finding { border-top: 1px solid #eee; border-left: 5px solid #607d8b; padding: 8px 12px; }
+.finding h3 { margin: 2px 0 6px; font-size: 1em; }
+.finding p { margin: 3px 0; font-size: 0.9em; }
+.critical { border-left-color: #b71c1c; } .bar i.critical { background: #b71c1c; }
+.error { border-left-color: #f44336; } .bar i.error { background: #f44336; }
+.warning { border-left-color: #ff9800; } .bar i.warning { background: #ff9800; }
+.info { border-left-color: #2196f3; } .bar i.info { background: #2196f3; }
+code { background: #f1f1f1; padding: 0 3px; }
+pre { background: #272822; color: #f8f8f2; padding: 8px 0; overflow-x: auto; margin: 6px 0; }
+pre span { display: block; padding: 0 10px; }
+pre span.hit { background: #49483e; }
+pre span::before { content: attr(data-line); display: inline-block; width: 4em; color: #75715e; }
+.hidden { display: none; }
+"#;
+
+const SCRIPT: &str = r#"
+(function () {
+  var boxes = document.querySelectorAll('.filters input[type=checkbox]');
+  var search = document.getElementById('search');
+  var shown = document.getElementById('shown');
+  function apply() {
+    var severities = {};
+    boxes.forEach(function (box) { severities[box.value] = box.checked; });
+    var needle = search.value.toLowerCase();
+    var visible = 0;
+    document.querySelectorAll('details.file').forEach(function (group) {
+      var inGroup = 0;
+      group.querySelectorAll('.finding').forEach(function (finding) {
+        var show = severities[finding.dataset.severity] && finding.dataset.search.indexOf(needle) >= 0;
+        finding.classList.toggle('hidden', !show);
+        if (show) { inGroup++; }
+      });
+      group.classList.toggle('hidden', inGroup === 0);
+      group.querySelector('.count').textContent = inGroup;
+      visible += inGroup;
+    });
+    shown.textContent = visible;
+  }
+  boxes.forEach(function (box) { box.addEventListener('change', apply); });
+  search.addEventListener('input', apply);
+})();
+"#;
+
+/// Render `findings` as a self-contained HTML page
+pub fn render(findings: &[Finding], stats: &AnalysisStatistics, total_time: std::time::Duration) -> String {
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str("<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n");
+    html.push_str("<title>astgrep Analysis Report</title>\n");
+    let _ = writeln!(html, "<style>{}</style>\n</head>\n<body>", STYLE);
+
+    html.push_str("<header>\n<h1>astgrep Analysis Report</h1>\n");
+    let _ = writeln!(html, "<p class=\"muted\">Generated on {}</p>", chrono::Utc::now().format("%Y-%m-%d %H:%M:%S UTC"));
+    html.push_str("<div class=\"cards\">\n");
+    let files_with_findings = group_by_file(findings).len();
+    let mut cards = vec![
+        ("Findings", findings.len().to_string()),
+        ("Files with findings", files_with_findings.to_string()),
+        ("Files analyzed", stats.files_analyzed.to_string()),
+        ("Rules executed", stats.rules_executed.to_string()),
+        ("Analysis time", format!("{:.2}s", total_time.as_secs_f64())),
+    ];
+    if !stats.failed_files.is_empty() {
+        cards.push(("Failed files", stats.failed_files.len().to_string()));
+    }
+    if !stats.skipped_files.is_empty() {
+        cards.push(("Skipped files", stats.skipped_files.len().to_string()));
+    }
+    for (label, value) in cards {
+        let _ = writeln!(html, "<div class=\"card\"><b>{}</b>{}</div>", escape(&value), label);
+    }
+    html.push_str("</div>\n");
+    if stats.status() != "completed" {
+        let _ = writeln!(html, "<p><strong>Partial results:</strong> the analysis status is {}.</p>", stats.status());
+    }
+    html.push_str("</header>\n<main>\n");
+
+    if findings.is_empty() {
+        html.push_str("<p>No findings.</p>\n");
+    } else {
+        render_charts(&mut html, findings);
+        render_filters(&mut html, findings);
+        render_findings(&mut html, findings);
+        let _ = writeln!(html, "<script>{}</script>", SCRIPT);
+    }
+
+    html.push_str("</main>\n</body>\n</html>\n");
+    html
+}
+
+fn render_charts(html: &mut String, findings: &[Finding]) {
+    html.push_str("<section class=\"charts\">\n");
+
+    let by_severity: Vec<(String, usize, &str)> = SEVERITIES
+        .iter()
+        .map(|severity| (severity.as_str().to_string(), findings.iter().filter(|f| f.severity == *severity).count(), severity_class(*severity)))
+        .filter(|(_, count, _)| *count > 0)
+        .collect();
+    render_chart(html, "By severity", &by_severity);
+
+    let mut rules: HashMap<&str, usize> = HashMap::new();
+    for finding in findings {
+        *rules.entry(finding.rule_id.as_str()).or_default() += 1;
+    }
+    render_chart(html, "Top rules", &top(rules.into_iter().map(|(rule, count)| (rule.to_string(), count))));
+
+    let files = group_by_file(findings).into_iter().map(|(file, group)| (file.display().to_string(), group.len()));
+    render_chart(html, "Top files", &top(files));
+
+    html.push_str("</section>\n");
+}
+
+/// The `CHART_ROWS` largest counts, largest first, as chart rows
+fn top(counts: impl Iterator<Item = (String, usize)>) -> Vec<(String, usize, &'static str)> {
+    let mut counts: Vec<(String, usize)> = counts.collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    counts.truncate(CHART_ROWS);
+    counts.into_iter().map(|(label, count)| (label, count, "")).collect()
+}
+
+/// A horizontal bar chart of `(label, count, class)` rows
+fn render_chart(html: &mut String, title: &str, rows: &[(String, usize, &str)]) {
+    let max = rows.iter().map(|(_, count, _)| *count).max().unwrap_or(1).max(1);
+    let _ = writeln!(html, "<div class=\"chart\"><h3>{}</h3>", title);
+    for (label, count, class) in rows {
+        let _ = writeln!(
+            html,
+            "<div class=\"bar\"><span title=\"{label}\">{label}</span><i class=\"{}\" style=\"width: {:.1}%\"></i><em>{}</em></div>",
+            class,
+            *count as f64 * 100.0 / max as f64,
+            count,
+            label = escape(label),
+        );
+    }
+    html.push_str("</div>\n");
+}
+
+fn render_filters(html: &mut String, findings: &[Finding]) {
+    html.push_str("<section class=\"filters\">\n");
+    for severity in SEVERITIES {
+        let count = findings.iter().filter(|f| f.severity == severity).count();
+        if count > 0 {
+            let _ = writeln!(
+                html,
+                "<label><input type=\"checkbox\" value=\"{0}\" checked> {0} ({1})</label>",
+                severity.as_str(),
+                count
+            );
+        }
+    }
+    html.push_str("<input type=\"search\" id=\"search\" placeholder=\"Filter by rule, file or message\">\n");
+    let _ = writeln!(html, "<span class=\"muted\"><span id=\"shown\">{}</span> shown</span>", findings.len());
+    html.push_str("</section>\n");
+}
+
+fn render_findings(html: &mut String, findings: &[Finding]) {
+    for (file, mut group) in group_by_file(findings) {
+        group.sort_by_key(|f| (f.location.start_line, f.location.start_column));
+        let source = std::fs::read_to_string(&file).ok();
+        let path = escape(&file.display().to_string());
+        let _ = writeln!(html, "<details class=\"file\" open>\n<summary>{}<span class=\"count\">{}</span></summary>", path, group.len());
+
+        for finding in group {
+            let loc = &finding.location;
+            let search = format!("{} {} {}", finding.rule_id, file.display(), finding.message).to_lowercase();
+            let _ = writeln!(
+                html,
+                "<div class=\"finding {}\" data-severity=\"{}\" data-search=\"{}\">",
+                severity_class(finding.severity),
+                finding.severity.as_str(),
+                escape(&search)
+            );
+            let _ = writeln!(html, "<h3>{}</h3>", escape(&finding.message));
+            let _ = writeln!(
+                html,
+                "<p><code>{}</code> · {} · confidence {} · line {}, column {}</p>",
+                escape(&finding.rule_id),
+                finding.severity.as_str(),
+                finding.confidence.as_str(),
+                loc.start_line,
+                loc.start_column
+            );
+            if let (Some(score), Some(effort)) = (finding.risk_score, finding.effort) {
+                let _ = writeln!(html, "<p><strong>Risk:</strong> {}/100 (effort: {})</p>", score, effort.as_str());
+            }
+            if let Some(ref fix) = finding.fix {
+                let _ = writeln!(html, "<p><strong>Fix:</strong> <code>{}</code></p>", escape(fix));
+            }
+            if let Some(ref fingerprint) = finding.fingerprint {
+                let _ = writeln!(html, "<p><strong>Fingerprint:</strong> <code>{}</code></p>", escape(fingerprint));
+            }
+            if let Some(snippet) = source.as_deref().and_then(|source| snippet(source, finding)) {
+                let _ = writeln!(html, "<details class=\"snippet\"><summary>Source</summary><pre>{}</pre></details>", snippet);
+            }
+            html.push_str("</div>\n");
+        }
+        html.push_str("</details>\n");
+    }
+}
+
+/// The finding's lines with `SNIPPET_CONTEXT` lines around them, as escaped and
+/// numbered HTML; matched lines are highlighted and redacted when the finding asks for it
+fn snippet(source: &str, finding: &Finding) -> Option<String> {
+    let lines: Vec<&str> = source.lines().collect();
+    let loc = &finding.location;
+    if loc.start_line == 0 || loc.start_line > lines.len() {
+        return None;
+    }
+    let first = loc.start_line.saturating_sub(SNIPPET_CONTEXT).max(1);
+    let last = (loc.end_line.max(loc.start_line) + SNIPPET_CONTEXT).min(lines.len());
+
+    let mut html = String::new();
+    for number in first..=last {
+        let matched = (loc.start_line..=loc.end_line).contains(&number);
+        let text = match finding.redaction {
+            Some(redaction) if matched => redaction.apply(lines[number - 1]),
+            _ => lines[number - 1].to_string(),
+        };
+        let class = if matched { " class=\"hit\"" } else { "" };
+        let _ = write!(html, "<span{} data-line=\"{}\">{}</span>", class, number, escape(&text));
+    }
+    Some(html)
+}
+
+/// Findings per file, in path order
+fn group_by_file(findings: &[Finding]) -> BTreeMap<PathBuf, Vec<&Finding>> {
+    let mut groups: BTreeMap<PathBuf, Vec<&Finding>> = BTreeMap::new();
+    for finding in findings {
+        groups.entry(finding.location.file.clone()).or_default().push(finding);
+    }
+    groups
+}
+
+fn severity_class(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Critical => "critical",
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+        Severity::Info => "info",
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::analyze_enhanced::{test_finding, Location};
+    use astgrep_core::Redaction;
+    use std::path::Path;
+
+    fn finding(file: &Path, rule_id: &str, severity: Severity, line: usize, message: &str) -> Finding {
+        Finding { message: message.to_string(), severity, location: Location::new(file, line, 1, line, 5), ..test_finding(rule_id, file, line) }
+    }
+
+    #[test]
+    fn test_render_groups_and_escapes() {
+        let dir = tempfile::tempdir().unwrap();
+        let app = dir.path().join("app.js");
+        std::fs::write(&app, "a();\nb();\neval(x);\nc();\nd();\ne();\n").unwrap();
+        let util = dir.path().join("util.js");
+        let findings = vec![
+            finding(&util, "js-exec", Severity::Warning, 9, "exec is risky"),
+            finding(&app, "js-eval", Severity::Error, 3, "Avoid <script> & eval"),
+            finding(&app, "js-exec", Severity::Warning, 1, "exec is risky"),
+        ];
+        let html = render(&findings, &AnalysisStatistics::new(), std::time::Duration::from_millis(20));
+
+        // Self-contained and safe: no external resources, messages escaped
+        assert!(!html.contains(" src=") && !html.contains("<link"));
+        assert!(html.contains("<h3>Avoid &lt;script&gt; &amp; eval</h3>"));
+
+        // One group per file, in path order, findings in line order
+        let app_group = html.find(&format!("<summary>{}<span class=\"count\">2</span>", app.display())).unwrap();
+        let util_group = html.find(&format!("<summary>{}<span class=\"count\">1</span>", util.display())).unwrap();
+        assert!(app_group < util_group);
+        assert!(html[app_group..].find("exec is risky").unwrap() < html[app_group..].find("Avoid &lt;script").unwrap());
+
+        // Filters and charts count by severity and rule
+        assert!(html.contains("<input type=\"checkbox\" value=\"ERROR\" checked> ERROR (1)"));
+        assert!(html.contains("<input type=\"checkbox\" value=\"WARNING\" checked> WARNING (2)"));
+        assert!(html.contains("<span title=\"js-exec\">js-exec</span><i class=\"\" style=\"width: 100.0%\"></i><em>2</em>"));
+
+        // Snippets only for readable sources, with context around the match
+        assert_eq!(html.matches("<details class=\"snippet\">").count(), 2);
+        assert!(html.contains("<span data-line=\"1\">a();</span><span data-line=\"2\">b();</span><span class=\"hit\" data-line=\"3\">eval(x);</span>"));
+    }
+
+    #[test]
+    fn test_snippet_redacts_matched_lines() {
+        let mut secret = finding(Path::new("config.py"), "py-secret", Severity::Error, 2, "Hardcoded key");
+        secret.redaction = Some(Redaction::Mask);
+        let html = snippet("import os\nKEY = 'abc'\nprint(KEY)\n", &secret).unwrap();
+        assert!(!html.contains("abc"));
+        assert!(html.contains("<span data-line=\"1\">import os</span>"));
+        assert!(snippet("one line\n", &finding(Path::new("a.py"), "r", Severity::Info, 5, "m")).is_none());
+    }
+}
@@ -0,0 +1,378 @@
+//! `.astgrepignore` files
+//!
+//! Any directory may hold a `.astgrepignore` in gitignore syntax listing paths the
+//! analysis skips. Patterns are relative to the directory of their file, files deeper in
+//! the tree take precedence over outer ones, and a later line overrides an earlier one,
+//! so `!keep.js` re-includes a file excluded above it. Files of an ignored directory
+//! cannot be re-included, as with git. Ignore files of the directories above a scanned
+//! target apply too, up to the enclosing git repository root.
+//!
+//! Unless `--no-git-ignore` is given, `.gitignore` files and the repository's
+//! `.git/info/exclude` are read the same way, so dependency and build trees such as
+//! `node_modules` or `target/` are not scanned, and `.git` itself is skipped. In a
+//! directory holding both, `.astgrepignore` takes precedence over `.gitignore`.
+
+use regex::Regex;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::warn;
+
+use crate::commands::analyze_enhanced::is_excluded;
+
+/// Name of the ignore file looked up in every scanned directory
+pub const IGNORE_FILE_NAME: &str = ".astgrepignore";
+
+/// Name of git's ignore file, honored unless `--no-git-ignore` is given
+pub const GIT_IGNORE_FILE_NAME: &str = ".gitignore";
+
+/// Repository-wide excludes of git, relative to the repository root
+const GIT_EXCLUDE_FILE: &str = ".git/info/exclude";
+
+#[derive(Debug)]
+struct IgnorePattern {
+    regex: Regex,
+    negated: bool,
+    dir_only: bool,
+}
+
+/// The patterns of one ignore file
+#[derive(Debug)]
+pub struct IgnoreFile {
+    patterns: Vec<IgnorePattern>,
+}
+
+impl IgnoreFile {
+    /// Parse the content of the ignore file of directory `dir`
+    pub fn parse(dir: &Path, content: &str) -> Self {
+        Self::parse_file(&dir.join(IGNORE_FILE_NAME), content)
+    }
+
+    fn parse_file(path: &Path, content: &str) -> Self {
+        let patterns = content.lines().filter_map(|line| parse_line(line, path)).collect();
+        Self { patterns }
+    }
+
+    /// The ignore file `name` of `dir`, if it has a readable one
+    pub fn load(dir: &Path, name: &str) -> Option<Self> {
+        let path = dir.join(name);
+        match std::fs::read_to_string(&path) {
+            Ok(content) => Some(Self::parse_file(&path, &content)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => {
+                warn!("Cannot read {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    /// `Some(true)` when the last pattern matching `relative`, a `/`-separated path from
+    /// the file's directory, ignores it, `Some(false)` when it re-includes it, `None` when
+    /// no pattern matches
+    pub fn decision(&self, relative: &str, is_dir: bool) -> Option<bool> {
+        self.patterns
+            .iter()
+            .rev()
+            .find(|p| (is_dir || !p.dir_only) && p.regex.is_match(relative))
+            .map(|p| !p.negated)
+    }
+}
+
+/// An ignore file in effect during a scan
+#[derive(Debug, Clone)]
+struct ScopedIgnoreFile {
+    /// Scanned directory the paths checked against the file are relative to
+    base: PathBuf,
+    /// Path of `base` below the file's directory, for files above the scanned target
+    prefix: Vec<String>,
+    file: Arc<IgnoreFile>,
+}
+
+/// The ignore files in effect in a directory, outermost first
+#[derive(Debug, Clone, Default)]
+pub struct IgnoreStack {
+    files: Vec<ScopedIgnoreFile>,
+    /// Whether git's ignore files are honored too
+    git_ignore: bool,
+}
+
+impl IgnoreStack {
+    /// The ignore files of the directories above the scanned directory `dir`, up to the
+    /// enclosing git repository root or the filesystem root
+    pub fn above(dir: &Path, git_ignore: bool) -> Self {
+        let canonical = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+        let mut stack = Self { files: Vec::new(), git_ignore };
+        if !canonical.join(".git").exists() {
+            for ancestor in canonical.ancestors().skip(1) {
+                let prefix = canonical.strip_prefix(ancestor).map(path_segments).unwrap_or_default();
+                // Pushed innermost first, and reversed below
+                for file in stack.ignore_files(ancestor).into_iter().rev() {
+                    stack.files.push(ScopedIgnoreFile { base: dir.to_path_buf(), prefix: prefix.clone(), file: Arc::new(file) });
+                }
+                if ancestor.join(".git").exists() {
+                    break;
+                }
+            }
+        }
+        stack.files.reverse();
+        stack
+    }
+
+    /// The stack in effect inside `dir`, adding its ignore files
+    pub fn enter(&self, dir: &Path) -> Self {
+        let mut stack = self.clone();
+        for file in self.ignore_files(dir) {
+            stack.files.push(ScopedIgnoreFile { base: dir.to_path_buf(), prefix: Vec::new(), file: Arc::new(file) });
+        }
+        stack
+    }
+
+    /// The ignore files of `dir`, lowest precedence first
+    fn ignore_files(&self, dir: &Path) -> Vec<IgnoreFile> {
+        let mut files = Vec::new();
+        if self.git_ignore {
+            if dir.join(".git").is_dir() {
+                files.extend(IgnoreFile::load(dir, GIT_EXCLUDE_FILE));
+            }
+            files.extend(IgnoreFile::load(dir, GIT_IGNORE_FILE_NAME));
+        }
+        files.extend(IgnoreFile::load(dir, IGNORE_FILE_NAME));
+        files
+    }
+
+    /// Whether `path`, found below the scanned directories, is ignored; the innermost file
+    /// with a matching pattern decides
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        if self.git_ignore && is_dir && path.file_name().is_some_and(|name| name == ".git") {
+            return true;
+        }
+        self.files
+            .iter()
+            .rev()
+            .find_map(|scoped| {
+                let mut segments = scoped.prefix.clone();
+                segments.extend(path_segments(path.strip_prefix(&scoped.base).ok()?));
+                scoped.file.decision(&segments.join("/"), is_dir)
+            })
+            .unwrap_or(false)
+    }
+}
+
+/// The files under `dir` in path order that neither its ignore files, those below it and
+/// those above it up to the repository root, nor one of the `exclude` patterns leave out
+pub fn walk_files(dir: &Path, git_ignore: bool, exclude: &[String]) -> std::io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    walk_dir(dir, &IgnoreStack::above(dir, git_ignore), exclude, &mut files)?;
+    Ok(files)
+}
+
+fn walk_dir(dir: &Path, ignores: &IgnoreStack, exclude: &[String], files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    let ignores = ignores.enter(dir);
+    let mut entries = std::fs::read_dir(dir)?.map(|e| e.map(|e| e.path())).collect::<std::io::Result<Vec<_>>>()?;
+    entries.sort();
+    for path in entries {
+        let is_dir = path.is_dir();
+        if ignores.is_ignored(&path, is_dir) {
+            continue;
+        }
+        if is_dir {
+            walk_dir(&path, &ignores, exclude, files)?;
+        } else if !is_excluded(&path, exclude) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn path_segments(path: &Path) -> Vec<String> {
+    path.components().map(|c| c.as_os_str().to_string_lossy().into_owned()).collect()
+}
+
+/// Compile one line of an ignore file; blank lines and comments yield nothing
+fn parse_line(line: &str, file: &Path) -> Option<IgnorePattern> {
+    let line = trim_unescaped_trailing_spaces(line);
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    let (negated, line) = match line.strip_prefix('!') {
+        Some(rest) => (true, rest),
+        None => (false, line),
+    };
+    let (dir_only, line) = match line.strip_suffix('/') {
+        Some(rest) => (true, rest),
+        None => (false, line),
+    };
+    // A slash anywhere but at the end anchors the pattern to the ignore file's directory
+    let anchored = line.contains('/');
+    let line = line.strip_prefix('/').unwrap_or(line);
+    if line.is_empty() {
+        return None;
+    }
+
+    let body = glob_to_regex(line);
+    let source = if anchored { format!("^{}$", body) } else { format!("^(?:.*/)?{}$", body) };
+    match Regex::new(&source) {
+        Ok(regex) => Some(IgnorePattern { regex, negated, dir_only }),
+        Err(e) => {
+            warn!("Ignoring pattern '{}' of {}: {}", line, file.display(), e);
+            None
+        }
+    }
+}
+
+fn trim_unescaped_trailing_spaces(line: &str) -> &str {
+    let mut end = line.len();
+    while line[..end].ends_with(' ') && !line[..end - 1].ends_with('\\') {
+        end -= 1;
+    }
+    &line[..end]
+}
+
+/// Translate a gitignore glob to a regex over `/`-separated relative paths
+fn glob_to_regex(glob: &str) -> String {
+    let chars: Vec<char> = glob.chars().collect();
+    let mut regex = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                let starts_segment = i == 0 || chars[i - 1] == '/';
+                match chars.get(i + 2) {
+                    // `**/` matches zero or more directories
+                    Some('/') if starts_segment => {
+                        regex.push_str("(?:.*/)?");
+                        i += 3;
+                    }
+                    // a trailing `/**` matches everything inside
+                    None if starts_segment => {
+                        regex.push_str(".*");
+                        i += 2;
+                    }
+                    _ => {
+                        regex.push_str("[^/]*");
+                        i += 2;
+                    }
+                }
+            }
+            '*' => {
+                regex.push_str("[^/]*");
+                i += 1;
+            }
+            '?' => {
+                regex.push_str("[^/]");
+                i += 1;
+            }
+            '[' => match chars[i + 1..].iter().position(|&c| c == ']') {
+                Some(len) if len > 0 => {
+                    let class: String = chars[i + 1..i + 1 + len].iter().collect();
+                    let class = class.strip_prefix('!').map(|rest| format!("^{}", rest)).unwrap_or(class);
+                    regex.push('[');
+                    regex.push_str(&class.replace('\\', "\\\\").replace('[', "\\["));
+                    regex.push(']');
+                    i += len + 2;
+                }
+                _ => {
+                    regex.push_str("\\[");
+                    i += 1;
+                }
+            },
+            '\\' if i + 1 < chars.len() => {
+                regex.push_str(&regex::escape(&chars[i + 1].to_string()));
+                i += 2;
+            }
+            c => {
+                regex.push_str(&regex::escape(&c.to_string()));
+                i += 1;
+            }
+        }
+    }
+    regex
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gitignore_syntax() {
+        let file = IgnoreFile::parse(
+            Path::new("/repo"),
+            "# generated\nbuild/\n*.min.js\n!keep.min.js\n/vendor\ndocs/**/*.py\n\\#notes.txt\nfixture?.[jt]s  \n",
+        );
+        let ignored = |path: &str, is_dir: bool| file.decision(path, is_dir) == Some(true);
+
+        assert!(ignored("build", true));
+        assert!(ignored("src/build", true));
+        assert!(!ignored("build", false), "build/ only matches directories");
+        assert!(ignored("src/app.min.js", false));
+        assert_eq!(file.decision("lib/keep.min.js", false), Some(false));
+        assert!(ignored("vendor", true));
+        assert!(file.decision("src/vendor", true).is_none(), "/vendor is anchored");
+        assert!(ignored("docs/conf.py", false));
+        assert!(ignored("docs/a/b/conf.py", false));
+        assert!(ignored("#notes.txt", false));
+        assert!(ignored("test/fixture1.ts", false));
+        assert!(!ignored("test/fixture10.ts", false));
+    }
+
+    #[test]
+    fn test_nested_files_take_precedence() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::create_dir_all(root.join(".git")).unwrap();
+        std::fs::create_dir_all(root.join("src/gen")).unwrap();
+        std::fs::write(root.join(IGNORE_FILE_NAME), "*.gen.js\nsrc/gen/\n").unwrap();
+        std::fs::write(root.join("src").join(IGNORE_FILE_NAME), "!api.gen.js\n").unwrap();
+
+        let stack = IgnoreStack::above(&root.join("src"), false).enter(&root.join("src"));
+        assert!(stack.is_ignored(&root.join("src/gen"), true));
+        assert!(stack.is_ignored(&root.join("src/ui.gen.js"), false));
+        assert!(!stack.is_ignored(&root.join("src/api.gen.js"), false));
+        assert!(!stack.is_ignored(&root.join("src/app.js"), false));
+
+        let top = IgnoreStack::above(&root, false).enter(&root);
+        assert!(top.is_ignored(&root.join("ui.gen.js"), false));
+        assert!(IgnoreStack::above(&root, false).files.is_empty(), "nothing above the repository root applies");
+    }
+
+    #[test]
+    fn test_git_ignore_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::create_dir_all(root.join(".git/info")).unwrap();
+        std::fs::create_dir_all(root.join("web/node_modules")).unwrap();
+        std::fs::write(root.join(".git/info/exclude"), "*.local.js\n").unwrap();
+        std::fs::write(root.join(GIT_IGNORE_FILE_NAME), "node_modules/\ntarget/\n*.gen.js\n").unwrap();
+        std::fs::write(root.join(IGNORE_FILE_NAME), "!api.gen.js\n").unwrap();
+
+        let web = IgnoreStack::above(&root.join("web"), true).enter(&root.join("web"));
+        assert!(web.is_ignored(&root.join("web/node_modules"), true));
+        assert!(web.is_ignored(&root.join("web/dev.local.js"), false));
+        assert!(web.is_ignored(&root.join("web/ui.gen.js"), false));
+        assert!(!web.is_ignored(&root.join("web/api.gen.js"), false), ".astgrepignore overrides .gitignore");
+
+        let top = IgnoreStack::above(&root, true).enter(&root);
+        assert!(top.is_ignored(&root.join("target"), true));
+        assert!(top.is_ignored(&root.join(".git"), true));
+
+        let without_git = IgnoreStack::above(&root, false).enter(&root);
+        assert!(!without_git.is_ignored(&root.join("target"), true));
+        assert!(!without_git.is_ignored(&root.join(".git"), true));
+        assert!(!without_git.is_ignored(&root.join("ui.gen.js"), false));
+    }
+
+    #[test]
+    fn test_walk_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::create_dir_all(root.join(".git")).unwrap();
+        std::fs::create_dir_all(root.join("rules/draft")).unwrap();
+        std::fs::write(root.join(GIT_IGNORE_FILE_NAME), "draft/\n").unwrap();
+        for name in ["rules/b.yaml", "rules/a.yaml", "rules/a.bak.yaml", "rules/draft/c.yaml"] {
+            std::fs::write(root.join(name), "rules: []\n").unwrap();
+        }
+
+        let files = walk_files(&root.join("rules"), true, &["*.bak.yaml".to_string()]).unwrap();
+        assert_eq!(files, vec![root.join("rules/a.yaml"), root.join("rules/b.yaml")]);
+        assert_eq!(walk_files(&root.join("rules"), false, &[]).unwrap().len(), 4);
+    }
+}
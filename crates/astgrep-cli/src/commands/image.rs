@@ -0,0 +1,504 @@
+//! Container image extraction for the analyze command
+//!
+//! Unpacks a docker-save tarball or OCI image layout, applies its layers in order
+//! (honouring whiteouts) and keeps only application code and dependency manifests,
+//! remembering which layer last wrote each file. Files matching an `--exclude` pattern
+//! by their path in the image are not extracted; the extracted root filesystem is then
+//! collected like any other target, so ignore files shipped in the image apply too.
+
+use anyhow::Result;
+use std::collections::HashMap;
+use std::io::{self, Read};
+use std::path::{Component, Path, PathBuf};
+use tracing::{debug, info, warn};
+use crate::commands::analyze_enhanced::is_excluded;
+
+/// Path prefixes inside an image that belong to the OS, not the application
+const SYSTEM_PREFIXES: &[&str] = &[
+    "bin/", "boot/", "dev/", "etc/", "lib/", "lib32/", "lib64/", "proc/", "run/", "sbin/", "sys/",
+    "usr/bin/", "usr/include/", "usr/lib/", "usr/lib64/", "usr/libexec/", "usr/sbin/", "usr/share/",
+    "var/cache/", "var/lib/", "var/log/",
+];
+
+/// Directories of packages installed into the image rather than its own code
+const SKIPPED_DIRS: &[&str] = &["node_modules", "site-packages", "dist-packages", "__pycache__", ".git"];
+
+/// Dependency manifests reported alongside the analyzed code
+const MANIFEST_FILES: &[&str] = &[
+    "package.json", "package-lock.json", "yarn.lock", "requirements.txt", "Pipfile", "Pipfile.lock",
+    "pyproject.toml", "pom.xml", "build.gradle", "build.gradle.kts", "go.mod", "go.sum", "Gemfile",
+    "Gemfile.lock", "composer.json", "composer.lock", "Cargo.toml", "Cargo.lock", "packages.config",
+];
+
+/// Source extensions worth extracting from layers
+const SOURCE_EXTENSIONS: &[&str] = &[
+    "java", "js", "jsx", "ts", "tsx", "py", "sql", "sh", "bash", "php", "cs", "c", "h", "rb", "rbw",
+    "kt", "kts", "swift", "xml",
+];
+
+/// An image unpacked to a local root filesystem
+#[derive(Debug, Clone)]
+pub struct ExtractedImage {
+    pub reference: String,
+    pub rootfs: PathBuf,
+    pub layers: Vec<String>,
+    /// Image path (e.g. `app/main.py`) -> id of the layer that last wrote it
+    pub provenance: HashMap<String, String>,
+    pub manifests: Vec<String>,
+}
+
+/// Image section of the analysis report
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ImageSummary {
+    pub reference: String,
+    pub layers: Vec<String>,
+    pub files_extracted: usize,
+    pub manifests: Vec<String>,
+}
+
+impl ExtractedImage {
+    pub fn summary(&self) -> ImageSummary {
+        ImageSummary {
+            reference: self.reference.clone(),
+            layers: self.layers.clone(),
+            files_extracted: self.provenance.len(),
+            manifests: self.manifests.clone(),
+        }
+    }
+
+    /// Translate a path under the extracted rootfs back to its in-image path and layer
+    pub fn locate(&self, file: &Path) -> Option<(PathBuf, Option<&String>)> {
+        let rel = file.strip_prefix(&self.rootfs).ok()?;
+        let key = rel.to_string_lossy().replace('\\', "/");
+        Some((Path::new("/").join(rel), self.provenance.get(&key)))
+    }
+}
+
+/// Extract `image` (a docker-save tarball, an OCI layout directory or tarball, or an
+/// image reference exported through `docker save`) into `work_dir`, leaving out files
+/// matching one of the `exclude` patterns
+pub fn extract_image(image: &str, work_dir: &Path, exclude: &[String]) -> Result<ExtractedImage> {
+    let staging = work_dir.join("image");
+    let rootfs = work_dir.join("rootfs");
+    std::fs::create_dir_all(&staging)?;
+    std::fs::create_dir_all(&rootfs)?;
+
+    let image_path = Path::new(image);
+    let layout_dir = if image_path.is_dir() {
+        image_path.to_path_buf()
+    } else {
+        let archive = if image_path.is_file() {
+            image_path.to_path_buf()
+        } else {
+            let archive = work_dir.join("image.tar");
+            docker_save(image, &archive)?;
+            archive
+        };
+        info!("Unpacking image archive {}", archive.display());
+        let reader = open_maybe_gzip(&archive)?;
+        read_tar(reader, |path, kind, data| {
+            if kind == TarEntryKind::File {
+                let dest = staging.join(path);
+                if let Some(parent) = dest.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                io::copy(data, &mut std::fs::File::create(dest)?)?;
+            }
+            Ok(())
+        })?;
+        staging.clone()
+    };
+
+    let layers = image_layers(&layout_dir)?;
+    let mut extracted = ExtractedImage {
+        reference: image.to_string(),
+        rootfs: rootfs.clone(),
+        layers: Vec::new(),
+        provenance: HashMap::new(),
+        manifests: Vec::new(),
+    };
+
+    for (layer_id, layer_path) in layers {
+        debug!("Applying layer {} from {}", layer_id, layer_path.display());
+        let reader = open_maybe_gzip(&layer_path)?;
+        apply_layer(reader, &layer_id, &mut extracted, exclude)?;
+        extracted.layers.push(layer_id);
+    }
+
+    extracted.manifests = extracted.provenance.keys()
+        .filter(|p| MANIFEST_FILES.contains(&file_name(p)))
+        .cloned()
+        .collect();
+    extracted.manifests.sort();
+
+    info!("Extracted {} application files from {} layers", extracted.provenance.len(), extracted.layers.len());
+    Ok(extracted)
+}
+
+/// Export an image reference from the local docker daemon
+fn docker_save(reference: &str, archive: &Path) -> Result<()> {
+    info!("Exporting image {} with docker save", reference);
+    let status = std::process::Command::new("docker")
+        .arg("save")
+        .arg("-o")
+        .arg(archive)
+        .arg(reference)
+        .status()
+        .map_err(|e| anyhow::anyhow!("Image {} is not a file or directory and docker is unavailable: {}", reference, e))?;
+    if !status.success() {
+        return Err(anyhow::anyhow!("docker save failed for image {}", reference));
+    }
+    Ok(())
+}
+
+/// Resolve the ordered layer list of an unpacked image as (layer id, blob path)
+fn image_layers(layout_dir: &Path) -> Result<Vec<(String, PathBuf)>> {
+    // docker save: manifest.json lists layer tarballs relative to the archive root
+    let docker_manifest = layout_dir.join("manifest.json");
+    if docker_manifest.is_file() {
+        let manifest: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&docker_manifest)?)?;
+        let layers = manifest.get(0)
+            .and_then(|m| m.get("Layers"))
+            .and_then(|l| l.as_array())
+            .ok_or_else(|| anyhow::anyhow!("manifest.json has no Layers"))?;
+        return layers.iter()
+            .filter_map(|l| l.as_str())
+            .map(|l| Ok((docker_layer_id(l), layout_path(layout_dir, l)?)))
+            .collect();
+    }
+
+    // OCI layout: index.json -> (optional nested index) -> image manifest -> layer blobs
+    let index_path = layout_dir.join("index.json");
+    if !index_path.is_file() {
+        return Err(anyhow::anyhow!("Not a container image: no manifest.json or index.json in {}", layout_dir.display()));
+    }
+    let mut manifest: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&index_path)?)?;
+    while manifest.get("layers").is_none() {
+        let digest = manifest.get("manifests")
+            .and_then(|m| m.get(0))
+            .and_then(|m| m.get("digest"))
+            .and_then(|d| d.as_str())
+            .ok_or_else(|| anyhow::anyhow!("OCI index has no manifests"))?;
+        manifest = serde_json::from_str(&std::fs::read_to_string(oci_blob_path(layout_dir, digest)?)?)?;
+    }
+    let layers = manifest["layers"].as_array().cloned().unwrap_or_default();
+    layers.iter()
+        .filter_map(|l| l.get("digest").and_then(|d| d.as_str()))
+        .map(|digest| Ok((digest.to_string(), oci_blob_path(layout_dir, digest)?)))
+        .collect()
+}
+
+/// Join a manifest-supplied relative path onto the layout dir, rejecting absolute paths
+/// and `..` so a crafted manifest cannot point outside the image
+fn layout_path(layout_dir: &Path, relative: &str) -> Result<PathBuf> {
+    let path = Path::new(relative);
+    let contained = path.components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+    if relative.is_empty() || !contained {
+        return Err(anyhow::anyhow!("Image layer path outside the image: {}", relative));
+    }
+    Ok(layout_dir.join(path))
+}
+
+/// `blobs/<alg>/<hex>` for an OCI digest, which must be `<alg>:<hex>`
+fn oci_blob_path(layout_dir: &Path, digest: &str) -> Result<PathBuf> {
+    let malformed = || anyhow::anyhow!("Malformed digest: {}", digest);
+    let (algorithm, hex) = digest.split_once(':').ok_or_else(malformed)?;
+    let valid_algorithm = algorithm.starts_with(|c: char| c.is_ascii_lowercase() || c.is_ascii_digit())
+        && algorithm.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '+' | '.' | '_' | '-'));
+    if !valid_algorithm || hex.is_empty() || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(malformed());
+    }
+    Ok(layout_dir.join("blobs").join(algorithm).join(hex))
+}
+
+/// `<id>/layer.tar` (legacy docker save) or `blobs/sha256/<hex>` (OCI-style docker save)
+fn docker_layer_id(layer: &str) -> String {
+    let parts: Vec<&str> = layer.split('/').collect();
+    match parts.as_slice() {
+        ["blobs", algorithm, hex] => format!("{}:{}", algorithm, hex),
+        [id, _] => id.to_string(),
+        _ => layer.to_string(),
+    }
+}
+
+/// Apply one layer on top of the rootfs, honouring whiteout markers
+fn apply_layer<R: Read>(reader: R, layer_id: &str, image: &mut ExtractedImage, exclude: &[String]) -> Result<()> {
+    let rootfs = image.rootfs.clone();
+    read_tar(reader, |path, kind, data| {
+        let name = file_name(path);
+        let parent = path.rsplit_once('/').map(|(dir, _)| dir).unwrap_or("");
+
+        if name == ".wh..wh..opq" {
+            // Opaque directory: hide everything lower layers put there
+            let prefix = format!("{}/", parent);
+            image.provenance.retain(|p, _| !p.starts_with(&prefix));
+            let dir = rootfs.join(parent);
+            if dir.is_dir() {
+                std::fs::remove_dir_all(&dir)?;
+            }
+            return Ok(());
+        }
+        if let Some(hidden) = name.strip_prefix(".wh.") {
+            let target = if parent.is_empty() { hidden.to_string() } else { format!("{}/{}", parent, hidden) };
+            let prefix = format!("{}/", target);
+            image.provenance.retain(|p, _| p != &target && !p.starts_with(&prefix));
+            let dest = rootfs.join(&target);
+            if dest.is_dir() {
+                std::fs::remove_dir_all(&dest)?;
+            } else if dest.exists() {
+                std::fs::remove_file(&dest)?;
+            }
+            return Ok(());
+        }
+
+        if kind != TarEntryKind::File || !is_application_file(path) || is_excluded(Path::new(path), exclude) {
+            return Ok(());
+        }
+        let dest = rootfs.join(path);
+        if let Some(dir) = dest.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        io::copy(data, &mut std::fs::File::create(&dest)?)?;
+        image.provenance.insert(path.to_string(), layer_id.to_string());
+        Ok(())
+    })
+}
+
+fn is_application_file(path: &str) -> bool {
+    if SYSTEM_PREFIXES.iter().any(|p| path.starts_with(p)) {
+        return false;
+    }
+    if path.split('/').any(|c| SKIPPED_DIRS.contains(&c)) {
+        return false;
+    }
+    let name = file_name(path);
+    MANIFEST_FILES.contains(&name)
+        || name.rsplit_once('.').is_some_and(|(_, ext)| SOURCE_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+fn file_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+/// Open a file, transparently decompressing gzip content
+pub(crate) fn open_maybe_gzip(path: &Path) -> Result<Box<dyn Read>> {
+    let mut magic = [0u8; 2];
+    let is_gzip = std::fs::File::open(path)?.read(&mut magic)? == 2 && magic == [0x1f, 0x8b];
+    let file = io::BufReader::new(std::fs::File::open(path)?);
+    if is_gzip {
+        Ok(Box::new(flate2::read::GzDecoder::new(file)))
+    } else {
+        Ok(Box::new(file))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TarEntryKind {
+    File,
+    Directory,
+    Other,
+}
+
+/// Minimal ustar/GNU/PAX reader. `visit` receives the normalized relative path, the entry
+/// kind and a reader over the entry contents; entries escaping the root are skipped.
+pub(crate) fn read_tar<R: Read>(
+    mut reader: R,
+    mut visit: impl FnMut(&str, TarEntryKind, &mut dyn Read) -> Result<()>,
+) -> Result<()> {
+    let mut header = [0u8; 512];
+    let mut long_name: Option<String> = None;
+
+    loop {
+        if !read_block(&mut reader, &mut header)? || header.iter().all(|b| *b == 0) {
+            break;
+        }
+        let size = parse_octal(&header[124..136]);
+        let padding = (512 - size % 512) % 512;
+        let typeflag = header[156];
+
+        if matches!(typeflag, b'L' | b'x' | b'g') {
+            // `size` comes from the archive; let the buffer grow with what is actually read
+            let mut data = Vec::new();
+            (&mut reader).take(size).read_to_end(&mut data)?;
+            io::copy(&mut (&mut reader).take(padding), &mut io::sink())?;
+            match typeflag {
+                b'L' => long_name = Some(c_string(&data)),
+                b'x' => {
+                    if let Some(path) = pax_path(&data) {
+                        long_name = Some(path);
+                    }
+                }
+                _ => {}
+            }
+            continue;
+        }
+
+        let raw_path = long_name.take().unwrap_or_else(|| {
+            let name = c_string(&header[0..100]);
+            let prefix = if &header[257..262] == b"ustar" { c_string(&header[345..500]) } else { String::new() };
+            if prefix.is_empty() { name } else { format!("{}/{}", prefix, name) }
+        });
+        let kind = match typeflag {
+            0 | b'0' | b'7' => TarEntryKind::File,
+            b'5' => TarEntryKind::Directory,
+            _ => TarEntryKind::Other,
+        };
+
+        let mut data = (&mut reader).take(size);
+        match normalize_entry_path(&raw_path) {
+            Some(path) if !path.is_empty() => visit(&path, kind, &mut data)?,
+            Some(_) => {}
+            None => warn!("Skipping tar entry outside the archive root: {}", raw_path),
+        }
+        io::copy(&mut data, &mut io::sink())?;
+        io::copy(&mut (&mut reader).take(padding), &mut io::sink())?;
+    }
+
+    Ok(())
+}
+
+/// Read one 512-byte block; false on clean end of stream
+fn read_block<R: Read>(reader: &mut R, block: &mut [u8; 512]) -> Result<bool> {
+    let mut filled = 0;
+    while filled < block.len() {
+        let n = reader.read(&mut block[filled..])?;
+        if n == 0 {
+            if filled == 0 {
+                return Ok(false);
+            }
+            return Err(anyhow::anyhow!("Truncated tar archive"));
+        }
+        filled += n;
+    }
+    Ok(true)
+}
+
+fn parse_octal(field: &[u8]) -> u64 {
+    if field[0] & 0x80 != 0 {
+        // GNU base-256 encoding for large sizes
+        return field[1..].iter().fold(0u64, |acc, b| (acc << 8) | *b as u64);
+    }
+    let text = c_string(field);
+    u64::from_str_radix(text.trim(), 8).unwrap_or(0)
+}
+
+fn c_string(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
+
+/// Extract the `path` record from a PAX extended header ("<len> path=<value>\n")
+fn pax_path(data: &[u8]) -> Option<String> {
+    String::from_utf8_lossy(data)
+        .lines()
+        .filter_map(|line| line.split_once(' ').map(|(_, record)| record))
+        .find_map(|record| record.strip_prefix("path=").map(|p| p.to_string()))
+}
+
+/// Strip leading `/` and `./`, rejecting any path that climbs out with `..`
+fn normalize_entry_path(path: &str) -> Option<String> {
+    let mut parts = Vec::new();
+    for component in Path::new(path).components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_string_lossy().into_owned()),
+            Component::CurDir | Component::RootDir => {}
+            Component::ParentDir | Component::Prefix(_) => return None,
+        }
+    }
+    Some(parts.join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tar_entry(name: &str, data: &[u8]) -> Vec<u8> {
+        let mut header = [0u8; 512];
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        header[124..135].copy_from_slice(format!("{:011o}", data.len()).as_bytes());
+        header[156] = b'0';
+        header[257..263].copy_from_slice(b"ustar\0");
+        let mut entry = header.to_vec();
+        entry.extend_from_slice(data);
+        entry.resize(entry.len() + (512 - data.len() % 512) % 512, 0);
+        entry
+    }
+
+    fn tar(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut archive: Vec<u8> = entries.iter().flat_map(|(n, d)| tar_entry(n, d)).collect();
+        archive.extend_from_slice(&[0u8; 1024]);
+        archive
+    }
+
+    #[test]
+    fn test_extract_docker_save_with_whiteouts() {
+        let work = tempfile::tempdir().unwrap();
+        let image = work.path().join("image.tar");
+        let base = tar(&[
+            ("app/main.py", b"import os\n"),
+            ("app/old.py", b"eval(x)\n"),
+            ("usr/lib/python3/os.py", b"# stdlib\n"),
+        ]);
+        let top = tar(&[
+            ("app/.wh.old.py", b""),
+            ("app/package.json", b"{}"),
+            ("app/main.py", b"import subprocess\n"),
+        ]);
+        let manifest = br#"[{"Config":"config.json","RepoTags":["demo:latest"],"Layers":["aaa/layer.tar","bbb/layer.tar"]}]"#;
+        std::fs::write(&image, tar(&[
+            ("manifest.json", manifest),
+            ("aaa/layer.tar", &base),
+            ("bbb/layer.tar", &top),
+        ])).unwrap();
+
+        let extracted = extract_image(image.to_str().unwrap(), &work.path().join("work"), &[]).unwrap();
+        assert_eq!(extracted.layers, vec!["aaa".to_string(), "bbb".to_string()]);
+        assert_eq!(extracted.provenance.get("app/main.py"), Some(&"bbb".to_string()));
+        assert!(!extracted.provenance.contains_key("app/old.py"));
+        assert!(!extracted.provenance.contains_key("usr/lib/python3/os.py"));
+        assert!(!extracted.rootfs.join("app/old.py").exists());
+        assert_eq!(extracted.manifests, vec!["app/package.json".to_string()]);
+
+        let (path, layer) = extracted.locate(&extracted.rootfs.join("app/main.py")).unwrap();
+        assert_eq!(path, PathBuf::from("/app/main.py"));
+        assert_eq!(layer, Some(&"bbb".to_string()));
+
+        // Excluded files are not extracted
+        let excluded = extract_image(image.to_str().unwrap(), &work.path().join("excluded"), &["app/main*".to_string()]).unwrap();
+        assert!(!excluded.provenance.contains_key("app/main.py"));
+        assert!(!excluded.rootfs.join("app/main.py").exists());
+        assert_eq!(excluded.manifests, vec!["app/package.json".to_string()]);
+    }
+
+    #[test]
+    fn test_normalize_entry_path() {
+        assert_eq!(normalize_entry_path("./app/x.py"), Some("app/x.py".to_string()));
+        assert_eq!(normalize_entry_path("/app/x.py"), Some("app/x.py".to_string()));
+        assert_eq!(normalize_entry_path("../etc/passwd"), None);
+        assert_eq!(docker_layer_id("blobs/sha256/abc"), "sha256:abc");
+    }
+
+    #[test]
+    fn test_manifest_paths_stay_inside_the_image() {
+        let layout = Path::new("/layout");
+        assert_eq!(layout_path(layout, "aaa/layer.tar").unwrap(), PathBuf::from("/layout/aaa/layer.tar"));
+        assert!(layout_path(layout, "../../etc/shadow").is_err());
+        assert!(layout_path(layout, "/etc/shadow").is_err());
+        assert_eq!(oci_blob_path(layout, "sha256:0a1b").unwrap(), PathBuf::from("/layout/blobs/sha256/0a1b"));
+        assert!(oci_blob_path(layout, "sha256:../../etc/shadow").is_err());
+        assert!(oci_blob_path(layout, "../x:0a1b").is_err());
+        assert!(oci_blob_path(layout, "sha256").is_err());
+    }
+
+    #[test]
+    fn test_oversized_tar_header_size_is_not_preallocated() {
+        let mut header = [0u8; 512];
+        header[..8].copy_from_slice(b"././@Lon");
+        // GNU base-256 size of 2^64 - 1
+        header[124] = 0x80;
+        header[125..136].fill(0xff);
+        header[156] = b'L';
+        let archive = header.to_vec();
+        assert!(read_tar(archive.as_slice(), |_, _, _| Ok(())).is_ok());
+    }
+}
@@ -0,0 +1,237 @@
+//! Index command for building the persistent project index
+//!
+//! Files are collected the way `analyze` collects them, honoring `.astgrepignore` and
+//! `.gitignore` files and `--exclude` patterns.
+
+use anyhow::Result;
+use astgrep_core::Language;
+use astgrep_dataflow::ProjectIndex;
+use astgrep_parser::LanguageParserRegistry;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use tracing::{debug, info, warn};
+
+use crate::commands::analyze_enhanced::collect_target_files;
+use crate::EnhancedAnalysisConfig;
+
+/// Summary of an index refresh
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct IndexUpdateSummary {
+    pub files_scanned: usize,
+    pub files_reindexed: usize,
+    pub files_removed: usize,
+    pub parse_failures: usize,
+}
+
+/// Build or incrementally update the project index
+pub async fn run(root: PathBuf, rebuild: bool, exclude: Vec<String>, git_ignore: bool) -> Result<()> {
+    if !root.is_dir() {
+        return Err(anyhow::anyhow!("Project root is not a directory: {}", root.display()));
+    }
+
+    let summary = refresh_project_index(&root, rebuild, exclude, git_ignore).await?;
+
+    println!("📇 Project index updated: {}", ProjectIndex::index_path(&root).display());
+    println!("  • Files scanned: {}", summary.files_scanned);
+    println!("  • Files re-indexed: {}", summary.files_reindexed);
+    println!("  • Stale entries removed: {}", summary.files_removed);
+    if summary.parse_failures > 0 {
+        println!("  • Parse failures: {}", summary.parse_failures);
+    }
+
+    Ok(())
+}
+
+/// Refresh the on-disk index for `root`, re-indexing only files whose content changed
+pub async fn refresh_project_index(root: &Path, rebuild: bool, exclude: Vec<String>, git_ignore: bool) -> Result<IndexUpdateSummary> {
+    let mut index = if rebuild {
+        ProjectIndex::new()
+    } else {
+        ProjectIndex::load(root).unwrap_or_else(|e| {
+            warn!("Failed to load project index, rebuilding: {}", e);
+            ProjectIndex::new()
+        })
+    };
+
+    let files = collect_source_files(root, exclude, git_ignore).await?;
+    let summary = update_index(&mut index, root, files);
+    index.save(root)?;
+
+    info!(
+        "Project index: {} files scanned, {} re-indexed, {} removed",
+        summary.files_scanned, summary.files_reindexed, summary.files_removed
+    );
+    Ok(summary)
+}
+
+/// The project index of the files of an analysis under its directory targets, for
+/// cross-file dataflow: the saved index of each target is brought up to date in memory, so
+/// only changed files are parsed and nothing is written into the scanned tree. None when
+/// no target is a directory.
+pub fn index_for_analysis(targets: &[PathBuf], files: &[PathBuf]) -> Option<ProjectIndex> {
+    let mut indexes: Vec<(&PathBuf, ProjectIndex)> = targets
+        .iter()
+        .filter(|target| target.is_dir())
+        .map(|root| {
+            let mut index = ProjectIndex::load(root).unwrap_or_else(|e| {
+                warn!("Failed to load project index, rebuilding: {}", e);
+                ProjectIndex::new()
+            });
+            let root_files = files
+                .iter()
+                .filter(|file| file.starts_with(root))
+                .filter_map(|file| Some((file.clone(), Language::from_extension(file.extension()?.to_str()?)?)))
+                .collect();
+            update_index(&mut index, root, root_files);
+            (root, index)
+        })
+        .collect();
+
+    if indexes.len() <= 1 {
+        return indexes.pop().map(|(_, index)| index);
+    }
+    let mut combined = ProjectIndex::new();
+    for (root, index) in indexes {
+        combined.merge(root, index);
+    }
+    Some(combined)
+}
+
+/// Re-index the `files` under `root` whose content changed and drop the entries of other files
+fn update_index(index: &mut ProjectIndex, root: &Path, files: Vec<(PathBuf, Language)>) -> IndexUpdateSummary {
+    let registry = LanguageParserRegistry::new();
+    let mut summary = IndexUpdateSummary::default();
+    let mut seen = HashSet::new();
+
+    for (path, language) in files {
+        summary.files_scanned += 1;
+        let key = path
+            .strip_prefix(root)
+            .unwrap_or(&path)
+            .to_string_lossy()
+            .to_string();
+        seen.insert(key.clone());
+
+        let source = match std::fs::read_to_string(&path) {
+            Ok(source) => source,
+            Err(e) => {
+                debug!("Skipping unreadable file {}: {}", path.display(), e);
+                continue;
+            }
+        };
+        if index.is_up_to_date(&key, &ProjectIndex::content_hash(&source)) {
+            continue;
+        }
+
+        let parser = match registry.get_parser(language) {
+            Some(parser) => parser,
+            None => continue,
+        };
+        match parser.parse(&source, &path) {
+            Ok(ast) => {
+                index.index_file(&key, language, &source, ast.as_ref());
+                summary.files_reindexed += 1;
+            }
+            Err(e) => {
+                debug!("Failed to parse {} for indexing: {}", path.display(), e);
+                summary.parse_failures += 1;
+            }
+        }
+    }
+
+    summary.files_removed = index.retain_files(&seen);
+    summary
+}
+
+/// The source files under `root` that `analyze` would scan, with their languages
+async fn collect_source_files(root: &Path, exclude: Vec<String>, git_ignore: bool) -> Result<Vec<(PathBuf, Language)>> {
+    let config = EnhancedAnalysisConfig {
+        target_paths: vec![root.to_path_buf()],
+        languages: astgrep_core::constants::languages::ALL_LANGUAGES.to_vec(),
+        exclude_patterns: exclude,
+        git_ignore,
+        ..Default::default()
+    };
+    let mut skipped = Vec::new();
+    let files = collect_target_files(&config, &mut skipped).await?;
+    Ok(files
+        .into_iter()
+        .filter_map(|path| {
+            let language = path.extension().and_then(|e| e.to_str()).and_then(Language::from_extension)?;
+            Some((path, language))
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_refresh_is_incremental() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("app.py"), "def main():\n    helper()\n").unwrap();
+
+        let first = refresh_project_index(dir.path(), false, Vec::new(), true).await.unwrap();
+        assert_eq!(first.files_scanned, 1);
+        assert_eq!(first.files_reindexed, 1);
+        assert!(ProjectIndex::index_path(dir.path()).exists());
+
+        let second = refresh_project_index(dir.path(), false, Vec::new(), true).await.unwrap();
+        assert_eq!(second.files_reindexed, 0);
+
+        std::fs::remove_file(dir.path().join("app.py")).unwrap();
+        let third = refresh_project_index(dir.path(), false, Vec::new(), true).await.unwrap();
+        assert_eq!(third.files_removed, 1);
+    }
+
+    #[tokio::test]
+    async fn test_refresh_skips_ignored_and_excluded_files() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("vendor")).unwrap();
+        std::fs::write(dir.path().join(".astgrepignore"), "vendor/\n").unwrap();
+        for name in ["app.py", "app_test.py", "vendor/lib.py"] {
+            std::fs::write(dir.path().join(name), "def main():\n    helper()\n").unwrap();
+        }
+
+        let summary = refresh_project_index(dir.path(), false, vec!["*_test.py".to_string()], true).await.unwrap();
+        assert_eq!(summary.files_scanned, 1);
+    }
+
+    #[tokio::test]
+    async fn test_dataflow_follows_the_index_across_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let rules = dir.path().join("rules.yaml");
+        std::fs::write(
+            &rules,
+            "rules:\n  - id: js-eval-input\n    message: eval of user input\n    severity: ERROR\n    languages: [javascript]\n    dataflow:\n      sources: [\"getUserInput()\"]\n      sinks: [\"eval($X)\"]\n",
+        )
+        .unwrap();
+        let project = dir.path().join("project");
+        std::fs::create_dir(&project).unwrap();
+        std::fs::write(project.join("handler.js"), "function handle() {\n  const input = getUserInput();\n  save(input);\n}\n").unwrap();
+        std::fs::write(project.join("store.js"), "function save(value) {\n  eval(value);\n}\nfunction unused(value) {\n  eval(value);\n}\n").unwrap();
+
+        let report = dir.path().join("report.json");
+        let flows = |enable_dataflow: bool| {
+            let config = EnhancedAnalysisConfig {
+                target_paths: vec![project.clone()],
+                rule_files: vec![rules.clone()],
+                languages: vec![Language::JavaScript],
+                enable_dataflow,
+                ..Default::default()
+            };
+            let report = report.clone();
+            async move {
+                crate::commands::analyze_enhanced::run_enhanced(config, Some(report.clone())).await.unwrap();
+                let findings = crate::commands::convert::Report::read(&report).unwrap().findings;
+                findings.iter().map(|f| (f.location.file.file_name().unwrap().to_string_lossy().into_owned(), f.location.start_line)).collect::<Vec<_>>()
+            }
+        };
+
+        // Only the sink that handle() passes its input to is reached
+        assert!(flows(false).await.is_empty());
+        assert_eq!(flows(true).await, vec![("store.js".to_string(), 2)]);
+        assert!(!ProjectIndex::index_path(&project).exists());
+    }
+}
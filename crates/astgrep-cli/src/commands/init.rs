@@ -90,7 +90,14 @@ fn generate_default_config() -> String {
         enabled = false\n\
         \n\
         [integrations.slack]\n\
-        enabled = false\n"
+        enabled = false\n\
+        \n\
+        # Scan profiles selectable with 'analyze --profile-name <name>'\n\
+        # (built-in: fast-ci, deep-audit)\n\
+        # [profiles.nightly]\n\
+        # dataflow = true\n\
+        # anchor_prefilter = false\n\
+        # time_budget_secs = 1800\n"
     )
 }
 
@@ -0,0 +1,245 @@
+//! Per-file error isolation and timeouts
+//!
+//! A file that fails to parse, makes a rule panic or sends the matcher into a pathological
+//! case should cost the run that file, not the whole report. Each file is analyzed through
+//! [`run_isolated`]: errors and panics become a [`FailedFile`] recorded in the run
+//! statistics. With `--timeout`, a single watchdog thread cancels the token of a file once
+//! the limit passes; the analysis stops at its next cancellation check and the file is
+//! reported as timed out. Tree-sitter parsing, the matchers and their ellipsis
+//! backtracking all poll the token, so a pathological file or rule stops promptly.
+
+use anyhow::Result;
+use astgrep_core::CancellationToken;
+use serde::{Deserialize, Serialize};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// Default of `--timeout`, in seconds
+pub const DEFAULT_FILE_TIMEOUT_SECS: u64 = 30;
+
+/// How often the watchdog looks at the deadlines and the run's cancellation token
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Why the analysis of a file was aborted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FailureReason {
+    /// Parsing and rule execution took longer than `--timeout`
+    Timeout,
+    /// Parsing or rule execution failed or panicked
+    Error,
+}
+
+/// A target file whose analysis was aborted; its findings are missing from the report
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FailedFile {
+    pub file: String,
+    pub reason: FailureReason,
+    pub message: String,
+}
+
+impl FailedFile {
+    fn new(file: &Path, reason: FailureReason, message: String) -> Self {
+        warn!("Aborted analysis of {}: {}", file.display(), message);
+        Self { file: file.display().to_string(), reason, message }
+    }
+}
+
+/// Run `analyze` for `file` on the current thread, turning its error, panic or, when
+/// `timeout` is set, overrun into a [`FailedFile`]. `analyze` receives the token to poll;
+/// it is `cancellation` itself without a timeout and otherwise a token of the file that the
+/// run's cancellation and the watchdog both set.
+pub fn run_isolated<T, F>(file: &Path, timeout: Option<Duration>, cancellation: &CancellationToken, analyze: F) -> Result<T, FailedFile>
+where
+    F: FnOnce(CancellationToken) -> Result<T>,
+{
+    let failed = |reason, message| Err(FailedFile::new(file, reason, message));
+    let watch = match timeout {
+        Some(timeout) => match Watch::start(timeout, cancellation) {
+            Some(watch) => Some(watch),
+            None => return failed(FailureReason::Error, "cannot start the timeout watchdog".to_string()),
+        },
+        None => None,
+    };
+    let token = watch.as_ref().map_or_else(|| cancellation.clone(), |watch| watch.state.token.clone());
+
+    let result = panic::catch_unwind(AssertUnwindSafe(|| analyze(token)));
+    if let (Some(watch), Some(timeout)) = (watch, timeout) {
+        if watch.state.timed_out.load(Ordering::Relaxed) {
+            return failed(FailureReason::Timeout, format!("exceeded the {}s timeout", timeout.as_secs_f64()));
+        }
+    }
+    match result {
+        Ok(result) => result.or_else(|e| failed(FailureReason::Error, e.to_string())),
+        Err(_) => failed(FailureReason::Error, "analysis panicked".to_string()),
+    }
+}
+
+/// Deadline of one file, checked by the watchdog until the file is done
+struct WatchState {
+    deadline: Instant,
+    token: CancellationToken,
+    run: CancellationToken,
+    timed_out: AtomicBool,
+    done: AtomicBool,
+}
+
+/// Registration of a file with the watchdog, withdrawn on drop
+struct Watch {
+    state: Arc<WatchState>,
+}
+
+impl Watch {
+    /// Register a file that may run for `timeout`; None when the watchdog cannot be started
+    fn start(timeout: Duration, run: &CancellationToken) -> Option<Self> {
+        let state = Arc::new(WatchState {
+            deadline: Instant::now() + timeout,
+            token: CancellationToken::new(),
+            run: run.clone(),
+            timed_out: AtomicBool::new(false),
+            done: AtomicBool::new(false),
+        });
+        watchdog()?.send(Arc::clone(&state)).ok()?;
+        Some(Self { state })
+    }
+}
+
+impl Drop for Watch {
+    fn drop(&mut self) {
+        self.state.done.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Sender to the watchdog thread shared by all files of the process, started on first use
+fn watchdog() -> Option<&'static Sender<Arc<WatchState>>> {
+    static WATCHDOG: OnceLock<Option<Sender<Arc<WatchState>>>> = OnceLock::new();
+    WATCHDOG
+        .get_or_init(|| {
+            let (sender, receiver) = mpsc::channel::<Arc<WatchState>>();
+            let spawned = std::thread::Builder::new().name("astgrep-watchdog".to_string()).spawn(move || {
+                let mut watched: Vec<Arc<WatchState>> = Vec::new();
+                loop {
+                    match receiver.recv_timeout(POLL_INTERVAL) {
+                        Ok(state) => watched.push(state),
+                        Err(RecvTimeoutError::Timeout) => {}
+                        Err(RecvTimeoutError::Disconnected) => return,
+                    }
+                    let now = Instant::now();
+                    watched.retain(|state| {
+                        if state.done.load(Ordering::Relaxed) {
+                            return false;
+                        }
+                        if now >= state.deadline {
+                            state.timed_out.store(true, Ordering::Relaxed);
+                            state.token.cancel();
+                            return false;
+                        }
+                        if state.run.is_cancelled() {
+                            state.token.cancel();
+                        }
+                        true
+                    });
+                }
+            });
+            match spawned {
+                Ok(_) => Some(sender),
+                Err(e) => {
+                    warn!("Cannot start the timeout watchdog: {}", e);
+                    None
+                }
+            }
+        })
+        .as_ref()
+}
+
+/// Failed files counted by reason, e.g. `1 timed out, 2 errors`
+pub fn describe(failed: &[FailedFile]) -> String {
+    let count = |reason| failed.iter().filter(|file| file.reason == reason).count();
+    let parts: Vec<String> = [(FailureReason::Timeout, "timed out"), (FailureReason::Error, "errors")]
+        .into_iter()
+        .map(|(reason, label)| (count(reason), label))
+        .filter(|(n, _)| *n > 0)
+        .map(|(n, label)| format!("{} {}", n, label))
+        .collect();
+    parts.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_isolated() {
+        let file = Path::new("src/app.py");
+        let run = CancellationToken::new();
+        let timeout = Some(Duration::from_secs(10));
+
+        assert_eq!(run_isolated(file, timeout, &run, |_| Ok(3)), Ok(3));
+        assert_eq!(run_isolated(file, None, &run, |_| Ok(4)), Ok(4));
+
+        let error = run_isolated(file, None, &run, |_| -> Result<()> { Err(anyhow::anyhow!("parse error")) }).unwrap_err();
+        assert_eq!((error.reason, error.message.as_str()), (FailureReason::Error, "parse error"));
+        for timeout in [timeout, None] {
+            let panicked = run_isolated(file, timeout, &run, |_| -> Result<()> { panic!("rule bug") }).unwrap_err();
+            assert_eq!((panicked.reason, panicked.message.as_str()), (FailureReason::Error, "analysis panicked"));
+        }
+
+        // A hanging file is told to stop through its token
+        let hung = run_isolated(file, Some(Duration::from_millis(200)), &run, |token| -> Result<()> {
+            while !token.is_cancelled() {
+                std::thread::sleep(Duration::from_millis(5));
+            }
+            Ok(())
+        })
+        .unwrap_err();
+        assert_eq!((hung.reason, hung.file.as_str()), (FailureReason::Timeout, "src/app.py"));
+
+        assert_eq!(describe(&[hung, error.clone(), error]), "1 timed out, 2 errors");
+    }
+
+    #[test]
+    fn test_run_cancellation_reaches_watched_files() {
+        let run = CancellationToken::new();
+        run.cancel();
+        let stopped = run_isolated(Path::new("src/app.py"), Some(Duration::from_secs(10)), &run, |token| -> Result<bool> {
+            while !token.is_cancelled() {
+                std::thread::sleep(Duration::from_millis(5));
+            }
+            Ok(true)
+        });
+        assert_eq!(stopped, Ok(true));
+    }
+
+    #[test]
+    fn test_timeout_stops_a_slow_rule() {
+        use crate::commands::analyze_enhanced::{analyze_file_isolated, EngineWorker};
+
+        // Three ellipses over a thousand distinct arguments backtrack through every split
+        let dir = tempfile::tempdir().unwrap();
+        let rules = dir.path().join("rules.yaml");
+        std::fs::write(
+            &rules,
+            "rules:\n  - id: js-repeated-argument\n    message: repeated argument\n    severity: WARNING\n    languages: [javascript]\n    pattern: f(..., $A, ..., $B, ..., $A, ...)\n",
+        )
+        .unwrap();
+        let file = dir.path().join("app.js");
+        let args: Vec<String> = (0..1000).map(|i| format!("a{}", i)).collect();
+        std::fs::write(&file, format!("f({});\n", args.join(", "))).unwrap();
+
+        let config = crate::EnhancedAnalysisConfig {
+            rule_files: vec![rules],
+            languages: vec![astgrep_core::Language::JavaScript],
+            file_timeout: Some(Duration::from_millis(500)),
+            ..Default::default()
+        };
+        let start = Instant::now();
+        let failed = analyze_file_isolated(file, &Arc::new(config), &mut EngineWorker::default(), &CancellationToken::new()).unwrap_err();
+        assert_eq!(failed.reason, FailureReason::Timeout);
+        assert!(start.elapsed() < Duration::from_secs(10), "{:?}", start.elapsed());
+    }
+}
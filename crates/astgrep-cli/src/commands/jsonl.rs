@@ -0,0 +1,117 @@
+//! JSON Lines output
+//!
+//! `--format jsonl` writes one finding per line, as the JSON report writes it in
+//! `findings`, with no enclosing document. Findings are written as soon as the file they
+//! are in has been analyzed instead of at the end of the run, so a scan of a large
+//! repository does not hold every finding in memory and consumers can start on the first
+//! lines right away. Lines of one file are written together; files come in the order they
+//! complete.
+//!
+//! Options that work on the findings of the whole run (`--sort-by-risk`, `--baseline-out`,
+//! `--record`, `--emit-patches`, `--emit-tests`, `--fix`, `--interactive`,
+//! `--metrics-out`, `--output-template`, `--compatible`) and workspace scans turn
+//! streaming off; the lines are then written at the end.
+
+use anyhow::Result;
+use std::io::Write;
+use std::sync::Mutex;
+use crate::commands::analyze_enhanced::Finding;
+
+/// Render `findings`, one JSON object per line
+pub fn render(findings: &[Finding]) -> Result<String> {
+    let mut out = String::new();
+    for finding in findings {
+        out.push_str(&serde_json::to_string(finding)?);
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// Writes findings as JSON lines from any worker, flushing after each batch
+pub struct JsonlWriter {
+    inner: Mutex<(Box<dyn Write + Send>, usize)>,
+    /// Findings written at most, from `--max-findings`
+    limit: Option<usize>,
+}
+
+impl JsonlWriter {
+    pub fn new(writer: Box<dyn Write + Send>, limit: Option<usize>) -> Self {
+        Self { inner: Mutex::new((writer, 0)), limit }
+    }
+
+    /// Write `findings`, up to the limit, and flush so they reach the consumer before the
+    /// next file
+    pub fn write(&self, findings: &[Finding]) -> Result<()> {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let remaining = self.limit.map_or(findings.len(), |limit| limit.saturating_sub(inner.1));
+        let findings = &findings[..findings.len().min(remaining)];
+        if findings.is_empty() {
+            return Ok(());
+        }
+        inner.0.write_all(render(findings)?.as_bytes())?;
+        inner.0.flush()?;
+        inner.1 += findings.len();
+        Ok(())
+    }
+
+    /// Number of findings written so far
+    pub fn written(&self) -> usize {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner()).1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::analyze_enhanced::test_finding;
+    use std::sync::Arc;
+
+    fn finding(rule_id: &str, line: usize) -> Finding {
+        Finding { fingerprint: Some("0123456789abcdef".to_string()), ..test_finding(rule_id, "src/app.js", line) }
+    }
+
+    /// A writer whose output can be read while it is owned by a [`JsonlWriter`]
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_writes_one_finding_per_line_as_batches_arrive() {
+        let out = Shared::default();
+        let writer = JsonlWriter::new(Box::new(out.clone()), None);
+        writer.write(&[finding("js-eval", 3), finding("js-eval", 7)]).unwrap();
+        assert_eq!(String::from_utf8(out.0.lock().unwrap().clone()).unwrap().lines().count(), 2);
+        writer.write(&[]).unwrap();
+        writer.write(&[finding("js-exec", 1)]).unwrap();
+        assert_eq!(writer.written(), 3);
+
+        let text = String::from_utf8(out.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<serde_json::Value> = text.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[1]["location"]["start_line"], 7);
+        assert_eq!(lines[2]["rule_id"], "js-exec");
+        assert_eq!(lines[2]["fingerprint"], "0123456789abcdef");
+        assert_eq!(text, render(&[finding("js-eval", 3), finding("js-eval", 7), finding("js-exec", 1)]).unwrap());
+    }
+
+    #[test]
+    fn test_stops_at_the_limit() {
+        let out = Shared::default();
+        let writer = JsonlWriter::new(Box::new(out.clone()), Some(3));
+        writer.write(&[finding("js-eval", 1), finding("js-eval", 2)]).unwrap();
+        writer.write(&[finding("js-eval", 3), finding("js-eval", 4)]).unwrap();
+        writer.write(&[finding("js-eval", 5)]).unwrap();
+        assert_eq!(writer.written(), 3);
+        let text = String::from_utf8(out.0.lock().unwrap().clone()).unwrap();
+        assert_eq!(text.lines().count(), 3);
+        assert!(text.lines().last().unwrap().contains("\"start_line\":3"));
+    }
+}
@@ -0,0 +1,191 @@
+//! Registry credentials
+//!
+//! `astgrep login` reads an API token from stdin and stores it for the host of a registry
+//! in `credentials.toml` of the config directory (`$ASTGREP_CONFIG_DIR`,
+//! `$XDG_CONFIG_HOME/astgrep` or `~/.config/astgrep`), readable only by the user.
+//! `astgrep logout` removes it. The token is sent as a bearer token when `p/<pack>`
+//! shorthands are downloaded from that host and when `update` clones or pulls a rules
+//! repository over HTTPS there, so private rule packs can be used.
+//!
+//! `ASTGREP_REGISTRY_TOKEN` takes precedence over the stored tokens, for CI jobs that
+//! get the token from a secret. Like a stored token it is bound to one host: it is only
+//! sent to the host of the registry named by `ASTGREP_REGISTRY_URL`, never to other
+//! repositories `update` talks to. Tokens are never put on a command line, where other
+//! users could read them from the process list.
+
+use anyhow::{anyhow, Context as _, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+use crate::commands::registry::registry_url;
+
+const CREDENTIALS_FILE: &str = "credentials.toml";
+
+/// Stored API tokens by registry host
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Credentials {
+    #[serde(default)]
+    pub tokens: BTreeMap<String, String>,
+}
+
+impl Credentials {
+    /// The credentials at `path`, empty when the file does not exist
+    pub fn load_from(path: &Path) -> Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(text) => toml::from_str(&text).with_context(|| format!("cannot read credentials {}", path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(anyhow!("cannot read credentials {}: {}", path.display(), e)),
+        }
+    }
+
+    /// Write the credentials to `path`, readable and writable only by the user
+    pub fn save_to(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let partial = path.with_extension("toml.part");
+        let _ = std::fs::remove_file(&partial);
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options.open(&partial).with_context(|| format!("cannot write {}", partial.display()))?;
+        file.write_all(toml::to_string(self)?.as_bytes())?;
+        drop(file);
+        std::fs::rename(&partial, path)?;
+        Ok(())
+    }
+
+    /// The token stored for the host of `url`
+    pub fn token(&self, url: &str) -> Option<&str> {
+        self.tokens.get(&host(url)?).map(String::as_str)
+    }
+}
+
+/// Where tokens are stored
+pub fn credentials_path() -> PathBuf {
+    std::env::var_os("ASTGREP_CONFIG_DIR")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("XDG_CONFIG_HOME").map(|dir| PathBuf::from(dir).join("astgrep")))
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config").join("astgrep")))
+        .unwrap_or_else(|| PathBuf::from(".astgrep"))
+        .join(CREDENTIALS_FILE)
+}
+
+/// The token to send to `url`: `ASTGREP_REGISTRY_TOKEN` for the configured registry host,
+/// else the stored token of its host. Only HTTPS URLs get a token.
+pub fn token_for(url: &str) -> Option<String> {
+    if !url.starts_with("https://") {
+        return None;
+    }
+    if registry_url().is_some_and(|registry| same_host(url, &registry)) {
+        if let Some(token) = std::env::var("ASTGREP_REGISTRY_TOKEN").ok().filter(|t| !t.trim().is_empty()) {
+            return Some(token.trim().to_string());
+        }
+    }
+    let credentials = Credentials::load_from(&credentials_path()).ok()?;
+    credentials.token(url).map(str::to_string)
+}
+
+/// The lowercase `host[:port]` of an `http(s)://` URL
+pub fn host(url: &str) -> Option<String> {
+    let rest = url.strip_prefix("https://").or_else(|| url.strip_prefix("http://"))?;
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host = authority.rsplit('@').next()?;
+    (!host.is_empty()).then(|| host.to_lowercase())
+}
+
+/// Whether two URLs name the same `host[:port]`
+fn same_host(url: &str, other: &str) -> bool {
+    matches!((host(url), host(other)), (Some(a), Some(b)) if a == b)
+}
+
+/// Store a token read from stdin for the host of `registry`
+pub async fn run(registry: Option<String>) -> Result<()> {
+    let registry = registry_or_env(registry)?;
+    let host = host(&registry).ok_or_else(|| anyhow!("{} is not an http(s) URL", registry))?;
+
+    let stdin = std::io::stdin();
+    if stdin.is_terminal() {
+        eprint!("API token for {}: ", host);
+        std::io::stderr().flush()?;
+    }
+    let mut token = String::new();
+    stdin.lock().read_line(&mut token)?;
+    let token = token.trim();
+    if token.is_empty() || token.contains(char::is_whitespace) {
+        return Err(anyhow!("expected a single token on stdin"));
+    }
+
+    let path = credentials_path();
+    let mut credentials = Credentials::load_from(&path)?;
+    credentials.tokens.insert(host.clone(), token.to_string());
+    credentials.save_to(&path)?;
+    info!("Stored token in {}", path.display());
+    println!("✅ Logged in to {}", host);
+    Ok(())
+}
+
+/// Remove the stored token of the host of `registry`
+pub async fn logout(registry: Option<String>) -> Result<()> {
+    let registry = registry_or_env(registry)?;
+    let host = host(&registry).ok_or_else(|| anyhow!("{} is not an http(s) URL", registry))?;
+
+    let path = credentials_path();
+    let mut credentials = Credentials::load_from(&path)?;
+    if credentials.tokens.remove(&host).is_none() {
+        println!("Not logged in to {}", host);
+        return Ok(());
+    }
+    credentials.save_to(&path)?;
+    println!("✅ Logged out of {}", host);
+    Ok(())
+}
+
+/// `registry`, else the registry named by `ASTGREP_REGISTRY_URL`
+fn registry_or_env(registry: Option<String>) -> Result<String> {
+    registry.or_else(registry_url).ok_or_else(|| anyhow!("no rule registry given: pass its URL or set ASTGREP_REGISTRY_URL"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hosts() {
+        assert_eq!(host("https://Rules.Example.com/packs/p.astgrep-pack").as_deref(), Some("rules.example.com"));
+        assert_eq!(host("https://user@git.example.com:8443/org/rules.git").as_deref(), Some("git.example.com:8443"));
+        assert_eq!(host("http://localhost?x=1").as_deref(), Some("localhost"));
+        assert_eq!(host("git@github.com:astgrep/rules.git"), None);
+        assert_eq!(host("https:///rules"), None);
+
+        // The environment token follows the registry host only
+        assert!(same_host("https://rules.example.com/org/rules.git", "https://Rules.Example.com/packs"));
+        assert!(!same_host("https://git.example.org/org/rules.git", "https://rules.example.com/packs"));
+        assert!(!same_host("https://rules.example.com:8443/packs", "https://rules.example.com/packs"));
+    }
+
+    #[test]
+    fn test_credentials_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("astgrep").join(CREDENTIALS_FILE);
+        assert!(Credentials::load_from(&path).unwrap().tokens.is_empty());
+
+        let mut credentials = Credentials::default();
+        credentials.tokens.insert("rules.example.com".to_string(), "s3cret".to_string());
+        credentials.save_to(&path).unwrap();
+        credentials.save_to(&path).unwrap();
+
+        let loaded = Credentials::load_from(&path).unwrap();
+        assert_eq!(loaded.token("https://rules.example.com/packs/a.astgrep-pack"), Some("s3cret"));
+        assert_eq!(loaded.token("https://other.example.com/packs/a.astgrep-pack"), None);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+    }
+}
@@ -0,0 +1,379 @@
+//! Language server
+//!
+//! `astgrep lsp` speaks the Language Server Protocol over stdio: documents are analyzed
+//! with the rule engine when they are opened, changed or saved, the findings are
+//! published as diagnostics, and findings whose rule has a fix are offered as quick-fix
+//! code actions. Documents are synchronized in full, so the text analyzed is always the
+//! editor's, saved or not. Logs go to stderr; stdout carries only protocol messages.
+
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+use tracing::{debug, info, warn};
+use crate::commands::analyze_enhanced::{analyze_source, Finding};
+use crate::vscode_integration::VsCodeDiagnostic;
+use crate::EnhancedAnalysisConfig;
+
+/// JSON-RPC error codes used by the server
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_REQUEST: i64 = -32600;
+
+/// Serve the rules under `rule_files` on stdin and stdout until the client exits
+pub async fn run(rule_files: Vec<PathBuf>) -> Result<()> {
+    let config = EnhancedAnalysisConfig { rule_files, ..EnhancedAnalysisConfig::default() };
+    let mut server = LanguageServer::new(config);
+    info!("Language server listening on stdio");
+
+    let stdin = std::io::stdin();
+    let mut reader = stdin.lock();
+    let stdout = std::io::stdout();
+    let mut writer = stdout.lock();
+    loop {
+        let message = match read_message(&mut reader) {
+            Ok(Some(message)) => message,
+            Ok(None) => break,
+            Err(e) => {
+                warn!("Ignoring malformed message: {}", e);
+                continue;
+            }
+        };
+        for reply in server.handle(&message) {
+            write_message(&mut writer, &reply)?;
+        }
+        if let Some(code) = server.exit_code() {
+            if code != 0 {
+                std::process::exit(code);
+            }
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// An open document and the findings last published for it
+struct Document {
+    path: PathBuf,
+    text: String,
+    findings: Vec<Finding>,
+}
+
+/// Protocol state of the server; [`LanguageServer::handle`] maps each client message to
+/// the messages sent back
+pub struct LanguageServer {
+    config: EnhancedAnalysisConfig,
+    documents: HashMap<String, Document>,
+    shutdown_requested: bool,
+    exit_code: Option<i32>,
+}
+
+impl LanguageServer {
+    pub fn new(config: EnhancedAnalysisConfig) -> Self {
+        Self { config, documents: HashMap::new(), shutdown_requested: false, exit_code: None }
+    }
+
+    /// Exit status once the client sent `exit`: 0 after a `shutdown` request, 1 otherwise
+    pub fn exit_code(&self) -> Option<i32> {
+        self.exit_code
+    }
+
+    /// The responses and notifications answering `message`
+    pub fn handle(&mut self, message: &Value) -> Vec<Value> {
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+        match (message.get("method").and_then(Value::as_str), message.get("id")) {
+            (Some(method), Some(id)) => vec![self.request(id.clone(), method, &params)],
+            (Some(method), None) => self.notification(method, &params),
+            // Responses to server requests; the server sends none
+            (None, _) => Vec::new(),
+        }
+    }
+
+    fn request(&mut self, id: Value, method: &str, params: &Value) -> Value {
+        if self.shutdown_requested {
+            return error_response(id, INVALID_REQUEST, "the server is shutting down");
+        }
+        match method {
+            "initialize" => response(id, json!({
+                "capabilities": {
+                    "textDocumentSync": { "openClose": true, "change": 1, "save": true },
+                    "codeActionProvider": { "codeActionKinds": ["quickfix"] }
+                },
+                "serverInfo": { "name": "astgrep", "version": env!("CARGO_PKG_VERSION") }
+            })),
+            "shutdown" => {
+                self.shutdown_requested = true;
+                response(id, Value::Null)
+            }
+            "textDocument/codeAction" => response(id, Value::Array(self.code_actions(params))),
+            _ => {
+                debug!("Unsupported request {}", method);
+                error_response(id, METHOD_NOT_FOUND, &format!("unsupported method {}", method))
+            }
+        }
+    }
+
+    fn notification(&mut self, method: &str, params: &Value) -> Vec<Value> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default().to_string();
+        match method {
+            "textDocument/didOpen" => {
+                let Some(path) = uri_to_path(&uri) else {
+                    debug!("Not analyzing {}: not a file URI", uri);
+                    return Vec::new();
+                };
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default().to_string();
+                self.documents.insert(uri.clone(), Document { path, text, findings: Vec::new() });
+                self.analyze(&uri)
+            }
+            "textDocument/didChange" => {
+                // Full synchronization: the last change holds the whole text
+                let text = params["contentChanges"].as_array().and_then(|changes| changes.last()).and_then(|c| c["text"].as_str());
+                match (self.documents.get_mut(&uri), text) {
+                    (Some(document), Some(text)) => document.text = text.to_string(),
+                    _ => return Vec::new(),
+                }
+                self.analyze(&uri)
+            }
+            "textDocument/didSave" => self.analyze(&uri),
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                vec![publish_diagnostics(&uri, Vec::new())]
+            }
+            "exit" => {
+                self.exit_code = Some(if self.shutdown_requested { 0 } else { 1 });
+                Vec::new()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Re-analyze the document at `uri` and publish its diagnostics; a failed analysis
+    /// keeps the previous diagnostics
+    fn analyze(&mut self, uri: &str) -> Vec<Value> {
+        let Some(document) = self.documents.get_mut(uri) else {
+            return Vec::new();
+        };
+        match analyze_source(&document.path, &document.text, &self.config) {
+            Ok(findings) => document.findings = findings,
+            Err(e) => {
+                warn!("Cannot analyze {}: {}", document.path.display(), e);
+                return Vec::new();
+            }
+        }
+        let diagnostics = document.findings.iter().map(|finding| diagnostic(finding, &document.text)).collect();
+        vec![publish_diagnostics(uri, diagnostics)]
+    }
+
+    /// Quick fixes for the findings with a fix that overlap the requested range
+    fn code_actions(&self, params: &Value) -> Vec<Value> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        let Some(document) = self.documents.get(uri) else {
+            return Vec::new();
+        };
+        let line = |key: &str| params["range"][key]["line"].as_u64().map(|l| l as usize + 1);
+        let (first, last) = (line("start").unwrap_or(1), line("end").unwrap_or(usize::MAX));
+        let repository = astgrep_rules::RuleRepository::from_paths(&self.config.rule_files);
+
+        document
+            .findings
+            .iter()
+            .filter(|f| f.fix.is_some() && f.location.start_line <= last && f.location.end_line >= first)
+            .filter_map(|finding| {
+                let fix = crate::commands::autofix::render_fix(finding, &document.path, &document.text, &repository)?;
+                let diagnostic = diagnostic(finding, &document.text);
+                Some(json!({
+                    "title": format!("Apply fix for {}", finding.rule_id),
+                    "kind": "quickfix",
+                    "diagnostics": [diagnostic],
+                    "isPreferred": true,
+                    "edit": { "changes": { uri: [{ "range": diagnostic["range"], "newText": fix }] } }
+                }))
+            })
+            .collect()
+    }
+}
+
+/// The LSP diagnostic of `finding`, whose 1-based character columns become 0-based
+/// UTF-16 offsets
+fn diagnostic(finding: &Finding, text: &str) -> Value {
+    vscode_diagnostic(finding, text).to_vscode_format()
+}
+
+/// The VS Code diagnostic of `finding` in `text`
+pub(crate) fn vscode_diagnostic(finding: &Finding, text: &str) -> VsCodeDiagnostic {
+    let loc = &finding.location;
+    let (line, column) = position(text, loc.start_line, loc.start_column);
+    let (end_line, end_column) = position(text, loc.end_line, loc.end_column);
+    let severity = match finding.severity {
+        astgrep_core::Severity::Critical | astgrep_core::Severity::Error => "error",
+        astgrep_core::Severity::Warning => "warning",
+        astgrep_core::Severity::Info => "information",
+    };
+    VsCodeDiagnostic::new(
+        loc.file.display().to_string(),
+        line,
+        column,
+        finding.message.clone(),
+        severity.to_string(),
+        finding.rule_id.clone(),
+    )
+    .with_end_position(end_line, end_column)
+}
+
+/// 0-based line and UTF-16 character of the 1-based `line` and character `column`
+fn position(text: &str, line: usize, column: usize) -> (u32, u32) {
+    let line_text = text.lines().nth(line.saturating_sub(1)).unwrap_or_default();
+    let character: usize = line_text.chars().take(column.saturating_sub(1)).map(char::len_utf16).sum();
+    (line.saturating_sub(1) as u32, character as u32)
+}
+
+fn publish_diagnostics(uri: &str, diagnostics: Vec<Value>) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/publishDiagnostics",
+        "params": { "uri": uri, "diagnostics": diagnostics }
+    })
+}
+
+fn response(id: Value, result: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "result": result })
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+/// Path of a `file://` URI, percent-decoded
+fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let path = uri.strip_prefix("file://")?;
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|h| std::str::from_utf8(h).ok()).and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    let path = String::from_utf8(decoded).ok()?;
+    // `file:///C:/src` names `C:/src` on Windows
+    let path = match path.as_bytes() {
+        [b'/', drive, b':', ..] if cfg!(windows) && drive.is_ascii_alphabetic() => path[1..].to_string(),
+        _ => path,
+    };
+    Some(PathBuf::from(path))
+}
+
+/// Read one `Content-Length` framed message; `None` at the end of the input
+fn read_message(reader: &mut impl BufRead) -> Result<Option<Value>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = Some(value.trim().parse::<usize>()?);
+            }
+        }
+    }
+    let length = length.ok_or_else(|| anyhow!("message without a Content-Length header"))?;
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    Ok(Some(serde_json::from_slice(&body)?))
+}
+
+fn write_message(writer: &mut impl Write, message: &Value) -> Result<()> {
+    let body = serde_json::to_string(message)?;
+    write!(writer, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::analyze_enhanced::{test_finding, Location};
+    use astgrep_core::Severity;
+
+    fn notification(method: &str, params: Value) -> Value {
+        json!({ "jsonrpc": "2.0", "method": method, "params": params })
+    }
+
+    #[test]
+    fn test_framing_round_trip() {
+        let mut buffer = Vec::new();
+        let message = json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": { "name": "é" } });
+        write_message(&mut buffer, &message).unwrap();
+        write_message(&mut buffer, &json!({ "jsonrpc": "2.0", "method": "exit" })).unwrap();
+
+        let mut reader = std::io::Cursor::new(buffer);
+        assert_eq!(read_message(&mut reader).unwrap(), Some(message));
+        assert_eq!(read_message(&mut reader).unwrap().unwrap()["method"], "exit");
+        assert_eq!(read_message(&mut reader).unwrap(), None);
+        assert!(read_message(&mut std::io::Cursor::new(b"X-Other: 1\r\n\r\n{}".to_vec())).is_err());
+    }
+
+    #[test]
+    fn test_lifecycle() {
+        let mut server = LanguageServer::new(EnhancedAnalysisConfig::default());
+        let init = server.handle(&json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {} }));
+        assert_eq!(init[0]["result"]["capabilities"]["textDocumentSync"]["change"], 1);
+        let unknown = server.handle(&json!({ "jsonrpc": "2.0", "id": 2, "method": "textDocument/hover", "params": {} }));
+        assert_eq!(unknown[0]["error"]["code"], METHOD_NOT_FOUND);
+
+        // Without rules a document has no diagnostics, and closing it clears them
+        let uri = "file:///tmp/my%20app/app.js";
+        let opened = server.handle(&notification("textDocument/didOpen", json!({ "textDocument": { "uri": uri, "languageId": "javascript", "version": 1, "text": "eval(x);\n" } })));
+        assert_eq!(opened[0]["params"]["diagnostics"], json!([]));
+        assert_eq!(server.documents[uri].path, PathBuf::from("/tmp/my app/app.js"));
+        let closed = server.handle(&notification("textDocument/didClose", json!({ "textDocument": { "uri": uri } })));
+        assert_eq!(closed[0]["method"], "textDocument/publishDiagnostics");
+
+        assert_eq!(server.handle(&json!({ "jsonrpc": "2.0", "id": 3, "method": "shutdown" }))[0]["result"], Value::Null);
+        assert!(server.exit_code().is_none());
+        server.handle(&notification("exit", Value::Null));
+        assert_eq!(server.exit_code(), Some(0));
+    }
+
+    #[test]
+    fn test_code_action_for_fix() {
+        let mut server = LanguageServer::new(EnhancedAnalysisConfig::default());
+        let uri = "file:///src/app.js";
+        let text = "const s = \"😀\"; eval(input);\n";
+        let finding = Finding {
+            severity: Severity::Warning,
+            location: Location::new("/src/app.js", 1, 16, 1, 27),
+            fix: Some("JSON.parse(input)".to_string()),
+            ..test_finding("js-eval", "/src/app.js", 1)
+        };
+        server.documents.insert(uri.to_string(), Document { path: PathBuf::from("/src/app.js"), text: text.to_string(), findings: vec![finding] });
+
+        let request = |line: u64| json!({ "jsonrpc": "2.0", "id": 7, "method": "textDocument/codeAction", "params": {
+            "textDocument": { "uri": uri },
+            "range": { "start": { "line": line, "character": 0 }, "end": { "line": line, "character": 0 } },
+            "context": { "diagnostics": [] }
+        } });
+        let actions = &server.handle(&request(0))[0]["result"];
+        assert_eq!(actions[0]["kind"], "quickfix");
+        assert_eq!(actions[0]["diagnostics"][0]["severity"], 2);
+        let edit = &actions[0]["edit"]["changes"][uri][0];
+        // The emoji is one character but two UTF-16 code units
+        assert_eq!(edit["range"]["start"], json!({ "line": 0, "character": 16 }));
+        assert_eq!(edit["range"]["end"], json!({ "line": 0, "character": 27 }));
+        assert_eq!(edit["newText"], "JSON.parse(input)");
+        assert_eq!(server.handle(&request(3))[0]["result"], json!([]));
+    }
+}
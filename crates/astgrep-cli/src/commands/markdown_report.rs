@@ -0,0 +1,208 @@
+//! Markdown report
+//!
+//! `--format markdown` writes a report meant to be pasted into a pull request comment: a
+//! summary table by severity and rule, then a section per file whose findings are
+//! `<details>` blocks, collapsed to a one-line summary with a severity badge and expanding
+//! to the rule, location, fix and source snippet. GitHub and GitLab both render the
+//! blocks.
+
+use crate::commands::analyze_enhanced::{AnalysisStatistics, Finding};
+use crate::commands::source_context;
+use astgrep_core::Severity;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::PathBuf;
+
+/// Lines of source shown around a finding unless `--context` says otherwise
+const SNIPPET_CONTEXT: usize = 1;
+
+const SEVERITIES: [Severity; 4] = [Severity::Critical, Severity::Error, Severity::Warning, Severity::Info];
+
+/// Render `findings` as a Markdown report
+pub fn render(findings: &[Finding], stats: &AnalysisStatistics, total_time: std::time::Duration, context: Option<usize>) -> String {
+    let context = context.unwrap_or(SNIPPET_CONTEXT);
+    let mut md = String::new();
+    md.push_str("# astgrep Analysis Report\n\n");
+    let _ = writeln!(md, "**Generated:** {}\n", chrono::Utc::now().format("%Y-%m-%d %H:%M:%S UTC"));
+
+    md.push_str("## Summary\n\n");
+    let _ = writeln!(md, "- **Total findings:** {}", findings.len());
+    let _ = writeln!(md, "- **Files analyzed:** {}", stats.files_analyzed);
+    let _ = writeln!(md, "- **Rules executed:** {}", stats.rules_executed);
+    let _ = writeln!(md, "- **Analysis time:** {:.2}s", total_time.as_secs_f64());
+    if !stats.failed_files.is_empty() {
+        let _ = writeln!(md, "- **Failed files:** {}", stats.failed_files.len());
+    }
+    if stats.status() != "completed" {
+        let _ = writeln!(md, "- **Status:** {} (partial results)", stats.status());
+    }
+    md.push('\n');
+
+    if findings.is_empty() {
+        md.push_str("No findings.\n");
+        return md;
+    }
+
+    md.push_str("| Severity | Findings |\n|---|---:|\n");
+    for severity in SEVERITIES {
+        let count = findings.iter().filter(|f| f.severity == severity).count();
+        if count > 0 {
+            let _ = writeln!(md, "| {} | {} |", badge(severity), count);
+        }
+    }
+    md.push('\n');
+
+    let mut rules: BTreeMap<&str, (Severity, usize)> = BTreeMap::new();
+    for finding in findings {
+        let entry = rules.entry(finding.rule_id.as_str()).or_insert((finding.severity, 0));
+        entry.0 = entry.0.max(finding.severity);
+        entry.1 += 1;
+    }
+    let mut rules: Vec<(&str, (Severity, usize))> = rules.into_iter().collect();
+    rules.sort_by(|a, b| b.1 .1.cmp(&a.1 .1).then_with(|| a.0.cmp(b.0)));
+    md.push_str("| Rule | Severity | Findings |\n|---|---|---:|\n");
+    for (rule_id, (severity, count)) in rules {
+        let _ = writeln!(md, "| {} | {} | {} |", code(rule_id), badge(severity), count);
+    }
+    md.push('\n');
+
+    md.push_str("## Findings\n\n");
+    for (file, mut group) in group_by_file(findings) {
+        group.sort_by_key(|f| (f.location.start_line, f.location.start_column));
+        let source = std::fs::read_to_string(&file).ok();
+        let _ = writeln!(md, "### {} ({})\n", code(&file.display().to_string()), group.len());
+        for finding in group {
+            render_finding(&mut md, finding, source.as_deref(), context);
+        }
+    }
+    md
+}
+
+fn render_finding(md: &mut String, finding: &Finding, source: Option<&str>, context: usize) {
+    let loc = &finding.location;
+    let _ = writeln!(
+        md,
+        "<details>\n<summary>{} <b>{}</b> {} (line {})</summary>\n",
+        emoji(finding.severity),
+        finding.severity.as_str(),
+        escape_html(&finding.message),
+        loc.start_line
+    );
+    let _ = writeln!(md, "- **Rule:** {}", code(&finding.rule_id));
+    let _ = writeln!(md, "- **Location:** {}", code(&format!("{}:{}:{}", loc.file.display(), loc.start_line, loc.start_column)));
+    let _ = writeln!(md, "- **Confidence:** {}", finding.confidence.as_str());
+    if let (Some(score), Some(effort)) = (finding.risk_score, finding.effort) {
+        let _ = writeln!(md, "- **Risk:** {}/100 (effort: {})", score, effort.as_str());
+    }
+    if let Some(ref fix) = finding.fix {
+        let _ = writeln!(md, "- **Fix:** {}", code(fix));
+    }
+    if let Some(ref fingerprint) = finding.fingerprint {
+        let _ = writeln!(md, "- **Fingerprint:** {}", code(fingerprint));
+    }
+    if let Some(snippet) = source.and_then(|source| snippet(source, finding, context)) {
+        let fence = fence_for(&snippet);
+        let _ = writeln!(md, "\n{}\n{}{}", fence, snippet, fence);
+    }
+    md.push_str("\n</details>\n\n");
+}
+
+/// The finding's lines with `context` lines around them, numbered, with matched lines
+/// marked and the match redacted when the finding asks for it
+fn snippet(source: &str, finding: &Finding, context: usize) -> Option<String> {
+    let mut text = String::new();
+    for line in source_context::lines(source, finding, context)? {
+        let marker = if line.matched.is_some() { '>' } else { ' ' };
+        let _ = writeln!(text, "{}{:>5} | {}", marker, line.line, line.text);
+    }
+    Some(text)
+}
+
+/// Findings per file, in path order
+fn group_by_file(findings: &[Finding]) -> BTreeMap<PathBuf, Vec<&Finding>> {
+    let mut groups: BTreeMap<PathBuf, Vec<&Finding>> = BTreeMap::new();
+    for finding in findings {
+        groups.entry(finding.location.file.clone()).or_default().push(finding);
+    }
+    groups
+}
+
+fn emoji(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Critical => "🟣",
+        Severity::Error => "🔴",
+        Severity::Warning => "🟡",
+        Severity::Info => "🔵",
+    }
+}
+
+fn badge(severity: Severity) -> String {
+    format!("{} **{}**", emoji(severity), severity.as_str())
+}
+
+/// `text` as an inline code span, delimited by more backticks than it contains in a row
+fn code(text: &str) -> String {
+    let longest = text.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let ticks = "`".repeat(longest + 1);
+    if longest == 0 {
+        format!("{}{}{}", ticks, text, ticks)
+    } else {
+        format!("{} {} {}", ticks, text, ticks)
+    }
+}
+
+/// A code fence longer than any backtick run of `text`
+fn fence_for(text: &str) -> String {
+    let longest = text.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    "`".repeat(longest.max(2) + 1)
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::analyze_enhanced::{test_finding, Location};
+    use std::path::Path;
+
+    fn finding(file: &Path, rule_id: &str, severity: Severity, line: usize, message: &str) -> Finding {
+        Finding { message: message.to_string(), severity, location: Location::new(file, line, 1, line, 5), ..test_finding(rule_id, file, line) }
+    }
+
+    #[test]
+    fn test_render_sections_per_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let app = dir.path().join("app.js");
+        std::fs::write(&app, "a();\neval(x);\nb();\nc();\n").unwrap();
+        let util = dir.path().join("util.js");
+        let findings = vec![
+            finding(&util, "js-exec", Severity::Warning, 9, "exec is risky"),
+            finding(&app, "js-eval", Severity::Error, 2, "Avoid <eval>"),
+            finding(&app, "js-exec", Severity::Warning, 4, "exec is risky"),
+        ];
+        let md = render(&findings, &AnalysisStatistics::new(), std::time::Duration::from_millis(20), None);
+
+        assert!(md.contains("| 🔴 **ERROR** | 1 |\n| 🟡 **WARNING** | 2 |\n"));
+        assert!(md.contains("| `js-exec` | 🟡 **WARNING** | 2 |\n| `js-eval` | 🔴 **ERROR** | 1 |\n"));
+
+        let app_section = md.find(&format!("### `{}` (2)", app.display())).unwrap();
+        let util_section = md.find(&format!("### `{}` (1)", util.display())).unwrap();
+        assert!(app_section < util_section);
+        assert!(md.contains("<details>\n<summary>🔴 <b>ERROR</b> Avoid &lt;eval&gt; (line 2)</summary>\n\n- **Rule:** `js-eval`\n"));
+        assert!(md.contains("\n```\n     1 | a();\n>    2 | eval(x);\n     3 | b();\n```\n"));
+        assert_eq!(md.matches("<details>").count(), 3);
+        assert_eq!(md.matches("```\n").count(), 4);
+
+        let md = render(&findings[1..2], &AnalysisStatistics::new(), std::time::Duration::from_millis(20), Some(0));
+        assert!(md.contains("\n```\n>    2 | eval(x);\n```\n"));
+    }
+
+    #[test]
+    fn test_code_spans_survive_backticks() {
+        assert_eq!(code("js-eval"), "`js-eval`");
+        assert_eq!(code("a `b` c"), "`` a `b` c ``");
+        assert_eq!(fence_for("let s = ```x```;"), "````");
+    }
+}
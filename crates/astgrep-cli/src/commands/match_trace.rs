@@ -0,0 +1,107 @@
+//! Match trace file
+//!
+//! `--trace-matching <file>` writes the rule engine's match decisions to a file, one JSON
+//! object per line with the rule, pattern, file, matched node, location, metavariable
+//! bindings and decision, so rule authors can see why a rule did or did not report a
+//! finding without debug output on stdout. `--trace-rule <id>` keeps the events of the
+//! given rules only. The same events are available on stderr with
+//! `RUST_LOG=astgrep::match=trace`.
+
+use anyhow::{anyhow, Result};
+use astgrep_rules::MATCH_TRACE_TARGET;
+use serde_json::{Map, Value};
+use std::fs::File;
+use std::io::{LineWriter, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::layer::{Context, Layer};
+
+/// Layer writing match tracing events to a file as JSON lines
+pub struct MatchTraceLayer {
+    // Line buffered, so the trace is complete even when the process exits early
+    writer: Mutex<LineWriter<File>>,
+    /// Rules whose events are kept; all when empty
+    rules: Vec<String>,
+}
+
+impl MatchTraceLayer {
+    /// Trace the rules in `rules`, or all rules, to a new file at `path`. The layer is
+    /// meant to be installed with the [`targets`] filter.
+    pub fn create(path: &Path, rules: Vec<String>) -> Result<Self> {
+        let file = File::create(path).map_err(|e| anyhow!("Cannot create trace file {}: {}", path.display(), e))?;
+        Ok(Self { writer: Mutex::new(LineWriter::new(file)), rules })
+    }
+
+    fn keeps(&self, event: &Map<String, Value>) -> bool {
+        self.rules.is_empty() || event.get("rule").and_then(Value::as_str).is_some_and(|rule| self.rules.iter().any(|r| r == rule))
+    }
+}
+
+impl<S: Subscriber> Layer<S> for MatchTraceLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut fields = JsonFields(Map::new());
+        event.record(&mut fields);
+        fields.0.remove("message");
+        if !self.keeps(&fields.0) {
+            return;
+        }
+        let mut writer = self.writer.lock().unwrap_or_else(|e| e.into_inner());
+        let _ = writeln!(writer, "{}", Value::Object(fields.0));
+    }
+}
+
+/// Filter selecting match tracing events only
+pub fn targets() -> Targets {
+    Targets::new().with_target(MATCH_TRACE_TARGET, Level::TRACE)
+}
+
+/// Event fields as JSON values
+struct JsonFields(Map<String, Value>);
+
+impl Visit for JsonFields {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0.insert(field.name().to_string(), Value::String(format!("{:?}", value)));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), Value::String(value.to_string()));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_string(), Value::Bool(value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::layer::SubscriberExt;
+    use tracing_subscriber::Registry;
+
+    #[test]
+    fn test_writes_matching_rule_events_as_json_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("trace.jsonl");
+        let layer = MatchTraceLayer::create(&path, vec!["js-eval".to_string()]).unwrap().with_filter(targets());
+        tracing::subscriber::with_default(Registry::default().with(layer), || {
+            tracing::trace!(target: MATCH_TRACE_TARGET, rule = "js-eval", pattern = "eval($X)", decision = "reported", "match decision");
+            tracing::trace!(target: MATCH_TRACE_TARGET, rule = "js-todo", pattern = "TODO", decision = "reported", "match decision");
+            tracing::info!(rule = "js-eval", "not a match decision");
+        });
+
+        let trace = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<Value> = trace.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(lines, vec![serde_json::json!({ "rule": "js-eval", "pattern": "eval($X)", "decision": "reported" })]);
+    }
+}
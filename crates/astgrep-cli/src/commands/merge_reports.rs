@@ -0,0 +1,244 @@
+//! Report merging
+//!
+//! `merge-reports` combines the JSON or SARIF reports of several runs, e.g. the shards of
+//! a CI scan, into one report in any output format. Findings reported by more than one
+//! input are kept once: findings are the same when their fingerprints are, or, for
+//! findings without a fingerprint, when rule and location are. Statistics are combined:
+//! file counts, failed, skipped and degraded entries add up, the rule count and analysis
+//! time are those of the largest and slowest input, and the merged run is partial when any
+//! input is.
+//!
+//! SARIF carries less than the JSON report: severities come from the result level,
+//! confidence is taken as medium and file and rule counts are not known.
+
+use anyhow::{anyhow, Result};
+use astgrep_core::{Confidence, Severity};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::info;
+
+use crate::commands::analyze_enhanced::{AnalysisStatistics, Finding, Location, SARIF_FINGERPRINT_KEY};
+use crate::commands::convert::Report;
+use crate::commands::isolation::{FailedFile, FailureReason};
+use crate::OutputFormatCli;
+
+/// Read a JSON report or, when it has SARIF `runs`, a SARIF report
+pub fn read(path: &Path) -> Result<Report> {
+    let text = std::fs::read_to_string(path).map_err(|e| anyhow!("Cannot read {}: {}", path.display(), e))?;
+    let value: Value = serde_json::from_str(&text).map_err(|e| anyhow!("{} is not a JSON or SARIF report: {}", path.display(), e))?;
+    if value.get("runs").is_some() {
+        return Ok(from_sarif(&value));
+    }
+    Report::parse(&text).map_err(|e| anyhow!("{} is not a JSON findings report: {}", path.display(), e))
+}
+
+/// The findings and statistics of a SARIF report
+fn from_sarif(sarif: &Value) -> Report {
+    let runs = || sarif["runs"].as_array().into_iter().flatten();
+    let mut stats = AnalysisStatistics::new();
+    let mut findings = Vec::new();
+    for result in runs().flat_map(|run| run["results"].as_array().into_iter().flatten()) {
+        let location = &result["locations"][0]["physicalLocation"];
+        let region = &location["region"];
+        let line = |key: &str, default: usize| region[key].as_u64().map_or(default, |n| n as usize);
+        let start_line = line("startLine", 1);
+        let properties = &result["properties"];
+        let finding = Finding {
+            rule_id: result["ruleId"].as_str().unwrap_or_default().to_string(),
+            message: result["message"]["text"].as_str().unwrap_or_default().to_string(),
+            severity: match result["level"].as_str() {
+                Some("error") => Severity::Error,
+                Some("warning") | None => Severity::Warning,
+                _ => Severity::Info,
+            },
+            confidence: Confidence::Medium,
+            location: Location {
+                file: PathBuf::from(location["artifactLocation"]["uri"].as_str().unwrap_or_default()),
+                start_line,
+                start_column: line("startColumn", 1),
+                end_line: line("endLine", start_line),
+                end_column: line("endColumn", 1),
+            },
+            category: properties["category"].as_str().map(str::to_string),
+            risk_score: properties["riskScore"].as_u64().map(|score| score.min(100) as u8),
+            effort: serde_json::from_value(properties["effort"].clone()).ok(),
+            suppressed: result.get("suppressions").is_some(),
+            fingerprint: result["partialFingerprints"][SARIF_FINGERPRINT_KEY].as_str().map(str::to_string),
+            ..Finding::default()
+        };
+        if finding.suppressed {
+            stats.suppressed_findings.push(finding);
+        } else {
+            findings.push(finding);
+        }
+    }
+
+    for invocation in runs().flat_map(|run| run["invocations"].as_array().into_iter().flatten()) {
+        match invocation["properties"]["status"].as_str() {
+            Some("cancelled") => stats.cancelled = true,
+            Some("budget-exhausted") => stats.budget_exhausted = true,
+            _ => {}
+        }
+        for notification in invocation["toolExecutionNotifications"].as_array().into_iter().flatten() {
+            let file = notification["locations"][0]["physicalLocation"]["artifactLocation"]["uri"].as_str().unwrap_or_default();
+            let message = notification["message"]["text"].as_str().unwrap_or_default();
+            stats.failed_files.push(FailedFile {
+                file: file.to_string(),
+                reason: FailureReason::Error,
+                message: message.strip_prefix("Analysis aborted: ").unwrap_or(message).to_string(),
+            });
+        }
+    }
+
+    Report { findings, stats, analysis_time: Duration::ZERO }
+}
+
+/// Combine `reports` into one, keeping each finding once
+pub fn merge(reports: Vec<Report>) -> Report {
+    let mut merged = Report { findings: Vec::new(), stats: AnalysisStatistics::new(), analysis_time: Duration::ZERO };
+    let mut seen = HashSet::new();
+    let mut seen_suppressed = HashSet::new();
+    for report in reports {
+        let stats = report.stats;
+        let total = &mut merged.stats;
+        total.files_analyzed += stats.files_analyzed;
+        total.rules_executed = total.rules_executed.max(stats.rules_executed);
+        total.parse_errors += stats.parse_errors;
+        total.analysis_errors += stats.analysis_errors;
+        total.dataflow_analyses += stats.dataflow_analyses;
+        total.cancelled |= stats.cancelled;
+        total.budget_exhausted |= stats.budget_exhausted;
+        total.profile = total.profile.take().or(stats.profile);
+        total.baseline_matched = match (total.baseline_matched, stats.baseline_matched) {
+            (None, None) => None,
+            (a, b) => Some(a.unwrap_or(0) + b.unwrap_or(0)),
+        };
+        total.roots.extend(stats.roots);
+        total.skipped_fixes.extend(stats.skipped_fixes);
+        total.skipped_files.extend(stats.skipped_files);
+        total.failed_files.extend(stats.failed_files);
+        for degraded in stats.degraded_languages {
+            if !total.degraded_languages.iter().any(|d| d.language == degraded.language) {
+                total.degraded_languages.push(degraded);
+            }
+        }
+        total.suppressed_findings.extend(stats.suppressed_findings.into_iter().filter(|f| seen_suppressed.insert(identity(f))));
+        merged.findings.extend(report.findings.into_iter().filter(|f| seen.insert(identity(f))));
+        merged.analysis_time = merged.analysis_time.max(report.analysis_time);
+    }
+    merged
+}
+
+/// What makes two findings of different reports the same finding
+fn identity(finding: &Finding) -> String {
+    finding.stable_id()
+}
+
+/// Merge the reports `inputs` and write the result in `format` to `output` or stdout
+pub async fn run(inputs: Vec<PathBuf>, format: OutputFormatCli, output: Option<PathBuf>) -> Result<()> {
+    let reports = inputs.iter().map(|input| read(input)).collect::<Result<Vec<_>>>()?;
+    let total: usize = reports.iter().map(|report| report.findings.len()).sum();
+    let merged = merge(reports);
+    info!("Merged {} report(s): {} finding(s), {} duplicate(s) dropped", inputs.len(), merged.findings.len(), total - merged.findings.len());
+
+    let color = crate::commands::color::ColorChoice::Auto.resolve(output.is_none());
+    let rendered = merged.render(&format, color)?;
+    match output {
+        Some(path) => {
+            std::fs::write(&path, rendered)?;
+            info!("Merged report written to {}", path.display());
+        }
+        None => println!("{}", rendered),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::color::ColorChoice;
+
+    const SHARD: &str = r#"{
+  "findings": [
+    {
+      "rule_id": "js-eval",
+      "message": "Avoid eval",
+      "severity": "ERROR",
+      "confidence": "HIGH",
+      "location": { "file": "src/app.js", "start_line": 3, "start_column": 5, "end_line": 3, "end_column": 15 },
+      "fix": null,
+      "fingerprint": "00000000000000aa"
+    },
+    {
+      "rule_id": "js-exec",
+      "message": "Avoid exec",
+      "severity": "WARNING",
+      "confidence": "LOW",
+      "location": { "file": "src/run.js", "start_line": 8, "start_column": 1, "end_line": 8, "end_column": 9 },
+      "fix": null
+    }
+  ],
+  "summary": { "files_analyzed": 4, "rules_executed": 2, "analysis_time_ms": 30, "status": "completed",
+               "failed_files": [{ "file": "src/big.js", "reason": "timeout", "message": "timed out" }] }
+}"#;
+
+    #[test]
+    fn test_merge_json_and_sarif_shards() {
+        let dir = tempfile::tempdir().unwrap();
+        let json_path = dir.path().join("shard-1.json");
+        std::fs::write(&json_path, SHARD).unwrap();
+
+        // A second shard, as SARIF, that overlaps the first on js-eval
+        let sarif = serde_json::json!({
+            "version": "2.1.0",
+            "runs": [{
+                "tool": { "driver": { "name": "astgrep" } },
+                "invocations": [{ "executionSuccessful": false, "properties": { "status": "cancelled" },
+                                  "toolExecutionNotifications": [{ "level": "error", "message": { "text": "Analysis aborted: parser panicked" },
+                                                                    "locations": [{ "physicalLocation": { "artifactLocation": { "uri": "lib/x.py" } } }] }] }],
+                "results": [
+                    { "ruleId": "js-eval", "level": "error", "message": { "text": "Avoid eval" },
+                      "partialFingerprints": { SARIF_FINGERPRINT_KEY: "00000000000000aa" },
+                      "locations": [{ "physicalLocation": { "artifactLocation": { "uri": "src/app.js" },
+                                      "region": { "startLine": 3, "startColumn": 5, "endLine": 3, "endColumn": 15 } } }] },
+                    { "ruleId": "py-pickle", "level": "note", "message": { "text": "Avoid pickle" },
+                      "properties": { "riskScore": 40, "effort": "small" },
+                      "locations": [{ "physicalLocation": { "artifactLocation": { "uri": "lib/y.py" }, "region": { "startLine": 2 } } }] },
+                    { "ruleId": "py-eval", "level": "warning", "message": { "text": "Avoid eval" }, "suppressions": [{ "kind": "inSource" }],
+                      "locations": [{ "physicalLocation": { "artifactLocation": { "uri": "lib/z.py" }, "region": { "startLine": 7 } } }] }
+                ]
+            }]
+        });
+        let sarif_path = dir.path().join("shard-2.sarif");
+        std::fs::write(&sarif_path, sarif.to_string()).unwrap();
+
+        let merged = merge(vec![read(&json_path).unwrap(), read(&sarif_path).unwrap(), read(&json_path).unwrap()]);
+        let rules: Vec<&str> = merged.findings.iter().map(|f| f.rule_id.as_str()).collect();
+        assert_eq!(rules, vec!["js-eval", "js-exec", "py-pickle"]);
+        assert_eq!(merged.findings[2].severity, Severity::Info);
+        assert_eq!(merged.findings[2].risk_score, Some(40));
+        assert_eq!(merged.stats.suppressed_findings.len(), 1);
+        assert_eq!(merged.stats.files_analyzed, 8);
+        assert_eq!(merged.stats.rules_executed, 2);
+        assert!(merged.stats.cancelled);
+        assert_eq!(merged.stats.failed_files.len(), 3);
+        assert_eq!(merged.stats.failed_files[1].message, "parser panicked");
+        assert_eq!(merged.analysis_time, Duration::from_millis(30));
+
+        let json: Value = serde_json::from_str(&merged.render(&OutputFormatCli::Json, ColorChoice::Never).unwrap()).unwrap();
+        assert_eq!(json["findings"].as_array().unwrap().len(), 3);
+        assert_eq!(json["summary"]["files_analyzed"], 8);
+    }
+
+    #[test]
+    fn test_rejects_other_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.txt");
+        std::fs::write(&path, "not json").unwrap();
+        let error = read(&path).err().unwrap().to_string();
+        assert!(error.contains("is not a JSON or SARIF report"));
+        assert!(read(&dir.path().join("missing.json")).is_err());
+    }
+}
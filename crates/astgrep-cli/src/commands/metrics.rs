@@ -0,0 +1,185 @@
+//! OpenMetrics export
+//!
+//! `--metrics --metrics-out <file>` writes the health of a scan in the OpenMetrics text
+//! format read by Prometheus (through a textfile collector or the Pushgateway) and most CI
+//! dashboards: the files scanned, skipped and failed, the parse time per language, the
+//! execution time per rule, the findings by severity and by rule, and the scan duration.
+//! Every value describes the one run, so all families are gauges. Parse and rule timings
+//! are only collected while an export is pending, see [`enable`].
+
+use crate::commands::analyze_enhanced::{AnalysisStatistics, Finding};
+use anyhow::Result;
+use astgrep_core::{Language, Severity};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+const SEVERITIES: [Severity; 4] = [Severity::Critical, Severity::Error, Severity::Warning, Severity::Info];
+
+/// Parse and rule timings of the run, added up over all files
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Timings {
+    /// Files parsed and the time spent parsing them, by language
+    pub parse: BTreeMap<&'static str, (u64, Duration)>,
+    /// Time spent executing each rule, by rule id
+    pub rules: BTreeMap<String, Duration>,
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+fn timings() -> &'static Mutex<Timings> {
+    static TIMINGS: OnceLock<Mutex<Timings>> = OnceLock::new();
+    TIMINGS.get_or_init(|| Mutex::new(Timings::default()))
+}
+
+/// Start collecting parse and rule timings for the rest of the process
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Whether timings are being collected; lets callers skip timing when nobody reads it
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Add the parse of one `language` file taking `elapsed`, if collecting
+pub fn record_parse(language: Language, elapsed: Duration) {
+    if !is_enabled() {
+        return;
+    }
+    let mut timings = timings().lock().unwrap_or_else(|e| e.into_inner());
+    let entry = timings.parse.entry(language.as_str()).or_default();
+    entry.0 += 1;
+    entry.1 += elapsed;
+}
+
+/// Add the execution times of rules, by rule id, if collecting
+pub fn record_rules(rule_timings: HashMap<String, Duration>) {
+    if !is_enabled() || rule_timings.is_empty() {
+        return;
+    }
+    let mut timings = timings().lock().unwrap_or_else(|e| e.into_inner());
+    for (rule_id, elapsed) in rule_timings {
+        *timings.rules.entry(rule_id).or_default() += elapsed;
+    }
+}
+
+/// The timings collected so far, leaving the collector empty
+pub fn take() -> Timings {
+    std::mem::take(&mut *timings().lock().unwrap_or_else(|e| e.into_inner()))
+}
+
+/// Write the metrics of a finished run to `path`
+pub fn write(path: &Path, findings: &[Finding], stats: &AnalysisStatistics, total_time: Duration) -> Result<()> {
+    std::fs::write(path, render(&take(), findings, stats, total_time))?;
+    Ok(())
+}
+
+/// The metrics of a run as an OpenMetrics text exposition
+pub fn render(timings: &Timings, findings: &[Finding], stats: &AnalysisStatistics, total_time: Duration) -> String {
+    let mut out = String::new();
+
+    family(&mut out, "astgrep_scan_info", "", "Version and completion status of the scan");
+    sample(&mut out, "astgrep_scan_info", &[("version", env!("CARGO_PKG_VERSION")), ("status", stats.status())], 1);
+    family(&mut out, "astgrep_scan_duration_seconds", "seconds", "Wall-clock duration of the scan");
+    sample(&mut out, "astgrep_scan_duration_seconds", &[], total_time.as_secs_f64());
+
+    family(&mut out, "astgrep_files_scanned", "", "Files analyzed");
+    sample(&mut out, "astgrep_files_scanned", &[], stats.files_analyzed);
+    family(&mut out, "astgrep_files_skipped", "", "Binary and oversized files left out of the scan");
+    sample(&mut out, "astgrep_files_skipped", &[], stats.skipped_files.len());
+    family(&mut out, "astgrep_files_failed", "", "Files whose analysis failed or timed out");
+    sample(&mut out, "astgrep_files_failed", &[], stats.failed_files.len());
+
+    family(&mut out, "astgrep_files_parsed", "", "Files parsed, by language");
+    for (language, (files, _)) in &timings.parse {
+        sample(&mut out, "astgrep_files_parsed", &[("language", language)], files);
+    }
+    family(&mut out, "astgrep_parse_duration_seconds", "seconds", "Time spent parsing, by language");
+    for (language, (_, elapsed)) in &timings.parse {
+        sample(&mut out, "astgrep_parse_duration_seconds", &[("language", language)], elapsed.as_secs_f64());
+    }
+    family(&mut out, "astgrep_rule_duration_seconds", "seconds", "Time spent executing each rule, over all files");
+    for (rule_id, elapsed) in &timings.rules {
+        sample(&mut out, "astgrep_rule_duration_seconds", &[("rule", rule_id)], elapsed.as_secs_f64());
+    }
+
+    family(&mut out, "astgrep_findings", "", "Findings reported, by severity");
+    for severity in SEVERITIES {
+        let count = findings.iter().filter(|f| f.severity == severity).count();
+        sample(&mut out, "astgrep_findings", &[("severity", severity.as_str())], count);
+    }
+    let mut by_rule: BTreeMap<&str, usize> = BTreeMap::new();
+    for finding in findings {
+        *by_rule.entry(finding.rule_id.as_str()).or_default() += 1;
+    }
+    family(&mut out, "astgrep_rule_findings", "", "Findings reported, by rule");
+    for (rule_id, count) in by_rule {
+        sample(&mut out, "astgrep_rule_findings", &[("rule", rule_id)], count);
+    }
+
+    out.push_str("# EOF\n");
+    out
+}
+
+/// The metadata lines of a gauge family
+fn family(out: &mut String, name: &str, unit: &str, help: &str) {
+    let _ = writeln!(out, "# TYPE {} gauge", name);
+    if !unit.is_empty() {
+        let _ = writeln!(out, "# UNIT {} {}", name, unit);
+    }
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+}
+
+fn sample(out: &mut String, name: &str, labels: &[(&str, &str)], value: impl std::fmt::Display) {
+    out.push_str(name);
+    if !labels.is_empty() {
+        let labels: Vec<String> = labels.iter().map(|(key, value)| format!("{}=\"{}\"", key, escape_label(value))).collect();
+        let _ = write!(out, "{{{}}}", labels.join(","));
+    }
+    let _ = writeln!(out, " {}", value);
+}
+
+/// Escape a label value: backslashes, double quotes and line feeds
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::analyze_enhanced::{test_finding, Location};
+
+    fn finding(rule_id: &str, severity: Severity) -> Finding {
+        Finding { severity, location: Location::new("src/app.js", 1, 1, 1, 10), ..test_finding(rule_id, "src/app.js", 1) }
+    }
+
+    #[test]
+    fn test_render_openmetrics() {
+        let mut timings = Timings::default();
+        timings.parse.insert("javascript", (2, Duration::from_millis(250)));
+        timings.rules.insert("js-eval".to_string(), Duration::from_millis(40));
+        timings.rules.insert("odd\"rule\\".to_string(), Duration::from_millis(5));
+        let mut stats = AnalysisStatistics::new();
+        stats.files_analyzed = 2;
+        let findings = vec![finding("js-eval", Severity::Error), finding("js-eval", Severity::Error), finding("js-todo", Severity::Info)];
+
+        let text = render(&timings, &findings, &stats, Duration::from_millis(1500));
+        assert!(text.contains("# TYPE astgrep_scan_duration_seconds gauge\n# UNIT astgrep_scan_duration_seconds seconds\n"));
+        assert!(text.contains("\nastgrep_scan_duration_seconds 1.5\n"));
+        assert!(text.contains("\nastgrep_scan_info{version=\"") && text.contains(",status=\"completed\"} 1\n"));
+        assert!(text.contains("\nastgrep_files_scanned 2\n"));
+        assert!(text.contains("\nastgrep_files_failed 0\n"));
+        assert!(text.contains("\nastgrep_files_parsed{language=\"javascript\"} 2\n"));
+        assert!(text.contains("\nastgrep_parse_duration_seconds{language=\"javascript\"} 0.25\n"));
+        assert!(text.contains("\nastgrep_rule_duration_seconds{rule=\"js-eval\"} 0.04\n"));
+        assert!(text.contains("\nastgrep_rule_duration_seconds{rule=\"odd\\\"rule\\\\\"} 0.005\n"));
+        assert!(text.contains("\nastgrep_findings{severity=\"ERROR\"} 2\n"));
+        assert!(text.contains("\nastgrep_findings{severity=\"CRITICAL\"} 0\n"));
+        assert!(text.contains("\nastgrep_rule_findings{rule=\"js-todo\"} 1\n"));
+        assert!(text.ends_with("\n# EOF\n"));
+    }
+}
@@ -2,11 +2,64 @@
 
 pub mod analyze;
 pub mod analyze_enhanced;
+pub mod archive;
+pub mod autofix;
+pub mod baseline;
+pub mod bench;
+pub mod cache;
+pub mod ci;
+pub mod color;
+pub mod completions;
+pub mod convert;
+pub mod csv;
+pub mod dump_ast;
+pub mod explain;
+pub mod finding_filter;
+pub mod fingerprint;
+pub mod fmt;
+pub mod github_actions;
+pub mod hook;
+pub mod html_report;
+pub mod ignore_file;
+pub mod image;
+pub mod index;
 pub mod info;
 pub mod init;
+pub mod isolation;
+pub mod jsonl;
 pub mod languages;
 pub mod list;
+pub mod login;
+pub mod lsp;
+pub mod markdown_report;
+pub mod match_trace;
+pub mod merge_reports;
+pub mod metrics;
+pub mod patches;
+pub mod pattern_query;
+pub mod progress;
+pub mod registry;
+pub mod report_template;
+pub mod repro;
+pub mod rewrite;
+pub mod risk;
+pub mod rule_tests;
+pub mod rule_wizard;
+pub mod rules;
+pub mod scan_profile;
+pub mod self_bench;
+pub mod source_context;
+pub mod stats;
+pub mod supply_chain;
+pub mod taint_models;
+pub mod target_filter;
+pub mod trends;
+pub mod triage;
+pub mod tui;
 pub mod update;
 pub mod validate;
 pub mod validate_enhanced;
 pub mod version;
+pub mod vscode;
+pub mod worker_pool;
+pub mod workspace;
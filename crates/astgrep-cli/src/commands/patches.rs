@@ -0,0 +1,277 @@
+//! Quickfix patch bundles
+//!
+//! Writes the autofixes of findings as unified diffs that can be reviewed and applied
+//! with `git apply` or `patch -p1`, either one patch per finding (named by the finding
+//! fingerprint, so a fix keeps its file name when code above it moves) or one combined
+//! patch per file.
+
+use anyhow::Result;
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+use crate::commands::analyze_enhanced::Finding;
+
+/// Context lines around each hunk
+const CONTEXT_LINES: usize = 3;
+
+/// A replacement of whole lines `start_line..=end_line` (1-based)
+#[derive(Debug, Clone)]
+pub(crate) struct LineEdit {
+    pub start_line: usize,
+    pub end_line: usize,
+    pub replacement: Vec<String>,
+}
+
+/// Write patches for all findings carrying a fix into `dir`; returns the number of patch files
+pub fn emit_patches(findings: &[Finding], dir: &Path, combine_per_file: bool) -> Result<usize> {
+    std::fs::create_dir_all(dir)?;
+
+    let mut by_file: BTreeMap<PathBuf, Vec<&Finding>> = BTreeMap::new();
+    for finding in findings.iter().filter(|f| f.fix.is_some()) {
+        by_file.entry(finding.location.file.clone()).or_default().push(finding);
+    }
+
+    let mut written = 0usize;
+    for (file, file_findings) in by_file {
+        let source = match std::fs::read_to_string(&file) {
+            Ok(source) => source,
+            Err(e) => {
+                warn!("Skipping patches for {}: {}", file.display(), e);
+                continue;
+            }
+        };
+        let display_path = patch_path(&file);
+
+        if combine_per_file {
+            let edits: Vec<(&Finding, LineEdit)> = file_findings.iter()
+                .filter_map(|f| line_edit(f, &source).map(|e| (*f, e)))
+                .collect();
+            let kept = non_overlapping(edits, &display_path);
+            if kept.is_empty() {
+                continue;
+            }
+
+            let name = format!("{}.patch", display_path.replace(['/', '\\'], "_"));
+            std::fs::write(dir.join(name), unified_diff(&display_path, &source, &kept))?;
+            written += 1;
+        } else {
+            for finding in file_findings {
+                if let Some(edit) = line_edit(finding, &source) {
+                    let name = format!("{}.patch", finding.stable_id());
+                    std::fs::write(dir.join(name), unified_diff(&display_path, &source, &[edit]))?;
+                    written += 1;
+                }
+            }
+        }
+    }
+
+    info!("Wrote {} patch(es) to {}", written, dir.display());
+    Ok(written)
+}
+
+/// Sort edits by position and drop those touching lines an earlier edit already replaces
+pub(crate) fn non_overlapping(mut edits: Vec<(&Finding, LineEdit)>, display_path: &str) -> Vec<LineEdit> {
+    edits.sort_by_key(|(f, e)| (e.start_line, f.location.start_column));
+
+    // Fixes touching the same lines cannot be combined; keep the first
+    let mut kept: Vec<LineEdit> = Vec::new();
+    for (finding, edit) in edits {
+        if kept.last().is_some_and(|prev| edit.start_line <= prev.end_line) {
+            warn!("Skipping overlapping fix for {} at {}:{}", finding.rule_id, display_path, finding.location.start_line);
+            continue;
+        }
+        kept.push(edit);
+    }
+    kept
+}
+
+/// Path as it should appear in the patch header: relative to the working directory when possible
+pub(crate) fn patch_path(file: &Path) -> String {
+    let relative = std::env::current_dir()
+        .ok()
+        .and_then(|cwd| file.strip_prefix(&cwd).ok().map(Path::to_path_buf))
+        .unwrap_or_else(|| file.to_path_buf());
+    let path = relative.to_string_lossy().replace('\\', "/");
+    path.trim_start_matches("./").trim_start_matches('/').to_string()
+}
+
+/// Turn a finding's fix into a whole-line replacement; None when the fix still contains
+/// unbound metavariables or the location does not fit the file
+pub(crate) fn line_edit(finding: &Finding, source: &str) -> Option<LineEdit> {
+    let fix = finding.fix.as_ref()?;
+    let unbound = Regex::new(r"\$[A-Z_][A-Z0-9_]*").ok()?;
+    if unbound.is_match(fix) {
+        return None;
+    }
+
+    let lines: Vec<&str> = source.lines().collect();
+    let loc = &finding.location;
+    if loc.start_line == 0 || loc.start_line > loc.end_line || loc.end_line > lines.len() {
+        return None;
+    }
+
+    let prefix: String = lines[loc.start_line - 1].chars().take(loc.start_column.saturating_sub(1)).collect();
+    let suffix: String = lines[loc.end_line - 1].chars().skip(loc.end_column.saturating_sub(1)).collect();
+    let new_text = format!("{}{}{}", prefix, fix, suffix);
+
+    Some(LineEdit {
+        start_line: loc.start_line,
+        end_line: loc.end_line,
+        replacement: new_text.split('\n').map(|l| l.to_string()).collect(),
+    })
+}
+
+/// Render sorted, non-overlapping edits as a unified diff, merging edits whose context overlaps
+pub(crate) fn unified_diff(path: &str, source: &str, edits: &[LineEdit]) -> String {
+    let lines: Vec<&str> = source.lines().collect();
+    let missing_final_newline = !source.is_empty() && !source.ends_with('\n');
+    let no_newline = |line: usize| missing_final_newline && line == lines.len();
+
+    let mut out = format!("--- a/{}\n+++ b/{}\n", path, path);
+    let mut delta: isize = 0;
+    let mut i = 0;
+
+    while i < edits.len() {
+        let mut j = i;
+        while j + 1 < edits.len() && edits[j + 1].start_line <= edits[j].end_line + 2 * CONTEXT_LINES + 1 {
+            j += 1;
+        }
+
+        let old_start = edits[i].start_line.saturating_sub(CONTEXT_LINES).max(1);
+        let old_end = (edits[j].end_line + CONTEXT_LINES).min(lines.len());
+        let mut body = String::new();
+        let (mut old_count, mut new_count) = (0usize, 0usize);
+        let mut line = old_start;
+
+        for edit in &edits[i..=j] {
+            while line < edit.start_line {
+                push_line(&mut body, ' ', lines[line - 1], no_newline(line));
+                old_count += 1;
+                new_count += 1;
+                line += 1;
+            }
+            for old in edit.start_line..=edit.end_line {
+                push_line(&mut body, '-', lines[old - 1], no_newline(old));
+                old_count += 1;
+            }
+            for (k, new) in edit.replacement.iter().enumerate() {
+                let last = k + 1 == edit.replacement.len();
+                push_line(&mut body, '+', new, last && no_newline(edit.end_line));
+                new_count += 1;
+            }
+            line = edit.end_line + 1;
+        }
+        while line <= old_end {
+            push_line(&mut body, ' ', lines[line - 1], no_newline(line));
+            old_count += 1;
+            new_count += 1;
+            line += 1;
+        }
+
+        let new_start = (old_start as isize + delta).max(1);
+        out.push_str(&format!("@@ -{},{} +{},{} @@\n", old_start, old_count, new_start, new_count));
+        out.push_str(&body);
+        delta += new_count as isize - old_count as isize;
+        i = j + 1;
+    }
+
+    out
+}
+
+/// Apply sorted, non-overlapping edits to `source`
+pub(crate) fn apply_edits(source: &str, edits: &[LineEdit]) -> String {
+    let lines: Vec<&str> = source.lines().collect();
+    let mut out: Vec<String> = Vec::with_capacity(lines.len());
+    let mut line = 1;
+    for edit in edits {
+        out.extend(lines[line - 1..edit.start_line - 1].iter().map(|l| l.to_string()));
+        out.extend(edit.replacement.iter().cloned());
+        line = edit.end_line + 1;
+    }
+    out.extend(lines[(line - 1).min(lines.len())..].iter().map(|l| l.to_string()));
+
+    let mut text = out.join("\n");
+    if source.ends_with('\n') {
+        text.push('\n');
+    }
+    text
+}
+
+fn push_line(body: &mut String, marker: char, text: &str, no_newline: bool) {
+    body.push(marker);
+    body.push_str(text);
+    body.push('\n');
+    if no_newline {
+        body.push_str("\\ No newline at end of file\n");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::analyze_enhanced::{test_finding, Location};
+    use astgrep_core::Severity;
+
+    fn finding(file: &Path, line: usize, start: usize, end: usize, fix: &str) -> Finding {
+        Finding {
+            message: "Use ===".to_string(),
+            severity: Severity::Warning,
+            location: Location::new(file, line, start, line, end),
+            fix: Some(fix.to_string()),
+            ..test_finding("use-strict-equals", file, line)
+        }
+    }
+
+    #[test]
+    fn test_unified_diff_single_edit() {
+        let source = "a\nb\nif (x == y) {}\nc\n";
+        let edit = LineEdit { start_line: 3, end_line: 3, replacement: vec!["if (x === y) {}".to_string()] };
+        let diff = unified_diff("src/app.js", source, &[edit]);
+        assert_eq!(
+            diff,
+            "--- a/src/app.js\n+++ b/src/app.js\n@@ -1,4 +1,4 @@\n a\n b\n-if (x == y) {}\n+if (x === y) {}\n c\n"
+        );
+    }
+
+    #[test]
+    fn test_apply_edits() {
+        let source = "a\nb\nc\nd\n";
+        let edits = [
+            LineEdit { start_line: 1, end_line: 1, replacement: vec!["A".to_string()] },
+            LineEdit { start_line: 3, end_line: 4, replacement: vec!["C".to_string(), "D".to_string(), "E".to_string()] },
+        ];
+        assert_eq!(apply_edits(source, &edits), "A\nb\nC\nD\nE\n");
+        assert_eq!(apply_edits("x\ny", &edits[..1]), "A\ny");
+    }
+
+    #[test]
+    fn test_emit_patches_per_finding_and_per_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("app.js");
+        std::fs::write(&file, "if (a == b) {}\nif (c == d) {}").unwrap();
+        let mut findings = vec![
+            finding(&file, 1, 5, 11, "a === b"),
+            finding(&file, 2, 5, 11, "c === d"),
+            finding(&file, 2, 1, 3, "$X"),
+        ];
+        crate::commands::fingerprint::assign(&mut findings, |_| None);
+
+        let out = dir.path().join("patches");
+        assert_eq!(emit_patches(&findings, &out, false).unwrap(), 2);
+        let fingerprint = findings[0].fingerprint.clone().unwrap();
+        assert!(out.join(format!("{}.patch", fingerprint)).exists());
+
+        let combined = dir.path().join("combined");
+        assert_eq!(emit_patches(&findings, &combined, true).unwrap(), 1);
+        let patch = std::fs::read_dir(&combined).unwrap().next().unwrap().unwrap().path();
+        let text = std::fs::read_to_string(patch).unwrap();
+        assert!(text.contains("+if (a === b) {}\n-if (c == d) {}\n\\ No newline at end of file\n+if (c === d) {}\n"));
+
+        // The patch keeps its name when a line is added above the fix
+        std::fs::write(&file, "// header\nif (a == b) {}\nif (c == d) {}").unwrap();
+        let mut moved = vec![finding(&file, 2, 5, 11, "a === b")];
+        crate::commands::fingerprint::assign(&mut moved, |_| None);
+        assert_eq!(moved[0].fingerprint, Some(fingerprint));
+    }
+}
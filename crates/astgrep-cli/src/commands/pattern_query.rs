@@ -0,0 +1,87 @@
+//! Ad-hoc pattern queries
+//!
+//! `analyze -e PATTERN --lang LANG` searches for a pattern without authoring a rule file.
+//! The pattern becomes a synthetic rule with the id `pattern`, written to a scratch rule
+//! file that is analyzed instead of `--rules` and removed after the run, so the rest of
+//! the analysis, output formats and filters included, works as for any rule.
+//!
+//! `-e` used to be short for `--exclude`, so a value that looks like a path glob is
+//! rejected with a hint instead of being searched for.
+
+use anyhow::{anyhow, Result};
+use astgrep_core::Language;
+use serde_json::json;
+use std::path::{Path, PathBuf};
+
+/// Rule id of the findings of an ad-hoc pattern
+pub const RULE_ID: &str = "pattern";
+
+/// The YAML of the synthetic rule matching `pattern` in `languages`
+pub fn rule_yaml(pattern: &str, languages: &[Language]) -> Result<String> {
+    if pattern.trim().is_empty() {
+        return Err(anyhow!("the pattern is empty"));
+    }
+    if looks_like_glob(pattern) {
+        return Err(anyhow!(
+            "'{}' looks like a path glob, not a pattern: -e is short for --pattern now; exclude paths with --exclude '{}'",
+            pattern.trim(),
+            pattern.trim()
+        ));
+    }
+    if languages.is_empty() {
+        return Err(anyhow!("--pattern needs the language of the pattern; pass --lang"));
+    }
+    let rule = json!({
+        "id": RULE_ID,
+        "message": pattern.trim(),
+        "severity": "INFO",
+        "languages": languages.iter().map(Language::as_str).collect::<Vec<_>>(),
+        "pattern": pattern,
+    });
+    Ok(serde_yaml::to_string(&json!({ "rules": [rule] }))?)
+}
+
+/// Whether `pattern` is more likely an `--exclude` glob than code: a single word with
+/// `*` or `/` and nothing only code has, like calls, metavariables or spaces
+fn looks_like_glob(pattern: &str) -> bool {
+    let pattern = pattern.trim();
+    pattern.contains(['*', '/']) && !pattern.contains(|c: char| c.is_whitespace() || matches!(c, '(' | ')' | '$' | '=' | ';' | '"' | '\''))
+}
+
+/// Write the synthetic rule of `pattern` below `dir`; returns the rule file to analyze
+pub fn write_rule(pattern: &str, languages: &[Language], dir: &Path) -> Result<PathBuf> {
+    let yaml = rule_yaml(pattern, languages)?;
+    std::fs::create_dir_all(dir)?;
+    let path = dir.join("pattern.yaml");
+    std::fs::write(&path, yaml)?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use astgrep_rules::RuleRepository;
+
+    #[test]
+    fn test_rule_yaml_loads_as_a_rule() {
+        // Quotes and YAML indicators in the pattern survive the round trip
+        let pattern = "eval(\"x: \" + $X) # ok";
+        let yaml = rule_yaml(pattern, &[Language::JavaScript]).unwrap();
+        let mut repository = RuleRepository::new();
+        assert_eq!(repository.load_yaml("pattern.yaml", &yaml).unwrap(), 1);
+        let rule = repository.rule(RULE_ID).unwrap();
+        assert_eq!(rule.languages, vec![Language::JavaScript]);
+        assert_eq!(rule.description, pattern);
+
+        assert!(rule_yaml("  ", &[Language::Python]).unwrap_err().to_string().contains("empty"));
+        assert!(rule_yaml("eval($X)", &[]).unwrap_err().to_string().contains("--lang"));
+
+        // Values meant for the old -e/--exclude get a hint, code with * or / does not
+        for glob in ["*.min.js", "vendor/", "src/**/generated/*"] {
+            assert!(rule_yaml(glob, &[Language::JavaScript]).unwrap_err().to_string().contains("--exclude"), "{}", glob);
+        }
+        for pattern in ["$X / $Y", "a * b", "foo(\"a/b\")", "*ptr = $X;"] {
+            assert!(!looks_like_glob(pattern), "{}", pattern);
+        }
+    }
+}
@@ -0,0 +1,83 @@
+//! Analysis progress bar
+//!
+//! Long scans print nothing until the report is written, so while files are analyzed a
+//! bar on stderr shows the files done out of the total, the elapsed time, an estimate of
+//! the time left and the file last started. It is drawn only when stderr is a terminal,
+//! and `--quiet` or `--no-progress` turn it off.
+
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+use std::io::IsTerminal;
+use std::path::Path;
+
+const TEMPLATE: &str = "{spinner} [{elapsed_precise}] {bar:30} {pos}/{len} files, ETA {eta} {wide_msg}";
+
+/// Progress of the analysis of a set of files; shared by the workers
+#[derive(Clone)]
+pub struct Progress {
+    bar: ProgressBar,
+}
+
+impl Progress {
+    /// Progress over `total` files, drawn on stderr when `enabled` and stderr is a terminal
+    pub fn new(total: usize, enabled: bool) -> Self {
+        let target = if enabled && std::io::stderr().is_terminal() {
+            ProgressDrawTarget::stderr()
+        } else {
+            ProgressDrawTarget::hidden()
+        };
+        Self::with_target(total, target)
+    }
+
+    fn with_target(total: usize, target: ProgressDrawTarget) -> Self {
+        let bar = ProgressBar::with_draw_target(Some(total as u64), target);
+        if let Ok(style) = ProgressStyle::with_template(TEMPLATE) {
+            bar.set_style(style.progress_chars("=> "));
+        }
+        Self { bar }
+    }
+
+    /// A worker started on `file`
+    pub fn start(&self, file: &Path) {
+        if !self.bar.is_hidden() {
+            self.bar.set_message(file.display().to_string());
+        }
+    }
+
+    /// A worker finished a file
+    pub fn finish_file(&self) {
+        self.bar.inc(1);
+    }
+
+    /// Remove the bar, leaving the terminal as it was
+    pub fn finish(&self) {
+        self.bar.finish_and_clear();
+    }
+
+    pub fn position(&self) -> u64 {
+        self.bar.position()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress_counts_files() {
+        let progress = Progress::with_target(3, ProgressDrawTarget::hidden());
+        let worker = progress.clone();
+        std::thread::spawn(move || {
+            worker.start(Path::new("src/a.py"));
+            worker.finish_file();
+        })
+        .join()
+        .unwrap();
+        progress.start(Path::new("src/b.py"));
+        progress.finish_file();
+        assert_eq!(progress.position(), 2);
+        progress.finish();
+
+        // Not drawn outside a terminal, as under the test harness
+        assert!(Progress::new(3, false).bar.is_hidden());
+    }
+}
@@ -0,0 +1,229 @@
+//! Rule registry shorthands
+//!
+//! `--rules p/<pack>` names a rule pack of a remote registry instead of a local path.
+//! The `.astgrep-pack` archive is downloaded over HTTP(S) to a cache directory and then
+//! loaded like a local pack. A cached pack is reused for a day, and when the registry
+//! cannot be reached a stale copy is used with a warning. A path that exists locally
+//! always wins over a shorthand, so a local directory named `p` keeps working.
+//!
+//! There is no built-in registry: `ASTGREP_REGISTRY_URL` names the one to use, and
+//! shorthands are rejected without it. `ASTGREP_CACHE_DIR` points at another cache
+//! directory (by default `$XDG_CACHE_HOME/astgrep` or `~/.cache/astgrep`). Without a cache
+//! directory, shorthands are rejected rather than cached in a shared temporary directory.
+//! Private registries accept the token stored by `astgrep login`.
+
+use anyhow::{anyhow, Result};
+use astgrep_rules::{RulePack, PACK_EXTENSION};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Age after which a cached pack is downloaded again
+const CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Time limit of one download
+const DOWNLOAD_TIMEOUT_SECS: u64 = 60;
+
+/// Where registry shorthands are downloaded from and cached
+#[derive(Debug, Clone)]
+pub struct Registry {
+    /// None when `ASTGREP_REGISTRY_URL` is not set
+    pub url: Option<String>,
+    /// None when the environment names no per-user cache directory
+    pub cache_dir: Option<PathBuf>,
+    pub ttl: Duration,
+    /// Bearer token sent with downloads
+    pub token: Option<String>,
+}
+
+impl Registry {
+    /// The registry and cache directory of the environment
+    pub fn from_env() -> Self {
+        let url = registry_url();
+        let cache_dir = std::env::var_os("ASTGREP_CACHE_DIR")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("XDG_CACHE_HOME").map(|dir| PathBuf::from(dir).join("astgrep")))
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache").join("astgrep")));
+        let token = url.as_deref().and_then(crate::commands::login::token_for);
+        Self { url: url.map(|url| url.trim_end_matches('/').to_string()), cache_dir: cache_dir.map(|dir| dir.join("registry")), ttl: CACHE_TTL, token }
+    }
+
+    /// `paths` with registry shorthands replaced by their cached pack files
+    pub fn resolve_all(&self, paths: Vec<PathBuf>) -> Result<Vec<PathBuf>> {
+        paths.into_iter().map(|path| self.resolve(path)).collect()
+    }
+
+    /// The cached pack archive of a registry shorthand, downloading it when missing or
+    /// stale; other paths are returned unchanged
+    pub fn resolve(&self, path: PathBuf) -> Result<PathBuf> {
+        match pack_name(&path) {
+            Some(pack) if !path.exists() => self.cached_pack(pack),
+            _ => Ok(path),
+        }
+    }
+
+    /// `paths` of a configuration file in `base`: relative paths are taken from `base`, and
+    /// registry shorthands are resolved unless `base` has a local path of that name
+    pub fn resolve_relative(&self, base: &Path, paths: &[PathBuf]) -> Result<Vec<PathBuf>> {
+        paths
+            .iter()
+            .map(|path| {
+                let local = base.join(path);
+                match pack_name(path) {
+                    Some(pack) if !local.exists() => self.cached_pack(pack),
+                    _ => Ok(local),
+                }
+            })
+            .collect()
+    }
+
+    /// The cached pack archive of `pack`, downloading it when missing or stale
+    fn cached_pack(&self, pack: &str) -> Result<PathBuf> {
+        let Some(ref registry) = self.url else {
+            return Err(anyhow!("cannot resolve rule pack p/{}: no rule registry is configured, set ASTGREP_REGISTRY_URL", pack));
+        };
+        let Some(ref cache_dir) = self.cache_dir else {
+            return Err(anyhow!("cannot cache rule pack p/{}: set ASTGREP_CACHE_DIR to a directory only you can write", pack));
+        };
+
+        let cached = cache_dir.join(format!("{}.{}", pack, PACK_EXTENSION));
+        let age = std::fs::metadata(&cached).and_then(|m| m.modified()).ok().and_then(|t| t.elapsed().ok());
+        if age.is_some_and(|age| age < self.ttl) {
+            return Ok(cached);
+        }
+
+        let url = format!("{}/{}.{}", registry, pack, PACK_EXTENSION);
+        match self.download(&url, &cached) {
+            Ok(()) => {
+                info!("Downloaded rule pack p/{} from {}", pack, url);
+                Ok(cached)
+            }
+            Err(e) if age.is_some() => {
+                warn!("Using the cached rule pack p/{}: {}", pack, e);
+                Ok(cached)
+            }
+            Err(e) => Err(anyhow!("cannot download rule pack p/{}: {}", pack, e)),
+        }
+    }
+
+    /// Download `url` to `target`, replacing it only with a pack this engine can load
+    fn download(&self, url: &str, target: &Path) -> Result<()> {
+        if let Some(dir) = target.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        // The blocking client runs its own runtime, which must not start on a tokio worker
+        let body = std::thread::scope(|scope| scope.spawn(|| self.fetch(url)).join())
+            .map_err(|_| anyhow!("download of {} panicked", url))??;
+        let partial = target.with_extension(format!("{}.part", PACK_EXTENSION));
+        std::fs::write(&partial, body)?;
+
+        let checked = RulePack::read_from(&partial).map_err(|e| anyhow!("{} is not a rule pack: {}", url, e)).and_then(|pack| {
+            if pack.manifest.is_compatible() {
+                Ok(())
+            } else {
+                let required = pack.manifest.min_engine_version.unwrap_or_default();
+                Err(anyhow!("{} requires engine version {}", url, required))
+            }
+        });
+        if let Err(e) = checked {
+            let _ = std::fs::remove_file(&partial);
+            return Err(e);
+        }
+        std::fs::rename(&partial, target)?;
+        Ok(())
+    }
+
+    /// The body of `url`, sending the token as a bearer token. Redirects to another
+    /// host do not carry it.
+    fn fetch(&self, url: &str) -> Result<Vec<u8>> {
+        let client = reqwest::blocking::Client::builder().timeout(Duration::from_secs(DOWNLOAD_TIMEOUT_SECS)).build()?;
+        let mut request = client.get(url);
+        if let Some(ref token) = self.token {
+            request = request.bearer_auth(token);
+        }
+        let response = request.send()?.error_for_status()?;
+        Ok(response.bytes()?.to_vec())
+    }
+}
+
+/// The registry named by `ASTGREP_REGISTRY_URL`, if any
+pub fn registry_url() -> Option<String> {
+    std::env::var("ASTGREP_REGISTRY_URL").ok().map(|url| url.trim().to_string()).filter(|url| !url.is_empty())
+}
+
+/// The pack named by a `p/<pack>` shorthand
+fn pack_name(path: &Path) -> Option<&str> {
+    let pack = path.to_str()?.strip_prefix("p/")?;
+    let valid = !pack.is_empty()
+        && !pack.starts_with('.')
+        && pack.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    valid.then_some(pack)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RULES: &str = "rules:\n  - id: js-eval\n    message: Avoid eval\n    severity: ERROR\n    languages: [javascript]\n    pattern: eval($X)\n";
+
+    fn registry(cache_dir: &Path) -> Registry {
+        Registry { url: Some("file:///nonexistent/registry".to_string()), cache_dir: Some(cache_dir.to_path_buf()), ttl: CACHE_TTL, token: None }
+    }
+
+    #[test]
+    fn test_pack_names() {
+        assert_eq!(pack_name(Path::new("p/owasp-top-ten")), Some("owasp-top-ten"));
+        assert_eq!(pack_name(Path::new("p/java_1.2")), Some("java_1.2"));
+        assert_eq!(pack_name(Path::new("p/../secrets")), None);
+        assert_eq!(pack_name(Path::new("p/")), None);
+        assert_eq!(pack_name(Path::new("rules/p/owasp")), None);
+    }
+
+    #[test]
+    fn test_resolve_uses_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = registry(dir.path());
+        let cached = dir.path().join("owasp-top-ten.astgrep-pack");
+        let manifest = astgrep_rules::RulePackManifest::new("owasp-top-ten".to_string(), "1.0.0".to_string());
+        let pack = RulePack { manifest, rules: vec![("rules/js.yaml".to_string(), RULES.to_string())], fixtures: Vec::new() };
+        pack.write_to(&cached).unwrap();
+
+        let resolved = registry.resolve_all(vec![PathBuf::from("rules/js.yaml"), PathBuf::from("p/owasp-top-ten")]).unwrap();
+        assert_eq!(resolved, vec![PathBuf::from("rules/js.yaml"), cached.clone()]);
+
+        // A stale copy is still used when the registry cannot be reached
+        let stale = Registry { ttl: Duration::ZERO, ..registry.clone() };
+        assert_eq!(stale.resolve(PathBuf::from("p/owasp-top-ten")).unwrap(), cached);
+
+        let error = registry.resolve(PathBuf::from("p/missing")).unwrap_err().to_string();
+        assert!(error.contains("cannot download rule pack p/missing"), "{}", error);
+        assert!(!dir.path().join("missing.astgrep-pack.part").exists());
+
+        // Without a registry shorthands are rejected
+        let unconfigured = Registry { url: None, ..registry.clone() };
+        let error = unconfigured.resolve(PathBuf::from("p/owasp-top-ten")).unwrap_err().to_string();
+        assert!(error.contains("ASTGREP_REGISTRY_URL"), "{}", error);
+
+        // Without a per-user cache directory shorthands are rejected, local paths still resolve
+        let uncached = Registry { cache_dir: None, ..registry };
+        let error = uncached.resolve(PathBuf::from("p/owasp-top-ten")).unwrap_err().to_string();
+        assert!(error.contains("ASTGREP_CACHE_DIR"), "{}", error);
+        assert_eq!(uncached.resolve(PathBuf::from("rules/js.yaml")).unwrap(), PathBuf::from("rules/js.yaml"));
+    }
+
+    #[test]
+    fn test_resolve_relative_to_config_directory() {
+        let cache = tempfile::tempdir().unwrap();
+        let registry = registry(cache.path());
+        let cached = cache.path().join("owasp-top-ten.astgrep-pack");
+        let manifest = astgrep_rules::RulePackManifest::new("owasp-top-ten".to_string(), "1.0.0".to_string());
+        let pack = RulePack { manifest, rules: vec![("rules/js.yaml".to_string(), RULES.to_string())], fixtures: Vec::new() };
+        pack.write_to(&cached).unwrap();
+
+        let root = tempfile::tempdir().unwrap();
+        let paths = vec![PathBuf::from("rules/api.yaml"), PathBuf::from("p/owasp-top-ten"), PathBuf::from("p/local")];
+        std::fs::create_dir_all(root.path().join("p/local")).unwrap();
+        let resolved = registry.resolve_relative(root.path(), &paths).unwrap();
+        assert_eq!(resolved, vec![root.path().join("rules/api.yaml"), cached, root.path().join("p/local")]);
+    }
+}
@@ -0,0 +1,156 @@
+//! Custom report templates
+//!
+//! `--output-template <file>` renders the run through a [Tera](https://keats.github.io/tera/)
+//! template instead of a built-in format, for bespoke outputs such as ticketing payloads or
+//! wiki pages. The template sees:
+//!
+//! - `findings`: the findings as in the JSON report, each with its line-independent
+//!   `fingerprint`
+//! - `summary`: the `summary` object of the JSON report
+//! - `rules`: the loaded rules that produced findings, by id, with their name,
+//!   description and metadata
+//! - `metadata`: the tool version, report schema version, generation time, targets and
+//!   rule paths
+//!
+//! Templates whose file name ends in `.html`, `.htm` or `.xml` have their values escaped.
+
+use anyhow::{anyhow, Context as _, Result};
+use serde_json::{json, Map, Value};
+use std::path::Path;
+use crate::commands::analyze_enhanced::{AnalysisStatistics, Finding};
+use crate::EnhancedAnalysisConfig;
+
+/// Render the run through the template at `path`
+pub fn render(
+    path: &Path,
+    findings: &[Finding],
+    stats: &AnalysisStatistics,
+    config: &EnhancedAnalysisConfig,
+    total_time: std::time::Duration,
+) -> Result<String> {
+    let template = std::fs::read_to_string(path).with_context(|| format!("cannot read template {}", path.display()))?;
+    let context = template_context(findings, stats, config, total_time)?;
+    render_str(&template, &context, autoescape(path)).map_err(|e| anyhow!("template {}: {}", path.display(), e))
+}
+
+/// Render `template` with the values of `context`
+fn render_str(template: &str, context: &Value, autoescape: bool) -> Result<String> {
+    let context = tera::Context::from_value(context.clone())?;
+    tera::Tera::one_off(template, &context, autoescape).map_err(|e| {
+        // Tera puts the cause of a failed render in the error's sources
+        let mut message = e.to_string();
+        let mut source = std::error::Error::source(&e);
+        while let Some(cause) = source {
+            message.push_str(": ");
+            message.push_str(&cause.to_string());
+            source = cause.source();
+        }
+        anyhow!(message)
+    })
+}
+
+fn autoescape(path: &Path) -> bool {
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or_default().to_lowercase();
+    matches!(extension.as_str(), "html" | "htm" | "xml")
+}
+
+/// The values a template sees
+fn template_context(
+    findings: &[Finding],
+    stats: &AnalysisStatistics,
+    config: &EnhancedAnalysisConfig,
+    total_time: std::time::Duration,
+) -> Result<Value> {
+    let fingerprints = crate::commands::analyze_enhanced::fingerprints(findings);
+    let findings: Vec<Value> = findings
+        .iter()
+        .zip(fingerprints)
+        .map(|(finding, fingerprint)| {
+            let mut value = serde_json::to_value(finding)?;
+            value["fingerprint"] = json!(fingerprint);
+            Ok(value)
+        })
+        .collect::<Result<_>>()?;
+
+    let mut summary = json!({
+        "total_findings": findings.len(),
+        "files_analyzed": stats.files_analyzed,
+        "rules_executed": stats.rules_executed,
+        "analysis_time_ms": total_time.as_millis() as u64,
+        "status": stats.status(),
+    });
+    if !stats.skipped_files.is_empty() {
+        summary["skipped_files"] = json!(stats.skipped_files);
+    }
+    if !stats.failed_files.is_empty() {
+        summary["failed_files"] = json!(stats.failed_files);
+    }
+    if let Some(matched) = stats.baseline_matched {
+        summary["baseline_matched"] = json!(matched);
+    }
+
+    let repository = astgrep_rules::RuleRepository::from_paths(&config.rule_files);
+    let mut rules = Map::new();
+    for finding in &findings {
+        let id = finding["rule_id"].as_str().unwrap_or_default();
+        if let (false, Some(rule)) = (rules.contains_key(id), repository.rule(id)) {
+            rules.insert(
+                id.to_string(),
+                json!({ "name": rule.name, "description": rule.description, "metadata": rule.metadata }),
+            );
+        }
+    }
+
+    Ok(json!({
+        "findings": findings,
+        "summary": summary,
+        "rules": rules,
+        "metadata": {
+            "tool": "astgrep",
+            "version": env!("CARGO_PKG_VERSION"),
+            "schema_version": astgrep_core::REPORT_SCHEMA_VERSION,
+            "generated_at": chrono::Utc::now().to_rfc3339(),
+            "targets": config.target_paths,
+            "rule_files": config.rule_files,
+        },
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context() -> Value {
+        json!({
+            "findings": [
+                { "rule_id": "js-eval", "message": "Avoid <eval>", "severity": "ERROR",
+                  "location": { "file": "src/app.js", "start_line": 3 }, "fingerprint": "0123456789abcdef" },
+                { "rule_id": "js-exec", "message": "exec", "severity": "WARNING",
+                  "location": { "file": "src/util.js", "start_line": 9 }, "fingerprint": "fedcba9876543210" }
+            ],
+            "summary": { "total_findings": 2, "status": "completed" },
+            "rules": { "js-eval": { "name": "eval", "description": "", "metadata": { "cwe": "CWE-95" } } },
+            "metadata": { "tool": "astgrep" }
+        })
+    }
+
+    #[test]
+    fn test_render_template() {
+        let template = "{{ metadata.tool }}: {{ summary.total_findings }} finding(s)\n\
+            {% for f in findings %}{{ f.location.file }}:{{ f.location.start_line }} {{ f.rule_id }} {{ f.message }}\
+            {% if rules[f.rule_id] %} [{{ rules[f.rule_id].metadata.cwe }}]{% endif %}\n{% endfor %}";
+        let text = render_str(template, &context(), false).unwrap();
+        assert_eq!(text, "astgrep: 2 finding(s)\nsrc/app.js:3 js-eval Avoid <eval> [CWE-95]\nsrc/util.js:9 js-exec exec\n");
+
+        let html = render_str("<li>{{ findings.0.message }}</li>", &context(), true).unwrap();
+        assert_eq!(html, "<li>Avoid &lt;eval&gt;</li>");
+    }
+
+    #[test]
+    fn test_template_errors_name_the_cause() {
+        let error = render_str("{{ summary.missing }}", &context(), false).unwrap_err().to_string();
+        assert!(error.contains("summary.missing"), "{}", error);
+        assert!(render_str("{% for f in findings %}", &context(), false).is_err());
+        assert!(autoescape(Path::new("report.HTML")) && !autoescape(Path::new("ticket.json.tera")));
+    }
+}
@@ -0,0 +1,196 @@
+//! Reproduction fixtures for findings
+//!
+//! For each finding, writes a minimized copy of the matched code (the matched lines,
+//! a little surrounding context and the file's import lines) annotated with a
+//! `ruleid:` comment, next to a rule file holding just the matching rule. The pair
+//! uses the `<name>.yaml` + `<name>.<ext>` layout of `tests/rules`, so a confirmed
+//! true positive can be copied there as a regression test.
+
+use anyhow::Result;
+use astgrep_core::Language;
+use astgrep_rules::{RuleEngine, RulePack, RuleRepository};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+use crate::commands::analyze_enhanced::{determine_language, Finding};
+use crate::commands::rules::count_matches;
+
+/// Lines kept before and after the matched lines
+const CONTEXT_LINES: usize = 2;
+
+/// Line prefixes that pull names into scope and are kept in every reproduction
+const IMPORT_PREFIXES: &[&str] = &[
+    "import ", "from ", "#include", "using ", "require", "package ", "use ", "<?php",
+];
+
+/// Write a reproduction fixture and rule file per finding into `dir`; returns the
+/// number of reproductions written
+pub fn emit_reproductions(findings: &[Finding], rule_files: &[PathBuf], dir: &Path) -> Result<usize> {
+    std::fs::create_dir_all(dir)?;
+    let repository = RuleRepository::from_paths(rule_files);
+    let mut sources: BTreeMap<PathBuf, Option<String>> = BTreeMap::new();
+
+    let mut written = 0usize;
+    for finding in findings {
+        let file = &finding.location.file;
+        let source = sources
+            .entry(file.clone())
+            .or_insert_with(|| std::fs::read_to_string(file).ok());
+        let (source, language) = match (source.as_deref(), determine_language(file)) {
+            (Some(source), Ok(language)) => (source, language),
+            _ => {
+                warn!("Skipping reproduction of {} in {}: file is unreadable or of unknown language", finding.rule_id, file.display());
+                continue;
+            }
+        };
+        let (loaded, stanza) = match (repository.get(&finding.rule_id), rule_stanza(&repository, &finding.rule_id)) {
+            (Some(loaded), Some(stanza)) => (loaded, stanza),
+            _ => {
+                warn!("Skipping reproduction of {}: rule source not found", finding.rule_id);
+                continue;
+            }
+        };
+
+        let mut engine = RuleEngine::new();
+        engine.add_rule(loaded.rule.clone())?;
+        let fixture = reproduction(finding, source, language, |candidate| {
+            count_matches(&mut engine, file, candidate, language)
+                .map(|counts| counts.contains_key(&finding.rule_id))
+                .unwrap_or(false)
+        });
+
+        let stem = format!("{}-{}", sanitize(&finding.rule_id), &finding.stable_id()[..8]);
+        let extension = file.extension().map(|e| e.to_string_lossy().to_string()).unwrap_or_default();
+        std::fs::write(dir.join(format!("{}.{}", stem, extension)), fixture)?;
+        std::fs::write(dir.join(format!("{}.yaml", stem)), stanza)?;
+        written += 1;
+    }
+
+    info!("Wrote {} reproduction(s) to {}", written, dir.display());
+    Ok(written)
+}
+
+/// Annotated reproduction of `finding`: the minimized snippet when `still_matches` accepts
+/// it, otherwise the whole annotated file
+fn reproduction(finding: &Finding, source: &str, language: Language, mut still_matches: impl FnMut(&str) -> bool) -> String {
+    let lines: Vec<&str> = source.lines().collect();
+    let loc = &finding.location;
+    let start = loc.start_line.clamp(1, lines.len().max(1)) - 1;
+    let end = loc.end_line.min(lines.len()).max(start + 1);
+
+    let window_start = start.saturating_sub(CONTEXT_LINES);
+    let window_end = (end + CONTEXT_LINES).min(lines.len());
+    let mut kept: Vec<usize> = (0..window_start)
+        .filter(|&i| IMPORT_PREFIXES.iter().any(|p| lines[i].trim_start().starts_with(p)))
+        .collect();
+    kept.extend(window_start..window_end);
+
+    let minimized = annotate(&lines, &kept, start, &finding.rule_id, language);
+    if still_matches(&minimized) {
+        return minimized;
+    }
+    annotate(&lines, &(0..lines.len()).collect::<Vec<_>>(), start, &finding.rule_id, language)
+}
+
+/// Join the `kept` lines, with a `ruleid:` comment above line index `matched`
+fn annotate(lines: &[&str], kept: &[usize], matched: usize, rule_id: &str, language: Language) -> String {
+    let mut out = String::new();
+    for &i in kept {
+        if i == matched {
+            let indent: String = lines[i].chars().take_while(|c| c.is_whitespace()).collect();
+            out.push_str(&indent);
+            out.push_str(&comment(language, &format!("ruleid: {}", rule_id)));
+            out.push('\n');
+        }
+        out.push_str(lines[i]);
+        out.push('\n');
+    }
+    out
+}
+
+fn comment(language: Language, text: &str) -> String {
+    match language {
+        Language::Python | Language::Bash | Language::Ruby => format!("# {}", text),
+        Language::Sql => format!("-- {}", text),
+        Language::Xml => format!("<!-- {} -->", text),
+        _ => format!("// {}", text),
+    }
+}
+
+/// The rule's YAML as written in its source file or pack, wrapped in a `rules:` list
+fn rule_stanza(repository: &RuleRepository, rule_id: &str) -> Option<String> {
+    let origin = &repository.get(rule_id)?.origin;
+    let content = match origin.split_once('!') {
+        Some((pack, entry)) => RulePack::read_from(Path::new(pack))
+            .ok()?
+            .rules
+            .into_iter()
+            .find(|(name, _)| name == entry)?
+            .1,
+        None => std::fs::read_to_string(origin).ok()?,
+    };
+
+    let document: serde_yaml::Value = serde_yaml::from_str(&content).ok()?;
+    let rule = document
+        .get("rules")?
+        .as_sequence()?
+        .iter()
+        .find(|r| r.get("id").and_then(|id| id.as_str()) == Some(rule_id))?
+        .clone();
+
+    let mut stanza = serde_yaml::Mapping::new();
+    stanza.insert("rules".into(), serde_yaml::Value::Sequence(vec![rule]));
+    serde_yaml::to_string(&stanza).ok()
+}
+
+/// Rule id made safe for use in a file name
+fn sanitize(rule_id: &str) -> String {
+    rule_id
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::analyze_enhanced::{test_finding, Location};
+
+    fn finding(line: usize) -> Finding {
+        Finding {
+            message: "os.system".to_string(),
+            location: Location::new("app.py", line, 1, line, 15),
+            ..test_finding("py.os-system", "app.py", line)
+        }
+    }
+
+    #[test]
+    fn test_reproduction_keeps_imports_and_context() {
+        let source = "import os\nx = 1\ny = 2\nz = 3\nw = 4\nos.system(cmd)\nv = 5\n";
+        let repro = reproduction(&finding(6), source, Language::Python, |_| true);
+        assert_eq!(repro, "import os\nz = 3\nw = 4\n# ruleid: py.os-system\nos.system(cmd)\nv = 5\n");
+    }
+
+    #[test]
+    fn test_reproduction_falls_back_to_whole_file() {
+        let source = "import os\nx = 1\ny = 2\nz = 3\nw = 4\nos.system(cmd)\n";
+        let repro = reproduction(&finding(6), source, Language::Python, |_| false);
+        assert_eq!(repro, "import os\nx = 1\ny = 2\nz = 3\nw = 4\n# ruleid: py.os-system\nos.system(cmd)\n");
+    }
+
+    #[test]
+    fn test_rule_stanza_extracts_single_rule() {
+        let dir = tempfile::tempdir().unwrap();
+        let rules = dir.path().join("rules.yaml");
+        std::fs::write(
+            &rules,
+            "rules:\n  - id: py.os-system\n    message: os.system\n    languages: [python]\n    severity: ERROR\n    pattern: os.system($X)\n  - id: py.eval\n    message: eval\n    languages: [python]\n    severity: ERROR\n    pattern: eval($X)\n",
+        ).unwrap();
+
+        let repository = RuleRepository::from_paths(&[rules]);
+        let stanza = rule_stanza(&repository, "py.os-system").unwrap();
+        assert!(stanza.contains("py.os-system"));
+        assert!(!stanza.contains("py.eval"));
+        assert_eq!(sanitize("py.os-system"), "py_os-system");
+    }
+}
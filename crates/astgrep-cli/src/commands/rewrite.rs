@@ -0,0 +1,275 @@
+//! Structural search-and-replace
+//!
+//! Runs one pattern as an ad-hoc rule whose fix is the replacement template, so
+//! metavariables bound by the pattern are substituted exactly as they are in rule
+//! autofixes. Rewrites are previewed as unified diffs, also when `--dry-run` asks for it
+//! explicitly; `--in-place` writes them to the files and `--interactive` shows each one
+//! and writes those that are confirmed.
+//!
+//! Files are collected the way `analyze` collects them, so `.astgrepignore` and
+//! `.gitignore` files and `--exclude` patterns keep vendored and generated code out.
+
+use anyhow::{anyhow, Result};
+use astgrep_core::Language;
+use astgrep_parser::LanguageParserRegistry;
+use astgrep_rules::{dsl, RuleBuilder, RuleContext, RuleEngine};
+use regex::Regex;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+use crate::commands::analyze_enhanced::{collect_target_files, Finding, Location};
+use crate::commands::patches::{apply_edits, line_edit, non_overlapping, patch_path, unified_diff, LineEdit};
+use crate::EnhancedAnalysisConfig;
+
+/// Id of the ad-hoc rule carrying the pattern and replacement
+const REWRITE_RULE_ID: &str = "rewrite";
+
+/// Answer to an interactive confirmation
+enum Answer {
+    Yes,
+    No,
+    All,
+    Quit,
+}
+
+/// The files `rewrite` is run on
+#[derive(Debug, Clone, Default)]
+pub struct RewriteTargets {
+    /// Files or directories (default: the current directory)
+    pub paths: Vec<PathBuf>,
+    /// Glob patterns of files to leave alone
+    pub exclude: Vec<String>,
+    /// Whether `.gitignore` files are honored
+    pub git_ignore: bool,
+}
+
+pub async fn run(
+    targets: RewriteTargets,
+    language: String,
+    pattern: String,
+    replacement: String,
+    in_place: bool,
+    dry_run: bool,
+    interactive: bool,
+) -> Result<()> {
+    if dry_run && (in_place || interactive) {
+        return Err(anyhow!("--dry-run cannot be combined with --in-place or --interactive"));
+    }
+    let dry_run = dry_run || (!in_place && !interactive);
+    let language = Language::from_str(&language).ok_or_else(|| anyhow!("Unsupported language: {}", language))?;
+    let mut engine = rewrite_engine(language, &pattern, &replacement)?;
+
+    let files = collect_files(targets, language).await?;
+
+    let mut apply_all = !interactive;
+    let (mut occurrences, mut changed_files) = (0usize, 0usize);
+    for file in files {
+        let source = match std::fs::read_to_string(&file) {
+            Ok(source) => source,
+            Err(e) => {
+                warn!("Skipping {}: {}", file.display(), e);
+                continue;
+            }
+        };
+        let edits = match plan_rewrites(&mut engine, &file, &source, language) {
+            Ok(edits) if !edits.is_empty() => edits,
+            Ok(_) => continue,
+            Err(e) => {
+                warn!("Skipping {}: {}", file.display(), e);
+                continue;
+            }
+        };
+        let display_path = patch_path(&file);
+
+        if dry_run {
+            print!("{}", unified_diff(&display_path, &source, &edits));
+            occurrences += edits.len();
+            changed_files += 1;
+            continue;
+        }
+
+        let mut accepted = Vec::new();
+        let mut quit = false;
+        for edit in edits {
+            if apply_all {
+                accepted.push(edit);
+                continue;
+            }
+            print!("{}", unified_diff(&display_path, &source, std::slice::from_ref(&edit)));
+            match prompt()? {
+                Answer::Yes => accepted.push(edit),
+                Answer::No => {}
+                Answer::All => {
+                    apply_all = true;
+                    accepted.push(edit);
+                }
+                Answer::Quit => {
+                    quit = true;
+                    break;
+                }
+            }
+        }
+
+        if !accepted.is_empty() {
+            std::fs::write(&file, apply_edits(&source, &accepted))?;
+            info!("Rewrote {} occurrence(s) in {}", accepted.len(), display_path);
+            occurrences += accepted.len();
+            changed_files += 1;
+        }
+        if quit {
+            break;
+        }
+    }
+
+    if dry_run {
+        eprintln!("{} occurrence(s) in {} file(s) would be rewritten; pass --in-place to apply", occurrences, changed_files);
+    } else {
+        eprintln!("{} occurrence(s) in {} file(s) rewritten", occurrences, changed_files);
+    }
+    Ok(())
+}
+
+/// Engine holding the single rule that matches `pattern` and fixes it to `replacement`
+fn rewrite_engine(language: Language, pattern: &str, replacement: &str) -> Result<RuleEngine> {
+    let metavariable = Regex::new(r"\$[A-Z_][A-Z0-9_]*")?;
+    let bound: Vec<&str> = metavariable.find_iter(pattern).map(|m| m.as_str()).collect();
+    if let Some(unbound) = metavariable.find_iter(replacement).map(|m| m.as_str()).find(|name| !bound.contains(name)) {
+        return Err(anyhow!("Replacement uses {} which the pattern does not bind", unbound));
+    }
+
+    let rule = RuleBuilder::new(REWRITE_RULE_ID)
+        .message(format!("Rewrite {}", pattern))
+        .language(language)
+        .pattern(dsl::pattern(pattern))
+        .fix(replacement)
+        .build()?;
+    let mut engine = RuleEngine::new();
+    engine.add_rule(rule)?;
+    Ok(engine)
+}
+
+/// Sorted, non-overlapping line edits rewriting every match in `source`
+fn plan_rewrites(engine: &mut RuleEngine, file: &Path, source: &str, language: Language) -> Result<Vec<LineEdit>> {
+    let registry = LanguageParserRegistry::new();
+    let parser = registry
+        .get_parser(language)
+        .ok_or_else(|| anyhow!("No parser registered for {:?}", language))?;
+    let ast = parser.parse(source, file)?;
+    let context = RuleContext::new(file.to_string_lossy().to_string(), language, source.to_string());
+
+    let findings: Vec<Finding> = engine
+        .analyze(ast.as_ref(), &context)?
+        .into_iter()
+        .map(|f| Finding {
+            rule_id: f.rule_id,
+            message: f.message,
+            severity: f.severity,
+            confidence: f.confidence,
+            location: Location::new(f.location.file, f.location.start_line, f.location.start_column, f.location.end_line, f.location.end_column),
+            fix: f.fix_suggestion,
+            ..Finding::default()
+        })
+        .collect();
+
+    let edits = findings.iter().filter_map(|f| line_edit(f, source).map(|e| (f, e))).collect();
+    Ok(non_overlapping(edits, &patch_path(file)))
+}
+
+/// Files of `language` among the targets that `analyze` would scan; an explicitly named
+/// file is always included
+async fn collect_files(targets: RewriteTargets, language: Language) -> Result<Vec<PathBuf>> {
+    let config = EnhancedAnalysisConfig {
+        target_paths: if targets.paths.is_empty() { vec![PathBuf::from(".")] } else { targets.paths },
+        languages: vec![language],
+        exclude_patterns: targets.exclude,
+        git_ignore: targets.git_ignore,
+        ..Default::default()
+    };
+    let mut skipped = Vec::new();
+    let mut files = collect_target_files(&config, &mut skipped).await?;
+    for skip in skipped {
+        warn!("Skipping {}: {:?}", skip.file, skip.reason);
+    }
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+fn prompt() -> Result<Answer> {
+    let stdin = std::io::stdin();
+    loop {
+        eprint!("Apply this change? [y]es, [n]o, [a]ll, [q]uit: ");
+        std::io::stderr().flush()?;
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            return Ok(Answer::Quit);
+        }
+        match line.trim().to_lowercase().as_str() {
+            "y" | "yes" => return Ok(Answer::Yes),
+            "n" | "no" => return Ok(Answer::No),
+            "a" | "all" => return Ok(Answer::All),
+            "q" | "quit" => return Ok(Answer::Quit),
+            _ => continue,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rewrite_substitutes_metavariables() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("App.java");
+        let source = "class App {\n    void run(String input) {\n        foo(input);\n    }\n}\n";
+        std::fs::write(&file, source).unwrap();
+
+        let mut engine = rewrite_engine(Language::Java, "foo($X)", "bar($X)").unwrap();
+        let edits = plan_rewrites(&mut engine, &file, source, Language::Java).unwrap();
+        assert_eq!(edits.len(), 1);
+        assert_eq!(
+            apply_edits(source, &edits),
+            "class App {\n    void run(String input) {\n        bar(input);\n    }\n}\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rewrite_previews_unless_in_place() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("app.py");
+        std::fs::write(&file, "foo(a)\nfoo(b)\n").unwrap();
+        let targets = RewriteTargets { paths: vec![dir.path().to_path_buf()], exclude: Vec::new(), git_ignore: true };
+        let rewrite = |in_place, dry_run| run(targets.clone(), "python".to_string(), "foo($X)".to_string(), "bar($X)".to_string(), in_place, dry_run, false);
+
+        rewrite(false, false).await.unwrap();
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "foo(a)\nfoo(b)\n");
+        rewrite(false, true).await.unwrap();
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "foo(a)\nfoo(b)\n");
+        assert!(rewrite(true, true).await.is_err());
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "foo(a)\nfoo(b)\n");
+        rewrite(true, false).await.unwrap();
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "bar(a)\nbar(b)\n");
+    }
+
+    #[tokio::test]
+    async fn test_rewrite_skips_ignored_and_excluded_files() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("vendor")).unwrap();
+        std::fs::write(dir.path().join(".astgrepignore"), "vendor/\n").unwrap();
+        for name in ["app.py", "app_test.py", "vendor/lib.py"] {
+            std::fs::write(dir.path().join(name), "foo(a)\n").unwrap();
+        }
+
+        let targets = RewriteTargets { paths: vec![dir.path().to_path_buf()], exclude: vec!["*_test.py".to_string()], git_ignore: true };
+        run(targets, "python".to_string(), "foo($X)".to_string(), "bar($X)".to_string(), true, false, false).await.unwrap();
+        assert_eq!(std::fs::read_to_string(dir.path().join("app.py")).unwrap(), "bar(a)\n");
+        assert_eq!(std::fs::read_to_string(dir.path().join("app_test.py")).unwrap(), "foo(a)\n");
+        assert_eq!(std::fs::read_to_string(dir.path().join("vendor/lib.py")).unwrap(), "foo(a)\n");
+    }
+
+    #[test]
+    fn test_rewrite_rejects_unbound_metavariable() {
+        assert!(rewrite_engine(Language::Java, "foo($X)", "bar($Y)").is_err());
+    }
+}
@@ -0,0 +1,265 @@
+//! Risk scoring
+//!
+//! Every reported finding gets a risk score from 0 to 100 and an estimated remediation
+//! effort. The score is a weighted sum of four factors: severity, confidence, whether
+//! the finding is in production code (no call graph is consulted; findings in test
+//! files simply count as less exposed) and taint corroboration (the engine traced data
+//! from a taint source into the match). Projects can re-weight the factors under
+//! `[risk]` in `astgrep.toml`; rules can state their fix effort in `metadata.effort`.
+
+use anyhow::{anyhow, Result};
+use astgrep_core::{Confidence, Severity};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::commands::analyze_enhanced::Finding;
+
+/// Rule metadata key overriding the estimated remediation effort
+pub const EFFORT_METADATA_KEY: &str = "effort";
+
+/// Findings spanning more lines than this are at least a large fix
+const LARGE_SPAN_LINES: usize = 10;
+
+/// Estimated work to remediate a finding
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Effort {
+    /// An autofix is available
+    Trivial,
+    /// A local change at the reported location
+    Small,
+    /// Untrusted data has to be validated or sanitized along its path
+    Medium,
+    /// The affected code has to be restructured
+    Large,
+}
+
+impl Effort {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Effort::Trivial => "trivial",
+            Effort::Small => "small",
+            Effort::Medium => "medium",
+            Effort::Large => "large",
+        }
+    }
+
+    pub fn parse(spec: &str) -> Result<Self> {
+        match spec.trim().to_lowercase().as_str() {
+            "trivial" => Ok(Effort::Trivial),
+            "small" => Ok(Effort::Small),
+            "medium" => Ok(Effort::Medium),
+            "large" => Ok(Effort::Large),
+            other => Err(anyhow!("unknown effort '{}' (expected trivial, small, medium or large)", other)),
+        }
+    }
+}
+
+/// Weights of the risk factors; they are normalized, so only their ratios matter
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RiskModel {
+    pub severity: f64,
+    pub confidence: f64,
+    /// Weight of being outside test files; `reachability` is accepted as its old name
+    #[serde(alias = "reachability")]
+    pub production: f64,
+    pub taint: f64,
+}
+
+impl Default for RiskModel {
+    fn default() -> Self {
+        Self { severity: 0.45, confidence: 0.25, production: 0.15, taint: 0.15 }
+    }
+}
+
+#[derive(Deserialize)]
+struct RiskFile {
+    #[serde(default)]
+    risk: Option<RiskModel>,
+}
+
+/// What the scoring pass knows about the rule behind a finding
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RuleSignals {
+    /// Effort stated by the rule's `effort` metadata
+    pub effort: Option<Effort>,
+}
+
+impl RuleSignals {
+    pub fn for_rule(rule: &astgrep_rules::Rule) -> Self {
+        Self {
+            effort: rule.metadata.get(EFFORT_METADATA_KEY).and_then(|spec| {
+                Effort::parse(spec)
+                    .map_err(|e| tracing::warn!("Ignoring effort of rule {}: {}", rule.id, e))
+                    .ok()
+            }),
+        }
+    }
+}
+
+impl RiskModel {
+    /// The `[risk]` weights of `config_file`, or the default model when it has none
+    pub fn load(config_file: &Path) -> Result<Self> {
+        let model = match std::fs::read_to_string(config_file) {
+            Ok(text) => toml::from_str::<RiskFile>(&text)
+                .map_err(|e| anyhow!("Invalid risk model in {}: {}", config_file.display(), e))?
+                .risk
+                .unwrap_or_default(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Self::default(),
+            Err(e) => return Err(e.into()),
+        };
+
+        let weights = [model.severity, model.confidence, model.production, model.taint];
+        if weights.iter().any(|w| !w.is_finite() || *w < 0.0) || weights.iter().sum::<f64>() <= 0.0 {
+            return Err(anyhow!("Invalid risk model in {}: weights must be non-negative and not all zero", config_file.display()));
+        }
+        Ok(model)
+    }
+
+    /// Risk score of a finding, from 0 (negligible) to 100
+    pub fn score(&self, finding: &Finding) -> u8 {
+        let severity = match finding.severity {
+            Severity::Info => 0.1,
+            Severity::Warning => 0.4,
+            Severity::Error => 0.75,
+            Severity::Critical => 1.0,
+        };
+        let confidence = match finding.confidence {
+            Confidence::Low => 0.3,
+            Confidence::Medium => 0.6,
+            Confidence::High => 1.0,
+        };
+        let production = if finding.is_test { 0.2 } else { 1.0 };
+        let taint = if finding.taint_corroborated { 1.0 } else { 0.0 };
+
+        let total = self.severity + self.confidence + self.production + self.taint;
+        let weighted = self.severity * severity
+            + self.confidence * confidence
+            + self.production * production
+            + self.taint * taint;
+        (weighted / total * 100.0).round().clamp(0.0, 100.0) as u8
+    }
+}
+
+/// Estimated remediation effort: the rule's own estimate, otherwise trivial when an
+/// autofix exists, medium for taint-corroborated findings and small or large by the matched span
+pub fn estimate_effort(finding: &Finding, signals: RuleSignals) -> Effort {
+    if let Some(effort) = signals.effort {
+        return effort;
+    }
+    if finding.fix.is_some() {
+        return Effort::Trivial;
+    }
+    let span = finding.location.end_line.saturating_sub(finding.location.start_line);
+    if span > LARGE_SPAN_LINES {
+        Effort::Large
+    } else if finding.taint_corroborated {
+        Effort::Medium
+    } else {
+        Effort::Small
+    }
+}
+
+/// Score every finding and estimate its effort, looking up the signals of its rule in
+/// the configured rule files
+pub fn score_findings(findings: &mut [Finding], model: &RiskModel, rule_files: &[std::path::PathBuf]) {
+    let repository = astgrep_rules::RuleRepository::from_paths(rule_files);
+    let mut signals: HashMap<String, RuleSignals> = HashMap::new();
+
+    for finding in findings.iter_mut() {
+        let rule_signals = *signals
+            .entry(finding.rule_id.clone())
+            .or_insert_with(|| repository.rule(&finding.rule_id).map(RuleSignals::for_rule).unwrap_or_default());
+        finding.risk_score = Some(model.score(finding));
+        finding.effort = Some(estimate_effort(finding, rule_signals));
+    }
+}
+
+/// Order findings from the highest to the lowest risk; equal scores keep their order
+pub fn sort_by_risk(findings: &mut [Finding]) {
+    findings.sort_by_key(|f| Reverse(f.risk_score));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::analyze_enhanced::test_finding;
+
+    fn finding(severity: Severity, confidence: Confidence, is_test: bool) -> Finding {
+        Finding { severity, confidence, is_test, ..test_finding("rule", "a.js", 3) }
+    }
+
+    #[test]
+    fn test_risk_score_and_effort() {
+        let model = RiskModel::default();
+        let critical = finding(Severity::Critical, Confidence::High, false);
+        let corroborated = Finding { taint_corroborated: true, ..critical.clone() };
+
+        assert_eq!(model.score(&corroborated), 100);
+        assert_eq!(model.score(&critical), 85);
+        assert!(model.score(&Finding { is_test: true, ..corroborated.clone() }) < 100);
+        assert!(model.score(&finding(Severity::Info, Confidence::Low, false)) < 30);
+
+        assert_eq!(estimate_effort(&corroborated, RuleSignals::default()), Effort::Medium);
+        assert_eq!(estimate_effort(&critical, RuleSignals::default()), Effort::Small);
+        let mut fixable = corroborated.clone();
+        fixable.fix = Some("fixed".to_string());
+        assert_eq!(estimate_effort(&fixable, RuleSignals::default()), Effort::Trivial);
+        assert_eq!(estimate_effort(&fixable, RuleSignals { effort: Some(Effort::Large) }), Effort::Large);
+
+        let mut findings = vec![finding(Severity::Info, Confidence::Low, false), critical];
+        for f in findings.iter_mut() {
+            f.risk_score = Some(model.score(f));
+        }
+        sort_by_risk(&mut findings);
+        assert_eq!(findings[0].severity, Severity::Critical);
+    }
+
+    #[test]
+    fn test_taint_factor_follows_the_flow() {
+        let dir = tempfile::tempdir().unwrap();
+        let rules = dir.path().join("rules.yaml");
+        std::fs::write(
+            &rules,
+            "rules:\n  - id: js-eval-request\n    message: eval of request data\n    severity: WARNING\n    languages: [javascript]\n    pattern: eval($X)\n    dataflow:\n      sources: [\"req.query.code\"]\n      sinks: [\"eval($X)\"]\n",
+        )
+        .unwrap();
+        let config = crate::EnhancedAnalysisConfig { rule_files: vec![rules.clone()], ..Default::default() };
+        let model = RiskModel::default();
+        let analyze = |source: &str| {
+            let file = dir.path().join("app.js");
+            let mut findings = crate::commands::analyze_enhanced::analyze_source(&file, source, &config).unwrap();
+            findings.retain(|f| f.location.start_line == 2);
+            score_findings(&mut findings, &model, std::slice::from_ref(&rules));
+            findings
+        };
+
+        // The same taint rule matches both files, only the first has a flow into the sink
+        let tainted = analyze("const code = req.query.code;\neval(code);\n");
+        let untainted = analyze("const code = '1 + 1';\neval(code);\n");
+        assert!(tainted.iter().all(|f| f.taint_corroborated), "{:?}", tainted);
+        assert!(!untainted.is_empty() && untainted.iter().all(|f| !f.taint_corroborated), "{:?}", untainted);
+        assert!(tainted[0].risk_score > untainted[0].risk_score);
+    }
+
+    #[test]
+    fn test_load_risk_model() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = dir.path().join("astgrep.toml");
+        assert_eq!(RiskModel::load(&config).unwrap(), RiskModel::default());
+
+        std::fs::write(&config, "[risk]\nseverity = 1.0\nconfidence = 0.0\nproduction = 0.0\ntaint = 0.0\n").unwrap();
+        let model = RiskModel::load(&config).unwrap();
+        assert_eq!(model.score(&finding(Severity::Warning, Confidence::High, false)), 40);
+
+        // The production weight used to be called reachability
+        std::fs::write(&config, "[risk]\nreachability = 0.5\n").unwrap();
+        assert_eq!(RiskModel::load(&config).unwrap().production, 0.5);
+
+        std::fs::write(&config, "[risk]\nseverity = -1.0\n").unwrap();
+        assert!(RiskModel::load(&config).is_err());
+    }
+}
@@ -0,0 +1,274 @@
+//! Rule unit tests
+//!
+//! A rule file `<name>.yaml` is tested by the fixtures next to it with the same stem
+//! (`<name>.py`, `<name>.java`, ...). Comments in a fixture state what the rules must do
+//! on the next code line: `ruleid: <id>` expects a match of rule `<id>`, `ok: <id>`
+//! expects none. Several ids can be listed, separated by commas. Any match on a line
+//! not annotated with `ruleid:` for that rule is a failure too. Directories are walked
+//! like `analyze` walks its targets, honoring ignore files and `--exclude` patterns.
+
+use anyhow::Result;
+use astgrep_rules::{RuleEngine, RuleRepository};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+use crate::commands::analyze_enhanced::{determine_language, load_rules_into_engine_from_paths};
+use crate::commands::ignore_file::walk_files;
+use crate::commands::rules::analyze_source;
+use crate::OutputFormatCli;
+
+/// What the annotations of a fixture expect
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Expectations {
+    /// `(rule id, line)` pairs that must match
+    pub matches: BTreeSet<(String, usize)>,
+    /// `(rule id, line)` pairs explicitly marked as not matching
+    pub ok: BTreeSet<(String, usize)>,
+}
+
+impl Expectations {
+    /// Rule ids mentioned by any annotation
+    pub fn rule_ids(&self) -> BTreeSet<&str> {
+        self.matches.iter().chain(&self.ok).map(|(id, _)| id.as_str()).collect()
+    }
+}
+
+/// Outcome of one fixture
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct FixtureResult {
+    pub rule_file: PathBuf,
+    pub fixture: PathBuf,
+    /// Annotated `ruleid:` matches that were found
+    pub passed: usize,
+    /// `ruleid:` annotations without a match, as `(rule id, line)`
+    pub missed: Vec<(String, usize)>,
+    /// Matches on lines not annotated with `ruleid:` for the rule
+    pub unexpected: Vec<(String, usize)>,
+    /// Annotations naming rules the rule file does not define
+    pub unknown_rules: Vec<String>,
+    /// Why the fixture could not be run
+    pub error: Option<String>,
+}
+
+impl FixtureResult {
+    pub fn is_success(&self) -> bool {
+        self.missed.is_empty() && self.unexpected.is_empty() && self.unknown_rules.is_empty() && self.error.is_none()
+    }
+}
+
+/// Run the rule tests found under `paths`, failing when any fixture fails
+pub async fn run(paths: Vec<PathBuf>, format: OutputFormatCli, exclude: Vec<String>, git_ignore: bool) -> Result<()> {
+    let mut pairs = Vec::new();
+    for path in &paths {
+        collect_rule_tests(path, &exclude, git_ignore, &mut pairs)?;
+    }
+    if pairs.is_empty() {
+        return Err(anyhow::anyhow!("No rule tests found: place fixtures next to rule files with the same stem (sqli.yaml + sqli.java)"));
+    }
+
+    info!("Running {} rule test fixture(s)", pairs.len());
+    let results: Vec<FixtureResult> = pairs.iter().map(|(rules, fixture)| test_fixture(rules, fixture)).collect();
+    let failed = results.iter().filter(|r| !r.is_success()).count();
+
+    let output = match format {
+        OutputFormatCli::Json => serde_json::to_string_pretty(&results)?,
+        _ => format_results(&results),
+    };
+    println!("{}", output);
+
+    if failed > 0 {
+        return Err(anyhow::anyhow!("{} of {} rule test fixture(s) failed", failed, results.len()));
+    }
+    Ok(())
+}
+
+/// Run the rules of `rule_file` on `fixture` and compare the matches with its annotations
+pub fn test_fixture(rule_file: &Path, fixture: &Path) -> FixtureResult {
+    let mut result = FixtureResult { rule_file: rule_file.to_path_buf(), fixture: fixture.to_path_buf(), ..FixtureResult::default() };
+    if let Err(e) = check_fixture(rule_file, fixture, &mut result) {
+        result.error = Some(e.to_string());
+    }
+    result
+}
+
+fn check_fixture(rule_file: &Path, fixture: &Path, result: &mut FixtureResult) -> Result<()> {
+    let source = std::fs::read_to_string(fixture)?;
+    let language = determine_language(fixture)?;
+    let expectations = parse_annotations(&source);
+
+    let rule_ids: BTreeSet<String> = RuleRepository::from_paths(&[rule_file.to_path_buf()]).rules().map(|r| r.id.clone()).collect();
+    result.unknown_rules = expectations.rule_ids().into_iter().filter(|id| !rule_ids.contains(*id)).map(str::to_string).collect();
+
+    let mut engine = RuleEngine::new();
+    if load_rules_into_engine_from_paths(&[rule_file.to_path_buf()], &mut engine)? == 0 {
+        return Err(anyhow::anyhow!("no rules loaded from {}", rule_file.display()));
+    }
+    let actual: BTreeSet<(String, usize)> = analyze_source(&mut engine, fixture, &source, language)?
+        .into_iter()
+        .map(|finding| (finding.rule_id, finding.location.start_line))
+        .collect();
+
+    result.passed = expectations.matches.intersection(&actual).count();
+    result.missed = expectations.matches.difference(&actual).cloned().collect();
+    result.unexpected = actual.difference(&expectations.matches).cloned().collect();
+    Ok(())
+}
+
+/// Read the `ruleid:` and `ok:` annotations of a fixture; each applies to the next line
+/// that is not itself an annotation
+pub fn parse_annotations(source: &str) -> Expectations {
+    let mut expectations = Expectations::default();
+    let mut pending: Vec<(bool, String)> = Vec::new();
+
+    for (index, line) in source.lines().enumerate() {
+        match annotation(line) {
+            Some((expect_match, ids)) => pending.extend(ids.into_iter().map(|id| (expect_match, id))),
+            None if line.trim().is_empty() => {}
+            None => {
+                for (expect_match, id) in pending.drain(..) {
+                    let target = if expect_match { &mut expectations.matches } else { &mut expectations.ok };
+                    target.insert((id, index + 1));
+                }
+            }
+        }
+    }
+    if !pending.is_empty() {
+        warn!("Ignoring annotations at the end of a fixture: nothing follows them");
+    }
+    expectations
+}
+
+/// `(expects a match, rule ids)` of an annotation comment line
+fn annotation(line: &str) -> Option<(bool, Vec<String>)> {
+    let body = ["//", "#", "--", "<!--", "/*", "*"]
+        .iter()
+        .find_map(|prefix| line.trim_start().strip_prefix(prefix))?
+        .trim()
+        .trim_end_matches("-->")
+        .trim_end_matches("*/");
+    let (expect_match, ids) = if let Some(ids) = body.strip_prefix("ruleid:") {
+        (true, ids)
+    } else {
+        (false, body.strip_prefix("ok:")?)
+    };
+    let ids: Vec<String> = ids.split(',').map(str::trim).filter(|id| !id.is_empty()).map(str::to_string).collect();
+    (!ids.is_empty()).then_some((expect_match, ids))
+}
+
+/// `(rule file, fixture)` pairs under `path`: fixtures share the rule file's stem
+fn collect_rule_tests(path: &Path, exclude: &[String], git_ignore: bool, pairs: &mut Vec<(PathBuf, PathBuf)>) -> Result<()> {
+    if path.is_dir() {
+        let files = walk_files(path, git_ignore, exclude)?;
+        for rule_file in files.iter().filter(|f| is_rule_file(f)) {
+            let fixtures = files
+                .iter()
+                .filter(|f| !is_rule_file(f) && f.parent() == rule_file.parent() && f.file_stem() == rule_file.file_stem());
+            for fixture in fixtures {
+                pairs.push((rule_file.clone(), fixture.clone()));
+            }
+        }
+    } else if is_rule_file(path) {
+        let dir = path.parent().filter(|d| !d.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        for entry in std::fs::read_dir(dir)? {
+            let fixture = entry?.path();
+            if fixture.is_file() && !is_rule_file(&fixture) && fixture.file_stem() == path.file_stem() {
+                pairs.push((path.to_path_buf(), fixture));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn is_rule_file(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "yaml" || ext == "yml")
+}
+
+fn format_results(results: &[FixtureResult]) -> String {
+    let mut output = String::from("🧪 Rule tests\n");
+    for result in results {
+        if result.is_success() {
+            output.push_str(&format!("\n  ✅ {} ({} expected match(es))\n", result.fixture.display(), result.passed));
+            continue;
+        }
+        output.push_str(&format!("\n  ❌ {} (rules: {})\n", result.fixture.display(), result.rule_file.display()));
+        if let Some(ref error) = result.error {
+            output.push_str(&format!("    • error: {}\n", error));
+        }
+        for id in &result.unknown_rules {
+            output.push_str(&format!("    • annotation names unknown rule {}\n", id));
+        }
+        for (id, line) in &result.missed {
+            output.push_str(&format!("    • line {}: expected {} to match\n", line, id));
+        }
+        for (id, line) in &result.unexpected {
+            output.push_str(&format!("    • line {}: {} matched unexpectedly\n", line, id));
+        }
+    }
+
+    let passed = results.iter().filter(|r| r.is_success()).count();
+    output.push_str(&format!("\n{} of {} fixture(s) passed", passed, results.len()));
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RULE: &str = r#"
+rules:
+  - id: os-system
+    message: Command execution via os.system
+    severity: ERROR
+    languages: [python]
+    pattern: "os.system(...)"
+"#;
+
+    #[test]
+    fn test_parse_annotations() {
+        let source = "// ruleid: a, b\n\n// ok: c\nfoo();\n/* ok: a */\nbar();\n# todo: a\nbaz()\n";
+        let expectations = parse_annotations(source);
+        assert_eq!(
+            expectations.matches,
+            BTreeSet::from([("a".to_string(), 4), ("b".to_string(), 4)])
+        );
+        assert_eq!(expectations.ok, BTreeSet::from([("c".to_string(), 4), ("a".to_string(), 6)]));
+    }
+
+    #[test]
+    fn test_rule_fixtures() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("os-system.yaml"), RULE).unwrap();
+        std::fs::write(
+            dir.path().join("os-system.py"),
+            "import os\n# ruleid: os-system\nos.system(cmd)\n# ok: os-system\nsubprocess.run(cmd)\n",
+        )
+        .unwrap();
+
+        // Ignored and excluded fixtures are not run
+        std::fs::create_dir_all(dir.path().join("drafts")).unwrap();
+        std::fs::write(dir.path().join("drafts/os-system.yaml"), RULE).unwrap();
+        std::fs::write(dir.path().join("drafts/os-system.py"), "os.system(cmd)\n").unwrap();
+        std::fs::write(dir.path().join("os-system.old.yaml"), RULE).unwrap();
+        std::fs::write(dir.path().join("os-system.old.py"), "os.system(cmd)\n").unwrap();
+        std::fs::write(dir.path().join(".astgrepignore"), "drafts/\n").unwrap();
+
+        let mut pairs = Vec::new();
+        collect_rule_tests(dir.path(), &["*.old.*".to_string()], true, &mut pairs).unwrap();
+        assert_eq!(pairs, vec![(dir.path().join("os-system.yaml"), dir.path().join("os-system.py"))]);
+        let result = test_fixture(&pairs[0].0, &pairs[0].1);
+        assert!(result.is_success(), "{:?}", result);
+        assert_eq!(result.passed, 1);
+
+        std::fs::write(
+            dir.path().join("os-system.py"),
+            "import os\n# ruleid: os-system\nprint(cmd)\nos.system(cmd)\n# ruleid: shell-true\nrun(cmd)\n",
+        )
+        .unwrap();
+        let result = test_fixture(&pairs[0].0, &pairs[0].1);
+        assert!(!result.is_success());
+        assert_eq!(result.missed, vec![("os-system".to_string(), 3), ("shell-true".to_string(), 6)]);
+        assert_eq!(result.unexpected, vec![("os-system".to_string(), 4)]);
+        assert_eq!(result.unknown_rules, vec!["shell-true".to_string()]);
+        assert!(format_results(&[result]).contains("line 4: os-system matched unexpectedly"));
+    }
+}
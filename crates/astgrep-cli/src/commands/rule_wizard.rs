@@ -0,0 +1,338 @@
+//! Rule scaffolding wizard
+//!
+//! `rules new` asks for a rule id, a language and an example of the code the rule should
+//! flag, plus optionally code it should not, and proposes a pattern from the example: the
+//! first call in it, with every argument replaced by a metavariable. It writes `<id>.yaml`
+//! with metadata stubs and a `<id>.<ext>` fixture annotated with `ruleid:` and `ok:` for
+//! the `test` command, then runs that test, so a pattern that misses its own example shows
+//! up before the rule is used.
+
+use anyhow::{anyhow, Result};
+use astgrep_core::Language;
+use serde_json::json;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use crate::commands::rule_tests::test_fixture;
+
+/// Line that ends a pasted code example
+const END_OF_EXAMPLE: &str = ".";
+
+/// Metavariables given to the arguments of a proposed pattern, in order
+const METAVARIABLES: &[&str] = &["$X", "$Y", "$Z", "$W"];
+
+/// Everything the wizard collected for a new rule
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleDraft {
+    pub id: String,
+    pub language: Language,
+    pub message: String,
+    pub severity: String,
+    pub pattern: String,
+    /// Code the rule must flag
+    pub bad_example: String,
+    /// Code the rule must not flag, possibly empty
+    pub good_example: String,
+}
+
+impl RuleDraft {
+    /// The rule file, with commented-out metadata stubs to fill in
+    pub fn rule_yaml(&self) -> Result<String> {
+        let scalar = |value: &str| -> Result<String> { Ok(serde_yaml::to_string(&json!(value))?.trim_end().to_string()) };
+        Ok(format!(
+            "rules:\n  - id: {}\n    message: {}\n    severity: {}\n    confidence: MEDIUM\n    languages: [{}]\n    pattern: {}\n    metadata:\n      category: security\n      # cwe: \"CWE-78: OS Command Injection\"\n      # owasp: \"A03:2021 - Injection\"\n      # references: https://example.com/why-this-is-a-problem\n",
+            scalar(&self.id)?,
+            scalar(&self.message)?,
+            self.severity,
+            self.language.as_str(),
+            scalar(&self.pattern)?,
+        ))
+    }
+
+    /// The fixture: the bad example with `ruleid:` before the line the pattern came from,
+    /// then the good example under `ok:`
+    pub fn fixture(&self) -> String {
+        let mut fixture = format!("{}\n", comment(self.language, &format!("Examples for {}; run 'astgrep test' to check the rule", self.id)));
+        let seed = seed_line(&self.bad_example, self.language);
+        for (index, line) in self.bad_example.lines().enumerate() {
+            if Some(index) == seed {
+                let indent = &line[..line.len() - line.trim_start().len()];
+                fixture.push_str(&format!("{}{}\n", indent, comment(self.language, &format!("ruleid: {}", self.id))));
+            }
+            fixture.push_str(line);
+            fixture.push('\n');
+        }
+        if let Some(first) = self.good_example.lines().position(|line| !line.trim().is_empty()) {
+            fixture.push('\n');
+            for (index, line) in self.good_example.lines().enumerate() {
+                if index == first {
+                    let indent = &line[..line.len() - line.trim_start().len()];
+                    fixture.push_str(&format!("{}{}\n", indent, comment(self.language, &format!("ok: {}", self.id))));
+                }
+                fixture.push_str(line);
+                fixture.push('\n');
+            }
+        }
+        fixture
+    }
+}
+
+/// `text` as a line comment of `language`
+fn comment(language: Language, text: &str) -> String {
+    match language {
+        Language::Python | Language::Ruby | Language::Bash => format!("# {}", text),
+        Language::Sql => format!("-- {}", text),
+        Language::Xml => format!("<!-- {} -->", text),
+        _ => format!("// {}", text),
+    }
+}
+
+/// Index of the example line a pattern is proposed from: the first line with a call that
+/// does not open a block, else the first line of code
+fn seed_line(example: &str, language: Language) -> Option<usize> {
+    let marker = comment(language, "");
+    let marker = marker.trim().trim_end_matches("-->").trim();
+    let code: Vec<(usize, &str)> = example
+        .lines()
+        .map(str::trim)
+        .enumerate()
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with(marker))
+        .collect();
+    code.iter()
+        .find(|(_, line)| line.contains('(') && !line.ends_with('{') && !line.ends_with(':'))
+        .or_else(|| code.first())
+        .map(|(index, _)| *index)
+}
+
+/// A pattern for the code of `example`: its first call with the arguments replaced by
+/// metavariables, e.g. `os.system("ls " + path)` becomes `os.system($X)`
+pub fn propose_pattern(example: &str, language: Language) -> Option<String> {
+    let line = example.lines().nth(seed_line(example, language)?)?.trim().trim_end_matches(';').trim_end();
+    // The value of an assignment is the interesting part
+    let line = match top_level_assignment(line) {
+        Some(index) if line[index + 1..].contains('(') => line[index + 1..].trim(),
+        _ => line,
+    };
+    let Some(body) = line.strip_suffix(')') else {
+        return Some(line.to_string());
+    };
+    let open = matching_open(body)?;
+    let callee = &body[..open];
+    let arguments = split_arguments(&body[open + 1..]);
+    let metavariables: Vec<String> = (0..arguments.len())
+        .map(|index| METAVARIABLES.get(index).map_or_else(|| format!("$ARG{}", index + 1), |name| name.to_string()))
+        .collect();
+    Some(format!("{}({})", callee, metavariables.join(", ")))
+}
+
+/// Byte index of the `=` of an assignment outside brackets and strings
+fn top_level_assignment(line: &str) -> Option<usize> {
+    let bytes = line.as_bytes();
+    let mut depth = 0i32;
+    let mut quote = None;
+    for (index, &byte) in bytes.iter().enumerate() {
+        match (quote, byte) {
+            (Some(q), _) if byte == q => quote = None,
+            (Some(_), _) => {}
+            (None, b'"' | b'\'' | b'`') => quote = Some(byte),
+            (None, b'(' | b'[' | b'{') => depth += 1,
+            (None, b')' | b']' | b'}') => depth -= 1,
+            (None, b'=') if depth == 0 => {
+                let before = index.checked_sub(1).map(|i| bytes[i]);
+                let after = bytes.get(index + 1).copied();
+                if after != Some(b'=') && !matches!(before, Some(b'=' | b'!' | b'<' | b'>')) {
+                    return Some(index);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Byte index of the `(` matching the `)` that was stripped from the end of `body`
+fn matching_open(body: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (index, c) in body.char_indices().rev() {
+        match c {
+            ')' => depth += 1,
+            '(' if depth == 0 => return Some(index),
+            '(' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The comma-separated arguments of a call, ignoring commas nested in brackets or strings
+fn split_arguments(arguments: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut depth, mut quote, mut start) = (0i32, None, 0);
+    for (index, c) in arguments.char_indices() {
+        match (quote, c) {
+            (Some(q), _) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'' | '`') => quote = Some(c),
+            (None, '(' | '[' | '{') => depth += 1,
+            (None, ')' | ']' | '}') => depth -= 1,
+            (None, ',') if depth == 0 => {
+                parts.push(arguments[start..index].trim());
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(arguments[start..].trim());
+    parts.retain(|part| !part.is_empty());
+    parts
+}
+
+/// Ask the questions of the wizard on `input`, prompting on stderr
+pub fn interview(input: &mut impl BufRead, id: Option<String>, language: Option<String>) -> Result<RuleDraft> {
+    let id = match id {
+        Some(id) => id,
+        None => ask(input, "Rule id (e.g. python-os-system)", None)?,
+    };
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c)) {
+        return Err(anyhow!("invalid rule id '{}': use letters, digits, '-', '_' and '.'", id));
+    }
+    let language = match language {
+        Some(language) => language,
+        None => {
+            let names: Vec<&str> = crate::DEFAULT_LANGUAGES.iter().map(Language::as_str).collect();
+            ask(input, &format!("Language ({})", names.join(", ")), None)?
+        }
+    };
+    let language = Language::from_str(&language).ok_or_else(|| anyhow!("unknown language '{}'", language))?;
+
+    let bad_example = read_example(input, "Paste code the rule should flag")?;
+    if bad_example.trim().is_empty() {
+        return Err(anyhow!("the rule needs an example of the code it should flag"));
+    }
+    let good_example = read_example(input, "Paste code the rule should not flag (optional)")?;
+
+    let proposed = propose_pattern(&bad_example, language).unwrap_or_default();
+    let pattern = ask(input, "Pattern", Some(&proposed))?;
+    let message = ask(input, "Message", Some(&format!("Avoid {}", pattern)))?;
+    let severity = ask(input, "Severity (ERROR, WARNING, INFO)", Some("WARNING"))?.to_uppercase();
+    if !["ERROR", "WARNING", "INFO"].contains(&severity.as_str()) {
+        return Err(anyhow!("unknown severity '{}'", severity));
+    }
+    Ok(RuleDraft { id, language, message, severity, pattern, bad_example, good_example })
+}
+
+/// One answer; an empty answer takes `default`
+fn ask(input: &mut impl BufRead, question: &str, default: Option<&str>) -> Result<String> {
+    match default {
+        Some(default) => eprint!("{} [{}]: ", question, default),
+        None => eprint!("{}: ", question),
+    }
+    std::io::stderr().flush()?;
+    let mut line = String::new();
+    if input.read_line(&mut line)? == 0 && default.is_none() {
+        return Err(anyhow!("input ended before '{}' was answered", question));
+    }
+    let answer = line.trim();
+    Ok(if answer.is_empty() { default.unwrap_or_default().to_string() } else { answer.to_string() })
+}
+
+/// Lines up to one holding only `.`, or the end of the input
+fn read_example(input: &mut impl BufRead, question: &str) -> Result<String> {
+    eprintln!("{}, then a line with only '{}':", question, END_OF_EXAMPLE);
+    let mut example = String::new();
+    for line in input.lines() {
+        let line = line?;
+        if line.trim() == END_OF_EXAMPLE {
+            break;
+        }
+        example.push_str(&line);
+        example.push('\n');
+    }
+    Ok(example)
+}
+
+/// Run the wizard and write the rule and its fixture to `dir`
+pub async fn run(id: Option<String>, language: Option<String>, dir: PathBuf, force: bool) -> Result<()> {
+    let draft = interview(&mut std::io::stdin().lock(), id, language)?;
+    let (rule_path, fixture_path) = write(&draft, &dir, force)?;
+    println!("✅ Created {} and {}", rule_path.display(), fixture_path.display());
+
+    let result = test_fixture(&rule_path, &fixture_path);
+    if result.is_success() {
+        println!("🧪 The pattern matches the example it should flag and nothing else");
+    } else {
+        if let Some(ref error) = result.error {
+            println!("⚠️  The fixture could not be tested: {}", error);
+        }
+        for (_, line) in &result.missed {
+            println!("⚠️  The pattern does not match line {} of {}; edit it in {}", line, fixture_path.display(), rule_path.display());
+        }
+        for (_, line) in &result.unexpected {
+            println!("⚠️  The pattern also matches line {} of {}", line, fixture_path.display());
+        }
+    }
+    println!("📝 Fill in the metadata stubs, then run: astgrep test {}", rule_path.display());
+    Ok(())
+}
+
+/// Write `<id>.yaml` and its fixture to `dir`; existing files are only replaced with `force`
+fn write(draft: &RuleDraft, dir: &Path, force: bool) -> Result<(PathBuf, PathBuf)> {
+    let extension = draft.language.extensions()[0].trim_start_matches('.');
+    let rule_path = dir.join(format!("{}.yaml", draft.id));
+    let fixture_path = dir.join(format!("{}.{}", draft.id, extension));
+    for path in [&rule_path, &fixture_path] {
+        if path.exists() && !force {
+            return Err(anyhow!("{} already exists; pass --force to replace it", path.display()));
+        }
+    }
+    std::fs::create_dir_all(dir)?;
+    std::fs::write(&rule_path, draft.rule_yaml()?)?;
+    std::fs::write(&fixture_path, draft.fixture())?;
+    Ok((rule_path, fixture_path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::rule_tests::parse_annotations;
+    use astgrep_rules::RuleRepository;
+
+    #[test]
+    fn test_propose_pattern() {
+        let java = "class A {\n    void run(String s) throws Exception {\n        // runs it\n        Runtime.getRuntime().exec(\"sh -c \" + s, env(a, b));\n    }\n}\n";
+        assert_eq!(propose_pattern(java, Language::Java).as_deref(), Some("Runtime.getRuntime().exec($X, $Y)"));
+        assert_eq!(propose_pattern("result = eval(user_input)\n", Language::Python).as_deref(), Some("eval($X)"));
+        assert_eq!(propose_pattern("if (a == b) x = 1;\n", Language::JavaScript).as_deref(), Some("if (a == b) x = 1"));
+        assert_eq!(propose_pattern("f(a, b, c, d, e)", Language::C).as_deref(), Some("f($X, $Y, $Z, $W, $ARG5)"));
+        assert_eq!(propose_pattern("debug()", Language::Ruby).as_deref(), Some("debug()"));
+        assert_eq!(propose_pattern("\n# only a comment\n", Language::Python), None);
+    }
+
+    #[test]
+    fn test_interview_writes_rule_and_annotated_fixture() {
+        let answers = "py-os-system\npython\nimport os\n\ndef run(cmd):\n    os.system(\"ls \" + cmd)\n.\nsubprocess.run([\"ls\", cmd])\n.\n\nRunning a shell command built from input\nerror\n";
+        let draft = interview(&mut std::io::Cursor::new(answers), None, None).unwrap();
+        assert_eq!(draft.pattern, "os.system($X)");
+        assert_eq!(draft.severity, "ERROR");
+
+        let mut repository = RuleRepository::new();
+        assert_eq!(repository.load_yaml("py-os-system.yaml", &draft.rule_yaml().unwrap()).unwrap(), 1);
+        let rule = repository.rule("py-os-system").unwrap();
+        assert_eq!(rule.description, "Running a shell command built from input");
+        assert_eq!(rule.metadata.get("category").map(String::as_str), Some("security"));
+
+        let fixture = draft.fixture();
+        assert!(fixture.contains("    # ruleid: py-os-system\n    os.system(\"ls \" + cmd)\n"));
+        let expectations = parse_annotations(&fixture);
+        assert!(expectations.matches.contains(&("py-os-system".to_string(), 6)));
+        assert!(expectations.ok.contains(&("py-os-system".to_string(), 9)));
+
+        let dir = tempfile::tempdir().unwrap();
+        let (rule_path, fixture_path) = write(&draft, dir.path(), false).unwrap();
+        assert_eq!(fixture_path.file_name().unwrap(), "py-os-system.py");
+        assert!(rule_path.exists());
+        assert!(write(&draft, dir.path(), false).unwrap_err().to_string().contains("--force"));
+
+        let error = interview(&mut std::io::Cursor::new("bad id!\n"), None, None).unwrap_err();
+        assert!(error.to_string().contains("invalid rule id"));
+    }
+}
@@ -0,0 +1,426 @@
+//! Rules command for managing rule packs, probing rule robustness and estimating the
+//! reach of new rules
+
+use anyhow::Result;
+use astgrep_core::Language;
+use astgrep_dataflow::ProjectIndex;
+use astgrep_parser::LanguageParserRegistry;
+use astgrep_rules::{Mutation, Rule, RuleContext, RuleEngine, RulePack, RuleRepository, PACK_EXTENSION};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tracing::{debug, info, warn};
+use crate::commands::analyze_enhanced::{determine_language, is_excluded, load_rules_into_engine_from_paths};
+use crate::commands::ignore_file::{walk_files, IgnoreStack};
+use crate::OutputFormatCli;
+
+/// Build a `.astgrep-pack` archive from a rule pack source directory
+pub async fn pack(dir: PathBuf, output: Option<PathBuf>) -> Result<()> {
+    if !dir.is_dir() {
+        return Err(anyhow::anyhow!("Rule pack source is not a directory: {}", dir.display()));
+    }
+
+    let pack = RulePack::from_directory(&dir, None)?;
+    let output = output.unwrap_or_else(|| {
+        PathBuf::from(format!("{}-{}.{}", pack.manifest.name, pack.manifest.version, PACK_EXTENSION))
+    });
+
+    info!("Writing rule pack to {}", output.display());
+    pack.write_to(&output)?;
+
+    println!("📦 Rule pack created: {}", output.display());
+    println!("  • Name: {} {}", pack.manifest.name, pack.manifest.version);
+    if let Some(ref min) = pack.manifest.min_engine_version {
+        println!("  • Minimum engine version: {}", min);
+    }
+    if !pack.manifest.categories.is_empty() {
+        println!("  • Categories: {}", pack.manifest.categories.join(", "));
+    }
+    println!("  • Rule files: {}", pack.rules.len());
+    println!("  • Fixtures: {}", pack.fixtures.len());
+
+    Ok(())
+}
+
+/// Match counts of one rule on a fixture before and after a mutation
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct MutationOutcome {
+    pub fixture: String,
+    pub rule_id: String,
+    pub mutation: String,
+    pub baseline_matches: usize,
+    pub mutated_matches: usize,
+}
+
+impl MutationOutcome {
+    /// The rule lost matches under a benign transformation
+    pub fn is_brittle(&self) -> bool {
+        self.mutated_matches < self.baseline_matches
+    }
+}
+
+/// Re-run rules on benignly mutated fixtures and report mutations that make them stop matching
+pub async fn mutate_test(rules: Vec<PathBuf>, fixtures: Vec<PathBuf>, fail_on_brittle: bool, exclude: Vec<String>, git_ignore: bool) -> Result<()> {
+    let mut engine = RuleEngine::new();
+    let loaded = load_rules_into_engine_from_paths(&rules, &mut engine)?;
+    if loaded == 0 {
+        return Err(anyhow::anyhow!("No rules loaded"));
+    }
+
+    let fixture_files = if fixtures.is_empty() {
+        sibling_fixtures(&rules, &exclude, git_ignore)?
+    } else {
+        let mut files = Vec::new();
+        for path in fixtures {
+            collect_fixture_files(&path, &exclude, git_ignore, &mut files)?;
+        }
+        files
+    };
+    if fixture_files.is_empty() {
+        return Err(anyhow::anyhow!("No fixtures found; pass fixture paths or place them next to the rule files"));
+    }
+
+    println!("🧬 Mutation testing {} rule(s) against {} fixture(s)", loaded, fixture_files.len());
+
+    let mut probes = 0usize;
+    let mut brittle = 0usize;
+    for fixture in &fixture_files {
+        let outcomes = match probe_fixture(&mut engine, fixture) {
+            Ok(outcomes) => outcomes,
+            Err(e) => {
+                warn!("Skipping fixture {}: {}", fixture.display(), e);
+                continue;
+            }
+        };
+
+        println!("\n  {}", fixture.display());
+        if outcomes.is_empty() {
+            println!("    ⚠️  No rule matches the unmutated fixture");
+            continue;
+        }
+        probes += outcomes.len();
+        let fixture_brittle: Vec<&MutationOutcome> = outcomes.iter().filter(|o| o.is_brittle()).collect();
+        if fixture_brittle.is_empty() {
+            println!("    ✅ All mutations preserved matches");
+        }
+        for outcome in fixture_brittle {
+            println!(
+                "    ❌ {} stops matching under {} ({} → {})",
+                outcome.rule_id, outcome.mutation, outcome.baseline_matches, outcome.mutated_matches
+            );
+            brittle += 1;
+        }
+    }
+
+    println!("\n{} of {} probe(s) exposed brittle patterns", brittle, probes);
+    if fail_on_brittle && brittle > 0 {
+        return Err(anyhow::anyhow!("{} rule/mutation pair(s) lost matches", brittle));
+    }
+    Ok(())
+}
+
+/// Run every applicable mutation over `fixture`, comparing per-rule match counts with
+/// the unmutated baseline. Only rules matching the baseline are probed.
+pub fn probe_fixture(engine: &mut RuleEngine, fixture: &Path) -> Result<Vec<MutationOutcome>> {
+    let language = determine_language(fixture)?;
+    let source = std::fs::read_to_string(fixture)?;
+    let baseline = count_matches(engine, fixture, &source, language)?;
+
+    let mut outcomes = Vec::new();
+    if baseline.is_empty() {
+        return Ok(outcomes);
+    }
+    for mutation in Mutation::ALL {
+        let mutated = match mutation.apply(&source, language) {
+            Some(mutated) => mutated,
+            None => continue,
+        };
+        let counts = match count_matches(engine, fixture, &mutated, language) {
+            Ok(counts) => counts,
+            Err(e) => {
+                warn!("Mutation {} of {} failed to analyze: {}", mutation.name(), fixture.display(), e);
+                continue;
+            }
+        };
+        for (rule_id, &baseline_matches) in &baseline {
+            outcomes.push(MutationOutcome {
+                fixture: fixture.display().to_string(),
+                rule_id: rule_id.clone(),
+                mutation: mutation.name().to_string(),
+                baseline_matches,
+                mutated_matches: counts.get(rule_id).copied().unwrap_or(0),
+            });
+        }
+    }
+    Ok(outcomes)
+}
+
+pub(crate) fn count_matches(engine: &mut RuleEngine, file: &Path, source: &str, language: Language) -> Result<BTreeMap<String, usize>> {
+    let mut counts = BTreeMap::new();
+    for finding in analyze_source(engine, file, source, language)? {
+        *counts.entry(finding.rule_id).or_insert(0) += 1;
+    }
+    Ok(counts)
+}
+
+/// Run the engine's rules on `source` as if it were the content of `file`
+pub(crate) fn analyze_source(engine: &mut RuleEngine, file: &Path, source: &str, language: Language) -> Result<Vec<astgrep_core::Finding>> {
+    let registry = LanguageParserRegistry::new();
+    let parser = registry
+        .get_parser(language)
+        .ok_or_else(|| anyhow::anyhow!("No parser registered for {:?}", language))?;
+    let ast = parser.parse(source, file)?;
+    let context = RuleContext::new(file.to_string_lossy().to_string(), language, source.to_string());
+    Ok(engine.analyze(ast.as_ref(), &context)?)
+}
+
+/// Estimated reach of one rule over the indexed project
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize)]
+pub struct RuleImpact {
+    pub rule_id: String,
+    /// Indexed files in one of the rule's languages
+    pub candidate_files: usize,
+    /// Literal anchors of the rule; None when it has to run on every candidate file
+    pub anchors: Option<Vec<String>>,
+    /// Candidate files passing the anchor prefilter
+    pub anchored_files: usize,
+    /// Lines of those files containing an anchor, an upper bound of the rule's findings
+    pub anchored_lines: usize,
+    /// Indexed call sites whose callee contains an anchor
+    pub call_sites: usize,
+}
+
+/// Estimate how many files and locations the rules would hit, without running them:
+/// the project index supplies the files and call sites, the anchor prefilter the hits
+pub async fn impact(rules: Vec<PathBuf>, root: PathBuf, format: OutputFormatCli) -> Result<()> {
+    if !root.is_dir() {
+        return Err(anyhow::anyhow!("Project root is not a directory: {}", root.display()));
+    }
+    let repository = RuleRepository::from_paths(&rules);
+    let rules: Vec<Rule> = repository.rules().cloned().collect();
+    if rules.is_empty() {
+        return Err(anyhow::anyhow!("No rules loaded"));
+    }
+
+    crate::commands::index::refresh_project_index(&root, false, Vec::new(), true).await?;
+    let index = ProjectIndex::load(&root)?;
+    let impacts = estimate_impact(&rules, &root, &index);
+
+    let output = match format {
+        OutputFormatCli::Json => serde_json::to_string_pretty(&impacts)?,
+        _ => format_impact(&impacts, index.file_count()),
+    };
+    println!("{}", output);
+    Ok(())
+}
+
+/// Impact of each rule on the files of `index`, read from under `root`
+pub fn estimate_impact(rules: &[Rule], root: &Path, index: &ProjectIndex) -> Vec<RuleImpact> {
+    let mut impacts: Vec<RuleImpact> = rules
+        .iter()
+        .map(|rule| RuleImpact { rule_id: rule.id.clone(), anchors: rule.anchors(), ..RuleImpact::default() })
+        .collect();
+
+    for (file, entry) in index.files() {
+        let applicable: Vec<usize> = (0..rules.len()).filter(|&i| rules[i].applies_to(entry.language)).collect();
+        if applicable.is_empty() {
+            continue;
+        }
+        let source = match std::fs::read_to_string(root.join(file)) {
+            Ok(source) => source.to_lowercase(),
+            Err(e) => {
+                debug!("Skipping indexed file {}: {}", file, e);
+                continue;
+            }
+        };
+
+        for i in applicable {
+            let impact = &mut impacts[i];
+            impact.candidate_files += 1;
+            let anchors = match &impact.anchors {
+                Some(anchors) => anchors,
+                None => {
+                    impact.anchored_files += 1;
+                    continue;
+                }
+            };
+            let hit = |text: &str| anchors.iter().any(|anchor| text.contains(anchor.as_str()));
+            let lines = source.lines().filter(|line| hit(line)).count();
+            if lines > 0 {
+                impact.anchored_files += 1;
+                impact.anchored_lines += lines;
+                impact.call_sites += entry.calls.iter().filter(|call| hit(&call.callee.to_lowercase())).count();
+            }
+        }
+    }
+    impacts
+}
+
+fn format_impact(impacts: &[RuleImpact], indexed_files: usize) -> String {
+    let mut output = format!("🎯 Estimated impact of {} rule(s) on {} indexed file(s)\n", impacts.len(), indexed_files);
+    for impact in impacts {
+        output.push_str(&format!("\n  {}\n", impact.rule_id));
+        match &impact.anchors {
+            Some(anchors) => {
+                output.push_str(&format!("    • Anchors: {}\n", anchors.join(", ")));
+                output.push_str(&format!(
+                    "    • Files: {} of {} candidate(s)\n",
+                    impact.anchored_files, impact.candidate_files
+                ));
+                output.push_str(&format!(
+                    "    • Likely locations: up to {} line(s), {} call site(s)\n",
+                    impact.anchored_lines, impact.call_sites
+                ));
+            }
+            None => output.push_str(&format!(
+                "    • No literal anchor: runs on all {} candidate file(s)\n",
+                impact.candidate_files
+            )),
+        }
+    }
+    output
+}
+
+/// Fixtures sharing a rule file's stem in the same directory (`sqli.yaml` + `sqli.java`)
+fn sibling_fixtures(rules: &[PathBuf], exclude: &[String], git_ignore: bool) -> Result<Vec<PathBuf>> {
+    let mut fixtures = Vec::new();
+    for path in rules {
+        // The files of a rules directory, or those next to a rule file
+        let (rule_files, candidates) = if path.is_dir() {
+            let files = walk_files(path, git_ignore, exclude)?;
+            (files.clone(), files)
+        } else {
+            let dir = path.parent().filter(|d| !d.as_os_str().is_empty()).unwrap_or(Path::new("."));
+            let ignores = IgnoreStack::above(dir, git_ignore).enter(dir);
+            let mut candidates = Vec::new();
+            for entry in std::fs::read_dir(dir)? {
+                let candidate = entry?.path();
+                if candidate.is_file() && !ignores.is_ignored(&candidate, false) && !is_excluded(&candidate, exclude) {
+                    candidates.push(candidate);
+                }
+            }
+            (vec![path.clone()], candidates)
+        };
+
+        for rule_file in rule_files.iter().filter(|f| is_yaml(f)) {
+            let siblings = candidates
+                .iter()
+                .filter(|f| !is_yaml(f) && f.parent() == rule_file.parent() && f.file_stem() == rule_file.file_stem());
+            for fixture in siblings {
+                if !fixtures.contains(fixture) {
+                    fixtures.push(fixture.clone());
+                }
+            }
+        }
+    }
+    fixtures.sort();
+    Ok(fixtures)
+}
+
+fn collect_fixture_files(path: &Path, exclude: &[String], git_ignore: bool, files: &mut Vec<PathBuf>) -> Result<()> {
+    if path.is_file() {
+        files.push(path.to_path_buf());
+    } else if path.is_dir() {
+        files.extend(walk_files(path, git_ignore, exclude)?);
+    }
+    Ok(())
+}
+
+fn is_yaml(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "yaml" || ext == "yml")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_probe_fixture_flags_alias_sensitive_rule() {
+        let dir = tempfile::tempdir().unwrap();
+        let rule = dir.path().join("os-system.yaml");
+        std::fs::write(&rule, r#"
+rules:
+  - id: os-system
+    message: Command execution via os.system
+    severity: ERROR
+    languages: [python]
+    pattern: "os.system(...)"
+    options:
+      import_aliasing: false
+"#).unwrap();
+        std::fs::write(dir.path().join("os-system.py"), "import os\nos.system(cmd)\n").unwrap();
+
+        let fixtures = sibling_fixtures(std::slice::from_ref(&rule), &[], true).unwrap();
+        assert_eq!(fixtures, vec![dir.path().join("os-system.py")]);
+
+        // Fixtures that ignore files or exclude patterns leave out are not probed
+        std::fs::write(dir.path().join("os-system.js"), "os.system(cmd)\n").unwrap();
+        std::fs::write(dir.path().join(".astgrepignore"), "*.js\n").unwrap();
+        assert_eq!(sibling_fixtures(&[dir.path().to_path_buf()], &[], true).unwrap(), fixtures);
+        assert!(sibling_fixtures(std::slice::from_ref(&rule), &["*.py".to_string()], true).unwrap().is_empty());
+
+        let mut engine = RuleEngine::new();
+        load_rules_into_engine_from_paths(&[rule], &mut engine).unwrap();
+        let outcomes = probe_fixture(&mut engine, &fixtures[0]).unwrap();
+        let alias = outcomes.iter().find(|o| o.mutation == "alias-imports").unwrap();
+        assert_eq!(alias.baseline_matches, 1);
+        assert!(alias.is_brittle());
+        assert!(outcomes.iter().filter(|o| o.mutation == "add-comments").all(|o| !o.is_brittle()));
+    }
+
+    #[test]
+    fn test_probe_fixture_accepts_aliased_imports_by_default() {
+        let dir = tempfile::tempdir().unwrap();
+        let rule = dir.path().join("os-system.yaml");
+        std::fs::write(&rule, r#"
+rules:
+  - id: os-system
+    message: Command execution via os.system
+    severity: ERROR
+    languages: [python]
+    pattern: "os.system(...)"
+"#).unwrap();
+        std::fs::write(dir.path().join("os-system.py"), "import os\nos.system(cmd)\n").unwrap();
+
+        let mut engine = RuleEngine::new();
+        load_rules_into_engine_from_paths(&[rule], &mut engine).unwrap();
+        let outcomes = probe_fixture(&mut engine, &dir.path().join("os-system.py")).unwrap();
+        let alias = outcomes.iter().find(|o| o.mutation == "alias-imports").unwrap();
+        assert_eq!(alias.baseline_matches, 1);
+        assert!(!alias.is_brittle());
+    }
+
+    #[tokio::test]
+    async fn test_estimate_impact_from_index() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("app.py"), "import os\nos.system(cmd)\nOS.SYSTEM(other)\n").unwrap();
+        std::fs::write(dir.path().join("util.py"), "def helper():\n    return 1\n").unwrap();
+        std::fs::write(dir.path().join("Main.java"), "class Main {}\n").unwrap();
+        crate::commands::index::refresh_project_index(dir.path(), false, Vec::new(), true).await.unwrap();
+        let index = ProjectIndex::load(dir.path()).unwrap();
+
+        let rule_file = dir.path().join("rules.yaml");
+        std::fs::write(&rule_file, r#"
+rules:
+  - id: os-system
+    message: Command execution via os.system
+    severity: ERROR
+    languages: [python]
+    pattern: "os.system(...)"
+  - id: any-call
+    message: Any call
+    severity: INFO
+    languages: [python]
+    pattern: "$F(...)"
+"#).unwrap();
+        let rules: Vec<Rule> = RuleRepository::from_paths(&[rule_file]).rules().cloned().collect();
+        let impacts = estimate_impact(&rules, dir.path(), &index);
+
+        let system = impacts.iter().find(|i| i.rule_id == "os-system").unwrap();
+        assert_eq!(system.candidate_files, 2);
+        assert_eq!(system.anchored_files, 1);
+        assert_eq!(system.anchored_lines, 2);
+        let any = impacts.iter().find(|i| i.rule_id == "any-call").unwrap();
+        assert_eq!((any.anchors.clone(), any.anchored_files), (None, 2));
+        assert!(format_impact(&impacts, index.file_count()).contains("runs on all 2 candidate file(s)"));
+    }
+}
@@ -0,0 +1,124 @@
+//! Scan profiles
+//!
+//! A scan profile is a named preset trading coverage for speed. `fast-ci` skips
+//! dataflow, only analyzes files containing a literal anchor of some rule and stops after
+//! two minutes; `deep-audit` runs interprocedural taint analysis without a time budget.
+//! Projects can define their own profiles, or redefine these, under `[profiles.<name>]`
+//! in `astgrep.toml`. The profile of a run is reported with its results so consumers know
+//! which guarantees a report carries.
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Duration;
+
+pub const FAST_CI: &str = "fast-ci";
+pub const DEEP_AUDIT: &str = "deep-audit";
+
+/// Analysis settings of a named profile
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScanProfile {
+    #[serde(default, skip_deserializing)]
+    pub name: String,
+    /// Run taint/dataflow analysis, including cross-file analysis through the project index
+    #[serde(default)]
+    pub dataflow: bool,
+    /// Skip files that contain none of the literal anchors of the rules of their language
+    #[serde(default)]
+    pub anchor_prefilter: bool,
+    /// Wall-clock budget in seconds after which the run stops and reports partial results
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_budget_secs: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct ProfilesFile {
+    #[serde(default)]
+    profiles: BTreeMap<String, ScanProfile>,
+}
+
+impl ScanProfile {
+    pub fn fast_ci() -> Self {
+        Self { name: FAST_CI.to_string(), dataflow: false, anchor_prefilter: true, time_budget_secs: Some(120) }
+    }
+
+    pub fn deep_audit() -> Self {
+        Self { name: DEEP_AUDIT.to_string(), dataflow: true, anchor_prefilter: false, time_budget_secs: None }
+    }
+
+    pub fn builtin(name: &str) -> Option<Self> {
+        match name {
+            FAST_CI => Some(Self::fast_ci()),
+            DEEP_AUDIT => Some(Self::deep_audit()),
+            _ => None,
+        }
+    }
+
+    /// Profile `name` from the `[profiles]` of `config_file` when it defines it, otherwise
+    /// the built-in profile of that name
+    pub fn resolve(name: &str, config_file: &Path) -> Result<Self> {
+        let mut profiles = match std::fs::read_to_string(config_file) {
+            Ok(text) => toml::from_str::<ProfilesFile>(&text)
+                .map_err(|e| anyhow!("Invalid profiles in {}: {}", config_file.display(), e))?
+                .profiles,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        if let Some(mut profile) = profiles.remove(name) {
+            profile.name = name.to_string();
+            return Ok(profile);
+        }
+        Self::builtin(name).ok_or_else(|| {
+            let mut known: Vec<String> = vec![FAST_CI.to_string(), DEEP_AUDIT.to_string()];
+            known.extend(profiles.into_keys());
+            anyhow!("Unknown scan profile '{}' (available: {})", name, known.join(", "))
+        })
+    }
+
+    pub fn time_budget(&self) -> Option<Duration> {
+        self.time_budget_secs.map(Duration::from_secs)
+    }
+
+    /// Short human-readable summary of the profile's guarantees
+    pub fn describe(&self) -> String {
+        let mut parts = vec![if self.dataflow { "dataflow" } else { "no dataflow" }.to_string()];
+        if self.anchor_prefilter {
+            parts.push("anchor prefilter".to_string());
+        }
+        parts.push(match self.time_budget_secs {
+            Some(secs) => format!("{}s budget", secs),
+            None => "no budget".to_string(),
+        });
+        format!("{} ({})", self.name, parts.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_profiles() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = dir.path().join("astgrep.toml");
+
+        assert_eq!(ScanProfile::resolve("fast-ci", &config).unwrap(), ScanProfile::fast_ci());
+        assert!(ScanProfile::resolve("nightly", &config).is_err());
+
+        std::fs::write(
+            &config,
+            "[analysis]\nlanguages = [\"java\"]\n\n[profiles.nightly]\ndataflow = true\ntime_budget_secs = 1800\n\n[profiles.fast-ci]\nanchor_prefilter = true\n",
+        )
+        .unwrap();
+        let nightly = ScanProfile::resolve("nightly", &config).unwrap();
+        assert_eq!(nightly.name, "nightly");
+        assert!(nightly.dataflow && !nightly.anchor_prefilter);
+        assert_eq!(nightly.describe(), "nightly (dataflow, 1800s budget)");
+
+        let fast = ScanProfile::resolve("fast-ci", &config).unwrap();
+        assert_eq!(fast.time_budget_secs, None);
+        assert_eq!(ScanProfile::resolve("deep-audit", &config).unwrap(), ScanProfile::deep_audit());
+    }
+}
@@ -0,0 +1,391 @@
+//! Self-benchmark command for validating engine builds
+//!
+//! Runs the rule engine against small bundled corpora and rule packs, and emits
+//! timing/memory figures as JSON. A previously stored report can be passed as a
+//! reference; the command fails if any case got slower than the allowed tolerance
+//! or produced a different number of findings, or if the peak memory of the run grew
+//! beyond the tolerance. The peak is the process's high-water mark (`VmHWM` on Linux),
+//! so short spikes between samples count.
+
+use anyhow::Result;
+use astgrep_core::Language;
+use astgrep_parser::LanguageParserRegistry;
+use astgrep_rules::{RuleContext, RuleEngine};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use tracing::info;
+
+/// A bundled benchmark case: representative source plus the rule pack run against it
+struct BenchCase {
+    name: &'static str,
+    language: Language,
+    file_name: &'static str,
+    source: &'static str,
+    rules: &'static str,
+}
+
+const JAVA_SOURCE: &str = r#"
+import java.sql.*;
+
+public class UserDao {
+    public User find(Connection conn, String id) throws SQLException {
+        Statement stmt = conn.createStatement();
+        ResultSet rs = stmt.executeQuery("SELECT * FROM users WHERE id = " + id);
+        System.out.println("lookup " + id);
+        return map(rs);
+    }
+
+    public void log(String message) {
+        System.out.println(message);
+    }
+}
+"#;
+
+const JAVA_RULES: &str = r#"
+rules:
+  - id: bench-java-println
+    name: System.out.println usage
+    description: Avoid System.out.println in production code
+    message: Avoid System.out.println in production code
+    severity: INFO
+    languages: [java]
+    patterns:
+      - "System.out.println($MSG)"
+  - id: bench-java-execute-query
+    name: Raw executeQuery
+    description: Raw executeQuery call
+    message: Raw executeQuery call
+    severity: WARNING
+    languages: [java]
+    patterns:
+      - "$STMT.executeQuery($QUERY)"
+"#;
+
+const PYTHON_SOURCE: &str = r#"
+import os
+import subprocess
+
+def run(cmd):
+    os.system(cmd)
+    subprocess.call(cmd, shell=True)
+
+def evaluate(expr):
+    return eval(expr)
+
+def main():
+    run(input())
+    print(evaluate("1 + 1"))
+"#;
+
+const PYTHON_RULES: &str = r#"
+rules:
+  - id: bench-python-os-system
+    name: os.system usage
+    description: os.system executes a shell command
+    message: os.system executes a shell command
+    severity: ERROR
+    languages: [python]
+    patterns:
+      - "os.system($CMD)"
+  - id: bench-python-eval
+    name: eval usage
+    description: eval on dynamic input
+    message: eval on dynamic input
+    severity: WARNING
+    languages: [python]
+    patterns:
+      - "eval($X)"
+"#;
+
+const JAVASCRIPT_SOURCE: &str = r#"
+const express = require('express');
+const app = express();
+
+app.get('/user', function (req, res) {
+    document.write(req.query.name);
+    eval(req.query.code);
+    console.log('request handled');
+    res.send('ok');
+});
+"#;
+
+const JAVASCRIPT_RULES: &str = r#"
+rules:
+  - id: bench-js-eval
+    name: eval usage
+    description: eval on request data
+    message: eval on request data
+    severity: ERROR
+    languages: [javascript]
+    patterns:
+      - "eval($X)"
+  - id: bench-js-console-log
+    name: console.log usage
+    description: console.log left in code
+    message: console.log left in code
+    severity: INFO
+    languages: [javascript]
+    patterns:
+      - "console.log($X)"
+"#;
+
+const SQL_SOURCE: &str = r#"
+SELECT * FROM users WHERE id = 1;
+SELECT name, email FROM accounts WHERE active = 1;
+DELETE FROM sessions;
+UPDATE users SET role = 'admin';
+"#;
+
+const SQL_RULES: &str = r#"
+rules:
+  - id: bench-sql-select-star
+    name: SELECT star
+    description: Avoid SELECT *
+    message: Avoid SELECT *
+    severity: INFO
+    languages: [sql]
+    patterns:
+      - "SELECT * FROM $TABLE"
+"#;
+
+fn bench_cases() -> Vec<BenchCase> {
+    vec![
+        BenchCase { name: "java-basic", language: Language::Java, file_name: "UserDao.java", source: JAVA_SOURCE, rules: JAVA_RULES },
+        BenchCase { name: "python-basic", language: Language::Python, file_name: "app.py", source: PYTHON_SOURCE, rules: PYTHON_RULES },
+        BenchCase { name: "javascript-basic", language: Language::JavaScript, file_name: "server.js", source: JAVASCRIPT_SOURCE, rules: JAVASCRIPT_RULES },
+        BenchCase { name: "sql-basic", language: Language::Sql, file_name: "queries.sql", source: SQL_SOURCE, rules: SQL_RULES },
+    ]
+}
+
+/// Timing results for a single benchmark case
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchCaseResult {
+    pub name: String,
+    pub language: Language,
+    pub source_bytes: usize,
+    pub rules: usize,
+    pub findings: usize,
+    pub mean_ms: f64,
+    pub min_ms: f64,
+    pub max_ms: f64,
+}
+
+/// Complete self-benchmark report
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchReport {
+    pub engine_version: String,
+    pub iterations: usize,
+    pub cases: Vec<BenchCaseResult>,
+    pub peak_memory_bytes: u64,
+}
+
+/// A case that regressed against the reference report
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchRegression {
+    pub case: String,
+    pub reason: String,
+}
+
+/// Run the self-benchmark command
+pub async fn run(
+    iterations: usize,
+    reference: Option<PathBuf>,
+    tolerance: f64,
+    output: Option<PathBuf>,
+) -> Result<()> {
+    if iterations == 0 {
+        return Err(anyhow::anyhow!("--iterations must be at least 1"));
+    }
+    if tolerance < 0.0 {
+        return Err(anyhow::anyhow!("--tolerance must not be negative"));
+    }
+
+    info!("Running self-benchmark with {} iterations", iterations);
+    let report = run_benchmarks(iterations)?;
+    let json = serde_json::to_string_pretty(&report)?;
+
+    if let Some(ref output_path) = output {
+        std::fs::write(output_path, &json)?;
+        info!("Benchmark report written to: {}", output_path.display());
+    } else {
+        println!("{}", json);
+    }
+
+    if let Some(reference_path) = reference {
+        let reference_report = load_report(&reference_path)?;
+        let regressions = compare_reports(&report, &reference_report, tolerance);
+        if !regressions.is_empty() {
+            for regression in &regressions {
+                eprintln!("❌ {}: {}", regression.case, regression.reason);
+            }
+            return Err(anyhow::anyhow!(
+                "{} benchmark check(s) regressed beyond {:.0}% tolerance",
+                regressions.len(),
+                tolerance * 100.0
+            ));
+        }
+        eprintln!("✅ All benchmark cases within {:.0}% of reference", tolerance * 100.0);
+    }
+
+    Ok(())
+}
+
+/// Run all bundled benchmark cases
+pub fn run_benchmarks(iterations: usize) -> Result<BenchReport> {
+    let registry = LanguageParserRegistry::new();
+    let mut cases = Vec::new();
+
+    for case in bench_cases() {
+        let parser = registry
+            .get_parser(case.language)
+            .ok_or_else(|| anyhow::anyhow!("No parser available for {:?}", case.language))?;
+
+        let mut engine = RuleEngine::new();
+        let rules = engine.load_rules_from_yaml(case.rules)?;
+        let mut timings = Vec::with_capacity(iterations);
+        let mut findings = 0;
+
+        for _ in 0..iterations {
+            let start = Instant::now();
+            let ast = parser.parse(case.source, Path::new(case.file_name))?;
+            let context = RuleContext::new(case.file_name.to_string(), case.language, case.source.to_string());
+            findings = engine.analyze(ast.as_ref(), &context)?.len();
+            timings.push(start.elapsed().as_secs_f64() * 1000.0);
+        }
+
+        cases.push(BenchCaseResult {
+            name: case.name.to_string(),
+            language: case.language,
+            source_bytes: case.source.len(),
+            rules,
+            findings,
+            mean_ms: timings.iter().sum::<f64>() / timings.len() as f64,
+            min_ms: timings.iter().cloned().fold(f64::INFINITY, f64::min),
+            max_ms: timings.iter().cloned().fold(0.0, f64::max),
+        });
+    }
+
+    Ok(BenchReport {
+        engine_version: env!("CARGO_PKG_VERSION").to_string(),
+        iterations,
+        cases,
+        peak_memory_bytes: crate::profiler::get_peak_memory_usage(),
+    })
+}
+
+fn load_report(path: &Path) -> Result<BenchReport> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Failed to read reference report {}: {}", path.display(), e))?;
+    Ok(serde_json::from_str(&content)?)
+}
+
+/// Compare a report against a reference, returning every case outside the tolerance
+pub fn compare_reports(current: &BenchReport, reference: &BenchReport, tolerance: f64) -> Vec<BenchRegression> {
+    let mut regressions = Vec::new();
+
+    for expected in &reference.cases {
+        let actual = match current.cases.iter().find(|c| c.name == expected.name) {
+            Some(actual) => actual,
+            None => {
+                regressions.push(BenchRegression {
+                    case: expected.name.clone(),
+                    reason: "case missing from current run".to_string(),
+                });
+                continue;
+            }
+        };
+
+        if actual.findings != expected.findings {
+            regressions.push(BenchRegression {
+                case: expected.name.clone(),
+                reason: format!("findings changed from {} to {}", expected.findings, actual.findings),
+            });
+        }
+
+        let limit = expected.mean_ms * (1.0 + tolerance);
+        if actual.mean_ms > limit {
+            regressions.push(BenchRegression {
+                case: expected.name.clone(),
+                reason: format!("mean time {:.3}ms exceeds reference {:.3}ms", actual.mean_ms, expected.mean_ms),
+            });
+        }
+    }
+
+    // 0 means the platform does not report memory usage
+    let memory_limit = reference.peak_memory_bytes as f64 * (1.0 + tolerance);
+    if reference.peak_memory_bytes > 0 && current.peak_memory_bytes as f64 > memory_limit {
+        regressions.push(BenchRegression {
+            case: "peak-memory".to_string(),
+            reason: format!(
+                "peak memory {:.1} MiB exceeds reference {:.1} MiB",
+                current.peak_memory_bytes as f64 / MIB,
+                reference.peak_memory_bytes as f64 / MIB
+            ),
+        });
+    }
+
+    regressions
+}
+
+const MIB: f64 = 1024.0 * 1024.0;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report_with(mean_ms: f64, findings: usize) -> BenchReport {
+        BenchReport {
+            engine_version: "0.1.0".to_string(),
+            iterations: 1,
+            cases: vec![BenchCaseResult {
+                name: "java-basic".to_string(),
+                language: Language::Java,
+                source_bytes: 10,
+                rules: 1,
+                findings,
+                mean_ms,
+                min_ms: mean_ms,
+                max_ms: mean_ms,
+            }],
+            peak_memory_bytes: 0,
+        }
+    }
+
+    #[test]
+    fn test_compare_reports_within_tolerance() {
+        let regressions = compare_reports(&report_with(11.0, 2), &report_with(10.0, 2), 0.2);
+        assert!(regressions.is_empty());
+    }
+
+    #[test]
+    fn test_compare_reports_detects_slowdown_and_finding_drift() {
+        let regressions = compare_reports(&report_with(13.0, 3), &report_with(10.0, 2), 0.2);
+        assert_eq!(regressions.len(), 2);
+    }
+
+    #[test]
+    fn test_compare_reports_detects_memory_growth() {
+        let reference = BenchReport { peak_memory_bytes: 100 * 1024 * 1024, ..report_with(10.0, 2) };
+        let within = BenchReport { peak_memory_bytes: 110 * 1024 * 1024, ..report_with(10.0, 2) };
+        assert!(compare_reports(&within, &reference, 0.2).is_empty());
+
+        let grown = BenchReport { peak_memory_bytes: 130 * 1024 * 1024, ..report_with(10.0, 2) };
+        let regressions = compare_reports(&grown, &reference, 0.2);
+        assert_eq!(regressions.len(), 1);
+        assert_eq!(regressions[0].case, "peak-memory");
+
+        // A reference without memory figures is not compared
+        assert!(compare_reports(&grown, &report_with(10.0, 2), 0.2).is_empty());
+    }
+
+    #[test]
+    fn test_run_benchmarks_covers_all_cases() {
+        let report = run_benchmarks(1).unwrap();
+        assert_eq!(report.cases.len(), bench_cases().len());
+        assert!(report.cases.iter().all(|c| c.rules > 0));
+        if cfg!(target_os = "linux") {
+            assert!(report.peak_memory_bytes > 0);
+        }
+    }
+}
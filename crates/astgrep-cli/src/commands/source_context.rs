@@ -0,0 +1,116 @@
+//! Source context of findings
+//!
+//! `analyze --context N` adds the N lines above and below each finding to the text, JSON
+//! and Markdown output, so a finding can be understood without reopening the file. Every
+//! line carries its number; lines inside the match also carry the matched column range,
+//! which the text output underlines and the JSON output reports as `match`. Matched text
+//! is redacted as in the rest of the output.
+
+use crate::commands::analyze_enhanced::Finding;
+use serde::Serialize;
+
+/// A source line around a finding
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ContextLine {
+    /// 1-based line number
+    pub line: usize,
+    pub text: String,
+    /// 1-based start column and exclusive end column of the match on this line
+    #[serde(rename = "match", skip_serializing_if = "Option::is_none")]
+    pub matched: Option<(usize, usize)>,
+}
+
+impl ContextLine {
+    /// The text before, inside and after the matched range
+    pub fn split(&self) -> (&str, &str, &str) {
+        let (start, end) = match self.matched {
+            Some((start, end)) => (start - 1, end - 1),
+            None => return (&self.text, "", ""),
+        };
+        (&self.text[..start], &self.text[start..end], &self.text[end..])
+    }
+}
+
+/// The lines of the finding with `context` lines above and below, read from its file
+pub fn read(finding: &Finding, context: usize) -> Option<Vec<ContextLine>> {
+    let source = std::fs::read_to_string(&finding.location.file).ok()?;
+    lines(&source, finding, context)
+}
+
+/// The lines of the finding in `source` with `context` lines above and below
+pub fn lines(source: &str, finding: &Finding, context: usize) -> Option<Vec<ContextLine>> {
+    let lines: Vec<&str> = source.lines().collect();
+    let loc = &finding.location;
+    if loc.start_line == 0 || loc.start_line > lines.len() {
+        return None;
+    }
+    let end_line = loc.end_line.clamp(loc.start_line, lines.len());
+    let first = loc.start_line.saturating_sub(context).max(1);
+    let last = (end_line + context).min(lines.len());
+
+    Some((first..=last).map(|number| {
+        let text = lines[number - 1];
+        if number < loc.start_line || number > end_line {
+            return ContextLine { line: number, text: text.to_string(), matched: None };
+        }
+        let start = if number == loc.start_line { loc.start_column.saturating_sub(1).min(text.len()) } else { 0 };
+        let end = if number == loc.end_line { loc.end_column.saturating_sub(1).clamp(start, text.len()) } else { text.len() };
+        match (text.get(..start), text.get(start..end), text.get(end..)) {
+            (Some(head), Some(span), Some(tail)) => {
+                let span = match finding.redaction {
+                    Some(redaction) => redaction.apply(span),
+                    None => span.to_string(),
+                };
+                ContextLine {
+                    line: number,
+                    text: format!("{}{}{}", head, span, tail),
+                    matched: Some((start + 1, start + span.len() + 1)),
+                }
+            }
+            // Columns that do not fall on character boundaries: the whole line is the match
+            _ => {
+                let text = match finding.redaction {
+                    Some(redaction) => redaction.apply(text),
+                    None => text.to_string(),
+                };
+                ContextLine { line: number, matched: Some((1, text.len() + 1)), text }
+            }
+        }
+    }).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::analyze_enhanced::{test_finding, Location};
+    use astgrep_core::Redaction;
+
+    fn finding(start: (usize, usize), end: (usize, usize), redaction: Option<Redaction>) -> Finding {
+        Finding { location: Location::new("app.js", start.0, start.1, end.0, end.1), redaction, ..test_finding("js-eval", "app.js", start.0) }
+    }
+
+    #[test]
+    fn test_lines_around_the_match() {
+        let source = "a();\nlet x = eval(s);\nb();\nc();\n";
+        let lines = lines(source, &finding((2, 9), (2, 16), None), 1).unwrap();
+        assert_eq!(lines.iter().map(|l| l.line).collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert_eq!(lines[1].matched, Some((9, 16)));
+        assert_eq!(lines[1].split(), ("let x = ", "eval(s)", ";"));
+        assert_eq!(lines[0].split(), ("a();", "", ""));
+
+        // Multi-line matches cover whole inner lines; context stops at the file bounds
+        let lines = super::lines(source, &finding((2, 9), (3, 4), None), 5).unwrap();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[2].matched, Some((1, 4)));
+        assert_eq!(lines[3].matched, None);
+
+        // Redaction changes the length of the span
+        let lines = super::lines(source, &finding((2, 9), (2, 16), Some(Redaction::Hash)), 0).unwrap();
+        assert_eq!(lines.len(), 1);
+        let (head, span, tail) = lines[0].split();
+        assert_eq!((head, tail), ("let x = ", ";"));
+        assert!(span.starts_with("[hash:") && span.ends_with(']'));
+
+        assert!(super::lines(source, &finding((9, 1), (9, 2), None), 1).is_none());
+    }
+}
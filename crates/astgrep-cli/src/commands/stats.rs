@@ -0,0 +1,258 @@
+//! Codebase inventory command
+//!
+//! `stats` walks the targets the way `analyze` does, honouring ignore files and the
+//! include, exclude and size limits, and reports per language how many files and lines
+//! there are, how many of the files the parser handles and how many loaded rules apply.
+//! It parses but never matches, so it gives the scope of a scan before running one: files
+//! of a language no rule applies to are walked but not analyzed, and files that fail to
+//! parse only get regex rules.
+
+use anyhow::Result;
+use astgrep_core::Language;
+use astgrep_parser::LanguageParserRegistry;
+use serde::Serialize;
+use std::path::PathBuf;
+use tracing::{debug, info};
+
+use crate::commands::analyze_enhanced::{collect_target_files, determine_language, parser_health, rule_repository};
+use crate::{EnhancedAnalysisConfig, OutputFormatCli};
+
+/// Inventory of the files of one language
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LanguageStats {
+    pub language: String,
+    pub files: usize,
+    pub lines: usize,
+    /// Lines that are not blank
+    pub code_lines: usize,
+    /// Files the parser turned into a syntax tree
+    pub parsed: usize,
+    pub parse_failures: usize,
+    /// Why the language's parser is unavailable, if it is
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parser_unavailable: Option<String>,
+    /// Loaded, enabled rules for the language
+    pub rules: usize,
+}
+
+impl LanguageStats {
+    fn new(language: Language) -> Self {
+        Self {
+            language: language.as_str().to_string(),
+            files: 0,
+            lines: 0,
+            code_lines: 0,
+            parsed: 0,
+            parse_failures: 0,
+            parser_unavailable: None,
+            rules: 0,
+        }
+    }
+}
+
+/// Inventory of all targets, languages with the most files first
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Inventory {
+    pub files: usize,
+    pub lines: usize,
+    /// Files of a language at least one loaded rule applies to
+    pub files_in_scope: usize,
+    /// Binary and oversized files `analyze` would skip
+    pub skipped_files: usize,
+    /// Files that could not be read as text
+    pub unreadable_files: usize,
+    pub rules: usize,
+    pub languages: Vec<LanguageStats>,
+}
+
+impl Inventory {
+    /// Count a file of `language`; `parsed` is none when the language has no parser
+    fn record(&mut self, language: Language, source: &str, parsed: Option<bool>) {
+        let index = match self.languages.iter().position(|stats| stats.language == language.as_str()) {
+            Some(index) => index,
+            None => {
+                self.languages.push(LanguageStats::new(language));
+                self.languages.len() - 1
+            }
+        };
+        let stats = &mut self.languages[index];
+        let (lines, code_lines) = count_lines(source);
+        stats.files += 1;
+        stats.lines += lines;
+        stats.code_lines += code_lines;
+        match parsed {
+            Some(true) => stats.parsed += 1,
+            Some(false) => stats.parse_failures += 1,
+            None => {}
+        }
+        self.files += 1;
+        self.lines += lines;
+    }
+
+    /// Fill in the rule counts and totals once every file is recorded
+    fn finish<'a>(&mut self, rules: impl Iterator<Item = &'a astgrep_rules::Rule>) {
+        let rules: Vec<_> = rules.filter(|rule| rule.enabled).collect();
+        self.rules = rules.len();
+        for stats in &mut self.languages {
+            let language = Language::from_str(&stats.language);
+            stats.rules = rules.iter().filter(|rule| language.is_some_and(|l| rule.applies_to(l))).count();
+        }
+        self.files_in_scope = self.languages.iter().filter(|stats| stats.rules > 0).map(|stats| stats.files).sum();
+        self.languages.sort_by(|a, b| b.files.cmp(&a.files).then_with(|| a.language.cmp(&b.language)));
+    }
+}
+
+/// Total and non-blank lines of `source`
+fn count_lines(source: &str) -> (usize, usize) {
+    source.lines().fold((0, 0), |(lines, code), line| (lines + 1, code + usize::from(!line.trim().is_empty())))
+}
+
+/// Inventory the files under `targets`, counting the rules of `rules` that apply
+pub async fn run(
+    targets: Vec<PathBuf>,
+    rules: Vec<PathBuf>,
+    languages: Vec<Language>,
+    exclude_patterns: Vec<String>,
+    include_patterns: Vec<String>,
+    format: OutputFormatCli,
+) -> Result<()> {
+    let config = EnhancedAnalysisConfig {
+        target_paths: if targets.is_empty() { vec![PathBuf::from(".")] } else { targets },
+        languages,
+        rule_files: rules,
+        exclude_patterns,
+        include_patterns,
+        ..Default::default()
+    };
+    let mut skipped = Vec::new();
+    let files = collect_target_files(&config, &mut skipped).await?;
+    info!("Taking inventory of {} file(s)", files.len());
+
+    let registry = LanguageParserRegistry::new();
+    let health = parser_health();
+    let mut inventory = Inventory { skipped_files: skipped.len(), ..Default::default() };
+    for path in files {
+        let language = match config.language_override(&path).map(Ok).unwrap_or_else(|| determine_language(&path)) {
+            Ok(language) => language,
+            Err(e) => {
+                debug!("Leaving {} out of the inventory: {}", path.display(), e);
+                continue;
+            }
+        };
+        let source = match std::fs::read_to_string(&path) {
+            Ok(source) => source,
+            Err(e) => {
+                debug!("Cannot read {}: {}", path.display(), e);
+                inventory.unreadable_files += 1;
+                continue;
+            }
+        };
+        let parsed = match registry.get_parser(language) {
+            Some(parser) if !health.is_degraded(language) => Some(parser.parse(&source, &path).is_ok()),
+            _ => None,
+        };
+        inventory.record(language, &source, parsed);
+    }
+    for stats in &mut inventory.languages {
+        if let Some(language) = Language::from_str(&stats.language) {
+            stats.parser_unavailable = health.reason(language).map(str::to_string);
+        }
+    }
+    inventory.finish(rule_repository(&config).rules());
+
+    let output = match format {
+        OutputFormatCli::Json => serde_json::to_string_pretty(&inventory)?,
+        _ => format_inventory(&inventory),
+    };
+    println!("{}", output);
+    Ok(())
+}
+
+fn format_inventory(inventory: &Inventory) -> String {
+    let mut output = format!("📊 {} file(s), {} line(s)\n", inventory.files, inventory.lines);
+    if inventory.rules == 0 {
+        output.push_str("   No rules loaded; pass --rules to see which files a scan would cover\n");
+    } else {
+        output.push_str(&format!(
+            "   {} rule(s) loaded; {} of the files are in a language they cover\n",
+            inventory.rules, inventory.files_in_scope
+        ));
+    }
+    if inventory.skipped_files > 0 {
+        output.push_str(&format!("   {} binary or oversized file(s) skipped\n", inventory.skipped_files));
+    }
+    if inventory.unreadable_files > 0 {
+        output.push_str(&format!("   {} file(s) not readable as text\n", inventory.unreadable_files));
+    }
+
+    output.push_str(&format!(
+        "\n  {:<12} {:>7} {:>9} {:>9} {:>8} {:>6}\n",
+        "Language", "Files", "Lines", "Code", "Parsed", "Rules"
+    ));
+    for stats in &inventory.languages {
+        let parsed = match stats.parser_unavailable {
+            Some(_) => "-".to_string(),
+            None => format!("{:.0}%", 100.0 * stats.parsed as f64 / stats.files.max(1) as f64),
+        };
+        output.push_str(&format!(
+            "  {:<12} {:>7} {:>9} {:>9} {:>8} {:>6}\n",
+            stats.language, stats.files, stats.lines, stats.code_lines, parsed, stats.rules
+        ));
+    }
+    for stats in inventory.languages.iter().filter(|stats| stats.parser_unavailable.is_some()) {
+        output.push_str(&format!(
+            "\n  ⚠️  {} parser unavailable ({}); only pattern-regex rules would run",
+            stats.language,
+            stats.parser_unavailable.as_deref().unwrap_or_default()
+        ));
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use astgrep_core::{Confidence, Severity};
+    use astgrep_rules::Rule;
+
+    fn rule(id: &str, languages: Vec<Language>) -> Rule {
+        Rule::new(id.to_string(), id.to_string(), String::new(), Severity::Warning, Confidence::Medium, languages)
+    }
+
+    #[test]
+    fn test_inventory_counts_files_lines_and_rules() {
+        let mut inventory = Inventory::default();
+        inventory.record(Language::Python, "import os\n\nos.system(x)\n", Some(true));
+        inventory.record(Language::Python, "def broken(:\n", Some(false));
+        inventory.record(Language::Java, "class A {}\n", Some(true));
+        inventory.record(Language::JavaScript, "eval(x)\n", Some(true));
+        inventory.record(Language::JavaScript, "\n", None);
+        inventory.record(Language::JavaScript, "run()\n", Some(true));
+
+        let mut disabled = rule("py-off", vec![Language::Python]);
+        disabled.enabled = false;
+        let rules = [rule("py-eval", vec![Language::Python]), rule("any-exec", vec![Language::Python, Language::Java]), disabled];
+        inventory.finish(rules.iter());
+
+        assert_eq!((inventory.files, inventory.lines, inventory.rules), (6, 8, 2));
+        assert_eq!(inventory.files_in_scope, 3);
+        let languages: Vec<&str> = inventory.languages.iter().map(|stats| stats.language.as_str()).collect();
+        assert_eq!(languages, vec!["javascript", "python", "java"]);
+        let python = &inventory.languages[1];
+        assert_eq!((python.files, python.lines, python.code_lines), (2, 4, 3));
+        assert_eq!((python.parsed, python.parse_failures, python.rules), (1, 1, 2));
+        assert_eq!(inventory.languages[0].rules, 0);
+        assert_eq!(inventory.languages[0].parsed, 2);
+
+        let text = format_inventory(&inventory);
+        assert!(text.contains("6 file(s), 8 line(s)"));
+        assert!(text.contains("3 of the files are in a language they cover"));
+        assert!(text.contains(" 50%"));
+    }
+
+    #[test]
+    fn test_count_lines() {
+        assert_eq!(count_lines(""), (0, 0));
+        assert_eq!(count_lines("a\n  \nb"), (3, 2));
+    }
+}
@@ -0,0 +1,535 @@
+//! Project-level supply-chain checks on dependency manifests and lockfiles
+//!
+//! Each manifest is compared with its lockfile (`package.json` / `package-lock.json`,
+//! `Cargo.toml` / `Cargo.lock`, `composer.json` / `composer.lock`). The checks report
+//! dependencies the lockfile does not reflect, several locked versions of a
+//! security-sensitive package, and dependencies that follow a git branch rather than
+//! a fixed revision. Findings carry the `supply-chain` category and go through the
+//! same filters and output formats as rule findings. Manifests that ignore files or
+//! `--exclude` patterns leave out of the analysis are not checked either.
+
+use anyhow::Result;
+use astgrep_core::{Confidence, Severity};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use tracing::debug;
+use crate::commands::analyze_enhanced::{is_excluded, Finding, Location};
+use crate::commands::ignore_file::IgnoreStack;
+use crate::EnhancedAnalysisConfig;
+
+/// Category attached to every supply-chain finding
+pub const CATEGORY: &str = "supply-chain";
+
+const DRIFT_RULE: &str = "supply-chain.lockfile-drift";
+const DUPLICATE_RULE: &str = "supply-chain.duplicate-sensitive-package";
+const GIT_BRANCH_RULE: &str = "supply-chain.git-branch-dependency";
+
+/// Directories of installed dependencies, whose manifests are not the project's
+const SKIPPED_DIRS: &[&str] = &["node_modules", "target", "vendor", ".git"];
+
+/// Checks of a manifest in a directory, given the project root
+type ManifestCheck = fn(&Path, &Path, &mut Vec<Finding>) -> Result<()>;
+
+/// Manifest file names and their checks
+const MANIFEST_CHECKS: [(&str, ManifestCheck); 3] = [
+    ("package.json", check_npm),
+    ("Cargo.toml", check_cargo),
+    ("composer.json", check_composer),
+];
+
+/// npm packages whose duplicated versions usually mean an unpatched copy is still shipped
+const SENSITIVE_NPM_PACKAGES: &[&str] = &[
+    "axios", "crypto-js", "follow-redirects", "jsonwebtoken", "lodash", "minimist", "node-fetch",
+    "qs", "semver", "tar", "tough-cookie", "ws", "xml2js",
+];
+
+/// Crates whose duplicated versions usually mean an unpatched copy is still linked
+const SENSITIVE_CRATES: &[&str] = &[
+    "h2", "hyper", "native-tls", "openssl", "openssl-sys", "reqwest", "ring", "rustls",
+    "rustls-webpki", "time", "tokio", "webpki",
+];
+
+/// Run every supply-chain check on the manifests under `root` that `config` scans
+pub fn check_project(root: &Path, config: &EnhancedAnalysisConfig) -> Vec<Finding> {
+    let mut manifests = Vec::new();
+    collect_manifests(root, &IgnoreStack::above(root, config.git_ignore), config, &mut manifests);
+
+    let mut findings = Vec::new();
+    for (dir, check) in &manifests {
+        if let Err(e) = check(root, dir, &mut findings) {
+            debug!("Skipping supply-chain checks for {}: {}", dir.display(), e);
+        }
+    }
+    findings
+}
+
+/// The directories under `dir` with a manifest that is neither ignored nor excluded,
+/// with the manifest's check
+fn collect_manifests(dir: &Path, ignores: &IgnoreStack, config: &EnhancedAnalysisConfig, manifests: &mut Vec<(PathBuf, ManifestCheck)>) {
+    let ignores = ignores.enter(dir);
+    for (name, check) in MANIFEST_CHECKS {
+        let manifest = dir.join(name);
+        if manifest.is_file() && !ignores.is_ignored(&manifest, false) && !is_excluded(&manifest, &config.exclude_patterns) {
+            manifests.push((dir.to_path_buf(), check));
+        }
+    }
+
+    let mut children: Vec<PathBuf> = match std::fs::read_dir(dir) {
+        Ok(entries) => entries.filter_map(|e| e.ok()).map(|e| e.path()).filter(|p| p.is_dir()).collect(),
+        Err(_) => return,
+    };
+    children.sort();
+    for child in children {
+        let skipped = child.file_name().and_then(|n| n.to_str()).is_some_and(|n| SKIPPED_DIRS.contains(&n));
+        if !skipped && !ignores.is_ignored(&child, true) {
+            collect_manifests(&child, &ignores, config, manifests);
+        }
+    }
+}
+
+/// Finding on the first line of `source` containing `needle` (line 1 if none does)
+fn finding_at(rule_id: &str, message: String, severity: Severity, file: &Path, source: &str, needle: &str) -> Finding {
+    let (line, text) = source
+        .lines()
+        .enumerate()
+        .find(|(_, line)| line.contains(needle))
+        .map_or((1, ""), |(i, line)| (i + 1, line));
+    let location = Location::new(file, line, 1, line, text.len() + 1);
+    Finding { category: Some(CATEGORY.to_string()), ..Finding::new(rule_id, message, severity, Confidence::High, location) }
+}
+
+/// Whether an npm dependency spec is a git source without a commit pin
+fn is_npm_git_branch(spec: &str) -> bool {
+    let is_git = ["git+", "git://", "github:", "gitlab:", "bitbucket:"].iter().any(|p| spec.starts_with(p))
+        || spec.ends_with(".git")
+        || spec.contains(".git#")
+        || is_github_shorthand(spec);
+    if !is_git {
+        return false;
+    }
+    match spec.rsplit_once('#') {
+        Some((_, fragment)) => !is_commit(fragment) && !fragment.starts_with("semver:"),
+        None => true,
+    }
+}
+
+/// `user/repo` or `user/repo#ref`
+fn is_github_shorthand(spec: &str) -> bool {
+    let repo = spec.split('#').next().unwrap_or(spec);
+    let mut parts = repo.split('/');
+    let valid = |part: Option<&str>| {
+        part.is_some_and(|p| !p.is_empty() && p.chars().all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c)))
+    };
+    !spec.starts_with('@') && valid(parts.next()) && valid(parts.next()) && parts.next().is_none()
+}
+
+fn is_commit(reference: &str) -> bool {
+    reference.len() == 40 && reference.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+fn json_string_map(value: &serde_json::Value, keys: &[&str]) -> BTreeMap<String, String> {
+    keys.iter()
+        .filter_map(|key| value.get(*key).and_then(|v| v.as_object()))
+        .flat_map(|deps| deps.iter().filter_map(|(name, spec)| Some((name.clone(), spec.as_str()?.to_string()))))
+        .collect()
+}
+
+const NPM_DEPENDENCY_KEYS: &[&str] = &["dependencies", "devDependencies", "optionalDependencies"];
+
+fn check_npm(_root: &Path, dir: &Path, findings: &mut Vec<Finding>) -> Result<()> {
+    let manifest_path = dir.join("package.json");
+    let manifest_source = std::fs::read_to_string(&manifest_path)?;
+    let manifest: serde_json::Value = serde_json::from_str(&manifest_source)?;
+    let declared = json_string_map(&manifest, NPM_DEPENDENCY_KEYS);
+
+    for (name, spec) in &declared {
+        if is_npm_git_branch(spec) {
+            findings.push(finding_at(
+                GIT_BRANCH_RULE,
+                format!("Dependency '{}' follows a git branch ({}); pin it to a commit or a published version", name, spec),
+                Severity::Warning,
+                &manifest_path,
+                &manifest_source,
+                &format!("\"{}\"", name),
+            ));
+        }
+    }
+
+    let lock_path = dir.join("package-lock.json");
+    if !lock_path.is_file() {
+        return Ok(());
+    }
+    let lock_source = std::fs::read_to_string(&lock_path)?;
+    let lock: serde_json::Value = serde_json::from_str(&lock_source)?;
+
+    let mut versions: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    if let Some(packages) = lock.get("packages").and_then(|p| p.as_object()) {
+        // lockfileVersion 2 and 3: the "" entry records what the manifest declared
+        let locked_specs = packages.get("").map(|root| json_string_map(root, NPM_DEPENDENCY_KEYS)).unwrap_or_default();
+        for (name, spec) in &declared {
+            match locked_specs.get(name) {
+                None => findings.push(npm_missing_from_lock(name, &manifest_path, &manifest_source)),
+                Some(locked) if locked != spec => findings.push(finding_at(
+                    DRIFT_RULE,
+                    format!("package.json requires {} {} but package-lock.json was generated for {}", name, spec, locked),
+                    Severity::Warning,
+                    &manifest_path,
+                    &manifest_source,
+                    &format!("\"{}\"", name),
+                )),
+                Some(_) => {}
+            }
+        }
+        for name in locked_specs.keys().filter(|name| !declared.contains_key(*name)) {
+            findings.push(finding_at(
+                DRIFT_RULE,
+                format!("package-lock.json still locks '{}', which package.json no longer declares", name),
+                Severity::Warning,
+                &lock_path,
+                &lock_source,
+                &format!("\"{}\"", name),
+            ));
+        }
+
+        for (key, entry) in packages {
+            let name = match key.rsplit_once("node_modules/") {
+                Some((_, name)) => name,
+                None => continue,
+            };
+            if let Some(version) = entry.get("version").and_then(|v| v.as_str()) {
+                versions.entry(name.to_string()).or_default().insert(version.to_string());
+            }
+        }
+    } else if let Some(dependencies) = lock.get("dependencies").and_then(|d| d.as_object()) {
+        // lockfileVersion 1: nested dependency trees
+        for name in declared.keys().filter(|name| !dependencies.contains_key(*name)) {
+            findings.push(npm_missing_from_lock(name, &manifest_path, &manifest_source));
+        }
+        collect_v1_versions(dependencies, &mut versions);
+    }
+
+    for name in SENSITIVE_NPM_PACKAGES {
+        if let Some(locked) = versions.get(*name).filter(|v| v.len() > 1) {
+            findings.push(duplicate_finding(name, locked, &lock_path, &lock_source, &format!("node_modules/{}\"", name)));
+        }
+    }
+    Ok(())
+}
+
+fn npm_missing_from_lock(name: &str, manifest_path: &Path, manifest_source: &str) -> Finding {
+    finding_at(
+        DRIFT_RULE,
+        format!("Dependency '{}' is declared in package.json but missing from package-lock.json", name),
+        Severity::Warning,
+        manifest_path,
+        manifest_source,
+        &format!("\"{}\"", name),
+    )
+}
+
+fn collect_v1_versions(dependencies: &serde_json::Map<String, serde_json::Value>, versions: &mut BTreeMap<String, BTreeSet<String>>) {
+    for (name, entry) in dependencies {
+        if let Some(version) = entry.get("version").and_then(|v| v.as_str()) {
+            versions.entry(name.clone()).or_default().insert(version.to_string());
+        }
+        if let Some(nested) = entry.get("dependencies").and_then(|d| d.as_object()) {
+            collect_v1_versions(nested, versions);
+        }
+    }
+}
+
+fn duplicate_finding(name: &str, versions: &BTreeSet<String>, lock_path: &Path, lock_source: &str, needle: &str) -> Finding {
+    let versions: Vec<&str> = versions.iter().map(String::as_str).collect();
+    finding_at(
+        DUPLICATE_RULE,
+        format!(
+            "Security-sensitive package '{}' is locked at {} versions ({}); deduplicate so every copy gets fixes",
+            name,
+            versions.len(),
+            versions.join(", ")
+        ),
+        Severity::Warning,
+        lock_path,
+        lock_source,
+        needle,
+    )
+}
+
+/// Dependency tables of a Cargo manifest, including target-specific and workspace ones
+fn cargo_dependency_tables(manifest: &toml::Value) -> Vec<&toml::value::Table> {
+    const KEYS: &[&str] = &["dependencies", "dev-dependencies", "build-dependencies"];
+    let mut tables: Vec<&toml::value::Table> = KEYS.iter().filter_map(|k| manifest.get(*k)?.as_table()).collect();
+    if let Some(targets) = manifest.get("target").and_then(|t| t.as_table()) {
+        for target in targets.values() {
+            tables.extend(KEYS.iter().filter_map(|k| target.get(*k)?.as_table()));
+        }
+    }
+    if let Some(deps) = manifest.get("workspace").and_then(|w| w.get("dependencies")).and_then(|d| d.as_table()) {
+        tables.push(deps);
+    }
+    tables
+}
+
+/// Nearest `Cargo.lock` in `dir` or one of its ancestors below `root`
+fn find_cargo_lock(root: &Path, dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .take_while(|d| d.starts_with(root))
+        .map(|d| d.join("Cargo.lock"))
+        .find(|lock| lock.is_file())
+}
+
+fn check_cargo(root: &Path, dir: &Path, findings: &mut Vec<Finding>) -> Result<()> {
+    let manifest_path = dir.join("Cargo.toml");
+    let manifest_source = std::fs::read_to_string(&manifest_path)?;
+    let manifest: toml::Value = toml::from_str(&manifest_source)?;
+
+    // (package name, key in the manifest)
+    let mut declared: Vec<(String, String)> = Vec::new();
+    for table in cargo_dependency_tables(&manifest) {
+        for (key, spec) in table {
+            let package = spec.get("package").and_then(|p| p.as_str()).unwrap_or(key);
+            declared.push((package.to_string(), key.clone()));
+
+            if spec.get("git").is_some() {
+                let pinned = spec.get("rev").is_some() || spec.get("tag").is_some();
+                if !pinned {
+                    let target = spec.get("branch").and_then(|b| b.as_str()).map_or("the default branch".to_string(), |b| format!("branch '{}'", b));
+                    findings.push(finding_at(
+                        GIT_BRANCH_RULE,
+                        format!("Dependency '{}' follows {} of a git repository; pin it with `rev` or `tag`", key, target),
+                        Severity::Warning,
+                        &manifest_path,
+                        &manifest_source,
+                        key,
+                    ));
+                }
+            }
+        }
+    }
+
+    let lock_path = match find_cargo_lock(root, dir) {
+        Some(lock_path) => lock_path,
+        None => return Ok(()),
+    };
+    let lock_source = std::fs::read_to_string(&lock_path)?;
+    let lock: toml::Value = toml::from_str(&lock_source)?;
+    let mut versions: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    for package in lock.get("package").and_then(|p| p.as_array()).into_iter().flatten() {
+        if let (Some(name), Some(version)) = (package.get("name").and_then(|n| n.as_str()), package.get("version").and_then(|v| v.as_str())) {
+            versions.entry(name.to_string()).or_default().insert(version.to_string());
+        }
+    }
+
+    for (package, key) in &declared {
+        if !versions.contains_key(package) {
+            findings.push(finding_at(
+                DRIFT_RULE,
+                format!("Dependency '{}' is declared in Cargo.toml but missing from {}", package, lock_path.display()),
+                Severity::Warning,
+                &manifest_path,
+                &manifest_source,
+                key,
+            ));
+        }
+    }
+
+    // Duplicates are reported once, by the manifest next to the lockfile
+    if lock_path.parent() == Some(dir) {
+        for name in SENSITIVE_CRATES {
+            if let Some(locked) = versions.get(*name).filter(|v| v.len() > 1) {
+                findings.push(duplicate_finding(name, locked, &lock_path, &lock_source, &format!("name = \"{}\"", name)));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Platform requirements in composer.json that are never locked
+fn is_composer_platform_package(name: &str) -> bool {
+    matches!(name, "php" | "php-64bit" | "composer" | "composer-plugin-api" | "composer-runtime-api")
+        || name.starts_with("ext-")
+        || name.starts_with("lib-")
+}
+
+fn check_composer(_root: &Path, dir: &Path, findings: &mut Vec<Finding>) -> Result<()> {
+    let manifest_path = dir.join("composer.json");
+    let manifest_source = std::fs::read_to_string(&manifest_path)?;
+    let manifest: serde_json::Value = serde_json::from_str(&manifest_source)?;
+    let declared: BTreeMap<String, String> = json_string_map(&manifest, &["require", "require-dev"])
+        .into_iter()
+        .filter(|(name, _)| !is_composer_platform_package(name))
+        .collect();
+
+    for (name, constraint) in &declared {
+        // `dev-main` and `1.x-dev` track a branch unless a commit is appended with `#`
+        let branch = constraint.split('|').map(str::trim).any(|c| {
+            let (version, commit) = c.split_once('#').unwrap_or((c, ""));
+            (version.starts_with("dev-") || version.ends_with("-dev")) && !is_commit(commit)
+        });
+        if branch {
+            findings.push(finding_at(
+                GIT_BRANCH_RULE,
+                format!("Dependency '{}' follows a branch ({}); require a tagged release or pin a commit", name, constraint),
+                Severity::Warning,
+                &manifest_path,
+                &manifest_source,
+                &format!("\"{}\"", name),
+            ));
+        }
+    }
+
+    let lock_path = dir.join("composer.lock");
+    if !lock_path.is_file() {
+        return Ok(());
+    }
+    let lock: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&lock_path)?)?;
+    let locked: BTreeSet<String> = ["packages", "packages-dev"]
+        .iter()
+        .filter_map(|key| lock.get(*key).and_then(|p| p.as_array()))
+        .flatten()
+        .filter_map(|package| package.get("name").and_then(|n| n.as_str()))
+        .map(|name| name.to_ascii_lowercase())
+        .collect();
+
+    for name in declared.keys().filter(|name| !locked.contains(&name.to_ascii_lowercase())) {
+        findings.push(finding_at(
+            DRIFT_RULE,
+            format!("Dependency '{}' is declared in composer.json but missing from composer.lock", name),
+            Severity::Warning,
+            &manifest_path,
+            &manifest_source,
+            &format!("\"{}\"", name),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_project() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+
+        let web = root.join("web");
+        std::fs::create_dir_all(&web).unwrap();
+        std::fs::write(web.join("package.json"), r#"{
+  "dependencies": {
+    "express": "^4.18.0",
+    "lodash": "^4.17.21",
+    "left-pad": "^1.3.0",
+    "widgets": "acme/widgets#main",
+    "pinned": "git+https://github.com/acme/pinned.git#0123456789abcdef0123456789abcdef01234567"
+  }
+}"#).unwrap();
+        std::fs::write(web.join("package-lock.json"), r#"{
+  "lockfileVersion": 3,
+  "packages": {
+    "": {
+      "dependencies": {
+        "express": "^4.17.0",
+        "lodash": "^4.17.21",
+        "widgets": "acme/widgets#main",
+        "pinned": "git+https://github.com/acme/pinned.git#0123456789abcdef0123456789abcdef01234567",
+        "moment": "^2.29.0"
+      }
+    },
+    "node_modules/lodash": { "version": "4.17.21" },
+    "node_modules/express/node_modules/lodash": { "version": "4.17.4" },
+    "node_modules/express": { "version": "4.17.3" }
+  }
+}"#).unwrap();
+
+        std::fs::write(root.join("Cargo.toml"), r#"
+[package]
+name = "app"
+version = "0.1.0"
+
+[dependencies]
+serde = "1.0"
+tls = { package = "rustls", version = "0.21" }
+helper = { git = "https://github.com/acme/helper", branch = "develop" }
+fixed = { git = "https://github.com/acme/fixed", rev = "abc123" }
+"#).unwrap();
+        std::fs::write(root.join("Cargo.lock"), r#"
+version = 3
+
+[[package]]
+name = "serde"
+version = "1.0.190"
+
+[[package]]
+name = "rustls"
+version = "0.20.9"
+
+[[package]]
+name = "rustls"
+version = "0.21.8"
+
+[[package]]
+name = "fixed"
+version = "0.1.0"
+"#).unwrap();
+
+        let php = root.join("php");
+        std::fs::create_dir_all(&php).unwrap();
+        std::fs::write(php.join("composer.json"), r#"{
+  "require": { "php": ">=8.1", "ext-json": "*", "monolog/monolog": "^3.0", "acme/tools": "dev-master" }
+}"#).unwrap();
+        std::fs::write(php.join("composer.lock"), r#"{ "packages": [ { "name": "monolog/monolog", "version": "3.5.0" } ], "packages-dev": [] }"#).unwrap();
+
+        // Lockfiles under node_modules are never inspected
+        let vendored = web.join("node_modules/dep");
+        std::fs::create_dir_all(&vendored).unwrap();
+        std::fs::write(vendored.join("package.json"), r#"{ "dependencies": { "x": "github:a/b" } }"#).unwrap();
+
+        let findings = check_project(root, &EnhancedAnalysisConfig::default());
+        let summary: BTreeSet<(String, String)> = findings
+            .iter()
+            .map(|f| (f.rule_id.clone(), f.location.file.strip_prefix(root).unwrap().to_string_lossy().to_string()))
+            .collect();
+        let count = |rule: &str| findings.iter().filter(|f| f.rule_id == rule).count();
+        assert!(findings.iter().all(|f| f.category.as_deref() == Some(CATEGORY)));
+
+        // express spec changed, left-pad unlocked, moment removed, helper and acme/tools missing
+        assert_eq!(count(DRIFT_RULE), 5, "{:#?}", findings);
+        // widgets#main, helper branch, acme/tools dev-master
+        assert_eq!(count(GIT_BRANCH_RULE), 3, "{:#?}", findings);
+        // lodash in npm, rustls in Cargo
+        assert_eq!(count(DUPLICATE_RULE), 2, "{:#?}", findings);
+        assert!(summary.contains(&(DUPLICATE_RULE.to_string(), "Cargo.lock".to_string())));
+        assert!(summary.contains(&(DRIFT_RULE.to_string(), "web/package-lock.json".to_string())));
+        assert!(!summary.iter().any(|(_, file)| file.contains("node_modules")));
+
+        let express = findings.iter().find(|f| f.message.contains("express")).unwrap();
+        assert_eq!(express.location.start_line, 3);
+    }
+
+    #[test]
+    fn test_check_project_skips_ignored_and_excluded_manifests() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        for sub in ["app", "generated", "fixtures"] {
+            std::fs::create_dir_all(root.join(sub)).unwrap();
+            std::fs::write(root.join(sub).join("package.json"), r#"{ "dependencies": { "widgets": "acme/widgets#main" } }"#).unwrap();
+        }
+        std::fs::write(root.join(".astgrepignore"), "generated/\n").unwrap();
+
+        let config = EnhancedAnalysisConfig { exclude_patterns: vec!["*/fixtures/*".to_string()], ..Default::default() };
+        let files: Vec<PathBuf> = check_project(root, &config).into_iter().map(|f| f.location.file).collect();
+        assert_eq!(files, vec![root.join("app").join("package.json")]);
+    }
+
+    #[test]
+    fn test_is_npm_git_branch() {
+        assert!(is_npm_git_branch("github:acme/widgets"));
+        assert!(is_npm_git_branch("acme/widgets#develop"));
+        assert!(is_npm_git_branch("git+ssh://git@github.com/acme/widgets.git"));
+        assert!(!is_npm_git_branch("acme/widgets#0123456789abcdef0123456789abcdef01234567"));
+        assert!(!is_npm_git_branch("github:acme/widgets#semver:^1.2.0"));
+        assert!(!is_npm_git_branch("^1.2.3"));
+        assert!(!is_npm_git_branch("npm:@scope/pkg@1.0.0"));
+    }
+}
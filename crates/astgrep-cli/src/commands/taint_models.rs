@@ -0,0 +1,111 @@
+//! Taint models of library functions
+//!
+//! Model files (see [`astgrep_dataflow::models`]) tell dataflow rules which library calls
+//! return untrusted data, which clean it and which are sinks, so flows through libraries
+//! are found without their source. Projects list model files or directories under
+//! `[taint]` in `astgrep.toml`, relative to that file; `--taint-models` adds more, which
+//! override the configured models of the same function.
+
+use anyhow::{anyhow, Result};
+use astgrep_dataflow::ModelSet;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+#[derive(Deserialize)]
+struct TaintFile {
+    #[serde(default)]
+    taint: TaintSection,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TaintSection {
+    #[serde(default)]
+    models: Vec<PathBuf>,
+}
+
+/// The model paths `[taint]` of `config_file` lists, relative to its directory
+fn configured_paths(config_file: &Path) -> Result<Vec<PathBuf>> {
+    let section = match std::fs::read_to_string(config_file) {
+        Ok(text) => toml::from_str::<TaintFile>(&text)
+            .map_err(|e| anyhow!("Invalid taint settings in {}: {}", config_file.display(), e))?
+            .taint,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => TaintSection::default(),
+        Err(e) => return Err(e.into()),
+    };
+    let base = config_file.parent().unwrap_or(Path::new(""));
+    Ok(section.models.iter().map(|path| base.join(path)).collect())
+}
+
+/// The models of the files and directories configured in `config_file` and then `extra`,
+/// or none when neither names any
+pub fn load(config_file: &Path, extra: &[PathBuf]) -> Result<Option<ModelSet>> {
+    let mut paths = configured_paths(config_file)?;
+    paths.extend(extra.iter().cloned());
+    if paths.is_empty() {
+        return Ok(None);
+    }
+
+    let mut models = ModelSet::new();
+    for path in &paths {
+        models.extend(ModelSet::load(path).map_err(|e| anyhow!("Cannot load taint models from {}: {}", path.display(), e))?);
+    }
+    Ok(Some(models))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_models_of_config_and_flag() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = dir.path().join("astgrep.toml");
+        assert!(load(&config, &[]).unwrap().is_none());
+
+        std::fs::create_dir(dir.path().join("models")).unwrap();
+        std::fs::write(dir.path().join("models/lodash.yaml"), "models:\n  - function: lodash.escape\n    sanitizer: html_encoding\n").unwrap();
+        std::fs::write(&config, "[taint]\nmodels = [\"models\"]\n").unwrap();
+        let extra = dir.path().join("knex.yaml");
+        std::fs::write(&extra, "models:\n  - function: knex.raw\n    sink: sql_execution\n").unwrap();
+
+        let models = load(&config, std::slice::from_ref(&extra)).unwrap().unwrap();
+        assert_eq!(models.len(), 2);
+        assert!(models.lookup("lodash.escape").is_some());
+        assert!(models.lookup("knex.raw").is_some());
+
+        std::fs::write(&config, "[taint]\nmodels = [\"missing.yaml\"]\n").unwrap();
+        assert!(load(&config, &[]).unwrap_err().to_string().contains("missing.yaml"));
+    }
+
+    #[test]
+    fn test_models_reach_dataflow_rules() {
+        let dir = tempfile::tempdir().unwrap();
+        let rules = dir.path().join("rules.yaml");
+        std::fs::write(
+            &rules,
+            "rules:\n  - id: js-raw-sql\n    message: request data in a raw query\n    severity: ERROR\n    languages: [javascript]\n    dataflow:\n      sources: [\"getUserInput()\"]\n      sinks: [\"model:sql_execution\"]\n",
+        )
+        .unwrap();
+        let models = "models:\n  - function: req.query\n    source: user_input\n  - function: knex.raw\n    sink: sql_execution\n    args: [0]\n  \
+                      - function: sqlstring.escape\n    sanitizer: sql_parameter_binding\n  - function: lodash.escape\n    sanitizer: html_encoding\n";
+        let model_file = dir.path().join("models.yaml");
+        std::fs::write(&model_file, models).unwrap();
+
+        let file = dir.path().join("app.js");
+        let source = "const id = req.query.id;\n\
+                      knex.raw(id);\n\
+                      knex.raw(sqlstring.escape(id));\n\
+                      knex.raw(lodash.escape(id));\n";
+        let mut config = crate::EnhancedAnalysisConfig { rule_files: vec![rules], ..Default::default() };
+        let flows = |config: &crate::EnhancedAnalysisConfig| {
+            let findings = crate::commands::analyze_enhanced::analyze_source(&file, source, config).unwrap();
+            findings.iter().map(|f| f.location.start_line).collect::<Vec<_>>()
+        };
+        assert!(flows(&config).is_empty());
+
+        // The HTML sanitizer does not clean the query, the SQL one does
+        config.taint_models = load(&dir.path().join("astgrep.toml"), &[model_file]).unwrap().map(std::sync::Arc::new);
+        assert_eq!(flows(&config), vec![2, 4]);
+    }
+}
@@ -0,0 +1,93 @@
+//! Binary and oversized targets
+//!
+//! Minified bundles, lockfiles and binaries are rarely worth analyzing and can be large
+//! enough to dominate a run's memory and time. The file collector checks each target's
+//! size against `--max-target-bytes` and looks for a NUL byte at its start, skipping the
+//! files that fail and listing them in the run statistics.
+
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::path::Path;
+use tracing::debug;
+
+/// Size limit of a target file unless `--max-target-bytes` sets another
+pub const DEFAULT_MAX_TARGET_BYTES: u64 = 1_000_000;
+
+/// Bytes read from the start of a file to decide whether it is binary
+const BINARY_SNIFF_BYTES: usize = 8192;
+
+/// Why a target file was not analyzed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SkipReason {
+    /// Larger than the size limit
+    TooLarge,
+    /// Contains a NUL byte, so it is not source code
+    Binary,
+}
+
+/// A target file left out of the analysis
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SkippedFile {
+    pub file: String,
+    pub reason: SkipReason,
+    pub bytes: u64,
+}
+
+/// Why `path` should not be analyzed, if it should not; files that cannot be read are
+/// left to the analysis to report
+pub fn check_target(path: &Path, max_bytes: Option<u64>) -> Option<SkippedFile> {
+    let bytes = std::fs::metadata(path).ok()?.len();
+    let skipped = |reason| {
+        debug!("Skipping {} ({:?}, {} bytes)", path.display(), reason, bytes);
+        Some(SkippedFile { file: path.display().to_string(), reason, bytes })
+    };
+
+    if max_bytes.is_some_and(|max| bytes > max) {
+        return skipped(SkipReason::TooLarge);
+    }
+    let mut head = Vec::with_capacity(BINARY_SNIFF_BYTES);
+    std::fs::File::open(path).ok()?.take(BINARY_SNIFF_BYTES as u64).read_to_end(&mut head).ok()?;
+    if head.contains(&0) {
+        return skipped(SkipReason::Binary);
+    }
+    None
+}
+
+/// Skipped files counted by reason, e.g. `2 too large, 1 binary`
+pub fn describe(skipped: &[SkippedFile]) -> String {
+    let count = |reason| skipped.iter().filter(|file| file.reason == reason).count();
+    let parts: Vec<String> = [(SkipReason::TooLarge, "too large"), (SkipReason::Binary, "binary")]
+        .into_iter()
+        .map(|(reason, label)| (count(reason), label))
+        .filter(|(n, _)| *n > 0)
+        .map(|(n, label)| format!("{} {}", n, label))
+        .collect();
+    parts.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_target() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("app.js");
+        let bundle = dir.path().join("bundle.min.js");
+        let image = dir.path().join("logo.js");
+        std::fs::write(&source, "eval(input);\n").unwrap();
+        std::fs::write(&bundle, "var a=1;".repeat(200)).unwrap();
+        std::fs::write(&image, b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR").unwrap();
+
+        assert_eq!(check_target(&source, Some(1000)), None);
+        let large = check_target(&bundle, Some(1000)).unwrap();
+        assert_eq!((large.reason, large.bytes), (SkipReason::TooLarge, 1600));
+        assert_eq!(check_target(&bundle, None), None);
+        let binary = check_target(&image, None).unwrap();
+        assert_eq!(binary.reason, SkipReason::Binary);
+        assert_eq!(check_target(&dir.path().join("missing.js"), Some(1)), None);
+
+        assert_eq!(describe(&[large, binary.clone(), binary]), "1 too large, 2 binary");
+    }
+}
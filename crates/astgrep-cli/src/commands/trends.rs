@@ -0,0 +1,371 @@
+//! Cross-run trend database and the `trends` command
+//!
+//! `analyze --record` appends each run's findings to a local SQLite database. The
+//! `trends` command reads it back and reports findings over time, how long resolved
+//! findings took to fix, and findings that came back after being fixed. Findings are
+//! tracked by their fingerprint, which does not change when code above them moves, and only runs over the same targets as the
+//! latest run are compared, so scanning a different tree does not look like fixes.
+
+use anyhow::Result;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use tracing::info;
+use crate::commands::analyze_enhanced::Finding;
+use crate::OutputFormatCli;
+
+/// Default location of the trend database, relative to the working directory
+pub const DEFAULT_TRENDS_DB: &str = ".astgrep/trends.db";
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS runs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    recorded_at INTEGER NOT NULL,
+    targets TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS findings (
+    run_id INTEGER NOT NULL REFERENCES runs(id),
+    fingerprint TEXT NOT NULL,
+    rule_id TEXT NOT NULL,
+    severity TEXT NOT NULL,
+    file TEXT NOT NULL,
+    line INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS findings_run ON findings(run_id);
+";
+
+/// Dimension findings are counted by in a trend report
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrendGroup {
+    Severity,
+    Rule,
+    /// Directory of the finding's file
+    Directory,
+}
+
+/// Local store of per-run findings
+pub struct TrendStore {
+    conn: Connection,
+}
+
+/// One finding as stored for a run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RecordedFinding {
+    pub fingerprint: String,
+    pub rule_id: String,
+    pub severity: String,
+    pub file: String,
+    pub line: usize,
+}
+
+impl RecordedFinding {
+    fn from_finding(finding: &Finding, fingerprint: String) -> Self {
+        Self {
+            fingerprint,
+            rule_id: finding.rule_id.clone(),
+            severity: finding.severity.as_str().to_string(),
+            file: finding.location.file.to_string_lossy().to_string(),
+            line: finding.location.start_line,
+        }
+    }
+
+    fn group_key(&self, group: TrendGroup) -> String {
+        match group {
+            TrendGroup::Severity => self.severity.clone(),
+            TrendGroup::Rule => self.rule_id.clone(),
+            TrendGroup::Directory => match Path::new(&self.file).parent() {
+                Some(dir) if !dir.as_os_str().is_empty() => dir.to_string_lossy().to_string(),
+                _ => ".".to_string(),
+            },
+        }
+    }
+}
+
+struct StoredRun {
+    id: i64,
+    recorded_at: i64,
+    findings: Vec<RecordedFinding>,
+}
+
+impl TrendStore {
+    /// Open the database at `path`, creating it and its parent directory if needed
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        let conn = Connection::open(path)?;
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self { conn })
+    }
+
+    /// Append a run over `targets`, recorded now; returns the run id
+    pub fn record_run(&mut self, targets: &[PathBuf], findings: &[Finding]) -> Result<i64> {
+        let fingerprints = crate::commands::analyze_enhanced::fingerprints(findings);
+        let findings: Vec<RecordedFinding> = findings.iter().zip(fingerprints).map(|(f, fingerprint)| RecordedFinding::from_finding(f, fingerprint)).collect();
+        self.record_run_at(chrono::Utc::now().timestamp(), &targets_key(targets), &findings)
+    }
+
+    /// Append a run recorded at `recorded_at` (Unix seconds)
+    pub fn record_run_at(&mut self, recorded_at: i64, targets: &str, findings: &[RecordedFinding]) -> Result<i64> {
+        let tx = self.conn.transaction()?;
+        tx.execute("INSERT INTO runs (recorded_at, targets) VALUES (?1, ?2)", params![recorded_at, targets])?;
+        let run_id = tx.last_insert_rowid();
+        {
+            let mut insert = tx.prepare(
+                "INSERT INTO findings (run_id, fingerprint, rule_id, severity, file, line) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )?;
+            for finding in findings {
+                insert.execute(params![run_id, finding.fingerprint, finding.rule_id, finding.severity, finding.file, finding.line as i64])?;
+            }
+        }
+        tx.commit()?;
+        Ok(run_id)
+    }
+
+    /// Runs over the same targets as the latest run, oldest first
+    fn runs_of_latest_scope(&self) -> Result<Vec<StoredRun>> {
+        let scope: Option<String> = self
+            .conn
+            .query_row("SELECT targets FROM runs ORDER BY recorded_at DESC, id DESC LIMIT 1", [], |row| row.get(0))
+            .optional()?;
+        let scope = match scope {
+            Some(scope) => scope,
+            None => return Ok(Vec::new()),
+        };
+
+        let mut runs: Vec<StoredRun> = self
+            .conn
+            .prepare("SELECT id, recorded_at FROM runs WHERE targets = ?1 ORDER BY recorded_at, id")?
+            .query_map(params![scope], |row| Ok(StoredRun { id: row.get(0)?, recorded_at: row.get(1)?, findings: Vec::new() }))?
+            .collect::<rusqlite::Result<_>>()?;
+        let positions: HashMap<i64, usize> = runs.iter().enumerate().map(|(i, run)| (run.id, i)).collect();
+
+        let mut query = self.conn.prepare(
+            "SELECT f.run_id, f.fingerprint, f.rule_id, f.severity, f.file, f.line
+             FROM findings f JOIN runs r ON r.id = f.run_id WHERE r.targets = ?1",
+        )?;
+        let rows = query.query_map(params![scope], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                RecordedFinding {
+                    fingerprint: row.get(1)?,
+                    rule_id: row.get(2)?,
+                    severity: row.get(3)?,
+                    file: row.get(4)?,
+                    line: row.get::<_, i64>(5)? as usize,
+                },
+            ))
+        })?;
+        for row in rows {
+            let (run_id, finding) = row?;
+            if let Some(&i) = positions.get(&run_id) {
+                runs[i].findings.push(finding);
+            }
+        }
+        Ok(runs)
+    }
+
+    /// Trend report over the latest run's scope; the per-run series covers the last
+    /// `last` runs, resolution and recurrence use the whole history
+    pub fn report(&self, group: TrendGroup, last: usize) -> Result<TrendReport> {
+        Ok(build_report(&self.runs_of_latest_scope()?, group, last))
+    }
+}
+
+/// Stable key of a run's target list
+fn targets_key(targets: &[PathBuf]) -> String {
+    let mut targets: Vec<String> = targets.iter().map(|t| t.to_string_lossy().to_string()).collect();
+    targets.sort();
+    targets.join("\n")
+}
+
+/// Finding counts of one run
+#[derive(Debug, Clone, Serialize)]
+pub struct RunTrend {
+    pub run_id: i64,
+    pub recorded_at: i64,
+    pub total: usize,
+    pub counts: BTreeMap<String, usize>,
+}
+
+/// Trend report over the recorded runs
+#[derive(Debug, Clone, Serialize)]
+pub struct TrendReport {
+    pub runs: Vec<RunTrend>,
+    /// Findings that disappeared from a later run, counted once per fix
+    pub resolved: usize,
+    /// Mean seconds between a finding's appearance and the first run without it
+    pub mean_time_to_fix_secs: Option<i64>,
+    /// Findings of the latest run that were fixed earlier and are back
+    pub recurring: Vec<RecordedFinding>,
+}
+
+fn build_report(runs: &[StoredRun], group: TrendGroup, last: usize) -> TrendReport {
+    let series = runs[runs.len().saturating_sub(last.max(1))..]
+        .iter()
+        .map(|run| {
+            let mut counts = BTreeMap::new();
+            for finding in &run.findings {
+                *counts.entry(finding.group_key(group)).or_insert(0) += 1;
+            }
+            RunTrend { run_id: run.id, recorded_at: run.recorded_at, total: run.findings.len(), counts }
+        })
+        .collect();
+
+    // Open findings with the time they appeared; a fingerprint missing from a run is fixed
+    let mut open: HashMap<&str, i64> = HashMap::new();
+    let mut fixed_before: BTreeSet<&str> = BTreeSet::new();
+    let mut fix_times = Vec::new();
+    let mut recurring = Vec::new();
+
+    for (i, run) in runs.iter().enumerate() {
+        let present: HashMap<&str, &RecordedFinding> = run.findings.iter().map(|f| (f.fingerprint.as_str(), f)).collect();
+        let is_latest = i + 1 == runs.len();
+
+        open.retain(|fingerprint, opened_at| {
+            if present.contains_key(fingerprint) {
+                return true;
+            }
+            fix_times.push(run.recorded_at - *opened_at);
+            fixed_before.insert(*fingerprint);
+            false
+        });
+        for (&fingerprint, &finding) in &present {
+            if open.contains_key(fingerprint) {
+                continue;
+            }
+            if is_latest && fixed_before.contains(fingerprint) {
+                recurring.push(finding.clone());
+            }
+            open.insert(fingerprint, run.recorded_at);
+        }
+    }
+    recurring.sort_by(|a, b| (&a.file, a.line, &a.rule_id).cmp(&(&b.file, b.line, &b.rule_id)));
+
+    TrendReport {
+        runs: series,
+        resolved: fix_times.len(),
+        mean_time_to_fix_secs: if fix_times.is_empty() { None } else { Some(fix_times.iter().sum::<i64>() / fix_times.len() as i64) },
+        recurring,
+    }
+}
+
+/// Report trends from the database at `db`
+pub async fn run(db: PathBuf, group: TrendGroup, last: usize, format: OutputFormatCli) -> Result<()> {
+    if !db.exists() {
+        return Err(anyhow::anyhow!(
+            "Trend database not found: {} (record runs with 'analyze --record')",
+            db.display()
+        ));
+    }
+
+    info!("Reading trend database {}", db.display());
+    let report = TrendStore::open(&db)?.report(group, last)?;
+    let output = match format {
+        OutputFormatCli::Json => serde_json::to_string_pretty(&report)?,
+        _ => format_text_report(&report, group),
+    };
+    println!("{}", output);
+    Ok(())
+}
+
+fn format_text_report(report: &TrendReport, group: TrendGroup) -> String {
+    let mut output = String::new();
+    if report.runs.is_empty() {
+        output.push_str("No runs recorded yet\n");
+        return output;
+    }
+
+    let by = match group {
+        TrendGroup::Severity => "severity",
+        TrendGroup::Rule => "rule",
+        TrendGroup::Directory => "directory",
+    };
+    let keys: BTreeSet<&String> = report.runs.iter().flat_map(|run| run.counts.keys()).collect();
+    output.push_str(&format!("📈 Findings over the last {} run(s) by {}\n", report.runs.len(), by));
+    for run in &report.runs {
+        let recorded = chrono::DateTime::from_timestamp(run.recorded_at, 0)
+            .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_else(|| run.recorded_at.to_string());
+        let counts: Vec<String> = keys
+            .iter()
+            .map(|key| format!("{}={}", key, run.counts.get(*key).copied().unwrap_or(0)))
+            .collect();
+        output.push_str(&format!("  • #{} {}  total={}  {}\n", run.run_id, recorded, run.total, counts.join("  ")));
+    }
+
+    output.push_str(&format!("\n🔧 Resolved findings: {}", report.resolved));
+    if let Some(secs) = report.mean_time_to_fix_secs {
+        output.push_str(&format!(" (mean time to fix: {})", format_duration(secs)));
+    }
+    output.push('\n');
+
+    output.push_str(&format!("🔁 Newly recurring findings: {}\n", report.recurring.len()));
+    for finding in &report.recurring {
+        output.push_str(&format!("  • [{}] {} at {}:{}\n", finding.severity, finding.rule_id, finding.file, finding.line));
+    }
+    output
+}
+
+/// `secs` as days and hours, or hours and minutes below a day
+fn format_duration(secs: i64) -> String {
+    let (days, hours, minutes) = (secs / 86_400, secs % 86_400 / 3_600, secs % 3_600 / 60);
+    if days > 0 {
+        format!("{}d {}h", days, hours)
+    } else {
+        format!("{}h {}m", hours, minutes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn finding(fingerprint: &str, rule_id: &str, severity: &str, file: &str) -> RecordedFinding {
+        RecordedFinding {
+            fingerprint: fingerprint.to_string(),
+            rule_id: rule_id.to_string(),
+            severity: severity.to_string(),
+            file: file.to_string(),
+            line: 1,
+        }
+    }
+
+    #[test]
+    fn test_trend_report() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = TrendStore::open(&dir.path().join(".astgrep/trends.db")).unwrap();
+        let sqli = finding("a", "sqli", "ERROR", "src/db/user.py");
+        let debug = finding("b", "debug", "INFO", "src/app.py");
+        let eval = finding("c", "eval", "ERROR", "src/app.py");
+
+        const HOUR: i64 = 3_600;
+        store.record_run_at(0, "src", &[sqli.clone(), debug.clone()]).unwrap();
+        store.record_run_at(10 * HOUR, "src", &[debug.clone(), eval.clone()]).unwrap();
+        // A run over other targets is not part of the history
+        store.record_run_at(15 * HOUR, "other", &[]).unwrap();
+        store.record_run_at(20 * HOUR, "src", std::slice::from_ref(&eval)).unwrap();
+        store.record_run_at(30 * HOUR, "src", &[sqli.clone(), eval.clone()]).unwrap();
+
+        let report = store.report(TrendGroup::Severity, 2).unwrap();
+        assert_eq!(report.runs.len(), 2);
+        assert_eq!(report.runs[0].counts.get("ERROR"), Some(&1));
+        assert_eq!(report.runs[1].total, 2);
+        // sqli fixed after 10h, debug after 20h
+        assert_eq!(report.resolved, 2);
+        assert_eq!(report.mean_time_to_fix_secs, Some(15 * HOUR));
+        assert_eq!(report.recurring, vec![sqli]);
+
+        let by_dir = store.report(TrendGroup::Directory, 10).unwrap();
+        assert_eq!(by_dir.runs.len(), 4);
+        assert_eq!(by_dir.runs[0].counts.get("src/db"), Some(&1));
+        assert_eq!(by_dir.runs[0].counts.get("src"), Some(&1));
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(90 * 60), "1h 30m");
+        assert_eq!(format_duration(2 * 86_400 + 4 * 3_600), "2d 4h");
+    }
+}
@@ -0,0 +1,108 @@
+//! Local triage state
+//!
+//! Triage decisions made in the interactive browser are kept per finding fingerprint in
+//! a JSON file, so they survive re-runs and edits that move the finding.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Default location of the triage file, relative to the working directory
+pub const DEFAULT_TRIAGE_FILE: &str = ".astgrep/triage.json";
+
+/// Triage decision on a finding
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TriageState {
+    #[default]
+    Open,
+    Confirmed,
+    FalsePositive,
+    Ignored,
+}
+
+impl TriageState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TriageState::Open => "open",
+            TriageState::Confirmed => "confirmed",
+            TriageState::FalsePositive => "false-positive",
+            TriageState::Ignored => "ignored",
+        }
+    }
+
+    /// One-character marker shown next to findings
+    pub fn marker(&self) -> char {
+        match self {
+            TriageState::Open => ' ',
+            TriageState::Confirmed => '!',
+            TriageState::FalsePositive => 'F',
+            TriageState::Ignored => '-',
+        }
+    }
+}
+
+/// Triage states by finding fingerprint, backed by a JSON file
+#[derive(Debug)]
+pub struct TriageStore {
+    path: PathBuf,
+    states: BTreeMap<String, TriageState>,
+}
+
+impl TriageStore {
+    /// Load the store at `path`; a missing file is an empty store
+    pub fn load(path: &Path) -> Result<Self> {
+        let states = match std::fs::read_to_string(path) {
+            Ok(text) => serde_json::from_str(&text)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Self { path: path.to_path_buf(), states })
+    }
+
+    pub fn state(&self, fingerprint: &str) -> TriageState {
+        self.states.get(fingerprint).copied().unwrap_or_default()
+    }
+
+    pub fn set(&mut self, fingerprint: &str, state: TriageState) {
+        if state == TriageState::Open {
+            self.states.remove(fingerprint);
+        } else {
+            self.states.insert(fingerprint.to_string(), state);
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&self.path, serde_json::to_string_pretty(&self.states)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_triage_store_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".astgrep").join("triage.json");
+
+        let mut store = TriageStore::load(&path).unwrap();
+        assert_eq!(store.state("a"), TriageState::Open);
+        store.set("a", TriageState::FalsePositive);
+        store.set("b", TriageState::Confirmed);
+        store.set("b", TriageState::Open);
+        store.set("c", TriageState::Ignored);
+        store.save().unwrap();
+
+        let store = TriageStore::load(&path).unwrap();
+        assert_eq!(store.state("a"), TriageState::FalsePositive);
+        assert_eq!(store.state("b"), TriageState::Open);
+        assert_eq!(store.state("c"), TriageState::Ignored);
+        assert!(std::fs::read_to_string(&path).unwrap().contains("\"false-positive\""));
+    }
+}
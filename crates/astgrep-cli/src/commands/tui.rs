@@ -0,0 +1,494 @@
+//! Interactive results browser
+//!
+//! `analyze --interactive` opens the findings in a terminal UI: findings grouped by file
+//! or rule, a highlighted preview of the surrounding source, triage marks kept in the
+//! triage file, opening `$EDITOR` at a finding and applying its fix in place.
+
+use anyhow::Result;
+use astgrep_core::{Language, Severity};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::execute;
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen};
+use ratatui::backend::CrosstermBackend;
+use ratatui::layout::{Constraint, Direction, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph};
+use ratatui::{Frame, Terminal};
+use std::collections::HashMap;
+use std::io::Stdout;
+use std::path::{Path, PathBuf};
+use crate::commands::analyze_enhanced::{determine_language, Finding};
+use crate::commands::patches::{apply_edits, line_edit};
+use crate::commands::triage::{TriageState, TriageStore};
+
+type Tui = Terminal<CrosstermBackend<Stdout>>;
+
+/// Source lines shown above a finding in the preview
+const PREVIEW_CONTEXT: usize = 6;
+
+/// Rows moved by page up/down
+const PAGE: isize = 10;
+
+const HELP: &str = "j/k move  g group  enter open  c confirm  f false-positive  i ignore  o reopen  x fix  h hide triaged  q quit";
+
+/// Keywords highlighted in the preview, shared by all languages
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "break", "case", "catch", "class", "const", "continue", "def",
+    "default", "defer", "do", "elif", "else", "end", "enum", "except", "export", "extends", "false",
+    "False", "fi", "final", "finally", "fn", "for", "from", "func", "function", "if", "implements",
+    "import", "in", "interface", "let", "new", "nil", "None", "null", "package", "private", "protected",
+    "public", "raise", "return", "self", "static", "struct", "super", "switch", "then", "this", "throw",
+    "throws", "true", "True", "try", "val", "var", "void", "while", "with", "yield",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Grouping {
+    File,
+    Rule,
+}
+
+enum Row {
+    Header(String, usize),
+    Finding(usize),
+}
+
+struct App {
+    findings: Vec<Finding>,
+    triage: TriageStore,
+    grouping: Grouping,
+    hide_triaged: bool,
+    rows: Vec<Row>,
+    selected: usize,
+    sources: HashMap<PathBuf, Option<String>>,
+    status: String,
+}
+
+/// Browse `findings` until the user quits; triage marks are saved to `triage_file`
+pub fn browse(findings: Vec<Finding>, triage_file: &Path) -> Result<()> {
+    if findings.is_empty() {
+        println!("No findings");
+        return Ok(());
+    }
+
+    let mut app = App::new(findings, TriageStore::load(triage_file)?);
+    let mut terminal = enter_terminal()?;
+    let result = app.run(&mut terminal);
+    leave_terminal(&mut terminal)?;
+    result
+}
+
+fn enter_terminal() -> Result<Tui> {
+    enable_raw_mode()?;
+    let mut stdout = std::io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    Ok(Terminal::new(CrosstermBackend::new(stdout))?)
+}
+
+fn leave_terminal(terminal: &mut Tui) -> Result<()> {
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    terminal.show_cursor()?;
+    Ok(())
+}
+
+impl App {
+    fn new(findings: Vec<Finding>, triage: TriageStore) -> Self {
+        let mut app = Self {
+            findings,
+            triage,
+            grouping: Grouping::File,
+            hide_triaged: false,
+            rows: Vec::new(),
+            selected: 0,
+            sources: HashMap::new(),
+            status: HELP.to_string(),
+        };
+        app.rebuild(None);
+        app
+    }
+
+    fn run(&mut self, terminal: &mut Tui) -> Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+
+            let key = match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => key,
+                _ => continue,
+            };
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Down | KeyCode::Char('j') => self.move_by(1),
+                KeyCode::Up | KeyCode::Char('k') => self.move_by(-1),
+                KeyCode::PageDown => self.move_by(PAGE),
+                KeyCode::PageUp => self.move_by(-PAGE),
+                KeyCode::Char('g') => {
+                    self.grouping = match self.grouping {
+                        Grouping::File => Grouping::Rule,
+                        Grouping::Rule => Grouping::File,
+                    };
+                    self.rebuild(self.selected_finding());
+                }
+                KeyCode::Char('h') => {
+                    self.hide_triaged = !self.hide_triaged;
+                    self.rebuild(self.selected_finding());
+                }
+                KeyCode::Char('c') => self.mark(TriageState::Confirmed),
+                KeyCode::Char('f') => self.mark(TriageState::FalsePositive),
+                KeyCode::Char('i') => self.mark(TriageState::Ignored),
+                KeyCode::Char('o') => self.mark(TriageState::Open),
+                KeyCode::Char('x') => self.apply_fix(),
+                KeyCode::Enter | KeyCode::Char('e') => self.open_in_editor(terminal)?,
+                _ => {}
+            }
+        }
+    }
+
+    fn selected_finding(&self) -> Option<usize> {
+        match self.rows.get(self.selected) {
+            Some(Row::Finding(i)) => Some(*i),
+            _ => None,
+        }
+    }
+
+    fn group_key(&self, finding: &Finding) -> String {
+        match self.grouping {
+            Grouping::File => finding.location.file.to_string_lossy().to_string(),
+            Grouping::Rule => finding.rule_id.clone(),
+        }
+    }
+
+    /// Recompute the rows, keeping `keep` selected when it is still listed
+    fn rebuild(&mut self, keep: Option<usize>) {
+        let mut visible: Vec<usize> = (0..self.findings.len())
+            .filter(|&i| !self.hide_triaged || self.triage.state(&self.findings[i].stable_id()) == TriageState::Open)
+            .collect();
+        visible.sort_by_key(|&i| {
+            let f = &self.findings[i];
+            (self.group_key(f), f.location.file.clone(), f.location.start_line, f.location.start_column)
+        });
+
+        let previous = self.selected;
+        self.rows.clear();
+        let mut start = 0;
+        while start < visible.len() {
+            let key = self.group_key(&self.findings[visible[start]]);
+            let end = visible[start..].iter().position(|&i| self.group_key(&self.findings[i]) != key).map_or(visible.len(), |n| start + n);
+            self.rows.push(Row::Header(key, end - start));
+            self.rows.extend(visible[start..end].iter().map(|&i| Row::Finding(i)));
+            start = end;
+        }
+
+        self.selected = keep
+            .and_then(|keep| self.rows.iter().position(|row| matches!(row, Row::Finding(i) if *i == keep)))
+            .unwrap_or_else(|| previous.min(self.rows.len().saturating_sub(1)));
+        if self.selected_finding().is_none() {
+            self.move_by(1);
+        }
+    }
+
+    /// Move the selection by `delta` finding rows, skipping group headers
+    fn move_by(&mut self, delta: isize) {
+        let step = if delta < 0 { -1 } else { 1 };
+        let mut remaining = delta.abs();
+        let mut position = self.selected as isize;
+        let mut candidate = position;
+        while remaining > 0 {
+            candidate += step;
+            if candidate < 0 || candidate >= self.rows.len() as isize {
+                break;
+            }
+            if let Row::Finding(_) = self.rows[candidate as usize] {
+                position = candidate;
+                remaining -= 1;
+            }
+        }
+        self.selected = position as usize;
+    }
+
+    fn mark(&mut self, state: TriageState) {
+        let Some(i) = self.selected_finding() else { return };
+        self.triage.set(&self.findings[i].stable_id(), state);
+        self.status = match self.triage.save() {
+            Ok(()) => format!("Marked {} as {}", self.findings[i].rule_id, state.as_str()),
+            Err(e) => format!("Failed to save triage state: {}", e),
+        };
+        if self.hide_triaged && state != TriageState::Open {
+            self.rebuild(None);
+        } else {
+            self.move_by(1);
+        }
+    }
+
+    /// Write the selected finding's fix to its file and drop the finding; later findings
+    /// in the same file move with the edit
+    fn apply_fix(&mut self) {
+        let Some(i) = self.selected_finding() else { return };
+        let file = self.findings[i].location.file.clone();
+        let source = match std::fs::read_to_string(&file) {
+            Ok(source) => source,
+            Err(e) => {
+                self.status = format!("Cannot read {}: {}", file.display(), e);
+                return;
+            }
+        };
+        let Some(edit) = line_edit(&self.findings[i], &source) else {
+            self.status = format!("{} has no applicable fix", self.findings[i].rule_id);
+            return;
+        };
+        if let Err(e) = std::fs::write(&file, apply_edits(&source, std::slice::from_ref(&edit))) {
+            self.status = format!("Cannot write {}: {}", file.display(), e);
+            return;
+        }
+
+        let fixed = self.findings.remove(i);
+        self.triage.set(&fixed.stable_id(), TriageState::Open);
+        // Fingerprints do not depend on lines, so the triage states of the moved findings stay
+        let delta = edit.replacement.len() as isize - (edit.end_line - edit.start_line + 1) as isize;
+        for other in self.findings.iter_mut().filter(|f| f.location.file == file && f.location.start_line > edit.end_line) {
+            other.location.start_line = (other.location.start_line as isize + delta) as usize;
+            other.location.end_line = (other.location.end_line as isize + delta) as usize;
+        }
+        self.sources.remove(&file);
+        self.status = match self.triage.save() {
+            Ok(()) => format!("Applied fix for {} at {}:{}", fixed.rule_id, file.display(), edit.start_line),
+            Err(e) => format!("Applied fix, but failed to save triage state: {}", e),
+        };
+        self.rebuild(None);
+    }
+
+    /// Suspend the UI and open the selected finding in `$VISUAL`/`$EDITOR` (default vi)
+    fn open_in_editor(&mut self, terminal: &mut Tui) -> Result<()> {
+        let Some(i) = self.selected_finding() else { return Ok(()) };
+        let location = self.findings[i].location.clone();
+        let editor = std::env::var("VISUAL").or_else(|_| std::env::var("EDITOR")).unwrap_or_else(|_| "vi".to_string());
+        let mut words = editor.split_whitespace();
+        let Some(program) = words.next() else { return Ok(()) };
+
+        let mut command = std::process::Command::new(program);
+        command.args(words);
+        let name = Path::new(program).file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        let position = format!("{}:{}:{}", location.file.display(), location.start_line, location.start_column);
+        if name.starts_with("code") {
+            command.arg("--goto").arg(position);
+        } else if name.starts_with("subl") || name.starts_with("zed") {
+            command.arg(position);
+        } else {
+            command.arg(format!("+{}", location.start_line)).arg(&location.file);
+        }
+
+        leave_terminal(terminal)?;
+        let status = command.status();
+        *terminal = enter_terminal()?;
+        terminal.clear()?;
+
+        self.sources.remove(&location.file);
+        self.status = match status {
+            Ok(status) if status.success() => HELP.to_string(),
+            Ok(status) => format!("{} exited with {}", program, status),
+            Err(e) => format!("Cannot run {}: {}", program, e),
+        };
+        Ok(())
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let screen = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(3), Constraint::Length(1)])
+            .split(frame.size());
+        let panes = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(45), Constraint::Percentage(55)])
+            .split(screen[0]);
+
+        let items: Vec<ListItem> = self.rows.iter().map(|row| self.list_item(row)).collect();
+        let title = match self.grouping {
+            Grouping::File => " Findings by file ",
+            Grouping::Rule => " Findings by rule ",
+        };
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        let mut state = ListState::default();
+        state.select(Some(self.selected));
+        frame.render_stateful_widget(list, panes[0], &mut state);
+
+        let preview = self.preview(panes[1].height.saturating_sub(2) as usize);
+        frame.render_widget(Paragraph::new(preview).block(Block::default().borders(Borders::ALL).title(" Preview ")), panes[1]);
+        frame.render_widget(Paragraph::new(self.status.as_str()).style(Style::default().fg(Color::DarkGray)), screen[1]);
+    }
+
+    fn list_item(&self, row: &Row) -> ListItem<'static> {
+        match row {
+            Row::Header(title, count) => ListItem::new(Line::from(Span::styled(
+                format!("{} ({})", title, count),
+                Style::default().add_modifier(Modifier::BOLD),
+            ))),
+            Row::Finding(i) => {
+                let finding = &self.findings[*i];
+                let state = self.triage.state(&finding.stable_id());
+                let detail = match self.grouping {
+                    Grouping::File => format!("{}:{} {}", finding.location.start_line, finding.location.start_column, finding.rule_id),
+                    Grouping::Rule => format!("{}:{}", finding.location.file.display(), finding.location.start_line),
+                };
+                ListItem::new(Line::from(vec![
+                    Span::raw(format!("  {} ", state.marker())),
+                    Span::styled(format!("{:<8} ", finding.severity.as_str()), severity_style(finding.severity)),
+                    Span::raw(detail),
+                ]))
+            }
+        }
+    }
+
+    fn preview(&mut self, height: usize) -> Vec<Line<'static>> {
+        let Some(i) = self.selected_finding() else { return vec![Line::from("No findings")] };
+        let finding = self.findings[i].clone();
+        let state = self.triage.state(&finding.stable_id());
+        let location = &finding.location;
+
+        let mut lines = vec![
+            Line::from(vec![
+                Span::styled(finding.rule_id.clone(), Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw("  "),
+                Span::styled(finding.severity.as_str(), severity_style(finding.severity)),
+                Span::raw(format!(" / {}  [{}]", finding.confidence.as_str(), state.as_str())),
+            ]),
+            Line::from(finding.message.clone()),
+            Line::from(format!("{}:{}:{}", location.file.display(), location.start_line, location.start_column)),
+        ];
+        if let Some(ref fix) = finding.fix {
+            lines.push(Line::from(vec![
+                Span::styled("fix: ", Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)),
+                Span::raw(fix.clone()),
+            ]));
+        }
+        lines.push(Line::from(""));
+
+        let language = determine_language(&location.file).ok();
+        let source = self
+            .sources
+            .entry(location.file.clone())
+            .or_insert_with(|| std::fs::read_to_string(&location.file).ok());
+        let Some(source) = source.as_deref() else {
+            lines.push(Line::from(Span::styled("(source not available)", Style::default().fg(Color::DarkGray))));
+            return lines;
+        };
+
+        let source_lines: Vec<&str> = source.lines().collect();
+        let first = location.start_line.saturating_sub(PREVIEW_CONTEXT).max(1);
+        let last = (first + height.saturating_sub(lines.len())).min(source_lines.len() + 1);
+        for number in first..last {
+            let matched = (location.start_line..=location.end_line).contains(&number);
+            let text = match finding.redaction {
+                Some(redaction) if matched => redaction.apply(source_lines[number - 1]),
+                _ => source_lines[number - 1].to_string(),
+            };
+            let gutter = if matched { Style::default().fg(Color::Yellow) } else { Style::default().fg(Color::DarkGray) };
+            let mut spans = vec![Span::styled(format!("{:>5} ", number), gutter)];
+            spans.extend(highlight(&text, language));
+            let mut line = Line::from(spans);
+            if matched {
+                line.style = Style::default().bg(Color::Rgb(48, 48, 24));
+            }
+            lines.push(line);
+        }
+        lines
+    }
+}
+
+fn severity_style(severity: Severity) -> Style {
+    let color = match severity {
+        Severity::Critical => Color::Magenta,
+        Severity::Error => Color::Red,
+        Severity::Warning => Color::Yellow,
+        Severity::Info => Color::Blue,
+    };
+    Style::default().fg(color)
+}
+
+/// Line comment marker of a language
+fn comment_marker(language: Option<Language>) -> &'static str {
+    match language {
+        Some(Language::Python) | Some(Language::Ruby) | Some(Language::Bash) => "#",
+        Some(Language::Sql) => "--",
+        _ => "//",
+    }
+}
+
+/// Split a source line into styled spans: keywords, string literals, numbers and comments
+fn highlight(line: &str, language: Option<Language>) -> Vec<Span<'static>> {
+    let comment = comment_marker(language);
+    let mut spans = Vec::new();
+    let mut plain = String::new();
+    let mut rest = line;
+
+    while let Some(c) = rest.chars().next() {
+        if rest.starts_with(comment) {
+            break;
+        }
+        let (token, style) = if c == '"' || c == '\'' || c == '`' {
+            let mut escaped = false;
+            let end = rest[1..]
+                .char_indices()
+                .find(|&(_, ch)| {
+                    let closes = ch == c && !escaped;
+                    escaped = ch == '\\' && !escaped;
+                    closes
+                })
+                .map_or(rest.len(), |(i, _)| i + 2);
+            (&rest[..end], Some(Style::default().fg(Color::Green)))
+        } else if c.is_alphanumeric() || c == '_' {
+            let end = rest.find(|ch: char| !(ch.is_alphanumeric() || ch == '_')).unwrap_or(rest.len());
+            let word = &rest[..end];
+            let style = if KEYWORDS.contains(&word) {
+                Some(Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD))
+            } else if c.is_ascii_digit() {
+                Some(Style::default().fg(Color::Cyan))
+            } else {
+                None
+            };
+            (word, style)
+        } else {
+            (&rest[..c.len_utf8()], None)
+        };
+
+        match style {
+            Some(style) => {
+                if !plain.is_empty() {
+                    spans.push(Span::raw(std::mem::take(&mut plain)));
+                }
+                spans.push(Span::styled(token.to_string(), style));
+            }
+            None => plain.push_str(token),
+        }
+        rest = &rest[token.len()..];
+    }
+
+    if !plain.is_empty() {
+        spans.push(Span::raw(plain));
+    }
+    if !rest.is_empty() {
+        spans.push(Span::styled(rest.to_string(), Style::default().fg(Color::DarkGray)));
+    }
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(spans: &[Span]) -> Vec<String> {
+        spans.iter().map(|s| s.content.to_string()).collect()
+    }
+
+    #[test]
+    fn test_highlight() {
+        let spans = highlight("if x == \"a\\\"b\": return 42  # done", Some(Language::Python));
+        assert_eq!(texts(&spans), vec!["if", " x == ", "\"a\\\"b\"", ": ", "return", " ", "42", "  ", "# done"]);
+        assert_eq!(spans[0].style.fg, Some(Color::Magenta));
+        assert_eq!(spans[8].style.fg, Some(Color::DarkGray));
+
+        let spans = highlight("String s = \"// not a comment\"; // comment", Some(Language::Java));
+        assert_eq!(texts(&spans), vec!["String s = ", "\"// not a comment\"", "; ", "// comment"]);
+    }
+}
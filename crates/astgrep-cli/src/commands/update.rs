@@ -58,9 +58,8 @@ pub async fn run(
 }
 
 async fn clone_repository(repo_url: &str, directory: &PathBuf) -> Result<()> {
-    use std::process::Command;
-
-    let output = Command::new("git")
+    let token = crate::commands::login::token_for(repo_url);
+    let output = authenticated_git(token.as_deref())
         .args(&["clone", repo_url, &directory.to_string_lossy()])
         .output()?;
 
@@ -76,6 +75,12 @@ async fn clone_repository(repo_url: &str, directory: &PathBuf) -> Result<()> {
 async fn update_existing_repository(directory: &PathBuf) -> Result<()> {
     use std::process::Command;
 
+    let origin_output = Command::new("git")
+        .args(["remote", "get-url", "origin"])
+        .current_dir(directory)
+        .output()?;
+    let token = crate::commands::login::token_for(String::from_utf8_lossy(&origin_output.stdout).trim());
+
     // Check if there are local changes
     let status_output = Command::new("git")
         .args(&["status", "--porcelain"])
@@ -100,7 +105,7 @@ async fn update_existing_repository(directory: &PathBuf) -> Result<()> {
     }
 
     // Pull latest changes
-    let pull_output = Command::new("git")
+    let pull_output = authenticated_git(token.as_deref())
         .args(&["pull", "origin", "main"])
         .current_dir(directory)
         .output()?;
@@ -109,7 +114,7 @@ async fn update_existing_repository(directory: &PathBuf) -> Result<()> {
         let stderr = String::from_utf8_lossy(&pull_output.stderr);
         
         // Try with master branch if main fails
-        let pull_master_output = Command::new("git")
+        let pull_master_output = authenticated_git(token.as_deref())
             .args(&["pull", "origin", "master"])
             .current_dir(directory)
             .output()?;
@@ -127,6 +132,18 @@ async fn update_existing_repository(directory: &PathBuf) -> Result<()> {
     Ok(())
 }
 
+/// A git command sending `token` as a bearer token over HTTP; it is passed through the
+/// environment rather than `-c` to keep it out of the process list
+fn authenticated_git(token: Option<&str>) -> std::process::Command {
+    let mut git = std::process::Command::new("git");
+    if let Some(token) = token {
+        git.env("GIT_CONFIG_COUNT", "1")
+            .env("GIT_CONFIG_KEY_0", "http.extraHeader")
+            .env("GIT_CONFIG_VALUE_0", format!("Authorization: Bearer {}", token));
+    }
+    git
+}
+
 fn is_git_repository(directory: &PathBuf) -> Result<bool> {
     let git_dir = directory.join(".git");
     Ok(git_dir.exists())
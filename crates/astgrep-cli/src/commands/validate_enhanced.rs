@@ -1,9 +1,11 @@
 //! Enhanced validate command with detailed analysis
 
 use anyhow::Result;
-use std::path::PathBuf;
+use astgrep_rules::RuleEngine;
+use std::path::{Path, PathBuf};
 use std::time::Instant;
 use tracing::info;
+use crate::commands::rules::count_matches;
 use crate::OutputFormatCli;
 
 // Simplified types for demonstration
@@ -117,6 +119,26 @@ async fn validate_rule_file(
                     result.total_rules = 1; // Simplified
                     result.valid_rules = 1;
 
+                    // Report rules this engine would skip at load time, and run the
+                    // embedded examples of the others
+                    if let Ok(rules) = astgrep_rules::RuleParser::new().parse_yaml(&content) {
+                        let validator = astgrep_rules::RuleValidator::new();
+                        result.total_rules = rules.len();
+                        result.valid_rules = rules.len();
+                        for rule in &rules {
+                            if let Err(e) = validator.check_capabilities(rule) {
+                                result.warnings.push(format!("{} (rule will be skipped)", e));
+                                continue;
+                            }
+                            let failures = check_examples(rule);
+                            if !failures.is_empty() {
+                                result.valid_rules -= 1;
+                                result.invalid_rules += 1;
+                                result.errors.extend(failures);
+                            }
+                        }
+                    }
+
                     if check_performance {
                         result.performance_metrics = Some(PerformanceMetrics {
                             load_time_ms: load_time.as_millis() as u64,
@@ -139,6 +161,49 @@ async fn validate_rule_file(
     Ok(result)
 }
 
+/// Run the `examples:` of `rule` against it; one message per failing example
+fn check_examples(rule: &astgrep_rules::Rule) -> Vec<String> {
+    if rule.examples.is_empty() {
+        return Vec::new();
+    }
+
+    let mut engine = RuleEngine::new();
+    if let Err(e) = engine.add_rule(rule.clone()) {
+        return vec![format!("Rule '{}' examples could not run: {}", rule.id, e)];
+    }
+
+    let mut failures = Vec::new();
+    for (i, example) in rule.examples.iter().enumerate() {
+        let language = match example.language.or_else(|| rule.languages.first().copied()) {
+            Some(language) if rule.languages.contains(&language) => language,
+            Some(language) => {
+                failures.push(format!(
+                    "Rule '{}' example {} is {} code, which the rule does not cover",
+                    rule.id, i + 1, language.as_str()
+                ));
+                continue;
+            }
+            None => continue,
+        };
+
+        let file = format!("example{}", language.extensions().first().copied().unwrap_or(""));
+        match count_matches(&mut engine, Path::new(&file), &example.code, language) {
+            Ok(counts) => {
+                let matched = counts.contains_key(&rule.id);
+                if matched != example.should_match {
+                    let expectation = if example.should_match { "should match but did not" } else { "should not match but did" };
+                    failures.push(format!(
+                        "Rule '{}' example {} {}: {}",
+                        rule.id, i + 1, expectation, example.code.trim()
+                    ));
+                }
+            }
+            Err(e) => failures.push(format!("Rule '{}' example {} failed to run: {}", rule.id, i + 1, e)),
+        }
+    }
+    failures
+}
+
 /// Recursively collect all rule files from a directory
 fn collect_rule_files_from_directory(dir: &PathBuf) -> Result<Vec<PathBuf>> {
     let mut rule_files = Vec::new();
@@ -376,6 +441,64 @@ mod tests {
         assert!(!is_valid_rule_id("a-"));
     }
 
+    #[tokio::test]
+    async fn test_validate_reports_unsupported_capabilities() {
+        let dir = tempfile::tempdir().unwrap();
+        let rule_file = dir.path().join("rules.yaml");
+        std::fs::write(&rule_file, r#"
+rules:
+  - id: ts-query-rule
+    message: Needs tree-sitter queries
+    severity: WARNING
+    languages: [java]
+    requires: [ts-query]
+    pattern: "foo($X)"
+"#).unwrap();
+
+        let result = validate_rule_file(&rule_file, &None, false).await.unwrap();
+        assert!(result.errors.is_empty());
+        assert_eq!(result.warnings.len(), 1);
+        assert!(result.warnings[0].contains("ts-query"));
+    }
+
+    #[tokio::test]
+    async fn test_validate_runs_rule_examples() {
+        let dir = tempfile::tempdir().unwrap();
+        let rule_file = dir.path().join("rules.yaml");
+        std::fs::write(&rule_file, r#"
+rules:
+  - id: os-system
+    message: Command execution via os.system
+    severity: ERROR
+    languages: [python]
+    pattern: "os.system(...)"
+    examples:
+      positive:
+        - "import os\nos.system(cmd)\n"
+      negative:
+        - "print(cmd)\n"
+  - id: dishonest
+    message: Claims to match what it does not
+    severity: ERROR
+    languages: [python]
+    pattern: "os.system(...)"
+    examples:
+      - code: "print(cmd)\n"
+        match: true
+      - code: "os.system(cmd)\n"
+        match: false
+"#).unwrap();
+
+        let result = validate_rule_file(&rule_file, &None, false).await.unwrap();
+        assert_eq!(result.total_rules, 2);
+        assert_eq!(result.valid_rules, 1);
+        assert_eq!(result.invalid_rules, 1);
+        assert_eq!(result.errors.len(), 2, "{:?}", result.errors);
+        assert!(result.errors.iter().all(|e| e.starts_with("Rule 'dishonest'")));
+        assert!(result.errors[0].contains("should match but did not"));
+        assert!(result.errors[1].contains("should not match but did"));
+    }
+
     #[test]
     fn test_basic_validation() {
         // Basic test that doesn't rely on missing functions
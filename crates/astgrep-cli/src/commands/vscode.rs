@@ -0,0 +1,361 @@
+//! Live diagnostics for VS Code
+//!
+//! `astgrep vscode` is the long-running backend of the VS Code extension. It analyzes the
+//! files of a workspace, then polls them for changes and re-analyzes the ones that changed,
+//! sending the diagnostics of a file whenever they change. Diagnostics are those of
+//! [`VsCodeDiagnostic::to_vscode_format`]: the other findings of the same rule in a file
+//! are its related information, and a rule's fix comes with the edit of a quick fix.
+//!
+//! Messages are JSON objects, one per line, over stdio or, with `--listen`, over a TCP
+//! connection from the extension. The server sends:
+//!
+//! - `{"type": "diagnostics", "uri", "file", "diagnostics"}` when the diagnostics of a file
+//!   change, with an empty list once they are gone;
+//! - `{"type": "ready", "files"}` when the first scan of the workspace is done.
+//!
+//! The extension sends:
+//!
+//! - `{"type": "update", "file", "text"}` to analyze an unsaved buffer instead of the file;
+//! - `{"type": "revert", "file"}` to go back to the file on disk;
+//! - `{"type": "configure", "config"}` with the extension settings of [`VsCodeConfig`];
+//! - `{"type": "shutdown"}` to stop the server, which also stops when the input ends.
+
+use anyhow::Result;
+use astgrep_rules::RuleRepository;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration, Instant, SystemTime};
+use tracing::{debug, info, warn};
+use crate::commands::analyze_enhanced::{analyze_source, collect_target_files, Finding};
+use crate::commands::autofix::render_fix;
+use crate::commands::lsp::vscode_diagnostic;
+use crate::vscode_integration::{file_uri, VsCodeConfig, VsCodeDiagnostic, VsCodeExtension, VsCodeRelatedInformation, VsCodeTextEdit};
+use crate::EnhancedAnalysisConfig;
+
+/// Most other locations of the same rule listed as related information of a diagnostic
+const MAX_RELATED: usize = 10;
+
+/// Diagnostics of a workspace as last sent to the extension
+pub struct DiagnosticsSession {
+    config: EnhancedAnalysisConfig,
+    repository: RuleRepository,
+    extension: VsCodeExtension,
+    /// Modification times of the files last analyzed from disk
+    modified: HashMap<PathBuf, SystemTime>,
+    /// Unsaved editor buffers, analyzed instead of their files
+    buffers: HashMap<PathBuf, String>,
+    shut_down: bool,
+}
+
+impl DiagnosticsSession {
+    pub fn new(config: EnhancedAnalysisConfig) -> Self {
+        let repository = RuleRepository::from_paths(&config.rule_files);
+        Self {
+            config,
+            repository,
+            extension: VsCodeExtension::new(),
+            modified: HashMap::new(),
+            buffers: HashMap::new(),
+            shut_down: false,
+        }
+    }
+
+    /// Whether the extension asked the server to stop
+    pub fn is_shut_down(&self) -> bool {
+        self.shut_down
+    }
+
+    /// Re-analyze the files that were added or changed since the last poll and clear
+    /// those that are gone; returns the messages for the files whose diagnostics changed
+    pub async fn poll(&mut self) -> Result<Vec<Value>> {
+        let mut skipped = Vec::new();
+        let files = collect_target_files(&self.config, &mut skipped).await?;
+        let files: Vec<PathBuf> = files.into_iter().filter(|file| self.extension.should_analyze_file(&file.to_string_lossy())).collect();
+
+        let mut messages = Vec::new();
+        let gone: Vec<PathBuf> = self.modified.keys().filter(|file| !files.contains(file)).cloned().collect();
+        for file in gone {
+            self.modified.remove(&file);
+            messages.extend(self.update(&file, Vec::new()));
+        }
+        for file in files {
+            let Ok(modified) = std::fs::metadata(&file).and_then(|m| m.modified()) else {
+                continue;
+            };
+            if self.buffers.contains_key(&file) || self.modified.get(&file) == Some(&modified) {
+                continue;
+            }
+            self.modified.insert(file.clone(), modified);
+            match std::fs::read_to_string(&file) {
+                Ok(text) => messages.extend(self.analyze(&file, &text)),
+                Err(e) => debug!("Cannot read {}: {}", file.display(), e),
+            }
+        }
+        Ok(messages)
+    }
+
+    /// The messages answering a message of the extension
+    pub fn handle(&mut self, message: &Value) -> Vec<Value> {
+        let file = message["file"].as_str().map(PathBuf::from);
+        match (message["type"].as_str(), file) {
+            (Some("update"), Some(file)) => {
+                let text = message["text"].as_str().unwrap_or_default().to_string();
+                let message = self.analyze(&file, &text);
+                self.buffers.insert(file, text);
+                message.into_iter().collect()
+            }
+            (Some("revert"), Some(file)) => {
+                self.buffers.remove(&file);
+                // Analyzed from disk again on the next poll
+                self.modified.remove(&file);
+                Vec::new()
+            }
+            (Some("configure"), _) => match serde_json::from_value::<VsCodeConfig>(message["config"].clone()) {
+                Ok(config) => {
+                    self.extension.update_config(config);
+                    // What is shown may change without the diagnostics changing
+                    let files: Vec<String> = self.extension.get_all_diagnostics().keys().cloned().collect();
+                    files.iter().map(|file| self.publish(file)).collect()
+                }
+                Err(e) => {
+                    warn!("Ignoring invalid configuration: {}", e);
+                    Vec::new()
+                }
+            },
+            (Some("shutdown"), _) => {
+                self.shut_down = true;
+                Vec::new()
+            }
+            _ => {
+                debug!("Ignoring message {}", message);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Analyze `text` as the content of `file`; the message for the file when its
+    /// diagnostics changed. A failed analysis keeps the previous diagnostics.
+    fn analyze(&mut self, file: &PathBuf, text: &str) -> Option<Value> {
+        match analyze_source(file, text, &self.config) {
+            Ok(findings) => {
+                let diagnostics = diagnostics(&findings, file, text, &self.repository);
+                self.update(file, diagnostics)
+            }
+            Err(e) => {
+                warn!("Cannot analyze {}: {}", file.display(), e);
+                None
+            }
+        }
+    }
+
+    fn update(&mut self, file: &Path, diagnostics: Vec<VsCodeDiagnostic>) -> Option<Value> {
+        let file = file.display().to_string();
+        self.extension.set_diagnostics(&file, diagnostics).then(|| self.publish(&file))
+    }
+
+    fn publish(&self, file: &str) -> Value {
+        let diagnostics: Vec<Value> = self.extension.visible_diagnostics(file).into_iter().map(VsCodeDiagnostic::to_vscode_format).collect();
+        json!({ "type": "diagnostics", "uri": file_uri(file), "file": file, "diagnostics": diagnostics })
+    }
+}
+
+/// Diagnostics of the findings in `file`, each related to the other findings of its rule
+/// and carrying the edit of its fix
+fn diagnostics(findings: &[Finding], file: &Path, text: &str, repository: &RuleRepository) -> Vec<VsCodeDiagnostic> {
+    let plain: Vec<VsCodeDiagnostic> = findings.iter().map(|finding| vscode_diagnostic(finding, text)).collect();
+    findings
+        .iter()
+        .zip(&plain)
+        .enumerate()
+        .map(|(index, (finding, diagnostic))| {
+            let related = plain
+                .iter()
+                .enumerate()
+                .filter(|(other, d)| *other != index && d.rule_id == diagnostic.rule_id)
+                .take(MAX_RELATED)
+                .map(|(_, d)| VsCodeRelatedInformation {
+                    file: d.file.clone(),
+                    line: d.line,
+                    column: d.column,
+                    end_line: d.end_line,
+                    end_column: d.end_column,
+                    message: format!("{} also matches here", d.rule_id),
+                })
+                .collect();
+            let mut diagnostic = diagnostic.clone().with_related_information(related);
+            if let Some(new_text) = render_fix(finding, file, text, repository) {
+                let edit = VsCodeTextEdit {
+                    line: diagnostic.line,
+                    column: diagnostic.column,
+                    end_line: diagnostic.end_line,
+                    end_column: diagnostic.end_column,
+                    new_text,
+                };
+                diagnostic = diagnostic.with_fix(edit);
+            }
+            diagnostic
+        })
+        .collect()
+}
+
+/// Serve live diagnostics for `workspace` until the extension shuts the server down or
+/// goes away, polling for changed files every `interval`
+pub async fn run(workspace: PathBuf, rule_files: Vec<PathBuf>, listen: Option<String>, interval: Duration) -> Result<()> {
+    let config = EnhancedAnalysisConfig {
+        target_paths: vec![workspace],
+        rule_files,
+        languages: crate::DEFAULT_LANGUAGES.to_vec(),
+        ..EnhancedAnalysisConfig::default()
+    };
+    let mut session = DiagnosticsSession::new(config);
+    match listen {
+        Some(address) => {
+            let listener = std::net::TcpListener::bind(&address)?;
+            info!("Waiting for the extension on {}", listener.local_addr()?);
+            let (stream, peer) = listener.accept()?;
+            info!("Extension connected from {}", peer);
+            serve(&mut session, std::io::BufReader::new(stream.try_clone()?), stream, interval).await
+        }
+        None => {
+            info!("Serving diagnostics on stdio");
+            serve(&mut session, std::io::BufReader::new(std::io::stdin()), std::io::stdout(), interval).await
+        }
+    }
+}
+
+async fn serve(
+    session: &mut DiagnosticsSession,
+    reader: impl BufRead + Send + 'static,
+    mut writer: impl Write,
+    interval: Duration,
+) -> Result<()> {
+    // Lines are read on their own thread so polling goes on while the extension is quiet
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        for line in reader.lines().map_while(std::io::Result::ok) {
+            if sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut ready = false;
+    loop {
+        for message in session.poll().await? {
+            send(&mut writer, &message)?;
+        }
+        if !ready {
+            ready = true;
+            send(&mut writer, &json!({ "type": "ready", "files": session.modified.len() }))?;
+        }
+
+        let next_poll = Instant::now() + interval;
+        loop {
+            match receiver.recv_timeout(next_poll.saturating_duration_since(Instant::now())) {
+                Ok(line) if line.trim().is_empty() => {}
+                Ok(line) => match serde_json::from_str::<Value>(&line) {
+                    Ok(message) => {
+                        for reply in session.handle(&message) {
+                            send(&mut writer, &reply)?;
+                        }
+                        if session.is_shut_down() {
+                            return Ok(());
+                        }
+                    }
+                    Err(e) => warn!("Ignoring malformed message: {}", e),
+                },
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => return Ok(()),
+            }
+        }
+    }
+}
+
+fn send(writer: &mut impl Write, message: &Value) -> Result<()> {
+    writeln!(writer, "{}", serde_json::to_string(message)?)?;
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::analyze_enhanced::{test_finding, Location};
+    use astgrep_core::Severity;
+
+    fn finding(rule_id: &str, line: usize, fix: Option<&str>) -> Finding {
+        Finding {
+            severity: Severity::Warning,
+            location: Location::new("/src/app.js", line, 1, line, 8),
+            fix: fix.map(str::to_string),
+            ..test_finding(rule_id, "/src/app.js", line)
+        }
+    }
+
+    #[test]
+    fn test_diagnostics_relate_findings_of_a_rule_and_carry_fixes() {
+        let text = "eval(a);\neval(b);\nexec(c);\n";
+        let findings = [finding("js-eval", 1, Some("JSON.parse(a)")), finding("js-eval", 2, None), finding("js-exec", 3, None)];
+        let diagnostics = diagnostics(&findings, Path::new("/src/app.js"), text, &RuleRepository::new());
+
+        assert_eq!(diagnostics[0].related_information.len(), 1);
+        assert_eq!(diagnostics[0].related_information[0].line, 1);
+        assert_eq!(diagnostics[1].related_information[0].line, 0);
+        assert!(diagnostics[2].related_information.is_empty());
+        let fix = diagnostics[0].fix.as_ref().unwrap();
+        assert_eq!((fix.line, fix.column, fix.end_column), (0, 0, 7));
+        assert_eq!(fix.new_text, "JSON.parse(a)");
+        assert!(diagnostics[1].fix.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_session_tracks_files_buffers_and_configuration() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("app.py");
+        std::fs::write(&file, "print(1)\n").unwrap();
+        let config = EnhancedAnalysisConfig {
+            target_paths: vec![dir.path().to_path_buf()],
+            languages: crate::DEFAULT_LANGUAGES.to_vec(),
+            ..EnhancedAnalysisConfig::default()
+        };
+        let mut session = DiagnosticsSession::new(config);
+
+        // Without rules nothing is reported, but the file is tracked
+        assert!(session.poll().await.unwrap().is_empty());
+        assert!(session.modified.contains_key(&file));
+        assert!(session.poll().await.unwrap().is_empty());
+
+        let name = file.display().to_string();
+        assert!(session.handle(&json!({ "type": "update", "file": name, "text": "print(2)\n" })).is_empty());
+        assert!(session.buffers.contains_key(&file));
+        session.handle(&json!({ "type": "revert", "file": name }));
+        assert!(!session.buffers.contains_key(&file) && !session.modified.contains_key(&file));
+
+        // Diagnostics of a file that is gone are cleared
+        session.extension.set_diagnostics(&name, vec![vscode_diagnostic(&finding("py-x", 1, None), "print(1)\n")]);
+        session.modified.insert(file.clone(), SystemTime::UNIX_EPOCH);
+        std::fs::remove_file(&file).unwrap();
+        let messages = session.poll().await.unwrap();
+        assert_eq!(messages[0]["type"], "diagnostics");
+        assert_eq!(messages[0]["diagnostics"], json!([]));
+
+        assert!(session.handle(&json!({ "type": "configure", "config": { "highlight_severity": "bogus" } })).is_empty());
+        session.handle(&json!({ "type": "shutdown" }));
+        assert!(session.is_shut_down());
+    }
+
+    #[tokio::test]
+    async fn test_serve_sends_ready_and_stops_on_shutdown() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = EnhancedAnalysisConfig { target_paths: vec![dir.path().to_path_buf()], ..EnhancedAnalysisConfig::default() };
+        let mut session = DiagnosticsSession::new(config);
+        let input = std::io::Cursor::new(b"\n{\"type\": \"shutdown\"}\n".to_vec());
+        let mut output = Vec::new();
+        serve(&mut session, input, &mut output, Duration::from_millis(10)).await.unwrap();
+        let lines: Vec<Value> = String::from_utf8(output).unwrap().lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(lines, vec![json!({ "type": "ready", "files": 0 })]);
+    }
+}
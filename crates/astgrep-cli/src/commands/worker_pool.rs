@@ -0,0 +1,132 @@
+//! Parallel file analysis
+//!
+//! Target files are independent, so `analyze` spreads them over a rayon pool of
+//! `--max-threads` workers (all cores by default, one with `--no-parallel`). Each file is
+//! analyzed start to finish on one worker. A worker builds its state (the rule engine
+//! with its rules and the parsers) once, for its first file, and reuses it for the rest.
+//! The per-file results are returned in the order of the files so reports do not depend
+//! on scheduling.
+
+use anyhow::{anyhow, Result};
+use astgrep_core::CancellationToken;
+use rayon::prelude::*;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// Number of workers for `parallel` and `max_threads`; `max_threads` of 0 means all cores
+pub fn worker_count(parallel: bool, max_threads: Option<usize>) -> usize {
+    if !parallel {
+        return 1;
+    }
+    match max_threads {
+        Some(threads) if threads > 0 => threads,
+        _ => std::thread::available_parallelism().map_or(1, |n| n.get()),
+    }
+}
+
+/// Run `analyze` on each of `files` with up to `workers` at a time, returning the results
+/// in the order of `files`. Each worker calls `init` once, before its first file, and
+/// passes the state to `analyze` for all of its files. Files not yet started when
+/// `cancellation` is set are left out.
+pub fn map_files<S, T, I, F>(files: Vec<PathBuf>, workers: usize, cancellation: &CancellationToken, init: I, analyze: F) -> Result<Vec<T>>
+where
+    S: Send,
+    T: Send,
+    I: Fn() -> S + Sync + Send,
+    F: Fn(&mut S, PathBuf) -> T + Sync + Send,
+{
+    let run = |state: &mut S, file: PathBuf| (!cancellation.is_cancelled()).then(|| analyze(state, file));
+    if workers <= 1 || files.len() <= 1 {
+        let mut state = None;
+        return Ok(files.into_iter().map_while(|file| run(state.get_or_insert_with(&init), file)).collect());
+    }
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(workers)
+        .thread_name(|i| format!("astgrep-worker-{}", i))
+        .build()
+        .map_err(|e| anyhow!("cannot start the analysis workers: {}", e))?;
+    // One slot per worker thread; only its own worker ever locks it
+    let states: Vec<Mutex<Option<S>>> = (0..workers).map(|_| Mutex::new(None)).collect();
+    let results: Vec<Option<T>> = pool.install(|| {
+        files
+            .into_par_iter()
+            .map(|file| {
+                let index = rayon::current_thread_index().expect("analysis runs on the worker pool");
+                let mut state = states[index].lock().unwrap_or_else(|e| e.into_inner());
+                run(state.get_or_insert_with(&init), file)
+            })
+            .collect()
+    });
+    Ok(results.into_iter().flatten().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_map_files_keeps_file_order() {
+        let files: Vec<PathBuf> = (0..64).map(|i| PathBuf::from(format!("src/{}.py", i))).collect();
+        let threads = Mutex::new(HashSet::new());
+        let results = map_files(files.clone(), 4, &CancellationToken::new(), || (), |_, file| {
+            threads.lock().unwrap().insert(std::thread::current().name().map(str::to_string));
+            // Later files finish first
+            let n: u64 = file.file_stem().unwrap().to_str().unwrap().parse().unwrap();
+            std::thread::sleep(std::time::Duration::from_micros(64 - n));
+            file
+        })
+        .unwrap();
+
+        assert_eq!(results, files);
+        let threads = threads.into_inner().unwrap();
+        assert!(threads.len() <= 4);
+        assert!(threads.iter().all(|name| name.as_deref().is_some_and(|n| n.starts_with("astgrep-worker-"))));
+    }
+
+    #[test]
+    fn test_map_files_stops_when_cancelled() {
+        let files: Vec<PathBuf> = (0..10).map(|i| PathBuf::from(format!("{}.js", i))).collect();
+        let cancellation = CancellationToken::new();
+        let results = map_files(files, 1, &cancellation, || (), |_, file| {
+            if file.to_str() == Some("2.js") {
+                cancellation.cancel();
+            }
+            file
+        })
+        .unwrap();
+        assert_eq!(results, vec![PathBuf::from("0.js"), PathBuf::from("1.js"), PathBuf::from("2.js")]);
+
+        cancellation.cancel();
+        assert!(map_files(vec![PathBuf::from("a.js"), PathBuf::from("b.js")], 2, &cancellation, || (), |_, f| f).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_map_files_builds_state_once_per_worker() {
+        let files: Vec<PathBuf> = (0..64).map(|i| PathBuf::from(format!("{}.rb", i))).collect();
+        for workers in [1, 4] {
+            let built = AtomicUsize::new(0);
+            let init = || {
+                built.fetch_add(1, Ordering::Relaxed);
+            };
+            let results = map_files(files.clone(), workers, &CancellationToken::new(), init, |_, file| {
+                std::thread::sleep(std::time::Duration::from_micros(50));
+                file
+            })
+            .unwrap();
+            assert_eq!(results, files);
+            let built = built.into_inner();
+            assert!((1..=workers).contains(&built), "{} workers built their state {} times", workers, built);
+        }
+    }
+
+    #[test]
+    fn test_worker_count() {
+        assert_eq!(worker_count(false, Some(8)), 1);
+        assert_eq!(worker_count(true, Some(3)), 3);
+        assert!(worker_count(true, Some(0)) >= 1);
+        assert!(worker_count(true, None) >= 1);
+    }
+}
@@ -0,0 +1,151 @@
+//! Multi-root workspace support for the analyze command
+//!
+//! Each project root may carry its own `.astgrep.yml` with rules, include/exclude
+//! lists and languages; these extend the settings given on the command line.
+
+use anyhow::Result;
+use astgrep_core::Language;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+use crate::{EnhancedAnalysisConfig, LanguageOverride};
+
+/// Per-root configuration file name
+pub const ROOT_CONFIG_FILE: &str = ".astgrep.yml";
+
+/// Contents of a root's `.astgrep.yml`
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+#[serde(default)]
+pub struct RootConfig {
+    /// Rule files, directories or packs, relative to the root, or registry shorthands
+    pub rules: Vec<PathBuf>,
+    /// Include patterns added to the command-line ones
+    pub include: Vec<String>,
+    /// Exclude patterns added to the command-line ones
+    pub exclude: Vec<String>,
+    /// Languages analyzed in this root (replaces the command-line list when set)
+    pub languages: Vec<String>,
+    /// Language forced for the files under a directory, relative to the root
+    pub language_overrides: BTreeMap<PathBuf, String>,
+}
+
+impl RootConfig {
+    /// Load `.astgrep.yml` from `root`, if present
+    pub fn load(root: &Path) -> Result<Option<Self>> {
+        let path = root.join(ROOT_CONFIG_FILE);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(&path)?;
+        let config: RootConfig = serde_yaml::from_str(&content)
+            .map_err(|e| anyhow::anyhow!("Invalid {}: {}", path.display(), e))?;
+        Ok(Some(config))
+    }
+
+    /// The rule paths of the file in `root`, with registry shorthands such as `p/<pack>`
+    /// resolved like those of `--rules`
+    pub fn rule_paths(&self, root: &Path) -> Result<Vec<PathBuf>> {
+        crate::commands::registry::Registry::from_env().resolve_relative(root, &self.rules)
+    }
+}
+
+/// Per-root section of a workspace report
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct RootStatistics {
+    pub root: String,
+    pub config_file: Option<String>,
+    pub files_analyzed: usize,
+    pub rules_executed: usize,
+    pub total_findings: usize,
+}
+
+/// Build the effective analysis config for one workspace root
+pub fn config_for_root(base: &EnhancedAnalysisConfig, root: &Path) -> Result<EnhancedAnalysisConfig> {
+    let mut config = base.clone();
+    config.target_paths = vec![root.to_path_buf()];
+
+    if let Some(root_config) = RootConfig::load(root)? {
+        config.rule_files.extend(root_config.rule_paths(root)?);
+        config.include_patterns.extend(root_config.include);
+        config.exclude_patterns.extend(root_config.exclude);
+
+        if !root_config.languages.is_empty() {
+            let mut languages = Vec::new();
+            for lang_str in &root_config.languages {
+                match Language::from_str(lang_str) {
+                    Some(lang) => languages.push(lang),
+                    None => warn!("Unknown language in {}: {}, skipping", root.join(ROOT_CONFIG_FILE).display(), lang_str),
+                }
+            }
+            if !languages.is_empty() {
+                config.languages = languages;
+            }
+        }
+
+        for (directory, lang_str) in &root_config.language_overrides {
+            match Language::from_str(lang_str) {
+                Some(language) => config.language_overrides.push(LanguageOverride { directory: root.join(directory), language }),
+                None => warn!("Unknown language override in {}: {}, skipping", root.join(ROOT_CONFIG_FILE).display(), lang_str),
+            }
+        }
+    }
+
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use astgrep_core::OutputFormat;
+
+    fn base_config() -> EnhancedAnalysisConfig {
+        EnhancedAnalysisConfig {
+            target_paths: vec![],
+            exclude_patterns: vec!["*.min.js".to_string()],
+            languages: vec![Language::Java, Language::Python],
+            rule_files: vec![PathBuf::from("shared-rules")],
+            output_format: OutputFormat::Json,
+            file_timeout: None,
+            workspace: true,
+            color: crate::commands::color::ColorChoice::Never,
+            ..EnhancedAnalysisConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_root_config_extends_base() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join(ROOT_CONFIG_FILE),
+            "rules: [rules/api.yaml]\nexclude: [generated]\nlanguages: [python]\n",
+        ).unwrap();
+
+        let config = config_for_root(&base_config(), dir.path()).unwrap();
+        assert_eq!(config.target_paths, vec![dir.path().to_path_buf()]);
+        assert_eq!(config.rule_files, vec![PathBuf::from("shared-rules"), dir.path().join("rules/api.yaml")]);
+        assert_eq!(config.exclude_patterns, vec!["*.min.js".to_string(), "generated".to_string()]);
+        assert_eq!(config.languages, vec![Language::Python]);
+    }
+
+    #[test]
+    fn test_root_language_overrides() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join(ROOT_CONFIG_FILE),
+            "language_overrides:\n  templates: php\n  templates/js: javascript\n",
+        ).unwrap();
+
+        let config = config_for_root(&base_config(), dir.path()).unwrap();
+        assert_eq!(config.language_override(&dir.path().join("templates/page.tpl")), Some(Language::Php));
+        assert_eq!(config.language_override(&dir.path().join("templates/js/app.tpl")), Some(Language::JavaScript));
+        assert_eq!(config.language_override(&dir.path().join("src/main.py")), None);
+    }
+
+    #[test]
+    fn test_root_without_config_uses_base() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = config_for_root(&base_config(), dir.path()).unwrap();
+        assert_eq!(config.rule_files, vec![PathBuf::from("shared-rules")]);
+        assert_eq!(config.languages, vec![Language::Java, Language::Python]);
+    }
+}
@@ -1,9 +1,10 @@
 //! Command-line interface for astgrep
 
 use anyhow::Result;
-use clap::{Parser, Subcommand, ValueEnum};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use astgrep_core::{AnalysisConfig, Language, OutputFormat, Severity, Confidence};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use commands::color::ColorChoice;
 use tracing::{info, warn};
 
 mod commands;
@@ -45,83 +46,314 @@ pub struct Cli {
     /// Enable performance profiling
     #[arg(long, global = true)]
     pub profile: bool,
+
+    /// Write the rule engine's match decisions to this file as JSON lines
+    #[arg(long, global = true, value_name = "FILE")]
+    pub trace_matching: Option<PathBuf>,
+
+    /// Only trace the match decisions of these rule ids
+    #[arg(long = "trace-rule", global = true, value_name = "RULE_ID", requires = "trace_matching")]
+    pub trace_rules: Vec<String>,
 }
 
-#[derive(Subcommand)]
-pub enum Commands {
-    /// Analyze source code for security vulnerabilities and quality issues
-    Analyze {
-        /// Target paths to analyze
-        #[arg(value_name = "PATH")]
-        targets: Vec<PathBuf>,
+/// Options of `astgrep analyze`
+#[derive(Args)]
+pub struct AnalyzeArgs {
+    /// Target paths to analyze; `-` reads one file from stdin, and .zip, .jar, .war and
+    /// .tar(.gz) archives are unpacked and analyzed
+    #[arg(value_name = "PATH")]
+    pub targets: Vec<PathBuf>,
+
+    /// Rule files or directories to use, or registry packs (p/<name>); later sources
+    /// replace and override rules of earlier ones by id
+    #[arg(short, long)]
+    pub rules: Vec<PathBuf>,
+
+    /// Policy file overriding the severity, confidence or enablement of rules by id
+    /// or glob, applied after every rule source
+    #[arg(long, value_name = "FILE")]
+    pub policy: Option<PathBuf>,
+
+    /// Search for this pattern instead of running rules, e.g. -e 'eval($X)' --lang javascript
+    #[arg(short = 'e', long, value_name = "PATTERN", requires = "language", conflicts_with_all = ["rules", "policy"])]
+    pub pattern: Option<String>,
+
+    /// Languages to analyze (java, javascript, python, sql, bash)
+    #[arg(short, long, visible_alias = "lang")]
+    pub language: Vec<String>,
+
+    /// Exclude patterns (glob patterns)
+    #[arg(long)]
+    pub exclude: Vec<String>,
+
+    /// Include only files matching these patterns
+    #[arg(long, value_name = "PATTERN")]
+    pub include: Vec<String>,
+
+    /// Output format
+    #[arg(short = 'f', long, default_value = "json")]
+    pub format: OutputFormatCli,
+
+    /// Output file (default: stdout)
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// Render the results through this Tera template instead of --format
+    #[arg(long, value_name = "FILE")]
+    pub output_template: Option<PathBuf>,
+
+    /// Do not show a progress bar while analyzing
+    #[arg(long)]
+    pub no_progress: bool,
+
+    /// Path reported for source read from stdin (`-`); its extension selects the language
+    #[arg(long, value_name = "PATH")]
+    pub stdin_filename: Option<PathBuf>,
+
+    /// Also scan paths ignored by .gitignore files and .git/info/exclude
+    #[arg(long)]
+    pub no_git_ignore: bool,
+
+    /// Severity level filter (only show issues at or above this level)
+    #[arg(short = 'S', long, default_value = "info")]
+    pub severity: SeverityFilter,
+
+    /// Confidence level filter (only show issues at or above this level)
+    #[arg(short = 'C', long, default_value = "low")]
+    pub confidence: ConfidenceFilter,
+
+    /// Include performance metrics in output
+    #[arg(long)]
+    pub metrics: bool,
+
+    /// Also write the scan metrics to this file in the OpenMetrics text format
+    #[arg(long, value_name = "FILE", requires = "metrics")]
+    pub metrics_out: Option<PathBuf>,
+
+    /// Directory caching the findings of every file; unchanged files are not analyzed
+    /// again while the rules stay the same
+    #[arg(long, value_name = "DIR")]
+    pub cache_dir: Option<PathBuf>,
+
+    /// Maximum number of findings to report (0 = unlimited)
+    #[arg(long, default_value = "0")]
+    pub max_findings: usize,
+
+    /// Skip files larger than this many bytes (0 = no limit)
+    #[arg(long, value_name = "BYTES", default_value_t = commands::target_filter::DEFAULT_MAX_TARGET_BYTES)]
+    pub max_target_bytes: u64,
+
+    /// Abort the analysis of a file after this many seconds and go on with the next (0 = no limit)
+    #[arg(long, value_name = "SECS", default_value_t = commands::isolation::DEFAULT_FILE_TIMEOUT_SECS)]
+    pub timeout: u64,
+
+    /// Enable data flow analysis
+    #[arg(long)]
+    pub dataflow: bool,
+
+    /// Taint model file or directory of library functions for dataflow rules, after
+    /// those listed under [taint] in astgrep.toml
+    #[arg(long, value_name = "PATH")]
+    pub taint_models: Vec<PathBuf>,
+
+    /// Baseline file or earlier SARIF report for comparison (show only new issues)
+    #[arg(long, value_name = "FILE")]
+    pub baseline: Option<PathBuf>,
+
+    /// Write the findings of this run to a baseline file for later --baseline runs
+    #[arg(long, value_name = "FILE")]
+    pub baseline_out: Option<PathBuf>,
 
-        /// Rule files or directories to use
-        #[arg(short, long)]
-        rules: Vec<PathBuf>,
+    /// Exit with non-zero code if issues are found
+    #[arg(long)]
+    pub fail_on_findings: bool,
 
-        /// Languages to analyze (java, javascript, python, sql, bash)
-        #[arg(short, long)]
-        language: Vec<String>,
+    /// Disable parallel processing
+    #[arg(long)]
+    pub no_parallel: bool,
 
-        /// Exclude patterns (glob patterns)
-        #[arg(short, long)]
-        exclude: Vec<String>,
+    /// Maximum number of threads
+    #[arg(long)]
+    pub max_threads: Option<usize>,
 
-        /// Include only files matching these patterns
-        #[arg(long, value_name = "PATTERN")]
-        include: Vec<String>,
+    /// Enable compatibility mode with external tools (e.g., "semgrep")
+    #[arg(long)]
+    pub compatible: Option<String>,
 
-        /// Output format
-        #[arg(short = 'f', long, default_value = "json")]
-        format: OutputFormatCli,
+    /// SQL: constrain simple matching within single statements (semicolon delimited). YAML 'options.sql_statement_boundary' overrides this.
+    #[arg(long = "sql-statement-boundary", value_enum, default_value = "on")]
+    pub sql_statement_boundary: OnOffCli,
 
-        /// Output file (default: stdout)
+    /// Map findings in generated files back to original sources (source maps, #line directives)
+    #[arg(long)]
+    pub source_maps: bool,
+
+    /// Treat each target directory as a separate project root with its own .astgrep.yml
+    #[arg(long)]
+    pub workspace: bool,
+
+    /// Scan a container image (docker save tarball, OCI layout, or image reference via docker)
+    #[arg(long, value_name = "REF|TAR")]
+    pub image: Option<String>,
+
+    /// Write a unified-diff patch per autofixable finding, named by its fingerprint, into this directory
+    #[arg(long, value_name = "DIR")]
+    pub emit_patches: Option<PathBuf>,
+
+    /// With --emit-patches, write one combined patch per file instead of per finding
+    #[arg(long, requires = "emit_patches")]
+    pub combine_patches: bool,
+
+    /// Write a minimized reproduction fixture and rule file per finding into this directory
+    #[arg(long, value_name = "DIR")]
+    pub emit_tests: Option<PathBuf>,
+
+    /// Redact matched text in reports: mask, partial[:PREFIX:SUFFIX] or hash (rules may override via metadata.redact)
+    #[arg(long, value_name = "MODE")]
+    pub redact: Option<String>,
+
+    /// Color text output: auto (terminal and NO_COLOR unset), always or never
+    #[arg(long, value_enum, default_value = "auto")]
+    pub color: ColorCli,
+
+    /// Analyze every file under DIR as LANG, regardless of its extension (repeatable)
+    #[arg(long = "language-override", value_name = "DIR=LANG")]
+    pub language_overrides: Vec<String>,
+
+    /// Append this run's findings to a local trend database (default: .astgrep/trends.db)
+    #[arg(long, value_name = "DB", num_args = 0..=1, default_missing_value = commands::trends::DEFAULT_TRENDS_DB)]
+    pub record: Option<PathBuf>,
+
+    /// Check dependency manifests against their lockfiles (drift, duplicate sensitive packages, git branch pins)
+    #[arg(long)]
+    pub supply_chain: bool,
+
+    /// Findings in test files: include, skip, downgrade (one severity level) or only (rules may override via metadata.test-files)
+    #[arg(long, value_enum, default_value = "include")]
+    pub test_files: TestFilesCli,
+
+    /// Browse the findings in a terminal UI (triage, open in $EDITOR, apply fixes) instead of printing them
+    #[arg(long)]
+    pub interactive: bool,
+
+    /// Scan profile: fast-ci, deep-audit or a [profiles.<name>] table of astgrep.toml
+    #[arg(long, value_name = "NAME")]
+    pub profile_name: Option<String>,
+
+    /// Only report findings whose risk score (0-100) is at least this
+    #[arg(long, value_name = "SCORE", value_parser = clap::value_parser!(u8).range(0..=100))]
+    pub min_risk_score: Option<u8>,
+
+    /// Only report findings the expression holds for, e.g. 'severity>=error && rule_id=~"sql" && path!~"tests/"'
+    #[arg(long, value_name = "EXPR")]
+    pub filter: Option<String>,
+
+    /// Show N lines of source around each finding in text, JSON and markdown output
+    #[arg(long, value_name = "N")]
+    pub context: Option<usize>,
+
+    /// Report the highest-risk findings first
+    #[arg(long)]
+    pub sort_by_risk: bool,
+
+    /// Apply the fixes of the findings to the source files, keeping a <file>.backup copy of each changed file
+    #[arg(long, conflicts_with_all = ["fix_dry_run", "interactive"])]
+    pub fix: bool,
+
+    /// Print the edits --fix would make as unified diffs without changing any file
+    #[arg(long)]
+    pub fix_dry_run: bool,
+
+    /// Fixes touching the same lines: keep the first (skip-later), the more severe (prefer-severity) or ask (prompt)
+    #[arg(long, value_enum, default_value = "skip-later")]
+    pub fix_conflicts: FixConflictsCli,
+
+    /// Record ignored rule constructs, engine fallbacks and unconverted syntax nodes to this JSON file
+    #[arg(long, value_name = "FILE")]
+    pub diagnostics: Option<PathBuf>,
+
+    /// Ignore inline astgrep-ignore / nosem suppression comments
+    #[arg(long)]
+    pub disable_nosem: bool,
+
+    /// List findings silenced by inline suppression comments in JSON and SARIF output
+    #[arg(long)]
+    pub report_suppressed: bool,
+
+    /// Columns of --format csv, comma-separated (e.g. rule_id,severity,file,line,message)
+    #[arg(long, value_name = "COLUMNS")]
+    pub csv_columns: Option<String>,
+
+    /// Print the JSON Schema of the findings report (--format json) and exit
+    #[arg(long)]
+    pub schema: bool,
+}
+
+#[derive(Subcommand)]
+pub enum Commands {
+    /// Analyze source code for security vulnerabilities and quality issues
+    Analyze(Box<AnalyzeArgs>),
+
+    /// Analyze the repository with defaults for the detected CI system (GitHub Actions, GitLab CI, Jenkins)
+    Ci {
+        /// Rule files, directories or packs (default: .astgrep.yml rules, else ./rules)
         #[arg(short, long)]
-        output: Option<PathBuf>,
+        rules: Vec<PathBuf>,
 
-        /// Severity level filter (only show issues at or above this level)
-        #[arg(short = 'S', long, default_value = "info")]
-        severity: SeverityFilter,
+        /// Where to write the SARIF report
+        #[arg(long, value_name = "FILE", default_value = commands::ci::DEFAULT_SARIF_OUTPUT)]
+        sarif_output: PathBuf,
 
-        /// Confidence level filter (only show issues at or above this level)
-        #[arg(short = 'C', long, default_value = "low")]
-        confidence: ConfidenceFilter,
+        /// Fail the job when a finding is at or above this severity
+        #[arg(long, value_enum, default_value = "error")]
+        fail_on: FailOnCli,
 
-        /// Include performance metrics in output
+        /// Analyze all files even in a pull or merge request
         #[arg(long)]
-        metrics: bool,
+        full_scan: bool,
+
+        /// Analyze the files changed since this revision instead of the detected target branch
+        #[arg(long, value_name = "REF", conflicts_with = "full_scan")]
+        base_ref: Option<String>,
+    },
 
-        /// Maximum number of findings to report (0 = unlimited)
-        #[arg(long, default_value = "0")]
-        max_findings: usize,
+    /// Install a git hook that analyzes staged (pre-commit) or pushed (pre-push) files
+    InstallHook {
+        /// Hook to install
+        #[arg(value_enum, default_value = "pre-commit")]
+        hook: HookCli,
 
-        /// Enable data flow analysis
-        #[arg(long)]
-        dataflow: bool,
+        /// Rule files, directories or packs (default: .astgrep.yml rules, else ./rules)
+        #[arg(short, long)]
+        rules: Vec<PathBuf>,
 
-        /// Baseline file for comparison (show only new issues)
-        #[arg(long, value_name = "FILE")]
-        baseline: Option<PathBuf>,
+        /// Block the commit or push when a finding is at or above this severity
+        #[arg(long, value_enum, default_value = "error")]
+        fail_on: FailOnCli,
 
-        /// Exit with non-zero code if issues are found
-        #[arg(long)]
-        fail_on_findings: bool,
+        /// Scan profile the hook runs with
+        #[arg(long, default_value = commands::scan_profile::FAST_CI)]
+        profile: String,
 
-        /// Disable parallel processing
+        /// Replace a hook not installed by astgrep
         #[arg(long)]
-        no_parallel: bool,
+        force: bool,
+    },
 
-        /// Maximum number of threads
-        #[arg(long)]
-        max_threads: Option<usize>,
+    /// Run the analysis of an installed git hook
+    #[command(hide = true)]
+    RunHook {
+        #[arg(value_enum)]
+        hook: HookCli,
 
-        /// Enable compatibility mode with external tools (e.g., "semgrep")
-        #[arg(long)]
-        compatible: Option<String>,
+        #[arg(short, long)]
+        rules: Vec<PathBuf>,
+
+        #[arg(long, value_enum, default_value = "error")]
+        fail_on: FailOnCli,
 
-        /// SQL: constrain simple matching within single statements (semicolon delimited). YAML 'options.sql_statement_boundary' overrides this.
-        #[arg(long = "sql-statement-boundary", value_enum, default_value = "on")]
-        sql_statement_boundary: OnOffCli,
+        #[arg(long, default_value = commands::scan_profile::FAST_CI)]
+        profile: String,
     },
 
     /// Validate rule files for syntax and semantic correctness
@@ -210,6 +442,310 @@ pub enum Commands {
         force: bool,
     },
 
+    /// Print a shell completion script (bash, zsh, fish, powershell or elvish)
+    Completions {
+        /// Shell to complete in
+        #[arg(value_enum)]
+        shell: clap_complete::Shell,
+
+        /// Rule files, directories or packs whose rule ids to complete (default: .astgrep.yml rules, else ./rules)
+        #[arg(short, long)]
+        rules: Vec<PathBuf>,
+    },
+
+    /// Store an API token, read from stdin, for a private rule registry
+    Login {
+        /// Registry URL (default: ASTGREP_REGISTRY_URL)
+        #[arg(long)]
+        registry: Option<String>,
+    },
+
+    /// Remove the stored API token of a rule registry
+    Logout {
+        /// Registry URL (default: ASTGREP_REGISTRY_URL)
+        #[arg(long)]
+        registry: Option<String>,
+    },
+
+    /// Manage rules and rule packs
+    Rules {
+        #[command(subcommand)]
+        command: RulesCommands,
+    },
+
+    /// Build or update the persistent project index (symbols, imports, call graph)
+    Index {
+        /// Project root to index
+        #[arg(value_name = "PATH", default_value = ".")]
+        path: PathBuf,
+
+        /// Discard the existing index and rebuild it from scratch
+        #[arg(long)]
+        rebuild: bool,
+
+        /// Exclude patterns (glob patterns)
+        #[arg(long)]
+        exclude: Vec<String>,
+
+        /// Also index paths ignored by .gitignore files and .git/info/exclude
+        #[arg(long)]
+        no_git_ignore: bool,
+    },
+
+    /// Report findings over time from runs recorded with 'analyze --record'
+    Trends {
+        /// Trend database
+        #[arg(long, value_name = "DB", default_value = commands::trends::DEFAULT_TRENDS_DB)]
+        db: PathBuf,
+
+        /// Count findings by severity, rule or directory
+        #[arg(long, value_enum, default_value = "severity")]
+        by: TrendGroupCli,
+
+        /// Number of most recent runs to show
+        #[arg(long, default_value = "10")]
+        last: usize,
+
+        /// Output format (text or json)
+        #[arg(short = 'f', long, default_value = "text")]
+        format: OutputFormatCli,
+    },
+
+    /// Structurally search and replace code, independent of rules
+    Rewrite {
+        /// Files or directories to rewrite (default: current directory)
+        #[arg(value_name = "PATH")]
+        paths: Vec<PathBuf>,
+
+        /// Language of the pattern and the files to rewrite
+        #[arg(short, long = "lang", alias = "language")]
+        lang: String,
+
+        /// Pattern to search for, with metavariables such as $X
+        #[arg(short, long)]
+        pattern: String,
+
+        /// Replacement, with the metavariables bound by the pattern
+        #[arg(long = "replace", visible_alias = "rewrite")]
+        replace: String,
+
+        /// Write the rewrites to the files instead of showing them as unified diffs
+        #[arg(long, conflicts_with = "dry_run")]
+        in_place: bool,
+
+        /// Only show the rewrites as unified diffs (the default without --in-place)
+        #[arg(long, conflicts_with = "interactive")]
+        dry_run: bool,
+
+        /// Confirm each rewrite before writing it
+        #[arg(short, long)]
+        interactive: bool,
+
+        /// Exclude patterns (glob patterns)
+        #[arg(long)]
+        exclude: Vec<String>,
+
+        /// Also rewrite paths ignored by .gitignore files and .git/info/exclude
+        #[arg(long)]
+        no_git_ignore: bool,
+    },
+
+    /// Run rule unit tests: fixtures next to rule files, annotated with 'ruleid: <id>' and 'ok: <id>' comments
+    Test {
+        /// Rule files or directories holding rules and their fixtures (default: current directory)
+        #[arg(value_name = "PATH", default_value = ".")]
+        paths: Vec<PathBuf>,
+
+        /// Output format (text or json)
+        #[arg(short = 'f', long, default_value = "text")]
+        format: OutputFormatCli,
+
+        /// Exclude patterns (glob patterns)
+        #[arg(long)]
+        exclude: Vec<String>,
+
+        /// Also run fixtures ignored by .gitignore files and .git/info/exclude
+        #[arg(long)]
+        no_git_ignore: bool,
+    },
+
+    /// Rewrite rule files in the canonical key order, indentation and pattern style
+    Fmt {
+        /// Rule files or directories (default: current directory)
+        #[arg(value_name = "PATH", default_value = ".")]
+        paths: Vec<PathBuf>,
+
+        /// Only report files that are not formatted, failing if there are any
+        #[arg(long)]
+        check: bool,
+
+        /// Exclude patterns (glob patterns)
+        #[arg(long)]
+        exclude: Vec<String>,
+
+        /// Also format paths ignored by .gitignore files and .git/info/exclude
+        #[arg(long)]
+        no_git_ignore: bool,
+    },
+
+    /// Re-render a JSON findings file produced by 'analyze' in another output format
+    Convert {
+        /// Findings file written by 'analyze --format json'
+        #[arg(value_name = "FILE")]
+        input: PathBuf,
+
+        /// Output format (json, sarif, text, html, csv or markdown)
+        #[arg(short = 't', long = "to")]
+        to: OutputFormatCli,
+
+        /// Output file (default: stdout)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Merge the JSON or SARIF reports of several runs, e.g. sharded CI jobs, into one
+    MergeReports {
+        /// Reports written by 'analyze --format json' or '--format sarif'
+        #[arg(value_name = "FILE", required = true)]
+        inputs: Vec<PathBuf>,
+
+        /// Output format (json, sarif, text, html, csv or markdown)
+        #[arg(short = 't', long = "to", default_value = "json")]
+        to: OutputFormatCli,
+
+        /// Output file (default: stdout)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Describe a rule: its patterns, CWE/OWASP references, examples and fix guidance
+    Explain {
+        /// Id of the rule to describe
+        rule_id: String,
+
+        /// Rule files, directories or packs (default: .astgrep.yml rules, else ./rules)
+        #[arg(short, long)]
+        rules: Vec<PathBuf>,
+
+        /// Output format (text or markdown)
+        #[arg(short = 'f', long, default_value = "text")]
+        format: OutputFormatCli,
+    },
+
+    /// Print the syntax tree of a file, to see which node types patterns should target
+    DumpAst {
+        /// Source file to parse
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+
+        /// Language to parse the file as (default: from its extension)
+        #[arg(short, long)]
+        language: Option<String>,
+
+        /// Output format
+        #[arg(short = 'f', long, default_value = "text")]
+        format: AstFormatCli,
+    },
+
+    /// Serve diagnostics and quick fixes to editors over the Language Server Protocol (stdio)
+    Lsp {
+        /// Rule files, directories or packs
+        #[arg(short, long, required = true)]
+        rules: Vec<PathBuf>,
+    },
+
+    /// Stream live diagnostics of a workspace to the VS Code extension as files change
+    Vscode {
+        /// Workspace folder to watch
+        #[arg(value_name = "DIR", default_value = ".")]
+        workspace: PathBuf,
+
+        /// Rule files, directories or packs
+        #[arg(short, long, required = true)]
+        rules: Vec<PathBuf>,
+
+        /// Accept the extension's connection on this address (e.g. 127.0.0.1:7878) instead of using stdio
+        #[arg(long, value_name = "ADDR")]
+        listen: Option<String>,
+
+        /// Milliseconds between checks for changed files
+        #[arg(long, default_value = "500")]
+        interval_ms: u64,
+    },
+
+    /// Benchmark each rule against a corpus to find the rules that make scans slow
+    Bench {
+        /// Files or directories forming the corpus (default: current directory)
+        #[arg(value_name = "PATH")]
+        targets: Vec<PathBuf>,
+
+        /// Rule files, directories or packs
+        #[arg(short, long, required = true)]
+        rules: Vec<PathBuf>,
+
+        /// Number of timed runs of each rule on each file
+        #[arg(long, default_value = "5")]
+        iterations: usize,
+
+        /// Flag, and fail on, rules whose mean time over the corpus exceeds this many milliseconds
+        #[arg(long, value_name = "MS")]
+        budget_ms: Option<f64>,
+
+        /// Number of slowest files listed per rule
+        #[arg(long, default_value = "3")]
+        slowest: usize,
+
+        /// Output format (text or json)
+        #[arg(short = 'f', long, default_value = "text")]
+        format: OutputFormatCli,
+    },
+
+    /// Benchmark the engine against bundled corpora to validate a build
+    SelfBench {
+        /// Number of timed iterations per benchmark case
+        #[arg(long, default_value = "10")]
+        iterations: usize,
+
+        /// Reference report to compare against (fails on regression)
+        #[arg(long, value_name = "FILE")]
+        reference: Option<PathBuf>,
+
+        /// Allowed slowdown and peak memory growth relative to the reference (0.2 = 20%)
+        #[arg(long, default_value = "0.2")]
+        tolerance: f64,
+
+        /// Write the JSON report to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Inventory the targets: files, lines, parseability and applicable rules per language
+    Stats {
+        /// Files or directories to inventory (default: current directory)
+        #[arg(value_name = "PATH")]
+        targets: Vec<PathBuf>,
+
+        /// Rule files, directories or packs to count applicable rules from (default: --config)
+        #[arg(short, long)]
+        rules: Vec<PathBuf>,
+
+        /// Languages to inventory (default: all supported)
+        #[arg(short, long)]
+        language: Vec<String>,
+
+        /// Exclude patterns (glob patterns)
+        #[arg(short, long)]
+        exclude: Vec<String>,
+
+        /// Include only files matching these patterns
+        #[arg(long, value_name = "PATTERN")]
+        include: Vec<String>,
+
+        /// Output format (text or json)
+        #[arg(short = 'f', long, default_value = "text")]
+        format: OutputFormatCli,
+    },
+
     /// List supported languages and their extensions (deprecated, use 'info')
     Languages,
 
@@ -217,6 +753,77 @@ pub enum Commands {
     Version,
 }
 
+#[derive(Subcommand)]
+pub enum RulesCommands {
+    /// Scaffold a rule interactively: proposes a pattern from example code and writes the rule with a test fixture
+    New {
+        /// Id of the rule, also the name of its files (asked for when missing)
+        #[arg(value_name = "ID")]
+        id: Option<String>,
+
+        /// Language of the rule (asked for when missing)
+        #[arg(short, long = "lang", alias = "language")]
+        lang: Option<String>,
+
+        /// Directory the rule and its fixture are written to
+        #[arg(short, long, default_value = ".")]
+        dir: PathBuf,
+
+        /// Replace existing files
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Bundle a rule pack directory (manifest.yaml, rules/, fixtures/) into a .astgrep-pack archive
+    Pack {
+        /// Rule pack source directory
+        #[arg(value_name = "DIR")]
+        dir: PathBuf,
+
+        /// Output archive (default: <name>-<version>.astgrep-pack)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Apply benign mutations to rule fixtures and report which ones make rules stop matching
+    MutateTest {
+        /// Rule files, directories or packs
+        #[arg(short, long, required = true)]
+        rules: Vec<PathBuf>,
+
+        /// Fixture files or directories (default: files next to each rule file with the same stem)
+        #[arg(value_name = "FIXTURE")]
+        fixtures: Vec<PathBuf>,
+
+        /// Exit with an error if any mutation makes a rule stop matching
+        #[arg(long)]
+        fail_on_brittle: bool,
+
+        /// Exclude patterns (glob patterns)
+        #[arg(long)]
+        exclude: Vec<String>,
+
+        /// Also probe fixtures ignored by .gitignore files and .git/info/exclude
+        #[arg(long)]
+        no_git_ignore: bool,
+    },
+
+    /// Estimate how many files and locations rules would hit, using the project index instead of a full scan
+    Impact {
+        /// Rule files, directories or packs
+        #[arg(short, long, required = true)]
+        rules: Vec<PathBuf>,
+
+        /// Project root whose index is used (refreshed incrementally first)
+        #[arg(long, default_value = ".")]
+        root: PathBuf,
+
+        /// Output format (text or json)
+        #[arg(short = 'f', long, default_value = "text")]
+        format: OutputFormatCli,
+    },
+}
+
 #[derive(Clone, ValueEnum)]
 pub enum OnOffCli {
     On,
@@ -224,6 +831,68 @@ pub enum OnOffCli {
 }
 
 #[derive(Clone, ValueEnum)]
+pub enum TrendGroupCli {
+    Severity,
+    Rule,
+    Directory,
+}
+
+#[derive(Clone, ValueEnum)]
+pub enum TestFilesCli {
+    Include,
+    Skip,
+    Downgrade,
+    Only,
+}
+
+#[derive(Clone, ValueEnum)]
+pub enum FixConflictsCli {
+    SkipLater,
+    PreferSeverity,
+    Prompt,
+}
+
+#[derive(Clone, ValueEnum)]
+pub enum FailOnCli {
+    Error,
+    Warning,
+    Info,
+    Never,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum HookCli {
+    PreCommit,
+    PrePush,
+}
+
+impl From<HookCli> for commands::hook::HookKind {
+    fn from(hook: HookCli) -> Self {
+        match hook {
+            HookCli::PreCommit => commands::hook::HookKind::PreCommit,
+            HookCli::PrePush => commands::hook::HookKind::PrePush,
+        }
+    }
+}
+
+#[derive(Clone, ValueEnum)]
+pub enum AstFormatCli {
+    /// Indented tree, one node per line
+    Text,
+    /// JSON with the full text of every node
+    Json,
+    /// S-expression
+    Sexp,
+}
+
+#[derive(Clone, ValueEnum)]
+pub enum ColorCli {
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Clone, Debug, ValueEnum)]
 pub enum OutputFormatCli {
     /// Human-readable text format
     Text,
@@ -243,6 +912,15 @@ pub enum OutputFormatCli {
     Table,
     /// YAML format
     Yaml,
+    /// Code Climate engine issues (null-delimited JSON)
+    #[value(alias = "codeclimate")]
+    CodeClimate,
+    /// JSON Lines, one finding per line, streamed as files complete
+    #[value(alias = "ndjson")]
+    Jsonl,
+    /// GitHub Actions workflow commands, shown as annotations on pull requests
+    #[value(alias = "github")]
+    GithubActions,
 }
 
 #[derive(Clone, ValueEnum)]
@@ -274,7 +952,8 @@ pub async fn run() -> Result<()> {
     let cli = Cli::parse();
 
     // Set up logging level based on flags
-    setup_logging(cli.verbose, cli.quiet)?;
+    setup_logging(cli.verbose, cli.quiet, cli.trace_matching.as_deref(), cli.trace_rules.clone())?;
+    info!("Starting astgrep-cli - Command Line Interface for astgrep");
 
     // Enable performance profiling if requested
     if cli.profile {
@@ -282,58 +961,57 @@ pub async fn run() -> Result<()> {
     }
 
     match cli.command {
-        Commands::Analyze {
-            targets,
-            rules,
-            language,
-            exclude,
-            include,
-            format,
-            output,
-            severity,
-            confidence,
-            metrics,
-            max_findings,
-            dataflow,
-            baseline,
-            fail_on_findings,
-            no_parallel,
-            max_threads,
-            compatible,
-            sql_statement_boundary,
-        } => {
+        Commands::Analyze(mut args) => {
+            if args.schema {
+                println!("{}", serde_json::to_string_pretty(&astgrep_core::report_schema())?);
+                return Ok(());
+            }
             info!("Starting code analysis");
 
             // Use --config parameter if provided and no rules specified, otherwise use rules
-            let rule_files = if rules.is_empty() && cli.config.is_some() {
+            let rule_files = if args.rules.is_empty() && cli.config.is_some() {
                 vec![cli.config.unwrap()]
             } else {
-                rules
+                std::mem::take(&mut args.rules)
+            };
+
+            // An ad-hoc pattern is analyzed as the only rule, from a rule file in a temporary
+            // directory removed when this arm returns
+            let mut pattern_dir = None;
+            let rule_files = match args.pattern {
+                Some(ref pattern) => {
+                    let languages = args
+                        .language
+                        .iter()
+                        .map(|name| Language::from_str(name).ok_or_else(|| anyhow::anyhow!("Unknown language: {}", name)))
+                        .collect::<Result<Vec<_>>>()?;
+                    let dir = pattern_dir.insert(tempfile::Builder::new().prefix("astgrep-pattern-").tempdir()?);
+                    vec![commands::pattern_query::write_rule(pattern, &languages, dir.path())?]
+                }
+                None => commands::registry::Registry::from_env().resolve_all(rule_files)?,
             };
 
-            let config = build_enhanced_analysis_config(
-                targets,
-                rule_files,
-                language,
-                exclude,
-                include,
-                format,
-                severity,
-                confidence,
-                metrics,
-                max_findings,
-                dataflow,
-                baseline,
-                fail_on_findings,
-                !no_parallel,
-                max_threads.or(if cli.threads > 0 { Some(cli.threads) } else { None }),
-                cli.profile,
-                compatible,
-                Some(matches!(sql_statement_boundary, OnOffCli::On)),
-            )?;
+            args.rules = rule_files;
+            args.max_threads = args.max_threads.or(if cli.threads > 0 { Some(cli.threads) } else { None });
+            args.no_progress |= cli.quiet;
+            let output = args.output.take();
+            let config = build_enhanced_analysis_config(*args, cli.profile)?;
 
             commands::analyze_enhanced::run_enhanced(config, output).await
         }
+        Commands::Ci { rules, sarif_output, fail_on, full_scan, base_ref } => {
+            info!("Running CI analysis");
+            commands::ci::run(rules, sarif_output, convert_fail_on(fail_on), full_scan, base_ref).await
+        }
+        Commands::InstallHook { hook, rules, fail_on, profile, force } => {
+            info!("Installing git hook");
+            let options = commands::hook::HookOptions { rules, fail_on: convert_fail_on(fail_on), profile };
+            commands::hook::install(hook.into(), options, force).await
+        }
+        Commands::RunHook { hook, rules, fail_on, profile } => {
+            let options = commands::hook::HookOptions { rules, fail_on: convert_fail_on(fail_on), profile };
+            commands::hook::run(hook.into(), options).await
+        }
         Commands::Validate { rule_files, format, language, performance } => {
             info!("Validating rule files");
             // Use --config parameter if provided and no rule_files specified, otherwise use rule_files
@@ -364,6 +1042,117 @@ pub async fn run() -> Result<()> {
             info!("Updating rules");
             commands::update::run(repository, directory, force).await
         }
+        Commands::Completions { shell, rules } => commands::completions::run(shell, rules).await,
+        Commands::Login { registry } => {
+            info!("Logging in to rule registry");
+            commands::login::run(registry).await
+        }
+        Commands::Logout { registry } => {
+            info!("Logging out of rule registry");
+            commands::login::logout(registry).await
+        }
+        Commands::Rules { command } => match command {
+            RulesCommands::New { id, lang, dir, force } => {
+                info!("Scaffolding a new rule");
+                commands::rule_wizard::run(id, lang, dir, force).await
+            }
+            RulesCommands::Pack { dir, output } => {
+                info!("Building rule pack");
+                commands::rules::pack(dir, output).await
+            }
+            RulesCommands::MutateTest { rules, fixtures, fail_on_brittle, exclude, no_git_ignore } => {
+                info!("Running mutation tests");
+                commands::rules::mutate_test(rules, fixtures, fail_on_brittle, exclude, !no_git_ignore).await
+            }
+            RulesCommands::Impact { rules, root, format } => {
+                info!("Estimating rule impact");
+                commands::rules::impact(rules, root, format).await
+            }
+        },
+        Commands::Index { path, rebuild, exclude, no_git_ignore } => {
+            info!("Updating project index");
+            commands::index::run(path, rebuild, exclude, !no_git_ignore).await
+        }
+        Commands::Trends { db, by, last, format } => {
+            info!("Reporting finding trends");
+            let group = match by {
+                TrendGroupCli::Severity => commands::trends::TrendGroup::Severity,
+                TrendGroupCli::Rule => commands::trends::TrendGroup::Rule,
+                TrendGroupCli::Directory => commands::trends::TrendGroup::Directory,
+            };
+            commands::trends::run(db, group, last, format).await
+        }
+        Commands::Rewrite { paths, lang, pattern, replace, in_place, dry_run, interactive, exclude, no_git_ignore } => {
+            info!("Rewriting code");
+            let targets = commands::rewrite::RewriteTargets { paths, exclude, git_ignore: !no_git_ignore };
+            commands::rewrite::run(targets, lang, pattern, replace, in_place, dry_run, interactive).await
+        }
+        Commands::Test { paths, format, exclude, no_git_ignore } => {
+            info!("Running rule tests");
+            commands::rule_tests::run(paths, format, exclude, !no_git_ignore).await
+        }
+        Commands::Fmt { paths, check, exclude, no_git_ignore } => {
+            info!("Formatting rule files");
+            commands::fmt::run(paths, check, exclude, !no_git_ignore).await
+        }
+        Commands::Convert { input, to, output } => {
+            info!("Converting findings file");
+            commands::convert::run(input, to, output).await
+        }
+        Commands::MergeReports { inputs, to, output } => {
+            info!("Merging {} report(s)", inputs.len());
+            commands::merge_reports::run(inputs, to, output).await
+        }
+        Commands::Explain { rule_id, rules, format } => {
+            info!("Explaining rule {}", rule_id);
+            let rules = if rules.is_empty() { cli.config.into_iter().collect() } else { rules };
+            let rules = commands::registry::Registry::from_env().resolve_all(rules)?;
+            commands::explain::run(rule_id, rules, format).await
+        }
+        Commands::DumpAst { file, language, format } => {
+            info!("Dumping syntax tree of {}", file.display());
+            let format = match format {
+                AstFormatCli::Text => commands::dump_ast::AstFormat::Text,
+                AstFormatCli::Json => commands::dump_ast::AstFormat::Json,
+                AstFormatCli::Sexp => commands::dump_ast::AstFormat::SExpr,
+            };
+            commands::dump_ast::run(file, language, format).await
+        }
+        Commands::Lsp { rules } => {
+            info!("Starting language server");
+            let rules = commands::registry::Registry::from_env().resolve_all(rules)?;
+            commands::lsp::run(rules).await
+        }
+        Commands::Vscode { workspace, rules, listen, interval_ms } => {
+            info!("Starting VS Code diagnostics server");
+            let rules = commands::registry::Registry::from_env().resolve_all(rules)?;
+            commands::vscode::run(workspace, rules, listen, std::time::Duration::from_millis(interval_ms)).await
+        }
+        Commands::Bench { targets, rules, iterations, budget_ms, slowest, format } => {
+            info!("Benchmarking rules");
+            let rules = commands::registry::Registry::from_env().resolve_all(rules)?;
+            commands::bench::run(targets, rules, iterations, budget_ms, slowest, format).await
+        }
+        Commands::SelfBench { iterations, reference, tolerance, output } => {
+            info!("Running self-benchmark");
+            commands::self_bench::run(iterations, reference, tolerance, output).await
+        }
+        Commands::Stats { targets, rules, language, exclude, include, format } => {
+            info!("Taking codebase inventory");
+            let rules = if rules.is_empty() { cli.config.into_iter().collect() } else { rules };
+            let rules = commands::registry::Registry::from_env().resolve_all(rules)?;
+            let mut languages = Vec::new();
+            for name in language {
+                match Language::from_str(&name) {
+                    Some(language) => languages.push(language),
+                    None => warn!("Unknown language: {}, skipping", name),
+                }
+            }
+            if languages.is_empty() {
+                languages = DEFAULT_LANGUAGES.to_vec();
+            }
+            commands::stats::run(targets, rules, languages, exclude, include, format).await
+        }
         Commands::Languages => {
             warn!("'languages' command is deprecated, use 'info --extensions' instead");
             commands::languages::run().await
@@ -375,8 +1164,9 @@ pub async fn run() -> Result<()> {
     }
 }
 
-fn setup_logging(verbose: bool, quiet: bool) -> Result<()> {
-    use tracing_subscriber::{fmt, EnvFilter};
+fn setup_logging(verbose: bool, quiet: bool, trace_matching: Option<&std::path::Path>, trace_rules: Vec<String>) -> Result<()> {
+    use tracing_subscriber::layer::SubscriberExt;
+    use tracing_subscriber::{fmt, EnvFilter, Layer};
 
     let level = if quiet {
         tracing::Level::ERROR
@@ -386,40 +1176,97 @@ fn setup_logging(verbose: bool, quiet: bool) -> Result<()> {
         tracing::Level::INFO
     };
 
+    let stderr = fmt::layer()
+        .with_target(false)
+        .with_thread_ids(verbose)
+        .with_file(verbose)
+        .with_line_number(verbose)
+        .with_writer(std::io::stderr)
+        .with_filter(EnvFilter::from_default_env().add_directive(level.into()));
+    let match_trace = match trace_matching {
+        Some(path) => Some(commands::match_trace::MatchTraceLayer::create(path, trace_rules)?.with_filter(commands::match_trace::targets())),
+        None => None,
+    };
+
     // Try to set global default, but don't fail if already set
-    let _ = tracing::subscriber::set_global_default(
-        fmt::Subscriber::builder()
-            .with_env_filter(EnvFilter::from_default_env().add_directive(level.into()))
-            .with_target(false)
-            .with_thread_ids(verbose)
-            .with_file(verbose)
-            .with_line_number(verbose)
-            .finish(),
-    );
+    let _ = tracing::subscriber::set_global_default(tracing_subscriber::registry().with(stderr).with(match_trace));
 
     Ok(())
 }
 
-fn build_enhanced_analysis_config(
-    targets: Vec<PathBuf>,
-    rules: Vec<PathBuf>,
-    languages: Vec<String>,
-    exclude: Vec<String>,
-    include: Vec<String>,
-    format: OutputFormatCli,
-    severity: SeverityFilter,
-    confidence: ConfidenceFilter,
-    metrics: bool,
-    max_findings: usize,
-    dataflow: bool,
-    baseline: Option<PathBuf>,
-    fail_on_findings: bool,
-    parallel: bool,
-    max_threads: Option<usize>,
-    profile: bool,
-    compatible: Option<String>,
-    sql_statement_boundary: Option<bool>,
-) -> Result<EnhancedAnalysisConfig> {
+/// Languages analyzed when none are given
+pub(crate) const DEFAULT_LANGUAGES: [Language; 8] = [
+    Language::Java,
+    Language::JavaScript,
+    Language::Python,
+    Language::Php,
+    Language::Sql,
+    Language::Bash,
+    Language::CSharp,
+    Language::C,
+];
+
+/// The analysis configuration of `astgrep analyze`; `args.rules` are the resolved rule
+/// files and `profile` is the global `--profile` flag
+fn build_enhanced_analysis_config(args: AnalyzeArgs, profile: bool) -> Result<EnhancedAnalysisConfig> {
+    let AnalyzeArgs {
+        targets,
+        rules,
+        language: languages,
+        exclude,
+        include,
+        format,
+        severity,
+        confidence,
+        metrics,
+        max_findings,
+        max_target_bytes,
+        timeout,
+        dataflow,
+        taint_models,
+        baseline,
+        baseline_out,
+        fail_on_findings,
+        no_parallel,
+        max_threads,
+        compatible,
+        sql_statement_boundary,
+        source_maps,
+        workspace,
+        image,
+        emit_patches,
+        combine_patches,
+        emit_tests,
+        redact,
+        color,
+        language_overrides,
+        record,
+        supply_chain,
+        test_files,
+        interactive,
+        profile_name,
+        min_risk_score,
+        filter,
+        context,
+        sort_by_risk,
+        fix,
+        fix_dry_run,
+        fix_conflicts,
+        diagnostics,
+        disable_nosem,
+        report_suppressed,
+        csv_columns,
+        output_template,
+        no_progress,
+        stdin_filename,
+        no_git_ignore,
+        metrics_out,
+        policy,
+        cache_dir,
+        pattern: _,
+        output: _,
+        schema: _,
+    } = args;
     let target_paths = if targets.is_empty() {
         vec![PathBuf::from(".")]
     } else {
@@ -427,16 +1274,7 @@ fn build_enhanced_analysis_config(
     };
 
     let parsed_languages = if languages.is_empty() {
-        vec![
-            Language::Java,
-            Language::JavaScript,
-            Language::Python,
-            Language::Php,
-            Language::Sql,
-            Language::Bash,
-            Language::CSharp,
-            Language::C,
-        ]
+        DEFAULT_LANGUAGES.to_vec()
     } else {
         let mut parsed = Vec::new();
         for lang_str in languages {
@@ -455,6 +1293,42 @@ fn build_enhanced_analysis_config(
 
     let output_format = convert_output_format(format);
 
+    let redact = match redact {
+        Some(spec) => astgrep_core::Redaction::parse(&spec).map_err(|e| anyhow::anyhow!("Invalid --redact: {}", e))?,
+        None => None,
+    };
+
+    let csv_columns = match csv_columns {
+        Some(spec) => commands::csv::parse_columns(&spec).map_err(|e| anyhow::anyhow!("Invalid --csv-columns: {}", e))?,
+        None => commands::csv::DEFAULT_COLUMNS.to_vec(),
+    };
+
+    let policy = match policy {
+        Some(path) => Some(astgrep_rules::RulePolicy::read(&path).map_err(|e| anyhow::anyhow!("Invalid --policy: {}", e))?),
+        None => None,
+    };
+
+    let finding_filter = match filter {
+        Some(expr) => Some(commands::finding_filter::FindingFilter::parse(&expr).map_err(|e| anyhow::anyhow!("Invalid --filter: {}", e))?),
+        None => None,
+    };
+
+    let language_overrides = language_overrides
+        .iter()
+        .map(|spec| LanguageOverride::parse(spec).map_err(|e| anyhow::anyhow!("Invalid --language-override: {}", e)))
+        .collect::<Result<Vec<_>>>()?;
+
+    let scan_profile = match profile_name {
+        Some(name) => Some(commands::scan_profile::ScanProfile::resolve(
+            &name,
+            Path::new(astgrep_core::constants::paths::CONFIG_FILE),
+        )?),
+        None => None,
+    };
+
+    let risk_model = commands::risk::RiskModel::load(Path::new(astgrep_core::constants::paths::CONFIG_FILE))?;
+    let taint_models = commands::taint_models::load(Path::new(astgrep_core::constants::paths::CONFIG_FILE), &taint_models)?;
+
     Ok(EnhancedAnalysisConfig {
         target_paths,
         exclude_patterns: exclude,
@@ -466,14 +1340,65 @@ fn build_enhanced_analysis_config(
         confidence_filter: convert_confidence_filter(confidence),
         include_metrics: metrics,
         max_findings: if max_findings == 0 { None } else { Some(max_findings) },
-        enable_dataflow: dataflow,
+        max_target_bytes: if max_target_bytes == 0 { None } else { Some(max_target_bytes) },
+        file_timeout: if timeout == 0 { None } else { Some(std::time::Duration::from_secs(timeout)) },
+        enable_dataflow: dataflow || scan_profile.as_ref().is_some_and(|p| p.dataflow),
+        taint_models: taint_models.map(std::sync::Arc::new),
+        project_index: None,
         baseline_file: baseline,
+        baseline_out,
         fail_on_findings,
-        parallel,
+        parallel: !no_parallel,
         max_threads,
         enable_profiling: profile,
         compatible_mode: compatible,
-        sql_statement_boundary,
+        sql_statement_boundary: Some(matches!(sql_statement_boundary, OnOffCli::On)),
+        map_source_locations: source_maps,
+        workspace,
+        image,
+        emit_patches,
+        combine_patches,
+        emit_tests,
+        redact,
+        color: match color {
+            ColorCli::Auto => ColorChoice::Auto,
+            ColorCli::Always => ColorChoice::Always,
+            ColorCli::Never => ColorChoice::Never,
+        },
+        language_overrides,
+        record,
+        supply_chain,
+        test_files: match test_files {
+            TestFilesCli::Include => astgrep_core::TestFilePolicy::Include,
+            TestFilesCli::Skip => astgrep_core::TestFilePolicy::Skip,
+            TestFilesCli::Downgrade => astgrep_core::TestFilePolicy::Downgrade,
+            TestFilesCli::Only => astgrep_core::TestFilePolicy::Only,
+        },
+        interactive,
+        scan_profile,
+        risk_model,
+        min_risk_score,
+        finding_filter,
+        context_lines: context,
+        sort_by_risk,
+        fix,
+        fix_dry_run,
+        fix_conflicts: match fix_conflicts {
+            FixConflictsCli::SkipLater => commands::autofix::ConflictStrategy::SkipLater,
+            FixConflictsCli::PreferSeverity => commands::autofix::ConflictStrategy::PreferSeverity,
+            FixConflictsCli::Prompt => commands::autofix::ConflictStrategy::Prompt,
+        },
+        diagnostics,
+        disable_nosem,
+        report_suppressed,
+        csv_columns,
+        output_template,
+        progress: !no_progress,
+        stdin_filename,
+        git_ignore: !no_git_ignore,
+        metrics_out,
+        policy,
+        cache_dir,
     })
 }
 
@@ -484,10 +1409,13 @@ fn convert_output_format(format: OutputFormatCli) -> OutputFormat {
         OutputFormatCli::Sarif => OutputFormat::Sarif,
         OutputFormatCli::Xml => OutputFormat::Xml,
         OutputFormatCli::Yaml => OutputFormat::Yaml,
+        OutputFormatCli::CodeClimate => OutputFormat::CodeClimate,
+        OutputFormatCli::Csv => OutputFormat::Csv,
+        OutputFormatCli::Html => OutputFormat::Html,
+        OutputFormatCli::Markdown => OutputFormat::Markdown,
+        OutputFormatCli::Jsonl => OutputFormat::Jsonl,
+        OutputFormatCli::GithubActions => OutputFormat::GithubActions,
         // Map unsupported formats to closest equivalent
-        OutputFormatCli::Csv => OutputFormat::Text,
-        OutputFormatCli::Html => OutputFormat::Text,
-        OutputFormatCli::Markdown => OutputFormat::Text,
         OutputFormatCli::Table => OutputFormat::Text,
     }
 }
@@ -501,6 +1429,15 @@ fn convert_severity_filter(filter: SeverityFilter) -> Option<Severity> {
     }
 }
 
+fn convert_fail_on(fail_on: FailOnCli) -> Option<Severity> {
+    match fail_on {
+        FailOnCli::Error => Some(Severity::Error),
+        FailOnCli::Warning => Some(Severity::Warning),
+        FailOnCli::Info => Some(Severity::Info),
+        FailOnCli::Never => None,
+    }
+}
+
 fn convert_confidence_filter(filter: ConfidenceFilter) -> Option<Confidence> {
     match filter {
         ConfidenceFilter::All => None,
@@ -527,16 +1464,7 @@ fn build_analysis_config(
     };
 
     let parsed_languages = if languages.is_empty() {
-        vec![
-            Language::Java,
-            Language::JavaScript,
-            Language::Python,
-            Language::Php,
-            Language::Sql,
-            Language::Bash,
-            Language::CSharp,
-            Language::C,
-        ]
+        DEFAULT_LANGUAGES.to_vec()
     } else {
         let mut parsed = Vec::new();
         for lang_str in languages {
@@ -580,14 +1508,186 @@ pub struct EnhancedAnalysisConfig {
     pub confidence_filter: Option<Confidence>,
     pub include_metrics: bool,
     pub max_findings: Option<usize>,
+    /// Skip target files larger than this
+    pub max_target_bytes: Option<u64>,
+    /// Abort the analysis of a file that takes longer than this
+    pub file_timeout: Option<std::time::Duration>,
     pub enable_dataflow: bool,
+    /// Library function models consulted by dataflow rules
+    pub taint_models: Option<std::sync::Arc<astgrep_dataflow::ModelSet>>,
+    /// Project index dataflow rules follow across files, set for the analyzed files when
+    /// dataflow is enabled
+    pub project_index: Option<std::sync::Arc<astgrep_dataflow::ProjectIndex>>,
+    /// Report only findings missing from this baseline
     pub baseline_file: Option<PathBuf>,
+    /// Write the run's findings to this baseline file
+    pub baseline_out: Option<PathBuf>,
     pub fail_on_findings: bool,
     pub parallel: bool,
     pub max_threads: Option<usize>,
     pub enable_profiling: bool,
     pub compatible_mode: Option<String>,
     pub sql_statement_boundary: Option<bool>,
+    pub map_source_locations: bool,
+    pub workspace: bool,
+    pub image: Option<String>,
+    pub emit_patches: Option<PathBuf>,
+    pub combine_patches: bool,
+    /// Directory for per-finding reproduction fixtures and rule files
+    pub emit_tests: Option<PathBuf>,
+    /// Default redaction of matched text, overridable per rule
+    pub redact: Option<astgrep_core::Redaction>,
+    /// When to color text output; resolved against the output target before rendering
+    pub color: ColorChoice,
+    /// Languages forced for the files under a directory
+    pub language_overrides: Vec<LanguageOverride>,
+    /// Trend database each run's findings are appended to
+    pub record: Option<PathBuf>,
+    /// Run the manifest and lockfile checks on directory targets
+    pub supply_chain: bool,
+    /// Default treatment of findings in test files, overridable per rule
+    pub test_files: astgrep_core::TestFilePolicy,
+    /// Open the findings in the terminal results browser instead of printing them
+    pub interactive: bool,
+    /// Named scan profile of the run, reported with the results
+    pub scan_profile: Option<commands::scan_profile::ScanProfile>,
+    /// Weights of the per-finding risk score
+    pub risk_model: commands::risk::RiskModel,
+    /// Drop findings scoring below this risk
+    pub min_risk_score: Option<u8>,
+    /// `--filter` expression findings must match to be reported
+    pub finding_filter: Option<commands::finding_filter::FindingFilter>,
+    /// Lines of source shown around each finding (`--context`)
+    pub context_lines: Option<usize>,
+    /// Order findings by descending risk score
+    pub sort_by_risk: bool,
+    /// Rewrite the matched spans of source files with the findings' fixes
+    pub fix: bool,
+    /// Print the edits of `fix` as diffs instead of applying them
+    pub fix_dry_run: bool,
+    /// Which of two overlapping fixes is applied
+    pub fix_conflicts: commands::autofix::ConflictStrategy,
+    /// File the fidelity diagnostics of the run are written to
+    pub diagnostics: Option<PathBuf>,
+    /// Report findings despite inline suppression comments
+    pub disable_nosem: bool,
+    /// Keep suppressed findings for the JSON and SARIF reports
+    pub report_suppressed: bool,
+    /// Columns of the CSV output, in order
+    pub csv_columns: Vec<commands::csv::CsvColumn>,
+    /// Template the results are rendered through instead of the output format
+    pub output_template: Option<PathBuf>,
+    /// Show a progress bar on stderr when it is a terminal
+    pub progress: bool,
+    /// Path reported for source read from stdin
+    pub stdin_filename: Option<PathBuf>,
+    /// Skip paths ignored by git as well as by `.astgrepignore` files
+    pub git_ignore: bool,
+    /// OpenMetrics file the scan metrics are written to
+    pub metrics_out: Option<PathBuf>,
+    /// Rule policy applied after loading the rule files
+    pub policy: Option<astgrep_rules::RulePolicy>,
+    /// Directory of the incremental analysis cache
+    pub cache_dir: Option<PathBuf>,
+}
+
+impl Default for EnhancedAnalysisConfig {
+    fn default() -> Self {
+        Self {
+            target_paths: vec![PathBuf::from(".")],
+            exclude_patterns: Vec::new(),
+            include_patterns: Vec::new(),
+            languages: Vec::new(),
+            rule_files: Vec::new(),
+            output_format: OutputFormat::Json,
+            severity_filter: None,
+            confidence_filter: None,
+            include_metrics: false,
+            max_findings: None,
+            max_target_bytes: Some(commands::target_filter::DEFAULT_MAX_TARGET_BYTES),
+            file_timeout: Some(std::time::Duration::from_secs(commands::isolation::DEFAULT_FILE_TIMEOUT_SECS)),
+            enable_dataflow: false,
+            taint_models: None,
+            project_index: None,
+            baseline_file: None,
+            baseline_out: None,
+            fail_on_findings: false,
+            parallel: true,
+            max_threads: None,
+            enable_profiling: false,
+            compatible_mode: None,
+            sql_statement_boundary: None,
+            map_source_locations: false,
+            workspace: false,
+            image: None,
+            emit_patches: None,
+            combine_patches: false,
+            emit_tests: None,
+            redact: None,
+            color: ColorChoice::Auto,
+            language_overrides: Vec::new(),
+            record: None,
+            supply_chain: false,
+            test_files: astgrep_core::TestFilePolicy::Include,
+            interactive: false,
+            scan_profile: None,
+            risk_model: commands::risk::RiskModel::default(),
+            min_risk_score: None,
+            finding_filter: None,
+            context_lines: None,
+            sort_by_risk: false,
+            fix: false,
+            fix_dry_run: false,
+            fix_conflicts: commands::autofix::ConflictStrategy::SkipLater,
+            diagnostics: None,
+            disable_nosem: false,
+            report_suppressed: false,
+            csv_columns: commands::csv::DEFAULT_COLUMNS.to_vec(),
+            output_template: None,
+            progress: false,
+            stdin_filename: None,
+            git_ignore: true,
+            metrics_out: None,
+            policy: None,
+            cache_dir: None,
+        }
+    }
+}
+
+impl EnhancedAnalysisConfig {
+    /// Language forced for `path` by the most specific override covering it
+    pub fn language_override(&self, path: &Path) -> Option<Language> {
+        let path = without_cur_dir(path);
+        self.language_overrides
+            .iter()
+            .filter(|o| path.starts_with(without_cur_dir(&o.directory)))
+            .max_by_key(|o| o.directory.components().count())
+            .map(|o| o.language)
+    }
+}
+
+/// Forces the language of every file under `directory`
+#[derive(Debug, Clone, PartialEq)]
+pub struct LanguageOverride {
+    pub directory: PathBuf,
+    pub language: Language,
+}
+
+impl LanguageOverride {
+    /// Parse `DIR=LANG`
+    pub fn parse(spec: &str) -> Result<Self> {
+        let (directory, language) = spec
+            .rsplit_once('=')
+            .ok_or_else(|| anyhow::anyhow!("expected DIR=LANG, got '{}'", spec))?;
+        let language = Language::from_str(language.trim())
+            .ok_or_else(|| anyhow::anyhow!("unknown language '{}' in '{}'", language.trim(), spec))?;
+        Ok(Self { directory: PathBuf::from(directory.trim()), language })
+    }
+}
+
+/// `path` without `.` components, so `./src/a.php` and `src` compare as expected
+fn without_cur_dir(path: &Path) -> PathBuf {
+    path.components().filter(|c| !matches!(c, std::path::Component::CurDir)).collect()
 }
 
 #[cfg(test)]
@@ -665,4 +1765,24 @@ mod tests {
         assert!(result.is_err());
         assert!(result.unwrap_err().to_string().contains("Unknown output format"));
     }
+
+    #[test]
+    fn test_build_enhanced_analysis_config_from_args() {
+        let cli = Cli::try_parse_from(["astgrep", "analyze", "--no-parallel", "--no-git-ignore", "--max-findings", "5", "-l", "python", "src"]).unwrap();
+        let Commands::Analyze(args) = cli.command else { panic!("expected analyze") };
+        let config = build_enhanced_analysis_config(*args, false).unwrap();
+        assert_eq!(config.target_paths, vec![PathBuf::from("src")]);
+        assert_eq!(config.languages, vec![Language::Python]);
+        assert_eq!(config.max_findings, Some(5));
+        assert_eq!(config.sql_statement_boundary, Some(true));
+        assert!(!config.parallel && !config.git_ignore && config.progress);
+    }
+
+    #[test]
+    fn test_language_override_parse() {
+        let parsed = LanguageOverride::parse("legacy/templates=php").unwrap();
+        assert_eq!(parsed, LanguageOverride { directory: PathBuf::from("legacy/templates"), language: Language::Php });
+        assert!(LanguageOverride::parse("legacy").is_err());
+        assert!(LanguageOverride::parse("legacy=cobol").is_err());
+    }
 }
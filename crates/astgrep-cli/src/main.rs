@@ -1,17 +1,7 @@
 use anyhow::Result;
-use tracing::{info, Level};
-use tracing_subscriber::FmtSubscriber;
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Initialize tracing
-    let subscriber = FmtSubscriber::builder()
-        .with_max_level(Level::INFO)
-        .finish();
-    tracing::subscriber::set_global_default(subscriber)?;
-
-    info!("Starting astgrep-cli - Command Line Interface for astgrep");
-
-    // Delegate to CLI module
+    // Delegate to CLI module, which sets up logging from the command line
     astgrep_cli::run().await
 }
@@ -264,16 +264,24 @@ fn calculate_duration_stats(durations: &[Duration]) -> DurationStats {
     }
 }
 
-fn get_memory_usage() -> u64 {
-    // This is a simplified implementation
-    // In a real implementation, you might use system-specific APIs
-    // or libraries like `sysinfo` to get actual memory usage
-    
+pub(crate) fn get_memory_usage() -> u64 {
+    // Resident set size of the process; 0 where /proc is not available
+    read_status_bytes("VmRSS:")
+}
+
+/// Peak resident set size of the process so far, which catches short allocation spikes
+/// that samples of [`get_memory_usage`] miss; 0 where /proc is not available
+pub(crate) fn get_peak_memory_usage() -> u64 {
+    read_status_bytes("VmHWM:")
+}
+
+/// A size field of `/proc/self/status` in bytes
+fn read_status_bytes(field: &str) -> u64 {
     #[cfg(target_os = "linux")]
     {
         if let Ok(status) = std::fs::read_to_string("/proc/self/status") {
             for line in status.lines() {
-                if line.starts_with("VmRSS:") {
+                if line.starts_with(field) {
                     if let Some(kb_str) = line.split_whitespace().nth(1) {
                         if let Ok(kb) = kb_str.parse::<u64>() {
                             return kb * 1024; // Convert KB to bytes
@@ -283,7 +291,9 @@ fn get_memory_usage() -> u64 {
             }
         }
     }
-    
+    #[cfg(not(target_os = "linux"))]
+    let _ = field;
+
     // Fallback: return 0 if we can't determine memory usage
     0
 }
@@ -1,13 +1,15 @@
 //! VS Code IDE integration for astgrep
 //!
 //! This module provides integration with VS Code through the Language Server Protocol (LSP)
-//! and diagnostic reporting capabilities.
+//! and diagnostic reporting capabilities. Diagnostics carry the information the companion
+//! extension needs beyond the LSP basics: related locations, and the edit of a rule's fix
+//! for a quick fix. `astgrep vscode` streams them for a whole workspace as files change.
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 /// Represents a diagnostic message for VS Code
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct VsCodeDiagnostic {
     /// File path
     pub file: String,
@@ -25,6 +27,42 @@ pub struct VsCodeDiagnostic {
     pub end_line: u32,
     /// End column (0-based)
     pub end_column: u32,
+    /// Other locations that explain the diagnostic
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub related_information: Vec<VsCodeRelatedInformation>,
+    /// Edit applying the rule's fix, offered as a quick fix
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fix: Option<VsCodeTextEdit>,
+}
+
+/// A location related to a diagnostic, with a message saying how
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VsCodeRelatedInformation {
+    pub file: String,
+    /// Line number (0-based)
+    pub line: u32,
+    /// Column number (0-based)
+    pub column: u32,
+    pub end_line: u32,
+    pub end_column: u32,
+    pub message: String,
+}
+
+/// Replacement of a range of the diagnostic's file (0-based positions)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VsCodeTextEdit {
+    pub line: u32,
+    pub column: u32,
+    pub end_line: u32,
+    pub end_column: u32,
+    pub new_text: String,
+}
+
+fn range(line: u32, column: u32, end_line: u32, end_column: u32) -> serde_json::Value {
+    serde_json::json!({
+        "start": { "line": line, "character": column },
+        "end": { "line": end_line, "character": end_column }
+    })
 }
 
 impl VsCodeDiagnostic {
@@ -46,6 +84,8 @@ impl VsCodeDiagnostic {
             rule_id,
             end_line: line,
             end_column: column + 1,
+            related_information: Vec::new(),
+            fix: None,
         }
     }
 
@@ -56,9 +96,22 @@ impl VsCodeDiagnostic {
         self
     }
 
-    /// Convert to VS Code diagnostic format
+    /// Set the related locations
+    pub fn with_related_information(mut self, related_information: Vec<VsCodeRelatedInformation>) -> Self {
+        self.related_information = related_information;
+        self
+    }
+
+    /// Set the edit of the quick fix
+    pub fn with_fix(mut self, fix: VsCodeTextEdit) -> Self {
+        self.fix = Some(fix);
+        self
+    }
+
+    /// Convert to VS Code diagnostic format; related locations become `relatedInformation`
+    /// and the fix is passed in `data` for the extension's code action provider
     pub fn to_vscode_format(&self) -> serde_json::Value {
-        serde_json::json!({
+        let mut diagnostic = serde_json::json!({
             "range": {
                 "start": {
                     "line": self.line,
@@ -73,7 +126,30 @@ impl VsCodeDiagnostic {
             "severity": self.severity_to_code(),
             "source": "astgrep",
             "code": self.rule_id
-        })
+        });
+        if !self.related_information.is_empty() {
+            diagnostic["relatedInformation"] = self
+                .related_information
+                .iter()
+                .map(|related| serde_json::json!({
+                    "location": {
+                        "uri": file_uri(&related.file),
+                        "range": range(related.line, related.column, related.end_line, related.end_column)
+                    },
+                    "message": related.message
+                }))
+                .collect();
+        }
+        if let Some(ref fix) = self.fix {
+            diagnostic["data"] = serde_json::json!({
+                "fix": {
+                    "title": format!("Apply fix for {}", self.rule_id),
+                    "range": range(fix.line, fix.column, fix.end_line, fix.end_column),
+                    "newText": fix.new_text
+                }
+            });
+        }
+        diagnostic
     }
 
     /// Convert severity string to VS Code severity code
@@ -178,6 +254,36 @@ impl VsCodeExtension {
             .push(diagnostic);
     }
 
+    /// Replace the diagnostics of a file; returns whether they changed
+    pub fn set_diagnostics(&mut self, file: &str, diagnostics: Vec<VsCodeDiagnostic>) -> bool {
+        let current = self.diagnostics.get(file).map_or(&[][..], Vec::as_slice);
+        if current == diagnostics.as_slice() {
+            return false;
+        }
+        if diagnostics.is_empty() {
+            self.diagnostics.remove(file);
+        } else {
+            self.diagnostics.insert(file.to_string(), diagnostics);
+        }
+        true
+    }
+
+    /// Diagnostics of a file the configuration shows: of enabled rules and at or above
+    /// the highlighted severity
+    pub fn visible_diagnostics(&self, file: &str) -> Vec<&VsCodeDiagnostic> {
+        let minimum = match self.config.highlight_severity.as_str() {
+            "error" => 1,
+            "warning" => 2,
+            _ => 4,
+        };
+        self.diagnostics
+            .get(file)
+            .into_iter()
+            .flatten()
+            .filter(|diagnostic| self.is_rule_enabled(&diagnostic.rule_id) && diagnostic.severity_to_code() <= minimum)
+            .collect()
+    }
+
     /// Get diagnostics for a file
     pub fn get_diagnostics(&self, file: &str) -> Option<&Vec<VsCodeDiagnostic>> {
         self.diagnostics.get(file)
@@ -245,10 +351,15 @@ impl VsCodeExtension {
         }
 
         if pattern.contains('*') {
-            // Handle patterns like "**/*.java"
-            if pattern.starts_with("**/") {
-                let suffix = &pattern[3..]; // Remove "**/"
-                return file.ends_with(suffix);
+            // Handle patterns like "**/*.java": the rest matches the path below any directory
+            if let Some(rest) = pattern.strip_prefix("**/") {
+                return self.matches_pattern(file, rest)
+                    || file.match_indices('/').any(|(index, _)| self.matches_pattern(&file[index + 1..], rest));
+            }
+
+            // Handle patterns like "node_modules/**"
+            if let Some(dir) = pattern.strip_suffix("/**") {
+                return file.starts_with(&format!("{}/", dir));
             }
 
             // Handle patterns like "*.java"
@@ -285,6 +396,19 @@ impl Default for VsCodeExtension {
     }
 }
 
+/// `file://` URI of a path, percent-encoding what URIs reserve
+pub fn file_uri(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    let mut uri = String::from(if path.starts_with('/') { "file://" } else { "file:///" });
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' | b':' => uri.push(byte as char),
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -361,6 +485,9 @@ mod tests {
         assert!(ext.should_analyze_file("test.java"));
         assert!(ext.should_analyze_file("test.js"));
         assert!(!ext.should_analyze_file("node_modules/test.js"));
+        assert!(ext.should_analyze_file("/work/src/App.kt"));
+        assert!(!ext.should_analyze_file("/work/web/node_modules/lib/index.js"));
+        assert!(!ext.should_analyze_file("/work/README.md"));
     }
 
     #[test]
@@ -369,6 +496,52 @@ mod tests {
         assert!(ext.is_rule_enabled("any_rule"));
     }
 
+    #[test]
+    fn test_related_information_and_fix_in_vscode_format() {
+        let related = VsCodeRelatedInformation {
+            file: "/src/my app.js".to_string(),
+            line: 7,
+            column: 2,
+            end_line: 7,
+            end_column: 9,
+            message: "js-eval also matches here".to_string(),
+        };
+        let fix = VsCodeTextEdit { line: 1, column: 4, end_line: 1, end_column: 11, new_text: "JSON.parse(x)".to_string() };
+        let diag = VsCodeDiagnostic::new("/src/my app.js".to_string(), 1, 4, "Avoid eval".to_string(), "warning".to_string(), "js-eval".to_string())
+            .with_end_position(1, 11)
+            .with_related_information(vec![related])
+            .with_fix(fix);
+
+        let json = diag.to_vscode_format();
+        assert_eq!(json["relatedInformation"][0]["location"]["uri"], "file:///src/my%20app.js");
+        assert_eq!(json["relatedInformation"][0]["location"]["range"]["start"], serde_json::json!({ "line": 7, "character": 2 }));
+        assert_eq!(json["data"]["fix"]["newText"], "JSON.parse(x)");
+        assert_eq!(json["data"]["fix"]["range"]["end"]["character"], 11);
+
+        let plain = VsCodeDiagnostic::new("a.js".to_string(), 0, 0, "m".to_string(), "error".to_string(), "r".to_string());
+        assert!(plain.to_vscode_format().get("relatedInformation").is_none());
+        assert!(plain.to_vscode_format().get("data").is_none());
+    }
+
+    #[test]
+    fn test_set_diagnostics_reports_changes_and_filters_visible() {
+        let mut ext = VsCodeExtension::new();
+        let diag = |rule: &str, severity: &str| VsCodeDiagnostic::new("a.py".to_string(), 0, 0, "m".to_string(), severity.to_string(), rule.to_string());
+        assert!(!ext.set_diagnostics("a.py", Vec::new()));
+        assert!(ext.set_diagnostics("a.py", vec![diag("py-eval", "error"), diag("py-todo", "information")]));
+        assert!(!ext.set_diagnostics("a.py", vec![diag("py-eval", "error"), diag("py-todo", "information")]));
+
+        // The default highlights warnings and errors
+        let visible: Vec<&str> = ext.visible_diagnostics("a.py").iter().map(|d| d.rule_id.as_str()).collect();
+        assert_eq!(visible, vec!["py-eval"]);
+        ext.update_config(VsCodeConfig { disabled_rules: vec!["py-eval".to_string()], ..VsCodeConfig::default() });
+        assert!(ext.visible_diagnostics("a.py").is_empty());
+
+        assert!(ext.set_diagnostics("a.py", Vec::new()));
+        assert_eq!(ext.diagnostic_count(), 0);
+        assert_eq!(file_uri("C:\\src\\a b.py"), "file:///C:/src/a%20b.py");
+    }
+
     #[test]
     fn test_vscode_extension_clear_diagnostics() {
         let mut ext = VsCodeExtension::new();
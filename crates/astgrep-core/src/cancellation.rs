@@ -0,0 +1,57 @@
+//! Cooperative cancellation for long-running analyses
+//!
+//! A [`CancellationToken`] is shared between the code driving an analysis (a Ctrl-C
+//! handler, a job-cancel endpoint, a GUI stop button) and the pipeline, which polls it
+//! between files and rules and stops early, keeping the results gathered so far.
+
+use crate::{AnalysisError, Result};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Shared flag requesting that an analysis stop; clones observe the same state
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    /// Create a token that is not cancelled
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request cancellation
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Check whether cancellation was requested
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Return `AnalysisError::Cancelled` once cancellation was requested
+    pub fn check(&self) -> Result<()> {
+        if self.is_cancelled() {
+            Err(AnalysisError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clones_share_state() {
+        let token = CancellationToken::new();
+        let observer = token.clone();
+        assert!(observer.check().is_ok());
+
+        token.cancel();
+        assert!(observer.is_cancelled());
+        assert!(matches!(observer.check(), Err(AnalysisError::Cancelled)));
+    }
+}
@@ -212,6 +212,16 @@ pub mod languages {
 
     /// Default languages for analysis
     pub const DEFAULT_LANGUAGES: &[Language] = ALL_LANGUAGES;
+
+    /// Languages with a bundled tree-sitter grammar, i.e. where structural matching
+    /// runs on a real syntax tree
+    pub const TREE_SITTER_LANGUAGES: &[Language] = &[
+        Language::Java,
+        Language::JavaScript,
+        Language::Python,
+        Language::Sql,
+        Language::Bash,
+    ];
 }
 
 /// Helper functions for creating Duration objects
@@ -0,0 +1,179 @@
+//! Opt-in fidelity diagnostics
+//!
+//! Where astgrep cannot honour a rule or a syntax tree fully it degrades quietly: an
+//! unknown rule key is ignored, a pattern runs on tokens or regexes instead of the syntax
+//! tree, a tree-sitter node without a universal counterpart becomes `Unknown`. When
+//! diagnostics are enabled these events are counted per language, keeping a few samples
+//! of each, so that a run can write its own list of fidelity gaps to a local file. Nothing
+//! is recorded, and nothing leaves the machine, unless a caller enables them.
+
+use crate::Language;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+/// Samples kept per diagnostic; later occurrences are only counted
+pub const MAX_DIAGNOSTIC_SAMPLES: usize = 5;
+
+/// What kind of fidelity gap a diagnostic records
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DiagnosticKind {
+    /// A rule key, option or pattern the engine does not evaluate
+    IgnoredConstruct,
+    /// A pattern evaluated by a less precise engine than it asked for
+    EngineFallback,
+    /// A syntax node kind without a universal node type
+    UnconvertedNode,
+}
+
+/// Occurrences of one construct, fallback or node kind
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiagnosticEntry {
+    pub kind: DiagnosticKind,
+    /// Language the event happened in, when it depends on one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<Language>,
+    /// The construct, fallback (`tree-sitter -> generic`) or node kind
+    pub subject: String,
+    pub count: usize,
+    /// Where the first occurrences happened (rule ids, files)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub samples: Vec<String>,
+}
+
+/// Diagnostics of a run, most frequent first
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiagnosticsReport {
+    pub entries: Vec<DiagnosticEntry>,
+}
+
+impl DiagnosticsReport {
+    /// Entries of one kind
+    pub fn of_kind(&self, kind: DiagnosticKind) -> impl Iterator<Item = &DiagnosticEntry> {
+        self.entries.iter().filter(move |entry| entry.kind == kind)
+    }
+
+    /// Write the report as pretty JSON
+    pub fn write_to(&self, path: &Path) -> crate::Result<()> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| crate::AnalysisError::internal_error(format!("Cannot serialize diagnostics: {}", e)))?;
+        std::fs::write(path, json)?;
+        Ok(())
+    }
+}
+
+type DiagnosticKey = (DiagnosticKind, Option<&'static str>, String);
+
+/// Collects diagnostics; [`record_diagnostic`] feeds the process-wide instance
+#[derive(Debug, Default)]
+pub struct DiagnosticsRecorder {
+    enabled: AtomicBool,
+    entries: Mutex<BTreeMap<DiagnosticKey, DiagnosticEntry>>,
+}
+
+impl DiagnosticsRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn enable(&self) {
+        self.enabled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Count an occurrence of `subject`; `sample` describes where it happened and is only
+    /// evaluated while samples are still kept
+    pub fn record(&self, kind: DiagnosticKind, language: Option<Language>, subject: &str, sample: impl FnOnce() -> String) {
+        if !self.is_enabled() {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        let key = (kind, language.map(|l| l.as_str()), subject.to_string());
+        let entry = entries.entry(key).or_insert_with(|| DiagnosticEntry {
+            kind,
+            language,
+            subject: subject.to_string(),
+            count: 0,
+            samples: Vec::new(),
+        });
+        entry.count += 1;
+        if entry.samples.len() < MAX_DIAGNOSTIC_SAMPLES {
+            let sample = sample();
+            if !entry.samples.contains(&sample) {
+                entry.samples.push(sample);
+            }
+        }
+    }
+
+    /// The diagnostics recorded so far, leaving the recorder empty
+    pub fn take(&self) -> DiagnosticsReport {
+        let entries = std::mem::take(&mut *self.entries.lock().unwrap_or_else(|e| e.into_inner()));
+        let mut entries: Vec<DiagnosticEntry> = entries.into_values().collect();
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.count));
+        DiagnosticsReport { entries }
+    }
+}
+
+fn recorder() -> &'static DiagnosticsRecorder {
+    static RECORDER: OnceLock<DiagnosticsRecorder> = OnceLock::new();
+    RECORDER.get_or_init(DiagnosticsRecorder::new)
+}
+
+/// Start recording diagnostics for the rest of the process
+pub fn enable_diagnostics() {
+    recorder().enable();
+}
+
+/// Whether diagnostics are being recorded; lets callers skip work only needed for them
+pub fn diagnostics_enabled() -> bool {
+    recorder().is_enabled()
+}
+
+/// Record a diagnostic if diagnostics are enabled
+pub fn record_diagnostic(kind: DiagnosticKind, language: Option<Language>, subject: &str, sample: impl FnOnce() -> String) {
+    recorder().record(kind, language, subject, sample);
+}
+
+/// The diagnostics recorded so far by the process
+pub fn take_diagnostics() -> DiagnosticsReport {
+    recorder().take()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recorder_counts_and_samples() {
+        let recorder = DiagnosticsRecorder::new();
+        recorder.record(DiagnosticKind::IgnoredConstruct, None, "taint", || "rule a".to_string());
+        assert!(recorder.take().entries.is_empty(), "disabled recorders record nothing");
+
+        recorder.enable();
+        for i in 0..8 {
+            recorder.record(DiagnosticKind::UnconvertedNode, Some(Language::Java), "lambda_expression", || format!("F{}.java", i % 7));
+        }
+        recorder.record(DiagnosticKind::UnconvertedNode, Some(Language::Python), "lambda_expression", || "f.py".to_string());
+        recorder.record(DiagnosticKind::IgnoredConstruct, None, "taint", || "rule a".to_string());
+        recorder.record(DiagnosticKind::IgnoredConstruct, None, "taint", || "rule a".to_string());
+
+        let report = recorder.take();
+        assert_eq!(report.entries.len(), 3);
+        let first = &report.entries[0];
+        assert_eq!((first.language, first.subject.as_str(), first.count), (Some(Language::Java), "lambda_expression", 8));
+        assert_eq!(first.samples.len(), MAX_DIAGNOSTIC_SAMPLES);
+        let ignored: Vec<_> = report.of_kind(DiagnosticKind::IgnoredConstruct).collect();
+        assert_eq!((ignored[0].count, ignored[0].samples.clone()), (2, vec!["rule a".to_string()]));
+        assert!(recorder.take().entries.is_empty());
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["entries"][0]["kind"], "unconverted-node");
+        assert_eq!(json["entries"][0]["language"], "java");
+    }
+}
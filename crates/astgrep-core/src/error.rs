@@ -46,6 +46,9 @@ pub enum AnalysisError {
 
     #[error("Recoverable error: {message}")]
     RecoverableError { message: String },
+
+    #[error("Analysis cancelled")]
+    Cancelled,
 }
 
 impl AnalysisError {
@@ -140,6 +143,7 @@ impl AnalysisError {
             Self::TimeoutError { .. } => "timeout",
             Self::ResourceLimitError { .. } => "resource_limit",
             Self::RecoverableError { .. } => "recoverable",
+            Self::Cancelled => "cancelled",
         }
     }
 
@@ -159,6 +163,7 @@ impl AnalysisError {
             Self::TimeoutError { .. } => true,
             Self::ResourceLimitError { .. } => true,
             Self::RecoverableError { .. } => true,
+            Self::Cancelled => false,
         }
     }
 
@@ -178,6 +183,7 @@ impl AnalysisError {
             Self::TimeoutError { .. } => "Increase timeout or reduce complexity",
             Self::ResourceLimitError { .. } => "Increase resource limits or reduce input size",
             Self::RecoverableError { .. } => "Follow the suggested recovery action",
+            Self::Cancelled => "Re-run the analysis to get complete results",
         }
     }
 
@@ -197,6 +203,7 @@ impl AnalysisError {
             Self::TimeoutError { .. } => ErrorSeverity::Medium,
             Self::ResourceLimitError { .. } => ErrorSeverity::Medium,
             Self::RecoverableError { .. } => ErrorSeverity::Low,
+            Self::Cancelled => ErrorSeverity::Low,
         }
     }
 }
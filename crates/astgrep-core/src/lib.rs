@@ -10,6 +10,14 @@ pub mod traits;
 pub mod optimization;
 pub mod patterns;
 pub mod constants;
+pub mod source_map;
+pub mod cancellation;
+pub mod redaction;
+pub mod test_code;
+pub mod numeric;
+pub mod schema;
+pub mod diagnostics;
+pub mod suppression;
 
 // Re-export commonly used types
 pub use error::{AnalysisError, Result};
@@ -19,6 +27,14 @@ pub use optimization::*;
 pub use traits::*;
 pub use patterns::*;
 pub use constants::*;
+pub use source_map::*;
+pub use cancellation::*;
+pub use redaction::*;
+pub use test_code::*;
+pub use numeric::*;
+pub use schema::*;
+pub use diagnostics::*;
+pub use suppression::*;
 
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,307 @@
+//! Numeric literals and unit-aware comparisons
+//!
+//! Rules constrain the value of a matched literal, e.g. flag `setTimeout($MS, ...)`
+//! when `$MS > 5min`, RSA key sizes below 2048 or buffers above `16MiB`. Literals are
+//! read with the numeric syntax of the analyzed language (radix prefixes, digit
+//! separators, type suffixes); a product of literals such as `5 * 60 * 1000` is folded.
+//! Bounds may carry a duration or size unit and are converted into the unit the rule
+//! declares for the matched literal.
+
+use crate::{AnalysisError, Language, Result};
+use serde::{Deserialize, Serialize};
+
+/// Comparison between a matched literal and a bound
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NumericOperator {
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Equal,
+    NotEqual,
+}
+
+impl NumericOperator {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NumericOperator::Less => "<",
+            NumericOperator::LessOrEqual => "<=",
+            NumericOperator::Greater => ">",
+            NumericOperator::GreaterOrEqual => ">=",
+            NumericOperator::Equal => "==",
+            NumericOperator::NotEqual => "!=",
+        }
+    }
+
+    pub fn holds(&self, value: f64, bound: f64) -> bool {
+        match self {
+            NumericOperator::Less => value < bound,
+            NumericOperator::LessOrEqual => value <= bound,
+            NumericOperator::Greater => value > bound,
+            NumericOperator::GreaterOrEqual => value >= bound,
+            NumericOperator::Equal => value == bound,
+            NumericOperator::NotEqual => value != bound,
+        }
+    }
+
+    /// Split a leading operator off `text`
+    fn split(text: &str) -> Option<(Self, &str)> {
+        // Two-character operators first so `<=` is not read as `<`
+        [
+            ("<=", NumericOperator::LessOrEqual),
+            (">=", NumericOperator::GreaterOrEqual),
+            ("==", NumericOperator::Equal),
+            ("!=", NumericOperator::NotEqual),
+            ("<", NumericOperator::Less),
+            (">", NumericOperator::Greater),
+            ("=", NumericOperator::Equal),
+        ]
+        .into_iter()
+        .find_map(|(symbol, operator)| text.strip_prefix(symbol).map(|rest| (operator, rest)))
+    }
+}
+
+/// What a unit measures; only units of the same dimension convert into each other
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Dimension {
+    Duration,
+    Size,
+}
+
+/// A duration or size unit, as a multiple of milliseconds or bytes
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Unit {
+    pub dimension: Dimension,
+    pub factor: f64,
+}
+
+impl Unit {
+    /// Parse a unit name such as `ms`, `min`, `KiB` or `bytes`
+    pub fn parse(name: &str) -> Option<Self> {
+        let (dimension, factor) = match name.trim() {
+            "ns" => (Dimension::Duration, 1e-6),
+            "us" | "µs" => (Dimension::Duration, 1e-3),
+            "ms" | "millis" | "milliseconds" => (Dimension::Duration, 1.0),
+            "s" | "sec" | "secs" | "seconds" => (Dimension::Duration, 1e3),
+            "m" | "min" | "mins" | "minutes" => (Dimension::Duration, 60e3),
+            "h" | "hours" => (Dimension::Duration, 3600e3),
+            "d" | "days" => (Dimension::Duration, 86400e3),
+            "bit" | "bits" => (Dimension::Size, 0.125),
+            "B" | "b" | "bytes" => (Dimension::Size, 1.0),
+            "KB" | "kB" | "kb" => (Dimension::Size, 1e3),
+            "MB" | "mb" => (Dimension::Size, 1e6),
+            "GB" | "gb" => (Dimension::Size, 1e9),
+            "KiB" | "K" | "k" => (Dimension::Size, 1024.0),
+            "MiB" | "M" => (Dimension::Size, 1024.0 * 1024.0),
+            "GiB" | "G" => (Dimension::Size, 1024.0 * 1024.0 * 1024.0),
+            _ => return None,
+        };
+        Some(Self { dimension, factor })
+    }
+}
+
+/// Constraint on the numeric value of a matched literal, with the bound expressed in
+/// the unit of the literal
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NumericConstraint {
+    pub operator: NumericOperator,
+    pub bound: f64,
+}
+
+impl NumericConstraint {
+    /// Parse a comparison such as `> 5min`, `< 2048` or `>= 0x10_0000`. A bound with a
+    /// unit requires `unit`, the unit of the matched literals, of the same dimension.
+    pub fn parse(comparison: &str, unit: Option<&str>) -> Result<Self> {
+        let (operator, rest) = NumericOperator::split(comparison.trim()).ok_or_else(|| {
+            AnalysisError::parse_error(format!("Numeric comparison '{}' must start with <, <=, >, >=, == or !=", comparison))
+        })?;
+        let literal_unit = match unit {
+            Some(name) => Some(Unit::parse(name).ok_or_else(|| AnalysisError::parse_error(format!("Unknown unit '{}'", name)))?),
+            None => None,
+        };
+
+        let rest = rest.trim();
+        let (value, bound_unit) = split_bound(rest)
+            .ok_or_else(|| AnalysisError::parse_error(format!("Invalid numeric bound '{}'", rest)))?;
+
+        let bound = match (bound_unit, literal_unit) {
+            (None, _) => value,
+            (Some(bound_unit), Some(literal_unit)) => {
+                if bound_unit.dimension != literal_unit.dimension {
+                    return Err(AnalysisError::parse_error(format!(
+                        "Bound '{}' is not in a unit compatible with '{}'",
+                        rest,
+                        unit.unwrap_or_default()
+                    )));
+                }
+                value * bound_unit.factor / literal_unit.factor
+            }
+            (Some(_), None) => {
+                return Err(AnalysisError::parse_error(format!(
+                    "Bound '{}' has a unit, so the unit of the matched literal must be given",
+                    rest
+                )))
+            }
+        };
+        Ok(Self { operator, bound })
+    }
+
+    /// Whether the literal `text` of `language` satisfies the constraint; text that is
+    /// not a numeric literal never does
+    pub fn holds(&self, text: &str, language: Language) -> bool {
+        parse_numeric_literal(text, Some(language)).is_some_and(|value| self.operator.holds(value, self.bound))
+    }
+}
+
+/// Value of a numeric literal, or of a product of literals (`5 * 60 * 1000`), in the
+/// syntax of `language`; `None` reads the syntax common to the supported languages
+pub fn parse_numeric_literal(text: &str, language: Option<Language>) -> Option<f64> {
+    let text = strip_parens(text.trim());
+    if let Some(factors) = split_top_level(text, '*') {
+        return factors
+            .into_iter()
+            .map(|factor| parse_numeric_literal(factor, language))
+            .try_fold(1.0, |product, factor| factor.map(|f| product * f));
+    }
+    match text.strip_prefix('-') {
+        Some(rest) => parse_numeric_literal(rest, language).map(|v| -v),
+        None => parse_single_literal(text.strip_prefix('+').unwrap_or(text).trim(), language),
+    }
+}
+
+/// Split a bound such as `16MiB` into its value and unit. Radix literals carry no unit,
+/// and a suffix that is no unit (`2.5f`) belongs to the literal.
+fn split_bound(text: &str) -> Option<(f64, Option<Unit>)> {
+    let radix = ["0x", "0X", "0b", "0B", "0o", "0O"].iter().any(|p| text.starts_with(p));
+    let number = text.trim_end_matches(|c: char| c.is_alphabetic() || c == 'µ');
+    if !radix && number.len() < text.len() {
+        if let (Some(unit), Some(value)) = (Unit::parse(&text[number.len()..]), parse_numeric_literal(number, None)) {
+            return Some((value, Some(unit)));
+        }
+    }
+    parse_numeric_literal(text, None).map(|value| (value, None))
+}
+
+/// Parts of `text` separated by `separator` outside parentheses, if there are several
+fn split_top_level(text: &str, separator: char) -> Option<Vec<&str>> {
+    let mut parts = Vec::new();
+    let (mut depth, mut start) = (0i32, 0);
+    for (i, c) in text.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            c if c == separator && depth == 0 => {
+                parts.push(&text[start..i]);
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(&text[start..]);
+    (parts.len() > 1).then_some(parts)
+}
+
+/// Remove parentheses enclosing the whole of `text`
+fn strip_parens(text: &str) -> &str {
+    let mut text = text;
+    while let Some(inner) = text.strip_prefix('(').and_then(|t| t.strip_suffix(')')) {
+        // `(a) * (b)` starts and ends with parentheses that do not enclose it
+        let mut depth = 0i32;
+        if inner.chars().any(|c| {
+            depth += match c { '(' => 1, ')' => -1, _ => 0 };
+            depth < 0
+        }) {
+            break;
+        }
+        text = inner.trim();
+    }
+    text
+}
+
+fn parse_single_literal(text: &str, language: Option<Language>) -> Option<f64> {
+    // Digit separators: `1_000` in most languages, `1'000` in C++
+    let mut cleaned: String = text.chars().filter(|&c| c != '_').collect();
+    if matches!(language, Some(Language::C) | None) {
+        cleaned.retain(|c| c != '\'');
+    }
+    if cleaned.is_empty() {
+        return None;
+    }
+
+    let lower = cleaned.to_ascii_lowercase();
+    for (prefix, radix) in [("0x", 16), ("0b", 2), ("0o", 8)] {
+        if let Some(digits) = lower.strip_prefix(prefix) {
+            // Integer suffixes only: `d` and `f` are hex digits
+            let digits = digits.trim_end_matches(['l', 'u', 'n']);
+            return u64::from_str_radix(digits, radix).ok().map(|v| v as f64);
+        }
+    }
+
+    let digits = lower.trim_end_matches(['l', 'u', 'n', 'f', 'd', 'm']);
+    if digits.is_empty() || !digits.starts_with(|c: char| c.is_ascii_digit() || c == '.') {
+        return None;
+    }
+    // Legacy octal `0755` in C-family languages and shells; Python rejects it
+    if legacy_octal(language) && digits.len() > 1 && digits.starts_with('0') && digits.bytes().all(|b| b.is_ascii_digit()) {
+        return u64::from_str_radix(&digits[1..], 8).ok().map(|v| v as f64);
+    }
+    if language == Some(Language::Python) && digits.len() > 1 && digits.starts_with('0') && digits.bytes().all(|b| b.is_ascii_digit()) && digits.bytes().any(|b| b != b'0') {
+        return None;
+    }
+    digits.parse::<f64>().ok().filter(|v| v.is_finite())
+}
+
+fn legacy_octal(language: Option<Language>) -> bool {
+    matches!(
+        language,
+        Some(Language::C | Language::Java | Language::JavaScript | Language::Php | Language::Bash)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_numeric_literals() {
+        assert_eq!(parse_numeric_literal("300000", Some(Language::JavaScript)), Some(300000.0));
+        assert_eq!(parse_numeric_literal("300_000", Some(Language::Python)), Some(300000.0));
+        assert_eq!(parse_numeric_literal("0x800", Some(Language::Java)), Some(2048.0));
+        assert_eq!(parse_numeric_literal("0xFFL", Some(Language::Java)), Some(255.0));
+        assert_eq!(parse_numeric_literal("0b1010", Some(Language::CSharp)), Some(10.0));
+        assert_eq!(parse_numeric_literal("0o17", Some(Language::Python)), Some(15.0));
+        assert_eq!(parse_numeric_literal("1024L", Some(Language::Kotlin)), Some(1024.0));
+        assert_eq!(parse_numeric_literal("10n", Some(Language::JavaScript)), Some(10.0));
+        assert_eq!(parse_numeric_literal("2.5f", Some(Language::Java)), Some(2.5));
+        assert_eq!(parse_numeric_literal("1e3", Some(Language::Python)), Some(1000.0));
+        assert_eq!(parse_numeric_literal("1'000'000", Some(Language::C)), Some(1_000_000.0));
+        assert_eq!(parse_numeric_literal("0755", Some(Language::C)), Some(493.0));
+        assert_eq!(parse_numeric_literal("0755", Some(Language::Python)), None);
+        assert_eq!(parse_numeric_literal("5 * 60 * 1000", Some(Language::JavaScript)), Some(300000.0));
+        assert_eq!(parse_numeric_literal("(5 * 60) * 1000", None), Some(300000.0));
+        assert_eq!(parse_numeric_literal("-(1)", None), Some(-1.0));
+        assert_eq!(parse_numeric_literal("timeout", Some(Language::Java)), None);
+        assert_eq!(parse_numeric_literal("\"2048\"", Some(Language::Java)), None);
+    }
+
+    #[test]
+    fn test_numeric_constraints_with_units() {
+        let timeout = NumericConstraint::parse("> 5min", Some("ms")).unwrap();
+        assert_eq!(timeout.bound, 300000.0);
+        assert!(timeout.holds("600000", Language::JavaScript));
+        assert!(timeout.holds("10 * 60 * 1000", Language::JavaScript));
+        assert!(!timeout.holds("300_000", Language::JavaScript));
+
+        let key_size = NumericConstraint::parse("< 2048", None).unwrap();
+        assert!(key_size.holds("1024", Language::Java));
+        assert!(!key_size.holds("0x1000", Language::Java));
+
+        let buffer = NumericConstraint::parse(">= 16MiB", Some("bytes")).unwrap();
+        assert!(buffer.holds("0x1000000", Language::C));
+        assert!(!buffer.holds("4096", Language::C));
+
+        assert!(NumericConstraint::parse("> 5min", None).is_err());
+        assert!(NumericConstraint::parse("> 5min", Some("bytes")).is_err());
+        assert!(NumericConstraint::parse("about 5", None).is_err());
+    }
+}
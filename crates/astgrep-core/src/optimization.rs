@@ -5,7 +5,9 @@
 
 use crate::{AstNode, Result};
 use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::hash::Hash;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
 use std::time::{Duration, Instant};
 
 /// Performance metrics collector
@@ -367,6 +369,70 @@ where
     }
 }
 
+/// Bounded cache shared between threads that evicts the least recently used entry when
+/// full. Lookups only take a read lock, so concurrent hits do not wait on each other.
+#[derive(Debug)]
+pub struct SharedLruCache<K, V> {
+    /// Values with the tick of their last use
+    entries: RwLock<HashMap<K, (V, AtomicU64)>>,
+    clock: AtomicU64,
+    capacity: usize,
+}
+
+impl<K, V> SharedLruCache<K, V>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+{
+    /// Create a cache holding at most `capacity` entries
+    pub fn new(capacity: usize) -> Self {
+        Self { entries: RwLock::new(HashMap::new()), clock: AtomicU64::new(0), capacity }
+    }
+
+    /// Get a cached value, marking it as recently used
+    pub fn get(&self, key: &K) -> Option<V> {
+        let entries = self.entries.read().unwrap_or_else(|e| e.into_inner());
+        let (value, used) = entries.get(key)?;
+        used.store(self.clock.fetch_add(1, Ordering::Relaxed), Ordering::Relaxed);
+        Some(value.clone())
+    }
+
+    /// Cache a value, evicting the least recently used entry when the cache is full
+    pub fn insert(&self, key: K, value: V) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut entries = self.entries.write().unwrap_or_else(|e| e.into_inner());
+        if entries.len() >= self.capacity && !entries.contains_key(&key) {
+            let oldest = entries.iter().min_by_key(|(_, (_, used))| used.load(Ordering::Relaxed)).map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert(key, (value, AtomicU64::new(self.clock.fetch_add(1, Ordering::Relaxed))));
+    }
+
+    /// Get a cached value or compute and cache it. Threads missing the same key at once
+    /// may each compute it.
+    pub fn get_or_insert_with(&self, key: K, compute: impl FnOnce() -> V) -> V {
+        if let Some(value) = self.get(&key) {
+            return value;
+        }
+        let value = compute();
+        self.insert(key, value.clone());
+        value
+    }
+
+    /// Number of cached entries
+    pub fn len(&self) -> usize {
+        self.entries.read().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -430,4 +496,23 @@ mod tests {
         assert_eq!(misses, 1);
         assert_eq!(hit_rate, 0.5);
     }
+
+    #[test]
+    fn test_shared_lru_cache_evicts_least_recently_used() {
+        let cache = SharedLruCache::new(2);
+        assert_eq!(cache.get_or_insert_with("a", || 1), 1);
+        assert_eq!(cache.get_or_insert_with("b", || 2), 2);
+        assert_eq!(cache.get_or_insert_with("a", || 10), 1);
+
+        // "b" was used least recently
+        cache.insert("c", 3);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(&"b"), None);
+        assert_eq!(cache.get(&"a"), Some(1));
+        assert_eq!(cache.get(&"c"), Some(3));
+
+        let disabled = SharedLruCache::new(0);
+        disabled.insert("a", 1);
+        assert!(disabled.is_empty());
+    }
 }
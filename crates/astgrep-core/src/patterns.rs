@@ -46,6 +46,9 @@ pub struct MetavariablePattern {
     pub type_constraint: Option<String>,
     pub name_constraint: Option<String>, // metavariable-name support
     pub analysis: Option<MetavariableAnalysis>, // metavariable-analysis support
+    /// Language to parse the bound code in, such as `sql` for a query string; `generic`
+    /// matches its text
+    pub language: Option<String>,
 }
 
 /// Metavariable analysis configuration
@@ -277,6 +280,7 @@ impl MetavariablePattern {
             type_constraint: None,
             name_constraint: None,
             analysis: None,
+            language: None,
         }
     }
 
@@ -289,6 +293,7 @@ impl MetavariablePattern {
             type_constraint: None,
             name_constraint: None,
             analysis: None,
+            language: None,
         }
     }
 
@@ -311,10 +316,31 @@ impl MetavariablePattern {
     }
 }
 
+/// A metavariable bound by a match: the code it stands for and where that code is
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BoundMetavariable {
+    /// The bound code
+    pub text: String,
+    /// Node type of the bound node (of the first node, for an ellipsis metavariable)
+    pub node_type: String,
+    /// (start_line, start_col, end_line, end_col) of the bound code, when the parser
+    /// records locations
+    pub location: Option<(usize, usize, usize, usize)>,
+}
+
+impl BoundMetavariable {
+    pub fn new(text: String, node_type: String, location: Option<(usize, usize, usize, usize)>) -> Self {
+        Self { text, node_type, location }
+    }
+}
+
 /// Result of a semgrep-style pattern match
 pub struct SemgrepMatchResult {
     pub node: Box<dyn crate::AstNode>,
+    /// Bound code by metavariable name, without the `$`
     pub bindings: HashMap<String, String>,
+    /// The same bindings with their node types and locations
+    pub metavariables: HashMap<String, BoundMetavariable>,
     pub confidence: f64,
 }
 
@@ -323,6 +349,7 @@ impl SemgrepMatchResult {
         Self {
             node,
             bindings,
+            metavariables: HashMap::new(),
             confidence: 1.0,
         }
     }
@@ -331,6 +358,15 @@ impl SemgrepMatchResult {
         self.confidence = confidence;
         self
     }
+
+    /// Attach the located bindings; `bindings` is filled from them where missing
+    pub fn with_metavariables(mut self, metavariables: HashMap<String, BoundMetavariable>) -> Self {
+        for (name, bound) in &metavariables {
+            self.bindings.entry(name.clone()).or_insert_with(|| bound.text.clone());
+        }
+        self.metavariables = metavariables;
+        self
+    }
 }
 
 impl std::fmt::Debug for SemgrepMatchResult {
@@ -338,6 +374,7 @@ impl std::fmt::Debug for SemgrepMatchResult {
         f.debug_struct("SemgrepMatchResult")
             .field("node_type", &self.node.node_type())
             .field("bindings", &self.bindings)
+            .field("metavariables", &self.metavariables)
             .field("confidence", &self.confidence)
             .finish()
     }
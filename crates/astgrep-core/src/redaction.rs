@@ -0,0 +1,144 @@
+//! Redaction of sensitive matched content
+//!
+//! Findings of secret and PII rules should not echo the matched value into reports.
+//! A [`Redaction`] rewrites every occurrence of the matched text in a finding's
+//! message, fix and displayed source. It is chosen per rule through the `redact`
+//! metadata key, falling back to a global default; `redact: none` opts a rule out.
+
+use crate::{AnalysisError, Location, Result};
+use std::collections::HashMap;
+
+/// Rule metadata key selecting the redaction of a rule's findings
+pub const REDACT_METADATA_KEY: &str = "redact";
+
+/// Characters kept on each side by `partial` without explicit counts
+const DEFAULT_KEEP: usize = 4;
+
+/// How matched text is hidden in reports
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Redaction {
+    /// Replace every character with `*`
+    Mask,
+    /// Keep `prefix` leading and `suffix` trailing characters and mask the rest
+    Partial { prefix: usize, suffix: usize },
+    /// Replace the value with a stable hash, so repeated secrets can still be correlated
+    Hash,
+}
+
+impl Redaction {
+    /// Parse `mask`, `hash`, `partial` or `partial:<prefix>:<suffix>`; `none`/`off` parse to
+    /// `Ok(None)`
+    pub fn parse(spec: &str) -> Result<Option<Self>> {
+        let spec = spec.trim().to_lowercase();
+        let mut parts = spec.split(':');
+        let redaction = match parts.next().unwrap_or_default() {
+            "none" | "off" => return Ok(None),
+            "mask" => Redaction::Mask,
+            "hash" => Redaction::Hash,
+            "partial" => {
+                let mut count = |name: &str| match parts.next() {
+                    Some(n) => n.parse::<usize>().map_err(|_| AnalysisError::config_error(format!("invalid {} count '{}' in redaction '{}'", name, n, spec))),
+                    None => Ok(DEFAULT_KEEP),
+                };
+                let prefix = count("prefix")?;
+                let suffix = count("suffix")?;
+                Redaction::Partial { prefix, suffix }
+            }
+            other => return Err(AnalysisError::config_error(format!("unknown redaction '{}' (expected mask, partial[:P:S], hash or none)", other))),
+        };
+        if parts.next().is_some() {
+            return Err(AnalysisError::config_error(format!("unexpected arguments in redaction '{}'", spec)));
+        }
+        Ok(Some(redaction))
+    }
+
+    /// Redaction for a rule: its `redact` metadata when set, otherwise `default`
+    pub fn for_rule(metadata: &HashMap<String, String>, default: Option<Redaction>) -> Option<Redaction> {
+        match metadata.get(REDACT_METADATA_KEY) {
+            Some(spec) => Redaction::parse(spec).unwrap_or_else(|e| {
+                tracing::warn!("Ignoring rule redaction: {}", e);
+                default
+            }),
+            None => default,
+        }
+    }
+
+    /// Redacted form of `value`
+    pub fn apply(&self, value: &str) -> String {
+        let chars: Vec<char> = value.chars().collect();
+        match *self {
+            Redaction::Mask => "*".repeat(chars.len()),
+            Redaction::Partial { prefix, suffix } if prefix + suffix < chars.len() => {
+                let head: String = chars[..prefix].iter().collect();
+                let tail: String = chars[chars.len() - suffix..].iter().collect();
+                format!("{}{}{}", head, "*".repeat(chars.len() - prefix - suffix), tail)
+            }
+            Redaction::Partial { .. } => "*".repeat(chars.len()),
+            Redaction::Hash => {
+                // FNV-1a: for correlating repeated values, not for hiding low-entropy ones
+                let hash = value.bytes().fold(0xcbf29ce484222325u64, |h, b| (h ^ b as u64).wrapping_mul(0x100000001b3));
+                format!("[hash:{:016x}]", hash)
+            }
+        }
+    }
+
+    /// `text` with every occurrence of `secret` redacted
+    pub fn redact_in(&self, text: &str, secret: &str) -> String {
+        if secret.trim().is_empty() {
+            return text.to_string();
+        }
+        text.replace(secret, &self.apply(secret))
+    }
+}
+
+/// Text covered by `location` (1-based lines, 1-based byte columns) in `source`
+pub fn matched_text<'a>(source: &'a str, location: &Location) -> Option<&'a str> {
+    let offset = |line: usize, column: usize| -> Option<usize> {
+        let line_start: usize = source.split_inclusive('\n').take(line.checked_sub(1)?).map(str::len).sum();
+        let offset = line_start + column.checked_sub(1)?;
+        (offset <= source.len()).then_some(offset)
+    };
+    let start = offset(location.start_line, location.start_column)?;
+    let end = offset(location.end_line, location.end_column)?;
+    source.get(start..end).filter(|text| !text.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_parse_and_apply() {
+        assert_eq!(Redaction::parse("mask").unwrap(), Some(Redaction::Mask));
+        assert_eq!(Redaction::parse("none").unwrap(), None);
+        assert_eq!(Redaction::parse("partial:2:1").unwrap(), Some(Redaction::Partial { prefix: 2, suffix: 1 }));
+        assert!(Redaction::parse("partial:x").is_err());
+        assert!(Redaction::parse("blur").is_err());
+
+        assert_eq!(Redaction::Mask.apply("s3cr3t"), "******");
+        assert_eq!(Redaction::Partial { prefix: 2, suffix: 1 }.apply("AKIA1234"), "AK*****4");
+        assert_eq!(Redaction::Partial { prefix: 4, suffix: 4 }.apply("short"), "*****");
+        assert_eq!(Redaction::Hash.apply("token"), Redaction::Hash.apply("token"));
+        assert!(!Redaction::Hash.apply("token").contains("token"));
+    }
+
+    #[test]
+    fn test_rule_metadata_overrides_default() {
+        let mut metadata = HashMap::new();
+        assert_eq!(Redaction::for_rule(&metadata, Some(Redaction::Hash)), Some(Redaction::Hash));
+        metadata.insert(REDACT_METADATA_KEY.to_string(), "none".to_string());
+        assert_eq!(Redaction::for_rule(&metadata, Some(Redaction::Hash)), None);
+        metadata.insert(REDACT_METADATA_KEY.to_string(), "mask".to_string());
+        assert_eq!(Redaction::for_rule(&metadata, None), Some(Redaction::Mask));
+    }
+
+    #[test]
+    fn test_matched_text_and_redact_in() {
+        let source = "x = 1\napi_key = \"AKIA1234\"\n";
+        let location = Location::new(PathBuf::from("a.py"), 2, 11, 2, 21);
+        let secret = matched_text(source, &location).unwrap();
+        assert_eq!(secret, "\"AKIA1234\"");
+        assert_eq!(Redaction::Mask.redact_in("Hardcoded key \"AKIA1234\"", secret), "Hardcoded key **********");
+    }
+}
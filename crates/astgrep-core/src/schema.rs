@@ -0,0 +1,222 @@
+//! Findings report schema
+//!
+//! The JSON report of `analyze --format json` is read by other tools, so its shape is
+//! versioned. `schema_version` follows semantic versioning:
+//!
+//! - a minor version only adds optional fields or enum values;
+//! - renaming, retyping or removing a field, or making one required, needs a new major
+//!   version.
+//!
+//! Consumers should ignore fields they do not know and reject reports of another major
+//! version. [`report_schema`] is the JSON Schema of the current version.
+
+use serde_json::{json, Value};
+
+/// Version of the findings report format
+pub const REPORT_SCHEMA_VERSION: &str = "1.6.0";
+
+/// Whether a report written with `version` can be read as the current version
+pub fn is_compatible_schema_version(version: &str) -> bool {
+    let major = |v: &str| v.split('.').next().and_then(|m| m.trim().parse::<u64>().ok());
+    major(version).is_some_and(|m| Some(m) == major(REPORT_SCHEMA_VERSION))
+}
+
+/// JSON Schema of one finding
+pub fn finding_schema() -> Value {
+    json!({
+        "type": "object",
+        "required": ["rule_id", "message", "severity", "confidence", "location"],
+        "properties": {
+            "rule_id": { "type": "string" },
+            "message": { "type": "string" },
+            "severity": { "enum": ["INFO", "WARNING", "ERROR", "CRITICAL"] },
+            "confidence": { "enum": ["LOW", "MEDIUM", "HIGH"] },
+            "location": {
+                "type": "object",
+                "required": ["file", "start_line", "start_column", "end_line", "end_column"],
+                "properties": {
+                    "file": { "type": "string" },
+                    "start_line": { "type": "integer", "minimum": 0 },
+                    "start_column": { "type": "integer", "minimum": 0 },
+                    "end_line": { "type": "integer", "minimum": 0 },
+                    "end_column": { "type": "integer", "minimum": 0 }
+                }
+            },
+            "fix": { "type": ["string", "null"], "description": "Replacement for the matched span" },
+            "layer": { "type": "string", "description": "Container image layer that introduced the file" },
+            "category": { "type": "string", "description": "Category of findings not produced by a rule file" },
+            "is_test": { "type": "boolean", "description": "The finding is in a test file; absent means false" },
+            "risk_score": { "type": "integer", "minimum": 0, "maximum": 100 },
+            "effort": { "enum": ["trivial", "small", "medium", "large"] },
+            "fingerprint": { "type": "string", "description": "Line-independent identifier kept across runs; added in 1.6.0" }
+        }
+    })
+}
+
+/// JSON Schema of a findings report
+pub fn report_schema() -> Value {
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "astgrep findings report",
+        "type": "object",
+        "required": ["schema_version", "findings", "summary"],
+        "properties": {
+            "schema_version": { "const": REPORT_SCHEMA_VERSION },
+            "findings": { "type": "array", "items": finding_schema() },
+            "suppressed_findings": {
+                "type": "array",
+                "description": "Findings silenced by inline comments, present with --report-suppressed; added in 1.2.0",
+                "items": finding_schema()
+            },
+            "summary": {
+                "type": "object",
+                "required": ["total_findings", "files_analyzed", "rules_executed", "analysis_time_ms", "status"],
+                "properties": {
+                    "total_findings": { "type": "integer", "minimum": 0 },
+                    "files_analyzed": { "type": "integer", "minimum": 0 },
+                    "rules_executed": { "type": "integer", "minimum": 0 },
+                    "analysis_time_ms": { "type": "integer", "minimum": 0 },
+                    "status": { "enum": ["completed", "cancelled", "budget-exhausted"] },
+                    "degraded_languages": { "type": "array", "items": { "type": "object" } },
+                    "roots": { "type": "array", "items": { "type": "object" } },
+                    "image": { "type": "object" },
+                    "profile": { "type": "object" },
+                    "skipped_fixes": {
+                        "type": "array",
+                        "description": "Fixes --fix did not apply; added in 1.1.0",
+                        "items": {
+                            "type": "object",
+                            "required": ["rule_id", "file", "line", "reason"],
+                            "properties": {
+                                "rule_id": { "type": "string" },
+                                "file": { "type": "string" },
+                                "line": { "type": "integer", "minimum": 0 },
+                                "reason": { "type": "string" }
+                            }
+                        }
+                    },
+                    "baseline_matched": {
+                        "type": "integer",
+                        "minimum": 0,
+                        "description": "Findings hidden because the --baseline file has them; added in 1.3.0"
+                    },
+                    "skipped_files": {
+                        "type": "array",
+                        "description": "Binary and oversized files that were not analyzed; added in 1.4.0",
+                        "items": {
+                            "type": "object",
+                            "required": ["file", "reason", "bytes"],
+                            "properties": {
+                                "file": { "type": "string" },
+                                "reason": { "enum": ["too-large", "binary"] },
+                                "bytes": { "type": "integer", "minimum": 0 }
+                            }
+                        }
+                    },
+                    "failed_files": {
+                        "type": "array",
+                        "description": "Files whose analysis failed or exceeded --timeout, so their findings are missing; added in 1.5.0",
+                        "items": {
+                            "type": "object",
+                            "required": ["file", "reason", "message"],
+                            "properties": {
+                                "file": { "type": "string" },
+                                "reason": { "enum": ["timeout", "error"] },
+                                "message": { "type": "string" }
+                            }
+                        }
+                    }
+                }
+            },
+            "statistics": { "type": "object", "description": "Present with --metrics" },
+            "performance": { "type": "object", "description": "Present with --metrics and profiling" }
+        }
+    })
+}
+
+/// Paths of the fields of `value` that `schema` does not declare, and of the required
+/// fields it lacks; checks the `properties`, `required` and `items` keywords only
+pub fn undeclared_fields(schema: &Value, value: &Value) -> Vec<String> {
+    let mut problems = Vec::new();
+    check_fields(schema, value, "", &mut problems);
+    problems
+}
+
+fn check_fields(schema: &Value, value: &Value, path: &str, problems: &mut Vec<String>) {
+    match value {
+        Value::Object(fields) => {
+            let Some(properties) = schema.get("properties").and_then(Value::as_object) else {
+                return;
+            };
+            for required in schema.get("required").and_then(Value::as_array).into_iter().flatten() {
+                if let Some(name) = required.as_str() {
+                    if !fields.contains_key(name) {
+                        problems.push(format!("{}/{} is required", path, name));
+                    }
+                }
+            }
+            for (name, field) in fields {
+                match properties.get(name) {
+                    Some(field_schema) => check_fields(field_schema, field, &format!("{}/{}", path, name), problems),
+                    None => problems.push(format!("{}/{} is not in the schema", path, name)),
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(item_schema) = schema.get("items") {
+                for (i, item) in items.iter().enumerate() {
+                    check_fields(item_schema, item, &format!("{}/{}", path, i), problems);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    fn keys(schema: &Value) -> BTreeSet<&str> {
+        schema["properties"].as_object().unwrap().keys().map(String::as_str).collect()
+    }
+
+    /// Changing these lists is a change of the report format: bump the version per the
+    /// policy above
+    #[test]
+    fn test_report_schema_is_pinned() {
+        assert_eq!(REPORT_SCHEMA_VERSION, "1.6.0");
+        assert_eq!(
+            keys(&finding_schema()),
+            BTreeSet::from(["rule_id", "message", "severity", "confidence", "location", "fix", "layer", "category", "is_test", "risk_score", "effort", "fingerprint"])
+        );
+        assert_eq!(
+            keys(&report_schema()["properties"]["summary"]),
+            BTreeSet::from(["total_findings", "files_analyzed", "rules_executed", "analysis_time_ms", "status", "degraded_languages", "roots", "image", "profile", "skipped_fixes", "baseline_matched", "skipped_files", "failed_files"])
+        );
+        assert_eq!(
+            keys(&report_schema()),
+            BTreeSet::from(["schema_version", "findings", "suppressed_findings", "summary", "statistics", "performance"])
+        );
+
+        assert!(is_compatible_schema_version("1.5.2"));
+        assert!(!is_compatible_schema_version("2.0.0"));
+        assert!(!is_compatible_schema_version("latest"));
+    }
+
+    #[test]
+    fn test_undeclared_fields() {
+        let finding = json!({
+            "rule_id": "r", "message": "m", "severity": "ERROR", "confidence": "HIGH",
+            "location": { "file": "a.js", "start_line": 1, "start_column": 1, "end_line": 1, "end_column": 2 },
+            "fixed": "typo"
+        });
+        let report = json!({ "schema_version": REPORT_SCHEMA_VERSION, "findings": [finding], "summary": {} });
+
+        let problems = undeclared_fields(&report_schema(), &report);
+        assert!(problems.contains(&"/findings/0/fixed is not in the schema".to_string()));
+        assert!(problems.contains(&"/summary/status is required".to_string()));
+        assert_eq!(problems.len(), 6);
+    }
+}
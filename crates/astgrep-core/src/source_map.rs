@@ -0,0 +1,423 @@
+//! Line mapping for generated code
+//!
+//! Findings in transpiled or generated files are more useful when they point at the
+//! code developers actually edit. This module maps generated positions back to
+//! original ones from two kinds of information:
+//! - Source Map v3 files (`//# sourceMappingURL=...`, or a sibling `<file>.map`)
+//! - `#line` directives (`#line 42 "parser.y"`, `# line 42`, or cpp's `# 42 "file.c"`)
+
+use crate::{AnalysisError, Location, Result};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// A position in an original (pre-generation) source file. Lines and columns are 1-based.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OriginalPosition {
+    pub file: PathBuf,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// One mapping segment, all fields 0-based
+#[derive(Debug, Clone, Copy)]
+struct Segment {
+    generated_column: usize,
+    source: usize,
+    original_line: usize,
+    original_column: usize,
+}
+
+/// How a line directive maps the lines that follow it
+#[derive(Debug, Clone)]
+struct LineDirective {
+    /// Generated line (1-based) of the first line the directive applies to
+    generated_line: usize,
+    original_line: usize,
+    source: usize,
+}
+
+#[derive(Debug, Clone)]
+enum Mappings {
+    /// Segments per generated line, sorted by generated column
+    SourceMap(Vec<Vec<Segment>>),
+    /// Directives sorted by generated line
+    Directives(Vec<LineDirective>),
+}
+
+/// Maps positions in a generated file back to the original sources
+#[derive(Debug, Clone)]
+pub struct LineMapper {
+    sources: Vec<PathBuf>,
+    mappings: Mappings,
+}
+
+#[derive(Deserialize)]
+struct RawSourceMap {
+    version: u32,
+    #[serde(default)]
+    sources: Vec<String>,
+    #[serde(default, rename = "sourceRoot")]
+    source_root: Option<String>,
+    mappings: String,
+}
+
+impl LineMapper {
+    /// Discover line mapping information for a file: an explicit `sourceMappingURL`
+    /// comment, a sibling `<file>.map`, or `#line` directives in the source.
+    pub fn for_file(path: &Path, source: &str) -> Option<Self> {
+        let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+
+        if let Some(url) = source_mapping_url(source) {
+            let mapper = if let Some(encoded) = url.strip_prefix("data:application/json;base64,") {
+                decode_base64(encoded)
+                    .and_then(|bytes| String::from_utf8(bytes).ok())
+                    .and_then(|json| Self::from_source_map(&json, base_dir).ok())
+            } else {
+                let map_path = base_dir.join(url);
+                std::fs::read_to_string(&map_path)
+                    .ok()
+                    .and_then(|json| Self::from_source_map(&json, map_path.parent().unwrap_or(base_dir)).ok())
+            };
+            if mapper.is_some() {
+                return mapper;
+            }
+        }
+
+        let mut sibling = path.as_os_str().to_owned();
+        sibling.push(".map");
+        let sibling = PathBuf::from(sibling);
+        if let Ok(json) = std::fs::read_to_string(&sibling) {
+            if let Ok(mapper) = Self::from_source_map(&json, base_dir) {
+                return Some(mapper);
+            }
+        }
+
+        Self::from_line_directives(source, path)
+    }
+
+    /// Parse a Source Map v3 document. Relative source paths are resolved against
+    /// `sourceRoot` and then `base_dir` (the directory containing the map).
+    pub fn from_source_map(json: &str, base_dir: &Path) -> Result<Self> {
+        let raw: RawSourceMap = serde_json::from_str(json)
+            .map_err(|e| AnalysisError::parse_error(format!("Invalid source map: {}", e)))?;
+        if raw.version != 3 {
+            return Err(AnalysisError::parse_error(format!(
+                "Unsupported source map version: {}",
+                raw.version
+            )));
+        }
+
+        let root = raw.source_root.as_deref().unwrap_or("");
+        let sources = raw
+            .sources
+            .iter()
+            .map(|s| normalize_path(&base_dir.join(root).join(s)))
+            .collect();
+
+        let mut lines = Vec::new();
+        // Source index, original line and original column are relative across the whole map
+        let (mut source, mut original_line, mut original_column) = (0i64, 0i64, 0i64);
+        for line in raw.mappings.split(';') {
+            let mut segments = Vec::new();
+            let mut generated_column = 0i64;
+            for segment in line.split(',').filter(|s| !s.is_empty()) {
+                let fields = decode_vlq(segment)?;
+                generated_column += fields[0];
+                if fields.len() < 4 {
+                    continue;
+                }
+                source += fields[1];
+                original_line += fields[2];
+                original_column += fields[3];
+                if generated_column < 0 || source < 0 || original_line < 0 || original_column < 0 {
+                    return Err(AnalysisError::parse_error("Source map mapping out of range"));
+                }
+                segments.push(Segment {
+                    generated_column: generated_column as usize,
+                    source: source as usize,
+                    original_line: original_line as usize,
+                    original_column: original_column as usize,
+                });
+            }
+            segments.sort_by_key(|s| s.generated_column);
+            lines.push(segments);
+        }
+
+        Ok(Self {
+            sources,
+            mappings: Mappings::SourceMap(lines),
+        })
+    }
+
+    /// Collect `#line` directives from generated source. Returns `None` if there are none.
+    pub fn from_line_directives(source: &str, path: &Path) -> Option<Self> {
+        let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+        let mut sources = vec![path.to_path_buf()];
+        let mut directives = Vec::new();
+        let mut current_source = 0;
+
+        for (index, line) in source.lines().enumerate() {
+            let (original_line, file) = match parse_line_directive(line) {
+                Some(directive) => directive,
+                None => continue,
+            };
+            if let Some(file) = file {
+                let file = normalize_path(&base_dir.join(file));
+                current_source = match sources.iter().position(|s| *s == file) {
+                    Some(existing) => existing,
+                    None => {
+                        sources.push(file);
+                        sources.len() - 1
+                    }
+                };
+            }
+            directives.push(LineDirective {
+                generated_line: index + 2,
+                original_line,
+                source: current_source,
+            });
+        }
+
+        if directives.is_empty() {
+            return None;
+        }
+        Some(Self {
+            sources,
+            mappings: Mappings::Directives(directives),
+        })
+    }
+
+    /// Map a 1-based generated position to the original position, if known
+    pub fn lookup(&self, line: usize, column: usize) -> Option<OriginalPosition> {
+        if line == 0 {
+            return None;
+        }
+        match &self.mappings {
+            Mappings::SourceMap(lines) => {
+                let segments = lines.get(line - 1)?;
+                let column = column.saturating_sub(1);
+                let segment = segments
+                    .iter()
+                    .rev()
+                    .find(|s| s.generated_column <= column)
+                    .or_else(|| segments.first())?;
+                Some(OriginalPosition {
+                    file: self.sources.get(segment.source)?.clone(),
+                    line: segment.original_line + 1,
+                    column: column.saturating_sub(segment.generated_column) + segment.original_column + 1,
+                })
+            }
+            Mappings::Directives(directives) => {
+                let directive = directives.iter().rev().find(|d| d.generated_line <= line)?;
+                Some(OriginalPosition {
+                    file: self.sources.get(directive.source)?.clone(),
+                    line: directive.original_line + (line - directive.generated_line),
+                    column,
+                })
+            }
+        }
+    }
+
+    /// Map a location to the original sources. Positions without a mapping are left as is;
+    /// if start and end map to different files only the start is kept.
+    pub fn map_location(&self, location: &Location) -> Location {
+        let start = match self.lookup(location.start_line, location.start_column) {
+            Some(start) => start,
+            None => return location.clone(),
+        };
+        let end = self
+            .lookup(location.end_line, location.end_column)
+            .filter(|end| end.file == start.file && (end.line, end.column) >= (start.line, start.column))
+            .unwrap_or_else(|| start.clone());
+        Location::new(start.file, start.line, start.column, end.line, end.column)
+    }
+}
+
+/// Lexically resolve `.` and `..` components so mapped paths read like the originals
+fn normalize_path(path: &Path) -> PathBuf {
+    use std::path::Component;
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if matches!(normalized.components().next_back(), Some(Component::Normal(_))) {
+                    normalized.pop();
+                } else {
+                    normalized.push("..");
+                }
+            }
+            other => normalized.push(other.as_os_str()),
+        }
+    }
+    normalized
+}
+
+/// Find a `sourceMappingURL` comment near the end of a file
+fn source_mapping_url(source: &str) -> Option<&str> {
+    source.lines().rev().take(5).find_map(|line| {
+        let line = line.trim();
+        let rest = line
+            .strip_prefix("//# sourceMappingURL=")
+            .or_else(|| line.strip_prefix("//@ sourceMappingURL="))
+            .or_else(|| line.strip_prefix("/*# sourceMappingURL=").map(|r| r.trim_end_matches("*/")))?;
+        let url = rest.trim();
+        (!url.is_empty()).then_some(url)
+    })
+}
+
+/// Parse `#line N ["file"]`, `# line N ["file"]` or cpp's `# N "file" [flags]`
+fn parse_line_directive(line: &str) -> Option<(usize, Option<&str>)> {
+    let rest = line.trim_start().strip_prefix('#')?.trim_start();
+    let (rest, keyword) = match rest.strip_prefix("line") {
+        Some(after) if after.starts_with(char::is_whitespace) => (after.trim_start(), true),
+        _ => (rest, false),
+    };
+
+    let digits = rest.chars().take_while(|c| c.is_ascii_digit()).count();
+    if digits == 0 {
+        return None;
+    }
+    let number: usize = rest[..digits].parse().ok()?;
+    let rest = rest[digits..].trim();
+
+    let (file, trailer) = if let Some(quoted) = rest.strip_prefix('"') {
+        let end = quoted.find('"')?;
+        (Some(&quoted[..end]), quoted[end + 1..].trim())
+    } else {
+        (None, rest)
+    };
+
+    // Without the `line` keyword only cpp linemarkers (`# 42 "file" 1 3`) qualify,
+    // so ordinary comments such as `# 42 items` are not mistaken for directives
+    let valid = if keyword {
+        trailer.is_empty()
+    } else {
+        file.is_some() && trailer.split_whitespace().all(|f| f.chars().all(|c| c.is_ascii_digit()))
+    };
+    valid.then_some((number, file))
+}
+
+fn base64_value(c: u8) -> Option<i64> {
+    match c {
+        b'A'..=b'Z' => Some((c - b'A') as i64),
+        b'a'..=b'z' => Some((c - b'a') as i64 + 26),
+        b'0'..=b'9' => Some((c - b'0') as i64 + 52),
+        b'+' => Some(62),
+        b'/' => Some(63),
+        _ => None,
+    }
+}
+
+/// Decode one Base64 VLQ segment into its signed fields
+fn decode_vlq(segment: &str) -> Result<Vec<i64>> {
+    let mut fields = Vec::new();
+    let (mut value, mut shift) = (0i64, 0u32);
+    for c in segment.bytes() {
+        let digit = base64_value(c)
+            .ok_or_else(|| AnalysisError::parse_error(format!("Invalid source map segment: {}", segment)))?;
+        if shift > 60 {
+            return Err(AnalysisError::parse_error(format!("Source map value too large: {}", segment)));
+        }
+        value += (digit & 31) << shift;
+        if digit & 32 != 0 {
+            shift += 5;
+            continue;
+        }
+        fields.push(if value & 1 == 1 { -(value >> 1) } else { value >> 1 });
+        value = 0;
+        shift = 0;
+    }
+    if shift != 0 || fields.is_empty() {
+        return Err(AnalysisError::parse_error(format!("Truncated source map segment: {}", segment)));
+    }
+    Ok(fields)
+}
+
+/// Decode standard Base64 (used for inline `data:` source maps)
+fn decode_base64(encoded: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let (mut buffer, mut bits) = (0u32, 0u32);
+    for c in encoded.trim().bytes().filter(|&c| c != b'=') {
+        buffer = (buffer << 6) | base64_value(c)? as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_vlq() {
+        assert_eq!(decode_vlq("AAAA").unwrap(), vec![0, 0, 0, 0]);
+        assert_eq!(decode_vlq("AACA").unwrap(), vec![0, 0, 1, 0]);
+        assert_eq!(decode_vlq("D").unwrap(), vec![-1]);
+        assert_eq!(decode_vlq("gB").unwrap(), vec![16]);
+        assert!(decode_vlq("g").is_err());
+    }
+
+    #[test]
+    fn test_source_map_lookup() {
+        // line 1 -> app.ts:1:1, line 2 col 1 -> app.ts:3:1, line 2 col 5 -> app.ts:3:9
+        let json = r#"{"version":3,"sources":["app.ts"],"sourceRoot":"src","mappings":"AAAA;AAEA,IAAQ"}"#;
+        let mapper = LineMapper::from_source_map(json, Path::new("dist")).unwrap();
+
+        let pos = mapper.lookup(1, 1).unwrap();
+        assert_eq!(pos.file, PathBuf::from("dist/src/app.ts"));
+        assert_eq!((pos.line, pos.column), (1, 1));
+        assert_eq!(mapper.lookup(2, 1).map(|p| (p.line, p.column)), Some((3, 1)));
+        assert_eq!(mapper.lookup(2, 6).map(|p| (p.line, p.column)), Some((3, 10)));
+        assert!(mapper.lookup(3, 1).is_none());
+    }
+
+    #[test]
+    fn test_line_directives() {
+        let source = "int a;\n#line 100 \"grammar.y\"\nint b;\nint c;\n# line 7\nint d;\n";
+        let mapper = LineMapper::from_line_directives(source, Path::new("gen/parser.c")).unwrap();
+
+        assert_eq!(mapper.lookup(1, 1).map(|p| p.line), None);
+        let b = mapper.lookup(3, 5).unwrap();
+        assert_eq!((b.file, b.line, b.column), (PathBuf::from("gen/grammar.y"), 100, 5));
+        assert_eq!(mapper.lookup(4, 1).map(|p| p.line), Some(101));
+        let d = mapper.lookup(6, 1).unwrap();
+        assert_eq!((d.file, d.line), (PathBuf::from("gen/grammar.y"), 7));
+
+        assert!(LineMapper::from_line_directives("# 42 items left\nx = 1\n", Path::new("a.py")).is_none());
+        assert!(LineMapper::from_line_directives("# 1 \"main.c\" 1 3\nint x;\n", Path::new("a.i")).is_some());
+    }
+
+    #[test]
+    fn test_for_file_discovers_inline_source_map() {
+        let json = r#"{"version":3,"sources":["../src/a.ts"],"mappings":"AAAA"}"#;
+        let encoded = encode_base64(json.as_bytes());
+        let source = format!("var a = 1;\n//# sourceMappingURL=data:application/json;base64,{}\n", encoded);
+        let mapper = LineMapper::for_file(Path::new("out/a.js"), &source).unwrap();
+
+        let location = mapper.map_location(&Location::new(PathBuf::from("out/a.js"), 1, 5, 1, 9));
+        assert_eq!(location.file, PathBuf::from("src/a.ts"));
+        assert_eq!((location.start_line, location.start_column, location.end_column), (1, 5, 9));
+    }
+
+    fn encode_base64(bytes: &[u8]) -> String {
+        const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+        let mut out = String::new();
+        for chunk in bytes.chunks(3) {
+            let n = chunk.iter().enumerate().fold(0u32, |acc, (i, &b)| acc | (b as u32) << (16 - 8 * i));
+            for i in 0..4 {
+                if i <= chunk.len() {
+                    out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+                } else {
+                    out.push('=');
+                }
+            }
+        }
+        out
+    }
+}
@@ -0,0 +1,119 @@
+//! Inline finding suppressions
+//!
+//! A comment containing `astgrep-ignore`, `nosemgrep` or `nosem` on the line a finding
+//! starts on, or alone on the line before it, suppresses the finding. With a rule list
+//! (`astgrep-ignore:sqli,xss`, `# nosem: sqli`) only the listed rules are suppressed.
+
+/// Markers of a suppression comment, longest first so `nosemgrep` is not read as `nosem`
+const MARKERS: &[&str] = &["astgrep-ignore", "nosemgrep", "nosem"];
+
+/// Tokens opening a comment in the supported languages
+const COMMENT_OPENERS: &[&str] = &["//", "#", "--", "/*", "<!--"];
+
+/// Rules an inline suppression comment applies to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Suppression {
+    /// Every rule
+    All,
+    /// Only the listed rule ids
+    Rules(Vec<String>),
+}
+
+impl Suppression {
+    /// Whether the suppression applies to `rule_id`
+    pub fn covers(&self, rule_id: &str) -> bool {
+        match self {
+            Suppression::All => true,
+            Suppression::Rules(ids) => ids.iter().any(|id| id == rule_id),
+        }
+    }
+}
+
+/// The suppression comment on `line`, if any
+pub fn parse_suppression(line: &str) -> Option<Suppression> {
+    let (comment_start, marker, rest) = MARKERS.iter().find_map(|marker| {
+        line.match_indices(marker)
+            .map(|(start, _)| (start, *marker, &line[start + marker.len()..]))
+            .find(|(_, _, rest)| rest.chars().next().is_none_or(|c| c.is_whitespace() || c == ':' || c == '*' || c == '-'))
+    })?;
+    let before = &line[..comment_start];
+    if !COMMENT_OPENERS.iter().any(|opener| before.contains(opener)) && !before.trim_start().starts_with('*') {
+        return None;
+    }
+
+    let rest = rest.trim_start();
+    let ids = match rest.strip_prefix(':') {
+        Some(ids) => ids,
+        None => return Some(Suppression::All),
+    };
+    let ids: Vec<String> = ids
+        .split(',')
+        .filter_map(|id| id.split_whitespace().next())
+        .map(|id| id.trim_end_matches("*/").trim_end_matches("-->").to_string())
+        .filter(|id| !id.is_empty())
+        .collect();
+    if ids.is_empty() {
+        tracing::debug!("{} comment without rule ids suppresses every rule", marker);
+        return Some(Suppression::All);
+    }
+    Some(Suppression::Rules(ids))
+}
+
+/// Whether a finding of `rule_id` starting on 1-based `line` of `source` is suppressed by
+/// a comment on that line or by a comment-only line right above it
+pub fn is_suppressed(source: &str, line: usize, rule_id: &str) -> bool {
+    if line == 0 {
+        return false;
+    }
+    let mut lines = source.lines().skip(line.saturating_sub(2));
+    let previous = if line > 1 { lines.next() } else { None };
+    let current = lines.next();
+
+    let on_line = current.and_then(parse_suppression).is_some_and(|s| s.covers(rule_id));
+    let above = previous
+        .filter(|text| is_comment_line(text))
+        .and_then(parse_suppression)
+        .is_some_and(|s| s.covers(rule_id));
+    on_line || above
+}
+
+fn is_comment_line(line: &str) -> bool {
+    let line = line.trim_start();
+    COMMENT_OPENERS.iter().any(|opener| line.starts_with(opener)) || line.starts_with('*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_suppression() {
+        assert_eq!(parse_suppression("eval(x) // astgrep-ignore"), Some(Suppression::All));
+        assert_eq!(parse_suppression("os.system(c)  # nosem"), Some(Suppression::All));
+        assert_eq!(
+            parse_suppression("run() // astgrep-ignore:sqli, xss reviewed by security"),
+            Some(Suppression::Rules(vec!["sqli".to_string(), "xss".to_string()]))
+        );
+        assert_eq!(
+            parse_suppression("q = 1 -- nosemgrep: sql-select-star"),
+            Some(Suppression::Rules(vec!["sql-select-star".to_string()]))
+        );
+        assert_eq!(parse_suppression("/* nosem:a*/ f()"), Some(Suppression::Rules(vec!["a".to_string()])));
+        assert_eq!(parse_suppression("let nosem = 1;"), None, "not in a comment");
+        assert_eq!(parse_suppression("// nosemantic check"), None);
+        assert!(!Suppression::Rules(vec!["sqli".to_string()]).covers("xss"));
+    }
+
+    #[test]
+    fn test_is_suppressed() {
+        let source = "a()\n// astgrep-ignore: r1\nb()\nc() # nosem\nd()\ne() // astgrep-ignore\nf()\n";
+        assert!(!is_suppressed(source, 1, "r1"));
+        assert!(is_suppressed(source, 3, "r1"));
+        assert!(!is_suppressed(source, 3, "r2"));
+        assert!(is_suppressed(source, 4, "r2"));
+        assert!(!is_suppressed(source, 5, "r2"), "a suppression after code only covers its own line");
+        assert!(is_suppressed(source, 6, "r2"));
+        assert!(!is_suppressed(source, 7, "r2"));
+        assert!(!is_suppressed(source, 42, "r2"));
+    }
+}
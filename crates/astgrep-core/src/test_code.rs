@@ -0,0 +1,176 @@
+//! Test-code awareness
+//!
+//! [`is_test_file`] recognizes test files by the naming and layout conventions of each
+//! supported language. A [`TestFilePolicy`] decides what happens to findings in them; it
+//! is chosen per rule through the `test-files` metadata key, falling back to a global
+//! default, and `test-severity` re-rates a rule's findings in test files.
+
+use crate::{AnalysisError, Result, Severity};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Rule metadata key selecting the test-file policy of a rule
+pub const TEST_FILES_METADATA_KEY: &str = "test-files";
+
+/// Rule metadata key setting the severity of a rule's findings in test files
+pub const TEST_SEVERITY_METADATA_KEY: &str = "test-severity";
+
+/// Directory names whose contents are test code
+const TEST_DIRS: &[&str] = &["test", "tests", "__tests__", "spec", "testdata", "androidTest"];
+
+/// Treatment of findings in test files
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TestFilePolicy {
+    /// Report findings in test files like any other
+    #[default]
+    Include,
+    /// Drop findings in test files
+    Skip,
+    /// Report findings in test files one severity level lower
+    Downgrade,
+    /// Report findings in test files only, for test-only rules such as "no sleeps in tests"
+    Only,
+}
+
+impl TestFilePolicy {
+    /// Parse `include`, `skip`, `downgrade` or `only`
+    pub fn parse(spec: &str) -> Result<Self> {
+        match spec.trim().to_lowercase().as_str() {
+            "include" => Ok(TestFilePolicy::Include),
+            "skip" | "exclude" => Ok(TestFilePolicy::Skip),
+            "downgrade" => Ok(TestFilePolicy::Downgrade),
+            "only" => Ok(TestFilePolicy::Only),
+            other => Err(AnalysisError::config_error(format!(
+                "unknown test-file policy '{}' (expected include, skip, downgrade or only)",
+                other
+            ))),
+        }
+    }
+
+    /// Policy for a rule: its `test-files` metadata when set, otherwise `default`
+    pub fn for_rule(metadata: &HashMap<String, String>, default: TestFilePolicy) -> TestFilePolicy {
+        match metadata.get(TEST_FILES_METADATA_KEY) {
+            Some(spec) => TestFilePolicy::parse(spec).unwrap_or_else(|e| {
+                tracing::warn!("Ignoring rule test-file policy: {}", e);
+                default
+            }),
+            None => default,
+        }
+    }
+
+    /// Severity of a finding under this policy, or `None` if the finding is dropped
+    pub fn apply(self, severity: Severity, is_test: bool) -> Option<Severity> {
+        match (self, is_test) {
+            (TestFilePolicy::Skip, true) | (TestFilePolicy::Only, false) => None,
+            (TestFilePolicy::Downgrade, true) => Some(match severity {
+                Severity::Critical => Severity::Error,
+                Severity::Error => Severity::Warning,
+                Severity::Warning | Severity::Info => Severity::Info,
+            }),
+            _ => Some(severity),
+        }
+    }
+}
+
+/// Severity a rule's `test-severity` metadata assigns to its findings in test files
+pub fn test_severity(metadata: &HashMap<String, String>) -> Option<Severity> {
+    match metadata.get(TEST_SEVERITY_METADATA_KEY)?.trim().to_uppercase().as_str() {
+        "INFO" => Some(Severity::Info),
+        "WARNING" => Some(Severity::Warning),
+        "ERROR" => Some(Severity::Error),
+        "CRITICAL" => Some(Severity::Critical),
+        other => {
+            tracing::warn!("Ignoring unknown rule test-severity '{}'", other);
+            None
+        }
+    }
+}
+
+/// Whether `path` is test code: it lies under a test directory (`tests/`,
+/// `src/test/java/`, `__tests__/`, ...) or is named like a test of its language
+/// (`*_test.go`, `test_*.py`, `*.spec.ts`, `FooTest.java`, ...)
+pub fn is_test_file(path: &Path) -> bool {
+    let mut components: Vec<&str> = path.iter().filter_map(|c| c.to_str()).collect();
+    let file_name = match components.pop() {
+        Some(name) => name,
+        None => return false,
+    };
+    if components.iter().any(|dir| TEST_DIRS.contains(dir)) {
+        return true;
+    }
+
+    let (stem, extension) = match file_name.rsplit_once('.') {
+        Some((stem, extension)) => (stem, extension.to_ascii_lowercase()),
+        None => return false,
+    };
+    match extension.as_str() {
+        "go" => stem.ends_with("_test"),
+        "py" => stem.starts_with("test_") || stem.ends_with("_test") || stem == "conftest",
+        "js" | "jsx" | "mjs" | "cjs" | "ts" | "tsx" => {
+            let inner = stem.rsplit('.').next().unwrap_or("");
+            stem.contains('.') && (inner == "test" || inner == "spec")
+        }
+        "java" | "kt" | "cs" | "php" | "swift" => {
+            ["Test", "Tests", "IT", "Spec"].iter().any(|suffix| stem.len() > suffix.len() && stem.ends_with(suffix))
+        }
+        "rb" => stem.ends_with("_spec") || stem.ends_with("_test"),
+        "c" | "cpp" | "cc" => stem.starts_with("test_") || stem.ends_with("_test"),
+        "sh" | "bash" => stem.ends_with("_test") || stem.starts_with("test_"),
+        "bats" => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_test_file() {
+        for path in [
+            "pkg/server/handler_test.go",
+            "app/test_views.py",
+            "tests/helpers.py",
+            "web/src/App.spec.ts",
+            "web/src/util.test.jsx",
+            "src/test/java/com/acme/Helper.java",
+            "src/main/java/com/acme/UserServiceTest.java",
+            "ui/__tests__/button.js",
+            "spec/models/user_spec.rb",
+            "conftest.py",
+        ] {
+            assert!(is_test_file(Path::new(path)), "{}", path);
+        }
+        for path in [
+            "pkg/server/handler.go",
+            "app/views.py",
+            "web/src/spec.ts",
+            "web/src/App.ts",
+            "src/main/java/com/acme/Test.java",
+            "src/main/java/com/acme/Latest.java",
+            "contest/app.py",
+        ] {
+            assert!(!is_test_file(Path::new(path)), "{}", path);
+        }
+    }
+
+    #[test]
+    fn test_policy() {
+        assert_eq!(TestFilePolicy::parse("downgrade").unwrap(), TestFilePolicy::Downgrade);
+        assert!(TestFilePolicy::parse("ignore").is_err());
+
+        assert_eq!(TestFilePolicy::Skip.apply(Severity::Error, true), None);
+        assert_eq!(TestFilePolicy::Skip.apply(Severity::Error, false), Some(Severity::Error));
+        assert_eq!(TestFilePolicy::Downgrade.apply(Severity::Critical, true), Some(Severity::Error));
+        assert_eq!(TestFilePolicy::Downgrade.apply(Severity::Info, true), Some(Severity::Info));
+        assert_eq!(TestFilePolicy::Only.apply(Severity::Warning, false), None);
+        assert_eq!(TestFilePolicy::Only.apply(Severity::Warning, true), Some(Severity::Warning));
+
+        let mut metadata = HashMap::new();
+        assert_eq!(TestFilePolicy::for_rule(&metadata, TestFilePolicy::Skip), TestFilePolicy::Skip);
+        metadata.insert(TEST_FILES_METADATA_KEY.to_string(), "only".to_string());
+        metadata.insert(TEST_SEVERITY_METADATA_KEY.to_string(), "info".to_string());
+        assert_eq!(TestFilePolicy::for_rule(&metadata, TestFilePolicy::Skip), TestFilePolicy::Only);
+        assert_eq!(test_severity(&metadata), Some(Severity::Info));
+    }
+}
@@ -1,6 +1,6 @@
 //! Core traits for astgrep
 
-use crate::{Finding, Language, Result};
+use crate::{CancellationToken, Finding, Language, Result};
 use std::path::Path;
 
 /// Trait for language parsers
@@ -8,6 +8,14 @@ pub trait LanguageParser: Send + Sync {
     /// Parse source code and return an AST
     fn parse(&self, source: &str, file_path: &Path) -> Result<Box<dyn AstNode>>;
 
+    /// Parse source code, giving up with `AnalysisError::Cancelled` once `cancellation`
+    /// is set. Parsers that can take long poll the token while parsing; the default only
+    /// checks it before starting.
+    fn parse_cancellable(&self, source: &str, file_path: &Path, cancellation: &CancellationToken) -> Result<Box<dyn AstNode>> {
+        cancellation.check()?;
+        self.parse(source, file_path)
+    }
+
     /// Get the language this parser supports
     fn language(&self) -> Language;
 
@@ -245,6 +245,17 @@ pub enum OutputFormat {
     Sarif,
     Text,
     Xml,
+    /// Code Climate engine issues
+    CodeClimate,
+    Csv,
+    /// Self-contained interactive HTML report
+    Html,
+    /// Markdown report for pull request comments
+    Markdown,
+    /// JSON Lines, one finding per line
+    Jsonl,
+    /// GitHub Actions workflow commands annotating the findings
+    GithubActions,
 }
 
 impl OutputFormat {
@@ -255,6 +266,12 @@ impl OutputFormat {
             OutputFormat::Sarif => "sarif",
             OutputFormat::Text => "text",
             OutputFormat::Xml => "xml",
+            OutputFormat::CodeClimate => "codeclimate",
+            OutputFormat::Csv => "csv",
+            OutputFormat::Html => "html",
+            OutputFormat::Markdown => "markdown",
+            OutputFormat::Jsonl => "jsonl",
+            OutputFormat::GithubActions => "github-actions",
         }
     }
 
@@ -265,6 +282,12 @@ impl OutputFormat {
             "sarif" => Some(OutputFormat::Sarif),
             "text" | "txt" => Some(OutputFormat::Text),
             "xml" => Some(OutputFormat::Xml),
+            "codeclimate" | "code-climate" => Some(OutputFormat::CodeClimate),
+            "csv" => Some(OutputFormat::Csv),
+            "html" | "htm" => Some(OutputFormat::Html),
+            "markdown" | "md" => Some(OutputFormat::Markdown),
+            "jsonl" | "ndjson" => Some(OutputFormat::Jsonl),
+            "github-actions" | "github" => Some(OutputFormat::GithubActions),
             _ => None,
         }
     }
@@ -386,6 +409,12 @@ mod tests {
         assert_eq!(OutputFormat::from_str("sarif"), Some(OutputFormat::Sarif));
         assert_eq!(OutputFormat::from_str("text"), Some(OutputFormat::Text));
         assert_eq!(OutputFormat::from_str("xml"), Some(OutputFormat::Xml));
+        assert_eq!(OutputFormat::from_str("code-climate"), Some(OutputFormat::CodeClimate));
+        assert_eq!(OutputFormat::from_str("CSV"), Some(OutputFormat::Csv));
+        assert_eq!(OutputFormat::from_str("html"), Some(OutputFormat::Html));
+        assert_eq!(OutputFormat::from_str("md"), Some(OutputFormat::Markdown));
+        assert_eq!(OutputFormat::from_str("ndjson"), Some(OutputFormat::Jsonl));
+        assert_eq!(OutputFormat::from_str("github"), Some(OutputFormat::GithubActions));
         assert_eq!(OutputFormat::from_str("unknown"), None);
     }
 
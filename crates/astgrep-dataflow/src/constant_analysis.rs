@@ -7,8 +7,9 @@
 //! - Constant propagation through function calls
 
 use crate::constant_propagation::ConstantValue;
-use astgrep_core::Result;
-use std::collections::HashMap;
+use astgrep_core::Language;
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
 
 /// Represents a constant value with metadata
 #[derive(Debug, Clone)]
@@ -53,6 +54,7 @@ impl ConstantInfo {
 }
 
 /// Constant analysis engine
+#[derive(Debug, Clone)]
 pub struct ConstantAnalyzer {
     /// Map from variable name to constant info
     constants: HashMap<String, ConstantInfo>,
@@ -79,6 +81,48 @@ impl ConstantAnalyzer {
         }
     }
 
+    /// Build an analyzer from the assignments found in source text.
+    ///
+    /// Like `SymbolTable::from_source` this is a scope-insensitive pass: a variable is
+    /// constant when it is assigned exactly once, from an expression that folds to a
+    /// literal (`ALGO = "MD5"`, `final String URL = BASE + "/login";`). Reassigned and
+    /// incremented variables are marked mutable.
+    pub fn from_source(source: &str, _language: Language) -> Self {
+        let mut analyzer = Self::new();
+        // `[modifiers] [Type] name [: Type] [op]= value` at the start of a statement
+        let assignment = Regex::new(
+            r"(?m)(?:^|[;{}])\s*(?:[A-Za-z_][\w.]*(?:<[^<>;=]*>)?(?:\[\])*\s+)*([A-Za-z_]\w*)\s*(?::\s*[A-Za-z_][\w.\[\]<>]*\s*)?([-+*/%|&^]?)=([^=][^;\n]*)",
+        ).unwrap();
+        let increment = Regex::new(r"([A-Za-z_]\w*)\s*(?:\+\+|--)|(?:\+\+|--)\s*([A-Za-z_]\w*)").unwrap();
+
+        let mut assigned = HashSet::new();
+        for cap in assignment.captures_iter(source) {
+            let name = &cap[1];
+            let value = if cap[2].is_empty() { analyzer.fold_expression(&cap[3]) } else { None };
+            match value {
+                Some(value) if assigned.insert(name.to_string()) => analyzer.register_constant(name.to_string(), value),
+                _ => {
+                    assigned.insert(name.to_string());
+                    analyzer.mark_reassigned(name);
+                }
+            }
+        }
+        for cap in increment.captures_iter(source) {
+            if let Some(name) = cap.get(1).or_else(|| cap.get(2)) {
+                analyzer.mark_reassigned(name.as_str());
+            }
+        }
+        analyzer
+    }
+
+    /// Record another assignment to `name`, which makes a registered constant mutable
+    fn mark_reassigned(&mut self, name: &str) {
+        if let Some(info) = self.constants.get_mut(name) {
+            info.is_mutable = true;
+            info.increment_assignments();
+        }
+    }
+
     /// Register a constant
     pub fn register_constant(&mut self, name: String, value: ConstantValue) {
         let mut info = ConstantInfo::new(value.clone());
@@ -155,9 +199,11 @@ impl ConstantAnalyzer {
             return Some(ConstantValue::Integer(num));
         }
 
-        if expr.starts_with('"') && expr.ends_with('"') {
-            let string = expr[1..expr.len() - 1].to_string();
-            return Some(ConstantValue::String(string));
+        for quote in ['"', '\''] {
+            if expr.len() >= 2 && expr.starts_with(quote) && expr.ends_with(quote) {
+                let string = expr[1..expr.len() - 1].to_string();
+                return Some(ConstantValue::String(string));
+            }
         }
 
         if expr == "true" {
@@ -175,6 +221,36 @@ impl ConstantAnalyzer {
         None
     }
 
+    /// Fold an expression of literals and constants joined by `+`: integers are added,
+    /// and once a string is involved the operands are concatenated
+    /// (`"SELECT * FROM " + TABLE`). Returns `None` when any operand is not constant.
+    pub fn fold_expression(&self, expr: &str) -> Option<ConstantValue> {
+        let mut folded: Option<ConstantValue> = None;
+        for operand in split_concatenation(expr.trim()) {
+            let operand = operand.trim();
+            let operand = operand.strip_prefix('(').and_then(|o| o.strip_suffix(')')).unwrap_or(operand).trim();
+            let value = match self.fold_constants(operand) {
+                Some(value) => value,
+                None => {
+                    let name = operand.strip_prefix("this.").or_else(|| operand.strip_prefix("self.")).unwrap_or(operand);
+                    if !self.is_constant(name) {
+                        return None;
+                    }
+                    self.get_constant_value(name)?.clone()
+                }
+            };
+            folded = Some(match (folded, value) {
+                (None, value) => value,
+                (Some(ConstantValue::Integer(a)), ConstantValue::Integer(b)) => ConstantValue::Integer(a.checked_add(b)?),
+                (Some(left), right @ (ConstantValue::String(_) | ConstantValue::Integer(_))) if matches!(left, ConstantValue::String(_)) || matches!(right, ConstantValue::String(_)) => {
+                    ConstantValue::String(left.to_string_value()? + &right.to_string_value()?)
+                }
+                _ => return None,
+            });
+        }
+        folded
+    }
+
     /// Get all constants
     pub fn get_all_constants(&self) -> &HashMap<String, ConstantInfo> {
         &self.constants
@@ -194,6 +270,39 @@ impl ConstantAnalyzer {
     }
 }
 
+/// Split an expression at the `+` operators outside string literals and brackets
+fn split_concatenation(expr: &str) -> Vec<&str> {
+    let mut operands = Vec::new();
+    let mut depth = 0i32;
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    let mut start = 0;
+    for (i, ch) in expr.char_indices() {
+        if let Some(q) = quote {
+            if escaped {
+                escaped = false;
+            } else if ch == '\\' {
+                escaped = true;
+            } else if ch == q {
+                quote = None;
+            }
+            continue;
+        }
+        match ch {
+            '"' | '\'' => quote = Some(ch),
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            '+' if depth == 0 => {
+                operands.push(&expr[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    operands.push(&expr[start..]);
+    operands
+}
+
 impl Default for ConstantAnalyzer {
     fn default() -> Self {
         Self::new()
@@ -260,6 +369,30 @@ mod tests {
         assert_eq!(analyzer.fold_constants("null"), Some(ConstantValue::Null));
     }
 
+    #[test]
+    fn test_constants_from_source() {
+        let java = "class C {\n\
+            private static final String ALGO = \"MD5\";\n\
+            static final String TABLE = \"users\";\n\
+            String query = \"SELECT * FROM \" + TABLE;\n\
+            int size = 1024; int count = 0;\n\
+            void f(String input) { String dynamic = \"a\"; dynamic = input; count++; }\n\
+        }\n";
+        let analyzer = ConstantAnalyzer::from_source(java, Language::Java);
+        assert_eq!(analyzer.get_constant_value("ALGO"), Some(&ConstantValue::String("MD5".to_string())));
+        assert_eq!(analyzer.fold_expression("query"), Some(ConstantValue::String("SELECT * FROM users".to_string())));
+        assert_eq!(analyzer.fold_expression("size + 1024"), Some(ConstantValue::Integer(2048)));
+        assert_eq!(analyzer.fold_expression("\"v\" + size"), Some(ConstantValue::String("v1024".to_string())));
+        assert!(!analyzer.is_constant("dynamic"));
+        assert!(!analyzer.is_constant("count"));
+        assert_eq!(analyzer.fold_expression("ALGO + input"), None);
+
+        let python = "ALGO = 'sha1'\nhashlib.new(algo=ALGO)\n";
+        let analyzer = ConstantAnalyzer::from_source(python, Language::Python);
+        assert_eq!(analyzer.fold_expression("ALGO"), Some(ConstantValue::String("sha1".to_string())));
+        assert!(!analyzer.is_constant("algo"));
+    }
+
     #[test]
     fn test_constant_analyzer_function_returns() {
         let mut analyzer = ConstantAnalyzer::new();
@@ -4,7 +4,8 @@ use crate::sources::{Source, SourceType};
 use crate::sinks::{Sink, SinkType};
 use crate::sanitizers::Sanitizer;
 use crate::graph::{DataFlowGraph, NodeId};
-use astgrep_core::Result;
+use astgrep_core::{AnalysisError, Result};
+use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 
 /// Applied sanitizer information
@@ -93,21 +94,25 @@ pub struct EnhancedTaintFlow {
 }
 
 /// Configuration for taint analysis
-#[derive(Debug, Clone)]
+///
+/// Every field has a default, so partial TOML/YAML/JSON documents deserialize and
+/// library users can start from `TaintAnalysisConfig::new()` and chain `with_*` calls.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct TaintAnalysisConfig {
-    /// Maximum path length to prevent infinite loops
+    /// Maximum path length to prevent infinite loops. Default: `50`
     pub max_path_length: usize,
-    /// Maximum number of contexts to track
+    /// Maximum number of contexts to track. Default: `100`
     pub max_contexts: usize,
-    /// Enable field-sensitive analysis
+    /// Enable field-sensitive analysis. Default: `true`
     pub field_sensitive: bool,
-    /// Enable context-sensitive analysis
+    /// Enable context-sensitive analysis. Default: `true`
     pub context_sensitive: bool,
-    /// Enable path-sensitive analysis
+    /// Enable path-sensitive analysis. Default: `false`
     pub path_sensitive: bool,
-    /// Minimum confidence threshold
+    /// Minimum confidence threshold (0 to 100). Default: `10`
     pub min_confidence: u8,
-    /// Minimum confidence threshold for reporting flows
+    /// Minimum confidence threshold for reporting flows (0 to 100). Default: `30`
     pub min_confidence_threshold: u8,
 }
 
@@ -125,6 +130,66 @@ impl Default for TaintAnalysisConfig {
     }
 }
 
+impl TaintAnalysisConfig {
+    /// Create a configuration with the default settings
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set maximum path length
+    pub fn with_max_path_length(mut self, length: usize) -> Self {
+        self.max_path_length = length;
+        self
+    }
+
+    /// Set maximum number of tracked contexts
+    pub fn with_max_contexts(mut self, contexts: usize) -> Self {
+        self.max_contexts = contexts;
+        self
+    }
+
+    /// Enable or disable field-sensitive analysis
+    pub fn with_field_sensitivity(mut self, enabled: bool) -> Self {
+        self.field_sensitive = enabled;
+        self
+    }
+
+    /// Enable or disable context-sensitive analysis
+    pub fn with_context_sensitivity(mut self, enabled: bool) -> Self {
+        self.context_sensitive = enabled;
+        self
+    }
+
+    /// Enable or disable path-sensitive analysis
+    pub fn with_path_sensitivity(mut self, enabled: bool) -> Self {
+        self.path_sensitive = enabled;
+        self
+    }
+
+    /// Set minimum confidence for tracking taint
+    pub fn with_min_confidence(mut self, confidence: u8) -> Self {
+        self.min_confidence = confidence;
+        self
+    }
+
+    /// Set minimum confidence for reporting flows
+    pub fn with_min_confidence_threshold(mut self, threshold: u8) -> Self {
+        self.min_confidence_threshold = threshold;
+        self
+    }
+
+    /// Check that the settings are usable
+    pub fn validate(&self) -> Result<()> {
+        if self.max_path_length == 0 {
+            return Err(AnalysisError::config_error("max_path_length must be greater than 0"));
+        }
+        if self.min_confidence > 100 || self.min_confidence_threshold > 100 {
+            return Err(AnalysisError::config_error("confidence thresholds must be between 0 and 100"));
+        }
+        Ok(())
+    }
+}
+
 /// Enhanced taint tracker
 pub struct EnhancedTaintTracker {
     /// Taint states for each node
@@ -195,6 +260,11 @@ impl EnhancedTaintTracker {
         }
     }
 
+    /// Get the analysis configuration
+    pub fn config(&self) -> &TaintAnalysisConfig {
+        &self.config
+    }
+
     /// Perform enhanced taint analysis
     pub fn analyze_taint(
         &mut self,
@@ -399,3 +469,22 @@ impl EnhancedTaintTracker {
         (confidence * 100.0) as u8
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_taint_config_builder_and_serde() {
+        let config = TaintAnalysisConfig::new()
+            .with_field_sensitivity(false)
+            .with_path_sensitivity(true)
+            .with_max_path_length(20);
+        assert!(config.validate().is_ok());
+        assert_eq!(EnhancedTaintTracker::with_config(config.clone()).config(), &config);
+
+        let parsed: TaintAnalysisConfig = serde_json::from_str(r#"{"field_sensitive": false, "path_sensitive": true, "max_path_length": 20}"#).unwrap();
+        assert_eq!(parsed, config);
+        assert!(TaintAnalysisConfig::new().with_min_confidence(150).validate().is_err());
+    }
+}
@@ -0,0 +1,344 @@
+//! Exception flow
+//!
+//! Nodes that can raise an exception inside a try body, i.e. throw/raise statements and
+//! calls, get `Exception` edges to the catch clauses that may handle them, and a thrown
+//! value flows into the catch parameter. User input embedded in an exception message is
+//! thereby tracked into whatever the handler does with the caught exception. A catch
+//! clause without incoming exception edges is unreachable: its try body cannot throw,
+//! or an earlier clause of the same try catches everything.
+
+use crate::graph::{DataFlowGraph, EdgeType, NodeId};
+use astgrep_core::AstNode;
+use std::collections::BTreeMap;
+
+/// Node attribute marking catch clauses in the data flow graph
+pub const EXCEPTION_HANDLER_ATTRIBUTE: &str = "exception_handler";
+
+/// Exception types every handler naming them catches
+const ROOT_EXCEPTION_TYPES: &[&str] = &["Exception", "Throwable", "BaseException"];
+
+/// Role of a node in a try statement
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TryPart {
+    Try,
+    Catch,
+    Finally,
+}
+
+impl TryPart {
+    /// Role of `node`, if it is a try statement or one of its clauses
+    pub fn of(node: &dyn AstNode) -> Option<Self> {
+        match (node.node_type(), node.get_attribute("ts_kind")) {
+            ("except_statement", _) | (_, Some("catch_clause" | "except_clause" | "rescue")) => Some(TryPart::Catch),
+            ("finally_statement", _) | (_, Some("finally_clause" | "ensure")) => Some(TryPart::Finally),
+            ("try_statement", _) | (_, Some("try_statement" | "try_with_resources_statement" | "begin")) => {
+                Some(TryPart::Try)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Exception types and parameter of a catch clause
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CatchClause {
+    /// Simple names of the caught types; empty for a clause catching everything
+    pub types: Vec<String>,
+    /// Variable the caught exception is bound to
+    pub parameter: Option<String>,
+}
+
+impl CatchClause {
+    /// Read the clause from `exception_type`/`name` attributes or from its header:
+    /// `catch (IOException | SQLException e)`, `catch (e)`, `catch (e: IOException)`,
+    /// `except (ValueError, KeyError) as e:`, `except:`
+    pub fn parse(node: &dyn AstNode) -> Self {
+        if let Some(types) = node.get_attribute("exception_type") {
+            return Self {
+                types: split_types(types),
+                parameter: node.get_attribute("name").map(|name| name.to_string()),
+            };
+        }
+
+        let text = node.text().unwrap_or("").trim();
+        let header = text
+            .trim_start_matches("catch")
+            .trim_start_matches("except")
+            .trim_start_matches("rescue");
+        let header = header.split(['{', '\n']).next().unwrap_or("").trim().trim_end_matches(':').trim();
+        let header = header.strip_prefix('(').and_then(|h| h.strip_suffix(')')).unwrap_or(header).trim();
+        if header.is_empty() {
+            return Self::default();
+        }
+
+        if let Some((types, parameter)) = header.rsplit_once(" as ").or_else(|| header.rsplit_once("=>")) {
+            return Self { types: split_types(types), parameter: Some(parameter.trim().to_string()) };
+        }
+        if let Some((parameter, types)) = header.split_once(':') {
+            return Self { types: split_types(types), parameter: Some(parameter.trim().to_string()) };
+        }
+        if text.starts_with("except") {
+            return Self { types: split_types(header), parameter: None };
+        }
+        match header.rsplit_once(char::is_whitespace) {
+            Some((types, parameter)) => Self { types: split_types(types), parameter: Some(parameter.trim().to_string()) },
+            None => Self { types: Vec::new(), parameter: Some(header.to_string()) },
+        }
+    }
+
+    /// Whether the clause catches every exception of type `thrown` (`None` when unknown)
+    pub fn definitely_catches(&self, thrown: Option<&str>) -> bool {
+        self.types.is_empty()
+            || self.types.iter().any(|t| ROOT_EXCEPTION_TYPES.contains(&t.as_str()) || Some(t.as_str()) == thrown)
+    }
+}
+
+/// Whether `node` throws or raises an exception explicitly
+pub fn is_throw(node: &dyn AstNode) -> bool {
+    node.node_type() == "throw_statement"
+        || matches!(node.get_attribute("ts_kind"), Some("raise_statement" | "throw_expression"))
+}
+
+/// Simple name of the type of exception a throw statement constructs, if it constructs one:
+/// `throw new IOException(...)`, `raise ValueError(...)`
+pub fn thrown_type(node: &dyn AstNode) -> Option<String> {
+    let text = node.text()?.trim();
+    let rest = text.strip_prefix("throw").or_else(|| text.strip_prefix("raise"))?.trim_start();
+    let (rest, constructed) = match rest.strip_prefix("new ") {
+        Some(rest) => (rest.trim_start(), true),
+        None => (rest, false),
+    };
+    let name: String = rest.chars().take_while(|c| c.is_alphanumeric() || matches!(c, '_' | '.' | '\\')).collect();
+    let called = rest[name.len()..].trim_start().starts_with('(');
+    if name.is_empty() || !(constructed || called) {
+        return None;
+    }
+    Some(simple_name(&name).to_string())
+}
+
+/// Try statements and raising nodes collected while the graph is built
+#[derive(Debug, Default)]
+pub(crate) struct ExceptionRegions {
+    /// Try statements whose body is being visited, innermost last
+    protecting: Vec<NodeId>,
+    tries: BTreeMap<NodeId, TryRegion>,
+    raises: Vec<RaiseSite>,
+}
+
+#[derive(Debug, Default)]
+struct TryRegion {
+    body: Vec<NodeId>,
+    catches: Vec<Handler>,
+    finally: Option<NodeId>,
+}
+
+#[derive(Debug)]
+struct Handler {
+    node: NodeId,
+    /// First node id after the clause's subtree
+    end: NodeId,
+    clause: CatchClause,
+}
+
+#[derive(Debug)]
+struct RaiseSite {
+    node: NodeId,
+    thrown: Option<String>,
+    is_throw: bool,
+    /// Enclosing try statements protecting the node, innermost last
+    protecting: Vec<NodeId>,
+}
+
+impl ExceptionRegions {
+    pub(crate) fn enter_try_body(&mut self, try_node: NodeId) {
+        self.protecting.push(try_node);
+    }
+
+    pub(crate) fn exit_try_body(&mut self, try_node: NodeId, body: NodeId) {
+        self.protecting.pop();
+        self.tries.entry(try_node).or_default().body.push(body);
+    }
+
+    pub(crate) fn add_catch(&mut self, try_node: NodeId, node: NodeId, end: NodeId, clause: CatchClause) {
+        self.tries.entry(try_node).or_default().catches.push(Handler { node, end, clause });
+    }
+
+    pub(crate) fn add_finally(&mut self, try_node: NodeId, node: NodeId) {
+        self.tries.entry(try_node).or_default().finally = Some(node);
+    }
+
+    /// Record `ast_node` if it may raise, i.e. is a throw or a call inside a try body
+    pub(crate) fn add_raise(&mut self, node: NodeId, ast_node: &dyn AstNode) {
+        let is_throw = is_throw(ast_node);
+        if self.protecting.is_empty() || !(is_throw || ast_node.node_type() == "call_expression") {
+            return;
+        }
+        self.raises.push(RaiseSite {
+            node,
+            thrown: if is_throw { thrown_type(ast_node) } else { None },
+            is_throw,
+            protecting: self.protecting.clone(),
+        });
+    }
+
+    /// Add exception, finally and catch-parameter edges to `graph`, whose node ids are
+    /// pre-order indices so a clause's subtree is the id range `node..end`
+    pub(crate) fn add_edges(&self, graph: &mut DataFlowGraph) {
+        let mut parameters = BTreeMap::new();
+        for region in self.tries.values() {
+            if let Some(finally) = region.finally {
+                for &from in region.body.iter().chain(region.catches.iter().map(|h| &h.node)) {
+                    graph.add_edge(from, finally, EdgeType::ControlFlow);
+                }
+            }
+            for handler in &region.catches {
+                parameters.insert(handler.node, Self::bind_parameter(graph, handler));
+            }
+        }
+
+        for raise in &self.raises {
+            'handled: for try_node in raise.protecting.iter().rev() {
+                let Some(region) = self.tries.get(try_node) else { continue };
+                for handler in &region.catches {
+                    graph.add_edge(raise.node, handler.node, EdgeType::Exception);
+                    if raise.is_throw {
+                        graph.add_edge(raise.node, parameters[&handler.node], EdgeType::DataFlow);
+                    }
+                    if handler.clause.definitely_catches(raise.thrown.as_deref()) {
+                        break 'handled;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Node the caught exception is bound to (the parameter identifier, or the clause
+    /// itself), connected to the uses of the parameter in the clause
+    fn bind_parameter(graph: &mut DataFlowGraph, handler: &Handler) -> NodeId {
+        let Some(name) = handler.clause.parameter.as_deref() else { return handler.node };
+        let uses: Vec<NodeId> = (handler.node + 1..handler.end)
+            .filter(|id| {
+                graph
+                    .get_node(*id)
+                    .is_some_and(|n| n.node_type == "identifier" && n.text.as_deref().map(str::trim) == Some(name))
+            })
+            .collect();
+        let Some((&parameter, uses)) = uses.split_first() else { return handler.node };
+        for &use_node in uses {
+            graph.add_edge(parameter, use_node, EdgeType::DataFlow);
+        }
+        parameter
+    }
+}
+
+fn split_types(types: &str) -> Vec<String> {
+    types
+        .trim()
+        .trim_start_matches('(')
+        .trim_end_matches(')')
+        .split(['|', ','])
+        .map(|t| simple_name(t.trim()).to_string())
+        .filter(|t| !t.is_empty())
+        .collect()
+}
+
+fn simple_name(name: &str) -> &str {
+    name.rsplit(['.', '\\']).next().unwrap_or(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use astgrep_ast::{NodeType, UniversalNode};
+
+    fn clause(text: &str) -> CatchClause {
+        CatchClause::parse(&UniversalNode::new(NodeType::TryStatement).with_text(text.to_string()))
+    }
+
+    #[test]
+    fn test_parse_catch_clauses() {
+        let java = clause("catch (IOException | java.sql.SQLException e) {\n log(e);\n}");
+        assert_eq!(java.types, vec!["IOException", "SQLException"]);
+        assert_eq!(java.parameter.as_deref(), Some("e"));
+
+        assert_eq!(clause("catch (err) { }").parameter.as_deref(), Some("err"));
+        assert!(clause("catch (err) { }").types.is_empty());
+        assert_eq!(clause("catch (e: IOException) { }").types, vec!["IOException"]);
+
+        let python = clause("except (ValueError, KeyError) as exc:\n    pass");
+        assert_eq!((python.types.len(), python.parameter.as_deref()), (2, Some("exc")));
+        assert_eq!(clause("except ValueError:\n    pass").parameter, None);
+        assert!(clause("except:\n    pass").definitely_catches(Some("KeyError")));
+
+        assert!(java.definitely_catches(Some("IOException")));
+        assert!(!java.definitely_catches(None));
+        assert!(clause("catch (Exception e) {}").definitely_catches(None));
+    }
+
+    #[test]
+    fn test_taint_through_caught_exception() {
+        use crate::{DataFlowAnalyzer, Sink, SinkType, Source, SourceType, TaintTracker};
+        use astgrep_ast::AstBuilder;
+
+        // try { throw Exception("bad: " + req); }
+        // catch (Exception e) { log(e.message); }
+        // catch (ValueError) { }
+        let message = UniversalNode::new(NodeType::BinaryExpression)
+            .add_child(AstBuilder::string_literal("bad: "))
+            .add_child(AstBuilder::identifier("req"));
+        let throw = UniversalNode::new(NodeType::ThrowStatement)
+            .add_child(AstBuilder::call_expression(AstBuilder::identifier("Exception"), vec![message]));
+        let handler = UniversalNode::new(NodeType::ExceptStatement)
+            .with_attribute("exception_type".to_string(), "Exception".to_string())
+            .with_attribute("name".to_string(), "e".to_string())
+            .add_child(AstBuilder::block_statement(vec![AstBuilder::expression_statement(AstBuilder::call_expression(
+                AstBuilder::identifier("log"),
+                vec![AstBuilder::member_expression(AstBuilder::identifier("e"), AstBuilder::identifier("message"))],
+            ))]));
+        let shadowed = AstBuilder::except_statement("ValueError").add_child(AstBuilder::block_statement(vec![]));
+        let root = AstBuilder::program(vec![AstBuilder::try_statement()
+            .add_child(AstBuilder::block_statement(vec![throw]))
+            .add_child(handler)
+            .add_child(shadowed)]);
+
+        let analysis = DataFlowAnalyzer::new().analyze(&root).unwrap();
+        let graph = &analysis.graph;
+        let id_of = |node_type: &str, text: &str| {
+            graph
+                .node_ids()
+                .find(|id| graph.get_node(*id).is_some_and(|n| n.node_type == node_type && n.text.as_deref() == Some(text)))
+                .unwrap()
+        };
+        let catches: Vec<NodeId> = {
+            let mut ids: Vec<NodeId> = graph
+                .node_ids()
+                .filter(|id| graph.get_node(*id).is_some_and(|n| n.attributes.contains_key(EXCEPTION_HANDLER_ATTRIBUTE)))
+                .collect();
+            ids.sort_unstable();
+            ids
+        };
+        assert_eq!(catches.len(), 2);
+        // The first clause catches everything, so the second one is unreachable
+        assert_eq!(analysis.unreachable_catches(), vec![catches[1]]);
+
+        let request = id_of("identifier", "req");
+        let log = graph
+            .node_ids()
+            .filter(|id| graph.get_node(*id).is_some_and(|n| n.node_type == "call_expression"))
+            .max()
+            .unwrap();
+        let sources = vec![Source::new(request, SourceType::UserInput, "Request".to_string())];
+        let sinks = vec![Sink::new(log, SinkType::HtmlOutput, "XSS".to_string(), "log".to_string())];
+        let flows = TaintTracker::new().track_taint(graph, &sources, &sinks, &[]).unwrap();
+        assert_eq!(flows.len(), 1);
+        assert!(flows[0].path.contains(&id_of("identifier", "e")));
+    }
+
+    #[test]
+    fn test_thrown_type() {
+        let throw = |text: &str| UniversalNode::new(NodeType::ThrowStatement).with_text(text.to_string());
+        assert_eq!(thrown_type(&throw("throw new IllegalStateException(\"bad \" + id);")).as_deref(), Some("IllegalStateException"));
+        assert_eq!(thrown_type(&throw("raise errors.ValidationError(msg)")).as_deref(), Some("ValidationError"));
+        assert_eq!(thrown_type(&throw("throw e;")), None);
+    }
+}
@@ -73,7 +73,7 @@ impl DataFlowGraph {
     /// Check if there's a data flow edge between two nodes
     pub fn has_data_flow_edge(&self, from: NodeId, to: NodeId) -> bool {
         if let Some(edges) = self.edges.get(&from) {
-            edges.iter().any(|edge| edge.to == to && matches!(edge.edge_type, EdgeType::DataFlow))
+            edges.iter().any(|edge| edge.to == to && edge.edge_type.carries_data())
         } else {
             false
         }
@@ -108,7 +108,7 @@ impl DataFlowGraph {
     pub fn data_flow_successors(&self, id: NodeId) -> Vec<NodeId> {
         self.outgoing_edges(id)
             .iter()
-            .filter(|e| matches!(e.edge_type, EdgeType::DataFlow))
+            .filter(|e| e.edge_type.carries_data())
             .map(|e| e.to)
             .collect()
     }
@@ -117,7 +117,7 @@ impl DataFlowGraph {
     pub fn data_flow_predecessors(&self, id: NodeId) -> Vec<NodeId> {
         self.incoming_edges(id)
             .iter()
-            .filter(|e| matches!(e.edge_type, EdgeType::DataFlow))
+            .filter(|e| e.edge_type.carries_data())
             .map(|e| e.from)
             .collect()
     }
@@ -315,6 +315,11 @@ pub enum EdgeType {
     Call,
     /// Return edge (function return)
     Return,
+    /// Captured variable edge (enclosing declaration to its use inside a closure, or
+    /// a closure's assignment back to the enclosing declaration)
+    Capture,
+    /// Exception edge (throw or call inside a try body to a catch clause that may handle it)
+    Exception,
 }
 
 impl EdgeType {
@@ -325,8 +330,15 @@ impl EdgeType {
             EdgeType::DataFlow => "data_flow",
             EdgeType::Call => "call",
             EdgeType::Return => "return",
+            EdgeType::Capture => "capture",
+            EdgeType::Exception => "exception",
         }
     }
+
+    /// Whether data moves along this edge, so taint propagates over it
+    pub fn carries_data(&self) -> bool {
+        matches!(self, EdgeType::DataFlow | EdgeType::Capture)
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,248 @@
+//! Import and alias tracking
+//!
+//! Records the local names a file gives to imported modules and members, so that a
+//! pattern written against the qualified name (`subprocess.call(...)`) also matches code
+//! using the alias (`sp.call(...)` after `import subprocess as sp`).
+
+use astgrep_core::Language;
+use regex::Regex;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// Local names bound by the imports of one file, mapped to the qualified names they stand for
+#[derive(Debug, Clone, Default)]
+pub struct ImportAliases {
+    aliases: HashMap<String, String>,
+}
+
+impl ImportAliases {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Collect the imports of a file from its source.
+    ///
+    /// Like `SymbolTable::from_source` this is a scope-insensitive pass over the text. It
+    /// covers Python `import m as a` and `from m import x [as a]`, JavaScript
+    /// `require('m')` assignments (plain and destructured) and `import` declarations, and
+    /// Java and Kotlin single-type and static imports (`import a.b.C [as D]`).
+    pub fn from_source(source: &str, language: Language) -> Self {
+        static PY_IMPORT: OnceLock<Regex> = OnceLock::new();
+        static PY_FROM: OnceLock<Regex> = OnceLock::new();
+        static JS_REQUIRE: OnceLock<Regex> = OnceLock::new();
+        static JS_IMPORT: OnceLock<Regex> = OnceLock::new();
+        static JVM_IMPORT: OnceLock<Regex> = OnceLock::new();
+
+        let mut aliases = Self::new();
+        match language {
+            Language::Python => {
+                let import = PY_IMPORT.get_or_init(|| Regex::new(r"(?m)^\s*import\s+([^\n#]+)").unwrap());
+                for cap in import.captures_iter(source) {
+                    for item in cap[1].split(',') {
+                        if let Some((module, alias)) = split_alias(item, " as ") {
+                            aliases.add(alias, module);
+                        }
+                    }
+                }
+                let from = PY_FROM.get_or_init(|| Regex::new(r"(?m)^\s*from\s+([\w.]+)\s+import\s+(\([^)]*\)|[^\n#]+)").unwrap());
+                for cap in from.captures_iter(source) {
+                    for item in cap[2].trim_matches(|c| c == '(' || c == ')').split(',') {
+                        let (name, alias) = split_alias(item, " as ").unwrap_or((item.trim(), item.trim()));
+                        if name != "*" {
+                            aliases.add(alias, &format!("{}.{}", &cap[1], name));
+                        }
+                    }
+                }
+            }
+            Language::JavaScript => {
+                // `const cp = require('child_process')`, `const { exec, spawn: run } = require(...)`
+                let require = JS_REQUIRE.get_or_init(|| {
+                    Regex::new(r#"\b(?:const|let|var)\s+(\{[^}]*\}|[A-Za-z_$][\w$]*)\s*=\s*require\(\s*['"]([^'"]+)['"]\s*\)"#).unwrap()
+                });
+                for cap in require.captures_iter(source) {
+                    aliases.add_js_binding(&cap[1], &cap[2], ':');
+                }
+                // `import cp from 'm'`, `import * as cp from 'm'`, `import { exec as run } from 'm'`
+                let import = JS_IMPORT.get_or_init(|| Regex::new(r#"\bimport\s+([^;'"]+?)\s+from\s+['"]([^'"]+)['"]"#).unwrap());
+                for cap in import.captures_iter(source) {
+                    let module = &cap[2];
+                    for part in split_top_level(&cap[1]) {
+                        match part.strip_prefix('*') {
+                            Some(namespace) => {
+                                if let Some(alias) = namespace.trim().strip_prefix("as") {
+                                    aliases.add(alias, module);
+                                }
+                            }
+                            None => aliases.add_js_binding(part, module, ' '),
+                        }
+                    }
+                }
+            }
+            Language::Java | Language::Kotlin => {
+                let import = JVM_IMPORT.get_or_init(|| Regex::new(r"(?m)^\s*import\s+(?:static\s+)?([\w.]+)(?:\s+as\s+(\w+))?").unwrap());
+                for cap in import.captures_iter(source) {
+                    let qualified = &cap[1];
+                    let alias = cap.get(2).map_or_else(|| qualified.rsplit('.').next().unwrap_or(qualified), |alias| alias.as_str());
+                    if qualified.contains('.') {
+                        aliases.add(alias, qualified);
+                    }
+                }
+            }
+            _ => {}
+        }
+        aliases
+    }
+
+    /// Record that `alias` stands for `qualified` in this file
+    pub fn add(&mut self, alias: &str, qualified: &str) {
+        let (alias, qualified) = (alias.trim(), qualified.trim());
+        if alias != qualified && is_name(alias) && !qualified.is_empty() {
+            self.aliases.insert(alias.to_string(), qualified.to_string());
+        }
+    }
+
+    /// Record the names bound by a JavaScript import or `require` target: a default or
+    /// namespace name, or `{ member [sep alias], ... }`
+    fn add_js_binding(&mut self, binding: &str, module: &str, separator: char) {
+        let Some(members) = binding.trim().strip_prefix('{').and_then(|b| b.strip_suffix('}')) else {
+            return self.add(binding, module);
+        };
+        for member in members.split(',') {
+            let (name, alias) = match separator {
+                ':' => member.split_once(':'),
+                _ => split_alias(member, " as "),
+            }
+            .unwrap_or((member, member));
+            self.add(alias, &format!("{}.{}", module, name.trim()));
+        }
+    }
+
+    /// The qualified name `alias` stands for
+    pub fn resolve(&self, alias: &str) -> Option<&str> {
+        self.aliases.get(alias).map(String::as_str)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.aliases.is_empty()
+    }
+
+    /// Spellings of `code` with a qualified name written in it replaced by an alias this
+    /// file imports it under, one replacement per spelling. A qualified name is replaced
+    /// where it is a whole name or the start of a longer dotted name, so with
+    /// `import subprocess as sp`, `subprocess.call(...)` is also spelled `sp.call(...)`.
+    pub fn local_spellings(&self, code: &str) -> Vec<String> {
+        let mut aliases: Vec<_> = self.aliases.iter().collect();
+        aliases.sort();
+        let mut spellings = Vec::new();
+        for (alias, qualified) in aliases {
+            for (start, _) in code.match_indices(qualified.as_str()) {
+                let end = start + qualified.len();
+                let before = code[..start].chars().next_back();
+                let after = code[end..].chars().next();
+                let whole = before.is_none_or(|c| !is_name_char(c) && c != '.' && c != '$') && after.is_none_or(|c| !is_name_char(c));
+                if whole && !inside_string(&code[..start]) {
+                    let spelling = format!("{}{}{}", &code[..start], alias, &code[end..]);
+                    if !spellings.contains(&spelling) {
+                        spellings.push(spelling);
+                    }
+                }
+            }
+        }
+        spellings
+    }
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
+
+fn is_name(text: &str) -> bool {
+    !text.is_empty() && !text.starts_with(|c: char| c.is_ascii_digit()) && text.chars().all(is_name_char)
+}
+
+/// `name sep alias` split into its parts, trimmed
+fn split_alias<'a>(item: &'a str, separator: &str) -> Option<(&'a str, &'a str)> {
+    item.split_once(separator).map(|(name, alias)| (name.trim(), alias.trim()))
+}
+
+/// Split the clause of a JavaScript import on commas outside braces
+fn split_top_level(clause: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut depth, mut start) = (0, 0);
+    for (i, c) in clause.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(clause[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(clause[start..].trim());
+    parts.retain(|part| !part.is_empty());
+    parts
+}
+
+/// Whether the end of `before` is inside a string literal
+fn inside_string(before: &str) -> bool {
+    let mut quote = None;
+    let mut escaped = false;
+    for c in before.chars() {
+        match quote {
+            _ if escaped => escaped = false,
+            Some(_) if c == '\\' => escaped = true,
+            Some(q) if c == q => quote = None,
+            None if matches!(c, '"' | '\'' | '`') => quote = Some(c),
+            _ => {}
+        }
+    }
+    quote.is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_python_imports() {
+        let source = "import os, subprocess as sp\nfrom os import path as osp, getenv\nfrom shlex import (quote)\nfrom x import *\n";
+        let aliases = ImportAliases::from_source(source, Language::Python);
+        assert_eq!(aliases.resolve("sp"), Some("subprocess"));
+        assert_eq!(aliases.resolve("osp"), Some("os.path"));
+        assert_eq!(aliases.resolve("getenv"), Some("os.getenv"));
+        assert_eq!(aliases.resolve("quote"), Some("shlex.quote"));
+        assert_eq!(aliases.resolve("os"), None);
+    }
+
+    #[test]
+    fn test_javascript_imports() {
+        let source = "const cp = require('child_process');\nconst { exec, spawn: run } = require(\"child_process\");\n\
+                      import fs, { readFile as rf } from 'fs';\nimport * as path from 'path';\n";
+        let aliases = ImportAliases::from_source(source, Language::JavaScript);
+        assert_eq!(aliases.resolve("cp"), Some("child_process"));
+        assert_eq!(aliases.resolve("exec"), Some("child_process.exec"));
+        assert_eq!(aliases.resolve("run"), Some("child_process.spawn"));
+        assert_eq!(aliases.resolve("fs"), None);
+        assert_eq!(aliases.resolve("rf"), Some("fs.readFile"));
+        assert_eq!(aliases.resolve("path"), None);
+    }
+
+    #[test]
+    fn test_java_and_kotlin_imports() {
+        let java = ImportAliases::from_source("import java.lang.Runtime;\nimport static java.lang.Runtime.getRuntime;\nimport java.util.*;\n", Language::Java);
+        assert_eq!(java.resolve("Runtime"), Some("java.lang.Runtime"));
+        assert_eq!(java.resolve("getRuntime"), Some("java.lang.Runtime.getRuntime"));
+        let kotlin = ImportAliases::from_source("import java.lang.ProcessBuilder as PB\n", Language::Kotlin);
+        assert_eq!(kotlin.resolve("PB"), Some("java.lang.ProcessBuilder"));
+    }
+
+    #[test]
+    fn test_local_spellings() {
+        let aliases = ImportAliases::from_source("import subprocess as sp\nfrom subprocess import call\n", Language::Python);
+        assert_eq!(aliases.local_spellings("subprocess.call($CMD, shell=True)"), ["call($CMD, shell=True)", "sp.call($CMD, shell=True)"]);
+        // Only whole names, outside strings
+        assert!(aliases.local_spellings("mysubprocess.run(\"subprocess\")").is_empty());
+    }
+}
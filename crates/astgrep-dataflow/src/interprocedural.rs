@@ -3,9 +3,10 @@
 //! This module provides cross-function taint tracking and data flow analysis
 //! by leveraging the call graph to trace taint through function calls.
 
-use crate::call_graph::{CallGraph, FunctionId, FunctionSignature, ParameterMapping};
+use crate::call_graph::{CallGraph, FunctionCall, FunctionId, FunctionSignature, ParameterMapping};
 use crate::graph::{DataFlowGraph, NodeId};
-use crate::taint::{TaintFlow, TaintState};
+use crate::models::{ModelKind, ModelSet, TaintPort};
+use crate::taint::{FlowType, TaintFlow, TaintState};
 use crate::sources::{Source, SourceType};
 use crate::sinks::{Sink, SinkType};
 use crate::sanitizers::Sanitizer;
@@ -21,6 +22,8 @@ pub struct InterproceduralTaintTracker {
     exit_taints: HashMap<FunctionId, Vec<TaintState>>,
     /// Visited functions to avoid infinite loops
     visited_functions: HashSet<FunctionId>,
+    /// Summaries of library functions that have no definition in the call graph
+    models: ModelSet,
 }
 
 impl InterproceduralTaintTracker {
@@ -31,9 +34,16 @@ impl InterproceduralTaintTracker {
             entry_taints: HashMap::new(),
             exit_taints: HashMap::new(),
             visited_functions: HashSet::new(),
+            models: ModelSet::new(),
         }
     }
 
+    /// Use `models` for calls to functions without a definition in the call graph
+    pub fn with_models(mut self, models: ModelSet) -> Self {
+        self.models = models;
+        self
+    }
+
     /// Trace taint through function calls
     pub fn trace_taint_through_calls(
         &mut self,
@@ -96,7 +106,7 @@ impl InterproceduralTaintTracker {
                 )?;
             }
 
-            // Recursively trace callees
+            // Recursively trace callees; library calls are resolved through their models
             if let Some(callee_def) = self.call_graph.functions().get(&call.callee_signature) {
                 let callee_id = callee_def.id;
                 self.trace_function_taint(
@@ -107,6 +117,8 @@ impl InterproceduralTaintTracker {
                     _sanitizers,
                     _flows,
                 )?;
+            } else {
+                self.trace_modeled_call(&call, _sources, _flows);
             }
         }
 
@@ -141,6 +153,69 @@ impl InterproceduralTaintTracker {
         Ok(())
     }
 
+    /// Record a flow when a tainted argument reaches a modeled sink
+    fn trace_modeled_call(&self, call: &FunctionCall, sources: &[Source], flows: &mut Vec<TaintFlow>) {
+        let model = match self.models.lookup(&call.callee_signature.name) {
+            Some(model) => model,
+            None => return,
+        };
+        let (sink_type, vulnerability_type) = match &model.kind {
+            ModelKind::Sink { sink_type, vulnerability_type, .. } => (sink_type, vulnerability_type),
+            _ => return,
+        };
+
+        for (index, argument) in call.arguments.iter().enumerate() {
+            if !model.is_sink_port(TaintPort::Argument(index)) {
+                continue;
+            }
+            if let Some(source) = self.expression_taint(argument, sources, vulnerability_type, call.node_id) {
+                let sink = Sink::new(
+                    call.node_id,
+                    sink_type.clone(),
+                    vulnerability_type.clone(),
+                    format!("{} (model)", model.function),
+                )
+                .with_confidence(model.confidence);
+                let confidence = source.confidence * model.confidence;
+                flows.push(
+                    TaintFlow::new(source.clone(), sink, vec![source.id, call.node_id], confidence, vulnerability_type.clone())
+                        .with_flow_type(FlowType::Interprocedural),
+                );
+            }
+        }
+    }
+
+    /// Source whose taint `expr` carries for `vulnerability_type`, looking through
+    /// nested calls to modeled functions; `node_id` identifies taint introduced by a
+    /// modeled source inside `expr`
+    fn expression_taint(&self, expr: &str, sources: &[Source], vulnerability_type: &str, node_id: NodeId) -> Option<Source> {
+        if let Some((callee, arguments)) = split_call(expr) {
+            if let Some(model) = self.models.lookup(callee) {
+                return match &model.kind {
+                    ModelKind::Source(source_type) => Some(
+                        Source::new(node_id, source_type.clone(), format!("{} (model)", model.function))
+                            .with_confidence(model.confidence),
+                    ),
+                    ModelKind::Sanitizer { .. } if model.sanitizes(vulnerability_type) => None,
+                    ModelKind::Propagator { from, to: TaintPort::Return } => from.iter().find_map(|port| {
+                        let input = match *port {
+                            TaintPort::Argument(index) => arguments.get(index).copied()?,
+                            TaintPort::Receiver => callee.rsplit_once('.')?.0,
+                            TaintPort::Return => return None,
+                        };
+                        self.expression_taint(input, sources, vulnerability_type, node_id)
+                    }),
+                    // Arguments of other calls keep their taint
+                    _ => arguments
+                        .iter()
+                        .find_map(|argument| self.expression_taint(argument, sources, vulnerability_type, node_id)),
+                };
+            }
+        }
+
+        sources.iter().find(|source| self.matches_expression(expr, source)).cloned()
+    }
+
     /// Check if an expression matches a source
     fn matches_expression(&self, expr: &str, source: &Source) -> bool {
         // Simple pattern matching - can be enhanced
@@ -176,6 +251,50 @@ impl InterproceduralTaintTracker {
     }
 }
 
+/// Split `callee(arg, ...)` into the callee and its top-level arguments
+fn split_call(expr: &str) -> Option<(&str, Vec<&str>)> {
+    let expr = expr.trim();
+    let open = expr.find('(')?;
+    let callee = expr[..open].trim();
+    if callee.is_empty() || !callee.chars().all(|c| c.is_alphanumeric() || matches!(c, '_' | '$' | '.')) {
+        return None;
+    }
+    let inner = expr[open + 1..].strip_suffix(')')?;
+
+    let mut arguments = Vec::new();
+    let mut depth = 0i32;
+    let mut quote: Option<char> = None;
+    let mut start = 0;
+    for (i, c) in inner.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'' | '`') => quote = Some(c),
+            (None, '(' | '[' | '{') => depth += 1,
+            (None, ')' | ']' | '}') => {
+                depth -= 1;
+                // `f(a)(b)` and `f(a) + g(b)` are not a single call
+                if depth < 0 {
+                    return None;
+                }
+            }
+            (None, ',') if depth == 0 => {
+                arguments.push(inner[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if depth != 0 {
+        return None;
+    }
+    let last = inner[start..].trim();
+    if !last.is_empty() || !arguments.is_empty() {
+        arguments.push(last);
+    }
+    Some((callee, arguments))
+}
+
 /// Symbol propagation for tracking variable definitions and uses
 pub struct SymbolPropagator {
     /// Symbol definitions: name -> definition location
@@ -273,6 +392,59 @@ mod tests {
         assert!(tracker.exit_taints.is_empty());
     }
 
+    fn signature(name: &str, param_count: usize) -> FunctionSignature {
+        FunctionSignature {
+            name: name.to_string(),
+            param_count,
+            language: "javascript".to_string(),
+        }
+    }
+
+    fn models() -> ModelSet {
+        ModelSet::from_str(
+            "models:\n  - function: lodash.escape\n    sanitizer: html_encoding\n  - function: knex.raw\n    sink: sql_execution\n    args: [0]\n  - function: res.send\n    sink: html_output\n  - function: axios.get\n    propagate: { from: arg0 }\n",
+            "models.yaml",
+        )
+        .unwrap()
+    }
+
+    fn trace(arguments: &[(&str, &str)]) -> Vec<TaintFlow> {
+        let mut call_graph = CallGraph::new();
+        let main = call_graph.add_function(signature("handler", 2), vec!["req".to_string(), "res".to_string()], None, 0);
+        for (i, (callee, argument)) in arguments.iter().enumerate() {
+            call_graph.add_call(main, signature(callee, 1), vec![argument.to_string()], i + 1);
+        }
+
+        let sources = vec![Source::new(0, SourceType::UserInput, "req.query".to_string())];
+        let mut tracker = InterproceduralTaintTracker::new(call_graph).with_models(models());
+        tracker
+            .trace_taint_through_calls(&DataFlowGraph::new(), &sources, &[], &[], main)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_modeled_library_calls() {
+        let flows = trace(&[("knex.raw", "axios.get(req.query.url)")]);
+        assert_eq!(flows.len(), 1);
+        assert_eq!(flows[0].vulnerability_type, "SQL_INJECTION");
+        assert_eq!(flows[0].flow_type, FlowType::Interprocedural);
+
+        // Sanitized for XSS, but not for SQL injection
+        assert!(trace(&[("res.send", "lodash.escape(req.query.name)")]).is_empty());
+        assert_eq!(trace(&[("knex.raw", "lodash.escape(req.query.id)")]).len(), 1);
+
+        // Unmodeled and clean calls do not produce flows
+        assert!(trace(&[("console.log", "req.query.id"), ("knex.raw", "'SELECT 1'")]).is_empty());
+    }
+
+    #[test]
+    fn test_split_call() {
+        assert_eq!(split_call("f(a, g(b, c), 'x,y')"), Some(("f", vec!["a", "g(b, c)", "'x,y'"])));
+        assert_eq!(split_call("obj.m()"), Some(("obj.m", vec![])));
+        assert_eq!(split_call("f(a) + g(b)"), None);
+        assert_eq!(split_call("x"), None);
+    }
+
     #[test]
     fn test_symbol_propagator() {
         let mut propagator = SymbolPropagator::new();
@@ -16,6 +16,11 @@ pub mod advanced_taint;
 pub mod symbol_table;
 pub mod constant_propagation;
 pub mod constant_analysis;
+pub mod symbolic_propagation;
+pub mod project_index;
+pub mod models;
+pub mod exceptions;
+pub mod imports;
 
 pub use graph::*;
 pub use sources::*;
@@ -30,7 +35,13 @@ pub use advanced_taint::*;
 pub use symbol_table::*;
 pub use constant_propagation::*;
 pub use constant_analysis::*;
+pub use symbolic_propagation::*;
+pub use project_index::*;
+pub use models::*;
+pub use exceptions::*;
+pub use imports::*;
 
+use astgrep_ast::ScopeTree;
 use astgrep_core::{AstNode, Result};
 use std::collections::{HashMap, HashSet};
 
@@ -41,6 +52,7 @@ pub struct DataFlowAnalyzer {
     sink_detector: SinkDetector,
     sanitizer_detector: SanitizerDetector,
     taint_tracker: TaintTracker,
+    exceptions: ExceptionRegions,
 }
 
 impl DataFlowAnalyzer {
@@ -52,9 +64,16 @@ impl DataFlowAnalyzer {
             sink_detector: SinkDetector::new(),
             sanitizer_detector: SanitizerDetector::new(),
             taint_tracker: TaintTracker::new(),
+            exceptions: ExceptionRegions::default(),
         }
     }
 
+    /// Treat calls to the modeled library functions as sources, sinks and sanitizers
+    pub fn with_models(mut self, models: &ModelSet) -> Self {
+        models.register_with(&mut self.source_detector, &mut self.sink_detector, &mut self.sanitizer_detector);
+        self
+    }
+
     /// Analyze data flow in an AST
     pub fn analyze(&mut self, ast: &dyn AstNode) -> Result<DataFlowAnalysis> {
         // Build the data flow graph
@@ -80,13 +99,41 @@ impl DataFlowAnalyzer {
     /// Build the data flow graph from AST
     fn build_graph(&mut self, ast: &dyn AstNode) -> Result<()> {
         self.graph.clear();
+        self.exceptions = ExceptionRegions::default();
         self.visit_node(ast, None)?;
+        self.add_capture_edges(&ScopeTree::build(ast));
+        self.exceptions.add_edges(&mut self.graph);
         Ok(())
     }
 
+    /// Connect variables captured by closures to their uses and assignments inside the
+    /// closure. Graph node ids are pre-order indices, as in the scope tree, since the
+    /// graph is built by a pre-order walk from an empty graph.
+    fn add_capture_edges(&mut self, scopes: &ScopeTree) {
+        let mut added = HashSet::new();
+        for capture in scopes.captures() {
+            for &use_node in &capture.uses {
+                if added.insert((capture.declaration, use_node)) {
+                    self.graph.add_edge(capture.declaration, use_node, EdgeType::Capture);
+                }
+            }
+            for &write_node in &capture.writes {
+                if added.insert((write_node, capture.declaration)) {
+                    self.graph.add_edge(write_node, capture.declaration, EdgeType::Capture);
+                }
+            }
+        }
+    }
+
     /// Visit a node and add it to the graph
     fn visit_node(&mut self, node: &dyn AstNode, parent_id: Option<NodeId>) -> Result<NodeId> {
-        let node_id = self.graph.add_node(DataFlowNode::from_ast_node(node));
+        let try_part = TryPart::of(node);
+        let mut data_flow_node = DataFlowNode::from_ast_node(node);
+        if try_part == Some(TryPart::Catch) {
+            data_flow_node.attributes.insert(EXCEPTION_HANDLER_ATTRIBUTE.to_string(), "catch".to_string());
+        }
+        let node_id = self.graph.add_node(data_flow_node);
+        self.exceptions.add_raise(node_id, node);
 
         // Connect to parent if exists
         if let Some(parent) = parent_id {
@@ -96,21 +143,50 @@ impl DataFlowAnalyzer {
         // Visit children
         for i in 0..node.child_count() {
             if let Some(child) = node.child(i) {
-                let child_id = self.visit_node(child, Some(node_id))?;
+                let child_id = if try_part == Some(TryPart::Try) {
+                    self.visit_try_child(node_id, child)?
+                } else {
+                    self.visit_node(child, Some(node_id))?
+                };
 
                 // Add data flow edges based on node type
-                self.add_data_flow_edges(node, node_id, child, child_id)?;
+                self.add_data_flow_edges(node, node_id, i, child, child_id)?;
             }
         }
 
         Ok(node_id)
     }
 
+    /// Visit a child of a try statement: its body is protected by the statement's catch
+    /// clauses, the clauses themselves are not
+    fn visit_try_child(&mut self, try_id: NodeId, child: &dyn AstNode) -> Result<NodeId> {
+        match TryPart::of(child) {
+            Some(TryPart::Catch) => {
+                let child_id = self.visit_node(child, Some(try_id))?;
+                let end = self.graph.node_count();
+                self.exceptions.add_catch(try_id, child_id, end, CatchClause::parse(child));
+                Ok(child_id)
+            }
+            Some(TryPart::Finally) => {
+                let child_id = self.visit_node(child, Some(try_id))?;
+                self.exceptions.add_finally(try_id, child_id);
+                Ok(child_id)
+            }
+            _ => {
+                self.exceptions.enter_try_body(try_id);
+                let child_id = self.visit_node(child, Some(try_id))?;
+                self.exceptions.exit_try_body(try_id, child_id);
+                Ok(child_id)
+            }
+        }
+    }
+
     /// Add data flow edges based on node semantics
     fn add_data_flow_edges(
         &mut self,
         parent: &dyn AstNode,
         parent_id: NodeId,
+        child_index: usize,
         child: &dyn AstNode,
         child_id: NodeId,
     ) -> Result<()> {
@@ -128,8 +204,28 @@ impl DataFlowAnalyzer {
                     self.graph.add_edge(child_id, parent_id, EdgeType::DataFlow);
                 }
             }
-            "return_statement" => {
-                // Data flows from expression to return
+            "variable_declaration" => {
+                // The initializer flows into the declared variable, the declared name does not
+                let declared = parent.get_attribute("name").or_else(|| {
+                    (0..parent.child_count())
+                        .filter_map(|i| parent.child(i))
+                        .find(|c| c.node_type() == "identifier")
+                        .and_then(|c| c.text())
+                });
+                if child.node_type() != "identifier" || child.text() != declared {
+                    self.graph.add_edge(child_id, parent_id, EdgeType::DataFlow);
+                }
+            }
+            "return_statement" | "throw_statement" => {
+                // Data flows from expression to return, or into the thrown exception
+                self.graph.add_edge(child_id, parent_id, EdgeType::DataFlow);
+            }
+            "binary_expression" => {
+                // Operands flow into the result, e.g. input concatenated into a message
+                self.graph.add_edge(child_id, parent_id, EdgeType::DataFlow);
+            }
+            "member_expression" if child_index == 0 => {
+                // Members of a value carry its data (`e.message`, `e.getMessage`)
                 self.graph.add_edge(child_id, parent_id, EdgeType::DataFlow);
             }
             _ => {
@@ -186,6 +282,21 @@ impl DataFlowAnalysis {
             .collect()
     }
 
+    /// Catch clauses no exception can reach: nothing in their try body can throw, or
+    /// an earlier clause catches everything it can
+    pub fn unreachable_catches(&self) -> Vec<NodeId> {
+        let mut catches: Vec<NodeId> = self
+            .graph
+            .nodes()
+            .iter()
+            .filter(|(_, node)| node.attributes.contains_key(EXCEPTION_HANDLER_ATTRIBUTE))
+            .map(|(id, _)| *id)
+            .filter(|id| !self.graph.incoming_edges(*id).iter().any(|e| e.edge_type == EdgeType::Exception))
+            .collect();
+        catches.sort_unstable();
+        catches
+    }
+
     /// Get statistics about the analysis
     pub fn statistics(&self) -> DataFlowStatistics {
         DataFlowStatistics {
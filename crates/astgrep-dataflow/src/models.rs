@@ -0,0 +1,423 @@
+//! Taint models for library functions
+//!
+//! A model file summarizes how third-party functions treat tainted data, so taint
+//! analysis can follow flows through libraries without their source code:
+//!
+//! ```yaml
+//! models:
+//!   - function: lodash.escape
+//!     sanitizer: html_encoding
+//!   - function: knex.raw
+//!     sink: sql_execution
+//!     args: [0]
+//!   - function: axios.get
+//!     propagate: { from: [arg0], to: return }
+//!   - function: req.query
+//!     source: user_input
+//! ```
+//!
+//! Kinds are the `as_str` names of [`SourceType`], [`SinkType`] and [`SanitizerType`].
+//! Model files are YAML; JSON files are accepted as well.
+
+use crate::sanitizers::{SanitizerDetector, SanitizerPattern, SanitizerType};
+use crate::sinks::{SinkDetector, SinkPattern, SinkType};
+use crate::sources::{SourceDetector, SourcePattern, SourceType};
+use astgrep_core::{AnalysisError, Result};
+use serde::Deserialize;
+use std::path::Path;
+
+/// Node type the detectors use for function calls
+const CALL_NODE_TYPE: &str = "call_expression";
+
+/// Confidence of a model that does not set one
+const DEFAULT_MODEL_CONFIDENCE: f32 = 0.9;
+
+/// Where taint enters or leaves a modeled call
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaintPort {
+    /// Positional argument, 0-based
+    Argument(usize),
+    /// The object the method is called on
+    Receiver,
+    /// The call's return value
+    Return,
+}
+
+impl TaintPort {
+    /// Parse `arg<N>`, `receiver` or `return`
+    pub fn parse(spec: &str) -> Option<Self> {
+        match spec.trim() {
+            "receiver" | "this" => Some(TaintPort::Receiver),
+            "return" => Some(TaintPort::Return),
+            other => other.strip_prefix("arg")?.parse().ok().map(TaintPort::Argument),
+        }
+    }
+}
+
+/// What a modeled function does with taint
+#[derive(Debug, Clone, PartialEq)]
+pub enum ModelKind {
+    /// The return value is tainted
+    Source(SourceType),
+    /// Tainted data reaching `args` (all arguments when empty) is a vulnerability
+    Sink {
+        sink_type: SinkType,
+        vulnerability_type: String,
+        args: Vec<usize>,
+    },
+    /// The return value is clean for `vulnerability_types`
+    Sanitizer {
+        sanitizer_type: SanitizerType,
+        vulnerability_types: Vec<String>,
+    },
+    /// Taint on any of `from` reaches `to`
+    Propagator { from: Vec<TaintPort>, to: TaintPort },
+}
+
+/// Taint summary of one library function
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionModel {
+    /// Qualified name, e.g. `lodash.escape`
+    pub function: String,
+    pub kind: ModelKind,
+    pub confidence: f32,
+}
+
+impl FunctionModel {
+    /// Create a model with the default confidence
+    pub fn new(function: impl Into<String>, kind: ModelKind) -> Self {
+        Self {
+            function: function.into(),
+            kind,
+            confidence: DEFAULT_MODEL_CONFIDENCE,
+        }
+    }
+
+    /// Set confidence level
+    pub fn with_confidence(mut self, confidence: f32) -> Self {
+        self.confidence = confidence.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Whether a call to `callee` is a call to this function; `escape` and
+    /// `_.escape` do not match `lodash.escape`, `app.lodash.escape` does
+    pub fn matches(&self, callee: &str) -> bool {
+        let callee = callee.trim();
+        callee == self.function
+            || callee
+                .strip_suffix(self.function.as_str())
+                .is_some_and(|prefix| prefix.ends_with('.'))
+    }
+
+    /// Whether taint on `port` of a call is a vulnerability under this model
+    pub fn is_sink_port(&self, port: TaintPort) -> bool {
+        match (&self.kind, port) {
+            (ModelKind::Sink { args, .. }, TaintPort::Argument(index)) => args.is_empty() || args.contains(&index),
+            _ => false,
+        }
+    }
+
+    /// Whether this model cleans taint for `vulnerability_type`
+    pub fn sanitizes(&self, vulnerability_type: &str) -> bool {
+        match &self.kind {
+            ModelKind::Sanitizer { vulnerability_types, .. } => vulnerability_types.iter().any(|v| v == vulnerability_type),
+            _ => false,
+        }
+    }
+}
+
+/// Collection of function models, consulted in reverse order so later files override
+/// earlier ones
+#[derive(Debug, Clone, Default)]
+pub struct ModelSet {
+    models: Vec<FunctionModel>,
+}
+
+impl ModelSet {
+    /// Create an empty model set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse a model file's content; `origin` names it in errors
+    pub fn from_str(content: &str, origin: &str) -> Result<Self> {
+        let file: ModelFile = serde_yaml::from_str(content)
+            .map_err(|e| AnalysisError::config_error(format!("invalid model file {}: {}", origin, e)))?;
+        let models = file
+            .models
+            .into_iter()
+            .map(|raw| raw.into_model().map_err(|e| AnalysisError::config_error(format!("{}: {}", origin, e))))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { models })
+    }
+
+    /// Load a model file, or every `.yaml`, `.yml` and `.json` file in a directory
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.is_dir() {
+            let content = std::fs::read_to_string(path)?;
+            return Self::from_str(&content, &path.display().to_string());
+        }
+
+        let mut files: Vec<_> = std::fs::read_dir(path)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|p| matches!(p.extension().and_then(|e| e.to_str()), Some("yaml" | "yml" | "json")))
+            .collect();
+        files.sort();
+
+        let mut set = Self::new();
+        for file in files {
+            set.extend(Self::load(&file)?);
+        }
+        Ok(set)
+    }
+
+    /// Add a model
+    pub fn add(&mut self, model: FunctionModel) {
+        self.models.push(model);
+    }
+
+    /// Add all models of `other`, overriding models of this set for the same function
+    pub fn extend(&mut self, other: ModelSet) {
+        self.models.extend(other.models);
+    }
+
+    /// Model for a call to `callee`, if any
+    pub fn lookup(&self, callee: &str) -> Option<&FunctionModel> {
+        self.models.iter().rev().find(|model| model.matches(callee))
+    }
+
+    /// All models
+    pub fn models(&self) -> &[FunctionModel] {
+        &self.models
+    }
+
+    pub fn len(&self) -> usize {
+        self.models.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.models.is_empty()
+    }
+
+    /// Register the source, sink and sanitizer models as detector patterns for call nodes;
+    /// propagators need no pattern since call nodes already carry their arguments' taint
+    pub fn register_with(
+        &self,
+        sources: &mut SourceDetector,
+        sinks: &mut SinkDetector,
+        sanitizers: &mut SanitizerDetector,
+    ) {
+        for model in &self.models {
+            let description = format!("{} (model)", model.function);
+            match &model.kind {
+                ModelKind::Source(source_type) => sources.add_pattern(
+                    CALL_NODE_TYPE.to_string(),
+                    SourcePattern::new(model.function.clone(), source_type.clone(), description, model.confidence),
+                ),
+                ModelKind::Sink { sink_type, vulnerability_type, .. } => sinks.add_pattern(
+                    CALL_NODE_TYPE.to_string(),
+                    SinkPattern::new(
+                        model.function.clone(),
+                        sink_type.clone(),
+                        vulnerability_type.clone(),
+                        description,
+                        model.confidence,
+                    ),
+                ),
+                ModelKind::Sanitizer { sanitizer_type, vulnerability_types } => sanitizers.add_pattern(
+                    CALL_NODE_TYPE.to_string(),
+                    SanitizerPattern::new(
+                        model.function.clone(),
+                        sanitizer_type.clone(),
+                        description,
+                        model.confidence,
+                        vulnerability_types.clone(),
+                    ),
+                ),
+                ModelKind::Propagator { .. } => {}
+            }
+        }
+    }
+}
+
+/// On-disk layout of a model file
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ModelFile {
+    #[serde(default)]
+    models: Vec<RawModel>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawModel {
+    function: String,
+    source: Option<String>,
+    sink: Option<String>,
+    sanitizer: Option<String>,
+    propagate: Option<RawPropagate>,
+    /// Sink arguments; all arguments when omitted
+    #[serde(default)]
+    args: Vec<usize>,
+    /// Vulnerability type of a sink, or those a sanitizer protects against
+    #[serde(default)]
+    vulnerability: Vec<String>,
+    confidence: Option<f32>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawPropagate {
+    #[serde(deserialize_with = "one_or_many")]
+    from: Vec<String>,
+    #[serde(default = "default_propagate_to")]
+    to: String,
+}
+
+fn default_propagate_to() -> String {
+    "return".to_string()
+}
+
+fn one_or_many<'de, D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(value) => vec![value],
+        OneOrMany::Many(values) => values,
+    })
+}
+
+impl RawModel {
+    fn into_model(self) -> std::result::Result<FunctionModel, String> {
+        let function = self.function.trim().to_string();
+        if function.is_empty() {
+            return Err("model has an empty function name".to_string());
+        }
+
+        let declared = [self.source.is_some(), self.sink.is_some(), self.sanitizer.is_some(), self.propagate.is_some()];
+        if declared.iter().filter(|&&d| d).count() != 1 {
+            return Err(format!("model for {} must set exactly one of source, sink, sanitizer or propagate", function));
+        }
+
+        let kind = if let Some(name) = self.source {
+            ModelKind::Source(SourceType::parse(&name).ok_or_else(|| format!("unknown source type '{}' for {}", name, function))?)
+        } else if let Some(name) = self.sink {
+            let sink_type = SinkType::parse(&name).ok_or_else(|| format!("unknown sink type '{}' for {}", name, function))?;
+            let vulnerability_type = match self.vulnerability.as_slice() {
+                [] => sink_type.vulnerability_type().to_string(),
+                [one] => one.clone(),
+                _ => return Err(format!("sink model for {} takes a single vulnerability", function)),
+            };
+            ModelKind::Sink { sink_type, vulnerability_type, args: self.args }
+        } else if let Some(name) = self.sanitizer {
+            let sanitizer_type = SanitizerType::parse(&name).ok_or_else(|| format!("unknown sanitizer type '{}' for {}", name, function))?;
+            let vulnerability_types = if self.vulnerability.is_empty() {
+                sanitizer_type.default_protections()
+            } else {
+                self.vulnerability
+            };
+            ModelKind::Sanitizer { sanitizer_type, vulnerability_types }
+        } else {
+            let propagate = self.propagate.expect("checked above");
+            let port = |spec: &str| TaintPort::parse(spec).ok_or_else(|| format!("invalid taint port '{}' for {} (expected argN, receiver or return)", spec, function));
+            let from = propagate.from.iter().map(|s| port(s)).collect::<std::result::Result<Vec<_>, _>>()?;
+            if from.is_empty() || from.contains(&TaintPort::Return) {
+                return Err(format!("propagator for {} needs argument or receiver ports in 'from'", function));
+            }
+            ModelKind::Propagator { from, to: port(&propagate.to)? }
+        };
+
+        let model = FunctionModel::new(function, kind);
+        Ok(match self.confidence {
+            Some(confidence) => model.with_confidence(confidence),
+            None => model,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MODELS: &str = r#"
+models:
+  - function: lodash.escape
+    sanitizer: html_encoding
+  - function: knex.raw
+    sink: sql_execution
+    args: [0]
+  - function: axios.get
+    propagate: { from: arg0, to: return }
+  - function: req.query
+    source: user_input
+    confidence: 0.8
+"#;
+
+    #[test]
+    fn test_parse_model_file() {
+        let set = ModelSet::from_str(MODELS, "models.yaml").unwrap();
+        assert_eq!(set.len(), 4);
+
+        let escape = set.lookup("lodash.escape").unwrap();
+        assert!(escape.sanitizes("XSS"));
+        assert!(!escape.sanitizes("SQL_INJECTION"));
+
+        let raw = set.lookup("db.knex.raw").unwrap();
+        assert!(raw.is_sink_port(TaintPort::Argument(0)));
+        assert!(!raw.is_sink_port(TaintPort::Argument(1)));
+        assert!(matches!(&raw.kind, ModelKind::Sink { vulnerability_type, .. } if vulnerability_type == "SQL_INJECTION"));
+
+        assert_eq!(
+            set.lookup("axios.get").unwrap().kind,
+            ModelKind::Propagator { from: vec![TaintPort::Argument(0)], to: TaintPort::Return }
+        );
+        assert_eq!(set.lookup("req.query").unwrap().confidence, 0.8);
+        assert!(set.lookup("escape").is_none());
+        assert!(set.lookup("myknex.raw").is_none());
+    }
+
+    #[test]
+    fn test_invalid_models_are_rejected() {
+        assert!(ModelSet::from_str("models:\n  - function: f\n    sink: nowhere\n", "m").is_err());
+        assert!(ModelSet::from_str("models:\n  - function: f\n    sink: sql_execution\n    source: user_input\n", "m").is_err());
+        assert!(ModelSet::from_str("models:\n  - function: f\n    propagate: { from: return }\n", "m").is_err());
+        assert!(ModelSet::from_str("models:\n  - function: f\n    sanitizer: html_encoding\n    unknown: 1\n", "m").is_err());
+    }
+
+    #[test]
+    fn test_later_files_override() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.yaml"), "models:\n  - function: lib.f\n    sink: sql_execution\n").unwrap();
+        std::fs::write(
+            dir.path().join("b.json"),
+            r#"{"models": [{"function": "lib.f", "sanitizer": "sql_parameter_binding"}]}"#,
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "ignored").unwrap();
+
+        let set = ModelSet::load(dir.path()).unwrap();
+        assert_eq!(set.len(), 2);
+        assert!(set.lookup("lib.f").unwrap().sanitizes("SQL_INJECTION"));
+    }
+
+    #[test]
+    fn test_register_with_detectors() {
+        use crate::graph::{DataFlowGraph, DataFlowNode};
+
+        let set = ModelSet::from_str(MODELS, "models.yaml").unwrap();
+        let mut sources = SourceDetector::new();
+        let mut sinks = SinkDetector::new();
+        let mut sanitizers = SanitizerDetector::new();
+        set.register_with(&mut sources, &mut sinks, &mut sanitizers);
+
+        let mut graph = DataFlowGraph::new();
+        graph.add_node(DataFlowNode::new("call_expression".to_string()).with_text("knex.raw".to_string()));
+        let detected = sinks.detect_sinks(&graph).unwrap();
+        assert_eq!(detected.len(), 1);
+        assert_eq!(detected[0].sink_type, SinkType::SqlExecution);
+    }
+}
@@ -0,0 +1,496 @@
+//! Persistent project index for cross-file analysis
+//!
+//! The project index records the symbols, imports and call edges of every analyzed
+//! file. Entries are keyed by a content hash so that only changed files are re-indexed
+//! between runs. The index is stored on disk under `.astgrep/index/` in the project
+//! root and can be turned into a [`CallGraph`] for inter-procedural analysis.
+
+use crate::call_graph::{CallGraph, FunctionSignature};
+use astgrep_core::{AnalysisError, AstNode, Language, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+
+/// Directory (relative to the project root) holding the persisted index
+pub const INDEX_DIR: &str = ".astgrep/index";
+
+/// File name of the serialized index inside [`INDEX_DIR`]
+pub const INDEX_FILE: &str = "index.json";
+
+/// On-disk format version; indexes with a different version are discarded
+pub const INDEX_FORMAT_VERSION: u32 = 2;
+
+/// Kind of an indexed symbol
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IndexedSymbolKind {
+    Function,
+    Class,
+}
+
+/// A symbol defined in a file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexedSymbol {
+    pub name: String,
+    pub kind: IndexedSymbolKind,
+    pub line: usize,
+    pub param_count: usize,
+}
+
+/// A call site recorded in a file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexedCall {
+    /// Enclosing function, if the call is not at module level
+    pub caller: Option<String>,
+    pub callee: String,
+    pub arg_count: usize,
+    pub line: usize,
+}
+
+/// Index entry for a single file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileIndex {
+    pub content_hash: String,
+    pub language: Language,
+    pub symbols: Vec<IndexedSymbol>,
+    pub imports: Vec<String>,
+    pub calls: Vec<IndexedCall>,
+}
+
+/// Project-wide symbol, import and call-graph index
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectIndex {
+    pub version: u32,
+    files: BTreeMap<String, FileIndex>,
+}
+
+impl ProjectIndex {
+    /// Create an empty index
+    pub fn new() -> Self {
+        Self {
+            version: INDEX_FORMAT_VERSION,
+            files: BTreeMap::new(),
+        }
+    }
+
+    /// Path of the index file for a project root
+    pub fn index_path(root: &Path) -> PathBuf {
+        root.join(INDEX_DIR).join(INDEX_FILE)
+    }
+
+    /// Load the index for a project root.
+    ///
+    /// A missing index or one written by an incompatible format version yields an
+    /// empty index, so callers can always update and save the result.
+    pub fn load(root: &Path) -> Result<Self> {
+        let path = Self::index_path(root);
+        if !path.exists() {
+            return Ok(Self::new());
+        }
+
+        let content = std::fs::read_to_string(&path)?;
+        let index: ProjectIndex = serde_json::from_str(&content).map_err(|e| {
+            AnalysisError::parse_error(format!("Invalid project index {}: {}", path.display(), e))
+        })?;
+
+        if index.version != INDEX_FORMAT_VERSION {
+            tracing::info!(
+                "Discarding project index with format version {} (expected {})",
+                index.version,
+                INDEX_FORMAT_VERSION
+            );
+            return Ok(Self::new());
+        }
+
+        Ok(index)
+    }
+
+    /// Persist the index under the project root
+    pub fn save(&self, root: &Path) -> Result<()> {
+        let path = Self::index_path(root);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let content = serde_json::to_string(self).map_err(|e| {
+            AnalysisError::internal_error(format!("Failed to serialize project index: {}", e))
+        })?;
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+
+    /// Compute the content hash used to detect changed files: the hex SHA-256 of the
+    /// source, which unlike `DefaultHasher` stays the same across Rust releases
+    pub fn content_hash(source: &str) -> String {
+        use sha2::{Digest, Sha256};
+
+        Sha256::digest(source.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    /// Check whether the stored entry for a file matches the given content hash
+    pub fn is_up_to_date(&self, file: &str, content_hash: &str) -> bool {
+        self.files
+            .get(file)
+            .is_some_and(|entry| entry.content_hash == content_hash)
+    }
+
+    /// Index a parsed file, replacing any previous entry
+    pub fn index_file(&mut self, file: &str, language: Language, source: &str, ast: &dyn AstNode) {
+        let mut entry = FileIndex {
+            content_hash: Self::content_hash(source),
+            language,
+            symbols: Vec::new(),
+            imports: Vec::new(),
+            calls: Vec::new(),
+        };
+        collect_entries(ast, None, &mut entry);
+        self.files.insert(file.to_string(), entry);
+    }
+
+    /// Remove a file from the index
+    pub fn remove_file(&mut self, file: &str) -> Option<FileIndex> {
+        self.files.remove(file)
+    }
+
+    /// Drop entries for files that are no longer part of the project.
+    /// Returns the number of removed entries.
+    pub fn retain_files(&mut self, existing: &HashSet<String>) -> usize {
+        let before = self.files.len();
+        self.files.retain(|file, _| existing.contains(file));
+        before - self.files.len()
+    }
+
+    /// Get the entry for a file
+    pub fn file(&self, file: &str) -> Option<&FileIndex> {
+        self.files.get(file)
+    }
+
+    /// Iterate over all indexed files
+    pub fn files(&self) -> impl Iterator<Item = (&String, &FileIndex)> {
+        self.files.iter()
+    }
+
+    /// Number of indexed files
+    pub fn file_count(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Add the files of `other`, the index of the project at `root`, under keys prefixed
+    /// with `root`, so the indexes of several projects can be consulted as one
+    pub fn merge(&mut self, root: &Path, other: ProjectIndex) {
+        for (file, entry) in other.files {
+            self.files.insert(root.join(file).to_string_lossy().into_owned(), entry);
+        }
+    }
+
+    /// Find all definitions of a symbol across the project
+    pub fn definitions_of(&self, name: &str) -> Vec<(&str, &IndexedSymbol)> {
+        self.files
+            .iter()
+            .flat_map(|(file, entry)| {
+                entry
+                    .symbols
+                    .iter()
+                    .filter(move |s| s.name == name)
+                    .map(move |s| (file.as_str(), s))
+            })
+            .collect()
+    }
+
+    /// Find all call sites of a function across the project
+    pub fn callers_of(&self, name: &str) -> Vec<(&str, &IndexedCall)> {
+        self.files
+            .iter()
+            .flat_map(|(file, entry)| {
+                entry
+                    .calls
+                    .iter()
+                    .filter(move |c| c.callee == name)
+                    .map(move |c| (file.as_str(), c))
+            })
+            .collect()
+    }
+
+    /// Find files whose imports mention the given module
+    pub fn importers_of(&self, module: &str) -> Vec<&str> {
+        self.files
+            .iter()
+            .filter(|(_, entry)| entry.imports.iter().any(|i| i.contains(module)))
+            .map(|(file, _)| file.as_str())
+            .collect()
+    }
+
+    /// Names of all functions transitively reachable from `entry` via call edges
+    pub fn reachable_from(&self, entry: &str) -> HashSet<String> {
+        let mut edges: HashMap<&str, Vec<&str>> = HashMap::new();
+        for file in self.files.values() {
+            for call in &file.calls {
+                if let Some(ref caller) = call.caller {
+                    edges.entry(caller.as_str()).or_default().push(call.callee.as_str());
+                }
+            }
+        }
+
+        let mut reachable = HashSet::new();
+        let mut queue = VecDeque::new();
+        reachable.insert(entry.to_string());
+        queue.push_back(entry);
+
+        while let Some(current) = queue.pop_front() {
+            if let Some(callees) = edges.get(current) {
+                for callee in callees {
+                    if reachable.insert(callee.to_string()) {
+                        queue.push_back(callee);
+                    }
+                }
+            }
+        }
+
+        reachable
+    }
+
+    /// Build a call graph from the indexed functions and call sites
+    pub fn build_call_graph(&self) -> CallGraph {
+        let mut graph = CallGraph::new();
+        let mut ids = HashMap::new();
+        let mut node_id = 0;
+
+        for entry in self.files.values() {
+            for symbol in entry.symbols.iter().filter(|s| s.kind == IndexedSymbolKind::Function) {
+                let signature = FunctionSignature {
+                    name: symbol.name.clone(),
+                    param_count: symbol.param_count,
+                    language: entry.language.as_str().to_string(),
+                };
+                let id = graph.add_function(signature, Vec::new(), None, node_id);
+                ids.insert((entry.language, symbol.name.clone()), id);
+                node_id += 1;
+            }
+        }
+
+        for entry in self.files.values() {
+            for call in &entry.calls {
+                let caller_id = match call.caller.as_ref().and_then(|c| ids.get(&(entry.language, c.clone()))) {
+                    Some(id) => *id,
+                    None => continue,
+                };
+                let signature = FunctionSignature {
+                    name: call.callee.clone(),
+                    param_count: call.arg_count,
+                    language: entry.language.as_str().to_string(),
+                };
+                graph.add_call(caller_id, signature, Vec::new(), node_id);
+                node_id += 1;
+            }
+        }
+
+        graph
+    }
+}
+
+impl Default for ProjectIndex {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Name under which the index records `node` as a function, if it is one
+pub fn indexed_function_name(node: &dyn AstNode) -> Option<String> {
+    is_function_node(node).then(|| first_identifier(node)).flatten()
+}
+
+fn is_function_node(node: &dyn AstNode) -> bool {
+    matches!(node.node_type(), "function_declaration" | "method_declaration")
+        || matches!(node.get_attribute("ts_kind"), Some("method_declaration"))
+}
+
+fn first_identifier(node: &dyn AstNode) -> Option<String> {
+    (0..node.child_count())
+        .filter_map(|i| node.child(i))
+        .find(|c| c.node_type() == "identifier")
+        .and_then(|c| c.text())
+        .map(|t| t.to_string())
+}
+
+fn parameter_count(node: &dyn AstNode) -> usize {
+    (0..node.child_count())
+        .filter_map(|i| node.child(i))
+        .find(|c| matches!(c.get_attribute("ts_kind"), Some("formal_parameters" | "parameters")))
+        .map_or(0, |params| params.child_count())
+}
+
+fn argument_count(node: &dyn AstNode) -> usize {
+    (0..node.child_count())
+        .filter_map(|i| node.child(i))
+        .find(|c| matches!(c.get_attribute("ts_kind"), Some("arguments" | "argument_list")))
+        .map_or(0, |args| args.child_count())
+}
+
+/// Extract the called name from a call expression, e.g. `obj.run(x)` -> `run`
+fn callee_name(node: &dyn AstNode) -> Option<String> {
+    indexed_callee(node.text()?)
+}
+
+/// Name under which the index records calls of the call expression `text`, e.g. `run`
+/// for `obj.run(x)`
+pub fn indexed_callee(text: &str) -> Option<String> {
+    let head = text.split('(').next()?.trim();
+    let name = head
+        .rsplit(|c: char| c == '.' || c == ':' || c == '>' || c.is_whitespace())
+        .next()?
+        .trim();
+    if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '$') {
+        return None;
+    }
+    Some(name.to_string())
+}
+
+fn line_of(node: &dyn AstNode) -> usize {
+    node.location().map_or(0, |(line, _, _, _)| line)
+}
+
+fn collect_entries(node: &dyn AstNode, current_fn: Option<&str>, entry: &mut FileIndex) {
+    let mut enclosing = current_fn.map(|s| s.to_string());
+
+    if is_function_node(node) {
+        if let Some(name) = first_identifier(node) {
+            entry.symbols.push(IndexedSymbol {
+                name: name.clone(),
+                kind: IndexedSymbolKind::Function,
+                line: line_of(node),
+                param_count: parameter_count(node),
+            });
+            enclosing = Some(name);
+        }
+    } else {
+        match node.node_type() {
+            "class_declaration" => {
+                if let Some(name) = first_identifier(node) {
+                    entry.symbols.push(IndexedSymbol {
+                        name,
+                        kind: IndexedSymbolKind::Class,
+                        line: line_of(node),
+                        param_count: 0,
+                    });
+                }
+            }
+            "import_declaration" => {
+                if let Some(text) = node.text() {
+                    entry.imports.push(text.trim().trim_end_matches(';').to_string());
+                }
+            }
+            "call_expression" => {
+                if let Some(callee) = callee_name(node) {
+                    entry.calls.push(IndexedCall {
+                        caller: current_fn.map(|s| s.to_string()),
+                        callee,
+                        arg_count: argument_count(node),
+                        line: line_of(node),
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    for i in 0..node.child_count() {
+        if let Some(child) = node.child(i) {
+            collect_entries(child, enclosing.as_deref(), entry);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use astgrep_ast::{NodeType, UniversalNode};
+
+    fn identifier(name: &str) -> UniversalNode {
+        UniversalNode::new(NodeType::Identifier).with_text(name.to_string())
+    }
+
+    fn call(text: &str, line: usize) -> UniversalNode {
+        UniversalNode::new(NodeType::CallExpression)
+            .with_text(text.to_string())
+            .with_location(line, 1, line, 10)
+    }
+
+    fn function(name: &str, line: usize, body: Vec<UniversalNode>) -> UniversalNode {
+        UniversalNode::new(NodeType::FunctionDeclaration)
+            .with_location(line, 1, line + 2, 1)
+            .add_child(identifier(name))
+            .add_children(body)
+    }
+
+    fn sample_ast() -> UniversalNode {
+        UniversalNode::new(NodeType::Program)
+            .add_child(UniversalNode::new(NodeType::ImportDeclaration).with_text("import os".to_string()))
+            .add_child(function("main", 2, vec![call("helper()", 3)]))
+            .add_child(function("helper", 5, vec![call("os.system(cmd)", 6)]))
+    }
+
+    #[test]
+    fn test_index_file_collects_symbols_imports_and_calls() {
+        let mut index = ProjectIndex::new();
+        index.index_file("app.py", Language::Python, "source", &sample_ast());
+
+        let entry = index.file("app.py").unwrap();
+        assert_eq!(entry.imports, vec!["import os".to_string()]);
+        assert_eq!(entry.symbols.len(), 2);
+        assert_eq!(entry.calls.len(), 2);
+        assert_eq!(entry.calls[0].caller.as_deref(), Some("main"));
+        assert_eq!(entry.calls[1].callee, "system");
+        assert_eq!(index.importers_of("os"), vec!["app.py"]);
+    }
+
+    #[test]
+    fn test_reachability_and_call_graph() {
+        let mut index = ProjectIndex::new();
+        index.index_file("app.py", Language::Python, "source", &sample_ast());
+
+        let reachable = index.reachable_from("main");
+        assert!(reachable.contains("helper"));
+        assert!(reachable.contains("system"));
+        assert_eq!(index.callers_of("helper").len(), 1);
+
+        let graph = index.build_call_graph();
+        assert_eq!(graph.functions().len(), 2);
+    }
+
+    #[test]
+    fn test_content_hash_detects_changes() {
+        let mut index = ProjectIndex::new();
+        index.index_file("app.py", Language::Python, "v1", &sample_ast());
+
+        assert!(index.is_up_to_date("app.py", &ProjectIndex::content_hash("v1")));
+        assert!(!index.is_up_to_date("app.py", &ProjectIndex::content_hash("v2")));
+        assert!(!index.is_up_to_date("other.py", &ProjectIndex::content_hash("v1")));
+
+        // A fixed digest, so persisted indexes stay valid across toolchains
+        assert_eq!(ProjectIndex::content_hash("v1"), "3bfc269594ef649228e9a74bab00f042efc91d5acc6fbee31a382e80d42388fe");
+    }
+
+    #[test]
+    fn test_save_and_load_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut index = ProjectIndex::new();
+        index.index_file("app.py", Language::Python, "source", &sample_ast());
+        index.save(dir.path()).unwrap();
+
+        let loaded = ProjectIndex::load(dir.path()).unwrap();
+        assert_eq!(loaded.file_count(), 1);
+        assert_eq!(loaded.file("app.py"), index.file("app.py"));
+
+        let mut existing = HashSet::new();
+        existing.insert("other.py".to_string());
+        let mut loaded = loaded;
+        assert_eq!(loaded.retain_files(&existing), 1);
+        assert_eq!(loaded.file_count(), 0);
+    }
+
+    #[test]
+    fn test_load_missing_index_is_empty() {
+        let dir = tempfile::tempdir().unwrap();
+        let index = ProjectIndex::load(dir.path()).unwrap();
+        assert_eq!(index.file_count(), 0);
+    }
+}
@@ -141,6 +141,25 @@ impl SanitizerType {
         }
     }
 
+    /// Parse the string representation produced by [`SanitizerType::as_str`]
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "input_validation" => Some(SanitizerType::InputValidation),
+            "output_encoding" => Some(SanitizerType::OutputEncoding),
+            "sql_parameter_binding" => Some(SanitizerType::SqlParameterBinding),
+            "html_encoding" => Some(SanitizerType::HtmlEncoding),
+            "url_encoding" => Some(SanitizerType::UrlEncoding),
+            "javascript_encoding" => Some(SanitizerType::JavaScriptEncoding),
+            "path_normalization" => Some(SanitizerType::PathNormalization),
+            "regex_validation" => Some(SanitizerType::RegexValidation),
+            "whitelist_filtering" => Some(SanitizerType::WhitelistFiltering),
+            "blacklist_filtering" => Some(SanitizerType::BlacklistFiltering),
+            "length_validation" => Some(SanitizerType::LengthValidation),
+            "type_validation" => Some(SanitizerType::TypeValidation),
+            _ => None,
+        }
+    }
+
     /// Convert to string representation
     pub fn as_str(&self) -> &'static str {
         match self {
@@ -89,6 +89,23 @@ impl SinkType {
         }
     }
 
+    /// Parse the string representation produced by [`SinkType::as_str`]
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "sql_execution" => Some(SinkType::SqlExecution),
+            "command_execution" => Some(SinkType::CommandExecution),
+            "file_operation" => Some(SinkType::FileOperation),
+            "network_operation" => Some(SinkType::NetworkOperation),
+            "html_output" => Some(SinkType::HtmlOutput),
+            "javascript_evaluation" => Some(SinkType::JavaScriptEvaluation),
+            "log_output" => Some(SinkType::LogOutput),
+            "database_operation" => Some(SinkType::DatabaseOperation),
+            "xml_operation" => Some(SinkType::XmlOperation),
+            "ldap_operation" => Some(SinkType::LdapOperation),
+            _ => None,
+        }
+    }
+
     /// Convert to string representation
     pub fn as_str(&self) -> &'static str {
         match self {
@@ -87,6 +87,23 @@ impl SourceType {
         }
     }
 
+    /// Parse the string representation produced by [`SourceType::as_str`]
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "user_input" => Some(SourceType::UserInput),
+            "file_input" => Some(SourceType::FileInput),
+            "network_input" => Some(SourceType::NetworkInput),
+            "database_input" => Some(SourceType::DatabaseInput),
+            "environment_input" => Some(SourceType::EnvironmentInput),
+            "command_line_input" => Some(SourceType::CommandLineInput),
+            "external_api_input" => Some(SourceType::ExternalApiInput),
+            "cookie_input" => Some(SourceType::CookieInput),
+            "header_input" => Some(SourceType::HeaderInput),
+            "url_parameter_input" => Some(SourceType::UrlParameterInput),
+            _ => None,
+        }
+    }
+
     /// Convert to string representation
     pub fn as_str(&self) -> &'static str {
         match self {
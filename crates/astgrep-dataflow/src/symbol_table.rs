@@ -7,7 +7,9 @@
 //! - Symbol resolution
 
 use std::collections::HashMap;
-use astgrep_core::{Result, AnalysisError};
+use astgrep_ast::{ScopeKind, ScopeTree};
+use astgrep_core::{Language, Result, AnalysisError};
+use regex::Regex;
 
 /// Represents a symbol in the program
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -51,6 +53,32 @@ impl TypeInfo {
         }
     }
 
+    /// Check whether this type names `expected`. Generic arguments are ignored and a
+    /// qualified name matches its simple name (`java.lang.Runtime` matches `Runtime`
+    /// and vice versa); two qualified names must be equal.
+    pub fn matches_type_name(&self, expected: &str) -> bool {
+        fn simple_name(name: &str) -> &str {
+            let name = name.split('<').next().unwrap_or(name).trim();
+            name.rsplit(['.', ':', '\\']).next().unwrap_or(name)
+        }
+        match self {
+            TypeInfo::Primitive(name) | TypeInfo::Object(name) => {
+                let name = name.split('<').next().unwrap_or(name).trim();
+                let expected = expected.split('<').next().unwrap_or(expected).trim();
+                // Source usually refers to imported types by their simple name, so a
+                // qualified name only has to agree with an unqualified one on that
+                name == expected
+                    || ((!expected.contains('.') || !name.contains('.'))
+                        && simple_name(name) == simple_name(expected))
+            }
+            TypeInfo::Array(inner) => expected
+                .strip_suffix("[]")
+                .is_some_and(|e| inner.matches_type_name(e)),
+            TypeInfo::Union(types) => types.iter().any(|t| t.matches_type_name(expected)),
+            TypeInfo::Function { .. } | TypeInfo::Unknown => false,
+        }
+    }
+
     /// Get a string representation
     pub fn to_string(&self) -> String {
         match self {
@@ -91,6 +119,8 @@ pub struct Scope {
 pub enum ScopeType {
     Global,
     Function(String),
+    /// Lambda, arrow function or anonymous function
+    Closure,
     Block,
     Class(String),
     Loop,
@@ -129,6 +159,8 @@ pub struct SymbolTable {
     current_scope_id: usize,
     scope_counter: usize,
     symbol_types: HashMap<String, TypeInfo>,
+    /// Symbols of enclosing functions captured by each closure scope
+    captures: HashMap<usize, Vec<Symbol>>,
 }
 
 impl SymbolTable {
@@ -139,6 +171,7 @@ impl SymbolTable {
             current_scope_id: 0,
             scope_counter: 1,
             symbol_types: HashMap::new(),
+            captures: HashMap::new(),
         };
 
         // Create global scope
@@ -173,6 +206,15 @@ impl SymbolTable {
         Err(AnalysisError::internal_error("Cannot exit global scope"))
     }
 
+    /// Make an existing scope the current one
+    pub fn set_current_scope(&mut self, scope_id: usize) -> Result<()> {
+        if !self.scopes.contains_key(&scope_id) {
+            return Err(AnalysisError::internal_error(format!("Unknown scope {}", scope_id)));
+        }
+        self.current_scope_id = scope_id;
+        Ok(())
+    }
+
     /// Define a symbol in current scope
     pub fn define_symbol(&mut self, name: String, node_id: usize, type_info: TypeInfo) -> Result<()> {
         let symbol = Symbol {
@@ -235,18 +277,184 @@ impl SymbolTable {
         self.scopes.get(&self.current_scope_id).map(|s| &s.scope_type)
     }
 
+    /// Build a table mirroring the lexical scopes of an AST. Scope ids are those of the
+    /// scope tree and symbol node ids are the pre-order indices of their declarations,
+    /// so a closure resolves its own locals, then the variables it captures, and never
+    /// the locals of a sibling closure.
+    pub fn from_scope_tree(tree: &ScopeTree) -> Self {
+        let mut table = Self::new();
+        for scope in tree.scopes() {
+            let name = scope.name.clone().unwrap_or_default();
+            let scope_type = match scope.kind {
+                ScopeKind::Global => ScopeType::Global,
+                ScopeKind::Function => ScopeType::Function(name),
+                ScopeKind::Closure => ScopeType::Closure,
+                ScopeKind::Class => ScopeType::Class(name),
+                ScopeKind::Block => ScopeType::Block,
+            };
+            let mut table_scope = Scope::new(scope.id, scope_type);
+            table_scope.parent_id = scope.parent;
+            for (symbol, &node_id) in &scope.declarations {
+                table_scope.add_symbol(Symbol { name: symbol.clone(), scope_id: scope.id, node_id });
+            }
+            table.scopes.insert(scope.id, table_scope);
+        }
+        table.scope_counter = tree.scopes().len().max(1);
+
+        for capture in tree.captures() {
+            table.captures.entry(capture.closure).or_default().push(Symbol {
+                name: capture.name.clone(),
+                scope_id: capture.defining_scope,
+                node_id: capture.declaration,
+            });
+        }
+        table
+    }
+
+    /// Symbols of enclosing functions captured by the closure or nested function `scope_id`
+    pub fn captured_symbols(&self, scope_id: usize) -> &[Symbol] {
+        self.captures.get(&scope_id).map(|symbols| symbols.as_slice()).unwrap_or(&[])
+    }
+
+    /// Build a table from the variable declarations found in source text.
+    ///
+    /// This is a lightweight, scope-insensitive pass used to answer questions such as
+    /// "what is the declared type of `rt`" when matching `$RT.exec(...)`. It covers
+    /// typed declarations and parameters (`Runtime rt`, `rt: Runtime`, `name: str` in Python
+    /// and `name: string` in TypeScript) and assignments from constructors
+    /// (`rt = new Runtime()`, `rt = Runtime()` in Python).
+    pub fn from_source(source: &str, language: Language) -> Self {
+        let mut table = Self::new();
+        let mut node_id = 0;
+        let mut define = |table: &mut Self, name: &str, type_name: &str| {
+            if is_keyword(name) || is_keyword(type_name) {
+                return;
+            }
+            node_id += 1;
+            table.define_symbol(name.to_string(), node_id, type_from_name(type_name)).ok();
+        };
+
+        // `Type name` followed by an initializer, terminator or parameter separator
+        let c_style = Regex::new(
+            r"\b([A-Za-z_][\w.]*(?:<[^<>;()]*(?:<[^<>;()]*>)?[^<>;()]*>)?(?:\[\])*)\s+([A-Za-z_]\w*)\s*(?:=|;|,|\)|:)",
+        ).unwrap();
+        // `name: Type` as in TypeScript, Kotlin, Swift, Python annotations
+        let annotated = Regex::new(r"\b([A-Za-z_]\w*)\s*:\s*([A-Z][\w.]*(?:<[^<>=;]*>)?(?:\[\])?)").unwrap();
+        // Built-in types are lowercase in Python hints and TypeScript annotations
+        let annotated_builtin = Regex::new(r"\b([A-Za-z_]\w*)\s*:\s*((?:str|int|float|bool|bytes|string|number|boolean)\b(?:\[\])?)").unwrap();
+        // `name = new Type(` / `var name = new Type(`
+        let constructed = Regex::new(r"\b([A-Za-z_]\w*)\s*=\s*new\s+([A-Za-z_][\w.]*)").unwrap();
+        // Python has no `new`: `name = Type(` with a capitalized callee
+        let py_constructed = Regex::new(r"(?m)^\s*([A-Za-z_]\w*)\s*=\s*([A-Z][\w]*(?:\.[A-Z]\w*)*)\s*\(").unwrap();
+
+        match language {
+            Language::Java | Language::CSharp | Language::C | Language::Kotlin => {
+                for cap in c_style.captures_iter(source) {
+                    define(&mut table, &cap[2], &cap[1]);
+                }
+            }
+            Language::Python => {
+                for cap in py_constructed.captures_iter(source) {
+                    define(&mut table, &cap[1], &cap[2]);
+                }
+            }
+            _ => {}
+        }
+        if matches!(language, Language::Python | Language::JavaScript) {
+            for cap in annotated_builtin.captures_iter(source) {
+                define(&mut table, &cap[1], &cap[2]);
+            }
+        }
+        if !matches!(language, Language::Java | Language::CSharp | Language::C) {
+            for cap in annotated.captures_iter(source) {
+                define(&mut table, &cap[1], &cap[2]);
+            }
+        }
+        // Constructor assignments override declared interface types (`var x = new Foo()`)
+        for cap in constructed.captures_iter(source) {
+            define(&mut table, &cap[1], &cap[2]);
+        }
+
+        table
+    }
+
+    /// Infer the type of an expression: a declared variable, a constructor call, a
+    /// static receiver (`Runtime`), or a static factory such as `Runtime.getRuntime()`.
+    pub fn infer_expression_type(&self, expr: &str) -> TypeInfo {
+        let expr = expr.trim();
+        if let Some(rest) = expr.strip_prefix("new ") {
+            let name = rest.split(|c: char| c == '(' || c == '{' || c.is_whitespace()).next().unwrap_or("");
+            return type_from_name(name);
+        }
+        if is_identifier(expr) {
+            if let Some(type_info) = self.resolve_symbol(expr).and_then(|_| self.get_symbol_type(expr)) {
+                return type_info.clone();
+            }
+            // A bare capitalized receiver is a static access on that type
+            if expr.chars().next().is_some_and(|c| c.is_ascii_uppercase()) {
+                return TypeInfo::Object(expr.to_string());
+            }
+            return TypeInfo::Unknown;
+        }
+        // `Type.getType()`, `Type.getInstance()`, `Type.newInstance()` return `Type`
+        if let Some((receiver, call)) = expr.split_once('.') {
+            let method = call.split('(').next().unwrap_or("");
+            if is_identifier(receiver)
+                && call.ends_with(')')
+                && receiver.chars().next().is_some_and(|c| c.is_ascii_uppercase())
+                && (method == format!("get{}", receiver) || method == "getInstance" || method == "newInstance")
+            {
+                return TypeInfo::Object(receiver.to_string());
+            }
+        }
+        TypeInfo::Unknown
+    }
+
     /// Clear all scopes and reset
     pub fn clear(&mut self) {
         self.scopes.clear();
         self.current_scope_id = 0;
         self.scope_counter = 1;
         self.symbol_types.clear();
+        self.captures.clear();
 
         let global_scope = Scope::new(0, ScopeType::Global);
         self.scopes.insert(0, global_scope);
     }
 }
 
+/// Map a source-level type name to `TypeInfo`
+fn type_from_name(name: &str) -> TypeInfo {
+    let name = name.trim();
+    if let Some(inner) = name.strip_suffix("[]") {
+        return TypeInfo::Array(Box::new(type_from_name(inner)));
+    }
+    match name {
+        "int" | "long" | "short" | "byte" | "char" | "float" | "double" | "boolean" | "bool"
+        | "str" | "string" | "number" | "void" => TypeInfo::Primitive(name.to_string()),
+        "" | "var" | "let" | "const" | "auto" | "any" => TypeInfo::Unknown,
+        _ => TypeInfo::Object(name.to_string()),
+    }
+}
+
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// Words that look like `Type name` pairs in source but are not declarations
+fn is_keyword(word: &str) -> bool {
+    matches!(
+        word,
+        "return" | "throw" | "new" | "else" | "case" | "package" | "import" | "class"
+            | "interface" | "enum" | "extends" | "implements" | "throws" | "instanceof"
+            | "public" | "private" | "protected" | "static" | "final" | "abstract"
+            | "goto" | "yield" | "await" | "typeof" | "in" | "of" | "as" | "is" | "def"
+            | "fun" | "val" | "using" | "namespace" | "struct" | "if" | "while" | "for"
+    )
+}
+
 impl Default for SymbolTable {
     fn default() -> Self {
         Self::new()
@@ -346,5 +554,88 @@ mod tests {
         assert!(type_info.is_some());
         assert_eq!(type_info.unwrap(), &TypeInfo::Primitive("int".to_string()));
     }
-}
 
+    #[test]
+    fn test_symbol_table_from_scope_tree() {
+        use astgrep_ast::{AstBuilder, NodeType, UniversalNode};
+
+        // function outer(token) { run(() => send(token)); each((item) => log(item)); }
+        let capturing = UniversalNode::new(NodeType::ArrowFunction)
+            .add_child(AstBuilder::call_expression(AstBuilder::identifier("send"), vec![AstBuilder::identifier("token")]));
+        let sibling = UniversalNode::new(NodeType::ArrowFunction)
+            .with_parameter("item".to_string())
+            .add_child(AstBuilder::call_expression(AstBuilder::identifier("log"), vec![AstBuilder::identifier("item")]));
+        let root = AstBuilder::program(vec![AstBuilder::function_declaration(
+            "outer",
+            vec![AstBuilder::identifier("token")],
+            AstBuilder::block_statement(vec![
+                AstBuilder::call_expression(AstBuilder::identifier("run"), vec![capturing]),
+                AstBuilder::call_expression(AstBuilder::identifier("each"), vec![sibling]),
+            ]),
+        )]);
+        let tree = ScopeTree::build(&root);
+        let closures: Vec<usize> = tree.scopes().iter().filter(|s| s.kind == ScopeKind::Closure).map(|s| s.id).collect();
+
+        let mut table = SymbolTable::from_scope_tree(&tree);
+        assert_eq!(table.captured_symbols(closures[0]).len(), 1);
+        assert_eq!(table.captured_symbols(closures[0])[0].name, "token");
+        assert!(table.captured_symbols(closures[1]).is_empty());
+
+        table.set_current_scope(closures[0]).unwrap();
+        assert_eq!(table.get_current_scope_type(), Some(&ScopeType::Closure));
+        assert!(table.resolve_symbol("token").is_some());
+        assert!(table.resolve_symbol("item").is_none());
+
+        table.set_current_scope(closures[1]).unwrap();
+        assert_eq!(table.resolve_symbol("item").map(|s| s.scope_id), Some(closures[1]));
+        assert!(table.set_current_scope(99).is_err());
+    }
+
+    #[test]
+    fn test_type_name_matching() {
+        let runtime = TypeInfo::Object("java.lang.Runtime".to_string());
+        assert!(runtime.matches_type_name("Runtime"));
+        assert!(runtime.matches_type_name("java.lang.Runtime"));
+        assert!(!runtime.matches_type_name("Pattern"));
+        assert!(!runtime.matches_type_name("com.example.Runtime"));
+        assert!(TypeInfo::Object("Runtime".to_string()).matches_type_name("java.lang.Runtime"));
+
+        let list = TypeInfo::Object("List<String>".to_string());
+        assert!(list.matches_type_name("List"));
+        assert!(TypeInfo::Array(Box::new(TypeInfo::Object("Foo".to_string()))).matches_type_name("Foo[]"));
+    }
+
+    #[test]
+    fn test_types_from_java_source() {
+        let source = r#"
+            void run(Runtime rt, String cmd, Pattern regex) {
+                ProcessBuilder pb = new ProcessBuilder(cmd);
+                List<String> args = new ArrayList<>();
+                return exec(cmd);
+            }
+        "#;
+        let table = SymbolTable::from_source(source, Language::Java);
+
+        assert!(table.infer_expression_type("rt").matches_type_name("Runtime"));
+        assert!(table.infer_expression_type("regex").matches_type_name("Pattern"));
+        assert!(table.infer_expression_type("pb").matches_type_name("ProcessBuilder"));
+        assert!(table.infer_expression_type("args").matches_type_name("ArrayList"));
+        assert!(table.infer_expression_type("Runtime.getRuntime()").matches_type_name("Runtime"));
+        assert!(table.infer_expression_type("new Foo(1)").matches_type_name("Foo"));
+        assert_eq!(table.infer_expression_type("unknown"), TypeInfo::Unknown);
+    }
+
+    #[test]
+    fn test_types_from_javascript_and_python_source() {
+        let ts = SymbolTable::from_source("function f(re: RegExp, cmd: string) { const cp = new ChildProcess(); }", Language::JavaScript);
+        assert!(ts.infer_expression_type("re").matches_type_name("RegExp"));
+        assert!(ts.infer_expression_type("cp").matches_type_name("ChildProcess"));
+        assert!(ts.infer_expression_type("cmd").matches_type_name("string"));
+
+        let py = SymbolTable::from_source("conn = Connection(dsn)\ncursor = conn.cursor()\ndef q(name: str, ids: List[int]):\n", Language::Python);
+        assert!(py.infer_expression_type("conn").matches_type_name("Connection"));
+        assert_eq!(py.infer_expression_type("cursor"), TypeInfo::Unknown);
+        assert!(py.infer_expression_type("name").matches_type_name("str"));
+        assert!(py.infer_expression_type("ids").matches_type_name("List"));
+    }
+}
@@ -0,0 +1,236 @@
+//! Symbolic propagation of local assignments
+//!
+//! Where constant propagation folds a variable to its literal value, symbolic propagation
+//! stands a variable assigned exactly once for the expression it was assigned: after
+//! `x = dangerous()`, `use(x)` reads as `use(dangerous())`. Matching runs on the source
+//! with such uses replaced, and maps matches back to the original source.
+
+use astgrep_core::Language;
+use regex::Regex;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::OnceLock;
+
+/// The expression a single-assignment variable stands for
+#[derive(Debug, Clone)]
+struct SymbolicValue {
+    /// The assigned expression, with earlier single-assignment variables in it replaced
+    expression: String,
+    /// Byte offset of the end of the assignment; only later uses are replaced
+    defined_at: usize,
+}
+
+/// The variables of a file assigned exactly once, with the expressions they stand for
+#[derive(Debug, Clone, Default)]
+pub struct SymbolicAssignments {
+    values: HashMap<String, SymbolicValue>,
+}
+
+/// A variable use replaced by its expression, as byte ranges in the original and the
+/// propagated source
+#[derive(Debug, Clone)]
+struct Replacement {
+    original: Range<usize>,
+    propagated: Range<usize>,
+}
+
+/// Source text with uses of single-assignment variables replaced by their expressions
+#[derive(Debug, Clone)]
+pub struct PropagatedSource {
+    pub text: String,
+    replacements: Vec<Replacement>,
+}
+
+impl SymbolicAssignments {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Collect the single-assignment variables of a file from its source.
+    ///
+    /// Like `ConstantAnalyzer::from_source` this is a scope-insensitive pass: a variable
+    /// qualifies when it is assigned exactly once, with `=`, from an expression on one
+    /// line that does not mention the variable itself. Reassigned, compound-assigned and
+    /// incremented variables do not.
+    pub fn from_source(source: &str, language: Language) -> Self {
+        // `[modifiers] [Type] name [: Type] [op]= value` at the start of a statement
+        static ASSIGNMENT: OnceLock<Regex> = OnceLock::new();
+        static INCREMENT: OnceLock<Regex> = OnceLock::new();
+        let assignment = ASSIGNMENT.get_or_init(|| {
+            Regex::new(
+                r"(?m)(?:^|[;{}])\s*(?:[A-Za-z_][\w.]*(?:<[^<>;=]*>)?(?:\[\])*\s+)*([A-Za-z_]\w*)\s*(?::\s*[A-Za-z_][\w.\[\]<>]*\s*)?([-+*/%|&^]?)=([^=][^;\n]*)",
+            )
+            .unwrap()
+        });
+        let increment = INCREMENT.get_or_init(|| Regex::new(r"([A-Za-z_]\w*)\s*(?:\+\+|--)|(?:\+\+|--)\s*([A-Za-z_]\w*)").unwrap());
+
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for cap in increment.captures_iter(source) {
+            if let Some(name) = cap.get(1).or_else(|| cap.get(2)) {
+                *counts.entry(name.as_str()).or_default() += 2;
+            }
+        }
+        let mut candidates = Vec::new();
+        for cap in assignment.captures_iter(source) {
+            let (name, value) = (cap.get(1).unwrap(), cap.get(3).unwrap());
+            *counts.entry(name.as_str()).or_default() += 1;
+            let mut expression = value.as_str().trim_start();
+            let start = value.end() - expression.len();
+            if language == Language::Python {
+                expression = expression.split(" #").next().unwrap_or(expression);
+            }
+            let expression = expression.trim_end();
+            let complete = !expression.is_empty() && !expression.ends_with(['(', '[', '{', ',', '\\']);
+            if cap[2].is_empty() && complete {
+                candidates.push((name.as_str(), start, expression, cap.get(0).unwrap().end()));
+            }
+        }
+
+        let mut assignments = Self::new();
+        for (name, start, expression, defined_at) in candidates {
+            if counts[name] != 1 || find_names(expression).any(|(_, found)| found == name) {
+                continue;
+            }
+            let expression = assignments.propagate_from(expression, start).text;
+            assignments.values.insert(name.to_string(), SymbolicValue { expression, defined_at });
+        }
+        assignments
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// The expression `name` stands for
+    pub fn expression(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(|value| value.expression.as_str())
+    }
+
+    /// Replace the uses of single-assignment variables in `source` that follow their
+    /// assignment. Member names (`obj.x`) and assignment targets are left alone.
+    pub fn propagate(&self, source: &str) -> PropagatedSource {
+        self.propagate_from(source, 0)
+    }
+
+    /// [`Self::propagate`] for a `text` found at byte `offset` of the source
+    fn propagate_from(&self, text: &str, offset: usize) -> PropagatedSource {
+        let mut propagated = String::with_capacity(text.len());
+        let mut replacements = Vec::new();
+        let mut copied = 0;
+        for (start, name) in find_names(text) {
+            let Some(value) = self.values.get(name) else { continue };
+            let end = start + name.len();
+            let member = text[..start].trim_end().ends_with('.');
+            let rest = text[end..].trim_start();
+            let assigned = rest.starts_with('=') && !rest.starts_with("==");
+            if offset + start < value.defined_at || member || assigned {
+                continue;
+            }
+            propagated.push_str(&text[copied..start]);
+            let replaced = propagated.len();
+            propagated.push_str(&value.expression);
+            replacements.push(Replacement { original: offset + start..offset + end, propagated: replaced..propagated.len() });
+            copied = end;
+        }
+        propagated.push_str(&text[copied..]);
+        PropagatedSource { text: propagated, replacements }
+    }
+}
+
+impl PropagatedSource {
+    /// Whether any use was replaced
+    pub fn is_unchanged(&self) -> bool {
+        self.replacements.is_empty()
+    }
+
+    /// The original byte range of a range of the propagated text. A range starting or
+    /// ending inside a replaced expression covers the variable it replaced.
+    pub fn original_range(&self, range: Range<usize>) -> Range<usize> {
+        self.original_offset(range.start, false)..self.original_offset(range.end, true)
+    }
+
+    fn original_offset(&self, offset: usize, end: bool) -> usize {
+        let before = self
+            .replacements
+            .iter()
+            .take_while(|r| if end { r.propagated.start < offset } else { r.propagated.start <= offset })
+            .last();
+        match before {
+            None => offset,
+            Some(r) if offset < r.propagated.end => if end { r.original.end } else { r.original.start },
+            Some(r) => offset - r.propagated.end + r.original.end,
+        }
+    }
+}
+
+/// Identifiers outside string literals, with their byte offsets
+fn find_names(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    let mut quote = None;
+    let mut escaped = false;
+    let mut name_start = None;
+    let mut names = Vec::new();
+    for (i, c) in text.char_indices().chain(std::iter::once((text.len(), ' '))) {
+        if let Some(q) = quote {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                _ if c == q => quote = None,
+                _ => {}
+            }
+            continue;
+        }
+        let is_name_char = c.is_ascii_alphanumeric() || c == '_';
+        match name_start {
+            Some(start) if !is_name_char => {
+                names.push((start, &text[start..i]));
+                name_start = None;
+            }
+            None if c.is_ascii_alphabetic() || c == '_' => {
+                // Not the tail of a number such as `0x1f`
+                let after_digit = text[..i].chars().next_back().is_some_and(|p| p.is_ascii_digit());
+                if !after_digit {
+                    name_start = Some(i);
+                }
+            }
+            _ => {}
+        }
+        if name_start.is_none() && matches!(c, '"' | '\'' | '`') {
+            quote = Some(c);
+        }
+    }
+    names.into_iter()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_single_assignments() {
+        let source = "cmd = input()\nx = build(cmd)\ny = 1\ny = 2\ni = 0\ni++\nflag = True  # default\nz = z + 1\n";
+        let assignments = SymbolicAssignments::from_source(source, Language::Python);
+        assert_eq!(assignments.expression("cmd"), Some("input()"));
+        // Earlier variables are propagated into later expressions
+        assert_eq!(assignments.expression("x"), Some("build(input())"));
+        assert_eq!(assignments.expression("flag"), Some("True"));
+        assert_eq!(assignments.expression("y"), None);
+        assert_eq!(assignments.expression("i"), None);
+        assert_eq!(assignments.expression("z"), None);
+    }
+
+    #[test]
+    fn test_propagate_and_map_back() {
+        let source = "x = dangerous()\nuse(x)\nlog(obj.x, \"x\")\n";
+        let assignments = SymbolicAssignments::from_source(source, Language::Python);
+        let propagated = assignments.propagate(source);
+        assert_eq!(propagated.text, "x = dangerous()\nuse(dangerous())\nlog(obj.x, \"x\")\n");
+
+        let call = propagated.text.find("use").unwrap();
+        let inner = propagated.text.rfind("dangerous()").unwrap();
+        assert_eq!(&source[propagated.original_range(call..call + 16)], "use(x)");
+        assert_eq!(&source[propagated.original_range(inner..inner + 11)], "x");
+        // After a replacement, offsets shift back by the difference in length
+        let obj = propagated.text.find("obj").unwrap();
+        assert_eq!(&source[propagated.original_range(obj..obj + 5)], "obj.x");
+    }
+}
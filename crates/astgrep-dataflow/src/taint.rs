@@ -790,4 +790,55 @@ mod tests {
         assert_eq!(flows[0].sink.id, sink_id);
         assert!(flows[0].is_vulnerable());
     }
+
+    #[test]
+    fn test_taint_through_captured_variable() {
+        use astgrep_ast::{AstBuilder, NodeType, ScopeTree, UniversalNode};
+
+        // function handler(req) {
+        //     let data = req;
+        //     run(() => exec(data));
+        //     each((data) => log(data));
+        // }
+        let capturing = UniversalNode::new(NodeType::ArrowFunction)
+            .add_child(AstBuilder::call_expression(AstBuilder::identifier("exec"), vec![AstBuilder::identifier("data")]));
+        let shadowing = UniversalNode::new(NodeType::ArrowFunction)
+            .with_parameter("data".to_string())
+            .add_child(AstBuilder::call_expression(AstBuilder::identifier("log"), vec![AstBuilder::identifier("data")]));
+        let root = AstBuilder::program(vec![AstBuilder::function_declaration(
+            "handler",
+            vec![AstBuilder::identifier("req")],
+            AstBuilder::block_statement(vec![
+                AstBuilder::variable_declaration("data", Some(AstBuilder::identifier("req"))),
+                AstBuilder::call_expression(AstBuilder::identifier("run"), vec![capturing]),
+                AstBuilder::call_expression(AstBuilder::identifier("each"), vec![shadowing]),
+            ]),
+        )]);
+
+        let analysis = crate::DataFlowAnalyzer::new().analyze(&root).unwrap();
+        let graph = &analysis.graph;
+        let scopes = ScopeTree::build(&root);
+        let capture = &scopes.captures()[0];
+        let captured_use = capture.uses[0];
+        let shadowed_use = scopes
+            .references()
+            .iter()
+            .find(|r| r.name == "data" && r.node > captured_use)
+            .map(|r| r.node)
+            .unwrap();
+        assert!(graph.outgoing_edges(capture.declaration).iter().any(|e| e.to == captured_use && e.edge_type == crate::graph::EdgeType::Capture));
+        assert!(graph.data_flow_predecessors(shadowed_use).is_empty());
+
+        // `req` flows into `data`, which the first closure captures; the second closure's
+        // `data` is its own parameter
+        let source_id = graph.data_flow_predecessors(capture.declaration)[0];
+        let sources = vec![Source::new(source_id, SourceType::UserInput, "Request".to_string())];
+        let sinks = vec![
+            Sink::new(captured_use, SinkType::CommandExecution, "COMMAND_INJECTION".to_string(), "exec".to_string()),
+            Sink::new(shadowed_use, SinkType::CommandExecution, "COMMAND_INJECTION".to_string(), "log".to_string()),
+        ];
+        let flows = TaintTracker::new().track_taint(graph, &sources, &sinks, &[]).unwrap();
+        assert_eq!(flows.len(), 1);
+        assert_eq!(flows[0].sink.id, captured_use);
+    }
 }
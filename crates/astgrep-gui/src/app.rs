@@ -6,12 +6,13 @@ use crate::components::{
     RuleEditor, CodeEditor, ResultsPanel, MenuBar, StatusBar, SettingsPanel
 };
 use crate::utils::file_operations::FileOperations;
-use astgrep_core::{Language, Finding, OutputFormat};
-use astgrep_rules::{RuleEngine, RuleParser, RuleContext};
+use astgrep_core::{CancellationToken, Language, Finding, OutputFormat};
+use astgrep_rules::{RuleEngine, RuleContext, RuleRepository};
 use astgrep_parser::LanguageParserRegistry;
-use std::sync::{mpsc, Arc};
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
 
+/// Name of the rule editor's inline source in the shared rule repository
+const EDITOR_RULES_SOURCE: &str = "gui-editor";
 
 
 fn md_flush_paragraph(ui: &mut egui::Ui, para_buf: &mut String) {
@@ -180,7 +181,7 @@ pub struct CrGuiApp {
     /// Async analysis: receiver for results
     analysis_rx: Option<mpsc::Receiver<AnalysisMessage>>,
     /// Async analysis: cancellation token
-    analysis_cancel: Option<Arc<AtomicBool>>,
+    analysis_cancel: Option<CancellationToken>,
     /// Async analysis: generation id to ignore stale results
     analysis_gen: u64,
     /// Whether analysis is running
@@ -255,7 +256,8 @@ pub struct UiState {
 enum AnalysisMessage {
     Finished(u64, Vec<Finding>),
     Error(u64, String),
-    Cancelled(u64),
+    /// Cancelled; carries the findings of the rules that ran before the cancel request
+    Cancelled(u64, Vec<Finding>),
 }
 
 #[derive(Default, PartialEq)]
@@ -572,15 +574,7 @@ impl eframe::App for CrGuiApp {
                 match msg {
                     AnalysisMessage::Finished(gen, findings) => {
                         if gen == self.analysis_gen {
-                            let mut findings = findings;
-                            findings.sort_by(|a, b| {
-                                a.location.start_line
-                                    .cmp(&b.location.start_line)
-                                    .then(a.location.start_column.cmp(&b.location.start_column))
-                                    .then(a.location.end_line.cmp(&b.location.end_line))
-                                    .then(a.location.end_column.cmp(&b.location.end_column))
-                            });
-                            self.analysis_results = findings;
+                            self.analysis_results = Self::sorted_findings(findings);
                             self.status_bar.analysis_completed(self.analysis_results.len());
                             self.update_code_highlights();
                             self.is_analysis_running = false;
@@ -594,9 +588,15 @@ impl eframe::App for CrGuiApp {
                             self.is_analysis_running = false;
                         }
                     }
-                    AnalysisMessage::Cancelled(gen) => {
+                    AnalysisMessage::Cancelled(gen, findings) => {
                         if gen == self.analysis_gen {
-                            self.status_bar.set_status("Analysis cancelled");
+                            // Keep whatever the rules that completed found
+                            self.analysis_results = Self::sorted_findings(findings);
+                            self.status_bar.set_status(&format!(
+                                "Analysis cancelled ({} partial finding(s))",
+                                self.analysis_results.len()
+                            ));
+                            self.update_code_highlights();
                             self.is_analysis_running = false;
                         }
                     }
@@ -1127,27 +1127,15 @@ impl CrGuiApp {
     ) -> anyhow::Result<Vec<astgrep_core::Finding>> {
         use std::path::PathBuf;
 
-        // Parse rules using the real RuleParser
-        let rule_parser = RuleParser::new();
-        let parsed_rules = rule_parser.parse_yaml(rule_content)
-            .map_err(|e| anyhow::anyhow!("Failed to parse rules: {}", e))?;
+        // Load the editor rules through the shared repository and keep those for this language
+        self.rule_engine = Self::editor_rule_engine(rule_content, language)?;
 
-        if parsed_rules.is_empty() {
+        if self.rule_engine.rule_count() == 0 {
             println!("⚠️ No applicable rules found for language {:?}", language);
             return Ok(Vec::new());
         }
 
-        println!("📋 Loaded {} rules for {:?}", parsed_rules.len(), language);
-
-
-
-        // Clear existing rules and add new ones
-        self.rule_engine = RuleEngine::new();
-        for rule in parsed_rules {
-            if let Err(e) = self.rule_engine.add_rule(rule) {
-                println!("⚠️ Failed to add rule: {}", e);
-            }
-        }
+        println!("📋 Loaded {} rules for {:?}", self.rule_engine.rule_count(), language);
 
         // Parse the source code using the appropriate parser
         // Create a proper file path with the correct extension for the language
@@ -1221,9 +1209,20 @@ impl CrGuiApp {
             self.code_editor.add_highlight(highlight);
         }
     }
+    fn sorted_findings(mut findings: Vec<Finding>) -> Vec<Finding> {
+        findings.sort_by(|a, b| {
+            a.location.start_line
+                .cmp(&b.location.start_line)
+                .then(a.location.start_column.cmp(&b.location.start_column))
+                .then(a.location.end_line.cmp(&b.location.end_line))
+                .then(a.location.end_column.cmp(&b.location.end_column))
+        });
+        findings
+    }
+
     fn cancel_analysis(&mut self) {
         if let Some(cancel) = &self.analysis_cancel {
-            cancel.store(true, Ordering::Relaxed);
+            cancel.cancel();
             self.status_bar.set_status("Cancelling analysis...");
         } else {
             self.status_bar.set_status("No analysis in progress");
@@ -1259,7 +1258,7 @@ impl CrGuiApp {
 
         // If a previous analysis is running, request cancel
         if self.is_analysis_running {
-            if let Some(c) = &self.analysis_cancel { c.store(true, Ordering::Relaxed); }
+            if let Some(c) = &self.analysis_cancel { c.cancel(); }
         }
 
         self.status_bar.analysis_started();
@@ -1268,18 +1267,19 @@ impl CrGuiApp {
         let gen = self.analysis_gen;
 
         let (tx, rx) = mpsc::channel();
-        let cancel = Arc::new(AtomicBool::new(false));
+        let cancel = CancellationToken::new();
         self.analysis_rx = Some(rx);
         self.analysis_cancel = Some(cancel.clone());
 
+        // The parser, AST and engine live on the worker thread and are dropped as soon as it returns
         std::thread::spawn(move || {
-            if cancel.load(Ordering::Relaxed) {
-                let _ = tx.send(AnalysisMessage::Cancelled(gen));
+            if cancel.is_cancelled() {
+                let _ = tx.send(AnalysisMessage::Cancelled(gen, Vec::new()));
                 return;
             }
-            match CrGuiApp::analyze_code_with_rules_stateless(&source_code, &rule_content, lang, cancel.clone()) {
-                Ok(Some(findings)) => { let _ = tx.send(AnalysisMessage::Finished(gen, findings)); }
-                Ok(None) => { let _ = tx.send(AnalysisMessage::Cancelled(gen)); }
+            match CrGuiApp::analyze_code_with_rules_stateless(&source_code, &rule_content, lang, &cancel) {
+                Ok(findings) if cancel.is_cancelled() => { let _ = tx.send(AnalysisMessage::Cancelled(gen, findings)); }
+                Ok(findings) => { let _ = tx.send(AnalysisMessage::Finished(gen, findings)); }
                 Err(e) => { let _ = tx.send(AnalysisMessage::Error(gen, format!("{}", e))); }
             }
         });
@@ -1303,29 +1303,28 @@ impl CrGuiApp {
         })
     }
 
+    /// Build a rule engine from the rule editor content via the shared rule repository,
+    /// which skips re-parsing when the content has not changed since the last analysis
+    fn editor_rule_engine(rule_content: &str, language: astgrep_core::Language) -> anyhow::Result<RuleEngine> {
+        let mut repository = RuleRepository::global().write().unwrap_or_else(|e| e.into_inner());
+        repository.load_yaml(EDITOR_RULES_SOURCE, rule_content)
+            .map_err(|e| anyhow::anyhow!("Failed to parse rules: {}", e))?;
+        Ok(repository.engine_for_language(language))
+    }
+
     fn analyze_code_with_rules_stateless(
         source_code: &str,
         rule_content: &str,
         language: astgrep_core::Language,
-        cancel: Arc<AtomicBool>,
-    ) -> anyhow::Result<Option<Vec<astgrep_core::Finding>>> {
+        cancel: &CancellationToken,
+    ) -> anyhow::Result<Vec<astgrep_core::Finding>> {
         use std::path::PathBuf;
 
-        if cancel.load(Ordering::Relaxed) { return Ok(None); }
+        if cancel.is_cancelled() { return Ok(Vec::new()); }
 
         // Parse rules
-        let rule_parser = RuleParser::new();
-        let parsed_rules = rule_parser.parse_yaml(rule_content)
-            .map_err(|e| anyhow::anyhow!("Failed to parse rules: {}", e))?;
-        if cancel.load(Ordering::Relaxed) { return Ok(None); }
-
-        let mut rule_engine = RuleEngine::new();
-        for rule in parsed_rules {
-            if let Err(e) = rule_engine.add_rule(rule) {
-                eprintln!("Failed to add rule: {}", e);
-            }
-        }
-        if cancel.load(Ordering::Relaxed) { return Ok(None); }
+        let mut rule_engine = Self::editor_rule_engine(rule_content, language)?;
+        if cancel.is_cancelled() { return Ok(Vec::new()); }
 
         // Parse source
         let file_extension = match language {
@@ -1346,22 +1345,22 @@ impl CrGuiApp {
         let parser_registry = LanguageParserRegistry::new();
         let ast = parser_registry.parse_file(&file_path, source_code)
             .map_err(|e| anyhow::anyhow!("Failed to parse source code: {}", e))?;
-        if cancel.load(Ordering::Relaxed) { return Ok(None); }
+        if cancel.is_cancelled() { return Ok(Vec::new()); }
 
+        // Rules stop at the next rule boundary once cancelled
         let mut context = RuleContext::new(
             file_path.to_string_lossy().to_string(),
             language,
             source_code.to_string(),
-        );
+        ).with_cancellation(cancel.clone());
         // GUI: default to ON; YAML can override per-rule in engine
         context = context.add_data("sql_statement_boundary".to_string(), "true".to_string());
         let rule_results = rule_engine.execute_rules(&*ast, &context)
             .map_err(|e| anyhow::anyhow!("Failed to execute rules: {}", e))?;
-        if cancel.load(Ordering::Relaxed) { return Ok(None); }
 
         let mut findings = Vec::new();
         for result in rule_results { findings.extend(result.findings); }
-        Ok(Some(findings))
+        Ok(findings)
     }
 
     fn show_find_replace_window(&mut self, ctx: &egui::Context) {
@@ -3,33 +3,192 @@
 //! This module implements a sophisticated pattern matcher that supports
 //! all semgrep pattern types including pattern-either, pattern-inside,
 //! pattern-not, metavariable-pattern, and metavariable-regex.
+//!
+//! A metavariable-pattern matches its patterns within the code bound to the
+//! metavariable. When it names another language, the bound code (the contents of a
+//! string literal, say) is parsed with the parser registered for that language.
+//!
+//! A pattern with focus reports the node bound to its first bound focus metavariable
+//! instead of the whole match, such as the `$X` of `exec(<... $X ...>)`.
+//!
+//! Simple patterns also match their equivalent spellings under the matcher's
+//! [`EquivalenceConfig`], so `$X == null` finds `null == x`.
+//!
+//! A search polls the matcher's cancellation token at every node it tries and on every
+//! attempt of the ellipsis backtracking, and ends with `AnalysisError::Cancelled` once
+//! it is set.
 
 use crate::parser::{PatternParser, ParsedPattern};
-use crate::metavar::MetavarManager;
+use crate::metavar::{MetavarBinding, MetavarManager};
+use crate::ellipsis::{self, ChildMatcher};
+use crate::equivalence::{self, EquivalenceConfig};
 use astgrep_core::{AstNode, Result, AnalysisError, SemgrepPattern, PatternType, Condition, MetavariableRegex, MetavariableComparison, ComparisonOperator, SemgrepMatchResult};
+use astgrep_core::{CancellationToken, Language, LanguageParser, MetavariablePattern, SharedLruCache};
+use astgrep_ast::{NodeType, UniversalNode};
 use astgrep_core::{MetavariableAnalysis, EntropyAnalysis, TypeAnalysis, ComplexityAnalysis};
 // Note: These types are defined in cr_rules but we'll use them through cr_core for now
 use std::collections::HashMap;
+use std::ops::ControlFlow;
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
 use regex::Regex;
 
 /// Advanced pattern matcher with full semgrep support
+///
+/// The matcher only holds configuration; the bindings of a match in progress live in a
+/// `MatchContext` created for each search, so one instance can be shared by reference
+/// across the threads of the parallel pipeline.
 pub struct AdvancedSemgrepMatcher {
     parser: PatternParser,
-    metavar_manager: MetavarManager,
     debug_mode: bool,
     max_depth: Option<usize>,
+    /// Parsers for code embedded in metavariables, by language
+    parsers: HashMap<Language, Arc<dyn LanguageParser>>,
+    equivalences: EquivalenceConfig,
+    cancellation: CancellationToken,
+}
+
+/// Per-search matching state: the metavariable bindings of the match being tried
+#[derive(Default)]
+struct MatchContext {
+    metavars: MetavarManager,
 }
 
+impl MatchContext {
+    fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// A match in progress, as the child sequence matching of `ellipsis` sees it
+struct ChildSearch<'m, 'c> {
+    matcher: &'m AdvancedSemgrepMatcher,
+    ctx: &'c mut MatchContext,
+    depth: usize,
+}
 
+impl ChildMatcher for ChildSearch<'_, '_> {
+    type Snapshot = HashMap<String, MetavarBinding>;
+
+    fn snapshot(&self) -> Self::Snapshot {
+        self.ctx.metavars.snapshot()
+    }
+
+    fn restore(&mut self, snapshot: Self::Snapshot) {
+        self.ctx.metavars.restore(snapshot);
+    }
+
+    fn match_node(&mut self, pattern: &ParsedPattern, node: &dyn AstNode) -> Result<bool> {
+        self.matcher.match_parsed_pattern(self.ctx, pattern, node, self.depth + 1)
+    }
+
+    fn bind_nodes(&mut self, metavar: &str, nodes: &[&dyn AstNode], parent: &dyn AstNode) -> Result<bool> {
+        let node_type = nodes.first().copied().unwrap_or(parent).node_type();
+        self.ctx.metavars.bind_at(metavar.to_string(), ellipsis::joined_text(nodes), node_type, ellipsis::joined_location(nodes))
+    }
+
+    fn checkpoint(&self) -> Result<()> {
+        self.matcher.cancellation.check()
+    }
+}
+
+/// A node whose children are still being searched; the node itself is on the path
+struct MatchFrame {
+    depth: usize,
+    next_child: usize,
+    subtree_has_match: bool,
+}
+
+/// Lazy iterator over the matches of a pattern, returned by
+/// [`AdvancedSemgrepMatcher::find_matches_iter`].
+///
+/// Prefers the smallest (most specific) nodes: children are searched first and a node
+/// is only tried when none of its descendants matched.
+pub struct SemgrepMatches<'m, 'a> {
+    matcher: &'m AdvancedSemgrepMatcher,
+    context: MatchContext,
+    pattern: &'a SemgrepPattern,
+    stack: Vec<MatchFrame>,
+    /// The nodes of `stack`, root first: the ancestors of the node being tried
+    path: Vec<&'a dyn AstNode>,
+    failed: bool,
+}
+
+impl<'m, 'a> Iterator for SemgrepMatches<'m, 'a> {
+    type Item = Result<SemgrepMatchResult>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        while let Some(frame) = self.stack.last_mut() {
+            // Descend into the next child
+            let node = *self.path.last()?;
+            if frame.next_child < node.child_count() {
+                let index = frame.next_child;
+                frame.next_child += 1;
+                let depth = frame.depth + 1;
+                if let Some(child) = node.child(index) {
+                    if self.matcher.max_depth.is_none_or(|max_depth| depth <= max_depth) {
+                        self.stack.push(MatchFrame { depth, next_child: 0, subtree_has_match: false });
+                        self.path.push(child);
+                    }
+                }
+                continue;
+            }
+
+            // All children searched: try this node unless a descendant matched
+            let frame = self.stack.pop()?;
+            let node = self.path.pop()?;
+            let mut result = None;
+            if !frame.subtree_has_match {
+                if let Err(e) = self.matcher.cancellation.check() {
+                    self.failed = true;
+                    return Some(Err(e));
+                }
+                let snapshot = self.context.metavars.snapshot();
+                match self.matcher.matches_pattern(&mut self.context, self.pattern, node, &self.path) {
+                    Ok(true) => {
+                        let bindings = self.context.metavars.get_binding_values();
+                        let metavariables = self.context.metavars.get_bound_metavariables();
+                        let focused = self.matcher.focused_node(&self.context, self.pattern, node);
+                        result = Some(SemgrepMatchResult::new(focused.clone_node(), bindings).with_metavariables(metavariables));
+                    }
+                    Ok(false) => {}
+                    Err(e) => {
+                        self.context.metavars.restore(snapshot);
+                        self.failed = true;
+                        return Some(Err(e));
+                    }
+                }
+                self.context.metavars.restore(snapshot);
+            }
+
+            if frame.subtree_has_match || result.is_some() {
+                if let Some(parent) = self.stack.last_mut() {
+                    parent.subtree_has_match = true;
+                }
+            }
+            if let Some(result) = result {
+                return Some(Ok(result));
+            }
+        }
+
+        None
+    }
+}
 
 impl AdvancedSemgrepMatcher {
     /// Create a new advanced semgrep matcher
     pub fn new() -> Self {
         Self {
             parser: PatternParser::new(),
-            metavar_manager: MetavarManager::new(),
             debug_mode: false,
             max_depth: None,
+            parsers: HashMap::new(),
+            equivalences: EquivalenceConfig::default(),
+            cancellation: CancellationToken::new(),
         }
     }
 
@@ -45,73 +204,79 @@ impl AdvancedSemgrepMatcher {
         self
     }
 
+    /// Parse code bound to metavariables with `parser` when a metavariable-pattern names
+    /// its language
+    pub fn with_parser(mut self, parser: Arc<dyn LanguageParser>) -> Self {
+        self.parsers.insert(parser.language(), parser);
+        self
+    }
+
+    /// Set the operator equivalences simple patterns are matched under
+    pub fn with_equivalences(mut self, equivalences: EquivalenceConfig) -> Self {
+        self.equivalences = equivalences;
+        self
+    }
+
+    /// Stop searches with `AnalysisError::Cancelled` once `cancellation` is set
+    pub fn with_cancellation(mut self, cancellation: CancellationToken) -> Self {
+        self.cancellation = cancellation;
+        self
+    }
+
     /// Find all matches for a pattern in the AST
-    pub fn find_matches(&mut self, pattern: &SemgrepPattern, root: &dyn AstNode) -> Result<Vec<SemgrepMatchResult>> {
-        let mut matches = Vec::new();
-        // Prefer the smallest (most specific) nodes: search children first and only
-        // record a match for a parent if no descendant matched.
-        self.find_matches_recursive(pattern, root, &mut matches, 0)?;
-        Ok(matches)
-    }
-
-    /// Recursively find matches in the AST
-    /// Returns whether this subtree produced any match (to enable parent suppression)
-    fn find_matches_recursive(
-        &mut self,
-        pattern: &SemgrepPattern,
-        node: &dyn AstNode,
-        matches: &mut Vec<SemgrepMatchResult>,
-        depth: usize,
-    ) -> Result<bool> {
-        // Check depth limit
-        if let Some(max_depth) = self.max_depth {
-            if depth > max_depth {
-                return Ok(false);
-            }
-        }
+    pub fn find_matches(&self, pattern: &SemgrepPattern, root: &dyn AstNode) -> Result<Vec<SemgrepMatchResult>> {
+        self.find_matches_iter(pattern, root).collect()
+    }
 
-        // First, recurse into children
-        let mut subtree_has_match = false;
-        for i in 0..node.child_count() {
-            if let Some(child) = node.child(i) {
-                if self.find_matches_recursive(pattern, child, matches, depth + 1)? {
-                    subtree_has_match = true;
-                }
-            }
+    /// Lazily find matches for a pattern in the AST, in the same order as `find_matches`.
+    /// Dropping the iterator stops the search, so callers that need only the first few
+    /// matches (a findings limit, an existence check) skip the rest of the tree.
+    pub fn find_matches_iter<'m, 'a>(&'m self, pattern: &'a SemgrepPattern, root: &'a dyn AstNode) -> SemgrepMatches<'m, 'a> {
+        SemgrepMatches {
+            matcher: self,
+            context: MatchContext::new(),
+            pattern,
+            stack: vec![MatchFrame { depth: 0, next_child: 0, subtree_has_match: false }],
+            path: vec![root],
+            failed: false,
         }
+    }
 
-        // Try to match at current node only if no descendant produced a match
-        if !subtree_has_match {
-            let snapshot = self.metavar_manager.snapshot();
-            if self.matches_pattern(pattern, node)? {
-                let bindings = self.metavar_manager.get_binding_values();
-                matches.push(SemgrepMatchResult::new(node.clone_node(), bindings));
-                self.metavar_manager.restore(snapshot);
-                return Ok(true);
+    /// Call `visit` for each match until it returns `ControlFlow::Break`
+    pub fn visit_matches<F>(&self, pattern: &SemgrepPattern, root: &dyn AstNode, mut visit: F) -> Result<()>
+    where
+        F: FnMut(SemgrepMatchResult) -> ControlFlow<()>,
+    {
+        for result in self.find_matches_iter(pattern, root) {
+            if visit(result?).is_break() {
+                break;
             }
-            self.metavar_manager.restore(snapshot);
         }
+        Ok(())
+    }
 
-        Ok(subtree_has_match)
+    /// Whether the pattern matches anywhere in the AST, stopping at the first match
+    pub fn has_match(&self, pattern: &SemgrepPattern, root: &dyn AstNode) -> Result<bool> {
+        self.find_matches_iter(pattern, root).next().transpose().map(|m| m.is_some())
     }
 
-    /// Check if a pattern matches a node
-    fn matches_pattern(&mut self, pattern: &SemgrepPattern, node: &dyn AstNode) -> Result<bool> {
-        match &pattern.pattern_type {
+    /// Check if a pattern matches a node; `ancestors` are the nodes above it, root first
+    fn matches_pattern(&self, ctx: &mut MatchContext, pattern: &SemgrepPattern, node: &dyn AstNode, ancestors: &[&dyn AstNode]) -> Result<bool> {
+        let matched = match &pattern.pattern_type {
             PatternType::Simple(pattern_str) => {
-                self.matches_simple_pattern(pattern_str, node)
+                self.matches_simple_pattern(ctx, pattern_str, node)
             }
             PatternType::Either(patterns) => {
-                self.matches_either_pattern(patterns, node)
+                self.matches_either_pattern(ctx, patterns, node, ancestors)
             }
             PatternType::Inside(inner_pattern) => {
-                self.matches_inside_pattern(inner_pattern, node)
+                self.matches_inside_pattern(ctx, inner_pattern, node, ancestors)
             }
             PatternType::NotInside(inner_pattern) => {
-                self.matches_not_inside_pattern(inner_pattern, node)
+                self.matches_not_inside_pattern(ctx, inner_pattern, node, ancestors)
             }
             PatternType::Not(inner_pattern) => {
-                self.matches_not_pattern(inner_pattern, node)
+                self.matches_not_pattern(ctx, inner_pattern, node, ancestors)
             }
             PatternType::Regex(regex_str) => {
                 self.matches_regex_pattern(regex_str, node)
@@ -120,86 +285,147 @@ impl AdvancedSemgrepMatcher {
                 self.matches_not_regex_pattern(regex_str, node)
             }
             PatternType::All(patterns) => {
-                self.matches_all_patterns(patterns, node)
+                self.matches_all_patterns(ctx, patterns, node, ancestors)
             }
             PatternType::Any(patterns) => {
-                self.matches_any_patterns(patterns, node)
+                self.matches_any_patterns(ctx, patterns, node, ancestors)
+            }
+        }?;
+
+        match &pattern.metavariable_pattern {
+            Some(spec) if matched => self.matches_metavariable_pattern(ctx, spec, node),
+            _ => Ok(matched),
+        }
+    }
+
+    /// Match metavariable-pattern: the code bound to the metavariable matches the regex,
+    /// if any, and contains a match of every pattern
+    fn matches_metavariable_pattern(&self, ctx: &mut MatchContext, spec: &MetavariablePattern, node: &dyn AstNode) -> Result<bool> {
+        let name = spec.metavariable.trim_start_matches('$').trim_start_matches("...");
+        let binding = match ctx.metavars.get_binding(name) {
+            Some(binding) => binding,
+            None => return Ok(false),
+        };
+
+        if let Some(ref regex_str) = spec.regex {
+            let regex = Regex::new(regex_str)
+                .map_err(|_| AnalysisError::pattern_match_error(format!("Invalid regex: {}", regex_str)))?;
+            if !regex.is_match(&binding.value) {
+                return Ok(false);
+            }
+        }
+
+        let parsed;
+        let text_node;
+        let target: &dyn AstNode = match spec.language.as_deref() {
+            Some(language) => {
+                // Embedded code: the contents of a string literal, in its own language
+                let code = ellipsis::unquote(binding.value.trim()).unwrap_or(&binding.value);
+                let parser = Language::from_str(language).and_then(|language| self.parsers.get(&language));
+                match parser.map(|parser| parser.parse(code, Path::new(language))) {
+                    Some(Ok(ast)) => {
+                        parsed = ast;
+                        parsed.as_ref()
+                    }
+                    // `generic`, no parser or code that does not parse: match the text
+                    _ => {
+                        text_node = UniversalNode::new(NodeType::Unknown).with_text(code.to_string());
+                        &text_node
+                    }
+                }
+            }
+            None => match find_bound_node(node, binding) {
+                Some(bound) => bound,
+                None => {
+                    text_node = UniversalNode::new(NodeType::Unknown).with_text(binding.value.clone());
+                    &text_node
+                }
+            },
+        };
+
+        for pattern in &spec.patterns {
+            if !self.has_match(&SemgrepPattern::simple(pattern.clone()), target)? {
+                return Ok(false);
             }
         }
+        Ok(true)
     }
 
-    /// Match a simple pattern string
-    fn matches_simple_pattern(&mut self, pattern_str: &str, node: &dyn AstNode) -> Result<bool> {
-        let parsed_pattern = self.parser.parse(pattern_str)?;
-        self.match_parsed_pattern(&parsed_pattern, node, 0)
+    /// The node bound to the first bound focus metavariable of `pattern` within `node`, or
+    /// `node` itself when the pattern has no focus
+    fn focused_node<'n>(&self, ctx: &MatchContext, pattern: &SemgrepPattern, node: &'n dyn AstNode) -> &'n dyn AstNode {
+        pattern
+            .focus
+            .iter()
+            .flatten()
+            .filter_map(|name| ctx.metavars.get_binding(name.trim_start_matches('$')))
+            .find_map(|binding| find_bound_node(node, binding))
+            .unwrap_or(node)
     }
 
-    /// Match pattern-either (OR logic)
-    fn matches_either_pattern(&mut self, patterns: &[SemgrepPattern], node: &dyn AstNode) -> Result<bool> {
-        for pattern in patterns {
-            let snapshot = self.metavar_manager.snapshot();
-            if self.matches_pattern(pattern, node)? {
+    /// Match a simple pattern string, or one of its equivalent spellings
+    fn matches_simple_pattern(&self, ctx: &mut MatchContext, pattern_str: &str, node: &dyn AstNode) -> Result<bool> {
+        for spelling in compiled_spellings(&self.parser, pattern_str, self.equivalences)?.iter() {
+            let snapshot = ctx.metavars.snapshot();
+            if self.match_parsed_pattern(ctx, spelling, node, 0)? {
                 return Ok(true);
             }
-            self.metavar_manager.restore(snapshot);
+            ctx.metavars.restore(snapshot);
         }
         Ok(false)
     }
 
-    /// Match pattern-inside
-    fn matches_inside_pattern(&mut self, inner_pattern: &SemgrepPattern, node: &dyn AstNode) -> Result<bool> {
-        // Check if the current node or any of its ancestors match the inner pattern
-        let mut current = Some(node);
-        while let Some(current_node) = current {
-            if self.matches_pattern(inner_pattern, current_node)? {
+    /// Match pattern-either (OR logic)
+    fn matches_either_pattern(&self, ctx: &mut MatchContext, patterns: &[SemgrepPattern], node: &dyn AstNode, ancestors: &[&dyn AstNode]) -> Result<bool> {
+        for pattern in patterns {
+            let snapshot = ctx.metavars.snapshot();
+            if self.matches_pattern(ctx, pattern, node, ancestors)? {
                 return Ok(true);
             }
-            // In a real implementation, we would traverse up the parent chain
-            // For now, we'll just check children
-            break;
+            ctx.metavars.restore(snapshot);
         }
-
-        // Also check if any descendant matches
-        self.matches_inside_recursive(inner_pattern, node)
+        Ok(false)
     }
 
-    /// Recursively check for pattern-inside matches
-    fn matches_inside_recursive(&mut self, pattern: &SemgrepPattern, node: &dyn AstNode) -> Result<bool> {
-        for i in 0..node.child_count() {
-            if let Some(child) = node.child(i) {
-                let snapshot = self.metavar_manager.snapshot();
-                if self.matches_pattern(pattern, child)? {
-                    return Ok(true);
-                }
-                self.metavar_manager.restore(snapshot);
+    /// Match pattern-inside: the node or one of its ancestors matches the inner pattern,
+    /// nearest first, so the match lies within the enclosing construct however many lines
+    /// it spans. Bindings of the enclosing match are kept.
+    fn matches_inside_pattern(&self, ctx: &mut MatchContext, inner_pattern: &SemgrepPattern, node: &dyn AstNode, ancestors: &[&dyn AstNode]) -> Result<bool> {
+        let snapshot = ctx.metavars.snapshot();
+        if self.matches_pattern(ctx, inner_pattern, node, ancestors)? {
+            return Ok(true);
+        }
+        ctx.metavars.restore(snapshot);
 
-                if self.matches_inside_recursive(pattern, child)? {
-                    return Ok(true);
-                }
+        for (index, ancestor) in ancestors.iter().enumerate().rev() {
+            let snapshot = ctx.metavars.snapshot();
+            if self.matches_pattern(ctx, inner_pattern, *ancestor, &ancestors[..index])? {
+                return Ok(true);
             }
+            ctx.metavars.restore(snapshot);
         }
         Ok(false)
     }
 
-    /// Match pattern-not-inside
-    fn matches_not_inside_pattern(&mut self, inner_pattern: &SemgrepPattern, node: &dyn AstNode) -> Result<bool> {
-        // A pattern matches pattern-not-inside if it does NOT match pattern-inside
-        let snapshot = self.metavar_manager.snapshot();
-        let matches_inside = self.matches_inside_pattern(inner_pattern, node)?;
-        self.metavar_manager.restore(snapshot);
+    /// Match pattern-not-inside: neither the node nor any of its ancestors matches the
+    /// inner pattern
+    fn matches_not_inside_pattern(&self, ctx: &mut MatchContext, inner_pattern: &SemgrepPattern, node: &dyn AstNode, ancestors: &[&dyn AstNode]) -> Result<bool> {
+        let snapshot = ctx.metavars.snapshot();
+        let matches_inside = self.matches_inside_pattern(ctx, inner_pattern, node, ancestors)?;
+        ctx.metavars.restore(snapshot);
         Ok(!matches_inside)
     }
 
     /// Match pattern-not
-    fn matches_not_pattern(&mut self, inner_pattern: &SemgrepPattern, node: &dyn AstNode) -> Result<bool> {
-        let snapshot = self.metavar_manager.snapshot();
-        let matches = self.matches_pattern(inner_pattern, node)?;
-        self.metavar_manager.restore(snapshot);
+    fn matches_not_pattern(&self, ctx: &mut MatchContext, inner_pattern: &SemgrepPattern, node: &dyn AstNode, ancestors: &[&dyn AstNode]) -> Result<bool> {
+        let snapshot = ctx.metavars.snapshot();
+        let matches = self.matches_pattern(ctx, inner_pattern, node, ancestors)?;
+        ctx.metavars.restore(snapshot);
         Ok(!matches)
     }
 
     /// Match pattern-regex
-    fn matches_regex_pattern(&mut self, regex_str: &str, node: &dyn AstNode) -> Result<bool> {
+    fn matches_regex_pattern(&self, regex_str: &str, node: &dyn AstNode) -> Result<bool> {
         if let Some(text) = node.text() {
             if let Ok(regex) = Regex::new(regex_str) {
                 Ok(regex.is_match(text))
@@ -212,7 +438,7 @@ impl AdvancedSemgrepMatcher {
     }
 
     /// Match pattern-not-regex
-    fn matches_not_regex_pattern(&mut self, regex_str: &str, node: &dyn AstNode) -> Result<bool> {
+    fn matches_not_regex_pattern(&self, regex_str: &str, node: &dyn AstNode) -> Result<bool> {
         if let Some(text) = node.text() {
             if let Ok(regex) = Regex::new(regex_str) {
                 Ok(!regex.is_match(text))
@@ -225,11 +451,11 @@ impl AdvancedSemgrepMatcher {
     }
 
     /// Match all patterns (AND logic)
-    fn matches_all_patterns(&mut self, patterns: &[SemgrepPattern], node: &dyn AstNode) -> Result<bool> {
+    fn matches_all_patterns(&self, ctx: &mut MatchContext, patterns: &[SemgrepPattern], node: &dyn AstNode, ancestors: &[&dyn AstNode]) -> Result<bool> {
         for pattern in patterns {
-            let snapshot = self.metavar_manager.snapshot();
-            if !self.matches_pattern(pattern, node)? {
-                self.metavar_manager.restore(snapshot);
+            let snapshot = ctx.metavars.snapshot();
+            if !self.matches_pattern(ctx, pattern, node, ancestors)? {
+                ctx.metavars.restore(snapshot);
                 return Ok(false);
             }
             // Keep bindings from successful matches
@@ -238,19 +464,21 @@ impl AdvancedSemgrepMatcher {
     }
 
     /// Match any patterns (OR logic, same as either)
-    fn matches_any_patterns(&mut self, patterns: &[SemgrepPattern], node: &dyn AstNode) -> Result<bool> {
-        self.matches_either_pattern(patterns, node)
+    fn matches_any_patterns(&self, ctx: &mut MatchContext, patterns: &[SemgrepPattern], node: &dyn AstNode, ancestors: &[&dyn AstNode]) -> Result<bool> {
+        self.matches_either_pattern(ctx, patterns, node, ancestors)
     }
 
     /// Match a parsed pattern against a node
-    fn match_parsed_pattern(&mut self, pattern: &ParsedPattern, node: &dyn AstNode, depth: usize) -> Result<bool> {
+    fn match_parsed_pattern(&self, ctx: &mut MatchContext, pattern: &ParsedPattern, node: &dyn AstNode, depth: usize) -> Result<bool> {
         match pattern {
             ParsedPattern::Literal(literal) => self.match_literal(literal, node),
-            ParsedPattern::Metavariable(metavar) => self.match_metavariable(metavar, node),
-            ParsedPattern::EllipsisMetavariable(metavar) => self.match_ellipsis_metavariable(metavar, node),
+            ParsedPattern::Metavariable(metavar) => self.match_metavariable(ctx, metavar, node),
+            ParsedPattern::EllipsisMetavariable(metavar) => self.match_ellipsis_metavariable(ctx, metavar, node),
             ParsedPattern::NodeType(node_type) => self.match_node_type(node_type, node),
-            ParsedPattern::Sequence(patterns) => self.match_sequence(patterns, node, depth),
-            ParsedPattern::Alternative(patterns) => self.match_alternative(patterns, node, depth),
+            ParsedPattern::Sequence(patterns) => self.match_sequence(ctx, patterns, node, depth),
+            ParsedPattern::Alternative(patterns) => self.match_alternative(ctx, patterns, node, depth),
+            ParsedPattern::Children(elements) => ellipsis::match_children(&mut ChildSearch { matcher: self, ctx, depth }, elements, node),
+            ParsedPattern::Deep(inner) => ellipsis::match_deep(&mut ChildSearch { matcher: self, ctx, depth }, inner, node),
             ParsedPattern::Wildcard => Ok(true),
         }
     }
@@ -265,21 +493,22 @@ impl AdvancedSemgrepMatcher {
     }
 
     /// Match metavariable
-    fn match_metavariable(&mut self, metavar: &str, node: &dyn AstNode) -> Result<bool> {
+    fn match_metavariable(&self, ctx: &mut MatchContext, metavar: &str, node: &dyn AstNode) -> Result<bool> {
         if let Some(text) = node.text() {
-            self.metavar_manager.bind(metavar.to_string(), text.to_string(), node)
+            ctx.metavars.bind(metavar.to_string(), text.to_string(), node)
         } else {
             Ok(false)
         }
     }
 
-    /// Match ellipsis metavariable
-    fn match_ellipsis_metavariable(&mut self, metavar: &str, node: &dyn AstNode) -> Result<bool> {
+    /// Match ellipsis metavariable on its own, standing for the whole node; in argument
+    /// lists and blocks it spans any number of children instead
+    fn match_ellipsis_metavariable(&self, ctx: &mut MatchContext, metavar: &str, node: &dyn AstNode) -> Result<bool> {
         if let Some(text) = node.text() {
-            self.metavar_manager.bind(metavar.to_string(), text.to_string(), node)
+            ctx.metavars.bind(metavar.to_string(), text.to_string(), node)
         } else {
             // Ellipsis can match empty content
-            self.metavar_manager.bind(metavar.to_string(), "".to_string(), node)
+            ctx.metavars.bind(metavar.to_string(), "".to_string(), node)
         }
     }
 
@@ -289,10 +518,15 @@ impl AdvancedSemgrepMatcher {
     }
 
     /// Match sequence of patterns
-    fn match_sequence(&mut self, patterns: &[ParsedPattern], node: &dyn AstNode, depth: usize) -> Result<bool> {
-        // For now, just check if all patterns match the current node
+    fn match_sequence(&self, ctx: &mut MatchContext, patterns: &[ParsedPattern], node: &dyn AstNode, depth: usize) -> Result<bool> {
+        // Every pattern matches the current node; the argument list or block of a call or
+        // statement pattern matches the children of its arguments or body
         for pattern in patterns {
-            if !self.match_parsed_pattern(pattern, node, depth + 1)? {
+            let matched = match pattern {
+                ParsedPattern::Children(elements) => ellipsis::match_trailing_children(&mut ChildSearch { matcher: self, ctx: &mut *ctx, depth }, elements, node)?,
+                _ => self.match_parsed_pattern(ctx, pattern, node, depth + 1)?,
+            };
+            if !matched {
                 return Ok(false);
             }
         }
@@ -300,13 +534,13 @@ impl AdvancedSemgrepMatcher {
     }
 
     /// Match alternative patterns
-    fn match_alternative(&mut self, patterns: &[ParsedPattern], node: &dyn AstNode, depth: usize) -> Result<bool> {
+    fn match_alternative(&self, ctx: &mut MatchContext, patterns: &[ParsedPattern], node: &dyn AstNode, depth: usize) -> Result<bool> {
         for pattern in patterns {
-            let snapshot = self.metavar_manager.snapshot();
-            if self.match_parsed_pattern(pattern, node, depth + 1)? {
+            let snapshot = ctx.metavars.snapshot();
+            if self.match_parsed_pattern(ctx, pattern, node, depth + 1)? {
                 return Ok(true);
             }
-            self.metavar_manager.restore(snapshot);
+            ctx.metavars.restore(snapshot);
         }
         Ok(false)
     }
@@ -591,6 +825,45 @@ impl AdvancedSemgrepMatcher {
     }
 }
 
+/// Most simple patterns whose parsed spellings are kept. Bounded because a long-running
+/// server compiles the patterns of every rule submitted to it.
+const SPELLING_CACHE_CAPACITY: usize = 4096;
+
+/// Cache key of the spellings of a pattern
+type SpellingKey = (String, EquivalenceConfig);
+
+/// Parsed spellings of a simple pattern under `equivalences`, the pattern as written
+/// first, shared by all matchers. The engine creates a matcher for each rule and file and
+/// simple patterns are tried at every node, so without the cache a scan would parse each
+/// pattern once per node. Pattern parsing does not depend on the language of the code.
+fn compiled_spellings(parser: &PatternParser, pattern: &str, equivalences: EquivalenceConfig) -> Result<Arc<[ParsedPattern]>> {
+    static CACHE: OnceLock<SharedLruCache<SpellingKey, Arc<[ParsedPattern]>>> = OnceLock::new();
+    let cache = CACHE.get_or_init(|| SharedLruCache::new(SPELLING_CACHE_CAPACITY));
+    let key = (pattern.to_string(), equivalences);
+    if let Some(spellings) = cache.get(&key) {
+        return Ok(spellings);
+    }
+
+    // A pattern that does not parse is an error each time; a respelling the pattern
+    // parser cannot read matches nothing
+    let mut spellings = vec![parser.parse(pattern)?];
+    spellings.extend(equivalence::equivalent_patterns(pattern, &equivalences).iter().skip(1).filter_map(|variant| parser.parse(variant).ok()));
+    let spellings: Arc<[ParsedPattern]> = spellings.into();
+    cache.insert(key, spellings.clone());
+    Ok(spellings)
+}
+
+/// The node a metavariable was bound to: the node or descendant of `node` with the
+/// bound type and text, at the bound location when it is known
+fn find_bound_node<'a>(node: &'a dyn AstNode, binding: &MetavarBinding) -> Option<&'a dyn AstNode> {
+    // The location tells apart several nodes with the same code
+    let located = binding.location.is_none_or(|location| node.location() == Some(location));
+    if located && node.node_type() == binding.node_type && node.text() == Some(binding.value.as_str()) {
+        return Some(node);
+    }
+    (0..node.child_count()).filter_map(|i| node.child(i)).find_map(|child| find_bound_node(child, binding))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -598,6 +871,7 @@ mod tests {
     use astgrep_ast::UniversalNode;
 
     // Mock AST node for testing
+    #[derive(Clone)]
     struct MockNode {
         text: Option<String>,
         children: Vec<MockNode>,
@@ -626,63 +900,192 @@ mod tests {
         fn child(&self, index: usize) -> Option<&dyn AstNode> {
             self.children.get(index).map(|c| c as &dyn AstNode)
         }
+        fn location(&self) -> Option<(usize, usize, usize, usize)> { None }
         fn clone_node(&self) -> Box<dyn AstNode> {
-            Box::new(MockNode {
-                text: self.text.clone(),
-                children: self.children.iter().map(|c| MockNode {
-                    text: c.text.clone(),
-                    children: c.children.clone(),
-                }).collect(),
-            })
+            Box::new(self.clone())
         }
     }
 
     #[test]
     fn test_pattern_not_regex() {
-        let mut matcher = AdvancedSemgrepMatcher::new();
+        let matcher = AdvancedSemgrepMatcher::new();
 
         // Create a pattern that should NOT match "test_function"
         let pattern = SemgrepPattern {
             pattern_type: PatternType::NotRegex("test_.*".to_string()),
             conditions: Vec::new(),
             focus: None,
+            metavariable_pattern: None,
         };
 
         let test_node = MockNode::new("test_function");
         let regular_node = MockNode::new("regular_function");
 
         // Should not match test_function (matches the regex, so not-regex is false)
-        assert!(!matcher.matches_pattern(&pattern, &test_node).unwrap());
+        assert!(!matcher.matches_pattern(&mut MatchContext::new(), &pattern, &test_node, &[]).unwrap());
 
         // Should match regular_function (doesn't match the regex, so not-regex is true)
-        assert!(matcher.matches_pattern(&pattern, &regular_node).unwrap());
+        assert!(matcher.matches_pattern(&mut MatchContext::new(), &pattern, &regular_node, &[]).unwrap());
+    }
+
+    fn simple(pattern: &str) -> SemgrepPattern {
+        SemgrepPattern::simple(pattern.to_string())
+    }
+
+    fn all(patterns: Vec<SemgrepPattern>) -> SemgrepPattern {
+        SemgrepPattern { pattern_type: PatternType::All(patterns), conditions: Vec::new(), focus: None, metavariable_pattern: None }
+    }
+
+    fn not_inside(pattern: SemgrepPattern) -> SemgrepPattern {
+        SemgrepPattern { pattern_type: PatternType::NotInside(Box::new(pattern)), conditions: Vec::new(), focus: None, metavariable_pattern: None }
+    }
+
+    fn matched_texts(pattern: &SemgrepPattern, root: &MockNode) -> Vec<String> {
+        let matcher = AdvancedSemgrepMatcher::new();
+        matcher.find_matches(pattern, root).unwrap().iter().filter_map(|m| m.node.text().map(str::to_string)).collect()
+    }
+
+    #[test]
+    fn test_pattern_inside_checks_ancestors() {
+        // A multi-line class holding one call, and the same call outside of it
+        let class = MockNode::with_children("class Safe {\n  fn f() {\n    eval(x)\n  }\n}", vec![
+            MockNode::new("class Safe"),
+            MockNode::with_children("fn f() {\n    eval(x)\n  }", vec![MockNode::new("eval(x)")]),
+        ]);
+        let root = MockNode::with_children("module", vec![class, MockNode::new("eval(y)")]);
+
+        let inside = all(vec![simple("eval"), SemgrepPattern::inside(simple("class Safe"))]);
+        assert_eq!(matched_texts(&inside, &root), vec!["eval(x)"]);
+
+        let not_inside = all(vec![simple("eval"), not_inside(simple("class Safe"))]);
+        assert_eq!(matched_texts(&not_inside, &root), vec!["eval(y)"]);
+
+        // A descendant matching the enclosing pattern does not make a node inside it
+        let root = MockNode::with_children("eval(z)", vec![MockNode::new("class Safe")]);
+        assert!(matched_texts(&inside, &root).is_empty());
     }
 
     #[test]
     fn test_pattern_not_inside() {
-        let mut matcher = AdvancedSemgrepMatcher::new();
+        let matcher = AdvancedSemgrepMatcher::new();
+        let pattern = not_inside(simple("class"));
 
-        // Create inner pattern for class context
-        let inner_pattern = SemgrepPattern {
-            pattern_type: PatternType::Simple("class".to_string()),
-            conditions: Vec::new(),
-            focus: None,
-        };
+        let class_node = MockNode::new("class");
+        let function_node = MockNode::new("function");
+        assert!(matcher.matches_pattern(&mut MatchContext::new(), &pattern, &function_node, &[]).unwrap());
+        assert!(!matcher.matches_pattern(&mut MatchContext::new(), &pattern, &function_node, &[&class_node]).unwrap());
+    }
+
+    /// Splits code into words and remembers what it parsed
+    struct WordParser {
+        parsed: std::sync::Mutex<Vec<String>>,
+    }
+
+    impl LanguageParser for WordParser {
+        fn parse(&self, source: &str, _file_path: &Path) -> Result<Box<dyn AstNode>> {
+            self.parsed.lock().unwrap().push(source.to_string());
+            Ok(Box::new(MockNode::with_children(source, source.split_whitespace().map(MockNode::new).collect())))
+        }
+
+        fn language(&self) -> Language {
+            Language::Sql
+        }
+    }
+
+    fn metavariable_pattern(metavariable: &str, patterns: &[&str], language: Option<&str>) -> SemgrepPattern {
+        let mut spec = MetavariablePattern::with_patterns(metavariable.to_string(), patterns.iter().map(|p| p.to_string()).collect());
+        spec.language = language.map(str::to_string);
+        simple(metavariable).with_metavariable_pattern(spec)
+    }
+
+    #[test]
+    fn test_metavariable_pattern_matches_bound_subtree() {
+        let root = MockNode::with_children("login(user, hash(password))", vec![
+            MockNode::new("user"),
+            MockNode::with_children("hash(password)", vec![MockNode::new("hash"), MockNode::new("password")]),
+        ]);
+        assert_eq!(matched_texts(&metavariable_pattern("$ARG", &["hash"], None), &root), vec!["hash"]);
+        assert!(matched_texts(&metavariable_pattern("$ARG", &["md5"], None), &root).is_empty());
+
+        let mut pattern = metavariable_pattern("$ARG", &["password"], None);
+        pattern.metavariable_pattern.as_mut().unwrap().regex = Some("^hash\\(".to_string());
+        assert_eq!(matched_texts(&pattern, &root), vec!["hash(password)"]);
+    }
+
+    #[test]
+    fn test_metavariable_pattern_parses_embedded_language() {
+        let parser = Arc::new(WordParser { parsed: Default::default() });
+        let root = MockNode::new("\"SELECT * FROM users\"");
+        let pattern = metavariable_pattern("$Q", &["FROM users"], Some("sql"));
+
+        let matcher = AdvancedSemgrepMatcher::new().with_parser(parser.clone());
+        assert!(matcher.has_match(&pattern, &root).unwrap());
+        assert_eq!(*parser.parsed.lock().unwrap(), vec!["SELECT * FROM users"]);
+
+        // Without a parser for the language, and for `generic`, the text is matched
+        assert!(AdvancedSemgrepMatcher::new().has_match(&pattern, &root).unwrap());
+        assert!(AdvancedSemgrepMatcher::new().has_match(&metavariable_pattern("$Q", &["SELECT"], Some("generic")), &root).unwrap());
+        assert!(!AdvancedSemgrepMatcher::new().has_match(&metavariable_pattern("$Q", &["DELETE"], Some("generic")), &root).unwrap());
+    }
+
+    #[test]
+    fn test_deep_expression_with_focus() {
+        let root = MockNode::with_children("exec(\"sh \" + build(input))", vec![
+            MockNode::new("exec"),
+            MockNode::with_children("(\"sh \" + build(input))", vec![
+                MockNode::with_children("\"sh \" + build(input)", vec![
+                    MockNode::new("\"sh \""),
+                    MockNode::with_children("build(input)", vec![
+                        MockNode::new("build"),
+                        MockNode::with_children("(input)", vec![MockNode::new("input")]),
+                    ]),
+                ]),
+            ]),
+        ]);
+        assert_eq!(matched_texts(&simple("exec(<... input ...>)"), &root), vec!["exec(\"sh \" + build(input))"]);
+        assert!(matched_texts(&simple("exec(<... other ...>)"), &root).is_empty());
+
+        let mut pattern = simple("exec(<... build($X) ...>)");
+        pattern.focus = Some(vec!["$X".to_string()]);
+        let matches = AdvancedSemgrepMatcher::new().find_matches(&pattern, &root).unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].node.text(), Some("input"));
+        assert_eq!(matches[0].bindings["X"], "input");
+    }
+
+    #[test]
+    fn test_pattern_spellings_are_compiled_once() {
+        let parser = PatternParser::new();
+        let first = compiled_spellings(&parser, "$X == cached_null", EquivalenceConfig::default()).unwrap();
+        let second = compiled_spellings(&parser, "$X == cached_null", EquivalenceConfig::default()).unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!(first.len(), 2);
+
+        let strict = compiled_spellings(&parser, "$X == cached_null", EquivalenceConfig::strict()).unwrap();
+        assert_eq!(strict.len(), 1);
+        assert!(compiled_spellings(&parser, "$", EquivalenceConfig::default()).is_err());
+    }
 
-        // Create not-inside pattern
+    #[test]
+    fn test_matcher_is_shared_across_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<AdvancedSemgrepMatcher>();
+
+        let matcher = AdvancedSemgrepMatcher::new();
         let pattern = SemgrepPattern {
-            pattern_type: PatternType::NotInside(Box::new(inner_pattern)),
+            pattern_type: PatternType::Regex("eval".to_string()),
             conditions: Vec::new(),
             focus: None,
+            metavariable_pattern: None,
         };
-
-        // Create test nodes
-        let class_node = MockNode::new("class");
-        let function_node = MockNode::new("function");
-        let nested_function = MockNode::with_children("class", vec![MockNode::new("function")]);
-
-        // Function inside class should not match (inside class context)
-        // Note: This is a simplified test - real implementation would need proper AST traversal
-        assert!(matcher.matches_pattern(&pattern, &function_node).unwrap());
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                let (matcher, pattern) = (&matcher, &pattern);
+                scope.spawn(move || {
+                    let root = MockNode::with_children("f", vec![MockNode::new("eval(x)"), MockNode::new("print(x)")]);
+                    assert_eq!(matcher.find_matches(pattern, &root).unwrap().len(), 1);
+                });
+            }
+        });
     }
 }
@@ -0,0 +1,305 @@
+//! Variable-length matching over child node sequences
+//!
+//! `...` and `$...NAME` match zero or more consecutive children of a node: the arguments
+//! of a call in `foo(..., password, ...)` or the statements of a block in
+//! `{ ... sink($X); ... }`. Matching backtracks over how many children each ellipsis
+//! takes, restoring the metavariable bindings of every failed attempt, so
+//! `foo(..., $X, $X, ...)` finds two equal adjacent arguments wherever they are.
+//!
+//! The matchers keep their bindings differently; each implements [`ChildMatcher`] over
+//! its own state and shares the sequence algorithm here.
+
+use crate::parser::ParsedPattern;
+use astgrep_core::{AstNode, Result};
+
+/// What a matcher provides to match a pattern sequence against child nodes
+pub(crate) trait ChildMatcher {
+    type Snapshot;
+
+    /// The current metavariable bindings
+    fn snapshot(&self) -> Self::Snapshot;
+
+    /// Return to bindings taken by [`ChildMatcher::snapshot`]
+    fn restore(&mut self, snapshot: Self::Snapshot);
+
+    /// Match one pattern against one node
+    fn match_node(&mut self, pattern: &ParsedPattern, node: &dyn AstNode) -> Result<bool>;
+
+    /// Bind an ellipsis metavariable to a run of children of `parent`, possibly empty
+    fn bind_nodes(&mut self, metavar: &str, nodes: &[&dyn AstNode], parent: &dyn AstNode) -> Result<bool>;
+
+    /// Called on every attempt of the backtracking loops; an error, such as
+    /// `AnalysisError::Cancelled`, ends the match
+    fn checkpoint(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Match `elements` against all children of `parent`, in order. Literal elements must be
+/// the whole text of a child, and an element that does not match a child is tried on the
+/// child's only child, so `sink($X)` matches the statement `sink(x);`.
+pub(crate) fn match_children<M: ChildMatcher>(matcher: &mut M, elements: &[ParsedPattern], parent: &dyn AstNode) -> Result<bool> {
+    let children = children(parent);
+    match_from(matcher, elements, &children, parent, Mode::List)
+}
+
+/// Match the list of a call or block pattern `foo(...)` against `node`: the arguments or
+/// body are the last child of the call or statement, or for `if`/`else` and the like an
+/// earlier non-leaf child.
+pub(crate) fn match_trailing_children<M: ChildMatcher>(matcher: &mut M, elements: &[ParsedPattern], node: &dyn AstNode) -> Result<bool> {
+    let children = children(node);
+    for (index, child) in children.iter().enumerate().rev() {
+        if index + 1 < children.len() && child.child_count() == 0 {
+            continue;
+        }
+        let snapshot = matcher.snapshot();
+        if match_children(matcher, elements, *child)? {
+            return Ok(true);
+        }
+        matcher.restore(snapshot);
+    }
+    Ok(false)
+}
+
+/// Match `patterns` against consecutive children of `parent`, starting at any child; each
+/// pattern takes one child and ellipses any number of them
+pub(crate) fn match_window<M: ChildMatcher>(matcher: &mut M, patterns: &[ParsedPattern], parent: &dyn AstNode) -> Result<bool> {
+    let children = children(parent);
+    for start in 0..=children.len() {
+        matcher.checkpoint()?;
+        let snapshot = matcher.snapshot();
+        if match_from(matcher, patterns, &children[start..], parent, Mode::Window)? {
+            return Ok(true);
+        }
+        matcher.restore(snapshot);
+    }
+    Ok(false)
+}
+
+/// Match a deep expression `<... inner ...>`: `node` or any node below it matches `inner`,
+/// tried in pre-order so the outermost match wins and its bindings are kept
+pub(crate) fn match_deep<M: ChildMatcher>(matcher: &mut M, inner: &ParsedPattern, node: &dyn AstNode) -> Result<bool> {
+    let mut stack = vec![node];
+    while let Some(current) = stack.pop() {
+        matcher.checkpoint()?;
+        let snapshot = matcher.snapshot();
+        if matcher.match_node(inner, current)? {
+            return Ok(true);
+        }
+        matcher.restore(snapshot);
+        stack.extend(children(current).into_iter().rev());
+    }
+    Ok(false)
+}
+
+fn is_ellipsis(pattern: &ParsedPattern) -> bool {
+    matches!(pattern, ParsedPattern::Wildcard | ParsedPattern::EllipsisMetavariable(_))
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Mode {
+    /// Every child is matched and elements follow the list rules of `match_children`
+    List,
+    /// Children after the last pattern are left over
+    Window,
+}
+
+fn children(node: &dyn AstNode) -> Vec<&dyn AstNode> {
+    (0..node.child_count()).filter_map(|i| node.child(i)).collect()
+}
+
+fn match_from<M: ChildMatcher>(
+    matcher: &mut M,
+    patterns: &[ParsedPattern],
+    children: &[&dyn AstNode],
+    parent: &dyn AstNode,
+    mode: Mode,
+) -> Result<bool> {
+    let (pattern, rest) = match patterns.split_first() {
+        Some(split) => split,
+        None => return Ok(mode == Mode::Window || children.is_empty()),
+    };
+
+    if is_ellipsis(pattern) {
+        // Fewest children first, so bindings and reported spans stay as small as possible
+        for taken in 0..=children.len() {
+            matcher.checkpoint()?;
+            let snapshot = matcher.snapshot();
+            let bound = match pattern {
+                ParsedPattern::EllipsisMetavariable(metavar) => matcher.bind_nodes(metavar, &children[..taken], parent)?,
+                _ => true,
+            };
+            if bound && match_from(matcher, rest, &children[taken..], parent, mode)? {
+                return Ok(true);
+            }
+            matcher.restore(snapshot);
+        }
+        return Ok(false);
+    }
+
+    let (child, remaining) = match children.split_first() {
+        Some(split) => split,
+        None => return Ok(false),
+    };
+    let snapshot = matcher.snapshot();
+    let matched = match mode {
+        Mode::List => match_element(matcher, pattern, *child)?,
+        Mode::Window => matcher.match_node(pattern, *child)?,
+    };
+    if matched && match_from(matcher, rest, remaining, parent, mode)? {
+        return Ok(true);
+    }
+    matcher.restore(snapshot);
+    Ok(false)
+}
+
+/// Match a list element against a child, or the only child of a wrapper such as an
+/// expression statement
+fn match_element<M: ChildMatcher>(matcher: &mut M, element: &ParsedPattern, node: &dyn AstNode) -> Result<bool> {
+    let mut current = node;
+    loop {
+        let snapshot = matcher.snapshot();
+        if element_matches(matcher, element, current)? {
+            return Ok(true);
+        }
+        matcher.restore(snapshot);
+        match (current.child_count(), current.child(0)) {
+            (1, Some(only)) => current = only,
+            _ => return Ok(false),
+        }
+    }
+}
+
+fn element_matches<M: ChildMatcher>(matcher: &mut M, element: &ParsedPattern, node: &dyn AstNode) -> Result<bool> {
+    let text = node.text().map(str::trim);
+    match element {
+        ParsedPattern::Literal(literal) => Ok(text.is_some_and(|text| text == literal || unquote(text) == Some(literal.as_str()))),
+        // `sink($X)` only matches children that start with the callee
+        ParsedPattern::Sequence(items) => match items.first() {
+            Some(ParsedPattern::Literal(head)) if !text.is_some_and(|text| text.starts_with(head.as_str())) => Ok(false),
+            _ => matcher.match_node(element, node),
+        },
+        _ => matcher.match_node(element, node),
+    }
+}
+
+/// The contents of a quoted string literal
+pub(crate) fn unquote(text: &str) -> Option<&str> {
+    ['"', '\'', '`'].iter().find_map(|&quote| text.strip_prefix(quote)?.strip_suffix(quote))
+}
+
+/// The value bound to an ellipsis metavariable: the texts of its children joined by `, `
+pub(crate) fn joined_text(nodes: &[&dyn AstNode]) -> String {
+    nodes.iter().filter_map(|node| node.text()).map(str::trim).collect::<Vec<_>>().join(", ")
+}
+
+/// The span from the start of the first of `nodes` to the end of the last
+pub(crate) fn joined_location(nodes: &[&dyn AstNode]) -> Option<(usize, usize, usize, usize)> {
+    let (start_line, start_col, ..) = nodes.first()?.location()?;
+    let (.., end_line, end_col) = nodes.last()?.location()?;
+    Some((start_line, start_col, end_line, end_col))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::AdvancedSemgrepMatcher;
+    use astgrep_ast::{NodeType, UniversalNode};
+    use astgrep_core::SemgrepPattern;
+
+    fn node(node_type: NodeType, text: &str, children: Vec<UniversalNode>) -> UniversalNode {
+        children.into_iter().fold(UniversalNode::new(node_type).with_text(text.to_string()), UniversalNode::add_child)
+    }
+
+    fn ident(name: &str) -> UniversalNode {
+        node(NodeType::Identifier, name, Vec::new())
+    }
+
+    /// A call shaped as the tree-sitter conversion shapes it: callee, then an argument list
+    fn call(callee: &str, args: &[&str]) -> UniversalNode {
+        let text = format!("{}({})", callee, args.join(", "));
+        let arguments = node(NodeType::Unknown, &format!("({})", args.join(", ")), args.iter().map(|a| ident(a)).collect());
+        node(NodeType::CallExpression, &text, vec![ident(callee), arguments])
+    }
+
+    fn bindings(pattern: &str, root: &UniversalNode) -> Vec<std::collections::HashMap<String, String>> {
+        let pattern = SemgrepPattern::simple(pattern.to_string());
+        AdvancedSemgrepMatcher::new().find_matches(&pattern, root).unwrap().into_iter().map(|m| m.bindings).collect()
+    }
+
+    #[test]
+    fn test_ellipsis_spans_arguments() {
+        let pattern = "login(..., password, ...)";
+        assert_eq!(bindings(pattern, &call("login", &["user", "password", "true"])).len(), 1);
+        assert_eq!(bindings(pattern, &call("login", &["password"])).len(), 1);
+        assert!(bindings(pattern, &call("login", &["user"])).is_empty());
+        assert!(bindings(pattern, &call("login", &["passwords"])).is_empty());
+        assert_eq!(bindings("login()", &call("login", &[])).len(), 1);
+        assert!(bindings("login()", &call("login", &["user"])).is_empty());
+
+        let found = bindings("exec($CMD, $...REST)", &call("exec", &["cmd", "a", "b"]));
+        assert_eq!(found[0]["CMD"], "cmd");
+        assert_eq!(found[0]["REST"], "a, b");
+        let found = bindings("exec($CMD, $...REST)", &call("exec", &["cmd"]));
+        assert_eq!(found[0]["REST"], "");
+    }
+
+    #[test]
+    fn test_bindings_carry_locations() {
+        // exec(cmd, a, b) on line 3, each argument one column wide after ", "
+        let arg = |name: &str, col: usize| ident(name).with_location(3, col, 3, col + name.len());
+        let arguments = node(NodeType::Unknown, "(cmd, a, b)", vec![arg("cmd", 6), arg("a", 11), arg("b", 14)]);
+        let root = node(NodeType::CallExpression, "exec(cmd, a, b)", vec![ident("exec"), arguments]).with_location(3, 1, 3, 16);
+
+        let pattern = SemgrepPattern::simple("exec($CMD, $...REST)".to_string());
+        let found = AdvancedSemgrepMatcher::new().find_matches(&pattern, &root).unwrap();
+        let cmd = &found[0].metavariables["CMD"];
+        assert_eq!((cmd.text.as_str(), cmd.node_type.as_str(), cmd.location), ("cmd", "identifier", Some((3, 6, 3, 9))));
+        let rest = &found[0].metavariables["REST"];
+        assert_eq!((rest.text.as_str(), rest.location), ("a, b", Some((3, 11, 3, 15))));
+
+        // Focus lands on the bound node, not an earlier one with the same code
+        let arguments = node(NodeType::Unknown, "(x, y, x)", vec![arg("x", 6), arg("y", 9), arg("x", 12)]);
+        let root = node(NodeType::CallExpression, "pair(x, y, x)", vec![ident("pair"), arguments]);
+        let mut pattern = SemgrepPattern::simple("pair(..., y, $B)".to_string());
+        pattern.focus = Some(vec!["$B".to_string()]);
+        let found = AdvancedSemgrepMatcher::new().find_matches(&pattern, &root).unwrap();
+        assert_eq!(found[0].node.location(), Some((3, 12, 3, 13)));
+    }
+
+    #[test]
+    fn test_ellipsis_backtracks() {
+        let found = bindings("f(..., $X, $X, ...)", &call("f", &["a", "b", "b", "c"]));
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0]["X"], "b");
+        assert!(bindings("f(..., $X, $X, ...)", &call("f", &["a", "b", "a"])).is_empty());
+    }
+
+    #[test]
+    fn test_ellipsis_spans_statements() {
+        let statement = |callee: &str, arg: &str| {
+            node(NodeType::ExpressionStatement, &format!("{}({});", callee, arg), vec![call(callee, &[arg])])
+        };
+        let if_statement = |statements: Vec<UniversalNode>| {
+            let condition = node(NodeType::SqlExpression, "(ok)", vec![ident("ok")]);
+            let body = node(NodeType::BlockStatement, "{ ... }", statements);
+            node(NodeType::IfStatement, "if (ok) { ... }", vec![ident("if"), condition, body])
+        };
+        let pattern = "if (...) { ... sink($X); ... }";
+
+        let found = bindings(pattern, &if_statement(vec![statement("log", "a"), statement("sink", "data"), statement("log", "b")]));
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0]["X"], "data");
+        assert_eq!(bindings(pattern, &if_statement(vec![statement("sink", "data")])).len(), 1);
+        assert!(bindings(pattern, &if_statement(vec![statement("log", "a")])).is_empty());
+    }
+
+    #[test]
+    fn test_cancelled_search_fails() {
+        let cancellation = astgrep_core::CancellationToken::new();
+        cancellation.cancel();
+        let pattern = SemgrepPattern::simple("f(..., $X, $X, ...)".to_string());
+        let matcher = AdvancedSemgrepMatcher::new().with_cancellation(cancellation);
+        let result = matcher.find_matches(&pattern, &call("f", &["a", "b", "b", "c"]));
+        assert!(matches!(result, Err(astgrep_core::AnalysisError::Cancelled)));
+    }
+}
@@ -0,0 +1,490 @@
+//! Operator equivalences between patterns and code
+//!
+//! `$X == null` should also find `null == $X`, and `a + b + c` should find the sum however
+//! the code parenthesizes it. Rather than teach each matcher these rules, a pattern is
+//! expanded into its equivalent spellings: the operands of `==`, `!=`, `===` and `!==`
+//! swapped, and chains of `+`, `*`, `&&` and `||` regrouped in every way. A match of any
+//! spelling is a match of the pattern.
+//!
+//! Only the outermost rewritable expression of each region is expanded, and chains of
+//! more than [`MAX_CHAIN_OPERANDS`] operands are left as written.
+
+use serde::{Deserialize, Serialize};
+
+/// Longest operator chain that is regrouped; longer chains have too many groupings
+pub const MAX_CHAIN_OPERANDS: usize = 5;
+
+/// Most spellings a pattern is expanded into
+const MAX_VARIANTS: usize = 64;
+
+/// Which operator equivalences the matchers apply
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EquivalenceConfig {
+    /// Match the operands of equality operators in either order. Default: `true`
+    pub commutative: bool,
+    /// Match chains of associative operators however they are parenthesized. Default: `true`
+    pub associative: bool,
+}
+
+impl Default for EquivalenceConfig {
+    fn default() -> Self {
+        Self { commutative: true, associative: true }
+    }
+}
+
+impl EquivalenceConfig {
+    /// Match patterns exactly as written
+    pub fn strict() -> Self {
+        Self { commutative: false, associative: false }
+    }
+
+    /// Enable or disable commutative equality operators
+    pub fn with_commutative(mut self, enabled: bool) -> Self {
+        self.commutative = enabled;
+        self
+    }
+
+    /// Enable or disable regrouping of associative operator chains
+    pub fn with_associative(mut self, enabled: bool) -> Self {
+        self.associative = enabled;
+        self
+    }
+}
+
+/// The spellings of `pattern` under `config`, the pattern itself first
+pub fn equivalent_patterns(pattern: &str, config: &EquivalenceConfig) -> Vec<String> {
+    let mut variants = vec![pattern.to_string()];
+    if !config.commutative && !config.associative {
+        return variants;
+    }
+
+    let lexemes = lex(pattern);
+    let mut rewrites = Vec::new();
+    collect_rewrites(pattern, &lexemes, config, &mut rewrites);
+
+    // Rewrites do not overlap; apply the last first so earlier byte ranges stay valid
+    rewrites.sort_by_key(|rewrite| std::cmp::Reverse(rewrite.start));
+    for rewrite in rewrites {
+        let mut expanded = Vec::new();
+        for variant in &variants {
+            for alternative in std::iter::once(&variant[rewrite.start..rewrite.end]).chain(rewrite.alternatives.iter().map(String::as_str)) {
+                if expanded.len() == MAX_VARIANTS {
+                    break;
+                }
+                expanded.push(format!("{}{}{}", &variant[..rewrite.start], alternative, &variant[rewrite.end..]));
+            }
+        }
+        variants = expanded;
+    }
+    let mut seen = std::collections::HashSet::new();
+    variants.retain(|variant| seen.insert(variant.clone()));
+    variants
+}
+
+/// Other spellings of the expression at `start..end` of the pattern
+struct Rewrite {
+    start: usize,
+    end: usize,
+    alternatives: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    /// Identifier, literal, metavariable or ellipsis
+    Atom,
+    Open,
+    Close,
+    Operator,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Lexeme {
+    kind: Kind,
+    start: usize,
+    end: usize,
+}
+
+/// Operators of more than one character, longest first
+const OPERATORS: &[&str] = &[
+    "===", "!==", ">>=", "<<=", "==", "!=", "<=", ">=", "&&", "||", "<<", ">>", "++", "--", "+=", "-=", "*=", "/=", "%=",
+    "&=", "|=", "^=", "->", "=>", "::", "??",
+];
+
+fn lex(pattern: &str) -> Vec<Lexeme> {
+    let bytes = pattern.as_bytes();
+    let mut lexemes = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = bytes[i];
+        let start = i;
+        let kind = if c.is_ascii_whitespace() {
+            i += 1;
+            continue;
+        } else if c == b'"' || c == b'\'' || c == b'`' {
+            i += 1;
+            while i < bytes.len() && bytes[i] != c {
+                i += if bytes[i] == b'\\' { 2 } else { 1 };
+            }
+            i = (i + 1).min(bytes.len());
+            Kind::Atom
+        } else if pattern[i..].starts_with("...") {
+            i += 3;
+            Kind::Atom
+        } else if c == b'$' && pattern[i + 1..].starts_with("...") {
+            i += 4;
+            while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+                i += 1;
+            }
+            Kind::Atom
+        } else if c.is_ascii_alphanumeric() || c == b'_' || c == b'$' || !c.is_ascii() {
+            i += 1;
+            while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_' || !bytes[i].is_ascii()) {
+                i += 1;
+            }
+            Kind::Atom
+        } else if matches!(c, b'(' | b'[' | b'{') {
+            i += 1;
+            Kind::Open
+        } else if matches!(c, b')' | b']' | b'}') {
+            i += 1;
+            Kind::Close
+        } else {
+            i += OPERATORS.iter().find(|op| pattern[i..].starts_with(**op)).map_or(1, |op| op.len());
+            Kind::Operator
+        };
+        lexemes.push(Lexeme { kind, start, end: i });
+    }
+    lexemes
+}
+
+/// Binding strength of a binary operator; higher binds more loosely
+fn precedence(op: &str) -> u8 {
+    match op {
+        "." | "->" | "::" => 1,
+        "*" | "/" | "%" => 3,
+        "+" | "-" => 4,
+        "<<" | ">>" => 5,
+        "<" | "<=" | ">" | ">=" => 6,
+        "==" | "!=" | "===" | "!==" => 7,
+        "&" => 8,
+        "^" => 9,
+        "|" => 10,
+        "&&" => 11,
+        "||" => 12,
+        "?" | ":" | "??" => 13,
+        "," | ";" => 15,
+        _ => 14,
+    }
+}
+
+/// A top-level element of a bracket level: an operand piece or a binary operator
+#[derive(Debug, Clone, Copy)]
+enum Item<'p> {
+    Operand { start: usize, end: usize },
+    Operator { op: &'p str, start: usize },
+    /// Where juxtaposed operands (`return x`, `if (c) { ... }`) separate expressions
+    Break,
+}
+
+fn collect_rewrites(pattern: &str, lexemes: &[Lexeme], config: &EquivalenceConfig, rewrites: &mut Vec<Rewrite>) {
+    // Group the lexemes of this level into operands, operators and breaks, and remember
+    // the contents of each bracket to search them too
+    let mut items: Vec<Item> = Vec::new();
+    let mut nested: Vec<&[Lexeme]> = Vec::new();
+    let mut prefix_start = None;
+    let mut i = 0;
+    while i < lexemes.len() {
+        let lexeme = lexemes[i];
+        let ends_operand = matches!(items.last(), Some(Item::Operand { .. }));
+        match lexeme.kind {
+            Kind::Atom | Kind::Open => {
+                let end_index = if lexeme.kind == Kind::Open { matching_close(lexemes, i) } else { i };
+                let end = lexemes.get(end_index).map_or(pattern.len(), |l| l.end);
+                if lexeme.kind == Kind::Open {
+                    nested.push(&lexemes[i + 1..end_index.min(lexemes.len())]);
+                }
+                match items.last_mut() {
+                    // A call, index or block after an operand belongs to it
+                    Some(Item::Operand { end: operand_end, .. }) if lexeme.kind == Kind::Open => *operand_end = end,
+                    _ => {
+                        if ends_operand {
+                            items.push(Item::Break);
+                        }
+                        let start = prefix_start.take().unwrap_or(lexeme.start);
+                        items.push(Item::Operand { start, end });
+                    }
+                }
+                i = end_index + 1;
+            }
+            Kind::Operator => {
+                let op = &pattern[lexeme.start..lexeme.end];
+                match items.last_mut() {
+                    // Postfix increments and member access stay within the operand
+                    Some(Item::Operand { end, .. }) if matches!(op, "++" | "--") => *end = lexeme.end,
+                    _ if ends_operand => items.push(Item::Operator { op, start: lexeme.start }),
+                    // A prefix operator starts the operand that follows it
+                    _ => {
+                        prefix_start.get_or_insert(lexeme.start);
+                    }
+                }
+                i += 1;
+            }
+            Kind::Close => i += 1,
+        }
+        merge_member_access(&mut items);
+    }
+
+    let before = rewrites.len();
+    for op in ["==", "!=", "===", "!==", "+", "*", "&&", "||"] {
+        let commutative = matches!(op, "==" | "!=" | "===" | "!==");
+        if (commutative && !config.commutative) || (!commutative && !config.associative) {
+            continue;
+        }
+        for run in runs(&items, precedence(op)) {
+            if let Some(rewrite) = rewrite_run(pattern, run, op, commutative) {
+                // Keep the outermost of overlapping rewrites
+                let overlaps = |r: &Rewrite| r.start < rewrite.end && rewrite.start < r.end;
+                if rewrites[before..].iter().any(overlaps) {
+                    continue;
+                }
+                rewrites.retain(|r| !(r.start >= rewrite.start && r.end <= rewrite.end));
+                rewrites.push(rewrite);
+            }
+        }
+    }
+
+    for inner in nested {
+        let mut inner_rewrites = Vec::new();
+        collect_rewrites(pattern, inner, config, &mut inner_rewrites);
+        for rewrite in inner_rewrites {
+            if !rewrites.iter().any(|r| r.start < rewrite.end && rewrite.start < r.end) {
+                rewrites.push(rewrite);
+            }
+        }
+    }
+}
+
+/// Merge the last operands if they are joined by `.`, `->` or `::`
+fn merge_member_access(items: &mut Vec<Item>) {
+    let n = items.len();
+    if let [.., Item::Operand { start, .. }, Item::Operator { op, .. }, Item::Operand { end, .. }] = items[..] {
+        if precedence(op) == 1 {
+            items.truncate(n - 3);
+            items.push(Item::Operand { start, end });
+        }
+    }
+}
+
+fn matching_close(lexemes: &[Lexeme], open: usize) -> usize {
+    let mut depth = 0usize;
+    for (index, lexeme) in lexemes.iter().enumerate().skip(open) {
+        match lexeme.kind {
+            Kind::Open => depth += 1,
+            Kind::Close => {
+                depth -= 1;
+                if depth == 0 {
+                    return index;
+                }
+            }
+            _ => {}
+        }
+    }
+    lexemes.len()
+}
+
+/// Maximal runs of items without a break or an operator binding more loosely than
+/// `precedence`
+fn runs<'i, 'p>(items: &'i [Item<'p>], precedence_limit: u8) -> impl Iterator<Item = &'i [Item<'p>]> {
+    items
+        .split(move |item| match item {
+            Item::Break => true,
+            Item::Operator { op, .. } => precedence(op) > precedence_limit,
+            Item::Operand { .. } => false,
+        })
+        .filter(|run| run.len() >= 3)
+}
+
+fn rewrite_run(pattern: &str, run: &[Item], op: &str, commutative: bool) -> Option<Rewrite> {
+    // Every operator at this precedence must be `op`
+    let level = precedence(op);
+    let mut operands = Vec::new();
+    for item in run {
+        match *item {
+            Item::Operand { start, end } => operands.push(pattern[start..end].trim()),
+            Item::Operator { op: other, .. } if precedence(other) == level && other != op => return None,
+            Item::Operator { .. } => {}
+            Item::Break => return None,
+        }
+    }
+    let (start, end) = match (run.first(), run.last()) {
+        (Some(Item::Operand { start, .. }), Some(Item::Operand { end, .. })) => (*start, *end),
+        _ => return None,
+    };
+    // Operands joined by tighter operators (`a * b + c`) are taken whole
+    let operands: Vec<&str> = split_operands(&pattern[start..end], run, start, op);
+
+    let alternatives = if commutative {
+        match operands[..] {
+            [left, right] => vec![format!("{} {} {}", right, op, left)],
+            _ => return None,
+        }
+    } else {
+        let mut flat = Vec::new();
+        for operand in &operands {
+            flatten(operand, op, &mut flat);
+        }
+        if flat.len() < 3 || flat.len() > MAX_CHAIN_OPERANDS {
+            return None;
+        }
+        let original = pattern[start..end].to_string();
+        groupings(&flat, op).into_iter().filter(|g| *g != original).collect()
+    };
+    Some(Rewrite { start, end, alternatives })
+}
+
+/// The operands of the `op` chain at `start` in the pattern, split at the top-level `op`s
+fn split_operands<'p>(text: &'p str, run: &[Item], start: usize, op: &str) -> Vec<&'p str> {
+    let mut operands = Vec::new();
+    let mut from = 0;
+    for item in run {
+        if let Item::Operator { op: other, start: at } = *item {
+            if other == op {
+                operands.push(text[from..at - start].trim());
+                from = at - start + op.len();
+            }
+        }
+    }
+    operands.push(text[from..].trim());
+    operands
+}
+
+/// Append the operands of `operand` to `flat`, opening parentheses around a chain of
+/// the same operator
+fn flatten<'p>(operand: &'p str, op: &str, flat: &mut Vec<&'p str>) {
+    let wrapped = lex(operand);
+    let inner = match operand.strip_prefix('(').and_then(|o| o.strip_suffix(')')) {
+        Some(inner) if matching_close(&wrapped, 0) + 1 == wrapped.len() => inner,
+        _ => {
+            flat.push(operand);
+            return;
+        }
+    };
+    let mut depth = 0usize;
+    let mut same_chain = true;
+    let mut split_at = Vec::new();
+    for lexeme in lex(inner) {
+        match lexeme.kind {
+            Kind::Open => depth += 1,
+            Kind::Close => depth = depth.saturating_sub(1),
+            Kind::Operator if depth == 0 => {
+                let other = &inner[lexeme.start..lexeme.end];
+                if other == op {
+                    split_at.push(lexeme.start);
+                } else if precedence(other) >= precedence(op) {
+                    same_chain = false;
+                }
+            }
+            _ => {}
+        }
+    }
+    if !same_chain || split_at.is_empty() {
+        flat.push(operand);
+        return;
+    }
+    let mut from = 0;
+    for at in split_at {
+        flatten(inner[from..at].trim(), op, flat);
+        from = at + op.len();
+    }
+    flatten(inner[from..].trim(), op, flat);
+}
+
+/// Every way to parenthesize the chain `operands` joined by `op`, the flat chain first
+fn groupings(operands: &[&str], op: &str) -> Vec<String> {
+    if operands.len() == 1 {
+        return vec![operands[0].to_string()];
+    }
+    let separator = format!(" {} ", op);
+    let mut results = Vec::new();
+    // Split the chain into consecutive blocks, at least two; blocks of several operands
+    // are parenthesized and grouped in turn
+    for blocks in compositions(operands.len()) {
+        let mut partial = vec![String::new()];
+        let mut from = 0;
+        for (index, size) in blocks.iter().enumerate() {
+            let block = &operands[from..from + size];
+            from += size;
+            let spellings: Vec<String> = if *size == 1 {
+                vec![block[0].to_string()]
+            } else {
+                groupings(block, op).into_iter().map(|g| format!("({})", g)).collect()
+            };
+            let mut extended = Vec::with_capacity(partial.len() * spellings.len());
+            for prefix in &partial {
+                for spelling in &spellings {
+                    extended.push(if index == 0 { spelling.clone() } else { format!("{}{}{}", prefix, separator, spelling) });
+                }
+            }
+            partial = extended;
+        }
+        results.extend(partial);
+    }
+    results
+}
+
+/// The ways to write `n` as an ordered sum of at least two positive parts, all ones first
+fn compositions(n: usize) -> Vec<Vec<usize>> {
+    fn extend(remaining: usize, current: &mut Vec<usize>, all: &mut Vec<Vec<usize>>) {
+        if remaining == 0 {
+            all.push(current.clone());
+            return;
+        }
+        for part in 1..=remaining {
+            current.push(part);
+            extend(remaining - part, current, all);
+            current.pop();
+        }
+    }
+    let mut all = Vec::new();
+    extend(n, &mut Vec::new(), &mut all);
+    all.retain(|parts| parts.len() >= 2);
+    all
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variants(pattern: &str) -> Vec<String> {
+        equivalent_patterns(pattern, &EquivalenceConfig::default())
+    }
+
+    #[test]
+    fn test_commutative_equality() {
+        assert_eq!(variants("$X == null"), vec!["$X == null", "null == $X"]);
+        assert_eq!(variants("if ($X.get() != null) { ... }"), vec!["if ($X.get() != null) { ... }", "if (null != $X.get()) { ... }"]);
+        assert_eq!(variants("a == b && c"), vec!["a == b && c", "b == a && c"]);
+        // Relational operators are not commutative
+        assert_eq!(variants("$X < 10"), vec!["$X < 10"]);
+        assert_eq!(equivalent_patterns("$X == null", &EquivalenceConfig::strict()), vec!["$X == null"]);
+    }
+
+    #[test]
+    fn test_associative_chains() {
+        assert_eq!(variants("a + b + c"), vec!["a + b + c", "a + (b + c)", "(a + b) + c"]);
+        assert_eq!(variants("a + (b + c)"), vec!["a + (b + c)", "a + b + c", "(a + b) + c"]);
+        assert_eq!(variants("$A && $B && $C").len(), 3);
+        assert_eq!(variants("a + b + c + d").len(), 11);
+        // Subtraction and tighter operators keep their operands whole
+        assert_eq!(variants("a - b + c"), vec!["a - b + c"]);
+        assert_eq!(variants("a * b + c + d"), vec!["a * b + c + d", "a * b + (c + d)", "(a * b + c) + d"]);
+        assert_eq!(variants("exec(a + b + c)").len(), 3);
+        assert_eq!(equivalent_patterns("a + b + c", &EquivalenceConfig::default().with_associative(false)), vec!["a + b + c"]);
+    }
+
+    #[test]
+    fn test_compositions() {
+        assert_eq!(compositions(3), vec![vec![1, 1, 1], vec![1, 2], vec![2, 1]]);
+    }
+}
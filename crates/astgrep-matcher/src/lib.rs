@@ -8,15 +8,21 @@ pub mod metavar;
 pub mod conditions;
 pub mod advanced_matcher;
 pub mod precise_matcher;
+pub mod equivalence;
+pub mod pattern_ast;
+mod ellipsis;
 
 pub use matcher::*;
 pub use parser::*;
 pub use advanced_matcher::*;
 pub use precise_matcher::*;
-pub use metavar::{MetavarBinding, MetavarConstraint, MetavarManager};
+pub use metavar::{is_anonymous_metavar, MetavarBinding, MetavarConstraint, MetavarManager};
 pub use conditions::{ConditionEvaluator, ConditionType, ComparisonOp};
+pub use equivalence::{equivalent_patterns, EquivalenceConfig};
+pub use pattern_ast::{PatternAst, PatternAstMatch};
 
 use astgrep_core::{AstNode, Result};
+use ellipsis::ChildMatcher;
 use std::collections::HashMap;
 
 /// Main pattern matcher interface
@@ -58,6 +64,23 @@ impl PatternMatcher {
         self.match_pattern(&parsed_pattern, node, 0)
     }
 
+    /// Find all nodes under `root` that match a pattern, in pre-order
+    pub fn find_matches(&mut self, pattern: &str, root: &dyn AstNode) -> Result<Vec<MatchResult>> {
+        self.find_matches_iter(pattern, root)?.collect()
+    }
+
+    /// Lazily find nodes that match a pattern; the search stops when the iterator is
+    /// dropped, so `take(n)` or `next()` only visit as much of the tree as needed
+    pub fn find_matches_iter<'m, 'a>(&'m mut self, pattern: &str, root: &'a dyn AstNode) -> Result<PatternMatches<'m, 'a>> {
+        let pattern = PatternParser::new().parse(pattern)?;
+        Ok(PatternMatches { matcher: self, pattern, stack: vec![root], failed: false })
+    }
+
+    /// Whether any node under `root` matches a pattern, stopping at the first match
+    pub fn has_match(&mut self, pattern: &str, root: &dyn AstNode) -> Result<bool> {
+        self.find_matches_iter(pattern, root)?.next().transpose().map(|m| m.is_some())
+    }
+
     /// Get metavariable bindings from the last match
     pub fn get_bindings(&self) -> &HashMap<String, String> {
         &self.metavar_bindings
@@ -84,6 +107,8 @@ impl PatternMatcher {
             ParsedPattern::NodeType(node_type) => self.match_node_type(node_type, node),
             ParsedPattern::Sequence(patterns) => self.match_sequence(patterns, node, depth),
             ParsedPattern::Alternative(patterns) => self.match_alternative(patterns, node, depth),
+            ParsedPattern::Children(elements) => ellipsis::match_children(&mut ChildSearch { matcher: self, depth }, elements, node),
+            ParsedPattern::Deep(inner) => ellipsis::match_deep(&mut ChildSearch { matcher: self, depth }, inner, node),
             ParsedPattern::Wildcard => Ok(true),
         }
     }
@@ -104,39 +129,41 @@ impl PatternMatcher {
     /// Match metavariable
     fn match_metavariable(&mut self, metavar: &str, node: &dyn AstNode) -> Result<bool> {
         if let Some(text) = node.text() {
-            // Check if this metavariable is already bound
-            if let Some(existing_binding) = self.metavar_bindings.get(metavar) {
-                Ok(existing_binding == text)
-            } else {
-                // Bind the metavariable
-                self.metavar_bindings.insert(metavar.to_string(), text.to_string());
-                Ok(true)
-            }
+            Ok(self.bind_metavariable(metavar, text))
         } else {
             Ok(false)
         }
     }
 
-    /// Match ellipsis metavariable (can match zero or more nodes)
+    /// Match ellipsis metavariable on its own, standing for the whole node; in sequences,
+    /// argument lists and blocks it spans zero or more children instead
     fn match_ellipsis_metavariable(&mut self, metavar: &str, node: &dyn AstNode) -> Result<bool> {
-        // For now, treat ellipsis metavariables like regular metavariables
-        // In a full implementation, this would handle variable-length matching
         if let Some(text) = node.text() {
-            // Check if this metavariable is already bound
-            if let Some(existing_binding) = self.metavar_bindings.get(metavar) {
-                Ok(existing_binding == text)
-            } else {
-                // Bind the metavariable
-                self.metavar_bindings.insert(metavar.to_string(), text.to_string());
-                Ok(true)
-            }
+            Ok(self.bind_metavariable(metavar, text))
         } else {
             // Ellipsis can match empty content
-            self.metavar_bindings.insert(metavar.to_string(), "".to_string());
+            if !is_anonymous_metavar(metavar) {
+                self.metavar_bindings.insert(metavar.to_string(), "".to_string());
+            }
             Ok(true)
         }
     }
 
+    /// Bind a metavariable to `text`, or check `text` against its earlier binding; the
+    /// anonymous `$_` binds nothing
+    fn bind_metavariable(&mut self, metavar: &str, text: &str) -> bool {
+        if is_anonymous_metavar(metavar) {
+            return true;
+        }
+        match self.metavar_bindings.get(metavar) {
+            Some(existing) => existing == text,
+            None => {
+                self.metavar_bindings.insert(metavar.to_string(), text.to_string());
+                true
+            }
+        }
+    }
+
     /// Match node type
     fn match_node_type(&self, expected_type: &str, node: &dyn AstNode) -> Result<bool> {
         Ok(node.node_type() == expected_type)
@@ -153,34 +180,8 @@ impl PatternMatcher {
             return self.match_pattern(&patterns[0], node, depth + 1);
         }
 
-        // For multiple patterns, try to match against children
-        if node.child_count() >= patterns.len() {
-            for i in 0..=node.child_count() - patterns.len() {
-                let temp_bindings = self.metavar_bindings.clone();
-                let mut all_match = true;
-
-                for (j, pattern) in patterns.iter().enumerate() {
-                    if let Some(child) = node.child(i + j) {
-                        if !self.match_pattern(pattern, child, depth + 1)? {
-                            all_match = false;
-                            break;
-                        }
-                    } else {
-                        all_match = false;
-                        break;
-                    }
-                }
-
-                if all_match {
-                    return Ok(true);
-                } else {
-                    // Restore bindings if match failed
-                    self.metavar_bindings = temp_bindings;
-                }
-            }
-        }
-
-        Ok(false)
+        // For multiple patterns, match consecutive children; ellipses span any number of them
+        ellipsis::match_window(&mut ChildSearch { matcher: self, depth }, patterns, node)
     }
 
     /// Match alternative patterns (OR)
@@ -203,6 +204,71 @@ impl Default for PatternMatcher {
     }
 }
 
+/// A match in progress, as the child sequence matching of `ellipsis` sees it
+struct ChildSearch<'m> {
+    matcher: &'m mut PatternMatcher,
+    depth: usize,
+}
+
+impl ChildMatcher for ChildSearch<'_> {
+    type Snapshot = HashMap<String, String>;
+
+    fn snapshot(&self) -> Self::Snapshot {
+        self.matcher.metavar_bindings.clone()
+    }
+
+    fn restore(&mut self, snapshot: Self::Snapshot) {
+        self.matcher.metavar_bindings = snapshot;
+    }
+
+    fn match_node(&mut self, pattern: &ParsedPattern, node: &dyn AstNode) -> Result<bool> {
+        self.matcher.match_pattern(pattern, node, self.depth + 1)
+    }
+
+    fn bind_nodes(&mut self, metavar: &str, nodes: &[&dyn AstNode], _parent: &dyn AstNode) -> Result<bool> {
+        Ok(self.matcher.bind_metavariable(metavar, &ellipsis::joined_text(nodes)))
+    }
+}
+
+/// Lazy iterator over pattern matches, returned by [`PatternMatcher::find_matches_iter`]
+pub struct PatternMatches<'m, 'a> {
+    matcher: &'m mut PatternMatcher,
+    pattern: ParsedPattern,
+    stack: Vec<&'a dyn AstNode>,
+    failed: bool,
+}
+
+impl<'m, 'a> Iterator for PatternMatches<'m, 'a> {
+    type Item = Result<MatchResult>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        while let Some(node) = self.stack.pop() {
+            // Queue children in reverse so they are visited in source order
+            for i in (0..node.child_count()).rev() {
+                if let Some(child) = node.child(i) {
+                    self.stack.push(child);
+                }
+            }
+
+            self.matcher.metavar_bindings.clear();
+            match self.matcher.match_pattern(&self.pattern, node, 0) {
+                Ok(true) => return Some(Ok(MatchResult::new(node.clone_node(), self.matcher.metavar_bindings.clone()))),
+                Ok(false) => {}
+                Err(e) => {
+                    self.failed = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+
+        None
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -290,6 +356,15 @@ mod tests {
         assert!(!result);
     }
 
+    #[test]
+    fn test_anonymous_metavariable_does_not_bind() {
+        let mut matcher = PatternMatcher::new();
+        let node = AstBuilder::identifier("variable_a").with_text("variable_a".to_string());
+
+        assert!(matcher.matches("$_", &node).unwrap());
+        assert!(matcher.get_bindings().is_empty());
+    }
+
     #[test]
     fn test_wildcard_match() {
         let mut matcher = PatternMatcher::new();
@@ -334,4 +409,25 @@ mod tests {
         let result = matcher_depth_0.matches("test", &node).unwrap();
         assert!(!result);
     }
+
+    #[test]
+    fn test_find_matches_iter_stops_early() {
+        let mut matcher = PatternMatcher::new();
+        let program = AstBuilder::program(vec![
+            AstBuilder::identifier("token_a"),
+            AstBuilder::identifier("other"),
+            AstBuilder::identifier("token_b"),
+        ]);
+
+        let all = matcher.find_matches("token", &program).unwrap();
+        let texts: Vec<_> = all.iter().map(|m| m.node().text().unwrap().to_string()).collect();
+        assert_eq!(texts, vec!["token_a", "token_b"]);
+
+        let first: Vec<_> = matcher.find_matches_iter("token", &program).unwrap().take(1).collect::<Result<_>>().unwrap();
+        assert_eq!(first.len(), 1);
+        assert_eq!(first[0].node().text(), Some("token_a"));
+
+        assert!(matcher.has_match("other", &program).unwrap());
+        assert!(!matcher.has_match("missing", &program).unwrap());
+    }
 }
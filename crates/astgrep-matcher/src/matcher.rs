@@ -4,7 +4,8 @@
 //! for metavariables, conditions, and complex patterns.
 
 use crate::{conditions::{ConditionEvaluator, ConditionType}, metavar::*, PatternParser, ParsedPattern};
-use astgrep_core::{AstNode, Result};
+use crate::ellipsis::{self, ChildMatcher};
+use astgrep_core::{AstNode, BoundMetavariable, Result};
 use std::collections::HashMap;
 
 /// Advanced pattern matcher with full feature support
@@ -95,9 +96,17 @@ impl AdvancedPatternMatcher {
 
     /// Find all nodes that match a pattern
     pub fn find_matches(&mut self, pattern: &str, root: &dyn AstNode) -> Result<Vec<MatchResult>> {
-        let mut matches = Vec::new();
-        self.find_matches_recursive(pattern, root, &mut matches, 0)?;
-        Ok(matches)
+        self.find_matches_iter(pattern, root).collect()
+    }
+
+    /// Lazily find nodes that match a pattern, in pre-order; the pattern is parsed once
+    /// and the search stops when the iterator is dropped
+    pub fn find_matches_iter<'m, 'a>(&'m mut self, pattern: &str, root: &'a dyn AstNode) -> AdvancedMatches<'m, 'a> {
+        let (pattern, error) = match self.parser.parse(pattern) {
+            Ok(parsed) => (Some(parsed), None),
+            Err(e) => (None, Some(e)),
+        };
+        AdvancedMatches { matcher: self, pattern, error, stack: vec![(root, 0)] }
     }
 
     /// Find matches with conditions
@@ -142,6 +151,8 @@ impl AdvancedPatternMatcher {
             ParsedPattern::NodeType(node_type) => self.match_node_type(node_type, node),
             ParsedPattern::Sequence(patterns) => self.match_sequence(patterns, node, depth),
             ParsedPattern::Alternative(patterns) => self.match_alternative(patterns, node, depth),
+            ParsedPattern::Children(elements) => ellipsis::match_children(&mut ChildSearch { matcher: self, depth }, elements, node),
+            ParsedPattern::Deep(inner) => ellipsis::match_deep(&mut ChildSearch { matcher: self, depth }, inner, node),
             ParsedPattern::Wildcard => Ok(true),
         }
     }
@@ -168,10 +179,9 @@ impl AdvancedPatternMatcher {
         }
     }
 
-    /// Match ellipsis metavariable (can match zero or more nodes)
+    /// Match ellipsis metavariable on its own, standing for the whole node; in sequences,
+    /// argument lists and blocks it spans zero or more children instead
     fn match_ellipsis_metavariable(&mut self, metavar: &str, node: &dyn AstNode) -> Result<bool> {
-        // For now, treat ellipsis metavariables like regular metavariables
-        // In a full implementation, this would handle variable-length matching
         if let Some(text) = node.text() {
             self.metavar_manager.bind(metavar.to_string(), text.to_string(), node)
         } else {
@@ -195,34 +205,8 @@ impl AdvancedPatternMatcher {
             return self.match_pattern(&patterns[0], node, depth + 1);
         }
 
-        // Try to match the sequence against the node and its children
-        if node.child_count() >= patterns.len() {
-            for i in 0..=node.child_count() - patterns.len() {
-                let snapshot = self.metavar_manager.snapshot();
-                let mut all_match = true;
-
-                for (j, pattern) in patterns.iter().enumerate() {
-                    if let Some(child) = node.child(i + j) {
-                        if !self.match_pattern(pattern, child, depth + 1)? {
-                            all_match = false;
-                            break;
-                        }
-                    } else {
-                        all_match = false;
-                        break;
-                    }
-                }
-
-                if all_match {
-                    return Ok(true);
-                } else {
-                    // Restore bindings if match failed
-                    self.metavar_manager.restore(snapshot);
-                }
-            }
-        }
-
-        Ok(false)
+        // Match the sequence against consecutive children; ellipses span any number of them
+        ellipsis::match_window(&mut ChildSearch { matcher: self, depth }, patterns, node)
     }
 
     /// Match alternative patterns (OR)
@@ -238,11 +222,12 @@ impl AdvancedPatternMatcher {
         Ok(false)
     }
 
-    /// Recursively find matches in the AST
-    fn find_matches_recursive(
+    /// Recursively find matches with conditions
+    fn find_matches_with_conditions_recursive(
         &mut self,
         pattern: &str,
         node: &dyn AstNode,
+        conditions: &[ConditionType],
         matches: &mut Vec<MatchResult>,
         depth: usize,
     ) -> Result<()> {
@@ -255,54 +240,103 @@ impl AdvancedPatternMatcher {
 
         // Try to match at current node
         let snapshot = self.metavar_manager.snapshot();
-        if self.matches(pattern, node)? {
+        if self.matches_with_conditions(pattern, node, conditions)? {
             let bindings = self.get_bindings();
-            matches.push(MatchResult::new(node.clone_node(), bindings));
+            matches.push(MatchResult::new(node.clone_node(), bindings).with_metavariables(self.metavar_manager.get_bound_metavariables()));
         }
         self.metavar_manager.restore(snapshot);
 
         // Recursively check children
         for i in 0..node.child_count() {
             if let Some(child) = node.child(i) {
-                self.find_matches_recursive(pattern, child, matches, depth + 1)?;
+                self.find_matches_with_conditions_recursive(pattern, child, conditions, matches, depth + 1)?;
             }
         }
 
         Ok(())
     }
+}
 
-    /// Recursively find matches with conditions
-    fn find_matches_with_conditions_recursive(
-        &mut self,
-        pattern: &str,
-        node: &dyn AstNode,
-        conditions: &[ConditionType],
-        matches: &mut Vec<MatchResult>,
-        depth: usize,
-    ) -> Result<()> {
-        // Check depth limit
-        if let Some(max_depth) = self.max_depth {
-            if depth > max_depth {
-                return Ok(());
-            }
-        }
+/// A match in progress, as the child sequence matching of `ellipsis` sees it
+struct ChildSearch<'m> {
+    matcher: &'m mut AdvancedPatternMatcher,
+    depth: usize,
+}
 
-        // Try to match at current node
-        let snapshot = self.metavar_manager.snapshot();
-        if self.matches_with_conditions(pattern, node, conditions)? {
-            let bindings = self.get_bindings();
-            matches.push(MatchResult::new(node.clone_node(), bindings));
+impl ChildMatcher for ChildSearch<'_> {
+    type Snapshot = HashMap<String, MetavarBinding>;
+
+    fn snapshot(&self) -> Self::Snapshot {
+        self.matcher.metavar_manager.snapshot()
+    }
+
+    fn restore(&mut self, snapshot: Self::Snapshot) {
+        self.matcher.metavar_manager.restore(snapshot);
+    }
+
+    fn match_node(&mut self, pattern: &ParsedPattern, node: &dyn AstNode) -> Result<bool> {
+        self.matcher.match_pattern(pattern, node, self.depth + 1)
+    }
+
+    fn bind_nodes(&mut self, metavar: &str, nodes: &[&dyn AstNode], parent: &dyn AstNode) -> Result<bool> {
+        let node_type = nodes.first().copied().unwrap_or(parent).node_type();
+        self.matcher.metavar_manager.bind_at(metavar.to_string(), ellipsis::joined_text(nodes), node_type, ellipsis::joined_location(nodes))
+    }
+}
+
+/// Lazy iterator over pattern matches, returned by [`AdvancedPatternMatcher::find_matches_iter`]
+pub struct AdvancedMatches<'m, 'a> {
+    matcher: &'m mut AdvancedPatternMatcher,
+    pattern: Option<ParsedPattern>,
+    error: Option<astgrep_core::AnalysisError>,
+    stack: Vec<(&'a dyn AstNode, usize)>,
+}
+
+impl<'m, 'a> Iterator for AdvancedMatches<'m, 'a> {
+    type Item = Result<MatchResult>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(error) = self.error.take() {
+            self.stack.clear();
+            return Some(Err(error));
         }
-        self.metavar_manager.restore(snapshot);
+        let pattern = self.pattern.as_ref()?;
 
-        // Recursively check children
-        for i in 0..node.child_count() {
-            if let Some(child) = node.child(i) {
-                self.find_matches_with_conditions_recursive(pattern, child, conditions, matches, depth + 1)?;
+        while let Some((node, depth)) = self.stack.pop() {
+            // Check depth limit
+            if self.matcher.max_depth.is_some_and(|max_depth| depth > max_depth) {
+                continue;
+            }
+
+            // Queue children in reverse so they are visited in source order
+            for i in (0..node.child_count()).rev() {
+                if let Some(child) = node.child(i) {
+                    self.stack.push((child, depth + 1));
+                }
+            }
+
+            // Try to match at current node
+            let snapshot = self.matcher.metavar_manager.snapshot();
+            self.matcher.reset();
+            let matched = self.matcher.match_pattern(pattern, node, 0);
+            let result = match matched {
+                Ok(true) => {
+                    let metavariables = self.matcher.metavar_manager.get_bound_metavariables();
+                    Some(Ok(MatchResult::new(node.clone_node(), self.matcher.get_bindings()).with_metavariables(metavariables)))
+                }
+                Ok(false) => None,
+                Err(e) => {
+                    self.stack.clear();
+                    Some(Err(e))
+                }
+            };
+            self.matcher.metavar_manager.restore(snapshot);
+            if result.is_some() {
+                return result;
             }
         }
 
-        Ok(())
+        None
     }
 }
 
@@ -316,12 +350,23 @@ impl Default for AdvancedPatternMatcher {
 pub struct MatchResult {
     pub node: Box<dyn AstNode>,
     pub bindings: HashMap<String, String>,
+    /// The bindings with the node type and location of the bound code
+    pub metavariables: HashMap<String, BoundMetavariable>,
 }
 
 impl MatchResult {
     /// Create a new match result
     pub fn new(node: Box<dyn AstNode>, bindings: HashMap<String, String>) -> Self {
-        Self { node, bindings }
+        Self { node, bindings, metavariables: HashMap::new() }
+    }
+
+    /// Attach the located bindings; `bindings` is filled from them where missing
+    pub fn with_metavariables(mut self, metavariables: HashMap<String, BoundMetavariable>) -> Self {
+        for (name, bound) in &metavariables {
+            self.bindings.entry(name.clone()).or_insert_with(|| bound.text.clone());
+        }
+        self.metavariables = metavariables;
+        self
     }
 
     /// Get the matched node
@@ -343,6 +388,11 @@ impl MatchResult {
     pub fn has_binding(&self, name: &str) -> bool {
         self.bindings.contains_key(name)
     }
+
+    /// Get the node type and location of a binding, when the matcher recorded them
+    pub fn get_metavariable(&self, name: &str) -> Option<&BoundMetavariable> {
+        self.metavariables.get(name)
+    }
 }
 
 #[cfg(test)]
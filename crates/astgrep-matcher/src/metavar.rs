@@ -2,10 +2,16 @@
 //! 
 //! This module provides functionality for handling metavariables in patterns.
 
-use astgrep_core::{AstNode, Result};
+use astgrep_core::{AstNode, BoundMetavariable, Result};
 use regex::Regex;
 use std::collections::HashMap;
 
+/// Whether `name` is the anonymous metavariable `$_` (or `$..._`), which matches like any
+/// other metavariable but binds nothing, so its occurrences need not agree
+pub fn is_anonymous_metavar(name: &str) -> bool {
+    name.trim_start_matches('$').trim_start_matches("...") == "_"
+}
+
 /// Metavariable constraint types
 #[derive(Debug, Clone)]
 pub enum MetavarConstraint {
@@ -37,6 +43,8 @@ pub struct MetavarBinding {
     pub name: String,
     pub value: String,
     pub node_type: String,
+    /// (start_line, start_col, end_line, end_col) of the bound code
+    pub location: Option<(usize, usize, usize, usize)>,
     pub constraints: Vec<MetavarConstraint>,
 }
 
@@ -47,10 +55,17 @@ impl MetavarBinding {
             name,
             value,
             node_type,
+            location: None,
             constraints: Vec::new(),
         }
     }
 
+    /// Set where the bound code is
+    pub fn with_location(mut self, location: Option<(usize, usize, usize, usize)>) -> Self {
+        self.location = location;
+        self
+    }
+
     /// Add a constraint to this binding
     pub fn add_constraint(mut self, constraint: MetavarConstraint) -> Self {
         self.constraints.push(constraint);
@@ -107,8 +122,16 @@ impl MetavarManager {
 
     /// Bind a metavariable to a value
     pub fn bind(&mut self, name: String, value: String, node: &dyn AstNode) -> Result<bool> {
-        let node_type = node.node_type().to_string();
-        
+        self.bind_at(name, value, node.node_type(), node.location())
+    }
+
+    /// Bind a metavariable to code of `node_type` at `location`, such as the span of the
+    /// several nodes an ellipsis metavariable stands for
+    pub fn bind_at(&mut self, name: String, value: String, node_type: &str, location: Option<(usize, usize, usize, usize)>) -> Result<bool> {
+        if is_anonymous_metavar(&name) {
+            return Ok(true);
+        }
+
         // Check if this metavariable is already bound
         if let Some(existing_binding) = self.bindings.get(&name) {
             // Check if the new binding is consistent with the existing one
@@ -116,7 +139,7 @@ impl MetavarManager {
         }
 
         // Create new binding with constraints
-        let mut binding = MetavarBinding::new(name.clone(), value, node_type);
+        let mut binding = MetavarBinding::new(name.clone(), value, node_type.to_string()).with_location(location);
         
         if let Some(constraints) = self.constraints.get(&name) {
             for constraint in constraints {
@@ -151,6 +174,14 @@ impl MetavarManager {
             .collect()
     }
 
+    /// Get the bound code, node type and location of every metavariable
+    pub fn get_bound_metavariables(&self) -> HashMap<String, BoundMetavariable> {
+        self.bindings
+            .iter()
+            .map(|(name, binding)| (name.clone(), BoundMetavariable::new(binding.value.clone(), binding.node_type.clone(), binding.location)))
+            .collect()
+    }
+
     /// Clear all bindings
     pub fn clear_bindings(&mut self) {
         self.bindings.clear();
@@ -334,6 +365,18 @@ mod tests {
         assert!(!result3.unwrap());
     }
 
+    #[test]
+    fn test_anonymous_metavar_binds_nothing() {
+        let mut manager = MetavarManager::new();
+        let node = AstBuilder::identifier("test_var");
+
+        assert!(manager.bind("_".to_string(), "first".to_string(), &node).unwrap());
+        assert!(manager.bind("_".to_string(), "second".to_string(), &node).unwrap());
+        assert_eq!(manager.binding_count(), 0);
+        assert!(is_anonymous_metavar("$_") && is_anonymous_metavar("$..._"));
+        assert!(!is_anonymous_metavar("$_X"));
+    }
+
     #[test]
     fn test_metavar_manager_with_constraints() {
         let mut manager = MetavarManager::new();
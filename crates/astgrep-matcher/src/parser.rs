@@ -20,6 +20,12 @@ pub enum ParsedPattern {
     Sequence(Vec<ParsedPattern>),
     /// Alternative patterns (OR)
     Alternative(Vec<ParsedPattern>),
+    /// Children of a node in order: the arguments of a call (`foo(a, ...)`) or the
+    /// statements of a block (`{ ... }`); `...` elements match any number of children
+    Children(Vec<ParsedPattern>),
+    /// An expression containing a match of the inner pattern anywhere in its subtree
+    /// (`<... $X ...>`)
+    Deep(Box<ParsedPattern>),
     /// Wildcard (matches anything)
     Wildcard,
 }
@@ -51,6 +57,17 @@ impl fmt::Display for ParsedPattern {
                 }
                 write!(f, ")")
             }
+            ParsedPattern::Children(elements) => {
+                write!(f, "[")?;
+                for (i, element) in elements.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", element)?;
+                }
+                write!(f, "]")
+            }
+            ParsedPattern::Deep(inner) => write!(f, "<... {} ...>", inner),
             ParsedPattern::Wildcard => write!(f, "..."),
         }
     }
@@ -87,13 +104,22 @@ impl PatternParser {
         let mut tokens = Vec::new();
         let mut chars = pattern.chars().peekable();
         let mut current_pos = 0;
+        // Whether whitespace separates the next token from the previous one
+        let mut spaced = true;
+        // Deep expressions `<... ...>` still open
+        let mut open_deep = 0usize;
 
         while let Some(ch) = chars.next() {
             current_pos += 1;
-            
+            let follows_name = !spaced && tokens.last().is_some_and(Token::ends_with_name);
+            spaced = false;
+
             match ch {
                 // Skip whitespace
-                ' ' | '\t' | '\n' | '\r' => continue,
+                ' ' | '\t' | '\n' | '\r' => {
+                    spaced = true;
+                    continue;
+                }
                 
                 // Metavariable
                 '$' => {
@@ -179,12 +205,28 @@ impl PatternParser {
                     tokens.push(Token::NodeType(name));
                 }
                 
-                // Parentheses
+                // Parentheses: directly after a name they hold the arguments of a call
+                '(' if follows_name => tokens.push(Token::CallParen),
                 '(' => tokens.push(Token::LeftParen),
                 ')' => tokens.push(Token::RightParen),
+
+                // Braces hold the statements of a block
+                '{' => tokens.push(Token::LeftBrace),
+                '}' => tokens.push(Token::RightBrace),
                 
+                // Deep expression operator
+                '<' if chars.clone().take(3).eq("...".chars()) => {
+                    chars.nth(2);
+                    current_pos += 3;
+                    open_deep += 1;
+                    tokens.push(Token::DeepOpen);
+                }
+
                 // Alternative operator
                 '|' => tokens.push(Token::Pipe),
+
+                // Separators of arguments and statements
+                ',' | ';' => tokens.push(Token::Literal(ch.to_string())),
                 
                 // Wildcard
                 '.' => {
@@ -194,7 +236,14 @@ impl PatternParser {
                         if chars.peek() == Some(&'.') {
                             chars.next(); // consume third dot
                             current_pos += 1;
-                            tokens.push(Token::Wildcard);
+                            if open_deep > 0 && chars.peek() == Some(&'>') {
+                                chars.next();
+                                current_pos += 1;
+                                open_deep -= 1;
+                                tokens.push(Token::DeepClose);
+                            } else {
+                                tokens.push(Token::Wildcard);
+                            }
                         } else {
                             return Err(AnalysisError::pattern_match_error(
                                 format!("Invalid wildcard at position {}", current_pos)
@@ -268,18 +317,24 @@ impl PatternParser {
             return Ok(ParsedPattern::Wildcard);
         }
 
-        self.parse_alternative(tokens, 0).map(|(pattern, _)| pattern)
+        let (pattern, pos) = self.parse_alternative(tokens, 0, None)?;
+        match tokens.get(pos) {
+            Some(Token::RightBrace) => Err(AnalysisError::pattern_match_error("Unexpected closing brace")),
+            Some(Token::DeepClose) => Err(AnalysisError::pattern_match_error("Unexpected end of deep expression")),
+            _ => Ok(pattern),
+        }
     }
 
-    /// Parse alternative patterns (lowest precedence)
-    fn parse_alternative(&self, tokens: &[Token], start: usize) -> Result<(ParsedPattern, usize)> {
-        let (pattern, mut pos) = self.parse_sequence(tokens, start)?;
+    /// Parse alternative patterns (lowest precedence); inside a list, `separator` ends
+    /// the element
+    fn parse_alternative(&self, tokens: &[Token], start: usize, separator: Option<&str>) -> Result<(ParsedPattern, usize)> {
+        let (pattern, mut pos) = self.parse_sequence(tokens, start, separator)?;
         let mut alternatives = vec![pattern];
 
         while pos < tokens.len() {
             if let Token::Pipe = tokens[pos] {
                 pos += 1; // consume pipe
-                let (alt_pattern, new_pos) = self.parse_sequence(tokens, pos)?;
+                let (alt_pattern, new_pos) = self.parse_sequence(tokens, pos, separator)?;
                 alternatives.push(alt_pattern);
                 pos = new_pos;
             } else {
@@ -295,13 +350,15 @@ impl PatternParser {
     }
 
     /// Parse sequence patterns
-    fn parse_sequence(&self, tokens: &[Token], start: usize) -> Result<(ParsedPattern, usize)> {
+    fn parse_sequence(&self, tokens: &[Token], start: usize, separator: Option<&str>) -> Result<(ParsedPattern, usize)> {
         let mut patterns = Vec::new();
         let mut pos = start;
 
         while pos < tokens.len() {
             match &tokens[pos] {
-                Token::RightParen | Token::Pipe => break,
+                Token::RightParen | Token::RightBrace | Token::DeepClose | Token::Pipe => break,
+                Token::Literal(s) if Some(s.as_str()) == separator => break,
+                Token::Wildcard | Token::EllipsisMetavariable(_) if separator.is_some() => break,
                 _ => {
                     let (pattern, new_pos) = self.parse_primary(tokens, pos)?;
                     patterns.push(pattern);
@@ -332,18 +389,59 @@ impl PatternParser {
             Token::NodeType(s) => Ok((ParsedPattern::NodeType(s.clone()), start + 1)),
             Token::Wildcard => Ok((ParsedPattern::Wildcard, start + 1)),
             Token::LeftParen => {
-                let (pattern, pos) = self.parse_alternative(tokens, start + 1)?;
+                let (pattern, pos) = self.parse_alternative(tokens, start + 1, None)?;
                 if pos >= tokens.len() || !matches!(tokens[pos], Token::RightParen) {
                     return Err(AnalysisError::pattern_match_error("Missing closing parenthesis"));
                 }
                 Ok((pattern, pos + 1))
             }
+            Token::CallParen => self.parse_list(tokens, start + 1, ",", &Token::RightParen, "Missing closing parenthesis"),
+            Token::LeftBrace => self.parse_list(tokens, start + 1, ";", &Token::RightBrace, "Missing closing brace"),
+            Token::DeepOpen => {
+                let (inner, pos) = self.parse_alternative(tokens, start + 1, None)?;
+                if !matches!(tokens.get(pos), Some(Token::DeepClose)) {
+                    return Err(AnalysisError::pattern_match_error("Missing closing ...> of deep expression"));
+                }
+                Ok((ParsedPattern::Deep(Box::new(inner)), pos + 1))
+            }
             Token::RightParen => Err(AnalysisError::pattern_match_error("Unexpected closing parenthesis")),
+            Token::RightBrace => Err(AnalysisError::pattern_match_error("Unexpected closing brace")),
+            Token::DeepClose => Err(AnalysisError::pattern_match_error("Unexpected end of deep expression")),
             Token::Pipe => Err(AnalysisError::pattern_match_error("Unexpected pipe operator")),
         }
     }
 }
 
+impl PatternParser {
+    /// Parse the elements of an argument list or block up to `close`
+    fn parse_list(&self, tokens: &[Token], start: usize, separator: &str, close: &Token, unclosed: &str) -> Result<(ParsedPattern, usize)> {
+        let mut elements = Vec::new();
+        let mut pos = start;
+        loop {
+            match tokens.get(pos) {
+                None => return Err(AnalysisError::pattern_match_error(unclosed)),
+                Some(token) if token == close => return Ok((ParsedPattern::Children(elements), pos + 1)),
+                Some(Token::Literal(s)) if s == separator => pos += 1,
+                Some(Token::RightParen | Token::RightBrace | Token::DeepClose) => return Err(AnalysisError::pattern_match_error(unclosed)),
+                // An ellipsis is an element of its own, separator or not: `{ ... f(); ... }`
+                Some(Token::Wildcard) => {
+                    elements.push(ParsedPattern::Wildcard);
+                    pos += 1;
+                }
+                Some(Token::EllipsisMetavariable(name)) => {
+                    elements.push(ParsedPattern::EllipsisMetavariable(name.clone()));
+                    pos += 1;
+                }
+                Some(_) => {
+                    let (element, new_pos) = self.parse_alternative(tokens, pos, Some(separator))?;
+                    elements.push(element);
+                    pos = new_pos;
+                }
+            }
+        }
+    }
+}
+
 impl Default for PatternParser {
     fn default() -> Self {
         Self::new()
@@ -358,11 +456,30 @@ enum Token {
     EllipsisMetavariable(String),
     NodeType(String),
     LeftParen,
+    /// Opening parenthesis of the arguments of a call
+    CallParen,
     RightParen,
+    LeftBrace,
+    RightBrace,
+    /// `<...`, opening a deep expression
+    DeepOpen,
+    /// `...>`, closing a deep expression
+    DeepClose,
     Pipe,
     Wildcard,
 }
 
+impl Token {
+    /// Whether a `(` right after this token starts the arguments of a call
+    fn ends_with_name(&self) -> bool {
+        match self {
+            Token::Metavariable(_) => true,
+            Token::Literal(s) => s.chars().last().is_some_and(|c| c.is_alphanumeric() || c == '_'),
+            _ => false,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -466,6 +583,58 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_parse_call_arguments_and_blocks() {
+        let parser = PatternParser::new();
+        let literal = |s: &str| ParsedPattern::Literal(s.to_string());
+        assert_eq!(
+            parser.parse("foo(..., password, ...)").unwrap(),
+            ParsedPattern::Sequence(vec![
+                literal("foo"),
+                ParsedPattern::Children(vec![ParsedPattern::Wildcard, literal("password"), ParsedPattern::Wildcard]),
+            ])
+        );
+        assert_eq!(
+            parser.parse("{ ... sink($X); ... }").unwrap(),
+            ParsedPattern::Children(vec![
+                ParsedPattern::Wildcard,
+                ParsedPattern::Sequence(vec![literal("sink"), ParsedPattern::Children(vec![ParsedPattern::Metavariable("X".to_string())])]),
+                ParsedPattern::Wildcard,
+            ])
+        );
+        assert_eq!(parser.parse("f()").unwrap(), ParsedPattern::Sequence(vec![literal("f"), ParsedPattern::Children(vec![])]));
+
+        // Parentheses after whitespace still group
+        assert_eq!(
+            parser.parse("f (a | b)").unwrap(),
+            ParsedPattern::Sequence(vec![literal("f"), ParsedPattern::Alternative(vec![literal("a"), literal("b")])])
+        );
+        assert!(parser.parse("f(a, b").is_err());
+        assert!(parser.parse("{ a; ").is_err());
+        assert!(parser.parse("a }").is_err());
+    }
+
+    #[test]
+    fn test_parse_deep_expression() {
+        let parser = PatternParser::new();
+        let literal = |s: &str| ParsedPattern::Literal(s.to_string());
+        assert_eq!(
+            parser.parse("eval(<... $X ...>)").unwrap(),
+            ParsedPattern::Sequence(vec![
+                literal("eval"),
+                ParsedPattern::Children(vec![ParsedPattern::Deep(Box::new(ParsedPattern::Metavariable("X".to_string())))]),
+            ])
+        );
+        assert_eq!(
+            parser.parse("<... <... x ...> ...>").unwrap(),
+            ParsedPattern::Deep(Box::new(ParsedPattern::Deep(Box::new(literal("x")))))
+        );
+        assert_eq!(parser.parse("<... f(x) ...>").unwrap().to_string(), "<... (\"f\" [\"x\"]) ...>");
+        // Outside a deep expression `...>` is an ellipsis and a comparison
+        assert_eq!(parser.parse("a ...>").unwrap(), ParsedPattern::Sequence(vec![literal("a"), ParsedPattern::Wildcard, literal(">")]));
+        assert!(parser.parse("<... x").is_err());
+    }
+
     #[test]
     fn test_pattern_display() {
         let pattern = ParsedPattern::Sequence(vec![
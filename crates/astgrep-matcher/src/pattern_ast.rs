@@ -0,0 +1,578 @@
+//! Patterns parsed with the grammar of the code they match
+//!
+//! A simple pattern is parsed by the target language's parser, so `foo($X, ...)` becomes
+//! the same call node the code contains and is matched node against node rather than
+//! token against token. Before parsing, metavariables and ellipses are replaced by
+//! identifiers every grammar accepts: `$X` by `__astgrep_mv_X`, `$...ARGS` by
+//! `__astgrep_ellipsis_ARGS` and `...` by `__astgrep_ellipsis`. Patterns that are not a
+//! complete program are wrapped in the smallest enclosing construct of the language (a
+//! method body in Java), and the pattern is the deepest node spanning exactly its text.
+//! A pattern of several statements is instead the run of statements spanning it, and
+//! matches a run of consecutive statements in any block.
+//!
+//! Matching compares node types and, for leaves, code. A metavariable matches any node,
+//! an ellipsis any run of sibling nodes (arguments, or statements of a block), and `"..."`
+//! any string literal. The search polls a cancellation token at every node and on every
+//! attempt of the ellipsis backtracking.
+
+use crate::ellipsis;
+use crate::metavar::is_anonymous_metavar;
+use astgrep_core::{AstNode, BoundMetavariable, CancellationToken, Language, LanguageParser};
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::Path;
+
+const METAVARIABLE_PREFIX: &str = "__astgrep_mv_";
+const ELLIPSIS: &str = "__astgrep_ellipsis";
+/// Node types whose children are statements
+const STATEMENT_LISTS: [&str; 2] = ["program", "block_statement"];
+
+/// A pattern parsed into the syntax tree of its language
+pub struct PatternAst {
+    tree: Box<dyn AstNode>,
+    /// Child indices from the root of `tree` to the pattern node, or to the statement list
+    /// holding a pattern of several statements
+    path: Vec<usize>,
+    /// The children of the node at `path` making up a pattern of several statements
+    statements: Option<Range<usize>>,
+}
+
+/// A match of a [`PatternAst`]: the matched node, or consecutive statements, and the
+/// metavariables bound, by name without the `$`
+pub struct PatternAstMatch<'t> {
+    pub nodes: Vec<&'t dyn AstNode>,
+    pub metavariables: HashMap<String, BoundMetavariable>,
+}
+
+impl PatternAstMatch<'_> {
+    /// The span of the matched nodes
+    pub fn location(&self) -> Option<(usize, usize, usize, usize)> {
+        ellipsis::joined_location(&self.nodes)
+    }
+}
+
+/// What a pattern node stands for when it is a placeholder
+enum Placeholder<'p> {
+    Metavariable(&'p str),
+    /// An ellipsis, with the name of an ellipsis metavariable
+    Ellipsis(Option<&'p str>),
+}
+
+impl PatternAst {
+    /// Parse `pattern` with `parser`. `None` when the pattern cannot be written in the
+    /// grammar (a deep expression, a lone metavariable) or does not parse into a node or
+    /// statements spanning it; token matching applies to such patterns.
+    pub fn parse(pattern: &str, parser: &dyn LanguageParser) -> Option<Self> {
+        let body = with_placeholders(pattern, statement_terminator(parser.language()))?;
+        let body = body.trim();
+        if body.is_empty() {
+            return None;
+        }
+        wrappers(parser.language()).iter().find_map(|(prefix, suffix)| {
+            let source = format!("{}{}{}", prefix, body, suffix);
+            let tree = parser.parse(&source, Path::new("pattern")).ok()?;
+            let start = line_col(&source, prefix.len());
+            let ends = [
+                line_col(&source, prefix.len() + body.len()),
+                line_col(&source, prefix.len() + body.trim_end_matches(';').trim_end().len()),
+            ];
+            let mut found = Spanning::default();
+            find_spanning(tree.as_ref(), start, &ends, &mut Vec::new(), &mut found);
+            let ast = match (found.node, found.statements) {
+                (node, Some((path, statements))) if node.as_ref().is_none_or(|node| node.len() <= path.len()) => {
+                    Self { tree, path, statements: Some(statements) }
+                }
+                (Some(path), _) => Self { tree, path, statements: None },
+                (None, _) => return None,
+            };
+            let nodes = ast.nodes();
+            let only_placeholders = nodes.iter().all(|node| placeholder(*node).is_some());
+            (!only_placeholders).then_some(ast)
+        })
+    }
+
+    /// The pattern node, or the statements of a pattern of several statements
+    pub fn nodes(&self) -> Vec<&dyn AstNode> {
+        let node = self.path.iter().try_fold(self.tree.as_ref(), |node, &index| node.child(index)).unwrap_or(self.tree.as_ref());
+        match &self.statements {
+            Some(statements) => statements.clone().filter_map(|i| node.child(i)).collect(),
+            None => vec![node],
+        }
+    }
+
+    /// The matches of the pattern in `target`, in pre-order. Once `cancellation` is set
+    /// the search stops with the matches found so far.
+    pub fn find_matches<'t>(&self, target: &'t dyn AstNode, cancellation: &CancellationToken) -> Vec<PatternAstMatch<'t>> {
+        let patterns = self.nodes();
+        let mut matches = Vec::new();
+        let mut stack = vec![target];
+        while let Some(node) = stack.pop() {
+            if cancellation.is_cancelled() {
+                break;
+            }
+            if self.statements.is_some() {
+                matches.extend(match_statements(&patterns, &children(node), cancellation));
+            } else {
+                let mut metavariables = HashMap::new();
+                if match_node(patterns[0], node, &mut metavariables, cancellation) {
+                    matches.push(PatternAstMatch { nodes: vec![node], metavariables });
+                }
+            }
+            stack.extend((0..node.child_count()).rev().filter_map(|i| node.child(i)));
+        }
+        matches
+    }
+}
+
+/// The deepest node, and the deepest run of statements, spanning a pattern, as child paths
+#[derive(Default)]
+struct Spanning {
+    node: Option<Vec<usize>>,
+    statements: Option<(Vec<usize>, Range<usize>)>,
+}
+
+/// Enclosing code tried around a pattern, in order, as (prefix, suffix)
+fn wrappers(language: Language) -> &'static [(&'static str, &'static str)] {
+    match language {
+        Language::Java | Language::CSharp => &[
+            ("", ""),
+            ("class __AstgrepPattern {\n", "\n}"),
+            ("class __AstgrepPattern { void __astgrep() {\n", "\n;}}"),
+        ],
+        Language::C => &[("", ""), ("void __astgrep() {\n", "\n;}")],
+        Language::Kotlin => &[("", ""), ("fun __astgrep() {\n", "\n}")],
+        Language::Swift => &[("", ""), ("func __astgrep() {\n", "\n}")],
+        Language::Php => &[("<?php\n", "\n"), ("<?php\n", "\n;")],
+        Language::JavaScript | Language::Python | Language::Bash | Language::Ruby => &[("", "")],
+        // Token matching handles SQL statement boundaries and keyword case
+        Language::Sql | Language::Xml => &[],
+    }
+}
+
+/// What ends a statement of `language`, so an ellipsis on a line of its own parses as a
+/// statement
+fn statement_terminator(language: Language) -> &'static str {
+    match language {
+        Language::Java | Language::CSharp | Language::C | Language::JavaScript | Language::Php => ";",
+        _ => "",
+    }
+}
+
+/// Replace metavariables and ellipses outside string literals with identifiers, ending an
+/// ellipsis that stands for statements with `terminator`; `None` for a deep expression
+/// `<... ...>`, which has no counterpart in the grammar
+fn with_placeholders(pattern: &str, terminator: &str) -> Option<String> {
+    let mut out = String::with_capacity(pattern.len() * 2);
+    let mut rest = pattern;
+    while let Some(c) = rest.chars().next() {
+        if c == '"' || c == '\'' || c == '`' {
+            let end = string_end(rest, c);
+            out.push_str(&rest[..end]);
+            rest = &rest[end..];
+        } else if rest.starts_with("<...") {
+            return None;
+        } else if let Some(after) = rest.strip_prefix("$...") {
+            let name_len = name_len(after);
+            let statement = is_statement_position(&out, &after[name_len..]);
+            out.push_str(ELLIPSIS);
+            if name_len > 0 {
+                out.push('_');
+                out.push_str(&after[..name_len]);
+            }
+            if statement {
+                out.push_str(terminator);
+            }
+            rest = &after[name_len..];
+        } else if let Some(after) = rest.strip_prefix("...") {
+            let statement = is_statement_position(&out, after);
+            out.push_str(ELLIPSIS);
+            if statement {
+                out.push_str(terminator);
+            }
+            rest = after;
+        } else if c == '$' && rest[1..].starts_with(|c: char| c.is_ascii_uppercase() || c == '_') {
+            let name_len = name_len(&rest[1..]);
+            out.push_str(METAVARIABLE_PREFIX);
+            out.push_str(&rest[1..1 + name_len]);
+            rest = &rest[1 + name_len..];
+        } else {
+            out.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    Some(out)
+}
+
+/// Whether an ellipsis between `before` and `after` is on a line of its own after the end
+/// of a statement or the start of a block
+fn is_statement_position(before: &str, after: &str) -> bool {
+    let line_before = before.rsplit('\n').next().unwrap_or("");
+    let line_after = after.split('\n').next().unwrap_or("");
+    let previous = before.trim_end().chars().last();
+    line_before.trim().is_empty() && line_after.trim().is_empty() && previous.is_none_or(|c| matches!(c, ';' | '{' | '}'))
+}
+
+fn name_len(text: &str) -> usize {
+    text.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(text.len())
+}
+
+/// Byte length of the string literal opened by `quote` at the start of `text`
+fn string_end(text: &str, quote: char) -> usize {
+    let mut escaped = false;
+    for (i, c) in text.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            _ if c == quote => return i + 1,
+            _ => {}
+        }
+    }
+    text.len()
+}
+
+/// 1-based line and character column of a byte offset, as parsers report locations
+fn line_col(source: &str, byte: usize) -> (usize, usize) {
+    let before = &source[..byte];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().map_or(0, |line| line.chars().count()) + 1;
+    (line, column)
+}
+
+/// Record in `found` the deepest node, and the deepest run of two or more statements,
+/// starting at `start` and ending at one of `ends`
+fn find_spanning(node: &dyn AstNode, start: (usize, usize), ends: &[(usize, usize)], path: &mut Vec<usize>, found: &mut Spanning) {
+    if let Some((start_line, start_col, end_line, end_col)) = node.location() {
+        let spans = (start_line, start_col) == start && ends.contains(&(end_line, end_col));
+        if spans && found.node.as_ref().is_none_or(|deepest| deepest.len() < path.len()) {
+            found.node = Some(path.clone());
+        }
+    }
+    if STATEMENT_LISTS.contains(&node.node_type()) && found.statements.as_ref().is_none_or(|(deepest, _)| deepest.len() < path.len()) {
+        let starts_at = |i: usize| node.child(i).and_then(|child| child.location()).is_some_and(|(line, col, ..)| (line, col) == start);
+        let ends_at = |i: usize| node.child(i).and_then(|child| child.location()).is_some_and(|(.., line, col)| ends.contains(&(line, col)));
+        let first = (0..node.child_count()).find(|&i| starts_at(i));
+        let last = first.and_then(|first| (first + 1..node.child_count()).find(|&i| ends_at(i)));
+        if let (Some(first), Some(last)) = (first, last) {
+            found.statements = Some((path.clone(), first..last + 1));
+        }
+    }
+    for i in 0..node.child_count() {
+        if let Some(child) = node.child(i) {
+            path.push(i);
+            find_spanning(child, start, ends, path, found);
+            path.pop();
+        }
+    }
+}
+
+fn placeholder(node: &dyn AstNode) -> Option<Placeholder<'_>> {
+    let text = node.text()?.trim().trim_end_matches(';').trim_end();
+    if let Some(name) = text.strip_prefix(METAVARIABLE_PREFIX) {
+        return (name_len(name) == name.len()).then_some(Placeholder::Metavariable(name));
+    }
+    match text.strip_prefix(ELLIPSIS)? {
+        "" => Some(Placeholder::Ellipsis(None)),
+        rest => {
+            let name = rest.strip_prefix('_')?;
+            (!name.is_empty() && name_len(name) == name.len()).then_some(Placeholder::Ellipsis(Some(name)))
+        }
+    }
+}
+
+/// Whether two pieces of code are the same apart from whitespace
+fn same_code(a: &str, b: &str) -> bool {
+    a.split_whitespace().eq(b.split_whitespace())
+}
+
+fn children(node: &dyn AstNode) -> Vec<&dyn AstNode> {
+    (0..node.child_count()).filter_map(|i| node.child(i)).collect()
+}
+
+fn match_node(pattern: &dyn AstNode, target: &dyn AstNode, bindings: &mut HashMap<String, BoundMetavariable>, cancellation: &CancellationToken) -> bool {
+    match placeholder(pattern) {
+        Some(Placeholder::Metavariable(name)) => return bind(bindings, name, &[target]),
+        Some(Placeholder::Ellipsis(name)) => return name.is_none_or(|name| bind(bindings, name, &[target])),
+        None => {}
+    }
+    if pattern.node_type() != target.node_type() {
+        return false;
+    }
+    let text = pattern.text().unwrap_or("").trim();
+    if matches!(text, "\"...\"" | "'...'") {
+        return true;
+    }
+    if pattern.child_count() == 0 {
+        return same_code(text, target.text().unwrap_or(""));
+    }
+    match_children(&children(pattern), &children(target), bindings, cancellation)
+}
+
+/// Match a run of pattern statements against consecutive statements of `targets`,
+/// returning the matches that do not overlap. A leading or trailing ellipsis anchors the
+/// run at the start or end of the list.
+fn match_statements<'t>(patterns: &[&dyn AstNode], targets: &[&'t dyn AstNode], cancellation: &CancellationToken) -> Vec<PatternAstMatch<'t>> {
+    let is_ellipsis = |node: Option<&&dyn AstNode>| node.is_some_and(|node| matches!(placeholder(*node), Some(Placeholder::Ellipsis(_))));
+    let (from_start, to_end) = (is_ellipsis(patterns.first()), is_ellipsis(patterns.last()));
+    let mut matches = Vec::new();
+    let mut start = 0;
+    while start < targets.len() {
+        let ends = if to_end { targets.len()..=targets.len() } else { start + 1..=targets.len() };
+        let found = ends.into_iter().find_map(|end| {
+            let mut metavariables = HashMap::new();
+            match_children(patterns, &targets[start..end], &mut metavariables, cancellation)
+                .then(|| PatternAstMatch { nodes: targets[start..end].to_vec(), metavariables })
+        });
+        start += found.as_ref().map_or(1, |found| found.nodes.len());
+        matches.extend(found);
+        if from_start {
+            break;
+        }
+    }
+    matches
+}
+
+/// Match sibling pattern nodes against sibling target nodes, an ellipsis taking as few
+/// targets as lets the rest match; nothing matches once `cancellation` is set
+fn match_children(patterns: &[&dyn AstNode], targets: &[&dyn AstNode], bindings: &mut HashMap<String, BoundMetavariable>, cancellation: &CancellationToken) -> bool {
+    let Some((first, rest)) = patterns.split_first() else { return targets.is_empty() };
+    if let Some(Placeholder::Ellipsis(name)) = placeholder(*first) {
+        for taken in 0..=targets.len() {
+            if cancellation.is_cancelled() {
+                return false;
+            }
+            let snapshot = bindings.clone();
+            if name.is_none_or(|name| bind(bindings, name, &targets[..taken])) && match_children(rest, &targets[taken..], bindings, cancellation) {
+                return true;
+            }
+            *bindings = snapshot;
+        }
+        return false;
+    }
+    let Some((target, other_targets)) = targets.split_first() else { return false };
+    let snapshot = bindings.clone();
+    if match_node(*first, *target, bindings, cancellation) && match_children(rest, other_targets, bindings, cancellation) {
+        return true;
+    }
+    *bindings = snapshot;
+    false
+}
+
+/// Bind a metavariable to `nodes`, or check them against its earlier binding
+fn bind(bindings: &mut HashMap<String, BoundMetavariable>, name: &str, nodes: &[&dyn AstNode]) -> bool {
+    if is_anonymous_metavar(name) {
+        return true;
+    }
+    let text = ellipsis::joined_text(nodes);
+    if let Some(bound) = bindings.get(name) {
+        return same_code(&bound.text, &text);
+    }
+    let node_type = nodes.first().map_or("", |node| node.node_type()).to_string();
+    bindings.insert(name.to_string(), BoundMetavariable::new(text, node_type, ellipsis::joined_location(nodes)));
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use astgrep_ast::{NodeType, UniversalNode};
+    use astgrep_core::Result;
+
+    /// Parses `;`-separated statements of calls, identifiers, strings and `+`, and `{ }`
+    /// blocks, shaped as the tree-sitter conversion shapes them (punctuation dropped)
+    struct CallParser;
+
+    struct Cursor<'s> {
+        source: &'s str,
+        pos: usize,
+    }
+
+    impl Cursor<'_> {
+        fn skip_spaces(&mut self) {
+            while self.source[self.pos..].starts_with(char::is_whitespace) {
+                self.pos += 1;
+            }
+        }
+
+        fn eat(&mut self, c: char) -> bool {
+            self.skip_spaces();
+            let found = self.source[self.pos..].starts_with(c);
+            if found {
+                self.pos += 1;
+            }
+            found
+        }
+
+        fn node(&self, node_type: NodeType, start: usize, children: Vec<UniversalNode>) -> UniversalNode {
+            let (start_line, start_col) = line_col(self.source, start);
+            let (end_line, end_col) = line_col(self.source, self.pos);
+            children.into_iter().fold(
+                UniversalNode::new(node_type)
+                    .with_text(self.source[start..self.pos].to_string())
+                    .with_location(start_line, start_col, end_line, end_col),
+                UniversalNode::add_child,
+            )
+        }
+
+        fn primary(&mut self) -> UniversalNode {
+            self.skip_spaces();
+            let start = self.pos;
+            if self.source[start..].starts_with('"') {
+                self.pos += string_end(&self.source[start..], '"');
+                return self.node(NodeType::Literal, start, Vec::new());
+            }
+            let rest = &self.source[start..];
+            self.pos += rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '.')).unwrap_or(rest.len());
+            let name = self.node(NodeType::Identifier, start, Vec::new());
+            if !self.eat('(') {
+                return name;
+            }
+            let args_start = self.pos - 1;
+            let mut args = Vec::new();
+            while !self.eat(')') {
+                args.push(self.expression());
+                self.eat(',');
+            }
+            let arguments = self.node(NodeType::Unknown, args_start, args);
+            self.node(NodeType::CallExpression, start, vec![name, arguments])
+        }
+
+        fn expression(&mut self) -> UniversalNode {
+            self.skip_spaces();
+            let start = self.pos;
+            let mut left = self.primary();
+            while self.eat('+') {
+                let plus = UniversalNode::new(NodeType::Unknown).with_text("+".to_string());
+                let right = self.primary();
+                left = self.node(NodeType::BinaryExpression, start, vec![left, plus, right]);
+            }
+            left
+        }
+
+        fn statement(&mut self) -> UniversalNode {
+            self.skip_spaces();
+            let start = self.pos;
+            if !self.eat('{') {
+                let expression = self.expression();
+                self.eat(';');
+                return self.node(NodeType::ExpressionStatement, start, vec![expression]);
+            }
+            let mut statements = Vec::new();
+            while !self.eat('}') {
+                statements.push(self.statement());
+            }
+            self.node(NodeType::BlockStatement, start, statements)
+        }
+    }
+
+    impl LanguageParser for CallParser {
+        fn parse(&self, source: &str, _file_path: &Path) -> Result<Box<dyn AstNode>> {
+            let mut cursor = Cursor { source, pos: 0 };
+            let mut statements = Vec::new();
+            loop {
+                cursor.skip_spaces();
+                if cursor.pos == source.len() {
+                    break;
+                }
+                statements.push(cursor.statement());
+            }
+            cursor.pos = source.len();
+            Ok(Box::new(cursor.node(NodeType::Program, 0, statements)))
+        }
+
+        fn language(&self) -> Language {
+            Language::JavaScript
+        }
+    }
+
+    fn matches(pattern: &str, code: &str) -> Vec<(String, HashMap<String, BoundMetavariable>)> {
+        let pattern = PatternAst::parse(pattern, &CallParser).expect("pattern parses");
+        let target = CallParser.parse(code, Path::new("code.js")).unwrap();
+        pattern
+            .find_matches(target.as_ref(), &CancellationToken::new())
+            .into_iter()
+            .map(|m| (m.nodes.iter().filter_map(|node| node.text()).collect::<Vec<_>>().join(" "), m.metavariables))
+            .collect()
+    }
+
+    #[test]
+    fn test_placeholders() {
+        assert_eq!(with_placeholders("f($X, $...REST, ...)", ";").unwrap(), "f(__astgrep_mv_X, __astgrep_ellipsis_REST, __astgrep_ellipsis)");
+        assert_eq!(with_placeholders("log(\"$X costs $5\", $_)", ";").unwrap(), "log(\"$X costs $5\", __astgrep_mv__)");
+        assert!(with_placeholders("exec(<... $X ...>)", ";").is_none());
+        // An ellipsis standing for statements becomes a statement
+        assert_eq!(with_placeholders("open();\n...\nclose();", ";").unwrap(), "open();\n__astgrep_ellipsis;\nclose();");
+        assert_eq!(with_placeholders("open()\n  ...\nclose()", "").unwrap(), "open()\n  __astgrep_ellipsis\nclose()");
+        assert_eq!(with_placeholders("open(\n...\n)", ";").unwrap(), "open(\n__astgrep_ellipsis\n)");
+    }
+
+    #[test]
+    fn test_pattern_root_is_deepest_spanning_node() {
+        let pattern = PatternAst::parse("exec($CMD);", &CallParser).unwrap();
+        assert_eq!(pattern.nodes().iter().map(|node| node.node_type()).collect::<Vec<_>>(), ["call_expression"]);
+        let pattern = PatternAst::parse("open($F);\n...\nclose($F);", &CallParser).unwrap();
+        assert_eq!(pattern.nodes().len(), 3);
+        // A lone metavariable would match every node
+        assert!(PatternAst::parse("$X", &CallParser).is_none());
+    }
+
+    #[test]
+    fn test_match_nodes_with_metavariables() {
+        let found = matches("exec($CMD)", "run(x); exec(build(a, b)); other.exec(y)");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].0, "exec(build(a, b))");
+        let cmd = &found[0].1["CMD"];
+        assert_eq!((cmd.text.as_str(), cmd.node_type.as_str(), cmd.location), ("build(a, b)", "call_expression", Some((1, 14, 1, 25))));
+
+        // Repeated metavariables must bind the same code; `$_` need not
+        assert_eq!(matches("eq($A, $A)", "eq(a, a); eq(a, b)").len(), 1);
+        assert_eq!(matches("eq($_, $_)", "eq(a, a); eq(a, b)").len(), 2);
+        // Code inside arguments is matched structurally, not as text
+        assert!(matches("exec(a + b)", "exec(a + b + c)").is_empty());
+    }
+
+    #[test]
+    fn test_match_ellipses_and_strings() {
+        assert_eq!(matches("login(..., password, ...)", "login(user, password); login(password); login(user)").len(), 2);
+        let found = matches("exec($CMD, $...REST)", "exec(cmd, a, b)");
+        assert_eq!(found[0].1["REST"].text, "a, b");
+        assert_eq!(found[0].1["REST"].location, Some((1, 11, 1, 15)));
+        assert_eq!(matches("query(\"...\")", "query(\"SELECT 1\"); query(sql)").len(), 1);
+    }
+
+    #[test]
+    fn test_match_statement_sequences() {
+        let found = matches("open($F);\n...\nclose($F);", "{ open(a); read(a); close(a); } { open(b); close(c); }");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].0, "open(a); read(a); close(a);");
+        assert_eq!(found[0].1["F"].text, "a");
+        // Consecutive statements only, within one block
+        assert!(matches("open(a);\nclose(a);", "open(a); read(a); close(a);").is_empty());
+        assert!(matches("open(a);\n...\nclose(a);", "{ open(a); } close(a);").is_empty());
+
+        // In a block pattern the ellipsis spans the statements before the last
+        let found = matches("{\n  ...\n  eval($X);\n}", "{ a(); b(); eval(x); } { eval(y); b(); }");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].1["X"].text, "x");
+    }
+
+    #[test]
+    fn test_search_stops_when_cancelled() {
+        // Three ellipses over many distinct arguments backtrack through every split
+        let pattern = PatternAst::parse("f(..., $A, ..., $B, ..., $A, ...)", &CallParser).unwrap();
+        let args: Vec<String> = (0..1000).map(|i| format!("a{}", i)).collect();
+        let target = CallParser.parse(&format!("f({});", args.join(", ")), Path::new("code.js")).unwrap();
+
+        let cancellation = CancellationToken::new();
+        let start = std::time::Instant::now();
+        let found = std::thread::scope(|scope| {
+            let token = cancellation.clone();
+            scope.spawn(move || {
+                std::thread::sleep(std::time::Duration::from_millis(50));
+                token.cancel();
+            });
+            pattern.find_matches(target.as_ref(), &cancellation).len()
+        });
+        assert_eq!(found, 0);
+        assert!(start.elapsed() < std::time::Duration::from_secs(5), "{:?}", start.elapsed());
+    }
+}
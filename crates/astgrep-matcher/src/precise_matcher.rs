@@ -8,6 +8,7 @@ use crate::parser::{PatternParser, ParsedPattern};
 use crate::metavar::MetavarManager;
 use astgrep_core::{AstNode, Result, AnalysisError, SemgrepPattern, PatternType, constants::defaults::analysis};
 use astgrep_ast::{NodeType, UniversalNode};
+use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet, VecDeque};
 
 /// Precise AST-based pattern matcher
@@ -23,19 +24,23 @@ pub struct PreciseExpressionMatcher {
 }
 
 /// Configuration for precise matching
-#[derive(Debug, Clone)]
+///
+/// Every field has a default, so partial TOML/YAML/JSON documents deserialize and
+/// library users can start from `MatchingConfig::new()` and chain `with_*` calls.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct MatchingConfig {
-    /// Enable structural matching (match AST structure)
+    /// Enable structural matching (match AST structure). Default: `true`
     pub structural_matching: bool,
-    /// Enable semantic matching (consider semantics)
+    /// Enable semantic matching (consider semantics). Default: `true`
     pub semantic_matching: bool,
-    /// Enable type-aware matching
+    /// Enable type-aware matching. Default: `true`
     pub type_aware_matching: bool,
-    /// Maximum depth for recursive matching
+    /// Maximum depth for recursive matching. Default: `MAX_ANALYSIS_DEPTH` (20)
     pub max_depth: usize,
-    /// Allow partial matches
+    /// Allow partial matches. Default: `false`
     pub allow_partial_matches: bool,
-    /// Similarity threshold for fuzzy matching
+    /// Similarity threshold for fuzzy matching, in `0.0..=1.0`. Default: `SIMILARITY_THRESHOLD` (0.8)
     pub similarity_threshold: f32,
 }
 
@@ -52,6 +57,63 @@ impl Default for MatchingConfig {
     }
 }
 
+impl MatchingConfig {
+    /// Create a configuration with the default settings
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enable or disable structural matching
+    pub fn with_structural_matching(mut self, enabled: bool) -> Self {
+        self.structural_matching = enabled;
+        self
+    }
+
+    /// Enable or disable semantic matching
+    pub fn with_semantic_matching(mut self, enabled: bool) -> Self {
+        self.semantic_matching = enabled;
+        self
+    }
+
+    /// Enable or disable type-aware matching
+    pub fn with_type_aware_matching(mut self, enabled: bool) -> Self {
+        self.type_aware_matching = enabled;
+        self
+    }
+
+    /// Set maximum depth for recursive matching
+    pub fn with_max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Allow or forbid partial matches
+    pub fn with_partial_matches(mut self, allowed: bool) -> Self {
+        self.allow_partial_matches = allowed;
+        self
+    }
+
+    /// Set the fuzzy matching similarity threshold
+    pub fn with_similarity_threshold(mut self, threshold: f32) -> Self {
+        self.similarity_threshold = threshold;
+        self
+    }
+
+    /// Check that the settings are usable
+    pub fn validate(&self) -> Result<()> {
+        if !(0.0..=1.0).contains(&self.similarity_threshold) {
+            return Err(AnalysisError::config_error(format!(
+                "similarity_threshold must be between 0.0 and 1.0, got {}",
+                self.similarity_threshold
+            )));
+        }
+        if self.max_depth == 0 {
+            return Err(AnalysisError::config_error("max_depth must be greater than 0"));
+        }
+        Ok(())
+    }
+}
+
 /// AST-based pattern representation
 #[derive(Debug, Clone)]
 pub struct AstPattern {
@@ -182,6 +244,11 @@ impl PreciseExpressionMatcher {
         }
     }
 
+    /// Get the matching configuration
+    pub fn config(&self) -> &MatchingConfig {
+        &self.config
+    }
+
     /// Find all precise matches for a pattern in the AST
     pub fn find_precise_matches(
         &mut self,
@@ -311,6 +378,16 @@ impl PreciseExpressionMatcher {
                 }
                 Ok(PatternNode::Alternative { patterns: pattern_nodes })
             }
+            ParsedPattern::Children(elements) => {
+                let mut pattern_nodes = Vec::new();
+                for element in elements {
+                    pattern_nodes.push(self.convert_parsed_node_to_pattern_node(element)?);
+                }
+                Ok(PatternNode::Sequence { patterns: pattern_nodes })
+            }
+            // The search visits every node, so the inner pattern finds what the deep
+            // expression would
+            ParsedPattern::Deep(inner) => self.convert_parsed_node_to_pattern_node(inner),
             ParsedPattern::Wildcard => Ok(PatternNode::Metavariable {
                 name: "$_".to_string(),
                 constraints: Vec::new(),
@@ -448,3 +525,22 @@ impl PreciseExpressionMatcher {
         Ok(matches)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matching_config_builder_and_serde() {
+        let config = MatchingConfig::new()
+            .with_semantic_matching(false)
+            .with_max_depth(8)
+            .with_similarity_threshold(0.5);
+        assert!(config.validate().is_ok());
+        assert_eq!(PreciseExpressionMatcher::with_config(config.clone()).config(), &config);
+
+        let parsed: MatchingConfig = serde_json::from_str(r#"{"max_depth": 8, "semantic_matching": false, "similarity_threshold": 0.5}"#).unwrap();
+        assert_eq!(parsed, config);
+        assert!(MatchingConfig::new().with_similarity_threshold(1.5).validate().is_err());
+    }
+}
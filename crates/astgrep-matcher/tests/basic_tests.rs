@@ -250,7 +250,7 @@ fn test_complex_ast_structure() {
 
 #[test]
 fn test_pattern_matching_with_metavariables() {
-    let mut matcher = AdvancedSemgrepMatcher::new();
+    let matcher = AdvancedSemgrepMatcher::new();
 
     // Create a pattern with metavariables
     let pattern = SemgrepPattern {
@@ -283,7 +283,7 @@ fn test_pattern_matching_with_metavariables() {
 
 #[test]
 fn test_pattern_matching_edge_cases() {
-    let mut matcher = AdvancedSemgrepMatcher::new();
+    let matcher = AdvancedSemgrepMatcher::new();
 
     // Test with empty pattern
     let empty_pattern = SemgrepPattern {
@@ -0,0 +1,306 @@
+//! Mixed-language single-file components
+//!
+//! Vue and Svelte components keep script, style and template sections in one file, and
+//! Blade templates embed PHP in HTML. [`split_component`] cuts such a file into regions
+//! so each can be parsed with its own language; [`SourceRegion::map_location`] maps a
+//! location found in a region back to the component file.
+
+use astgrep_core::{Language, Location};
+use std::path::Path;
+
+/// Kind of mixed-language file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComponentKind {
+    Vue,
+    Svelte,
+    /// Laravel Blade template (`.blade.php`)
+    Blade,
+}
+
+impl ComponentKind {
+    /// Component kind of `path`, judged by its file name
+    pub fn for_path(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_str()?.to_ascii_lowercase();
+        if name.ends_with(".blade.php") {
+            Some(ComponentKind::Blade)
+        } else if name.ends_with(".vue") {
+            Some(ComponentKind::Vue)
+        } else if name.ends_with(".svelte") {
+            Some(ComponentKind::Svelte)
+        } else {
+            None
+        }
+    }
+
+    /// Languages the analyzable regions of this kind of component are written in
+    pub fn languages(&self) -> &'static [Language] {
+        match self {
+            ComponentKind::Vue | ComponentKind::Svelte => &[Language::JavaScript],
+            ComponentKind::Blade => &[Language::Php],
+        }
+    }
+}
+
+/// Role of a region within its component
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegionKind {
+    /// `<script>` block
+    Script,
+    /// `<style>` block
+    Style,
+    /// Template markup of a Vue or Svelte component
+    Template,
+    /// PHP code of a Blade template: echoes, `@php` blocks and `<?php` tags
+    Php,
+    /// HTML between the PHP segments of a Blade template
+    Markup,
+}
+
+/// Contiguous part of a component written in a single language
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceRegion {
+    pub kind: RegionKind,
+    /// Language to parse the region with; `None` when no parser applies
+    pub language: Option<Language>,
+    pub content: String,
+    /// Byte offset of `content` in the component
+    pub offset: usize,
+    /// 1-based line of the region's first byte in the component
+    pub start_line: usize,
+    /// 1-based byte column of the region's first byte in the component
+    pub start_column: usize,
+}
+
+impl SourceRegion {
+    fn new(kind: RegionKind, language: Option<Language>, source: &str, start: usize, end: usize) -> Self {
+        let before = &source[..start];
+        let start_line = before.matches('\n').count() + 1;
+        let start_column = start - before.rfind('\n').map(|i| i + 1).unwrap_or(0) + 1;
+        Self {
+            kind,
+            language,
+            content: source[start..end].to_string(),
+            offset: start,
+            start_line,
+            start_column,
+        }
+    }
+
+    /// Component position of a 1-based (line, column) position in the region
+    pub fn to_original(&self, line: usize, column: usize) -> (usize, usize) {
+        if line <= 1 {
+            (self.start_line, self.start_column + column.saturating_sub(1))
+        } else {
+            (self.start_line + line - 1, column)
+        }
+    }
+
+    /// `location` in the region, moved to the component's coordinates
+    pub fn map_location(&self, location: &Location) -> Location {
+        let (start_line, start_column) = self.to_original(location.start_line, location.start_column);
+        let (end_line, end_column) = self.to_original(location.end_line, location.end_column);
+        Location::new(location.file.clone(), start_line, start_column, end_line, end_column)
+    }
+}
+
+/// Split a component into its regions, in file order; whitespace-only stretches
+/// between code regions are dropped
+pub fn split_component(kind: ComponentKind, source: &str) -> Vec<SourceRegion> {
+    let (code, filler) = match kind {
+        ComponentKind::Vue | ComponentKind::Svelte => (find_blocks(source), RegionKind::Template),
+        ComponentKind::Blade => (find_blade_php(source), RegionKind::Markup),
+    };
+
+    let mut regions = Vec::new();
+    let mut cursor = 0;
+    for (start, end, region) in code {
+        if !source[cursor..start].trim().is_empty() {
+            regions.push(SourceRegion::new(filler, None, source, cursor, start));
+        }
+        regions.push(region);
+        cursor = end;
+    }
+    if !source[cursor..].trim().is_empty() {
+        regions.push(SourceRegion::new(filler, None, source, cursor, source.len()));
+    }
+    regions
+}
+
+/// `<script>` and `<style>` blocks as (block start, block end, content region)
+fn find_blocks(source: &str) -> Vec<(usize, usize, SourceRegion)> {
+    let lower = source.to_ascii_lowercase();
+    let mut blocks = Vec::new();
+    let mut cursor = 0;
+
+    while let Some((start, tag)) = next_open_tag(&lower, cursor, &["script", "style"]) {
+        let content_start = match lower[start..].find('>') {
+            Some(i) => start + i + 1,
+            None => break,
+        };
+        let close = format!("</{}", tag);
+        let content_end = lower[content_start..].find(&close).map_or(source.len(), |i| content_start + i);
+        let block_end = lower[content_end..].find('>').map_or(source.len(), |i| content_end + i + 1);
+
+        let (kind, language) = if tag == "script" {
+            (RegionKind::Script, script_language(&source[start..content_start]))
+        } else {
+            (RegionKind::Style, None)
+        };
+        blocks.push((start, block_end, SourceRegion::new(kind, language, source, content_start, content_end)));
+        cursor = block_end;
+    }
+    blocks
+}
+
+/// Earliest `<tag` (followed by whitespace, `>` or `/`) of one of `tags` at or after `from`
+fn next_open_tag(lower: &str, from: usize, tags: &[&'static str]) -> Option<(usize, &'static str)> {
+    tags.iter()
+        .filter_map(|&tag| {
+            let needle = format!("<{}", tag);
+            let mut search = from;
+            while let Some(i) = lower[search..].find(&needle) {
+                let at = search + i;
+                match lower[at + needle.len()..].chars().next() {
+                    Some(c) if c.is_whitespace() || c == '>' || c == '/' => return Some((at, tag)),
+                    _ => search = at + needle.len(),
+                }
+            }
+            None
+        })
+        .min_by_key(|&(at, _)| at)
+}
+
+/// Language of a `<script>` block from its `lang` attribute; JavaScript also covers
+/// TypeScript, other languages are not analyzed
+fn script_language(open_tag: &str) -> Option<Language> {
+    let lower = open_tag.to_ascii_lowercase();
+    let lang = match lower.find("lang=") {
+        Some(i) => lower[i + 5..].trim_start_matches(['"', '\'']).split(['"', '\'', ' ', '>']).next().unwrap_or(""),
+        None => return Some(Language::JavaScript),
+    };
+    match lang {
+        "" | "js" | "javascript" | "jsx" | "ts" | "typescript" | "tsx" => Some(Language::JavaScript),
+        _ => None,
+    }
+}
+
+/// PHP segments of a Blade template as (segment start, segment end, code region)
+fn find_blade_php(source: &str) -> Vec<(usize, usize, SourceRegion)> {
+    // (opening, closing); comments and `@{{` escapes produce no code
+    const DELIMITERS: &[(&str, &str)] = &[
+        ("{{--", "--}}"),
+        ("@{{", "}}"),
+        ("{!!", "!!}"),
+        ("{{", "}}"),
+        ("@php", "@endphp"),
+        ("<?php", "?>"),
+        ("<?=", "?>"),
+    ];
+
+    let mut segments = Vec::new();
+    let mut cursor = 0;
+    loop {
+        // Earliest opening; at equal positions the longer delimiter listed first wins
+        let next = DELIMITERS
+            .iter()
+            .filter_map(|&(open, close)| source[cursor..].find(open).map(|i| (cursor + i, open, close)))
+            .min_by_key(|&(at, _, _)| at);
+        let (start, open, close) = match next {
+            Some(next) => next,
+            None => break,
+        };
+
+        let content_start = start + open.len();
+        // Inline `@php($x = 1)` form
+        if open == "@php" && source[content_start..].starts_with('(') {
+            if let Some(end) = matching_paren(source, content_start) {
+                segments.push((start, end + 1, SourceRegion::new(RegionKind::Php, Some(Language::Php), source, content_start + 1, end)));
+                cursor = end + 1;
+                continue;
+            }
+        }
+
+        let content_end = source[content_start..].find(close).map_or(source.len(), |i| content_start + i);
+        let end = (content_end + close.len()).min(source.len());
+        if open != "{{--" && open != "@{{" {
+            segments.push((start, end, SourceRegion::new(RegionKind::Php, Some(Language::Php), source, content_start, content_end)));
+        }
+        cursor = end;
+    }
+    segments
+}
+
+/// Offset of the `)` closing the `(` at `open`
+fn matching_paren(source: &str, open: usize) -> Option<usize> {
+    let mut depth = 0usize;
+    for (i, c) in source[open..].char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(open + i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_component_kind_for_path() {
+        assert_eq!(ComponentKind::for_path(Path::new("src/App.vue")), Some(ComponentKind::Vue));
+        assert_eq!(ComponentKind::for_path(Path::new("Button.svelte")), Some(ComponentKind::Svelte));
+        assert_eq!(ComponentKind::for_path(Path::new("views/home.blade.php")), Some(ComponentKind::Blade));
+        assert_eq!(ComponentKind::for_path(Path::new("index.php")), None);
+    }
+
+    #[test]
+    fn test_split_vue() {
+        let source = "<template>\n  <div v-html=\"html\"></div>\n</template>\n\n<script setup lang=\"ts\">\nconst html = location.hash;\n</script>\n\n<style scoped>\ndiv { color: red; }\n</style>\n";
+        let regions = split_component(ComponentKind::Vue, source);
+        let kinds: Vec<_> = regions.iter().map(|r| r.kind).collect();
+        assert_eq!(kinds, vec![RegionKind::Template, RegionKind::Script, RegionKind::Style]);
+
+        let script = &regions[1];
+        assert_eq!(script.language, Some(Language::JavaScript));
+        assert_eq!(script.content, "\nconst html = location.hash;\n");
+        assert_eq!((script.start_line, script.start_column), (5, 25));
+        // `location` on the script's second line is line 6, column 14 of the component
+        assert_eq!(script.to_original(2, 14), (6, 14));
+
+        let mapped = script.map_location(&Location::new(PathBuf::from("App.vue"), 2, 14, 2, 27));
+        assert_eq!((mapped.start_line, mapped.start_column, mapped.end_column), (6, 14, 27));
+        assert_eq!(regions[2].language, None);
+    }
+
+    #[test]
+    fn test_split_svelte_skips_unknown_script_language() {
+        let source = "<script lang=\"coffee\">x = 1</script>\n<h1>{name}</h1>\n<script context=\"module\">export const y = 2;</script>";
+        let regions = split_component(ComponentKind::Svelte, source);
+        assert_eq!(regions.len(), 3);
+        assert_eq!(regions[0].language, None);
+        assert_eq!(regions[1].kind, RegionKind::Template);
+        assert_eq!(regions[2].language, Some(Language::JavaScript));
+        assert_eq!(regions[2].content, "export const y = 2;");
+        assert_eq!(regions[0].to_original(1, 1), (1, 23));
+    }
+
+    #[test]
+    fn test_split_blade() {
+        let source = "<p>{{ $name }}</p>\n{{-- {{ $hidden }} --}}\n@{{ notPhp }}\n<div>{!! $request->input('bio') !!}</div>\n@php\n$x = eval($code);\n@endphp\n@php($y = 1)\n";
+        let regions = split_component(ComponentKind::Blade, source);
+        let php: Vec<&str> = regions.iter().filter(|r| r.kind == RegionKind::Php).map(|r| r.content.as_str()).collect();
+        assert_eq!(php, vec![" $name ", " $request->input('bio') ", "\n$x = eval($code);\n", "$y = 1"]);
+
+        let raw = regions.iter().find(|r| r.content.contains("bio")).unwrap();
+        assert_eq!((raw.start_line, raw.start_column), (4, 9));
+        assert!(regions.iter().any(|r| r.kind == RegionKind::Markup && r.content.contains("notPhp")));
+    }
+}
@@ -0,0 +1,192 @@
+//! Parser health checks
+//!
+//! Verifies at startup that every bundled tree-sitter grammar loads and that each
+//! registered parser can handle a small smoke snippet, so callers can fall back to
+//! regex-only analysis for a broken language instead of failing on every file.
+
+use crate::LanguageParserRegistry;
+use astgrep_core::constants::languages::ALL_LANGUAGES;
+use astgrep_core::Language;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::Path;
+
+/// Health of one language's parsing stack
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParserStatus {
+    /// Grammar and parser handled the smoke snippet
+    Healthy,
+    /// A grammar failed to load or a parser failed on the smoke snippet
+    Broken(String),
+    /// No parser is registered for the language
+    Unsupported,
+}
+
+/// Health check result for one language
+#[derive(Debug, Clone)]
+pub struct ParserHealth {
+    pub language: Language,
+    pub status: ParserStatus,
+}
+
+/// Health of all languages known to the registry
+#[derive(Debug, Clone, Default)]
+pub struct ParserHealthReport {
+    pub entries: Vec<ParserHealth>,
+}
+
+impl ParserHealthReport {
+    /// Whether `language` has a broken parser and should be analyzed regex-only
+    pub fn is_degraded(&self, language: Language) -> bool {
+        self.reason(language).is_some()
+    }
+
+    /// Why `language` is degraded, if it is
+    pub fn reason(&self, language: Language) -> Option<&str> {
+        self.entries.iter().find(|e| e.language == language).and_then(|e| match &e.status {
+            ParserStatus::Broken(reason) => Some(reason.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Degraded languages with their reasons
+    pub fn degraded(&self) -> Vec<(Language, &str)> {
+        self.entries
+            .iter()
+            .filter_map(|e| match &e.status {
+                ParserStatus::Broken(reason) => Some((e.language, reason.as_str())),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// Small snippet every healthy parser for `language` must accept
+pub fn smoke_snippet(language: Language) -> &'static str {
+    match language {
+        Language::Java => "System.out.println(\"ok\");",
+        Language::JavaScript => "console.log(\"ok\");",
+        Language::Python => "print(\"ok\")",
+        Language::Sql => "SELECT id FROM users",
+        Language::Bash => "echo ok",
+        Language::Php => "<?php echo \"ok\";",
+        Language::CSharp => "string x = \"ok\";",
+        Language::C => "int x = 1;",
+        Language::Ruby => "puts \"ok\"",
+        Language::Kotlin => "println(\"ok\")",
+        Language::Swift => "print(\"ok\")",
+        Language::Xml => "<root/>",
+    }
+}
+
+/// Tree-sitter grammar bundled for `language`, if any
+fn bundled_grammar(language: Language) -> Option<tree_sitter::Language> {
+    match language {
+        Language::Python => Some(tree_sitter_python::LANGUAGE.into()),
+        Language::JavaScript => Some(tree_sitter_javascript::LANGUAGE.into()),
+        Language::Java => Some(tree_sitter_java::LANGUAGE.into()),
+        Language::Bash => Some(tree_sitter_bash::LANGUAGE.into()),
+        #[cfg(feature = "sql-tree-sitter")]
+        Language::Sql => Some(tree_sitter_sequel::LANGUAGE.into()),
+        _ => None,
+    }
+}
+
+/// Load the bundled grammar and parse the smoke snippet with it
+fn check_grammar(language: Language) -> Result<(), String> {
+    let grammar = match bundled_grammar(language) {
+        Some(grammar) => grammar,
+        None => return Ok(()),
+    };
+    let snippet = smoke_snippet(language);
+    let outcome = catch_unwind(AssertUnwindSafe(|| {
+        let mut parser = tree_sitter::Parser::new();
+        parser
+            .set_language(&grammar)
+            .map_err(|e| format!("tree-sitter grammar failed to load: {}", e))?;
+        let tree = parser
+            .parse(snippet, None)
+            .ok_or_else(|| "tree-sitter grammar returned no tree for the smoke snippet".to_string())?;
+        if tree.root_node().has_error() {
+            return Err("tree-sitter grammar reported syntax errors in the smoke snippet".to_string());
+        }
+        Ok(())
+    }));
+    outcome.unwrap_or_else(|_| Err("tree-sitter grammar panicked on the smoke snippet".to_string()))
+}
+
+impl LanguageParserRegistry {
+    /// Check every language's grammar and parser against its smoke snippet
+    pub fn health_check(&self) -> ParserHealthReport {
+        let entries = ALL_LANGUAGES
+            .iter()
+            .map(|&language| ParserHealth { language, status: self.check_language(language) })
+            .collect();
+        ParserHealthReport { entries }
+    }
+
+    fn check_language(&self, language: Language) -> ParserStatus {
+        if let Err(reason) = check_grammar(language) {
+            return ParserStatus::Broken(reason);
+        }
+        let parser = match self.get_parser(language) {
+            Some(parser) => parser,
+            None => return ParserStatus::Unsupported,
+        };
+
+        let path = format!("smoke{}", language.extensions().first().copied().unwrap_or(""));
+        let outcome = catch_unwind(AssertUnwindSafe(|| parser.parse(smoke_snippet(language), Path::new(&path))));
+        match outcome {
+            Ok(Ok(_)) => ParserStatus::Healthy,
+            Ok(Err(e)) => ParserStatus::Broken(format!("parser failed on the smoke snippet: {}", e)),
+            Err(_) => ParserStatus::Broken("parser panicked on the smoke snippet".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use astgrep_core::{AnalysisError, AstNode, LanguageParser, Result};
+
+    struct FailingParser;
+
+    impl LanguageParser for FailingParser {
+        fn parse(&self, _source: &str, _file_path: &Path) -> Result<Box<dyn AstNode>> {
+            Err(AnalysisError::parse_error("grammar unavailable"))
+        }
+
+        fn language(&self) -> Language {
+            Language::Ruby
+        }
+
+        fn supports_file(&self, _file_path: &Path) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn test_default_registry_is_healthy() {
+        let report = LanguageParserRegistry::new().health_check();
+        assert!(report.degraded().is_empty(), "{:?}", report.degraded());
+        assert!(report.entries.iter().any(|e| e.language == Language::Xml && e.status == ParserStatus::Unsupported));
+    }
+
+    #[test]
+    fn test_failing_parser_is_degraded() {
+        let mut registry = LanguageParserRegistry::new();
+        registry.register_parser(Language::Ruby, Box::new(FailingParser));
+        let report = registry.health_check();
+        assert!(report.is_degraded(Language::Ruby));
+        assert!(report.reason(Language::Ruby).unwrap().contains("grammar unavailable"));
+        assert!(!report.is_degraded(Language::Python));
+    }
+
+    #[test]
+    #[cfg(feature = "sql-tree-sitter")]
+    fn test_tree_sitter_languages_match_bundled_grammars() {
+        use astgrep_core::constants::languages::TREE_SITTER_LANGUAGES;
+        for &language in ALL_LANGUAGES {
+            assert_eq!(bundled_grammar(language).is_some(), TREE_SITTER_LANGUAGES.contains(&language), "{:?}", language);
+        }
+    }
+}
@@ -4,7 +4,7 @@
 
 use crate::adapters::{AdapterContext, AdapterMetadata, AstAdapter, BaseParser};
 use astgrep_ast::{AstBuilder, UniversalNode};
-use astgrep_core::{AstNode, Language, LanguageParser, Result};
+use astgrep_core::{AstNode, CancellationToken, Language, LanguageParser, Result};
 use std::path::Path;
 
 /// JavaScript AST adapter
@@ -291,12 +291,17 @@ impl JavaScriptParser {
 
 impl LanguageParser for JavaScriptParser {
     fn parse(&self, source: &str, file_path: &Path) -> Result<Box<dyn AstNode>> {
+        self.parse_cancellable(source, file_path, &CancellationToken::new())
+    }
+
+    fn parse_cancellable(&self, source: &str, file_path: &Path, cancellation: &CancellationToken) -> Result<Box<dyn AstNode>> {
         // Try to use tree-sitter parser first for better AST structure
-        if let Ok(mut ts_parser) = crate::tree_sitter_parser::TreeSitterParser::new() {
-            if let Ok(Some(tree)) = ts_parser.parse(source, Language::JavaScript) {
-                if let Ok(universal_node) = ts_parser.tree_to_universal_ast(&tree, source) {
-                    return Ok(Box::new(universal_node));
-                }
+        let ts_parser = crate::tree_sitter_parser::TreeSitterParser::shared();
+        let parsed = ts_parser.parse_cancellable(source, Language::JavaScript, cancellation);
+        cancellation.check()?;
+        if let Ok(Some(tree)) = parsed {
+            if let Ok(universal_node) = ts_parser.tree_to_universal_ast(&tree, source) {
+                return Ok(Box::new(universal_node));
             }
         }
 
@@ -21,9 +21,13 @@ pub mod ruby;
 pub mod kotlin;
 pub mod swift;
 pub mod xml;
+pub mod health;
+pub mod components;
 
 pub use registry::*;
 pub use adapters::*;
+pub use health::*;
+pub use components::*;
 
 // Re-export types for macro usage
 pub use astgrep_core::{Language, Result, AstNode, LanguageParser};
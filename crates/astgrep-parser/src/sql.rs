@@ -4,7 +4,7 @@
 
 use crate::adapters::{AdapterContext, AdapterMetadata, AstAdapter};
 use astgrep_ast::{AstBuilder, UniversalNode};
-use astgrep_core::{AstNode, Language, LanguageParser, Result};
+use astgrep_core::{AstNode, CancellationToken, Language, LanguageParser, Result};
 use std::path::Path;
 
 /// SQL AST adapter
@@ -287,15 +287,20 @@ impl SqlParser {
 
 impl LanguageParser for SqlParser {
     fn parse(&self, source: &str, file_path: &Path) -> Result<Box<dyn AstNode>> {
+        self.parse_cancellable(source, file_path, &CancellationToken::new())
+    }
+
+    fn parse_cancellable(&self, source: &str, file_path: &Path, cancellation: &CancellationToken) -> Result<Box<dyn AstNode>> {
         // Prefer tree-sitter (tree-sitter-sequel) by default; allow override via env: ASTGREP_SQL_PARSER=manual
         #[cfg(feature = "sql-tree-sitter")]
         {
             if std::env::var("ASTGREP_SQL_PARSER").as_deref() != Ok("manual") {
-                if let Ok(mut ts_parser) = crate::tree_sitter_parser::TreeSitterParser::new() {
-                    if let Ok(Some(tree)) = ts_parser.parse(source, Language::Sql) {
-                        if let Ok(universal_node) = ts_parser.tree_to_universal_ast(&tree, source) {
-                            return Ok(Box::new(universal_node));
-                        }
+                let ts_parser = crate::tree_sitter_parser::TreeSitterParser::shared();
+                let parsed = ts_parser.parse_cancellable(source, Language::Sql, cancellation);
+                cancellation.check()?;
+                if let Ok(Some(tree)) = parsed {
+                    if let Ok(universal_node) = ts_parser.tree_to_universal_ast(&tree, source) {
+                        return Ok(Box::new(universal_node));
                     }
                 }
             }
@@ -3,9 +3,10 @@
 //! This module provides tree-sitter based parsing for various languages.
 
 use astgrep_ast::{UniversalNode, NodeType};
-use astgrep_core::{Language, Result};
-use tree_sitter::{Parser, Tree, Node};
+use astgrep_core::{diagnostics_enabled, record_diagnostic, AnalysisError, CancellationToken, DiagnosticKind, Language, Result};
+use tree_sitter::{Parser, ParseOptions, Tree, Node};
 use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard, OnceLock};
 
 /// Pattern types for AST-based matching
 #[derive(Debug, Clone)]
@@ -73,82 +74,128 @@ impl MetaVariableBindings {
 }
 
 /// Tree-sitter based parser
+///
+/// Shareable across threads: the grammars are immutable and the stateful
+/// `tree_sitter::Parser`s are checked out of a per-language pool for each `parse` call,
+/// so one instance serves the whole parallel pipeline without re-creating parsers per file.
 pub struct TreeSitterParser {
-    parsers: HashMap<Language, Parser>,
+    languages: HashMap<Language, tree_sitter::Language>,
+    pool: Mutex<HashMap<Language, Vec<Parser>>>,
 }
 
 impl TreeSitterParser {
     /// Create a new tree-sitter parser
     pub fn new() -> Result<Self> {
-        let mut parsers = HashMap::new();
-        
+        let mut this = Self { languages: HashMap::new(), pool: Mutex::new(HashMap::new()) };
+
         // Initialize Python parser
-        let mut parser = Parser::new();
-        if parser.set_language(&tree_sitter_python::LANGUAGE.into()).is_ok() {
-            parsers.insert(Language::Python, parser);
-        }
+        this.register(Language::Python, tree_sitter_python::LANGUAGE.into());
 
         // Initialize JavaScript parser
-        let mut parser = Parser::new();
-        if parser.set_language(&tree_sitter_javascript::LANGUAGE.into()).is_ok() {
-            parsers.insert(Language::JavaScript, parser);
-        }
+        this.register(Language::JavaScript, tree_sitter_javascript::LANGUAGE.into());
 
         // Initialize Java parser
-        let mut parser = Parser::new();
-        if parser.set_language(&tree_sitter_java::LANGUAGE.into()).is_ok() {
-            parsers.insert(Language::Java, parser);
-        }
+        this.register(Language::Java, tree_sitter_java::LANGUAGE.into());
 
         // Initialize PHP parser (if available)
         #[cfg(feature = "php")]
         {
-            let mut parser = Parser::new();
             if let Ok(php_lang) = tree_sitter_php::language() {
-                if parser.set_language(php_lang).is_ok() {
-                    parsers.insert(Language::PHP, parser);
-                }
+                this.register(Language::PHP, php_lang);
             }
         }
 
         // Initialize SQL parser via tree-sitter-sequel when feature enabled
         #[cfg(feature = "sql-tree-sitter")]
         {
-            let mut parser = Parser::new();
-            if parser.set_language(&tree_sitter_sequel::LANGUAGE.into()).is_ok() {
-                parsers.insert(Language::Sql, parser);
-            }
+            this.register(Language::Sql, tree_sitter_sequel::LANGUAGE.into());
         }
 
         // Initialize Bash parser
-        {
-            let mut parser = Parser::new();
-            if parser.set_language(&tree_sitter_bash::LANGUAGE.into()).is_ok() {
-                parsers.insert(Language::Bash, parser);
-            }
-        }
+        this.register(Language::Bash, tree_sitter_bash::LANGUAGE.into());
 
-        Ok(Self { parsers })
+        Ok(this)
     }
-    
-    /// Parse source code using tree-sitter
-    pub fn parse(&mut self, source: &str, language: Language) -> Result<Option<Tree>> {
-        if let Some(parser) = self.parsers.get_mut(&language) {
-            Ok(parser.parse(source, None))
-        } else {
-            Ok(None)
+
+    /// Process-wide instance shared by the language parsers
+    pub fn shared() -> &'static TreeSitterParser {
+        static SHARED: OnceLock<TreeSitterParser> = OnceLock::new();
+        SHARED.get_or_init(TreeSitterParser::default)
+    }
+
+    /// Register a grammar, seeding the pool with the parser that validated it
+    fn register(&mut self, language: Language, grammar: tree_sitter::Language) {
+        let mut parser = Parser::new();
+        if parser.set_language(&grammar).is_ok() {
+            self.languages.insert(language, grammar);
+            self.pool.get_mut().unwrap_or_else(|e| e.into_inner()).insert(language, vec![parser]);
         }
     }
+
+    /// Parse source code using tree-sitter
+    pub fn parse(&self, source: &str, language: Language) -> Result<Option<Tree>> {
+        self.parse_cancellable(source, language, &CancellationToken::new())
+    }
+
+    /// Parse source code using tree-sitter, which polls `cancellation` as it goes and
+    /// stops with `AnalysisError::Cancelled` once it is set
+    pub fn parse_cancellable(&self, source: &str, language: Language, cancellation: &CancellationToken) -> Result<Option<Tree>> {
+        cancellation.check()?;
+        let Some(grammar) = self.languages.get(&language) else {
+            return Ok(None);
+        };
+
+        let pooled = self.lock_pool().get_mut(&language).and_then(|parsers| parsers.pop());
+        let mut parser = match pooled {
+            Some(parser) => parser,
+            None => {
+                let mut parser = Parser::new();
+                parser
+                    .set_language(grammar)
+                    .map_err(|e| AnalysisError::parse_error(format!("Cannot load the {} grammar: {}", language.as_str(), e)))?;
+                parser
+            }
+        };
+
+        let bytes = source.as_bytes();
+        let mut stop = |_: &tree_sitter::ParseState| cancellation.is_cancelled();
+        let tree = parser.parse_with_options(
+            &mut |offset, _| bytes.get(offset..).unwrap_or_default(),
+            None,
+            Some(ParseOptions::new().progress_callback(&mut stop)),
+        );
+        parser.reset();
+        self.lock_pool().entry(language).or_default().push(parser);
+        cancellation.check()?;
+        Ok(tree)
+    }
+
+    /// The parser pool; a panic while it was held cannot leave it inconsistent
+    fn lock_pool(&self) -> MutexGuard<'_, HashMap<Language, Vec<Parser>>> {
+        self.pool.lock().unwrap_or_else(|e| e.into_inner())
+    }
     
     /// Convert tree-sitter tree to universal AST
     pub fn tree_to_universal_ast(&self, tree: &Tree, source: &str) -> Result<UniversalNode> {
         let root_node = tree.root_node();
-        self.convert_node(&root_node, source)
+        let language = if diagnostics_enabled() {
+            self.languages.iter().find(|(_, grammar)| **grammar == *tree.language()).map(|(language, _)| *language)
+        } else {
+            None
+        };
+        self.convert_node(&root_node, source, language)
     }
     
-    /// Convert a tree-sitter node to universal node with improved precision
-    fn convert_node(&self, node: &Node, source: &str) -> Result<UniversalNode> {
+    /// Convert a tree-sitter node to universal node with improved precision; `language` is
+    /// only known when nodes without a universal type are recorded as diagnostics
+    fn convert_node(&self, node: &Node, source: &str, language: Option<Language>) -> Result<UniversalNode> {
         let node_type = self.map_node_type(node.kind());
+        if language.is_some() && matches!(node_type, NodeType::Unknown) && !node.is_error() {
+            record_diagnostic(DiagnosticKind::UnconvertedNode, language, node.kind(), || {
+                let text = node.utf8_text(source.as_bytes()).unwrap_or("");
+                text.lines().next().unwrap_or("").chars().take(80).collect()
+            });
+        }
         let text = node.utf8_text(source.as_bytes()).unwrap_or("").to_string();
 
         // Calculate precise location information
@@ -179,7 +226,7 @@ impl TreeSitterParser {
             if let Some(child) = node.child(i) {
                 // Skip certain auxiliary nodes that don't add semantic value
                 if self.should_include_child(&child) {
-                    let child_universal = self.convert_node(&child, source)?;
+                    let child_universal = self.convert_node(&child, source, language)?;
                     universal_node = universal_node.add_child(child_universal);
                 }
             }
@@ -1073,7 +1120,7 @@ impl TreeSitterParser {
 
 impl Default for TreeSitterParser {
     fn default() -> Self {
-        Self::new().unwrap_or_else(|_| Self { parsers: HashMap::new() })
+        Self::new().unwrap_or_else(|_| Self { languages: HashMap::new(), pool: Mutex::new(HashMap::new()) })
     }
 }
 
@@ -1084,7 +1131,7 @@ mod tests {
 
     #[test]
     fn test_python_parsing() {
-        let mut parser = TreeSitterParser::new().unwrap();
+        let parser = TreeSitterParser::new().unwrap();
         let source = r#"
 def hello():
     print("world")
@@ -1099,7 +1146,7 @@ def hello():
     
     #[test]
     fn test_pattern_matching() {
-        let mut parser = TreeSitterParser::new().unwrap();
+        let parser = TreeSitterParser::new().unwrap();
         let source = r#"
 print("hello")
 x = 42
@@ -1120,4 +1167,45 @@ eval(code)
             assert!(!matches.is_empty());
         }
     }
+
+    #[test]
+    fn test_parser_is_shared_across_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<TreeSitterParser>();
+
+        let parser = TreeSitterParser::new().unwrap();
+        std::thread::scope(|scope| {
+            for i in 0..4 {
+                let parser = &parser;
+                scope.spawn(move || {
+                    let source = format!("def f{}():\n    return {}\n", i, i);
+                    let tree = parser.parse(&source, Language::Python).unwrap().unwrap();
+                    assert!(!tree.root_node().has_error());
+                });
+            }
+        });
+        assert!(parser.parse("x = 1", Language::Python).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_parse_stops_when_cancelled() {
+        let parser = TreeSitterParser::new().unwrap();
+        let cancelled = CancellationToken::new();
+        cancelled.cancel();
+        assert!(matches!(parser.parse_cancellable("x = 1", Language::Python, &cancelled), Err(AnalysisError::Cancelled)));
+
+        // A long parse is interrupted part way, and its parser goes back to the pool usable
+        let source = format!("const values = [{}];\n", "f(1, [2, {a: 3}]), ".repeat(200_000));
+        let cancellation = CancellationToken::new();
+        let parsed = std::thread::scope(|scope| {
+            let token = cancellation.clone();
+            scope.spawn(move || {
+                std::thread::sleep(std::time::Duration::from_millis(20));
+                token.cancel();
+            });
+            parser.parse_cancellable(&source, Language::JavaScript, &cancellation)
+        });
+        assert!(matches!(parsed, Err(AnalysisError::Cancelled)));
+        assert!(!parser.parse("let x = 1;", Language::JavaScript).unwrap().unwrap().root_node().has_error());
+    }
 }
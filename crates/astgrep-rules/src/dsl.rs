@@ -0,0 +1,412 @@
+//! Typed DSL for building rules in Rust code
+//!
+//! Embedders that generate rules at runtime (for example from a banned-API table)
+//! can assemble them here instead of templating YAML. The pattern combinators mirror
+//! the YAML operators, and [`RuleBuilder::build`] runs the same validation the YAML
+//! loader does.
+//!
+//! ```
+//! use astgrep_core::{Language, Severity};
+//! use astgrep_rules::dsl::*;
+//!
+//! let rule = RuleBuilder::new("banned-md5")
+//!     .message("MD5 is banned; use SHA-256")
+//!     .severity(Severity::Error)
+//!     .language(Language::Java)
+//!     .pattern(either([pattern("MessageDigest.getInstance(\"MD5\")"), regex(r"DigestUtils\.md5")]))
+//!     .metadata("cwe", "CWE-327")
+//!     .build()
+//!     .unwrap();
+//! assert_eq!(rule.patterns.len(), 1);
+//! ```
+//!
+//! The functions here are not re-exported at the crate root; import them with
+//! `use astgrep_rules::dsl::*`.
+
+use crate::types::*;
+use crate::validator::RuleValidator;
+use astgrep_core::{AnalysisError, ComparisonOperator, Confidence, Language, NumericConstraint, Result, Severity};
+use std::collections::HashMap;
+
+/// `pattern:` — code pattern with metavariables, typed metavariables `($X : Type)` and ellipses
+pub fn pattern(code: impl Into<String>) -> Pattern {
+    Pattern::simple(code.into()).expand_typed_metavariables()
+}
+
+/// `pattern-regex:`
+pub fn regex(regex: impl Into<String>) -> Pattern {
+    Pattern::regex(regex.into())
+}
+
+/// `pattern-not-regex:`
+pub fn not_regex(regex: impl Into<String>) -> Pattern {
+    Pattern::not_regex(regex.into())
+}
+
+/// `pattern-either:` — any of `patterns` matches
+pub fn either(patterns: impl IntoIterator<Item = Pattern>) -> Pattern {
+    Pattern::either(patterns.into_iter().collect())
+}
+
+/// `patterns:` — all of `patterns` hold at the same place
+pub fn all(patterns: impl IntoIterator<Item = Pattern>) -> Pattern {
+    Pattern::all(patterns.into_iter().collect())
+}
+
+/// `pattern-inside:`
+pub fn inside(pattern: Pattern) -> Pattern {
+    Pattern::inside(pattern)
+}
+
+/// `pattern-not-inside:`
+pub fn not_inside(pattern: Pattern) -> Pattern {
+    Pattern::not_inside(pattern)
+}
+
+/// `pattern-not:`
+pub fn not(pattern: Pattern) -> Pattern {
+    Pattern::not(pattern)
+}
+
+/// Metavariable constraints on a [`Pattern`], mirroring the `metavariable-*` operators
+pub trait PatternExt: Sized {
+    /// `metavariable-regex:`
+    fn where_regex(self, metavariable: impl Into<String>, regex: impl Into<String>) -> Self;
+    /// `metavariable-type:`
+    fn where_type(self, metavariable: impl Into<String>, types: impl IntoIterator<Item = impl Into<String>>) -> Self;
+    /// `metavariable-name:`
+    fn where_name(self, metavariable: impl Into<String>, name_pattern: impl Into<String>) -> Self;
+    /// `metavariable-comparison:`
+    fn where_compare(self, metavariable: impl Into<String>, operator: ComparisonOperator, value: impl Into<String>) -> Self;
+    /// `metavariable-numeric:`
+    fn where_numeric(self, metavariable: impl Into<String>, constraint: NumericConstraint) -> Self;
+    /// `focus-metavariable:`
+    fn focus(self, metavariable: impl Into<String>) -> Self;
+}
+
+impl PatternExt for Pattern {
+    fn where_regex(self, metavariable: impl Into<String>, regex: impl Into<String>) -> Self {
+        self.add_condition(Condition::MetavariableRegex(MetavariableRegex::new(metavariable.into(), regex.into())))
+    }
+
+    fn where_type(self, metavariable: impl Into<String>, types: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        let types = types.into_iter().map(Into::into).collect();
+        self.add_condition(Condition::MetavariableType(MetavariableType::new(metavariable.into(), types)))
+    }
+
+    fn where_name(self, metavariable: impl Into<String>, name_pattern: impl Into<String>) -> Self {
+        self.add_condition(Condition::MetavariableName(MetavariableName::new(metavariable.into(), name_pattern.into())))
+    }
+
+    fn where_compare(self, metavariable: impl Into<String>, operator: ComparisonOperator, value: impl Into<String>) -> Self {
+        self.add_condition(Condition::MetavariableComparison(MetavariableComparison::new(
+            metavariable.into(),
+            operator,
+            value.into(),
+        )))
+    }
+
+    fn where_numeric(self, metavariable: impl Into<String>, constraint: NumericConstraint) -> Self {
+        self.add_condition(Condition::MetavariableNumeric(MetavariableNumeric::new(metavariable.into(), constraint)))
+    }
+
+    fn focus(self, metavariable: impl Into<String>) -> Self {
+        let metavariable = metavariable.into();
+        match self.focus.clone() {
+            Some(mut focus) => {
+                focus.push(metavariable);
+                self.with_focus_metavariables(focus)
+            }
+            None => self.with_focus(metavariable),
+        }
+    }
+}
+
+/// Builder for a taint (`dataflow:`) specification
+#[derive(Debug, Clone, Default)]
+pub struct TaintBuilder {
+    sources: Vec<String>,
+    sinks: Vec<String>,
+    sanitizers: Vec<String>,
+    must_flow: Option<bool>,
+    max_depth: Option<usize>,
+}
+
+/// Start a taint specification
+pub fn taint() -> TaintBuilder {
+    TaintBuilder::default()
+}
+
+impl TaintBuilder {
+    pub fn source(mut self, pattern: impl Into<String>) -> Self {
+        self.sources.push(pattern.into());
+        self
+    }
+
+    pub fn sink(mut self, pattern: impl Into<String>) -> Self {
+        self.sinks.push(pattern.into());
+        self
+    }
+
+    pub fn sanitizer(mut self, pattern: impl Into<String>) -> Self {
+        self.sanitizers.push(pattern.into());
+        self
+    }
+
+    pub fn must_flow(mut self, must_flow: bool) -> Self {
+        self.must_flow = Some(must_flow);
+        self
+    }
+
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    pub fn build(self) -> DataFlowSpec {
+        let mut spec = DataFlowSpec::new(self.sources, self.sinks).with_sanitizers(self.sanitizers);
+        if let Some(must_flow) = self.must_flow {
+            spec = spec.with_must_flow(must_flow);
+        }
+        if let Some(max_depth) = self.max_depth {
+            spec = spec.with_max_depth(max_depth);
+        }
+        spec
+    }
+}
+
+impl From<TaintBuilder> for DataFlowSpec {
+    fn from(builder: TaintBuilder) -> Self {
+        builder.build()
+    }
+}
+
+/// Builder for a [`Rule`]; defaults match the YAML loader (WARNING severity, MEDIUM
+/// confidence, name and description taken from the id and message)
+#[derive(Debug, Clone)]
+pub struct RuleBuilder {
+    id: String,
+    name: Option<String>,
+    message: Option<String>,
+    description: Option<String>,
+    severity: Severity,
+    confidence: Confidence,
+    languages: Vec<Language>,
+    patterns: Vec<Pattern>,
+    dataflow: Option<DataFlowSpec>,
+    fix: Option<String>,
+    fix_regex: Option<FixRegex>,
+    paths: Option<PathsFilter>,
+    examples: Vec<RuleExample>,
+    metadata: HashMap<String, String>,
+    requires: Vec<String>,
+    enabled: bool,
+}
+
+impl RuleBuilder {
+    pub fn new(id: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            name: None,
+            message: None,
+            description: None,
+            severity: Severity::Warning,
+            confidence: Confidence::Medium,
+            languages: Vec::new(),
+            patterns: Vec::new(),
+            dataflow: None,
+            fix: None,
+            fix_regex: None,
+            paths: None,
+            examples: Vec::new(),
+            metadata: HashMap::new(),
+            requires: Vec::new(),
+            enabled: true,
+        }
+    }
+
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    pub fn message(mut self, message: impl Into<String>) -> Self {
+        self.message = Some(message.into());
+        self
+    }
+
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    pub fn severity(mut self, severity: Severity) -> Self {
+        self.severity = severity;
+        self
+    }
+
+    pub fn confidence(mut self, confidence: Confidence) -> Self {
+        self.confidence = confidence;
+        self
+    }
+
+    pub fn language(mut self, language: Language) -> Self {
+        if !self.languages.contains(&language) {
+            self.languages.push(language);
+        }
+        self
+    }
+
+    pub fn languages(self, languages: impl IntoIterator<Item = Language>) -> Self {
+        languages.into_iter().fold(self, Self::language)
+    }
+
+    /// Add a top-level pattern; several patterns must all match, like `patterns:`
+    pub fn pattern(mut self, pattern: Pattern) -> Self {
+        self.patterns.push(pattern);
+        self
+    }
+
+    pub fn taint(mut self, taint: impl Into<DataFlowSpec>) -> Self {
+        self.dataflow = Some(taint.into());
+        self
+    }
+
+    pub fn fix(mut self, fix: impl Into<String>) -> Self {
+        self.fix = Some(fix.into());
+        self
+    }
+
+    pub fn fix_regex(mut self, regex: impl Into<String>, replacement: impl Into<String>) -> Self {
+        self.fix_regex = Some(FixRegex { regex: regex.into(), replacement: replacement.into() });
+        self
+    }
+
+    /// `paths.include` glob
+    pub fn include_path(mut self, glob: impl Into<String>) -> Self {
+        self.paths.get_or_insert_with(|| PathsFilter { includes: Vec::new(), excludes: Vec::new() }).includes.push(glob.into());
+        self
+    }
+
+    /// `paths.exclude` glob
+    pub fn exclude_path(mut self, glob: impl Into<String>) -> Self {
+        self.paths.get_or_insert_with(|| PathsFilter { includes: Vec::new(), excludes: Vec::new() }).excludes.push(glob.into());
+        self
+    }
+
+    pub fn example(mut self, example: RuleExample) -> Self {
+        self.examples.push(example);
+        self
+    }
+
+    pub fn metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.metadata.insert(key.into(), value.into());
+        self
+    }
+
+    /// Engine capability the rule needs, as in `requires:`
+    pub fn requires(mut self, capability: impl Into<String>) -> Self {
+        self.requires.push(capability.into());
+        self
+    }
+
+    /// Preferred match engine, as in `engine:`
+    pub fn engine(mut self, engine: MatchEngine, required: bool) -> Self {
+        self.metadata.insert(ENGINE_METADATA_KEY.to_string(), engine.as_str().to_string());
+        self.metadata.insert(ENGINE_REQUIRED_METADATA_KEY.to_string(), required.to_string());
+        self
+    }
+
+    pub fn enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
+    /// Assemble the rule and validate it like a loaded YAML rule
+    pub fn build(self) -> Result<Rule> {
+        let message = self
+            .message
+            .ok_or_else(|| AnalysisError::rule_validation_error(format!("Rule '{}' has no message", self.id)))?;
+
+        let name = self.name.unwrap_or_else(|| self.id.clone());
+        let description = self.description.unwrap_or_else(|| message.clone());
+        let mut rule = Rule::new(self.id, name, description, self.severity, self.confidence, self.languages);
+        rule.patterns = self.patterns;
+        rule.dataflow = self.dataflow;
+        rule.fix = self.fix;
+        rule.fix_regex = self.fix_regex;
+        rule.paths = self.paths;
+        rule.examples = self.examples;
+        rule.metadata = self.metadata;
+        if !self.requires.is_empty() {
+            rule.metadata.insert("requires".to_string(), self.requires.join(","));
+        }
+        rule.enabled = self.enabled;
+
+        RuleValidator::new().validate_rule(&rule)?;
+        Ok(rule)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_banned_api_rules() {
+        let banned = [("java.util.Random", "CWE-330"), ("MessageDigest.getInstance(\"MD5\")", "CWE-327")];
+        let rules: Vec<Rule> = banned
+            .iter()
+            .enumerate()
+            .map(|(i, (api, cwe))| {
+                RuleBuilder::new(format!("banned-api-{}", i))
+                    .message(format!("{} is banned", api))
+                    .severity(Severity::Error)
+                    .languages([Language::Java, Language::Java])
+                    .pattern(all([
+                        pattern(format!("{}(...)", api)),
+                        not_inside(pattern("@SuppressBanned(...) class $C { ... }")),
+                    ]))
+                    .metadata("cwe", *cwe)
+                    .example(RuleExample::positive(format!("{}();", api)))
+                    .build()
+                    .unwrap()
+            })
+            .collect();
+
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0].languages, vec![Language::Java]);
+        assert_eq!(rules[1].description, "MessageDigest.getInstance(\"MD5\") is banned");
+        assert!(matches!(&rules[0].patterns[0].pattern_type, PatternType::All(parts) if parts.len() == 2));
+        assert_eq!(rules[1].get_metadata("cwe"), Some(&"CWE-327".to_string()));
+    }
+
+    #[test]
+    fn test_build_taint_rule_with_constraints() {
+        let rule = RuleBuilder::new("cmd-injection")
+            .message("User input reaches a shell command")
+            .language(Language::Python)
+            .pattern(pattern("subprocess.call($CMD, shell=True)").where_regex("$CMD", ".*").focus("$CMD"))
+            .taint(taint().source("request.args.get(...)").sink("subprocess.call(...)").sanitizer("shlex.quote(...)"))
+            .requires("dataflow")
+            .engine(MatchEngine::TreeSitter, false)
+            .exclude_path("tests/**")
+            .build()
+            .unwrap();
+
+        let dataflow = rule.dataflow.as_ref().unwrap();
+        assert_eq!(dataflow.sanitizers, vec!["shlex.quote(...)".to_string()]);
+        assert!(dataflow.must_flow);
+        assert_eq!(rule.patterns[0].conditions.len(), 1);
+        assert_eq!(rule.patterns[0].focus, Some(vec!["$CMD".to_string()]));
+        assert_eq!(rule.paths.as_ref().unwrap().excludes, vec!["tests/**".to_string()]);
+        assert_eq!(rule.get_metadata("requires"), Some(&"dataflow".to_string()));
+        assert_eq!(rule.engine_hint().map(|hint| hint.engine), Some(MatchEngine::TreeSitter));
+    }
+
+    #[test]
+    fn test_build_requires_message_and_language() {
+        assert!(RuleBuilder::new("x").language(Language::Java).pattern(pattern("foo()")).build().is_err());
+        assert!(RuleBuilder::new("x").message("m").pattern(pattern("foo()")).build().is_err());
+    }
+}
@@ -2,13 +2,38 @@
 //!
 //! This module provides the core rule execution engine that applies rules to AST nodes.
 
+use crate::match_trace::{trace_node, trace_span, MatchDecision};
+use crate::regex_index::{RegexHits, RegexIndex};
 use crate::types::*;
-use astgrep_core::{AstNode, Finding, Location, Result};
-use std::collections::HashMap;
+use astgrep_core::{record_diagnostic, AnalysisError, AstNode, CancellationToken, Confidence, DiagnosticKind, Finding, LanguageParser, Location, Result, Severity, SharedLruCache};
+use astgrep_dataflow::{indexed_callee, indexed_function_name, ConstantAnalyzer, ConstantValue, FunctionModel, ImportAliases, ModelKind, ModelSet, ProjectIndex, PropagatedSource, SinkType, SymbolTable, TaintPort};
+use astgrep_matcher::{equivalent_patterns, is_anonymous_metavar, AdvancedSemgrepMatcher, EquivalenceConfig, PatternAst};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::path::PathBuf;
-use std::time::Instant;
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
 use regex::Regex;
 
+/// A match in the source: byte start, byte end and the byte range bound to each metavariable
+type SourceMatch = (usize, usize, HashMap<String, (usize, usize)>);
+
+/// A match of pattern tokens: the index after the last matched text token and the byte
+/// range bound to each metavariable
+type TokenMatch = (usize, HashMap<String, (usize, usize)>);
+
+/// Cache key of a simple pattern compiled for a language
+type PatternKey = (String, astgrep_core::Language);
+
+/// Prefix of the metavariables that stand for pattern literals under constant propagation
+const FOLDED_LITERAL_PREFIX: &str = "$__LITERAL";
+
+/// Most compiled patterns kept per cache. Bounded because a long-running server compiles
+/// the patterns of every rule submitted to it.
+const PATTERN_CACHE_CAPACITY: usize = 4096;
+
+/// Prefix of a dataflow sink that stands for the calls of a sink kind of the taint models,
+/// e.g. `model:sql_execution`
+pub const MODEL_SINK_PREFIX: &str = "model:";
 
 /// Rule execution engine
 pub struct RuleExecutionEngine {
@@ -16,6 +41,17 @@ pub struct RuleExecutionEngine {
     max_execution_time_ms: Option<u64>,
     cache_enabled: bool,
     execution_cache: HashMap<String, Vec<Finding>>,
+    regex_index: RegexIndex,
+    /// Result of scanning the current file with `regex_index`, during `execute_rules`
+    regex_hits: Option<RegexHits>,
+    /// Time spent in each rule since the last `take_timings`, when recording
+    timings: Option<HashMap<String, Duration>>,
+    /// Parsers for patterns and code embedded in metavariables, see [`RuleExecutionEngine::register_parser`]
+    parsers: Vec<Arc<dyn LanguageParser>>,
+    /// Library function models consulted by dataflow rules, see [`RuleExecutionEngine::set_taint_models`]
+    taint_models: Arc<ModelSet>,
+    /// Symbols and calls of the project, see [`RuleExecutionEngine::set_project_index`]
+    project_index: Option<Arc<ProjectIndex>>,
 }
 
 impl RuleExecutionEngine {
@@ -26,9 +62,52 @@ impl RuleExecutionEngine {
             max_execution_time_ms: Some(30000), // 30 seconds default
             cache_enabled: false,
             execution_cache: HashMap::new(),
+            regex_index: RegexIndex::default(),
+            regex_hits: None,
+            timings: None,
+            parsers: Vec::new(),
+            taint_models: Arc::new(ModelSet::new()),
+            project_index: None,
         }
     }
 
+    /// Start or stop adding up the time spent in each rule
+    pub fn record_timings(&mut self, enabled: bool) {
+        self.timings = enabled.then(HashMap::new);
+    }
+
+    /// The time spent in each rule since recording started or the last call, by rule id
+    pub fn take_timings(&mut self) -> HashMap<String, Duration> {
+        self.timings.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    /// Compile the regexes of `rules` once, so `execute_rules` scans each file in a single
+    /// pass and skips regexes without a match
+    pub fn index_regexes(&mut self, rules: &[Rule]) {
+        self.regex_index = RegexIndex::build(rules);
+    }
+
+    /// Parse simple patterns for files of the parser's language with `parser`, and code
+    /// bound to metavariables when a `metavariable-pattern` asks for its language, e.g. SQL
+    /// in a string literal
+    pub fn register_parser(&mut self, parser: Arc<dyn LanguageParser>) {
+        self.parsers.push(parser);
+    }
+
+    /// Consult library function models in dataflow rules: modeled sources are sources of
+    /// every rule, a sink whose arguments all pass through a modeled sanitizer is clean,
+    /// and a `model:<kind>` sink of a rule stands for the calls of modeled sinks of that kind
+    pub fn set_taint_models(&mut self, models: Arc<ModelSet>) {
+        self.taint_models = models;
+    }
+
+    /// Follow dataflow rules across files through the call graph of `index`: a sink in a
+    /// file without a source is reported when a function calling a source call of the rule
+    /// calls into the sink's function, or is called from it
+    pub fn set_project_index(&mut self, index: Arc<ProjectIndex>) {
+        self.project_index = Some(index);
+    }
+
     /// Enable or disable parallel execution
     pub fn set_parallel_execution(mut self, enabled: bool) -> Self {
         self.parallel_execution = enabled;
@@ -65,26 +144,28 @@ impl RuleExecutionEngine {
         };
 
         // Check cache first
-        if let Some(ref key) = cache_key {
-            if let Some(cached_findings) = self.execution_cache.get(key) {
-                return RuleResult::success(
-                    rule.id.clone(),
-                    cached_findings.clone(),
-                    start_time.elapsed().as_millis() as u64,
-                );
+        let cached = cache_key.as_ref().and_then(|key| self.execution_cache.get(key)).map(|cached_findings| {
+            RuleResult::success(rule.id.clone(), cached_findings.clone(), start_time.elapsed().as_millis() as u64)
+        });
+        let result = match cached {
+            Some(result) => result,
+            None => {
+                // Execute the rule
+                let result = self.execute_rule_internal(rule, ast, context, start_time);
+
+                // Cache successful results
+                if let Some(key) = cache_key {
+                    if result.is_success() {
+                        self.execution_cache.insert(key, result.findings.clone());
+                    }
+                }
+                result
             }
-        }
-
-        // Execute the rule
-        let result = self.execute_rule_internal(rule, ast, context, start_time);
+        };
 
-        // Cache successful results
-        if let Some(key) = cache_key {
-            if result.is_success() {
-                self.execution_cache.insert(key, result.findings.clone());
-            }
+        if let Some(ref mut timings) = self.timings {
+            *timings.entry(rule.id.clone()).or_default() += start_time.elapsed();
         }
-
         result
     }
 
@@ -95,10 +176,26 @@ impl RuleExecutionEngine {
         ast: &dyn AstNode,
         context: &RuleContext,
     ) -> Vec<RuleResult> {
-        if self.parallel_execution && rules.len() > 1 {
+        if !self.regex_index.is_empty() {
+            self.regex_hits = Some(self.regex_index.scan(context.language, &context.source_code));
+        }
+        let results = if self.parallel_execution && rules.len() > 1 {
             self.execute_rules_parallel(rules, ast, context)
         } else {
             self.execute_rules_sequential(rules, ast, context)
+        };
+        self.regex_hits = None;
+        results
+    }
+
+    /// Compiled regex for a pattern, or `None` when the file scan showed it cannot match
+    fn regex_for(&self, source: &str) -> std::result::Result<Option<Regex>, regex::Error> {
+        if self.regex_hits.as_ref().is_some_and(|hits| !hits.may_match(source)) {
+            return Ok(None);
+        }
+        match self.regex_index.regex(source) {
+            Some(regex) => Ok(Some(regex.clone())),
+            None => Regex::new(source).map(Some),
         }
     }
 
@@ -112,6 +209,7 @@ impl RuleExecutionEngine {
         rules
             .iter()
             .filter(|rule| rule.applies_to(context.language))
+            .take_while(|_| !context.cancellation.is_cancelled())
             .map(|rule| self.execute_rule(rule, ast, context))
             .collect()
     }
@@ -147,21 +245,50 @@ impl RuleExecutionEngine {
             }
         }
 
-        println!("🔍 Executing rule: {}", rule.id);
-        println!("🔍 Rule has {} patterns", rule.patterns.len());
+        tracing::debug!(rule = %rule.id, patterns = rule.patterns.len(), "executing rule");
+
+        // Honour the rule's engine hint, falling back to the generic engine where a
+        // preferred engine is unavailable for this language
+        let (engine, downgraded_from) = match rule.engine_hint() {
+            None => (None, None),
+            Some(hint) if hint.engine.supports(context.language) => (Some(hint.engine), None),
+            Some(hint) if hint.required => {
+                return RuleResult::error(
+                    rule.id.clone(),
+                    format!("Rule requires the {} engine, which is unavailable for {}", hint.engine, context.language.as_str()),
+                    start_time.elapsed().as_millis() as u64,
+                );
+            }
+            Some(hint) => (Some(MatchEngine::Generic), Some(hint.engine)),
+        };
+        if let Some(requested) = downgraded_from {
+            record_diagnostic(DiagnosticKind::EngineFallback, Some(context.language), &format!("{} -> generic", requested), || rule.id.clone());
+        }
 
         let mut findings = Vec::new();
 
-        // Execute pattern matching
+        // Execute pattern matching; a cancelled analysis keeps the findings made so far
         for (i, pattern) in rule.patterns.iter().enumerate() {
-            println!("🔍 Processing pattern {} of {}", i + 1, rule.patterns.len());
-            match self.execute_pattern(pattern, ast, rule, context) {
+            if context.cancellation.is_cancelled() {
+                break;
+            }
+            tracing::debug!(rule = %rule.id, pattern = i + 1, "executing pattern");
+            let outcome = match engine {
+                Some(MatchEngine::TreeSitter) => self.execute_structural_pattern(pattern, ast, rule, context),
+                Some(MatchEngine::Regex) if !pattern.is_regex_only() => {
+                    record_diagnostic(DiagnosticKind::IgnoredConstruct, Some(context.language), &format!("{} with engine: regex", pattern.key()), || rule.id.clone());
+                    Ok(Vec::new())
+                }
+                _ => self.execute_pattern(pattern, ast, rule, context),
+            };
+            match outcome {
                 Ok(mut pattern_findings) => {
-                    println!("🔍 Pattern {} generated {} findings", i + 1, pattern_findings.len());
+                    tracing::debug!(rule = %rule.id, pattern = i + 1, findings = pattern_findings.len(), "pattern executed");
                     findings.append(&mut pattern_findings)
                 },
+                Err(AnalysisError::Cancelled) => break,
                 Err(e) => {
-                    println!("🔍 Pattern {} failed with error: {}", i + 1, e);
+                    tracing::debug!(rule = %rule.id, pattern = i + 1, error = %e, "pattern failed");
                     return RuleResult::error(
                         rule.id.clone(),
                         format!("Pattern execution error: {}", e),
@@ -172,9 +299,14 @@ impl RuleExecutionEngine {
         }
 
         // Execute dataflow analysis if specified
-        if let Some(ref dataflow) = rule.dataflow {
+        if let Some(dataflow) = rule.dataflow.as_ref().filter(|_| !context.cancellation.is_cancelled()) {
             match self.execute_dataflow(dataflow, ast, rule, context) {
-                Ok(mut dataflow_findings) => findings.append(&mut dataflow_findings),
+                Ok(mut dataflow_findings) => {
+                    // Mark, and escalate when the rule asks for it, pattern matches that a taint flow corroborates
+                    Self::corroborate_findings(&mut findings, &dataflow_findings, rule.escalation());
+                    findings.append(&mut dataflow_findings)
+                }
+                Err(AnalysisError::Cancelled) => {}
                 Err(e) => {
                     return RuleResult::error(
                         rule.id.clone(),
@@ -185,12 +317,64 @@ impl RuleExecutionEngine {
             }
         }
 
-        RuleResult::success(
+        if let Some(engine) = engine {
+            for finding in &mut findings {
+                finding.metadata.insert("engine".to_string(), engine.as_str().to_string());
+                if let Some(requested) = downgraded_from {
+                    finding.metadata.insert("engine_downgraded_from".to_string(), requested.as_str().to_string());
+                }
+            }
+        }
+
+        let mut result = RuleResult::success(
             rule.id.clone(),
             findings,
             start_time.elapsed().as_millis() as u64,
-        )
+        );
+        result.downgraded_from = downgraded_from;
+        result
+    }
 
+    /// Execute a pattern with structural matching on the syntax tree
+    fn execute_structural_pattern(
+        &self,
+        pattern: &Pattern,
+        ast: &dyn AstNode,
+        rule: &Rule,
+        context: &RuleContext,
+    ) -> Result<Vec<Finding>> {
+        let semgrep_pattern = pattern.to_semgrep_pattern();
+        let matcher = self
+            .parsers
+            .iter()
+            .cloned()
+            .fold(AdvancedSemgrepMatcher::new(), AdvancedSemgrepMatcher::with_parser)
+            .with_equivalences(Self::equivalence_config(rule))
+            .with_cancellation(context.cancellation.clone());
+
+        let pattern_label = pattern.get_pattern_string().map_or(pattern.key(), String::as_str);
+        let mut findings = Vec::new();
+        for result in matcher.find_matches_iter(&semgrep_pattern, ast) {
+            let result = result?;
+            trace_node(&rule.id, pattern_label, &context.file_path, result.node.as_ref(), &result.bindings, MatchDecision::Reported);
+            let location = self.create_best_location_from_node_or_pattern(result.node.as_ref(), pattern, context);
+            let values: Vec<(String, &str)> = result.bindings.iter().map(|(name, value)| (format!("${}", name), value.as_str())).collect();
+            let mut finding = Finding::new(
+                rule.id.clone(),
+                Self::substitute_metavariables(&self.generate_finding_message(rule, pattern, result.node.as_ref()), &values),
+                rule.severity,
+                rule.confidence,
+                location,
+            );
+            if let Some(pattern_str) = pattern.get_pattern_string() {
+                finding = finding.with_metadata("pattern".to_string(), pattern_str.clone());
+            }
+            if let Some(ref fix) = rule.fix {
+                finding = finding.with_fix(Self::substitute_metavariables(fix, &values));
+            }
+            findings.push(finding);
+        }
+        Ok(findings)
     }
 
 
@@ -212,24 +396,199 @@ impl RuleExecutionEngine {
         true // default ON
     }
 
+    /// Whether the rule opts into constant propagation (`options: constant_propagation: true`)
+    fn constant_propagation_enabled(rule: &Rule) -> bool {
+        rule.get_metadata("constant_propagation").is_some_and(|v| v == "true")
+    }
+
+    /// Operator equivalences of the rule: both on unless its options turn them off
+    fn equivalence_config(rule: &Rule) -> EquivalenceConfig {
+        let enabled = |key: &str| rule.get_metadata(key).is_none_or(|v| v != "false");
+        EquivalenceConfig::default()
+            .with_commutative(enabled("commutative_matching"))
+            .with_associative(enabled("associative_matching"))
+    }
+
+    /// Whether the rule opts into symbolic propagation (`options: symbolic_propagation: true`)
+    fn symbolic_propagation_enabled(rule: &Rule) -> bool {
+        rule.get_metadata("symbolic_propagation").is_some_and(|v| v == "true")
+    }
+
+    /// Whether the rule leaves import alias resolution on (`options: import_aliasing: false`
+    /// turns it off)
+    fn import_aliasing_enabled(rule: &Rule) -> bool {
+        rule.get_metadata("import_aliasing").is_none_or(|v| v != "false")
+    }
+
+    /// Find the matches of a simple pattern and its equivalent spellings in the source,
+    /// in source order. Spellings include the pattern with a qualified name replaced by
+    /// the alias the file imports it under. With symbolic propagation each spelling also
+    /// matches the source with single-assignment variables replaced by their values. A
+    /// span matched by several spellings is reported once, with the bindings of the first.
+    fn source_matches(&self, pattern_str: &str, ast: &dyn AstNode, rule: &Rule, context: &RuleContext) -> Vec<SourceMatch> {
+        let no_aliases = ImportAliases::new();
+        let aliases = if Self::import_aliasing_enabled(rule) { context.import_aliases() } else { &no_aliases };
+        let propagated = if Self::symbolic_propagation_enabled(rule) { context.propagated_source() } else { None };
+        let mut matches = Vec::new();
+        for variant in equivalent_patterns(pattern_str, &Self::equivalence_config(rule)) {
+            let aliased = aliases.local_spellings(&variant).into_iter().map(|spelling| (spelling, true));
+            for (spelling, is_alias) in std::iter::once((variant, false)).chain(aliased) {
+                let mut spelling_matches = self.spelling_matches(&spelling, ast, rule, context);
+                if let Some(propagated) = propagated {
+                    spelling_matches.extend(self.propagated_matches(&spelling, propagated, rule, context));
+                }
+                for found in spelling_matches {
+                    // An imported name is not a member of something else
+                    let member = is_alias && context.source_code[..found.0].trim_end().ends_with('.');
+                    if !member && !matches.iter().any(|(start, end, _): &SourceMatch| (*start, *end) == (found.0, found.1)) {
+                        matches.push(found);
+                    }
+                }
+            }
+        }
+        matches.sort_by_key(|(start, end, _)| (*start, *end));
+        matches
+    }
+
+    /// Find the matches of a pattern spelling in the source with single-assignment
+    /// variables replaced by their values, as spans of the original source. Matching is on
+    /// tokens, as the syntax tree is that of the original source.
+    fn propagated_matches(&self, pattern_str: &str, propagated: &PropagatedSource, rule: &Rule, context: &RuleContext) -> Vec<SourceMatch> {
+        let seg_by_stmt = matches!(context.language, astgrep_core::Language::Sql) && Self::effective_sql_stmt_boundary(rule, context);
+        let original = |(start, end): (usize, usize)| {
+            let range = propagated.original_range(start..end);
+            (range.start, range.end)
+        };
+        self.find_pattern_matches_in_source(pattern_str, &propagated.text, context.language, seg_by_stmt, &context.cancellation)
+            .into_iter()
+            .map(|(start, end, bindings)| {
+                let (start, end) = original((start, end));
+                (start, end, bindings.into_iter().map(|(name, span)| (name, original(span))).collect())
+            })
+            .collect()
+    }
+
+    /// Find the matches of one spelling of a simple pattern, honouring the rule's SQL
+    /// statement boundary and constant propagation options. With constant propagation a
+    /// literal in the pattern also matches an expression that folds to it, such as the
+    /// constant `ALGO` for `"MD5"` or `"SELECT * FROM " + TABLE` for `"SELECT * FROM users"`.
+    fn spelling_matches(&self, pattern_str: &str, ast: &dyn AstNode, rule: &Rule, context: &RuleContext) -> Vec<SourceMatch> {
+        let seg_by_stmt = matches!(context.language, astgrep_core::Language::Sql) && Self::effective_sql_stmt_boundary(rule, context);
+        let abstracted = if Self::constant_propagation_enabled(rule) { self.abstract_literals(pattern_str) } else { None };
+        let (abstracted, literals) = match abstracted {
+            Some(abstracted) => abstracted,
+            None => return self.pattern_matches(pattern_str, ast, rule, context, seg_by_stmt),
+        };
+
+        let constants = context.constants();
+        let mut matches = self.pattern_matches(&abstracted, ast, rule, context, seg_by_stmt);
+        matches.retain(|(start, end, bindings)| {
+            let folds = literals.iter().all(|(name, literal)| {
+                bindings.get(name).is_some_and(|&(from, to)| constants.fold_expression(&context.source_code[from..to]).as_ref() == Some(literal))
+            });
+            if !folds {
+                trace_span(&rule.id, pattern_str, &context.file_path, &context.source_code, (*start, *end), bindings, MatchDecision::RejectedByConstant);
+            }
+            folds
+        });
+        for (_, _, bindings) in &mut matches {
+            bindings.retain(|name, _| !name.starts_with(FOLDED_LITERAL_PREFIX));
+        }
+        matches
+    }
+
+    /// Whether the rule leaves matching on the syntax tree on (`options: ast_matching: false`
+    /// turns it off)
+    fn ast_matching_enabled(rule: &Rule) -> bool {
+        rule.get_metadata("ast_matching").is_none_or(|v| v != "false")
+    }
+
+    /// Find the matches of one pattern spelling. A pattern that parses with the parser
+    /// registered for the file's language is matched node against node on `ast`. Matching
+    /// tokens of the source is left for what a [`PatternAst`] cannot express:
+    /// - languages without a registered parser, and rules with `ast_matching: false`
+    /// - patterns that are not a node or a run of statements of the grammar, such as a
+    ///   clause of a SQL statement or an unbalanced fragment
+    /// - source rewritten by symbolic propagation, which has no syntax tree
+    fn pattern_matches(&self, pattern: &str, ast: &dyn AstNode, rule: &Rule, context: &RuleContext, sql_stmt_boundary: bool) -> Vec<SourceMatch> {
+        let Some(pattern_ast) = Self::ast_matching_enabled(rule).then(|| self.pattern_ast(pattern, context.language)).flatten() else {
+            return self.find_pattern_matches_in_source(pattern, &context.source_code, context.language, sql_stmt_boundary, &context.cancellation);
+        };
+        let source = &context.source_code;
+        let span = |(start_line, start_col, end_line, end_col)| {
+            (Self::line_col_to_byte_index(source, start_line, start_col), Self::line_col_to_byte_index(source, end_line, end_col))
+        };
+        pattern_ast
+            .find_matches(ast, &context.cancellation)
+            .into_iter()
+            .filter_map(|found| {
+                let (start, end) = span(found.location()?);
+                let bindings = found
+                    .metavariables
+                    .iter()
+                    .filter_map(|(name, bound)| Some((format!("${}", name), span(bound.location?))))
+                    .collect();
+                Some((start, end, bindings))
+            })
+            .collect()
+    }
+
+    /// A simple pattern parsed by the parser registered for `language`, parsed once per
+    /// process and bounded like [`Self::compiled_pattern`]. `None` when no parser is
+    /// registered or the pattern does not parse into a node or a run of statements.
+    fn pattern_ast(&self, pattern: &str, language: astgrep_core::Language) -> Option<Arc<PatternAst>> {
+        static CACHE: OnceLock<SharedLruCache<PatternKey, Option<Arc<PatternAst>>>> = OnceLock::new();
+        let parser = self.parsers.iter().find(|parser| parser.language() == language)?;
+        CACHE.get_or_init(|| SharedLruCache::new(PATTERN_CACHE_CAPACITY)).get_or_insert_with((pattern.to_string(), language), || {
+            let parsed = PatternAst::parse(pattern, parser.as_ref()).map(Arc::new);
+            tracing::trace!(pattern, language = language.as_str(), parsed = parsed.is_some(), "parsing pattern");
+            parsed
+        })
+    }
+
+    /// Replace the string and integer literals of a pattern with metavariables, returning
+    /// the rewritten pattern and the value each metavariable must fold to. `None` when the
+    /// pattern has no literals, or nothing but literals to anchor a match on.
+    fn abstract_literals(&self, pattern: &str) -> Option<(String, Vec<(String, ConstantValue)>)> {
+        static LITERAL: OnceLock<Regex> = OnceLock::new();
+        let literal = LITERAL.get_or_init(|| Regex::new(r#""(?:[^"\\]|\\.)*"|'(?:[^'\\]|\\.)*'|\b\d+\b"#).unwrap());
+        let folder = ConstantAnalyzer::new();
+        let mut literals = Vec::new();
+        let abstracted = literal.replace_all(pattern, |cap: &regex::Captures| {
+            match folder.fold_constants(&cap[0]) {
+                // `"..."` stands for any string
+                Some(ConstantValue::String(ref s)) if s == "..." => cap[0].to_string(),
+                Some(value @ (ConstantValue::String(_) | ConstantValue::Integer(_))) => {
+                    let name = format!("{}{}", FOLDED_LITERAL_PREFIX, literals.len());
+                    literals.push((name.clone(), value));
+                    name
+                }
+                _ => cap[0].to_string(),
+            }
+        });
+        let anchored = self.tokenize_pattern(&abstracted).iter().any(|t| Self::is_anchor(t));
+        (!literals.is_empty() && anchored).then(|| (abstracted.into_owned(), literals))
+    }
+
     /// Execute pattern matching
     fn execute_pattern(
         &self,
         pattern: &Pattern,
-        _ast: &dyn AstNode,
+        ast: &dyn AstNode,
         rule: &Rule,
         context: &RuleContext,
     ) -> Result<Vec<Finding>> {
-        println!("🔍 Executing pattern for rule: {}", rule.id);
-        println!("🔍 Pattern: {:?}", pattern);
+        tracing::trace!(rule = %rule.id, pattern = ?pattern.pattern_type, "matching pattern on source");
 
         let mut findings = Vec::new();
 
         // 1) Regex patterns: run real regex over full source
         if let PatternType::Regex(ref regex_str) = &pattern.pattern_type {
-            match Regex::new(regex_str) {
-                Ok(re) => {
+            match self.regex_for(regex_str) {
+                Ok(None) => return Ok(findings),
+                Ok(Some(re)) => {
                     for m in re.find_iter(&context.source_code) {
+                        trace_span(&rule.id, regex_str, &context.file_path, &context.source_code, (m.start(), m.end()), &HashMap::new(), MatchDecision::Reported);
                         let (start_line, start_col) = Self::byte_index_to_line_col(&context.source_code, m.start());
                         let (end_line, end_col) = Self::byte_index_to_line_col(&context.source_code, m.end());
 
@@ -255,7 +614,7 @@ impl RuleExecutionEngine {
                         let finding = if let Some(ref fix) = rule.fix { finding.with_fix(fix.clone()) } else { finding };
                         findings.push(finding);
                     }
-                    println!("🔍 Regex pattern execution complete. Generated {} findings", findings.len());
+                    tracing::debug!(rule = %rule.id, findings = findings.len(), "regex pattern executed");
                     return Ok(findings);
                 }
                 Err(e) => {
@@ -267,18 +626,27 @@ impl RuleExecutionEngine {
 
         // 2) Simple patterns (with or without metavariables): scan full source and emit one finding per occurrence
         if let PatternType::Simple(ref pattern_str) = &pattern.pattern_type {
-            let seg_by_stmt = if matches!(context.language, astgrep_core::Language::Sql) {
-                Self::effective_sql_stmt_boundary(rule, context)
-            } else { false };
-            let spans = self.find_pattern_spans_in_source(&pattern_str, &context.source_code, context.language, seg_by_stmt);
-            println!("🔍 Pattern matching found {} spans", spans.len());
+            let matches = self.source_matches(pattern_str, ast, rule, context);
+            let matches = Self::apply_focus(pattern.focus.as_deref(), matches);
+            tracing::debug!(rule = %rule.id, spans = matches.len(), "pattern matched source");
+            let types = Self::type_table_for(&[pattern], context);
 
             // Optional: deduplicate identical spans
             use std::collections::HashSet;
             let mut seen: HashSet<(usize, usize)> = HashSet::new();
 
-            for (start_byte, end_byte) in spans {
-                if !seen.insert((start_byte, end_byte)) { continue; }
+            for (start_byte, end_byte, bindings) in matches {
+                let decision = if !Self::satisfies_type_constraints(&pattern.conditions, &bindings, &context.source_code, types.as_ref()) {
+                    MatchDecision::RejectedByType
+                } else if !Self::satisfies_numeric_constraints(&pattern.conditions, &bindings, context) {
+                    MatchDecision::RejectedByNumeric
+                } else if !seen.insert((start_byte, end_byte)) {
+                    MatchDecision::Duplicate
+                } else {
+                    MatchDecision::Reported
+                };
+                trace_span(&rule.id, pattern_str, &context.file_path, &context.source_code, (start_byte, end_byte), &bindings, decision);
+                if decision != MatchDecision::Reported { continue; }
                 let (start_line, start_col) = Self::byte_index_to_line_col(&context.source_code, start_byte);
                 let (end_line, end_col) = Self::byte_index_to_line_col(&context.source_code, end_byte);
 
@@ -295,18 +663,18 @@ impl RuleExecutionEngine {
 
                 let finding = Finding::new(
                     rule.id.clone(),
-                    if !rule.description.is_empty() { rule.description.clone() } else { format!("Match: {}", matched_text) },
+                    Self::render_message(rule, &bindings, &context.source_code, matched_text),
                     rule.severity,
                     rule.confidence,
                     location,
                 )
                 .with_metadata("pattern".to_string(), pattern_str.clone());
 
-                let finding = if let Some(ref fix) = rule.fix { finding.with_fix(fix.clone()) } else { finding };
+                let finding = if let Some(ref fix) = rule.fix { finding.with_fix(Self::render_fix(fix, &bindings, &context.source_code)) } else { finding };
                 findings.push(finding);
             }
 
-            println!("🔍 Pattern execution complete. Generated {} findings", findings.len());
+            tracing::debug!(rule = %rule.id, findings = findings.len(), "pattern executed");
             return Ok(findings);
         }
 
@@ -318,11 +686,13 @@ impl RuleExecutionEngine {
             for sub in subs {
                 match &sub.pattern_type {
                     PatternType::Regex(r) => {
-                        if let Ok(re) = Regex::new(r) {
+                        if let Ok(Some(re)) = self.regex_for(r) {
                             for m in re.find_iter(&context.source_code) {
                                 let start_byte = m.start();
                                 let end_byte = m.end();
-                                if !seen.insert((start_byte, end_byte)) { continue; }
+                                let decision = if seen.insert((start_byte, end_byte)) { MatchDecision::Reported } else { MatchDecision::Duplicate };
+                                trace_span(&rule.id, r, &context.file_path, &context.source_code, (start_byte, end_byte), &HashMap::new(), decision);
+                                if decision != MatchDecision::Reported { continue; }
                                 let (start_line, start_col) = Self::byte_index_to_line_col(&context.source_code, start_byte);
                                 let (end_line, end_col) = Self::byte_index_to_line_col(&context.source_code, end_byte);
                                 let location = Location::new(
@@ -347,13 +717,26 @@ impl RuleExecutionEngine {
                         }
                     }
                     PatternType::Simple(s) => {
-                        let seg_by_stmt = if matches!(context.language, astgrep_core::Language::Sql) {
-                            Self::effective_sql_stmt_boundary(rule, context)
-                        } else { false };
-                        let spans = self.find_pattern_spans_in_source(s, &context.source_code, context.language, seg_by_stmt);
-                        println!("DEBUG either: simple pattern '{}' produced {} spans", s, spans.len());
-                        for (start_byte, end_byte) in spans {
-                            if !seen.insert((start_byte, end_byte)) { continue; }
+                        let matches = self.source_matches(s, ast, rule, context);
+                        let matches = Self::apply_focus(sub.focus.as_ref().or(pattern.focus.as_ref()).map(Vec::as_slice), matches);
+                        tracing::trace!(pattern = %s, spans = matches.len(), "pattern-either alternative matched");
+                        let types = Self::type_table_for(&[pattern, sub], context);
+                        for (start_byte, end_byte, bindings) in matches {
+                            let decision = if !Self::satisfies_type_constraints(&pattern.conditions, &bindings, &context.source_code, types.as_ref())
+                                || !Self::satisfies_type_constraints(&sub.conditions, &bindings, &context.source_code, types.as_ref())
+                            {
+                                MatchDecision::RejectedByType
+                            } else if !Self::satisfies_numeric_constraints(&pattern.conditions, &bindings, context)
+                                || !Self::satisfies_numeric_constraints(&sub.conditions, &bindings, context)
+                            {
+                                MatchDecision::RejectedByNumeric
+                            } else if !seen.insert((start_byte, end_byte)) {
+                                MatchDecision::Duplicate
+                            } else {
+                                MatchDecision::Reported
+                            };
+                            trace_span(&rule.id, s, &context.file_path, &context.source_code, (start_byte, end_byte), &bindings, decision);
+                            if decision != MatchDecision::Reported { continue; }
                             let (start_line, start_col) = Self::byte_index_to_line_col(&context.source_code, start_byte);
                             let (end_line, end_col) = Self::byte_index_to_line_col(&context.source_code, end_byte);
                             let location = Location::new(
@@ -366,28 +749,29 @@ impl RuleExecutionEngine {
                             let matched_text = &context.source_code[start_byte..end_byte.min(context.source_code.len())];
                             let mut finding = Finding::new(
                                 rule.id.clone(),
-                                if !rule.description.is_empty() { rule.description.clone() } else { format!("Match: {}", matched_text) },
+                                Self::render_message(rule, &bindings, &context.source_code, matched_text),
                                 rule.severity,
                                 rule.confidence,
                                 location,
                             );
                             finding = finding.with_metadata("pattern".to_string(), s.clone());
-                            if let Some(ref fix) = rule.fix { finding = finding.with_fix(fix.clone()); }
+                            if let Some(ref fix) = rule.fix { finding = finding.with_fix(Self::render_fix(fix, &bindings, &context.source_code)); }
                             findings.push(finding);
                         }
                     }
                     _ => {}
                 }
             }
-            println!("🔍 pattern-either execution complete. Generated {} findings", findings.len());
+            tracing::debug!(rule = %rule.id, findings = findings.len(), "pattern-either executed");
             if !findings.is_empty() {
                 return Ok(findings);
             }
         }
 
         // Fallback: no simple/regex pattern string available, use node-based matching (locations may be coarse)
-        let matches = self.find_pattern_matches(pattern, _ast, context.language)?;
-        println!("🔍 Fallback matching found {} matches", matches.len());
+        record_diagnostic(DiagnosticKind::EngineFallback, Some(context.language), &format!("{} -> node text", pattern.key()), || rule.id.clone());
+        let matches = self.find_pattern_matches(pattern, ast, context.language, Self::ast_matching_enabled(rule), &context.cancellation)?;
+        tracing::debug!(rule = %rule.id, nodes = matches.len(), "node text fallback matched");
 
         // Keep only smallest, non-overlapping node spans
         let mut mm: Vec<((usize, usize), usize, usize, usize, usize, Box<dyn AstNode>)> = matches
@@ -415,12 +799,13 @@ impl RuleExecutionEngine {
 
         let mut selected_spans: Vec<(usize, usize, usize, usize)> = Vec::new();
         let mut filtered_nodes: Vec<Box<dyn AstNode>> = Vec::new();
-        'outer: for (_, sl, sc, el, ec, m) in mm {
-            for s in &selected_spans {
-                if overlaps((sl, sc, el, ec), *s) {
-                    continue 'outer;
-                }
+        let pattern_label = pattern.get_pattern_string().map_or(pattern.key(), String::as_str);
+        for (_, sl, sc, el, ec, m) in mm {
+            if selected_spans.iter().any(|s| overlaps((sl, sc, el, ec), *s)) {
+                trace_node(&rule.id, pattern_label, &context.file_path, m.as_ref(), &HashMap::new(), MatchDecision::Overlapped);
+                continue;
             }
+            trace_node(&rule.id, pattern_label, &context.file_path, m.as_ref(), &HashMap::new(), MatchDecision::Reported);
             selected_spans.push((sl, sc, el, ec));
             filtered_nodes.push(m);
         }
@@ -439,6 +824,102 @@ impl RuleExecutionEngine {
         Ok(findings)
     }
 
+    /// Build the source-level type table when any of the patterns carries a
+    /// `metavariable-type` constraint
+    fn type_table_for(patterns: &[&Pattern], context: &RuleContext) -> Option<SymbolTable> {
+        let needs_types = patterns
+            .iter()
+            .flat_map(|p| p.conditions.iter())
+            .any(|c| matches!(c, Condition::MetavariableType(_)));
+        needs_types.then(|| SymbolTable::from_source(&context.source_code, context.language))
+    }
+
+    /// Check `metavariable-type` constraints against the bindings of a source match.
+    /// A metavariable whose type cannot be inferred does not satisfy the constraint.
+    fn satisfies_type_constraints(conditions: &[Condition], bindings: &HashMap<String, (usize, usize)>, source: &str, types: Option<&SymbolTable>) -> bool {
+        let types = match types {
+            Some(types) => types,
+            None => return true,
+        };
+        conditions.iter().all(|condition| match condition {
+            Condition::MetavariableType(metavar_type) => bindings
+                .get(&metavar_type.metavariable)
+                .map(|&(from, to)| types.infer_expression_type(Self::receiver_expression(source, from, to)))
+                .is_some_and(|inferred| metavar_type.types.iter().any(|t| inferred.matches_type_name(t))),
+            _ => true,
+        })
+    }
+
+    /// Check `metavariable-numeric` constraints against the literals bound by a source
+    /// match, read with the numeric syntax of the analyzed language
+    fn satisfies_numeric_constraints(conditions: &[Condition], bindings: &HashMap<String, (usize, usize)>, context: &RuleContext) -> bool {
+        conditions.iter().all(|condition| match condition {
+            Condition::MetavariableNumeric(metavar_numeric) => bindings
+                .get(&metavar_numeric.metavariable)
+                .is_some_and(|&(from, to)| metavar_numeric.holds(&context.source_code[from..to], context.language)),
+            _ => true,
+        })
+    }
+
+    /// Substitute the code bound to each `$NAME` into a template such as a fix or a
+    /// message; names without a binding are left as written
+    fn substitute_metavariables(template: &str, values: &[(String, &str)]) -> String {
+        // Longest names first so `$XY` is not clobbered by `$X`
+        let mut values: Vec<&(String, &str)> = values.iter().collect();
+        values.sort_by_key(|(name, _)| std::cmp::Reverse(name.len()));
+        values.iter().fold(template.to_string(), |rendered, (name, value)| rendered.replace(name.as_str(), value))
+    }
+
+    /// The code bound by a source match, by `$NAME`
+    fn source_values<'s>(bindings: &HashMap<String, (usize, usize)>, source: &'s str) -> Vec<(String, &'s str)> {
+        bindings.iter().map(|(name, &(from, to))| (name.clone(), &source[from..to.min(source.len())])).collect()
+    }
+
+    /// Substitute metavariable bindings of a source match into a rule's fix template
+    fn render_fix(fix: &str, bindings: &HashMap<String, (usize, usize)>, source: &str) -> String {
+        Self::substitute_metavariables(fix, &Self::source_values(bindings, source))
+    }
+
+    /// The message of a source match: the rule's description with its metavariables
+    /// interpolated, or the matched code when the rule has none
+    fn render_message(rule: &Rule, bindings: &HashMap<String, (usize, usize)>, source: &str, matched_text: &str) -> String {
+        if rule.description.is_empty() {
+            format!("Match: {}", matched_text)
+        } else {
+            Self::substitute_metavariables(&rule.description, &Self::source_values(bindings, source))
+        }
+    }
+
+    /// A metavariable right before an anchor only captures the last token of a call
+    /// chain (`)` in `Runtime.getRuntime().exec`); widen such captures to the whole
+    /// receiver expression so it can be typed.
+    fn receiver_expression(source: &str, from: usize, to: usize) -> &str {
+        let captured = &source[from..to];
+        if captured != ")" && captured != "]" {
+            return captured;
+        }
+        let bytes = source.as_bytes();
+        let mut pos = to;
+        loop {
+            if pos > 0 && (bytes[pos - 1] == b')' || bytes[pos - 1] == b']') {
+                let (open, close) = if bytes[pos - 1] == b')' { (b'(', b')') } else { (b'[', b']') };
+                let mut depth = 0i32;
+                let mut p = pos;
+                while p > 0 {
+                    p -= 1;
+                    if bytes[p] == close { depth += 1; } else if bytes[p] == open { depth -= 1; if depth == 0 { break; } }
+                }
+                if depth != 0 { break; }
+                pos = p;
+            }
+            let ident_end = pos;
+            while pos > 0 && (bytes[pos - 1].is_ascii_alphanumeric() || bytes[pos - 1] == b'_') { pos -= 1; }
+            if pos == ident_end || pos == 0 || bytes[pos - 1] != b'.' { break; }
+            pos -= 1;
+        }
+        &source[pos..to]
+    }
+
     /// Try to create a best-effort location for a match using node.location() first,
     /// then fallback to approximating from the pattern's literal anchors in source text.
     fn create_best_location_from_node_or_pattern(&self, node: &dyn AstNode, pattern: &Pattern, context: &RuleContext) -> Location {
@@ -502,49 +983,49 @@ impl RuleExecutionEngine {
     }
 
     /// Find pattern matches in AST (simplified implementation)
-    fn find_pattern_matches(&self, pattern: &Pattern, ast: &dyn AstNode, language: astgrep_core::Language) -> Result<Vec<Box<dyn AstNode>>> {
+    fn find_pattern_matches(&self, pattern: &Pattern, ast: &dyn AstNode, language: astgrep_core::Language, ast_matching: bool, cancellation: &CancellationToken) -> Result<Vec<Box<dyn AstNode>>> {
         let mut matches = Vec::new();
-        let mut node_count = 0;
 
-        println!("🔍 Starting AST traversal for pattern: {:?}", pattern);
+        tracing::trace!(pattern = ?pattern.pattern_type, "matching pattern on nodes");
 
         // Handle different pattern types
         match &pattern.pattern_type {
             crate::types::PatternType::Either(sub_patterns) => {
-                println!("🔍 Processing Either pattern with {} sub-patterns", sub_patterns.len());
                 // For Either patterns, try each sub-pattern
                 for (i, sub_pattern) in sub_patterns.iter().enumerate() {
-                    println!("🔍 Trying Either sub-pattern {}: {:?}", i + 1, sub_pattern);
-                    let sub_matches = self.find_pattern_matches(sub_pattern, ast, language)?;
-                    println!("🔍 Either sub-pattern {} found {} matches", i + 1, sub_matches.len());
+                    let sub_matches = self.find_pattern_matches(sub_pattern, ast, language, ast_matching, cancellation)?;
+                    tracing::trace!(alternative = i + 1, nodes = sub_matches.len(), "pattern-either alternative matched");
                     matches.extend(sub_matches);
                 }
             }
-            _ => {
-                // Simple text-based matching for demonstration
-                // In a real implementation, this would use proper AST pattern matching
-                astgrep_core::ast_utils::visit_nodes(ast, &mut |node| {
-                    node_count += 1;
-                    if let Some(text) = node.text() {
-                        println!("🔍 Visiting node #{}: '{}'", node_count, text);
-                        if let Some(pattern_str) = pattern.get_pattern_string() {
-                            println!("🔍 Pattern string: '{}'", pattern_str);
-                            if self.simple_pattern_match(pattern_str, text, language) {
-                                println!("🔍 MATCH FOUND! Adding node to matches");
-                                matches.push(node.clone_node());
-                            }
-                        } else {
-                            println!("🔍 No pattern string found for pattern: {:?}", pattern.pattern_type);
-                        }
-                    } else {
-                        println!("🔍 Visiting node #{}: <no text>", node_count);
-                    }
-                    Ok(())
-                })?;
-            }
+            _ => match pattern.get_pattern_string() {
+                Some(pattern_str) => matches.extend(self.nodes_matching(pattern_str, ast, language, ast_matching, cancellation)?),
+                None => tracing::debug!(pattern = ?pattern.pattern_type, "no pattern string to match on nodes"),
+            },
         }
 
-        println!("🔍 AST traversal complete. Visited {} nodes, found {} matches", node_count, matches.len());
+        tracing::trace!(nodes = matches.len(), "node matching complete");
+        Ok(matches)
+    }
+
+    /// Nodes of `ast` matching a simple pattern: on the syntax tree when the pattern parses
+    /// with the grammar of `language`, and otherwise by its tokens on the text of each node,
+    /// in the cases listed at [`Self::pattern_matches`]. Fails with `AnalysisError::Cancelled`
+    /// once `cancellation` is set.
+    fn nodes_matching(&self, pattern: &str, ast: &dyn AstNode, language: astgrep_core::Language, ast_matching: bool, cancellation: &CancellationToken) -> Result<Vec<Box<dyn AstNode>>> {
+        if let Some(pattern_ast) = ast_matching.then(|| self.pattern_ast(pattern, language)).flatten() {
+            let matches = pattern_ast.find_matches(ast, cancellation);
+            cancellation.check()?;
+            return Ok(matches.into_iter().flat_map(|found| found.nodes).map(|node| node.clone_node()).collect());
+        }
+        let mut matches = Vec::new();
+        astgrep_core::ast_utils::visit_nodes(ast, &mut |node| {
+            cancellation.check()?;
+            if node.text().is_some_and(|text| self.simple_pattern_match(pattern, text, language)) {
+                matches.push(node.clone_node());
+            }
+            Ok(())
+        })?;
         Ok(matches)
     }
 
@@ -638,16 +1119,18 @@ impl RuleExecutionEngine {
         tokens
     }
 
-    /// Try to match a pattern starting at token index `start` and return end token index on success
+    /// Try to match a pattern starting at token index `start` and return end token index on success,
+    /// together with the byte range captured by each metavariable.
     /// `case_insensitive` controls literal comparisons (used for SQL keywords, etc.)
-    fn try_match_tokens(&self, pattern_tokens: &[String], text_tokens: &[(String, usize, usize)], start: usize, case_insensitive: bool) -> Option<usize> {
+    fn try_match_tokens(&self, pattern_tokens: &[String], text_tokens: &[(String, usize, usize)], start: usize, case_insensitive: bool, cancellation: &CancellationToken) -> Option<TokenMatch> {
         let mut i = 0usize; // pattern index
         let mut j = start;  // text token index
         let mut bindings: HashMap<String, Vec<String>> = HashMap::new();
+        let mut captures: HashMap<String, (usize, usize)> = HashMap::new();
         while i < pattern_tokens.len() {
             if j >= text_tokens.len() { return None; }
             let p_tok = &pattern_tokens[i];
-            if case_insensitive { println!("TRACE try_match: i={}, j={}, p_tok='{}', text='{}'", i, j, p_tok, text_tokens[j].0); }
+            if case_insensitive { tracing::trace!("TRACE try_match: i={}, j={}, p_tok='{}', text='{}'", i, j, p_tok, text_tokens[j].0); }
 
             // Treat "$ ..." (a dollar immediately followed by ellipsis token) as a pure ellipsis (no binding),
             // to be Semgrep-compatible with `$...` syntax commonly used in SQL patterns.
@@ -657,21 +1140,20 @@ impl RuleExecutionEngine {
 
             // Ellipsis: match variable-length sequence (including empty) until next anchor
             if p_tok == "..." || is_dollar_ellipsis {
-                if case_insensitive { println!("TRACE ellipsis encountered at i={}, j={}, is_dollar_ellipsis={}", i, j, is_dollar_ellipsis); }
+                if case_insensitive { tracing::trace!("TRACE ellipsis encountered at i={}, j={}, is_dollar_ellipsis={}", i, j, is_dollar_ellipsis); }
                 // When consuming `$ ...`, advance pattern by 2 tokens; otherwise by 1
                 if is_dollar_ellipsis { i += 1; } // so the common handling below will also `i += 1` at the end
                 // find next anchor that is neither metavariable nor ellipsis
-                let next_anchor_idx = (i + 1..pattern_tokens.len())
-                    .find(|&k| pattern_tokens[k] != "..." && !pattern_tokens[k].starts_with('$'));
+                let next_anchor_idx = (i + 1..pattern_tokens.len()).find(|&k| Self::is_anchor(&pattern_tokens[k]));
                 match next_anchor_idx {
                     None => {
-                        if case_insensitive { println!("TRACE ellipsis to end: returning len={}", text_tokens.len()); }
+                        if case_insensitive { tracing::trace!("TRACE ellipsis to end: returning len={}", text_tokens.len()); }
                         // Ellipsis at end: matches the rest (including empty)
-                        return Some(text_tokens.len());
+                        return Some((text_tokens.len(), captures));
                     }
                     Some(k) => {
                         let next_lit = &pattern_tokens[k];
-                        if case_insensitive { println!("TRACE ellipsis next anchor literal='{}' (k={})", next_lit, k); }
+                        if case_insensitive { tracing::trace!("TRACE ellipsis next anchor literal='{}' (k={})", next_lit, k); }
                         // Balanced delimiters for common closers
                         let mut set_pos: Option<usize> = None;
                         if next_lit == ")" || next_lit == "]" || next_lit == "}" {
@@ -685,7 +1167,7 @@ impl RuleExecutionEngine {
                                 pos += 1;
                             }
                             if let Some(end_pos) = set_pos {
-                                if case_insensitive { println!("TRACE ellipsis matched to close at pos={}", end_pos); }
+                                if case_insensitive { tracing::trace!("TRACE ellipsis matched to close at pos={}", end_pos); }
                                 // Allow empty between open and close (end_pos == j)
                                 i += 1; j = end_pos; continue;
                             } else { return None; }
@@ -699,22 +1181,47 @@ impl RuleExecutionEngine {
                                 pos += 1;
                             }
                             if let Some(end_pos) = found {
-                                if case_insensitive { println!("TRACE ellipsis skipped to anchor at pos={}", end_pos); }
+                                if case_insensitive { tracing::trace!("TRACE ellipsis skipped to anchor at pos={}", end_pos); }
                                 // empty allowed (end_pos == j)
                                 i += 1; j = end_pos; continue;
                             } else { return None; }
                         }
                     }
                 }
+            } else if let Some(inner) = Self::deep_inner(p_tok) {
+                // Deep expression: the expression up to the next anchor contains a match of `inner`
+                let stop = (i + 1..pattern_tokens.len()).find(|&k| Self::is_anchor(&pattern_tokens[k])).map(|k| pattern_tokens[k].as_str());
+                let end = Self::expression_end(text_tokens, j, stop, case_insensitive);
+                if end == j { return None; }
+                let inner_tokens = self.group_deep_expressions(self.tokenize_pattern(inner));
+                let tokens_in = |(from, to): (usize, usize)| -> Vec<String> {
+                    text_tokens.iter().filter(|t| t.1 >= from && t.2 <= to).map(|t| t.0.clone()).collect()
+                };
+                let inner_captures = (j..end).take_while(|_| !cancellation.is_cancelled()).find_map(|from| {
+                    let (_, inner_captures) = self.try_match_tokens(&inner_tokens, &text_tokens[..end], from, case_insensitive, cancellation)?;
+                    // Inner metavariables bind whole expressions, and those bound both inside
+                    // and outside must agree
+                    let consistent = inner_captures.iter().all(|(name, span)| {
+                        let tokens = tokens_in(*span);
+                        Self::is_balanced(&tokens) && bindings.get(name).is_none_or(|prev| *prev == tokens)
+                    });
+                    consistent.then_some(inner_captures)
+                })?;
+                for (name, span) in inner_captures {
+                    bindings.entry(name.clone()).or_insert_with(|| tokens_in(span));
+                    captures.entry(name).or_insert(span);
+                }
+                i += 1; j = end;
             } else if p_tok.starts_with('$') {
                 // Handle normal metavariables like `$T1`, `$SUBQUERY`. Do NOT conflate with `$ ...` which is handled above.
-                let next_lit_idx = (i + 1..pattern_tokens.len()).find(|&k| pattern_tokens[k] != "..." && !pattern_tokens[k].starts_with('$'));
+                let next_lit_idx = (i + 1..pattern_tokens.len()).find(|&k| Self::is_anchor(&pattern_tokens[k]));
                 match next_lit_idx {
                     None => {
                         let capture: Vec<String> = text_tokens[j..].iter().map(|t| t.0.clone()).collect();
                         if capture.is_empty() { return None; }
-                        if let Some(prev) = bindings.get(p_tok) { if *prev != capture { return None; } } else { bindings.insert(p_tok.clone(), capture); }
-                        return Some(text_tokens.len());
+                        if !Self::unify(&mut bindings, p_tok, capture) { return None; }
+                        if !is_anonymous_metavar(p_tok) { captures.insert(p_tok.clone(), (text_tokens[j].1, text_tokens[text_tokens.len() - 1].2)); }
+                        return Some((text_tokens.len(), captures));
                     }
                     Some(k) => {
                         let next_lit = &pattern_tokens[k];
@@ -729,7 +1236,8 @@ impl RuleExecutionEngine {
                             if let Some(end_pos) = end_pos {
                                 if end_pos == j { return None; }
                                 let capture: Vec<String> = text_tokens[j..end_pos].iter().map(|t| t.0.clone()).collect();
-                                if let Some(prev) = bindings.get(p_tok) { if *prev != capture { return None; } } else { bindings.insert(p_tok.clone(), capture); }
+                                if !Self::unify(&mut bindings, p_tok, capture) { return None; }
+                                if !is_anonymous_metavar(p_tok) { captures.entry(p_tok.clone()).or_insert((text_tokens[j].1, text_tokens[end_pos - 1].2)); }
                                 i += 1; j = end_pos; continue;
                             } else { return None; }
                         } else {
@@ -743,7 +1251,8 @@ impl RuleExecutionEngine {
                             if let Some(end_pos) = found {
                                 if end_pos == j { return None; }
                                 let capture: Vec<String> = text_tokens[j..end_pos].iter().map(|t| t.0.clone()).collect();
-                                if let Some(prev) = bindings.get(p_tok) { if *prev != capture { return None; } } else { bindings.insert(p_tok.clone(), capture); }
+                                if !Self::unify(&mut bindings, p_tok, capture) { return None; }
+                                if !is_anonymous_metavar(p_tok) { captures.entry(p_tok.clone()).or_insert((text_tokens[j].1, text_tokens[end_pos - 1].2)); }
                                 i += 1; j = end_pos; continue;
                             } else { return None; }
                         }
@@ -755,16 +1264,146 @@ impl RuleExecutionEngine {
                 i += 1; j += 1;
             }
         }
-        Some(j)
+        Some((j, captures))
+    }
+
+    /// Bind `metavar` to the captured tokens, or check them against its earlier binding.
+    /// The anonymous `$_` matches anything and binds nothing.
+    fn unify(bindings: &mut HashMap<String, Vec<String>>, metavar: &str, capture: Vec<String>) -> bool {
+        if is_anonymous_metavar(metavar) {
+            return true;
+        }
+        match bindings.get(metavar) {
+            Some(prev) => *prev == capture,
+            None => {
+                bindings.insert(metavar.to_string(), capture);
+                true
+            }
+        }
     }
 
-    /// Find spans (byte start, byte end) of matches in the given source
-    fn find_pattern_spans_in_source(&self, pattern: &str, source: &str, language: astgrep_core::Language, sql_stmt_boundary: bool) -> Vec<(usize, usize)> {
+    /// Whether a pattern token must appear literally in the source, as opposed to an
+    /// ellipsis, a metavariable or a deep expression
+    fn is_anchor(token: &str) -> bool {
+        token != "..." && !token.starts_with('$') && Self::deep_inner(token).is_none()
+    }
+
+    /// The inner pattern of a deep expression token `<... inner ...>`
+    fn deep_inner(token: &str) -> Option<&str> {
+        token.strip_prefix("<...")?.strip_suffix("...>")
+    }
+
+    /// Merge the `<`, `...`, inner tokens, `...`, `>` of each outermost deep expression
+    /// `<... inner ...>` into one token
+    fn group_deep_expressions(&self, tokens: Vec<String>) -> Vec<String> {
+        let mut grouped = Vec::with_capacity(tokens.len());
+        let mut idx = 0usize;
+        while idx < tokens.len() {
+            let opens = |k: usize| tokens[k] == "<" && tokens.get(k + 1).is_some_and(|t| t == "...");
+            let closes = |k: usize| tokens[k] == "..." && tokens.get(k + 1).is_some_and(|t| t == ">");
+            if !opens(idx) {
+                grouped.push(tokens[idx].clone());
+                idx += 1;
+                continue;
+            }
+            let mut depth = 0usize;
+            let mut k = idx;
+            let close = loop {
+                if k >= tokens.len() { break None; }
+                if opens(k) { depth += 1; k += 2; continue; }
+                if closes(k) {
+                    depth -= 1;
+                    if depth == 0 { break Some(k); }
+                    k += 2;
+                    continue;
+                }
+                k += 1;
+            };
+            match close {
+                Some(close) => {
+                    grouped.push(format!("<...{}...>", tokens[idx + 2..close].join(" ")));
+                    idx = close + 2;
+                }
+                // Unclosed: an ordinary `<` comparison
+                None => {
+                    grouped.push(tokens[idx].clone());
+                    idx += 1;
+                }
+            }
+        }
+        grouped
+    }
+
+    /// Whether every bracket in `tokens` is closed within them
+    fn is_balanced(tokens: &[String]) -> bool {
+        let mut depth = 0i32;
+        for tok in tokens {
+            match tok.as_str() {
+                "(" | "[" | "{" => depth += 1,
+                ")" | "]" | "}" => depth -= 1,
+                _ => {}
+            }
+            if depth < 0 { return false; }
+        }
+        depth == 0
+    }
+
+    /// The token index where the expression starting at `start` ends: at `stop` outside
+    /// brackets, a `;` outside brackets, or the bracket closing around it
+    fn expression_end(text_tokens: &[(String, usize, usize)], start: usize, stop: Option<&str>, case_insensitive: bool) -> usize {
+        let mut depth = 0usize;
+        for (pos, (tok, _, _)) in text_tokens.iter().enumerate().skip(start) {
+            let is_stop = stop.is_some_and(|stop| if case_insensitive { tok.eq_ignore_ascii_case(stop) } else { tok == stop });
+            if depth == 0 && (is_stop || tok == ";") {
+                return pos;
+            }
+            match tok.as_str() {
+                "(" | "[" | "{" => depth += 1,
+                ")" | "]" | "}" if depth == 0 => return pos,
+                ")" | "]" | "}" => depth -= 1,
+                _ => {}
+            }
+        }
+        text_tokens.len()
+    }
+
+    /// Narrow each match to the spans of its focused metavariables, one match per bound
+    /// focus metavariable; without focus the matches are kept as they are
+    fn apply_focus(focus: Option<&[String]>, matches: Vec<SourceMatch>) -> Vec<SourceMatch> {
+        let focus = match focus {
+            Some(focus) if !focus.is_empty() => focus,
+            _ => return matches,
+        };
+        matches
+            .into_iter()
+            .flat_map(|(_, _, bindings)| {
+                focus
+                    .iter()
+                    .filter_map(|name| bindings.get(name).or_else(|| bindings.get(&format!("${}", name))).copied())
+                    .map(|(start, end)| (start, end, bindings.clone()))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// Pattern tokens of a simple pattern for `language`, compiled once per process. Every
+    /// file of a scan matches the same patterns, and there are few distinct ones; the
+    /// least recently used are dropped past [`PATTERN_CACHE_CAPACITY`].
+    fn compiled_pattern(&self, pattern: &str, language: astgrep_core::Language) -> Arc<[String]> {
+        static CACHE: OnceLock<SharedLruCache<PatternKey, Arc<[String]>>> = OnceLock::new();
+        CACHE
+            .get_or_init(|| SharedLruCache::new(PATTERN_CACHE_CAPACITY))
+            .get_or_insert_with((pattern.to_string(), language), || self.compile_pattern(pattern, language).into())
+    }
+
+    /// Tokenize a simple pattern for matching on source: `$...` becomes `...`, deep
+    /// expressions are grouped, and outside SQL a trailing `;` is optional
+    fn compile_pattern(&self, pattern: &str, language: astgrep_core::Language) -> Vec<String> {
         // Preprocess: make `$...` Semgrep form equivalent to `...` before tokenization
         let preprocessed = pattern.replace("$...", "...");
-        println!("DEBUG find_pattern_spans_in_source: pattern='{}', preprocessed='{}', lang={:?}", pattern, preprocessed, language);
+        tracing::trace!(pattern, preprocessed = %preprocessed, language = language.as_str(), "compiling pattern");
         let mut pattern_tokens = self.tokenize_pattern(&preprocessed);
-        println!("DEBUG pattern_tokens={:?}", pattern_tokens);
+        tracing::trace!(pattern_tokens = ?pattern_tokens, "pattern tokenized");
         if pattern_tokens.last() == Some(&";".to_string()) {
             // For SQL patterns, keep explicit trailing semicolon as an anchor to prevent
             // trailing ellipsis from spanning to end-of-file across statements.
@@ -784,17 +1423,24 @@ impl RuleExecutionEngine {
                 idx += 1;
             }
         }
-        pattern_tokens = coalesced;
-        println!("DEBUG coalesced_pattern_tokens={:?}", pattern_tokens);
+        let pattern_tokens = self.group_deep_expressions(coalesced);
+        tracing::trace!(pattern_tokens = ?pattern_tokens, "pattern tokens coalesced");
+        pattern_tokens
+    }
+
+    /// Find matches in the given source as (byte start, byte end, metavariable byte ranges),
+    /// stopping with the matches found so far once `cancellation` is set
+    fn find_pattern_matches_in_source(&self, pattern: &str, source: &str, language: astgrep_core::Language, sql_stmt_boundary: bool, cancellation: &CancellationToken) -> Vec<SourceMatch> {
+        let pattern_tokens = self.compiled_pattern(pattern, language);
 
         // Determine first literal anchor (the first token that is neither ellipsis nor metavariable)
         let first_anchor_idx: Option<usize> = pattern_tokens
             .iter()
-            .position(|t| t.as_str() != "..." && !t.starts_with('$'));
+            .position(|t| Self::is_anchor(t));
         let first_anchor: Option<String> = first_anchor_idx.map(|idx| pattern_tokens[idx].clone());
 
         let text_tokens = self.tokenize_spanned(source);
-        println!("DEBUG text_tokens (first 40)={:?}", text_tokens.iter().take(40).map(|t| &t.0).collect::<Vec<_>>());
+        tracing::trace!(text_tokens = ?text_tokens.iter().take(40).map(|t| &t.0).collect::<Vec<_>>(), "source tokenized (first 40)");
         let mut spans = Vec::new();
         let case_insensitive = matches!(language, astgrep_core::Language::Sql);
 
@@ -805,6 +1451,7 @@ impl RuleExecutionEngine {
                 (Some(anchor_idx), Some(anchor_tok)) => {
                     // Scan by anchor occurrences and back-compute the candidate start so that anchor aligns with its index in the pattern
                     for pos in 0..window.len() {
+                        if cancellation.is_cancelled() { break; }
                         let tok = &window[pos].0;
                         let lit_ok = if case_insensitive { tok.eq_ignore_ascii_case(anchor_tok) } else { tok == anchor_tok };
                         if !lit_ok { continue; }
@@ -819,19 +1466,20 @@ impl RuleExecutionEngine {
                                 }
                             }
                         }
-                        if let Some(rel_end) = self.try_match_tokens(&pattern_tokens, window, rel_start, case_insensitive) {
+                        if let Some((rel_end, captures)) = self.try_match_tokens(&pattern_tokens, window, rel_start, case_insensitive, cancellation) {
                             if rel_end == 0 { continue; }
                             let abs_start_idx = win_start + rel_start;
                             let abs_end_idx_exclusive = win_start + rel_end;
                             let start_byte = text_tokens[abs_start_idx].1;
                             let end_byte = text_tokens[abs_end_idx_exclusive - 1].2;
-                            spans.push((start_byte, end_byte));
+                            spans.push((start_byte, end_byte, captures));
                         }
                     }
                 }
                 _ => {
                     // No literal anchor: fall back to trying every position
                     for rel_start in 0..window.len() {
+                        if cancellation.is_cancelled() { break; }
                         if matches!(language, astgrep_core::Language::Java) {
                             if let Some(first_lit) = pattern_tokens.iter().find(|t| !t.starts_with('$')) {
                                 let is_ident = first_lit.chars().all(|c| c.is_alphanumeric() || c == '_');
@@ -840,13 +1488,13 @@ impl RuleExecutionEngine {
                                 }
                             }
                         }
-                        if let Some(rel_end) = self.try_match_tokens(&pattern_tokens, window, rel_start, case_insensitive) {
+                        if let Some((rel_end, captures)) = self.try_match_tokens(&pattern_tokens, window, rel_start, case_insensitive, cancellation) {
                             if rel_end == 0 { continue; }
                             let abs_start_idx = win_start + rel_start;
                             let abs_end_idx_exclusive = win_start + rel_end;
                             let start_byte = text_tokens[abs_start_idx].1;
                             let end_byte = text_tokens[abs_end_idx_exclusive - 1].2;
-                            spans.push((start_byte, end_byte));
+                            spans.push((start_byte, end_byte, captures));
                         }
                     }
                 }
@@ -886,6 +1534,17 @@ impl RuleExecutionEngine {
         (line, col)
     }
 
+    /// Convert a 1-based (line, column) in `s` to a byte index, the inverse of
+    /// [`Self::byte_index_to_line_col`]
+    fn line_col_to_byte_index(s: &str, line: usize, col: usize) -> usize {
+        let line_start = if line <= 1 {
+            0
+        } else {
+            s.match_indices('\n').nth(line - 2).map_or(s.len(), |(i, _)| i + 1)
+        };
+        s[line_start..].char_indices().nth(col.saturating_sub(1)).map_or(s.len(), |(i, _)| line_start + i)
+    }
+
 
 
 
@@ -897,21 +1556,16 @@ impl RuleExecutionEngine {
     /// - 允许 pattern 末尾分号为可选；
     /// - 从每个可能的起点尝试匹配，一旦成功即返回 true。
     fn simple_pattern_match(&self, pattern: &str, text: &str, language: astgrep_core::Language) -> bool {
-        println!("🔍 Pattern: '{}'", pattern);
-        println!("🔍 Node text: '{}'", text);
-
         // Tokenize pattern and text
         let mut pattern_tokens = self.tokenize_pattern(pattern);
         let text_tokens = self.tokenize(text);
 
-        println!("🔍 Pattern tokens: {:?}", pattern_tokens);
-        println!("🔍 Text tokens (len={}): <omitted>", text_tokens.len());
+        tracing::trace!(pattern, pattern_tokens = ?pattern_tokens, text_tokens = text_tokens.len(), "matching tokens");
 
         if pattern_tokens.is_empty() { return false; }
 
         // Allow trailing semicolon in pattern to be optional
         if pattern_tokens.last() == Some(&";".to_string()) {
-            println!("🔍 Pattern has trailing semicolon; making it optional for matching");
             pattern_tokens.pop();
         }
 
@@ -992,11 +1646,7 @@ impl RuleExecutionEngine {
                             if j >= text_tokens.len() { return false; }
                             let capture: Vec<String> = text_tokens[j..].to_vec();
                             if capture.is_empty() { return false; }
-                            if let Some(prev) = bindings.get(p_tok) {
-                                if *prev != capture { return false; }
-                            } else {
-                                bindings.insert(p_tok.clone(), capture);
-                            }
+                            if !Self::unify(&mut bindings, p_tok, capture) { return false; }
                             // 完整匹配
                             return true;
                         }
@@ -1017,11 +1667,7 @@ impl RuleExecutionEngine {
                                 if let Some(end_pos) = found_end {
                                     if end_pos == j { return false; } // 至少一个 token
                                     let capture: Vec<String> = text_tokens[j..end_pos].to_vec();
-                                    if let Some(prev) = bindings.get(p_tok) {
-                                        if *prev != capture { return false; }
-                                    } else {
-                                        bindings.insert(p_tok.clone(), capture);
-                                    }
+                                    if !Self::unify(&mut bindings, p_tok, capture) { return false; }
                                     // 不消耗 next_lit，本轮只前进 pattern 到下一个 token，文本前进到 end_pos
                                     i += 1;
                                     j = end_pos;
@@ -1041,11 +1687,7 @@ impl RuleExecutionEngine {
                                 if let Some(end_pos) = found_k {
                                     if end_pos == j { return false; }
                                     let capture: Vec<String> = text_tokens[j..end_pos].to_vec();
-                                    if let Some(prev) = bindings.get(p_tok) {
-                                        if *prev != capture { return false; }
-                                    } else {
-                                        bindings.insert(p_tok.clone(), capture);
-                                    }
+                                    if !Self::unify(&mut bindings, p_tok, capture) { return false; }
                                     i += 1;
                                     j = end_pos; // 不消耗 next_lit
                                     continue;
@@ -1079,11 +1721,10 @@ impl RuleExecutionEngine {
                 }
             }
             if try_match_from(start) {
-                println!("🔍 Match successful starting at token index {}", start);
+                tracing::trace!(pattern, start_token = start, "tokens matched");
                 return true;
             }
         }
-        println!("🔍 No matching span found");
         false
     }
 
@@ -1099,45 +1740,146 @@ impl RuleExecutionEngine {
 
         // Simplified dataflow analysis
         // In a real implementation, this would use proper taint analysis
-        let sources = self.find_dataflow_nodes(ast, &dataflow.sources, context.language)?;
-        let sinks = self.find_dataflow_nodes(ast, &dataflow.sinks, context.language)?;
+        let ast_matching = Self::ast_matching_enabled(rule);
+        let (model_sinks, sink_patterns): (Vec<&String>, Vec<&String>) =
+            dataflow.sinks.iter().partition(|sink| sink.starts_with(MODEL_SINK_PREFIX));
+        let sink_patterns: Vec<String> = sink_patterns.into_iter().cloned().collect();
+        let mut sources = self.find_dataflow_nodes(ast, &dataflow.sources, context.language, ast_matching, &context.cancellation)?;
+        let mut sinks: Vec<(Box<dyn AstNode>, Option<&FunctionModel>)> = self
+            .find_dataflow_nodes(ast, &sink_patterns, context.language, ast_matching, &context.cancellation)?
+            .into_iter()
+            .map(|sink| (sink, None))
+            .collect();
+
+        if !self.taint_models.is_empty() {
+            let sink_types: Vec<SinkType> = model_sinks
+                .iter()
+                .filter_map(|sink| {
+                    let kind = &sink[MODEL_SINK_PREFIX.len()..];
+                    let sink_type = SinkType::parse(kind);
+                    if sink_type.is_none() {
+                        tracing::warn!(rule = %rule.id, "unknown sink kind '{}' of the taint models", kind);
+                    }
+                    sink_type
+                })
+                .collect();
+            astgrep_core::ast_utils::visit_nodes(ast, &mut |node| {
+                context.cancellation.check()?;
+                if let Some(model) = modeled_callee(node).and_then(|callee| self.taint_models.lookup(callee)) {
+                    match model.kind {
+                        ModelKind::Source(_) => sources.push(node.clone_node()),
+                        ModelKind::Sink { ref sink_type, .. } if node.node_type() == "call_expression" && sink_types.contains(sink_type) => {
+                            sinks.push((node.clone_node(), Some(model)))
+                        }
+                        _ => {}
+                    }
+                }
+                Ok(())
+            })?;
+            sinks.retain(|(sink, model)| !self.is_sanitized(sink.as_ref(), *model));
+        }
 
-        // Check if there are potential flows from sources to sinks
-        if !sources.is_empty() && !sinks.is_empty() {
-            for sink in sinks {
-                let location = self.create_location_from_node(sink.as_ref(), context);
+        // Check if there are potential flows from sources to sinks. Without a source in the
+        // file, a sink may still receive data that a function of another file read from a
+        // source, which the project index tells
+        let index = self.project_index.as_deref().filter(|_| sources.is_empty());
+        let tainted = index.map(|index| Self::tainted_functions(index, &dataflow.sources)).unwrap_or_default();
+        if sources.is_empty() && tainted.is_empty() {
+            return Ok(findings);
+        }
+        let cross_file = index.filter(|_| !tainted.is_empty()).map(|index| CrossFileTaint::new(index, tainted, ast));
 
-                let finding = Finding::new(
-                    rule.id.clone(),
-                    format!("Potential data flow from source to sink: {}", rule.description),
-                    rule.severity,
-                    rule.confidence,
-                    location,
-                )
-                .with_metadata("analysis_type".to_string(), "dataflow".to_string());
+        for (sink, model) in sinks {
+            let source_function = match cross_file {
+                Some(ref cross_file) => match cross_file.source_function(sink.as_ref()) {
+                    Some(function) => Some(function),
+                    None => continue,
+                },
+                None => None,
+            };
+            let location = self.create_location_from_node(sink.as_ref(), context);
 
-                findings.push(finding);
+            let mut finding = Finding::new(
+                rule.id.clone(),
+                format!("Potential data flow from source to sink: {}", rule.description),
+                rule.severity,
+                rule.confidence,
+                location,
+            )
+            .with_metadata("analysis_type".to_string(), "dataflow".to_string());
+            if let Some(model) = model {
+                finding = finding.with_metadata("taint_model".to_string(), model.function.clone());
             }
+            if let Some(function) = source_function {
+                finding = finding.with_metadata("taint_source_function".to_string(), function);
+            }
+
+            findings.push(finding);
         }
 
         Ok(findings)
     }
 
+    /// Mark pattern findings whose span contains the sink of a taint flow, i.e. the location
+    /// of a dataflow finding, as `taint_corroborated` and raise them to at least `escalation`
+    fn corroborate_findings(findings: &mut [Finding], flows: &[Finding], escalation: Option<(Severity, Confidence)>) {
+        for finding in findings.iter_mut() {
+            let corroborated = flows.iter().any(|flow| {
+                flow.location.file == finding.location.file
+                    && flow.location.start_line <= finding.location.end_line
+                    && finding.location.start_line <= flow.location.end_line
+            });
+            if corroborated {
+                if let Some((severity, confidence)) = escalation {
+                    finding.severity = finding.severity.max(severity);
+                    finding.confidence = finding.confidence.max(confidence);
+                }
+                finding.metadata.insert("taint_corroborated".to_string(), "true".to_string());
+            }
+        }
+    }
+
+    /// Whether every argument that reaches `sink` is cleaned by a modeled sanitizer: the
+    /// arguments of `model` for a modeled sink, all arguments for a sink of the rule. A
+    /// modeled sink called without any of its arguments is no sink at all.
+    fn is_sanitized(&self, sink: &dyn AstNode, model: Option<&FunctionModel>) -> bool {
+        let arguments: Vec<&dyn AstNode> = call_arguments(sink)
+            .into_iter()
+            .enumerate()
+            .filter(|(index, _)| model.is_none_or(|model| model.is_sink_port(TaintPort::Argument(*index))))
+            .map(|(_, argument)| argument)
+            .collect();
+        if arguments.is_empty() {
+            return model.is_some();
+        }
+        arguments.iter().all(|argument| {
+            modeled_callee(*argument)
+                .filter(|_| argument.node_type() == "call_expression")
+                .and_then(|callee| self.taint_models.lookup(callee))
+                .is_some_and(|sanitizer| match (&sanitizer.kind, model.map(|m| &m.kind)) {
+                    (ModelKind::Sanitizer { .. }, Some(ModelKind::Sink { vulnerability_type, .. })) => sanitizer.sanitizes(vulnerability_type),
+                    (ModelKind::Sanitizer { .. }, _) => true,
+                    _ => false,
+                })
+        })
+    }
+
+    /// Functions of the project index that call one of the rule's source calls
+    fn tainted_functions(index: &ProjectIndex, sources: &[String]) -> BTreeSet<String> {
+        sources
+            .iter()
+            .filter(|source| source.contains('('))
+            .filter_map(|source| indexed_callee(source))
+            .flat_map(|callee| index.callers_of(&callee).into_iter().filter_map(|(_, call)| call.caller.clone()))
+            .collect()
+    }
+
     /// Find nodes matching dataflow patterns
-    fn find_dataflow_nodes(&self, ast: &dyn AstNode, patterns: &[String], language: astgrep_core::Language) -> Result<Vec<Box<dyn AstNode>>> {
+    fn find_dataflow_nodes(&self, ast: &dyn AstNode, patterns: &[String], language: astgrep_core::Language, ast_matching: bool, cancellation: &CancellationToken) -> Result<Vec<Box<dyn AstNode>>> {
         let mut matches = Vec::new();
-
         for pattern in patterns {
-            astgrep_core::ast_utils::visit_nodes(ast, &mut |node| {
-                if let Some(text) = node.text() {
-                    if self.simple_pattern_match(pattern, text, language) {
-                        matches.push(node.clone_node());
-                    }
-                }
-                Ok(())
-            })?;
+            matches.extend(self.nodes_matching(pattern, ast, language, ast_matching, cancellation)?);
         }
-
         Ok(matches)
     }
 
@@ -1202,6 +1944,75 @@ impl Default for RuleExecutionEngine {
     }
 }
 
+/// Cross-file taint of one file: a sink in a function of the file receives tainted data
+/// when the function calls, directly or not, a function reading a source, whose result
+/// it may use, or when such a function calls it, passing the data down
+struct CrossFileTaint<'i> {
+    index: &'i ProjectIndex,
+    /// Functions reading a source, with the functions reachable from each
+    tainted: Vec<(String, HashSet<String>)>,
+    /// Start line, end line and name of the functions of the file
+    functions: Vec<(usize, usize, String)>,
+}
+
+impl<'i> CrossFileTaint<'i> {
+    fn new(index: &'i ProjectIndex, tainted: BTreeSet<String>, ast: &dyn AstNode) -> Self {
+        let tainted = tainted.into_iter().map(|function| {
+            let reachable = index.reachable_from(&function);
+            (function, reachable)
+        });
+        let mut functions = Vec::new();
+        let _ = astgrep_core::ast_utils::visit_nodes(ast, &mut |node| {
+            if let (Some(name), Some((start, _, end, _))) = (indexed_function_name(node), node.location()) {
+                functions.push((start, end, name));
+            }
+            Ok(())
+        });
+        Self { index, tainted: tainted.collect(), functions }
+    }
+
+    /// The function reading the source whose data may reach `sink`, if any
+    fn source_function(&self, sink: &dyn AstNode) -> Option<String> {
+        let (line, _, _, _) = sink.location()?;
+        let (_, _, enclosing) = self
+            .functions
+            .iter()
+            .filter(|(start, end, _)| (*start..=*end).contains(&line))
+            .min_by_key(|(start, end, _)| end - start)?;
+        let callees = self.index.reachable_from(enclosing);
+        self.tainted
+            .iter()
+            .find(|(function, _)| callees.contains(function))
+            .or_else(|| self.tainted.iter().find(|(_, reachable)| reachable.contains(enclosing)))
+            .map(|(function, _)| function.clone())
+    }
+}
+
+/// The function a call calls, or the object path of a property read, as taint models name
+/// them, e.g. `lodash.escape` for `lodash.escape(x)` and `req.query` for `req.query`
+fn modeled_callee(node: &dyn AstNode) -> Option<&str> {
+    match node.node_type() {
+        "call_expression" => node.child(0)?.text(),
+        "member_expression" => node.text(),
+        _ => None,
+    }
+}
+
+/// The arguments of a call, in order
+fn call_arguments(call: &dyn AstNode) -> Vec<&dyn AstNode> {
+    let arguments = (0..call.child_count())
+        .filter_map(|i| call.child(i))
+        .find(|child| matches!(child.get_attribute("ts_kind"), Some("arguments" | "argument_list")));
+    arguments
+        .map(|arguments| {
+            (0..arguments.child_count())
+                .filter_map(|i| arguments.child(i))
+                .filter(|argument| !matches!(argument.text(), Some("(" | ")" | ",")))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1266,6 +2077,40 @@ mod tests {
         assert!(results.iter().all(|r| r.is_success()));
     }
 
+    #[test]
+    fn test_rule_timings() {
+        let mut engine = RuleExecutionEngine::new();
+        let mut rule2 = create_test_rule();
+        rule2.id = "test-rule-2".to_string();
+        let rules = vec![create_test_rule(), rule2];
+        let ast = create_test_ast();
+        let context = create_test_context();
+
+        engine.execute_rules(&rules, &ast, &context);
+        assert!(engine.take_timings().is_empty(), "not recorded unless asked");
+
+        engine.record_timings(true);
+        engine.execute_rules(&rules, &ast, &context);
+        engine.execute_rule(&rules[0], &ast, &context);
+        let timings = engine.take_timings();
+        let mut ids: Vec<&str> = timings.keys().map(String::as_str).collect();
+        ids.sort();
+        assert_eq!(ids, vec!["test-rule", "test-rule-2"]);
+        assert!(engine.take_timings().is_empty(), "taking the timings resets them");
+    }
+
+    #[test]
+    fn test_cancelled_context_skips_rules() {
+        let mut engine = RuleExecutionEngine::new();
+        let rules = vec![create_test_rule()];
+        let ast = create_test_ast();
+        let token = astgrep_core::CancellationToken::new();
+        let context = create_test_context().with_cancellation(token.clone());
+
+        token.cancel();
+        assert!(engine.execute_rules(&rules, &ast, &context).is_empty());
+    }
+
     #[test]
     fn test_rule_not_applicable_to_language() {
         let mut engine = RuleExecutionEngine::new();
@@ -1327,6 +2172,56 @@ mod tests {
         assert_eq!(result.rule_id, "dataflow-rule");
     }
 
+    #[test]
+    fn test_taint_flow_escalates_pattern_match() {
+        let mut engine = RuleExecutionEngine::new();
+        let rule = Rule::new(
+            "java-sqli".to_string(),
+            "SQL injection".to_string(),
+            "Query built from a request parameter".to_string(),
+            Severity::Warning,
+            Confidence::Low,
+            vec![Language::Java],
+        )
+        .add_pattern(Pattern::simple("stmt.executeQuery($Q)".to_string()))
+        .with_dataflow(DataFlowSpec::new(vec!["request.getParameter($P)".to_string()], vec!["stmt.executeQuery($Q)".to_string()]))
+        .add_metadata("escalate_severity".to_string(), "CRITICAL".to_string())
+        .add_metadata("escalate_confidence".to_string(), "HIGH".to_string());
+
+        let source = "String q = request.getParameter(\"id\");\nstmt.executeQuery(q);\n";
+        let statement = |line: usize, text: &str| {
+            UniversalNode::new(NodeType::ExpressionStatement).with_text(text.to_string()).with_location(line, 1, line, text.len() + 1)
+        };
+        let tainted = UniversalNode::new(NodeType::Program)
+            .add_child(statement(1, "String q = request.getParameter(\"id\");"))
+            .add_child(statement(2, "stmt.executeQuery(q);"));
+        let context = RuleContext::new("Query.java".to_string(), Language::Java, source.to_string());
+        let result = engine.execute_rule(&rule, &tainted, &context);
+        let pattern_finding = result.findings.iter().find(|f| !f.metadata.contains_key("analysis_type")).unwrap();
+        assert_eq!(pattern_finding.severity, Severity::Critical);
+        assert_eq!(pattern_finding.confidence, Confidence::High);
+        assert_eq!(pattern_finding.metadata.get("taint_corroborated").map(String::as_str), Some("true"));
+
+        // Without escalation metadata the match is only marked
+        let mut plain = rule.clone();
+        plain.metadata.clear();
+        let result = engine.execute_rule(&plain, &tainted, &context);
+        let pattern_finding = result.findings.iter().find(|f| !f.metadata.contains_key("analysis_type")).unwrap();
+        assert_eq!(pattern_finding.severity, Severity::Warning);
+        assert_eq!(pattern_finding.metadata.get("taint_corroborated").map(String::as_str), Some("true"));
+
+        // Without a source there is no flow, so the match keeps the rule's severity
+        let source = "String q = \"SELECT 1\";\nstmt.executeQuery(q);\n";
+        let untainted = UniversalNode::new(NodeType::Program)
+            .add_child(statement(1, "String q = \"SELECT 1\";"))
+            .add_child(statement(2, "stmt.executeQuery(q);"));
+        let context = RuleContext::new("Query.java".to_string(), Language::Java, source.to_string());
+        let result = engine.execute_rule(&rule, &untainted, &context);
+        assert_eq!(result.findings.len(), 1);
+        assert_eq!(result.findings[0].severity, Severity::Warning);
+        assert!(!result.findings[0].metadata.contains_key("taint_corroborated"));
+    }
+
 
     #[test]
     fn test_sql_case_insensitive_simple_pattern() {
@@ -1475,6 +2370,33 @@ response.getWriter().write(\"<script>var data = '\" + scriptParam + \"';</script
         assert_eq!(result.findings.len(), 3);
     }
 
+    #[test]
+    fn test_fix_template_substitutes_metavariables() {
+        let mut engine = RuleExecutionEngine::new();
+        let rule = Rule::new(
+            "java-writer-write".to_string(),
+            "Detect writer.write".to_string(),
+            "Unencoded output".to_string(),
+            Severity::Error,
+            Confidence::Medium,
+            vec![Language::Java],
+        )
+        .add_pattern(Pattern::simple("response.getWriter().write($INPUT)".to_string()))
+        .with_fix("response.getWriter().write(Encode.forHtml($INPUT))".to_string());
+
+        let context = RuleContext::new(
+            "Xss.java".to_string(),
+            Language::Java,
+            "response.getWriter().write(userInput);\n".to_string(),
+        );
+        let result = engine.execute_rule(&rule, &create_test_ast(), &context);
+        assert_eq!(result.findings.len(), 1);
+        assert_eq!(
+            result.findings[0].fix_suggestion.as_deref(),
+            Some("response.getWriter().write(Encode.forHtml(userInput))")
+        );
+    }
+
     #[test]
     fn test_java_either_with_metavar_multiple_occurrences() {
         let mut engine = RuleExecutionEngine::new();
@@ -1569,7 +2491,434 @@ response.getWriter().write(\"<script>var data = '\" + scriptParam + \"';</script
             assert_eq!(result.findings.len(), 1);
         }
 
+        #[test]
+        fn test_metavariable_type_distinguishes_receivers() {
+            let mut engine = RuleExecutionEngine::new();
+            let rule = Rule::new(
+                "java-runtime-exec".to_string(),
+                "Runtime exec".to_string(),
+                "Command execution via Runtime.exec".to_string(),
+                Severity::Error,
+                Confidence::High,
+                vec![Language::Java],
+            ).add_pattern(
+                Pattern::simple("$RT.exec(...)".to_string()).add_condition(Condition::MetavariableType(
+                    MetavariableType::new("$RT".to_string(), vec!["java.lang.Runtime".to_string()]),
+                )),
+            );
 
-}
+            let java_code = "class D {\n\
+    void f(Runtime rt, Matcher regex, String cmd) {\n\
+        rt.exec(cmd);\n\
+        Runtime.getRuntime().exec(cmd);\n\
+        regex.exec(cmd);\n\
+        exec(cmd);\n\
+    }\n\
+}\n";
+            let ast = create_test_ast();
+            let context = RuleContext::new("D.java".to_string(), Language::Java, java_code.to_string());
+            let result = engine.execute_rule(&rule, &ast, &context);
+            assert!(result.is_success());
+            let lines: Vec<usize> = result.findings.iter().map(|f| f.location.start_line).collect();
+            assert_eq!(lines, vec![3, 4]);
+        }
+
+        #[test]
+        fn test_typed_metavariables_constrain_matches() {
+            let mut engine = RuleExecutionEngine::new();
+            let rule = Rule::new(
+                "java-string-equality".to_string(),
+                "String equality".to_string(),
+                "Strings compared with ==".to_string(),
+                Severity::Warning,
+                Confidence::High,
+                vec![Language::Java],
+            ).add_pattern(Pattern::simple("($A : String) == $B".to_string()).expand_typed_metavariables());
+
+            let java_code = "class D {\n\
+    boolean f(String name, int count) {\n\
+        if (name == other) return true;\n\
+        return count == 3;\n\
+    }\n\
+}\n";
+            let ast = create_test_ast();
+            let context = RuleContext::new("D.java".to_string(), Language::Java, java_code.to_string());
+            let result = engine.execute_rule(&rule, &ast, &context);
+            assert!(result.is_success());
+            let lines: Vec<usize> = result.findings.iter().map(|f| f.location.start_line).collect();
+            assert_eq!(lines, vec![3]);
+
+            // Python hints
+            let rule = Rule::new(
+                "py-str-format".to_string(),
+                "String format".to_string(),
+                "Query built with format".to_string(),
+                Severity::Warning,
+                Confidence::High,
+                vec![Language::Python],
+            ).add_pattern(Pattern::simple("cursor.execute(($Q : str))".to_string()).expand_typed_metavariables());
+            let python_code = "def f(q: str, n: int):\n    cursor.execute(q)\n    cursor.execute(n)\n";
+            let context = RuleContext::new("d.py".to_string(), Language::Python, python_code.to_string());
+            let result = engine.execute_rule(&rule, &ast, &context);
+            let lines: Vec<usize> = result.findings.iter().map(|f| f.location.start_line).collect();
+            assert_eq!(lines, vec![2]);
+        }
+
+        #[test]
+        fn test_deep_expression_matches_nested_arguments() {
+            let mut engine = RuleExecutionEngine::new();
+            let rule = Rule::new(
+                "java-tainted-exec".to_string(),
+                "Tainted exec".to_string(),
+                "User input reaches exec".to_string(),
+                Severity::Error,
+                Confidence::Medium,
+                vec![Language::Java],
+            ).add_pattern(Pattern::simple("rt.exec(<... userInput ...>)".to_string()));
+
+            let java_code = "class D {\n\
+    void f(Runtime rt, String userInput) {\n\
+        rt.exec(\"sh -c \" + build(userInput.trim()));\n\
+        rt.exec(\"ls\");\n\
+        rt.exec(userInput);\n\
+        rt.exec(cmd); log(userInput);\n\
+    }\n\
+}\n";
+            let ast = create_test_ast();
+            let context = RuleContext::new("D.java".to_string(), Language::Java, java_code.to_string());
+            let result = engine.execute_rule(&rule, &ast, &context);
+            assert!(result.is_success());
+            let lines: Vec<usize> = result.findings.iter().map(|f| f.location.start_line).collect();
+            assert_eq!(lines, vec![3, 5]);
+
+            // Focusing on a metavariable of the inner pattern reports the inner expression
+            let rule = Rule::new(
+                "java-trimmed-exec".to_string(),
+                "Trimmed exec".to_string(),
+                "Trimmed value reaches exec".to_string(),
+                Severity::Error,
+                Confidence::Medium,
+                vec![Language::Java],
+            ).add_pattern(Pattern::simple("rt.exec(<... $X.trim() ...>)".to_string()).with_focus("$X".to_string()));
+            let result = engine.execute_rule(&rule, &ast, &context);
+            assert_eq!(result.findings.len(), 1);
+            let location = &result.findings[0].location;
+            assert_eq!((location.start_line, location.start_column, location.end_column), (3, 26, 35));
+        }
+
+        #[test]
+        fn test_constant_propagation_matches_folded_literals() {
+            let mut engine = RuleExecutionEngine::new();
+            let mut rule = Rule::new(
+                "java-weak-hash".to_string(),
+                "Weak hash".to_string(),
+                "MD5 is a weak hash".to_string(),
+                Severity::Warning,
+                Confidence::High,
+                vec![Language::Java],
+            ).add_pattern(Pattern::simple("MessageDigest.getInstance(\"MD5\")".to_string()))
+            .add_pattern(Pattern::simple("$S.executeQuery(\"SELECT * FROM users\")".to_string()));
+
+            let java_code = "class H {\n\
+    static final String ALGO = \"MD5\";\n\
+    static final String TABLE = \"users\";\n\
+    void f(Statement s, String other) {\n\
+        MessageDigest.getInstance(ALGO);\n\
+        MessageDigest.getInstance(\"MD5\");\n\
+        MessageDigest.getInstance(other);\n\
+        s.executeQuery(\"SELECT * FROM \" + TABLE);\n\
+    }\n\
+}\n";
+            let ast = create_test_ast();
+            let context = RuleContext::new("H.java".to_string(), Language::Java, java_code.to_string());
+            let lines = |engine: &mut RuleExecutionEngine, rule: &Rule| -> Vec<usize> {
+                engine.execute_rule(rule, &ast, &context).findings.iter().map(|f| f.location.start_line).collect()
+            };
+            // Off by default: only the literal call matches
+            assert_eq!(lines(&mut engine, &rule), vec![6]);
+
+            rule = rule.add_metadata("constant_propagation".to_string(), "true".to_string());
+            assert_eq!(lines(&mut engine, &rule), vec![5, 6, 8]);
+            // Collected once for the file, not per pattern
+            assert!(std::ptr::eq(context.constants(), context.constants()));
+        }
 
+        #[test]
+        fn test_equivalent_operator_spellings() {
+            let mut engine = RuleExecutionEngine::new();
+            let mut rule = Rule::new(
+                "java-null-or-sum".to_string(),
+                "Null check".to_string(),
+                "Comparison with null".to_string(),
+                Severity::Info,
+                Confidence::High,
+                vec![Language::Java],
+            ).add_pattern(Pattern::simple("$X == null".to_string()))
+            .add_pattern(Pattern::simple("total(a + b + c)".to_string()));
+
+            let java_code = "class N {\n\
+    void f(String user) {\n\
+        if (user == null) return;\n\
+        if (null == user) return;\n\
+        total(a + (b + c));\n\
+        total((a + b) + c);\n\
+        total(a + b - c);\n\
+    }\n\
+}\n";
+            let ast = create_test_ast();
+            let context = RuleContext::new("N.java".to_string(), Language::Java, java_code.to_string());
+            let lines = |engine: &mut RuleExecutionEngine, rule: &Rule| -> Vec<usize> {
+                let mut lines: Vec<usize> = engine.execute_rule(rule, &ast, &context).findings.iter().map(|f| f.location.start_line).collect();
+                lines.sort();
+                lines
+            };
+            assert_eq!(lines(&mut engine, &rule), vec![3, 4, 5, 6]);
+
+            rule = rule
+                .add_metadata("commutative_matching".to_string(), "false".to_string())
+                .add_metadata("associative_matching".to_string(), "false".to_string());
+            assert_eq!(lines(&mut engine, &rule), vec![3]);
+        }
 
+        #[test]
+        fn test_message_and_fix_interpolate_bindings() {
+            let mut engine = RuleExecutionEngine::new();
+            let mut rule = Rule::new(
+                "java-exec".to_string(),
+                "Command execution".to_string(),
+                "$RT.exec runs $CMD".to_string(),
+                Severity::Warning,
+                Confidence::High,
+                vec![Language::Java],
+            ).add_pattern(Pattern::simple("$RT.exec($CMD)".to_string()));
+            rule.fix = Some("$RT.exec(sanitize($CMD))".to_string());
+
+            let java_code = "class C { void f(Runtime runtime, String cmd) { runtime.exec(cmd); } }\n";
+            let ast = create_test_ast();
+            let context = RuleContext::new("C.java".to_string(), Language::Java, java_code.to_string());
+            let result = engine.execute_rule(&rule, &ast, &context);
+            assert_eq!(result.findings.len(), 1);
+            assert_eq!(result.findings[0].message, "runtime.exec runs cmd");
+            assert_eq!(result.findings[0].fix_suggestion.as_deref(), Some("runtime.exec(sanitize(cmd))"));
+        }
+
+        #[test]
+        fn test_patterns_are_compiled_once_per_language() {
+            let engine = RuleExecutionEngine::new();
+            let java = engine.compiled_pattern("execute($Q);", Language::Java);
+            assert!(Arc::ptr_eq(&java, &engine.compiled_pattern("execute($Q);", Language::Java)));
+            assert_eq!(java.last().map(String::as_str), Some(")"));
+
+            // SQL keeps the trailing semicolon as a statement anchor
+            let sql = engine.compiled_pattern("execute($Q);", Language::Sql);
+            assert_eq!(sql.last().map(String::as_str), Some(";"));
+        }
+
+        /// Parses each line into a statement of its words
+        struct LineParser;
+
+        impl LanguageParser for LineParser {
+            fn parse(&self, source: &str, _file_path: &std::path::Path) -> Result<Box<dyn AstNode>> {
+                let statements = source.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()).map(|(i, line)| {
+                    let words = line.split_whitespace().map(|word| {
+                        let col = line.find(word).unwrap() + 1;
+                        UniversalNode::new(NodeType::Identifier)
+                            .with_text(word.to_string())
+                            .with_location(i + 1, col, i + 1, col + word.len())
+                    });
+                    let start = line.len() - line.trim_start().len() + 1;
+                    UniversalNode::new(NodeType::ExpressionStatement)
+                        .with_text(line.trim().to_string())
+                        .with_location(i + 1, start, i + 1, line.trim_end().len() + 1)
+                        .add_children(words.collect())
+                });
+                Ok(Box::new(UniversalNode::new(NodeType::Program).add_children(statements.collect())))
+            }
+
+            fn language(&self) -> Language {
+                Language::Python
+            }
+        }
+
+        #[test]
+        fn test_patterns_match_syntax_tree_of_registered_parser() {
+            let mut engine = RuleExecutionEngine::new();
+            engine.register_parser(Arc::new(LineParser));
+            let mut rule = Rule::new(
+                "python-grant".to_string(),
+                "Grant".to_string(),
+                "grants $WHAT".to_string(),
+                Severity::Info,
+                Confidence::High,
+                vec![Language::Python],
+            ).add_pattern(Pattern::simple("grant $WHAT".to_string()));
+
+            let code = "grant all\ngrant all to admin\n";
+            let ast = LineParser.parse(code, std::path::Path::new("grants.py")).unwrap();
+            let context = RuleContext::new("grants.py".to_string(), Language::Python, code.to_string());
+            // A statement node matches whole, not as a prefix of a longer statement
+            let result = engine.execute_rule(&rule, ast.as_ref(), &context);
+            assert_eq!(result.findings.len(), 1);
+            assert_eq!(result.findings[0].location.start_line, 1);
+            assert_eq!(result.findings[0].message, "grants all");
+
+            rule = rule.add_metadata("ast_matching".to_string(), "false".to_string());
+            assert_eq!(engine.execute_rule(&rule, ast.as_ref(), &context).findings.len(), 2);
+        }
+
+        #[test]
+        fn test_node_matching_prefers_the_syntax_tree() {
+            let mut engine = RuleExecutionEngine::new();
+            engine.register_parser(Arc::new(LineParser));
+            let code = "grant all\ngrant all to admin\n";
+            let ast = LineParser.parse(code, std::path::Path::new("grants.py")).unwrap();
+            let lines = |ast_matching| {
+                let nodes = engine.nodes_matching("grant $WHAT", ast.as_ref(), Language::Python, ast_matching, &CancellationToken::new()).unwrap();
+                nodes.iter().map(|node| node.location().unwrap().0).collect::<Vec<_>>()
+            };
+            assert_eq!(lines(true), [1]);
+            // Tokens on node text also match a prefix of the longer statement
+            assert_eq!(lines(false), [1, 2]);
+        }
+
+        #[test]
+        fn test_statement_patterns_match_consecutive_statements() {
+            let mut engine = RuleExecutionEngine::new();
+            engine.register_parser(Arc::new(LineParser));
+            let rule = Rule::new(
+                "python-grant-revoke".to_string(),
+                "Grant and revoke".to_string(),
+                "$WHO is granted and revoked".to_string(),
+                Severity::Info,
+                Confidence::High,
+                vec![Language::Python],
+            ).add_pattern(Pattern::simple("grant $WHO\n...\nrevoke $WHO".to_string()));
+
+            let code = "grant bob\nlog bob\nrevoke bob\ngrant eve\nrevoke bob\n";
+            let ast = LineParser.parse(code, std::path::Path::new("grants.py")).unwrap();
+            let context = RuleContext::new("grants.py".to_string(), Language::Python, code.to_string());
+            let result = engine.execute_rule(&rule, ast.as_ref(), &context);
+            assert_eq!(result.findings.len(), 1);
+            assert_eq!((result.findings[0].location.start_line, result.findings[0].location.end_line), (1, 3));
+            assert_eq!(result.findings[0].message, "bob is granted and revoked");
+        }
+
+        #[test]
+        fn test_patterns_match_through_import_aliases() {
+            let mut engine = RuleExecutionEngine::new();
+            let mut rule = Rule::new(
+                "python-shell-call".to_string(),
+                "Shell call".to_string(),
+                "subprocess call with a shell".to_string(),
+                Severity::Warning,
+                Confidence::High,
+                vec![Language::Python],
+            ).add_pattern(Pattern::simple("subprocess.call($CMD, shell=True)".to_string()));
+
+            let code = "import subprocess as sp\nfrom subprocess import call\nsp.call(cmd, shell=True)\ncall(cmd, shell=True)\nos.call(cmd, shell=True)\n";
+            let context = RuleContext::new("run.py".to_string(), Language::Python, code.to_string());
+            let result = engine.execute_rule(&rule, &create_test_ast(), &context);
+            assert_eq!(result.findings.iter().map(|f| f.location.start_line).collect::<Vec<_>>(), [3, 4]);
+            // Collected once for the file, not per pattern
+            assert!(std::ptr::eq(context.import_aliases(), context.import_aliases()));
+            assert_eq!(context.import_aliases().resolve("sp"), Some("subprocess"));
+
+            rule = rule.add_metadata("import_aliasing".to_string(), "false".to_string());
+            assert!(engine.execute_rule(&rule, &create_test_ast(), &context).findings.is_empty());
+        }
+
+        #[test]
+        fn test_symbolic_propagation_substitutes_single_assignments() {
+            let mut engine = RuleExecutionEngine::new();
+            let mut rule = Rule::new(
+                "python-eval-input".to_string(),
+                "Eval of input".to_string(),
+                "eval of user input".to_string(),
+                Severity::Error,
+                Confidence::High,
+                vec![Language::Python],
+            ).add_pattern(Pattern::simple("eval(input())".to_string()));
+            rule = rule.add_metadata("symbolic_propagation".to_string(), "true".to_string());
+
+            let code = "data = input()\neval(data)\nother = input()\nother = \"1\"\neval(other)\neval(input())\n";
+            let context = RuleContext::new("run.py".to_string(), Language::Python, code.to_string());
+            let result = engine.execute_rule(&rule, &create_test_ast(), &context);
+            assert_eq!(result.findings.iter().map(|f| f.location.start_line).collect::<Vec<_>>(), [2, 6]);
+            // The finding covers the original code
+            assert_eq!((result.findings[0].location.start_column, result.findings[0].location.end_column), (1, 11));
+            // Propagated once for the file, not per pattern
+            assert!(std::ptr::eq(context.propagated_source().unwrap(), context.propagated_source().unwrap()));
+
+            rule.metadata.remove("symbolic_propagation");
+            assert_eq!(engine.execute_rule(&rule, &create_test_ast(), &context).findings.len(), 1);
+        }
+
+        #[test]
+        fn test_line_col_to_byte_index() {
+            let source = "ab\nçd\n";
+            for byte in [0, 1, 3, 5, 6, 7] {
+                let (line, col) = RuleExecutionEngine::byte_index_to_line_col(source, byte);
+                assert_eq!(RuleExecutionEngine::line_col_to_byte_index(source, line, col), byte);
+            }
+        }
+
+        #[test]
+        fn test_anonymous_metavariable_does_not_unify() {
+            let mut engine = RuleExecutionEngine::new();
+            let named = Rule::new(
+                "java-self-compare".to_string(),
+                "Self comparison".to_string(),
+                "Comparing a value with itself".to_string(),
+                Severity::Info,
+                Confidence::High,
+                vec![Language::Java],
+            ).add_pattern(Pattern::simple("$X.equals($X)".to_string()));
+            let mut anonymous = named.clone();
+            anonymous.patterns = vec![Pattern::simple("$_.equals($_)".to_string())];
+
+            let java_code = "class E {\n\
+    boolean f(String a, String b) {\n\
+        return a.equals(a) || a.equals(b);\n\
+    }\n\
+}\n";
+            let ast = create_test_ast();
+            let context = RuleContext::new("E.java".to_string(), Language::Java, java_code.to_string());
+            let named_result = engine.execute_rule(&named, &ast, &context);
+            assert_eq!(named_result.findings.len(), 1);
+
+            let anonymous_result = engine.execute_rule(&anonymous, &ast, &context);
+            assert_eq!(anonymous_result.findings.len(), 2);
+        }
+
+        #[test]
+        fn test_metavariable_numeric_reads_language_literals() {
+            let mut engine = RuleExecutionEngine::new();
+            let rule = Rule::new(
+                "java-weak-rsa-key".to_string(),
+                "Weak RSA key".to_string(),
+                "RSA keys shorter than 2048 bits".to_string(),
+                Severity::Warning,
+                Confidence::High,
+                vec![Language::Java],
+            ).add_pattern(
+                Pattern::simple("$GEN.initialize($BITS)".to_string()).add_condition(Condition::MetavariableNumeric(
+                    MetavariableNumeric::new("$BITS".to_string(), astgrep_core::NumericConstraint::parse("< 2048", None).unwrap()),
+                )),
+            );
+
+            let java_code = "class K {\n\
+    void f(KeyPairGenerator gen) {\n\
+        gen.initialize(1024);\n\
+        gen.initialize(0x800);\n\
+        gen.initialize(1_536);\n\
+        gen.initialize(size);\n\
+    }\n\
+}\n";
+            let ast = create_test_ast();
+            let context = RuleContext::new("K.java".to_string(), Language::Java, java_code.to_string());
+            let result = engine.execute_rule(&rule, &ast, &context);
+            assert!(result.is_success());
+            let lines: Vec<usize> = result.findings.iter().map(|f| f.location.start_line).collect();
+            assert_eq!(lines, vec![3, 5]);
+        }
+}
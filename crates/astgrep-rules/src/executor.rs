@@ -4,9 +4,9 @@
 //! matching engine and data flow analyzer for comprehensive static analysis.
 
 use crate::types::*;
-use astgrep_core::{AstNode, Finding, Language, Location, Result, Severity, MetavariableAnalysis, ComparisonOperator, SemgrepPattern, SemgrepMatchResult};
+use astgrep_core::{AstNode, Finding, Language, Location, Result, Severity, MetavariableAnalysis, ComparisonOperator, SemgrepMatchResult};
 use astgrep_matcher::{PatternMatcher, AdvancedSemgrepMatcher};
-use astgrep_dataflow::{DataFlowAnalyzer, DataFlowAnalysis};
+use astgrep_dataflow::{DataFlowAnalyzer, DataFlowAnalysis, ModelSet};
 use std::collections::HashMap;
 use std::path::Path;
 
@@ -27,6 +27,12 @@ impl AdvancedRuleExecutor {
         }
     }
 
+    /// Use library function models in data flow analysis, replacing any set before
+    pub fn with_models(mut self, models: &ModelSet) -> Self {
+        self.dataflow_analyzer = DataFlowAnalyzer::new().with_models(models);
+        self
+    }
+
     /// Execute rules with full analysis
     pub fn execute_comprehensive_analysis(
         &mut self,
@@ -60,7 +66,7 @@ impl AdvancedRuleExecutor {
         for rule in applicable_rules {
             let rule_start = std::time::Instant::now();
             
-            match self.execute_single_rule(rule, ast, dataflow_analysis.as_ref(), file_path) {
+            match self.execute_single_rule(rule, ast, language, dataflow_analysis.as_ref(), file_path) {
                 Ok(findings) => {
                     let execution_time = rule_start.elapsed();
                     self.execution_stats.record_rule_execution(&rule.id, execution_time, findings.len());
@@ -103,6 +109,7 @@ impl AdvancedRuleExecutor {
         &mut self,
         rule: &Rule,
         ast: &dyn AstNode,
+        language: Language,
         dataflow_analysis: Option<&DataFlowAnalysis>,
         file_path: Option<&Path>,
     ) -> Result<Vec<Finding>> {
@@ -110,7 +117,7 @@ impl AdvancedRuleExecutor {
 
         // Execute pattern-based analysis
         for pattern in &rule.patterns {
-            let pattern_findings = self.execute_pattern_analysis(rule, pattern, ast, dataflow_analysis, file_path)?;
+            let pattern_findings = self.execute_pattern_analysis(rule, pattern, ast, language, dataflow_analysis, file_path)?;
             findings.extend(pattern_findings);
         }
 
@@ -127,10 +134,11 @@ impl AdvancedRuleExecutor {
 
     /// Execute pattern-based analysis
     fn execute_pattern_analysis(
-        &mut self,
+        &self,
         rule: &Rule,
         pattern: &Pattern,
         ast: &dyn AstNode,
+        language: Language,
         dataflow_analysis: Option<&DataFlowAnalysis>,
         file_path: Option<&Path>,
     ) -> Result<Vec<Finding>> {
@@ -181,7 +189,7 @@ impl AdvancedRuleExecutor {
 
         for match_result in filtered {
             // Check pattern conditions
-            if self.check_pattern_conditions(pattern, &match_result, dataflow_analysis)? {
+            if self.check_pattern_conditions(pattern, &match_result, language, dataflow_analysis)? {
                 let finding = self.create_finding_from_match(rule, pattern, &match_result, file_path)?;
                 findings.push(finding);
             }
@@ -219,10 +227,11 @@ impl AdvancedRuleExecutor {
         &self,
         pattern: &Pattern,
         match_result: &SemgrepMatchResult,
+        language: Language,
         dataflow_analysis: Option<&DataFlowAnalysis>,
     ) -> Result<bool> {
         for condition in &pattern.conditions {
-            if !self.evaluate_condition(condition, match_result, dataflow_analysis)? {
+            if !self.evaluate_condition(condition, match_result, language, dataflow_analysis)? {
                 return Ok(false);
             }
         }
@@ -234,6 +243,7 @@ impl AdvancedRuleExecutor {
         &self,
         condition: &Condition,
         match_result: &SemgrepMatchResult,
+        language: Language,
         _dataflow_analysis: Option<&DataFlowAnalysis>,
     ) -> Result<bool> {
         match condition {
@@ -282,6 +292,23 @@ impl AdvancedRuleExecutor {
                     Ok(false)
                 }
             }
+            Condition::MetavariableType(metavar_type) => {
+                // Without a source-level symbol table only constructors and static
+                // receivers can be typed here
+                if let Some(metavar_value) = match_result.bindings.get(&metavar_type.metavariable) {
+                    let inferred = astgrep_dataflow::SymbolTable::new().infer_expression_type(metavar_value);
+                    Ok(metavar_type.types.iter().any(|t| inferred.matches_type_name(t)))
+                } else {
+                    Ok(false)
+                }
+            }
+            Condition::MetavariableNumeric(metavar_numeric) => {
+                // Literals are read with the numeric syntax of the analyzed language
+                match match_result.bindings.get(&metavar_numeric.metavariable) {
+                    Some(metavar_value) => Ok(metavar_numeric.holds(metavar_value, language)),
+                    None => Ok(false),
+                }
+            }
             Condition::Custom(custom_condition) => {
                 // Custom condition evaluation
                 self.evaluate_custom_condition(custom_condition, match_result)
@@ -638,50 +665,7 @@ impl AdvancedRuleExecutor {
 
     /// Convert astgrep_rules::Pattern to astgrep_core::SemgrepPattern
     fn convert_pattern_to_semgrep_pattern(&self, pattern: &Pattern) -> Result<astgrep_core::SemgrepPattern> {
-        use astgrep_core::{SemgrepPattern, PatternType as CorePatternType};
-
-        let core_pattern_type = match &pattern.pattern_type {
-            crate::PatternType::Simple(pattern_str) => CorePatternType::Simple(pattern_str.clone()),
-            crate::PatternType::Either(patterns) => {
-                let converted: Result<Vec<_>> = patterns.iter()
-                    .map(|p| self.convert_pattern_to_semgrep_pattern(p))
-                    .collect();
-                CorePatternType::Either(converted?)
-            }
-            crate::PatternType::Inside(inner_pattern) => {
-                let converted = self.convert_pattern_to_semgrep_pattern(inner_pattern)?;
-                CorePatternType::Inside(Box::new(converted))
-            }
-            crate::PatternType::NotInside(inner_pattern) => {
-                let converted = self.convert_pattern_to_semgrep_pattern(inner_pattern)?;
-                CorePatternType::NotInside(Box::new(converted))
-            }
-            crate::PatternType::Not(inner_pattern) => {
-                let converted = self.convert_pattern_to_semgrep_pattern(inner_pattern)?;
-                CorePatternType::Not(Box::new(converted))
-            }
-            crate::PatternType::Regex(regex) => CorePatternType::Regex(regex.clone()),
-            crate::PatternType::NotRegex(regex) => CorePatternType::NotRegex(regex.clone()),
-            crate::PatternType::All(patterns) => {
-                let converted: Result<Vec<_>> = patterns.iter()
-                    .map(|p| self.convert_pattern_to_semgrep_pattern(p))
-                    .collect();
-                CorePatternType::All(converted?)
-            }
-            crate::PatternType::Any(patterns) => {
-                let converted: Result<Vec<_>> = patterns.iter()
-                    .map(|p| self.convert_pattern_to_semgrep_pattern(p))
-                    .collect();
-                CorePatternType::Any(converted?)
-            }
-        };
-
-        Ok(SemgrepPattern {
-            pattern_type: core_pattern_type,
-            metavariable_pattern: None, // TODO: Convert metavariable patterns
-            conditions: Vec::new(), // TODO: Convert conditions
-            focus: pattern.focus.clone(),
-        })
+        Ok(pattern.to_semgrep_pattern())
     }
 }
 
@@ -34,6 +34,7 @@ impl RuleExecutionExample {
             fix: Some("Use prepared statements instead of string concatenation".to_string()),
             fix_regex: None,
             paths: None,
+            examples: Vec::new(),
             metadata: {
                 let mut metadata = std::collections::HashMap::new();
                 metadata.insert("category".to_string(), "security".to_string());
@@ -68,6 +69,7 @@ impl RuleExecutionExample {
             fix: Some("Encode HTML output to prevent XSS".to_string()),
             fix_regex: None,
             paths: None,
+            examples: Vec::new(),
             metadata: {
                 let mut metadata = std::collections::HashMap::new();
                 metadata.insert("category".to_string(), "security".to_string());
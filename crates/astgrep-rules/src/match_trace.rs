@@ -0,0 +1,203 @@
+//! Structured tracing of match decisions
+//!
+//! While a rule runs, every candidate match the engine considers is reported as a TRACE
+//! event with target [`MATCH_TRACE_TARGET`], carrying the rule, the pattern, the file, the
+//! matched node or span with its location, the metavariable bindings and the decision
+//! taken. Subscribers select the events by target, e.g. `RUST_LOG=astgrep::match=trace`,
+//! or collect them to a file. Nothing is formatted unless a subscriber is interested.
+
+use astgrep_core::AstNode;
+use std::collections::{BTreeMap, HashMap};
+
+/// Target of match tracing events
+pub const MATCH_TRACE_TARGET: &str = "astgrep::match";
+
+/// Characters of matched text kept in an event
+const TEXT_PREVIEW_CHARS: usize = 80;
+
+/// What the engine did with a candidate match
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchDecision {
+    /// Matched and reported as a finding
+    Reported,
+    /// Matched the pattern but failed a `metavariable-type` constraint
+    RejectedByType,
+    /// Matched the pattern but failed a `metavariable-numeric` constraint
+    RejectedByNumeric,
+    /// Matched the pattern but an expression in place of a literal does not fold to it
+    RejectedByConstant,
+    /// Matched the pattern at a span already reported
+    Duplicate,
+    /// Matched, but left out in favour of a smaller overlapping node
+    Overlapped,
+}
+
+impl MatchDecision {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MatchDecision::Reported => "reported",
+            MatchDecision::RejectedByType => "rejected-by-type",
+            MatchDecision::RejectedByNumeric => "rejected-by-numeric",
+            MatchDecision::RejectedByConstant => "rejected-by-constant",
+            MatchDecision::Duplicate => "duplicate",
+            MatchDecision::Overlapped => "overlapped",
+        }
+    }
+}
+
+/// Whether any subscriber wants match tracing events
+pub fn match_tracing_enabled() -> bool {
+    tracing::enabled!(target: MATCH_TRACE_TARGET, tracing::Level::TRACE)
+}
+
+/// Trace a source span `start..end` matched by `pattern`, with byte-range `bindings`
+pub(crate) fn trace_span(
+    rule_id: &str,
+    pattern: &str,
+    file: &str,
+    source: &str,
+    (start, end): (usize, usize),
+    bindings: &HashMap<String, (usize, usize)>,
+    decision: MatchDecision,
+) {
+    if !match_tracing_enabled() {
+        return;
+    }
+    let text = source.get(start..end).unwrap_or_default();
+    let bindings: BTreeMap<&str, String> = bindings
+        .iter()
+        .map(|(name, &(from, to))| (name.as_str(), preview(source.get(from..to).unwrap_or_default())))
+        .collect();
+    let (start_line, start_column) = line_col(source, start);
+    let (end_line, end_column) = line_col(source, end);
+    tracing::trace!(
+        target: MATCH_TRACE_TARGET,
+        rule = rule_id,
+        pattern,
+        file,
+        node = %preview(text),
+        location = %format!("{}:{}-{}:{}", start_line, start_column, end_line, end_column),
+        decision = decision.as_str(),
+        bindings = ?bindings,
+        "match decision"
+    );
+}
+
+/// Trace a syntax tree `node` matched by `pattern`, with textual `bindings`
+pub(crate) fn trace_node(
+    rule_id: &str,
+    pattern: &str,
+    file: &str,
+    node: &dyn AstNode,
+    bindings: &HashMap<String, String>,
+    decision: MatchDecision,
+) {
+    if !match_tracing_enabled() {
+        return;
+    }
+    let bindings: BTreeMap<&str, String> = bindings.iter().map(|(name, value)| (name.as_str(), preview(value))).collect();
+    let location = node
+        .location()
+        .map(|(start_line, start_column, end_line, end_column)| format!("{}:{}-{}:{}", start_line, start_column, end_line, end_column))
+        .unwrap_or_default();
+    tracing::trace!(
+        target: MATCH_TRACE_TARGET,
+        rule = rule_id,
+        pattern,
+        file,
+        node = %format!("{} {:?}", node.node_type(), preview(node.text().unwrap_or_default())),
+        location = %location,
+        decision = decision.as_str(),
+        bindings = ?bindings,
+        "match decision"
+    );
+}
+
+/// `text` on one line, cut to `TEXT_PREVIEW_CHARS`
+fn preview(text: &str) -> String {
+    let line = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if line.chars().count() > TEXT_PREVIEW_CHARS {
+        format!("{}...", line.chars().take(TEXT_PREVIEW_CHARS).collect::<String>())
+    } else {
+        line
+    }
+}
+
+/// 1-based line and column of byte `index` in `source`
+fn line_col(source: &str, index: usize) -> (usize, usize) {
+    let before = &source[..index.min(source.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rfind('\n').map_or(before.len(), |newline| before.len() - newline - 1) + 1;
+    (line, column)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    /// Collects the fields of match tracing events
+    #[derive(Default, Clone)]
+    struct Collector(Arc<Mutex<Vec<BTreeMap<String, String>>>>);
+
+    struct Fields(BTreeMap<String, String>);
+
+    impl Visit for Fields {
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            self.0.insert(field.name().to_string(), format!("{:?}", value));
+        }
+
+        fn record_str(&mut self, field: &Field, value: &str) {
+            self.0.insert(field.name().to_string(), value.to_string());
+        }
+    }
+
+    impl Subscriber for Collector {
+        fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+            metadata.target() == MATCH_TRACE_TARGET
+        }
+        fn new_span(&self, _: &Attributes<'_>) -> Id {
+            Id::from_u64(1)
+        }
+        fn record(&self, _: &Id, _: &Record<'_>) {}
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+        fn event(&self, event: &Event<'_>) {
+            let mut fields = Fields(BTreeMap::new());
+            event.record(&mut fields);
+            self.0.lock().unwrap().push(fields.0);
+        }
+        fn enter(&self, _: &Id) {}
+        fn exit(&self, _: &Id) {}
+    }
+
+    #[test]
+    fn test_span_events_carry_bindings_and_decision() {
+        let source = "let a = 1;\nrun(eval(input));\n";
+        let start = source.find("eval").unwrap();
+        let end = start + "eval(input)".len();
+        let bindings = HashMap::from([("$X".to_string(), (start + 5, start + 10))]);
+
+        let collector = Collector::default();
+        tracing::subscriber::with_default(collector.clone(), || {
+            assert!(match_tracing_enabled());
+            trace_span("js-eval", "eval($X)", "app.js", source, (start, end), &bindings, MatchDecision::RejectedByType);
+        });
+        let events = collector.0.lock().unwrap();
+        assert_eq!(events.len(), 1);
+        let event = &events[0];
+        assert_eq!(event["rule"], "js-eval");
+        assert_eq!(event["pattern"], "eval($X)");
+        assert_eq!(event["node"], "eval(input)");
+        assert_eq!(event["location"], "2:5-2:16");
+        assert_eq!(event["decision"], "rejected-by-type");
+        assert_eq!(event["bindings"], "{\"$X\": \"input\"}");
+    }
+
+    #[test]
+    fn test_disabled_without_subscriber() {
+        assert!(!match_tracing_enabled());
+    }
+}
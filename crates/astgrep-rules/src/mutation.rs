@@ -0,0 +1,422 @@
+//! Benign source mutations for probing rule brittleness
+//!
+//! Each mutation rewrites a fixture without changing what the code does (renamed
+//! locals, reordered keyword arguments and literal keys, extra whitespace and comments,
+//! aliased imports).
+//! A rule that stops matching after a mutation is likely to miss real-world variants.
+
+use astgrep_core::Language;
+use regex::Regex;
+use std::ops::Range;
+
+/// Suffix appended to renamed identifiers
+const RENAME_SUFFIX: &str = "_mut";
+
+/// Words never treated as renameable identifiers
+const KEYWORDS: &[&str] = &[
+    "if", "else", "for", "while", "do", "switch", "case", "return", "new", "this", "self", "super",
+    "class", "def", "function", "import", "from", "as", "in", "is", "not", "and", "or", "true",
+    "false", "True", "False", "None", "null", "nil", "undefined", "let", "var", "const", "final",
+    "static", "public", "private", "protected", "local", "export", "catch", "try", "with",
+];
+
+/// A semantics-preserving source transformation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mutation {
+    /// Rename locally assigned variables
+    RenameVariables,
+    /// Reverse the keyword arguments of calls and the keys of object and dict literals,
+    /// whose order does not matter; positional arguments are left alone
+    ReorderArguments,
+    /// Add whitespace inside parentheses and around commas
+    AddWhitespace,
+    /// Interleave line comments between source lines
+    AddComments,
+    /// Import modules and names under an alias and use the alias
+    AliasImports,
+}
+
+impl Mutation {
+    /// All mutations, in the order they are reported
+    pub const ALL: [Mutation; 5] = [
+        Mutation::RenameVariables,
+        Mutation::ReorderArguments,
+        Mutation::AddWhitespace,
+        Mutation::AddComments,
+        Mutation::AliasImports,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Mutation::RenameVariables => "rename-variables",
+            Mutation::ReorderArguments => "reorder-arguments",
+            Mutation::AddWhitespace => "add-whitespace",
+            Mutation::AddComments => "add-comments",
+            Mutation::AliasImports => "alias-imports",
+        }
+    }
+
+    /// Apply the mutation; None when it does not apply to this source
+    pub fn apply(&self, source: &str, language: Language) -> Option<String> {
+        let mutated = match self {
+            Mutation::RenameVariables => rename_variables(source, language),
+            Mutation::ReorderArguments => reorder_arguments(source, language),
+            Mutation::AddWhitespace => map_code(source, |code| {
+                code.replace('(', "( ").replace(')', " )").replace(',', " , ")
+            }),
+            Mutation::AddComments => add_comments(source, language)?,
+            Mutation::AliasImports => alias_imports(source, language)?,
+        };
+        (mutated != source).then_some(mutated)
+    }
+}
+
+/// Apply `f` to the parts of `source` outside string literals
+fn map_code(source: &str, f: impl Fn(&str) -> String) -> String {
+    let mut out = String::with_capacity(source.len());
+    let mut code = String::new();
+    let mut chars = source.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if matches!(c, '"' | '\'' | '`') {
+            out.push_str(&f(&code));
+            code.clear();
+            out.push(c);
+            while let Some(s) = chars.next() {
+                out.push(s);
+                if s == '\\' {
+                    if let Some(escaped) = chars.next() {
+                        out.push(escaped);
+                    }
+                } else if s == c || (s == '\n' && c != '`') {
+                    break;
+                }
+            }
+        } else {
+            code.push(c);
+        }
+    }
+    out.push_str(&f(&code));
+    out
+}
+
+/// `source` with the characters inside string literals replaced by `_`, byte for byte, so
+/// that offsets stay valid and punctuation inside strings is not taken for code
+fn mask_strings(source: &str) -> String {
+    let mut out = String::with_capacity(source.len());
+    let mut chars = source.chars();
+    let blank = |out: &mut String, c: char| out.extend(std::iter::repeat_n('_', c.len_utf8()));
+
+    while let Some(c) = chars.next() {
+        out.push(c);
+        if !matches!(c, '"' | '\'' | '`') {
+            continue;
+        }
+        while let Some(s) = chars.next() {
+            if s == '\\' {
+                blank(&mut out, s);
+                if let Some(escaped) = chars.next() {
+                    blank(&mut out, escaped);
+                }
+            } else if s == c || (s == '\n' && c != '`') {
+                out.push(s);
+                break;
+            } else {
+                blank(&mut out, s);
+            }
+        }
+    }
+    out
+}
+
+/// Separator between a keyword argument's name and value, for languages with keyword arguments
+fn keyword_separator(language: Language) -> Option<&'static str> {
+    match language {
+        Language::Python | Language::Kotlin => Some("="),
+        Language::Ruby | Language::CSharp | Language::Swift => Some(":"),
+        _ => None,
+    }
+}
+
+/// Whether the identifier at `range` of `code` names a keyword argument (`f(x=1)`) or an
+/// object key (`{x: 1}` in JavaScript) rather than referring to a variable
+fn is_argument_name(code: &str, range: Range<usize>, language: Language) -> bool {
+    let before = code[..range.start].trim_end();
+    let after = code[range.end..].trim_start();
+    match (keyword_separator(language), language) {
+        (Some("="), _) => (before.ends_with('(') || before.ends_with(',')) && after.starts_with('=') && !after.starts_with("=="),
+        (Some(_), _) => (before.ends_with('(') || before.ends_with(',')) && after.starts_with(':') && !after.starts_with("::"),
+        (None, Language::JavaScript) => (before.ends_with('{') || before.ends_with(',')) && after.starts_with(':'),
+        (None, _) => false,
+    }
+}
+
+/// Replace whole-word `from` with `to` outside strings, leaving member accesses (`x.from`),
+/// keyword argument names and object keys alone
+fn rename_identifier(source: &str, from: &str, to: &str, language: Language) -> String {
+    let word = match Regex::new(&format!(r"\b{}\b", regex::escape(from))) {
+        Ok(word) => word,
+        Err(_) => return source.to_string(),
+    };
+    map_code(source, |code| {
+        let mut out = String::with_capacity(code.len());
+        let mut last = 0;
+        for m in word.find_iter(code) {
+            out.push_str(&code[last..m.start()]);
+            let member = code[..m.start()].trim_end().ends_with('.');
+            let keep = member || is_argument_name(code, m.range(), language);
+            out.push_str(if keep { m.as_str() } else { to });
+            last = m.end();
+        }
+        out.push_str(&code[last..]);
+        out
+    })
+}
+
+fn rename_variables(source: &str, language: Language) -> String {
+    let assignment = Regex::new(
+        r"(?m)^[ \t]*(?:(?:let|var|const|final|local|my|[A-Za-z_][\w<>\[\]]*)\s+)?\$?([A-Za-z_]\w*)\s*=[^=]",
+    )
+    .expect("valid assignment regex");
+
+    let mut names: Vec<String> = Vec::new();
+    for caps in assignment.captures_iter(source) {
+        let name = caps[1].to_string();
+        if !KEYWORDS.contains(&name.as_str()) && !names.contains(&name) {
+            names.push(name);
+        }
+    }
+
+    names.iter().fold(source.to_string(), |acc, name| {
+        rename_identifier(&acc, name, &format!("{}{}", name, RENAME_SUFFIX), language)
+    })
+}
+
+fn reorder_arguments(source: &str, language: Language) -> String {
+    let masked = mask_strings(source);
+    let mut edits: Vec<(Range<usize>, String)> = Vec::new();
+
+    // Keyword arguments: `f(a, x=1, y=2)` becomes `f(a, y=2, x=1)`
+    if let Some(separator) = keyword_separator(language) {
+        let keyword = Regex::new(&format!(r"^\s*([A-Za-z_]\w*)\s*{}\s*[^\s=:]", regex::escape(separator))).expect("valid keyword regex");
+        let call = Regex::new(r"\b[A-Za-z_][\w.]*\(([^()]*)\)").expect("valid call regex");
+        for caps in call.captures_iter(&masked) {
+            let args = caps.get(1).expect("arguments group").range();
+            if let Some(edit) = reorder_entries(source, &masked, args, &keyword, true) {
+                edits.push(edit);
+            }
+        }
+    }
+
+    // Keys of object, dict and hash literals: `{a: 1, b: 2}` becomes `{b: 2, a: 1}`
+    if matches!(language, Language::JavaScript | Language::Python | Language::Ruby) {
+        let key = Regex::new(r#"^\s*([A-Za-z_$][\w$]*|"_*"|'_*')\s*(?::|=>)\s*[^\s:]"#).expect("valid key regex");
+        let literal = Regex::new(r"\{([^{}()\[\]]*)\}").expect("valid literal regex");
+        for caps in literal.captures_iter(&masked) {
+            let entries = caps.get(1).expect("entries group").range();
+            if let Some(edit) = reorder_entries(source, &masked, entries, &key, false) {
+                edits.push(edit);
+            }
+        }
+    }
+
+    // Calls and literals without nested brackets never overlap
+    edits.sort_by_key(|(range, _)| std::cmp::Reverse(range.start));
+    let mut out = source.to_string();
+    for (range, replacement) in edits {
+        out.replace_range(range, &replacement);
+    }
+    out
+}
+
+/// The comma-separated entries of `source[range]` with the trailing run of entries that
+/// `entry` matches reversed, or None when fewer than two match. With `positional_prefix`
+/// other entries may precede that run, otherwise every entry must match. The key captured
+/// by `entry` must be unique, since a repeated key makes the order significant.
+fn reorder_entries(source: &str, masked: &str, range: Range<usize>, entry: &Regex, positional_prefix: bool) -> Option<(Range<usize>, String)> {
+    let inner = &masked[range.clone()];
+    if inner.contains(';') || inner.contains('\n') {
+        return None;
+    }
+    let mut parts: Vec<Range<usize>> = Vec::new();
+    let mut start = range.start;
+    for (i, _) in inner.match_indices(',') {
+        parts.push(start..range.start + i);
+        start = range.start + i + 1;
+    }
+    parts.push(start..range.end);
+
+    let first = parts.iter().position(|part| entry.is_match(&masked[part.clone()]))?;
+    if first > 0 && !positional_prefix {
+        return None;
+    }
+    let (prefix, reordered) = parts.split_at(first);
+    if reordered.len() < 2 {
+        return None;
+    }
+    let mut keys: Vec<&str> = Vec::new();
+    for part in reordered {
+        let key = entry.captures(&masked[part.clone()])?.get(1)?.range();
+        let key = &source[part.start + key.start..part.start + key.end];
+        if keys.contains(&key) {
+            return None;
+        }
+        keys.push(key);
+    }
+
+    let text = |part: &Range<usize>| source[part.clone()].trim().to_string();
+    let entries: Vec<String> = prefix.iter().map(text).chain(reordered.iter().rev().map(text)).collect();
+    let leading = &source[range.start..range.start + (inner.len() - inner.trim_start().len())];
+    let trailing = &source[range.start + inner.trim_end().len()..range.end];
+    Some((range, format!("{}{}{}", leading, entries.join(", "), trailing)))
+}
+
+fn line_comment(language: Language) -> Option<&'static str> {
+    match language {
+        Language::Python | Language::Bash | Language::Ruby => Some("#"),
+        Language::Sql => Some("--"),
+        Language::Xml => None,
+        _ => Some("//"),
+    }
+}
+
+fn add_comments(source: &str, language: Language) -> Option<String> {
+    let marker = line_comment(language)?;
+    let mut out = String::with_capacity(source.len() * 2);
+    let mut previous = "";
+    for line in source.split_inclusive('\n') {
+        // Never split a continued line
+        if !line.trim().is_empty() && !previous.trim_end().ends_with('\\') {
+            let indent: String = line.chars().take_while(|c| c.is_whitespace() && *c != '\n').collect();
+            out.push_str(&format!("{}{} mutation probe\n", indent, marker));
+        }
+        out.push_str(line);
+        previous = line;
+    }
+    Some(out)
+}
+
+fn alias_imports(source: &str, language: Language) -> Option<String> {
+    // (line index, rewritten import line) and (original name, alias) pairs
+    let mut rewrites: Vec<(usize, String)> = Vec::new();
+    let mut aliases: Vec<(String, String)> = Vec::new();
+    let alias_of = |name: &str| format!("{}_alias", name);
+
+    match language {
+        Language::Python => {
+            let plain = Regex::new(r"^(\s*)import\s+([A-Za-z_]\w*)\s*$").ok()?;
+            let from = Regex::new(r"^(\s*)from\s+([\w.]+)\s+import\s+([A-Za-z_]\w*(?:\s*,\s*[A-Za-z_]\w*)*)\s*$").ok()?;
+            for (i, line) in source.lines().enumerate() {
+                if let Some(caps) = plain.captures(line) {
+                    rewrites.push((i, format!("{}import {} as {}", &caps[1], &caps[2], alias_of(&caps[2]))));
+                    aliases.push((caps[2].to_string(), alias_of(&caps[2])));
+                } else if let Some(caps) = from.captures(line) {
+                    let names: Vec<&str> = caps[3].split(',').map(str::trim).collect();
+                    let aliased: Vec<String> = names.iter().map(|n| format!("{} as {}", n, alias_of(n))).collect();
+                    rewrites.push((i, format!("{}from {} import {}", &caps[1], &caps[2], aliased.join(", "))));
+                    aliases.extend(names.iter().map(|n| (n.to_string(), alias_of(n))));
+                }
+            }
+        }
+        Language::JavaScript => {
+            let named = Regex::new(r"^(\s*)import\s*\{([^}]*)\}\s*from\s*(.+)$").ok()?;
+            for (i, line) in source.lines().enumerate() {
+                if let Some(caps) = named.captures(line) {
+                    let names: Vec<&str> = caps[2].split(',').map(str::trim).filter(|n| !n.is_empty() && !n.contains(' ')).collect();
+                    if names.is_empty() {
+                        continue;
+                    }
+                    let aliased: Vec<String> = names.iter().map(|n| format!("{} as {}", n, alias_of(n))).collect();
+                    rewrites.push((i, format!("{}import {{ {} }} from {}", &caps[1], aliased.join(", "), &caps[3])));
+                    aliases.extend(names.iter().map(|n| (n.to_string(), alias_of(n))));
+                }
+            }
+        }
+        _ => return None,
+    }
+
+    if rewrites.is_empty() {
+        return None;
+    }
+
+    let renamed = aliases.iter().fold(source.to_string(), |acc, (name, alias)| rename_identifier(&acc, name, alias, language));
+    let mut lines: Vec<String> = renamed.split('\n').map(|l| l.to_string()).collect();
+    for (i, rewritten) in rewrites {
+        if let Some(line) = lines.get_mut(i) {
+            *line = if line.ends_with('\r') { format!("{}\r", rewritten) } else { rewritten };
+        }
+    }
+    Some(lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rename_variables_skips_strings_and_members() {
+        let source = "String query = \"query\" + input;\nstmt.query = query;\n";
+        let mutated = Mutation::RenameVariables.apply(source, Language::Java).unwrap();
+        assert_eq!(mutated, "String query_mut = \"query\" + input;\nstmt.query = query_mut;\n");
+    }
+
+    #[test]
+    fn test_rename_variables_keeps_argument_names() {
+        let source = "timeout = 5\nrequests.get(url, timeout=timeout)\n";
+        let mutated = Mutation::RenameVariables.apply(source, Language::Python).unwrap();
+        assert_eq!(mutated, "timeout_mut = 5\nrequests.get(url, timeout=timeout_mut)\n");
+
+        let source = "const user = load();\nsend({ user: user });\n";
+        let mutated = Mutation::RenameVariables.apply(source, Language::JavaScript).unwrap();
+        assert_eq!(mutated, "const user_mut = load();\nsend({ user: user_mut });\n");
+    }
+
+    #[test]
+    fn test_reorder_arguments() {
+        // Only keyword arguments move; positional ones and non-call parentheses stay
+        let source = "cursor.execute(sql, params, timeout=3, retries=2)\nprint(a, b)\nif (a, b)\n";
+        let mutated = Mutation::ReorderArguments.apply(source, Language::Python).unwrap();
+        assert_eq!(mutated, "cursor.execute(sql, params, retries=2, timeout=3)\nprint(a, b)\nif (a, b)\n");
+
+        // Dict and object keys move, quoted keys and string values included
+        let source = "data = {\"name\": \"a, b\", 'id': 1}\n";
+        let mutated = Mutation::ReorderArguments.apply(source, Language::Python).unwrap();
+        assert_eq!(mutated, "data = {'id': 1, \"name\": \"a, b\"}\n");
+        let source = "res.cookie('sid', token, { httpOnly: false, secure: true });\n";
+        let mutated = Mutation::ReorderArguments.apply(source, Language::JavaScript).unwrap();
+        assert_eq!(mutated, "res.cookie('sid', token, { secure: true, httpOnly: false });\n");
+    }
+
+    #[test]
+    fn test_reorder_arguments_keeps_positional_and_order_dependent_code() {
+        // Positional arguments of languages without keyword arguments
+        assert!(Mutation::ReorderArguments.apply("stmt.setString(1, id);\n", Language::Java).is_none());
+        // Assignments in JavaScript calls are not keyword arguments
+        assert!(Mutation::ReorderArguments.apply("f(a = 1, b = 2);\n", Language::JavaScript).is_none());
+        // Repeated keys, comprehensions and spreads depend on their order
+        assert!(Mutation::ReorderArguments.apply("d = {a: 1, a: 2}\n", Language::Python).is_none());
+        assert!(Mutation::ReorderArguments.apply("d = {k: v for k, v in items}\n", Language::Python).is_none());
+        assert!(Mutation::ReorderArguments.apply("const o = { ...base, id: 1 };\n", Language::JavaScript).is_none());
+        // A positional argument after keyword ones
+        assert!(Mutation::ReorderArguments.apply("f(x=1, y=2, z)\n", Language::Python).is_none());
+    }
+
+    #[test]
+    fn test_add_comments_respects_continuations() {
+        let source = "x = 1 + \\\n    2\n";
+        let mutated = Mutation::AddComments.apply(source, Language::Python).unwrap();
+        assert_eq!(mutated, "# mutation probe\nx = 1 + \\\n    2\n");
+        assert!(Mutation::AddComments.apply("<a/>", Language::Xml).is_none());
+    }
+
+    #[test]
+    fn test_alias_imports_python() {
+        let source = "import os\nfrom subprocess import call\nos.system(cmd)\ncall(cmd)\n";
+        let mutated = Mutation::AliasImports.apply(source, Language::Python).unwrap();
+        assert_eq!(
+            mutated,
+            "import os as os_alias\nfrom subprocess import call as call_alias\nos_alias.system(cmd)\ncall_alias(cmd)\n"
+        );
+        assert!(Mutation::AliasImports.apply(source, Language::Java).is_none());
+    }
+}
@@ -0,0 +1,162 @@
+//! Rule overrides
+//!
+//! Rules given by several sources are layered: a source listed later takes precedence over
+//! the ones before it. Defining a rule id again replaces the earlier rule, patterns and
+//! all; a top-level `overrides:` list, next to or instead of `rules:`, changes single
+//! fields of a rule without copying it:
+//!
+//! ```yaml
+//! overrides:
+//!   - id: python-eval
+//!     severity: INFO
+//!   - id: js-console-log
+//!     enabled: false
+//! ```
+//!
+//! An override may set `enabled`, `severity` and `confidence`.
+
+use crate::Rule;
+use astgrep_core::{AnalysisError, Confidence, Result, Severity};
+use serde_yaml::Value;
+
+/// Keys an override entry may set besides `id`
+const OVERRIDE_KEYS: &[&str] = &["enabled", "severity", "confidence"];
+
+/// Changes a source makes to a rule defined by an earlier source
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleOverride {
+    pub id: String,
+    pub enabled: Option<bool>,
+    pub severity: Option<Severity>,
+    pub confidence: Option<Confidence>,
+    /// File, pack or inline source the override was loaded from
+    pub origin: String,
+}
+
+impl RuleOverride {
+    /// The entries of the `overrides` list of a YAML rule document; none when it has no list
+    pub fn parse_all(document: &Value, origin: &str) -> Result<Vec<Self>> {
+        let Some(entries) = document.get("overrides") else {
+            return Ok(Vec::new());
+        };
+        let entries = entries.as_sequence().ok_or_else(|| AnalysisError::parse_error("'overrides' must be an array"))?;
+        entries.iter().enumerate().map(|(index, entry)| Self::parse(entry, index, origin)).collect()
+    }
+
+    fn parse(entry: &Value, index: usize, origin: &str) -> Result<Self> {
+        if !entry.is_mapping() {
+            return Err(AnalysisError::parse_error(format!("Override {} is not an object", index)));
+        }
+        let id = entry
+            .get("id")
+            .and_then(Value::as_str)
+            .ok_or_else(|| AnalysisError::parse_error(format!("Override {} has no 'id'", index)))?;
+        Self::with_fields(id, entry, origin)
+    }
+
+    /// Override of `id` setting the fields of the YAML mapping `fields`; an `id` key in it
+    /// is ignored
+    pub fn with_fields(id: &str, fields: &Value, origin: &str) -> Result<Self> {
+        let mapping = fields
+            .as_mapping()
+            .ok_or_else(|| AnalysisError::parse_error(format!("Override of '{}' is not an object", id)))?;
+        if let Some(key) = mapping.keys().filter_map(Value::as_str).find(|key| *key != "id" && !OVERRIDE_KEYS.contains(key)) {
+            return Err(AnalysisError::parse_error(format!(
+                "Override of '{}' sets '{}' (expected one of {})",
+                id,
+                key,
+                OVERRIDE_KEYS.join(", ")
+            )));
+        }
+
+        let text = |key: &str| -> Result<Option<String>> {
+            match fields.get(key) {
+                None => Ok(None),
+                Some(value) => value
+                    .as_str()
+                    .map(|s| Some(s.to_uppercase()))
+                    .ok_or_else(|| AnalysisError::parse_error(format!("Override of '{}' has a non-string '{}'", id, key))),
+            }
+        };
+        let severity = match text("severity")?.as_deref() {
+            None => None,
+            Some("INFO") => Some(Severity::Info),
+            Some("WARNING") => Some(Severity::Warning),
+            Some("ERROR") => Some(Severity::Error),
+            Some("CRITICAL") => Some(Severity::Critical),
+            Some(other) => return Err(AnalysisError::parse_error(format!("Override of '{}' has invalid severity: {}", id, other))),
+        };
+        let confidence = match text("confidence")?.as_deref() {
+            None => None,
+            Some("LOW") => Some(Confidence::Low),
+            Some("MEDIUM") => Some(Confidence::Medium),
+            Some("HIGH") => Some(Confidence::High),
+            Some(other) => return Err(AnalysisError::parse_error(format!("Override of '{}' has invalid confidence: {}", id, other))),
+        };
+        let enabled = match fields.get("enabled") {
+            None => None,
+            Some(value) => Some(
+                value
+                    .as_bool()
+                    .ok_or_else(|| AnalysisError::parse_error(format!("Override of '{}' has a non-boolean 'enabled'", id)))?,
+            ),
+        };
+
+        Ok(Self { id: id.to_string(), enabled, severity, confidence, origin: origin.to_string() })
+    }
+
+    /// Apply to `rule`, returning the fields whose value changed
+    pub fn apply(&self, rule: &mut Rule) -> Vec<&'static str> {
+        let mut changed = Vec::new();
+        if let Some(enabled) = self.enabled.filter(|&enabled| enabled != rule.enabled) {
+            rule.enabled = enabled;
+            changed.push("enabled");
+        }
+        if let Some(severity) = self.severity.filter(|&severity| severity != rule.severity) {
+            rule.severity = severity;
+            changed.push("severity");
+        }
+        if let Some(confidence) = self.confidence.filter(|&confidence| confidence != rule.confidence) {
+            rule.confidence = confidence;
+            changed.push("confidence");
+        }
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use astgrep_core::Language;
+
+    #[test]
+    fn test_parse_and_apply() {
+        let document: Value = serde_yaml::from_str(
+            "overrides:\n  - id: py-eval\n    severity: info\n  - id: py-exec\n    enabled: false\n    confidence: HIGH\n",
+        )
+        .unwrap();
+        let overrides = RuleOverride::parse_all(&document, "team.yaml").unwrap();
+        assert_eq!(overrides.len(), 2);
+        assert_eq!(overrides[0].severity, Some(Severity::Info));
+        assert_eq!(overrides[1].enabled, Some(false));
+        assert_eq!(overrides[1].origin, "team.yaml");
+
+        let mut rule = Rule::new("py-exec".into(), "exec".into(), "d".into(), Severity::Error, Confidence::High, vec![Language::Python]);
+        assert_eq!(overrides[1].apply(&mut rule), vec!["enabled"]);
+        assert!(!rule.enabled);
+        assert!(overrides[1].apply(&mut rule).is_empty());
+
+        let none: Value = serde_yaml::from_str("rules: []\n").unwrap();
+        assert!(RuleOverride::parse_all(&none, "base.yaml").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_rejects_unknown_keys_and_values() {
+        let parse = |yaml: &str| RuleOverride::parse_all(&serde_yaml::from_str(yaml).unwrap(), "o.yaml");
+        assert!(parse("overrides:\n  - severity: INFO\n").unwrap_err().to_string().contains("no 'id'"));
+        assert!(parse("overrides:\n  - id: a\n    pattern: eval(...)\n").unwrap_err().to_string().contains("sets 'pattern'"));
+        assert!(parse("overrides:\n  - id: a\n    severity: LOUD\n").unwrap_err().to_string().contains("invalid severity"));
+        assert!(parse("overrides:\n  - id: a\n    enabled: 'no'\n").is_err());
+        assert!(parse("overrides: {}\n").is_err());
+    }
+}
@@ -0,0 +1,339 @@
+//! Rule pack bundle format (`.astgrep-pack`)
+//!
+//! A rule pack is a single zip archive that bundles rule files, test fixtures and
+//! a `manifest.yaml` describing the pack:
+//!
+//! ```text
+//! manifest.yaml        name, version, min_engine_version, categories, description
+//! rules/**.yaml        rule files, loaded in archive order
+//! fixtures/**          test fixtures (any file type)
+//! ```
+//!
+//! Packs are built from a directory with the same layout via [`RulePack::from_directory`]
+//! and loaded with [`RulePack::read_from`].
+
+use crate::RuleEngine;
+use astgrep_core::{AnalysisError, Result};
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use std::path::Path;
+
+/// File extension of rule pack archives
+pub const PACK_EXTENSION: &str = "astgrep-pack";
+
+/// Name of the manifest inside a pack (and inside a pack source directory)
+pub const MANIFEST_FILE: &str = "manifest.yaml";
+
+/// Version of the running rule engine
+pub const ENGINE_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Rule pack manifest
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RulePackManifest {
+    pub name: String,
+    pub version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_engine_version: Option<String>,
+    #[serde(default)]
+    pub categories: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+impl RulePackManifest {
+    /// Create a manifest with the given name and version
+    pub fn new(name: String, version: String) -> Self {
+        Self {
+            name,
+            version,
+            min_engine_version: None,
+            categories: Vec::new(),
+            description: None,
+        }
+    }
+
+    /// Check whether the running engine satisfies the pack's minimum version
+    pub fn is_compatible(&self) -> bool {
+        self.min_engine_version
+            .as_deref()
+            .is_none_or(|min| version_at_least(ENGINE_VERSION, min))
+    }
+}
+
+/// An in-memory rule pack
+#[derive(Debug, Clone)]
+pub struct RulePack {
+    pub manifest: RulePackManifest,
+    /// Rule files as (path inside the pack, YAML content)
+    pub rules: Vec<(String, String)>,
+    /// Fixture files as (path inside the pack, raw bytes)
+    pub fixtures: Vec<(String, Vec<u8>)>,
+}
+
+impl RulePack {
+    /// Check whether a path looks like a rule pack archive
+    pub fn is_pack_path(path: &Path) -> bool {
+        path.extension().is_some_and(|ext| ext == PACK_EXTENSION)
+    }
+
+    /// Build a pack from a source directory containing `manifest.yaml`, `rules/` and
+    /// optionally `fixtures/`. Without a `rules/` subdirectory, every YAML file outside
+    /// `fixtures/` is treated as a rule file.
+    pub fn from_directory(dir: &Path, manifest: Option<RulePackManifest>) -> Result<Self> {
+        let manifest = match manifest {
+            Some(manifest) => manifest,
+            None => {
+                let manifest_path = dir.join(MANIFEST_FILE);
+                let content = std::fs::read_to_string(&manifest_path).map_err(|e| {
+                    AnalysisError::config_error(format!(
+                        "Missing pack manifest {}: {}",
+                        manifest_path.display(),
+                        e
+                    ))
+                })?;
+                parse_manifest(&content)?
+            }
+        };
+
+        let mut pack = Self {
+            manifest,
+            rules: Vec::new(),
+            fixtures: Vec::new(),
+        };
+
+        let rules_root = if dir.join("rules").is_dir() { dir.join("rules") } else { dir.to_path_buf() };
+        let mut rule_files = Vec::new();
+        collect_files(&rules_root, &mut rule_files)?;
+        for file in rule_files {
+            let relative = file.strip_prefix(dir).unwrap_or(&file);
+            if relative.starts_with("fixtures") || !is_yaml(&file) || file.file_name().is_some_and(|n| n == MANIFEST_FILE) {
+                continue;
+            }
+            let content = std::fs::read_to_string(&file)?;
+            pack.rules.push((format!("rules/{}", archive_name(file.strip_prefix(&rules_root).unwrap_or(&file))), content));
+        }
+
+        let fixtures_root = dir.join("fixtures");
+        if fixtures_root.is_dir() {
+            let mut fixture_files = Vec::new();
+            collect_files(&fixtures_root, &mut fixture_files)?;
+            for file in fixture_files {
+                let content = std::fs::read(&file)?;
+                pack.fixtures.push((format!("fixtures/{}", archive_name(file.strip_prefix(&fixtures_root).unwrap_or(&file))), content));
+            }
+        }
+
+        if pack.rules.is_empty() {
+            return Err(AnalysisError::config_error(format!("No rule files found in {}", dir.display())));
+        }
+
+        Ok(pack)
+    }
+
+    /// Write the pack as a zip archive
+    pub fn write_to(&self, path: &Path) -> Result<()> {
+        let file = std::fs::File::create(path)?;
+        let mut zip = zip::ZipWriter::new(file);
+        let options = zip::write::FileOptions::default()
+            .compression_method(zip::CompressionMethod::Deflated);
+
+        let manifest = serde_yaml::to_string(&self.manifest)
+            .map_err(|e| AnalysisError::internal_error(format!("Failed to serialize manifest: {}", e)))?;
+        zip.start_file(MANIFEST_FILE, options).map_err(zip_error)?;
+        zip.write_all(manifest.as_bytes())?;
+
+        for (name, content) in &self.rules {
+            zip.start_file(name.as_str(), options).map_err(zip_error)?;
+            zip.write_all(content.as_bytes())?;
+        }
+        for (name, content) in &self.fixtures {
+            zip.start_file(name.as_str(), options).map_err(zip_error)?;
+            zip.write_all(content)?;
+        }
+
+        zip.finish().map_err(zip_error)?;
+        Ok(())
+    }
+
+    /// Read a pack from a zip archive
+    pub fn read_from(path: &Path) -> Result<Self> {
+        let file = std::fs::File::open(path)?;
+        let mut archive = zip::ZipArchive::new(file).map_err(zip_error)?;
+
+        let mut manifest = None;
+        let mut rules = Vec::new();
+        let mut fixtures = Vec::new();
+
+        for i in 0..archive.len() {
+            let mut entry = archive.by_index(i).map_err(zip_error)?;
+            if entry.is_dir() {
+                continue;
+            }
+            let name = entry.name().to_string();
+            let mut content = Vec::new();
+            entry.read_to_end(&mut content)?;
+
+            if name == MANIFEST_FILE {
+                manifest = Some(parse_manifest(&String::from_utf8_lossy(&content))?);
+            } else if name.starts_with("rules/") {
+                let text = String::from_utf8(content).map_err(|_| {
+                    AnalysisError::parse_error(format!("Rule file {} in pack is not valid UTF-8", name))
+                })?;
+                rules.push((name, text));
+            } else if name.starts_with("fixtures/") {
+                fixtures.push((name, content));
+            }
+        }
+
+        let manifest = manifest.ok_or_else(|| {
+            AnalysisError::parse_error(format!("Rule pack {} has no {}", path.display(), MANIFEST_FILE))
+        })?;
+
+        Ok(Self { manifest, rules, fixtures })
+    }
+
+    /// Load all rules of the pack into an engine, returning the number of loaded rules.
+    ///
+    /// Fails if the pack requires a newer engine than the running one.
+    pub fn load_into(&self, engine: &mut RuleEngine) -> Result<usize> {
+        if !self.manifest.is_compatible() {
+            return Err(AnalysisError::config_error(format!(
+                "Rule pack '{}' requires engine version {} (running {})",
+                self.manifest.name,
+                self.manifest.min_engine_version.as_deref().unwrap_or_default(),
+                ENGINE_VERSION
+            )));
+        }
+
+        let mut loaded = 0;
+        for (name, content) in &self.rules {
+            match engine.load_rules_from_yaml(content) {
+                Ok(count) => loaded += count,
+                Err(e) => tracing::warn!("Failed to load rules from {} in pack '{}': {}", name, self.manifest.name, e),
+            }
+        }
+        Ok(loaded)
+    }
+}
+
+/// Compare dotted version strings, returning true if `current >= required`.
+/// Missing or non-numeric components count as zero.
+pub fn version_at_least(current: &str, required: &str) -> bool {
+    fn parts(v: &str) -> Vec<u64> {
+        v.trim()
+            .trim_start_matches('v')
+            .split(['.', '-', '+'])
+            .take(3)
+            .map(|p| p.parse().unwrap_or(0))
+            .collect()
+    }
+    let (mut current, mut required) = (parts(current), parts(required));
+    current.resize(3, 0);
+    required.resize(3, 0);
+    current >= required
+}
+
+fn parse_manifest(content: &str) -> Result<RulePackManifest> {
+    serde_yaml::from_str(content)
+        .map_err(|e| AnalysisError::parse_error(format!("Invalid pack manifest: {}", e)))
+}
+
+fn zip_error(e: zip::result::ZipError) -> AnalysisError {
+    AnalysisError::io_error(format!("Rule pack archive error: {}", e))
+}
+
+fn is_yaml(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "yaml" || ext == "yml")
+}
+
+fn archive_name(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<std::path::PathBuf>) -> Result<()> {
+    let mut entries: Vec<_> = std::fs::read_dir(dir)?.filter_map(|e| e.ok()).map(|e| e.path()).collect();
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RULES: &str = r#"
+rules:
+  - id: pack-rule
+    name: Pack Rule
+    description: A rule shipped in a pack
+    message: A rule shipped in a pack
+    severity: WARNING
+    languages: [java]
+    patterns:
+      - "System.out.println($X)"
+"#;
+
+    fn write_pack_source(dir: &Path, min_engine_version: Option<&str>) {
+        std::fs::create_dir_all(dir.join("rules/java")).unwrap();
+        std::fs::create_dir_all(dir.join("fixtures")).unwrap();
+        std::fs::write(dir.join("rules/java/println.yaml"), RULES).unwrap();
+        std::fs::write(dir.join("fixtures/Test.java"), "System.out.println(x);").unwrap();
+        let mut manifest = RulePackManifest::new("demo".to_string(), "1.2.0".to_string());
+        manifest.categories = vec!["security".to_string()];
+        manifest.min_engine_version = min_engine_version.map(|v| v.to_string());
+        std::fs::write(dir.join(MANIFEST_FILE), serde_yaml::to_string(&manifest).unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_pack_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        write_pack_source(dir.path(), None);
+
+        let pack = RulePack::from_directory(dir.path(), None).unwrap();
+        assert_eq!(pack.rules.len(), 1);
+        assert_eq!(pack.rules[0].0, "rules/java/println.yaml");
+        assert_eq!(pack.fixtures.len(), 1);
+
+        let archive = dir.path().join("demo.astgrep-pack");
+        pack.write_to(&archive).unwrap();
+        assert!(RulePack::is_pack_path(&archive));
+
+        let loaded = RulePack::read_from(&archive).unwrap();
+        assert_eq!(loaded.manifest, pack.manifest);
+        assert_eq!(loaded.rules, pack.rules);
+        assert_eq!(loaded.fixtures, pack.fixtures);
+
+        let mut engine = RuleEngine::new();
+        assert_eq!(loaded.load_into(&mut engine).unwrap(), 1);
+        assert_eq!(engine.rules()[0].id, "pack-rule");
+    }
+
+    #[test]
+    fn test_pack_requiring_newer_engine_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        write_pack_source(dir.path(), Some("999.0.0"));
+
+        let pack = RulePack::from_directory(dir.path(), None).unwrap();
+        let mut engine = RuleEngine::new();
+        assert!(pack.load_into(&mut engine).is_err());
+        assert_eq!(engine.rule_count(), 0);
+    }
+
+    #[test]
+    fn test_version_at_least() {
+        assert!(version_at_least("0.1.0", "0.1.0"));
+        assert!(version_at_least("1.2.0", "1.1.9"));
+        assert!(version_at_least("v2.0", "1.9.9"));
+        assert!(!version_at_least("0.1.0", "0.2.0"));
+        assert!(!version_at_least("0.1.0", "1"));
+    }
+}
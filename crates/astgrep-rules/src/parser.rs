@@ -3,11 +3,37 @@
 //! This module provides functionality to parse rules from YAML format.
 
 use crate::types::*;
-use astgrep_core::{AnalysisError, Confidence, Language, Result, Severity};
-use astgrep_core::{MetavariableAnalysis, EntropyAnalysis, TypeAnalysis, ComplexityAnalysis};
+use astgrep_core::{diagnostics_enabled, record_diagnostic, AnalysisError, Confidence, DiagnosticKind, Language, Result, Severity};
+use astgrep_core::{MetavariableAnalysis, EntropyAnalysis, TypeAnalysis, ComplexityAnalysis, NumericConstraint};
 use serde_yaml::Value;
 use std::collections::HashMap;
 
+/// Keys of a rule the parser reads, in the order `astgrep fmt` writes them
+pub const RULE_KEYS: &[&str] = &[
+    "id", "name", "description", "message", "severity", "confidence", "languages",
+    "patterns", "pattern", "pattern-either", "pattern-inside", "pattern-regex",
+    "dataflow", "fix", "fix-regex", "paths", "examples", "tests", "metadata", "options",
+    "min_version", "requires", "engine", "enabled",
+];
+
+/// Keys holding the patterns of a rule, by precedence: only the first present is read
+pub(crate) const RULE_PATTERN_KEYS: &[&str] = &["patterns", "pattern", "pattern-either", "pattern-inside", "pattern-regex"];
+
+/// Keys giving the kind of a pattern object, by precedence: only the first present is read
+pub(crate) const PATTERN_KIND_KEYS: &[&str] = &[
+    "pattern", "pattern-inside", "pattern-not-inside", "pattern-not", "pattern-regex",
+    "pattern-not-regex", "pattern-either", "pattern-all", "pattern-any",
+];
+
+/// Keys refining a pattern object
+pub(crate) const PATTERN_MODIFIER_KEYS: &[&str] = &[
+    "metavariable-pattern", "metavariable-regex", "metavariable-name", "metavariable-type",
+    "metavariable-numeric", "metavariable-analysis", "focus", "focus-metavariable",
+];
+
+/// Keys of a rule's `options:` block
+pub(crate) const OPTION_KEYS: &[&str] = &["escalate_severity", "escalate_confidence", "sql_statement_boundary", "constant_propagation", "commutative_matching", "associative_matching", "ast_matching", "import_aliasing", "symbolic_propagation"];
+
 /// YAML rule parser
 pub struct RuleParser {
     strict_mode: bool,
@@ -69,6 +95,9 @@ impl RuleParser {
 
         // Parse required fields
         let id = self.get_string_field(rule_obj, "id", index)?;
+        if diagnostics_enabled() {
+            Self::record_ignored_keys(&id, rule_obj);
+        }
         let severity = self.parse_severity(rule_obj, index)?;
         let languages = self.parse_languages(rule_obj, index)?;
 
@@ -86,27 +115,75 @@ impl RuleParser {
         let fix = self.get_optional_string_field(rule_obj, "fix");
         let fix_regex = self.parse_fix_regex(rule_obj, index)?;
         let paths = self.parse_paths(rule_obj, index)?;
+        let examples = self.parse_examples(rule_obj, index)?;
         let mut metadata = self.parse_metadata(rule_obj, index)?;
         // Parse optional options block and merge into metadata (stringified)
         if let Some(opts) = self.parse_options(rule_obj, index)? {
             for (k, v) in opts { metadata.insert(k, v); }
         }
+        // Engine requirements are kept in metadata so the engine can gate on them
+        if let Some(min_version) = self.parse_min_version(rule_obj, index)? {
+            metadata.insert("min_version".to_string(), min_version);
+        }
+        let requires = self.parse_string_list(rule_obj, "requires");
+        if !requires.is_empty() {
+            metadata.insert("requires".to_string(), requires.join(","));
+        }
+        if let Some(hint) = self.parse_engine(rule_obj, index)? {
+            metadata.insert(ENGINE_METADATA_KEY.to_string(), hint.engine.as_str().to_string());
+            metadata.insert(ENGINE_REQUIRED_METADATA_KEY.to_string(), hint.required.to_string());
+        }
         let enabled = self.get_optional_bool_field(rule_obj, "enabled").unwrap_or(true);
 
         let mut rule = Rule::new(id, name, description, severity, confidence, languages);
-        rule.patterns = patterns;
+        rule.patterns = patterns.into_iter().map(Pattern::expand_typed_metavariables).collect();
         rule.dataflow = dataflow;
         rule.fix = fix;
         rule.fix_regex = fix_regex;
         rule.paths = paths;
+        rule.examples = examples;
         rule.metadata = metadata;
         rule.enabled = enabled;
 
         Ok(rule)
     }
 
-    /// Parse optional options block; currently recognizes sql_statement_boundary
-    fn parse_options(&self, obj: &serde_yaml::Mapping, _index: usize) -> Result<Option<HashMap<String, String>>> {
+    /// Record the keys of a rule and of its pattern objects that parsing skips
+    fn record_ignored_keys(id: &str, rule_obj: &serde_yaml::Mapping) {
+        let record = |construct: String| record_diagnostic(DiagnosticKind::IgnoredConstruct, None, &construct, || id.to_string());
+        for key in rule_obj.keys().filter_map(Value::as_str).filter(|key| !RULE_KEYS.contains(key)) {
+            record(format!("rule key {}", key));
+        }
+        let mut present = RULE_PATTERN_KEYS.iter().filter(|key| rule_obj.contains_key(**key));
+        if let Some(used) = present.next() {
+            for key in present {
+                record(format!("rule key {} beside {}", key, used));
+            }
+        }
+
+        let mut pending: Vec<&Value> = ["patterns", "pattern-either"].iter().filter_map(|key| rule_obj.get(*key)).collect();
+        while let Some(value) = pending.pop() {
+            for pattern_obj in value.as_sequence().into_iter().flatten().filter_map(Value::as_mapping) {
+                let keys: Vec<&str> = pattern_obj.keys().filter_map(Value::as_str).collect();
+                for key in keys.iter().filter(|key| !PATTERN_KIND_KEYS.contains(key) && !PATTERN_MODIFIER_KEYS.contains(key)) {
+                    record(format!("pattern key {}", key));
+                }
+                let mut kinds = PATTERN_KIND_KEYS.iter().filter(|key| keys.contains(key));
+                if let Some(used) = kinds.next() {
+                    for key in kinds {
+                        record(format!("pattern key {} beside {}", key, used));
+                    }
+                }
+                pending.extend(["pattern-either", "pattern-all", "pattern-any"].iter().filter_map(|key| pattern_obj.get(*key)));
+            }
+        }
+    }
+
+    /// Parse optional options block; currently recognizes sql_statement_boundary,
+    /// constant_propagation, commutative_matching, associative_matching, ast_matching,
+    /// import_aliasing, symbolic_propagation and the taint escalation targets
+    /// escalate_severity / escalate_confidence
+    fn parse_options(&self, obj: &serde_yaml::Mapping, index: usize) -> Result<Option<HashMap<String, String>>> {
         let options_value = obj.get(&Value::String("options".to_string()));
         if options_value.is_none() { return Ok(None); }
         let options_obj = options_value
@@ -114,26 +191,105 @@ impl RuleParser {
             .as_mapping()
             .ok_or_else(|| AnalysisError::parse_error("'options' must be an object".to_string()))?;
         let mut options = HashMap::new();
-        if let Some(val) = options_obj.get(&Value::String("sql_statement_boundary".to_string())) {
-            // Accept boolean or string "on"/"off" and stringify to "true"/"false"
-            let str_val = if let Some(b) = val.as_bool() {
-                b.to_string()
-            } else if let Some(s) = val.as_str() {
-                match s.to_ascii_lowercase().as_str() {
-                    "on" | "true" | "1" | "yes" => "true".to_string(),
-                    "off" | "false" | "0" | "no" => "false".to_string(),
-                    _ => s.to_string(),
-                }
-            } else {
-                // Unsupported type: ignore this option instead of forcing a string
-                // so only boolean or string values are accepted
-                return Ok(Some(options));
-            };
-            options.insert("sql_statement_boundary".to_string(), str_val);
+        if let Some(severity) = self.get_optional_string_field(options_obj, "escalate_severity") {
+            let severity = severity.to_uppercase();
+            if !matches!(severity.as_str(), "INFO" | "WARNING" | "ERROR" | "CRITICAL") {
+                return Err(AnalysisError::parse_error(format!(
+                    "Rule {} has invalid escalate_severity: {}",
+                    index, severity
+                )));
+            }
+            options.insert("escalate_severity".to_string(), severity);
+        }
+        if let Some(confidence) = self.get_optional_string_field(options_obj, "escalate_confidence") {
+            let confidence = confidence.to_uppercase();
+            if !matches!(confidence.as_str(), "LOW" | "MEDIUM" | "HIGH") {
+                return Err(AnalysisError::parse_error(format!(
+                    "Rule {} has invalid escalate_confidence: {}",
+                    index, confidence
+                )));
+            }
+            options.insert("escalate_confidence".to_string(), confidence);
+        }
+        for key in OPTION_KEYS.iter().filter(|key| !key.starts_with("escalate_")) {
+            if let Some(val) = options_obj.get(&Value::String(key.to_string())) {
+                // Accept boolean or string "on"/"off" and stringify to "true"/"false"
+                let str_val = if let Some(b) = val.as_bool() {
+                    b.to_string()
+                } else if let Some(s) = val.as_str() {
+                    match s.to_ascii_lowercase().as_str() {
+                        "on" | "true" | "1" | "yes" => "true".to_string(),
+                        "off" | "false" | "0" | "no" => "false".to_string(),
+                        _ => s.to_string(),
+                    }
+                } else {
+                    // Unsupported type: ignore this option instead of forcing a string
+                    // so only boolean or string values are accepted
+                    continue;
+                };
+                options.insert(key.to_string(), str_val);
+            }
         }
         Ok(Some(options))
     }
 
+    /// Parse the optional `min_version` field. Numbers are rejected: YAML reads `0.10`
+    /// as `0.1`, which would let older engines load the rule.
+    fn parse_min_version(&self, obj: &serde_yaml::Mapping, index: usize) -> Result<Option<String>> {
+        match obj.get(&Value::String("min_version".to_string())) {
+            Some(Value::String(s)) if !s.trim().is_empty() => Ok(Some(s.trim().to_string())),
+            Some(Value::Number(n)) => Err(AnalysisError::parse_error(format!(
+                "Rule {} min_version must be a quoted string, e.g. min_version: \"{}\"",
+                index, n
+            ))),
+            _ => Ok(None),
+        }
+    }
+
+    /// Parse the optional `engine` field: `engine: <name>` prefers an engine,
+    /// `engine: {require: <name>}` or `engine: {prefer: <name>}` states it explicitly
+    fn parse_engine(&self, obj: &serde_yaml::Mapping, index: usize) -> Result<Option<EngineHint>> {
+        let (name, required) = match obj.get(&Value::String("engine".to_string())) {
+            None => return Ok(None),
+            Some(Value::String(name)) => (name.clone(), false),
+            Some(Value::Mapping(map)) => match (
+                self.get_optional_string_field(map, "require"),
+                self.get_optional_string_field(map, "prefer"),
+            ) {
+                (Some(name), None) => (name, true),
+                (None, Some(name)) => (name, false),
+                _ => {
+                    return Err(AnalysisError::parse_error(format!(
+                        "Rule {} 'engine' must set exactly one of 'require' or 'prefer'",
+                        index
+                    )))
+                }
+            },
+            Some(_) => return Err(AnalysisError::parse_error(format!("Rule {} 'engine' must be a string or object", index))),
+        };
+        let engine = MatchEngine::parse(&name).ok_or_else(|| {
+            AnalysisError::parse_error(format!(
+                "Rule {} has unknown engine: {} (expected tree-sitter, generic or regex)",
+                index, name
+            ))
+        })?;
+        Ok(Some(EngineHint { engine, required }))
+    }
+
+    /// Parse a field holding either a single string or a list of strings
+    fn parse_string_list(&self, obj: &serde_yaml::Mapping, field: &str) -> Vec<String> {
+        match obj.get(&Value::String(field.to_string())) {
+            Some(Value::String(s)) => vec![s.trim().to_string()],
+            Some(Value::Sequence(seq)) => seq
+                .iter()
+                .filter_map(|v| v.as_str())
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
     /// Get a required string field
     fn get_string_field(&self, obj: &serde_yaml::Mapping, field: &str, index: usize) -> Result<String> {
         obj.get(&Value::String(field.to_string()))
@@ -239,6 +395,11 @@ impl RuleParser {
             return Ok(vec![pattern]);
         }
 
+        // Check for top-level 'pattern-regex' field
+        if let Some(pattern_regex) = self.get_optional_string_field(obj, "pattern-regex") {
+            return Ok(vec![Pattern::regex(pattern_regex)]);
+        }
+
         // No patterns found
         Ok(Vec::new())
     }
@@ -249,8 +410,16 @@ impl RuleParser {
             .as_sequence()
             .ok_or_else(|| AnalysisError::parse_error(format!("Rule {} 'patterns' must be an array", index)))?;
 
-        let mut patterns = Vec::new();
+        let mut patterns: Vec<Pattern> = Vec::new();
         for (pattern_index, pattern_value) in patterns_array.iter().enumerate() {
+            // `- metavariable-pattern: ...` and the like on their own refine the pattern before
+            if let (Some(obj), Some(previous)) = (pattern_value.as_mapping(), patterns.last_mut()) {
+                let has_key = |keys: &[&str]| keys.iter().any(|key| obj.contains_key(&Value::String(key.to_string())));
+                if !has_key(PATTERN_KIND_KEYS) && has_key(PATTERN_MODIFIER_KEYS) {
+                    self.parse_pattern_modifiers(previous, obj, index, pattern_index)?;
+                    continue;
+                }
+            }
             let pattern = self.parse_single_pattern(pattern_value, index, pattern_index)?;
             patterns.push(pattern);
         }
@@ -332,6 +501,12 @@ impl RuleParser {
             )));
         };
 
+        self.parse_pattern_modifiers(&mut pattern, pattern_obj, rule_index, pattern_index)?;
+        Ok(pattern)
+    }
+
+    /// Parse the metavariable constraints and focus of a pattern object into `pattern`
+    fn parse_pattern_modifiers(&self, pattern: &mut Pattern, pattern_obj: &serde_yaml::Mapping, rule_index: usize, pattern_index: usize) -> Result<()> {
         // Parse optional metavariable pattern
         if let Some(metavar_value) = pattern_obj.get(&Value::String("metavariable-pattern".to_string())) {
             let metavar_pattern = self.parse_metavariable_pattern(metavar_value, rule_index, pattern_index)?;
@@ -350,6 +525,18 @@ impl RuleParser {
             pattern.conditions.push(Condition::MetavariableName(metavar_name));
         }
 
+        // Parse optional metavariable-type
+        if let Some(metavar_type_value) = pattern_obj.get(&Value::String("metavariable-type".to_string())) {
+            let metavar_type = self.parse_metavariable_type(metavar_type_value, rule_index, pattern_index)?;
+            pattern.conditions.push(Condition::MetavariableType(metavar_type));
+        }
+
+        // Parse optional metavariable-numeric
+        if let Some(metavar_numeric_value) = pattern_obj.get(&Value::String("metavariable-numeric".to_string())) {
+            let metavar_numeric = self.parse_metavariable_numeric(metavar_numeric_value, rule_index, pattern_index)?;
+            pattern.conditions.push(Condition::MetavariableNumeric(metavar_numeric));
+        }
+
         // Parse optional metavariable-analysis
         if let Some(metavar_analysis_value) = pattern_obj.get(&Value::String("metavariable-analysis".to_string())) {
             let metavar_analysis = self.parse_metavariable_analysis(metavar_analysis_value, rule_index, pattern_index)?;
@@ -380,7 +567,7 @@ impl RuleParser {
             }
         }
 
-        Ok(pattern)
+        Ok(())
     }
 
     /// Parse metavariable pattern
@@ -393,31 +580,39 @@ impl RuleParser {
             )))?;
 
         let metavariable = self.get_string_field(metavar_obj, "metavariable", rule_index)?;
-        
-        let patterns_value = metavar_obj
-            .get(&Value::String("patterns".to_string()))
-            .ok_or_else(|| AnalysisError::parse_error(format!(
-                "Rule {} pattern {} metavariable_pattern missing 'patterns'",
-                rule_index, pattern_index
-            )))?;
 
-        let patterns_array = patterns_value
-            .as_sequence()
-            .ok_or_else(|| AnalysisError::parse_error(format!(
-                "Rule {} pattern {} metavariable_pattern 'patterns' must be an array",
-                rule_index, pattern_index
-            )))?;
+        // A single `pattern`, or `patterns` given as strings or `- pattern:` entries
+        let patterns = if let Some(pattern) = self.get_optional_string_field(metavar_obj, "pattern") {
+            vec![pattern]
+        } else {
+            let patterns_value = metavar_obj
+                .get(&Value::String("patterns".to_string()))
+                .ok_or_else(|| AnalysisError::parse_error(format!(
+                    "Rule {} pattern {} metavariable_pattern missing 'patterns'",
+                    rule_index, pattern_index
+                )))?;
 
-        let mut patterns = Vec::new();
-        for pattern_value in patterns_array {
-            let pattern_str = pattern_value
-                .as_str()
+            let patterns_array = patterns_value
+                .as_sequence()
                 .ok_or_else(|| AnalysisError::parse_error(format!(
-                    "Rule {} pattern {} metavariable pattern must be a string",
+                    "Rule {} pattern {} metavariable_pattern 'patterns' must be an array",
                     rule_index, pattern_index
                 )))?;
-            patterns.push(pattern_str.to_string());
-        }
+
+            let mut patterns = Vec::new();
+            for pattern_value in patterns_array {
+                let pattern_str = pattern_value
+                    .as_str()
+                    .map(str::to_string)
+                    .or_else(|| pattern_value.as_mapping().and_then(|entry| self.get_optional_string_field(entry, "pattern")))
+                    .ok_or_else(|| AnalysisError::parse_error(format!(
+                        "Rule {} pattern {} metavariable pattern must be a string",
+                        rule_index, pattern_index
+                    )))?;
+                patterns.push(pattern_str);
+            }
+            patterns
+        };
 
         let mut metavar_pattern = MetavariablePattern::with_patterns(metavariable, patterns);
 
@@ -436,6 +631,11 @@ impl RuleParser {
             metavar_pattern.name_constraint = Some(name_constraint);
         }
 
+        // Parse optional language of the bound code
+        if let Some(language) = self.get_optional_string_field(metavar_obj, "language") {
+            metavar_pattern.language = Some(language);
+        }
+
         // Parse optional analysis
         if let Some(analysis_value) = metavar_obj.get(&Value::String("analysis".to_string())) {
             let analysis = self.parse_metavariable_analysis_config(analysis_value, rule_index, pattern_index)?;
@@ -475,6 +675,50 @@ impl RuleParser {
         Ok(MetavariableName::new(metavariable, name_pattern))
     }
 
+    /// Parse metavariable type constraint (`type: Foo` or `types: [Foo, Bar]`)
+    fn parse_metavariable_type(&self, value: &Value, rule_index: usize, pattern_index: usize) -> Result<MetavariableType> {
+        let metavar_obj = value
+            .as_mapping()
+            .ok_or_else(|| AnalysisError::parse_error(format!(
+                "Rule {} pattern {} metavariable-type must be an object",
+                rule_index, pattern_index
+            )))?;
+
+        let metavariable = self.get_string_field(metavar_obj, "metavariable", rule_index)?;
+        let mut types = self.parse_string_list(metavar_obj, "types");
+        types.extend(self.parse_string_list(metavar_obj, "type"));
+        if types.is_empty() {
+            return Err(AnalysisError::parse_error(format!(
+                "Rule {} pattern {} metavariable-type requires 'type' or 'types'",
+                rule_index, pattern_index
+            )));
+        }
+
+        Ok(MetavariableType::new(metavariable, types))
+    }
+
+    /// Parse a numeric constraint (`comparison: "> 5min"` with an optional `unit` of the literal)
+    fn parse_metavariable_numeric(&self, value: &Value, rule_index: usize, pattern_index: usize) -> Result<MetavariableNumeric> {
+        let metavar_obj = value
+            .as_mapping()
+            .ok_or_else(|| AnalysisError::parse_error(format!(
+                "Rule {} pattern {} metavariable-numeric must be an object",
+                rule_index, pattern_index
+            )))?;
+
+        let metavariable = self.get_string_field(metavar_obj, "metavariable", rule_index)?;
+        let comparison = self.get_string_field(metavar_obj, "comparison", rule_index)?;
+        // `$MS > 300000` reads like `> 300000`
+        let comparison = comparison.trim().strip_prefix(metavariable.as_str()).unwrap_or(&comparison).to_string();
+        let unit = self.get_optional_string_field(metavar_obj, "unit");
+        let constraint = NumericConstraint::parse(&comparison, unit.as_deref()).map_err(|e| AnalysisError::parse_error(format!(
+            "Rule {} pattern {} metavariable-numeric: {}",
+            rule_index, pattern_index, e
+        )))?;
+
+        Ok(MetavariableNumeric::new(metavariable, constraint))
+    }
+
     /// Parse metavariable analysis
     fn parse_metavariable_analysis(&self, value: &Value, rule_index: usize, pattern_index: usize) -> Result<MetavariableAnalysisCondition> {
         let metavar_obj = value
@@ -728,6 +972,73 @@ impl RuleParser {
         Ok(Some(PathsFilter { includes, excludes }))
     }
 
+    /// Parse the optional `examples:` block (alias `tests:`), either a list of
+    /// `{code, match, language}` entries or a `{positive, negative}` mapping of snippets
+    fn parse_examples(&self, obj: &serde_yaml::Mapping, index: usize) -> Result<Vec<RuleExample>> {
+        let value = match obj
+            .get(&Value::String("examples".to_string()))
+            .or_else(|| obj.get(&Value::String("tests".to_string())))
+        {
+            Some(value) => value,
+            None => return Ok(Vec::new()),
+        };
+
+        let mut examples = Vec::new();
+        match value {
+            Value::Sequence(entries) => {
+                for entry in entries {
+                    examples.push(self.parse_example(entry, true, index)?);
+                }
+            }
+            Value::Mapping(groups) => {
+                for (key, should_match) in [("positive", true), ("negative", false)] {
+                    match groups.get(&Value::String(key.to_string())) {
+                        Some(Value::Sequence(entries)) => {
+                            for entry in entries {
+                                examples.push(self.parse_example(entry, should_match, index)?);
+                            }
+                        }
+                        Some(_) => {
+                            return Err(AnalysisError::parse_error(format!(
+                                "Rule {} examples '{}' must be an array",
+                                index, key
+                            )))
+                        }
+                        None => {}
+                    }
+                }
+            }
+            _ => {
+                return Err(AnalysisError::parse_error(format!(
+                    "Rule {} 'examples' must be an array or an object with 'positive'/'negative'",
+                    index
+                )))
+            }
+        }
+        Ok(examples)
+    }
+
+    /// Parse one example: a bare snippet, or an object with `code` and optional
+    /// `match` (defaults to `default_match`) and `language`
+    fn parse_example(&self, value: &Value, default_match: bool, index: usize) -> Result<RuleExample> {
+        if let Some(code) = value.as_str() {
+            return Ok(RuleExample { code: code.to_string(), language: None, should_match: default_match });
+        }
+        let entry = value
+            .as_mapping()
+            .ok_or_else(|| AnalysisError::parse_error(format!("Rule {} example must be a string or an object", index)))?;
+
+        let code = self.get_string_field(entry, "code", index)?;
+        let should_match = self.get_optional_bool_field(entry, "match").unwrap_or(default_match);
+        let language = match self.get_optional_string_field(entry, "language") {
+            Some(name) => Some(Language::from_str(&name).ok_or_else(|| {
+                AnalysisError::parse_error(format!("Rule {} example has unknown language: {}", index, name))
+            })?),
+            None => None,
+        };
+        Ok(RuleExample { code, language, should_match })
+    }
+
     /// Parse optional string array
     fn parse_optional_string_array(&self, obj: &serde_yaml::Mapping, field: &str) -> Result<Vec<String>> {
         let array_value = obj.get(&Value::String(field.to_string()));
@@ -963,4 +1274,292 @@ rules:
         let result = parser.parse_yaml(yaml);
         assert!(result.is_ok()); // Would be Err in true strict mode
     }
+
+    #[test]
+    fn test_ignored_keys_are_recorded() {
+        let yaml = r#"
+rules:
+  - id: diagnostics-rule
+    message: Ignored keys
+    severity: ERROR
+    languages: [python]
+    pattern-sources:
+      - pattern: input()
+    pattern-regex: "eval"
+    patterns:
+      - pattern: eval($X)
+        pattern-not: eval("1")
+      - pattern-either:
+          - pattern: exec($X)
+            where: $X
+"#;
+
+        astgrep_core::enable_diagnostics();
+        RuleParser::new().parse_yaml(yaml).unwrap();
+        let report = astgrep_core::take_diagnostics();
+        let mut recorded: Vec<&str> = report
+            .of_kind(DiagnosticKind::IgnoredConstruct)
+            .filter(|entry| entry.samples.iter().any(|s| s == "diagnostics-rule"))
+            .map(|entry| entry.subject.as_str())
+            .collect();
+        recorded.sort();
+        assert_eq!(
+            recorded,
+            vec!["pattern key pattern-not beside pattern", "pattern key where", "rule key pattern-regex beside patterns", "rule key pattern-sources"]
+        );
+    }
+
+    #[test]
+    fn test_parse_engine_requirements() {
+        let yaml = r#"
+rules:
+  - id: gated-rule
+    message: Needs newer engine features
+    severity: WARNING
+    languages: [java]
+    min_version: "0.10"
+    requires: [taint-labels, pattern-inside]
+    pattern: "foo($X)"
+"#;
+
+        let parser = RuleParser::new();
+        let rules = parser.parse_yaml(yaml).unwrap();
+        assert_eq!(rules[0].get_metadata("min_version"), Some(&"0.10".to_string()));
+        assert_eq!(rules[0].get_metadata("requires"), Some(&"taint-labels,pattern-inside".to_string()));
+
+        // Unquoted, 0.10 would be read as 0.1; the lenient parser skips the rule
+        let err = RuleParser::strict().parse_yaml(&yaml.replace("\"0.10\"", "0.10")).unwrap_err().to_string();
+        assert!(err.contains("must be a quoted string"), "{}", err);
+    }
+
+    #[test]
+    fn test_parse_examples() {
+        let yaml = r#"
+rules:
+  - id: listed
+    message: List form
+    severity: WARNING
+    languages: [python, javascript]
+    pattern: "eval($X)"
+    examples:
+      - code: "eval(data)"
+      - code: "evaluate(data)"
+        match: false
+      - code: "eval(location.hash)"
+        language: javascript
+  - id: grouped
+    message: Grouped form
+    severity: WARNING
+    languages: [python]
+    pattern: "eval($X)"
+    tests:
+      positive:
+        - "eval(data)"
+      negative:
+        - "literal_eval(data)"
+"#;
+
+        let rules = RuleParser::new().parse_yaml(yaml).unwrap();
+        assert_eq!(rules[0].examples, vec![
+            RuleExample::positive("eval(data)".to_string()),
+            RuleExample::negative("evaluate(data)".to_string()),
+            RuleExample::positive("eval(location.hash)".to_string()).with_language(Language::JavaScript),
+        ]);
+        assert_eq!(rules[1].examples, vec![
+            RuleExample::positive("eval(data)".to_string()),
+            RuleExample::negative("literal_eval(data)".to_string()),
+        ]);
+
+        let bad = yaml.replace("language: javascript", "language: cobol");
+        assert!(RuleParser::strict().parse_yaml(&bad).is_err());
+        assert_eq!(RuleParser::new().parse_yaml(&bad).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_parse_metavariable_type() {
+        let yaml = r#"
+rules:
+  - id: runtime-exec
+    message: Command execution via Runtime.exec
+    severity: ERROR
+    languages: [java]
+    patterns:
+      - pattern: "$RT.exec(...)"
+        metavariable-type:
+          metavariable: $RT
+          type: java.lang.Runtime
+"#;
+
+        let parser = RuleParser::new();
+        let rules = parser.parse_yaml(yaml).unwrap();
+        match &rules[0].patterns[0].conditions[0] {
+            Condition::MetavariableType(t) => {
+                assert_eq!(t.metavariable, "$RT");
+                assert_eq!(t.types, vec!["java.lang.Runtime".to_string()]);
+            }
+            other => panic!("unexpected condition: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_metavariable_numeric() {
+        let yaml = r#"
+rules:
+  - id: long-timeout
+    message: Timeout longer than five minutes
+    severity: WARNING
+    languages: [javascript]
+    patterns:
+      - pattern: "setTimeout($F, $MS)"
+        metavariable-numeric:
+          metavariable: $MS
+          unit: ms
+          comparison: "$MS > 5min"
+"#;
+
+        let parser = RuleParser::new();
+        let rules = parser.parse_yaml(yaml).unwrap();
+        match &rules[0].patterns[0].conditions[0] {
+            Condition::MetavariableNumeric(n) => {
+                assert_eq!(n.metavariable, "$MS");
+                assert_eq!(n.constraint.bound, 300000.0);
+                assert!(n.holds("10 * 60 * 1000", Language::JavaScript));
+                assert!(!n.holds("300_000", Language::JavaScript));
+            }
+            other => panic!("unexpected condition: {:?}", other),
+        }
+
+        let invalid = yaml.replace("unit: ms", "unit: bytes");
+        assert!(RuleParser::strict().parse_yaml(&invalid).is_err());
+    }
+
+    #[test]
+    fn test_parse_metavariable_pattern_language() {
+        let yaml = r#"
+rules:
+  - id: sql-in-string
+    message: Query reads the users table
+    severity: WARNING
+    languages: [python]
+    patterns:
+      - pattern: "cursor.execute($Q)"
+        metavariable-pattern:
+          metavariable: $Q
+          language: sql
+          patterns:
+            - pattern: "FROM users"
+            - "SELECT"
+"#;
+
+        let parser = RuleParser::new();
+        let rules = parser.parse_yaml(yaml).unwrap();
+        let spec = rules[0].patterns[0].metavariable_pattern.as_ref().unwrap();
+        assert_eq!(spec.patterns, vec!["FROM users".to_string(), "SELECT".to_string()]);
+        assert_eq!(spec.language.as_deref(), Some("sql"));
+        assert_eq!(rules[0].embedded_languages(), vec![Language::Sql]);
+
+        let converted = rules[0].patterns[0].to_semgrep_pattern();
+        assert_eq!(converted.metavariable_pattern.unwrap().language.as_deref(), Some("sql"));
+
+        // A single `pattern`, given as its own entry of `patterns`
+        let standalone = r#"
+rules:
+  - id: sql-in-string
+    message: Query reads the users table
+    severity: WARNING
+    languages: [python]
+    patterns:
+      - pattern: "cursor.execute($Q)"
+      - metavariable-pattern:
+          metavariable: $Q
+          pattern: "FROM users"
+"#;
+        let rules = parser.parse_yaml(standalone).unwrap();
+        assert_eq!(rules[0].patterns.len(), 1);
+        let spec = rules[0].patterns[0].metavariable_pattern.as_ref().unwrap();
+        assert_eq!(spec.patterns, vec!["FROM users".to_string()]);
+        assert_eq!(spec.language, None);
+    }
+
+    #[test]
+    fn test_parse_escalation_options() {
+        let yaml = r#"
+rules:
+  - id: sqli-concat
+    message: SQL built by string concatenation
+    severity: WARNING
+    confidence: LOW
+    languages: [java]
+    pattern: "$STMT.executeQuery($Q + $X)"
+    options:
+      escalate_severity: critical
+      escalate_confidence: high
+"#;
+
+        let parser = RuleParser::new();
+        let rules = parser.parse_yaml(yaml).unwrap();
+        assert_eq!(rules[0].get_metadata("escalate_severity"), Some(&"CRITICAL".to_string()));
+        assert_eq!(rules[0].escalation(), Some((Severity::Critical, Confidence::High)));
+
+        let invalid = yaml.replace("escalate_confidence: high", "escalate_confidence: certain");
+        assert!(RuleParser::strict().parse_yaml(&invalid).is_err());
+    }
+
+    #[test]
+    fn test_parse_constant_propagation_option() {
+        let yaml = r#"
+rules:
+  - id: weak-hash
+    message: Weak hash algorithm
+    severity: WARNING
+    languages: [java]
+    pattern: MessageDigest.getInstance("MD5")
+    options:
+      constant_propagation: on
+"#;
+        let rules = RuleParser::new().parse_yaml(yaml).unwrap();
+        assert_eq!(rules[0].get_metadata("constant_propagation"), Some(&"true".to_string()));
+
+        let rules = RuleParser::new().parse_yaml(&yaml.replace("constant_propagation: on", "constant_propagation: false")).unwrap();
+        assert_eq!(rules[0].get_metadata("constant_propagation"), Some(&"false".to_string()));
+
+        let rules = RuleParser::new().parse_yaml(&yaml.replace("constant_propagation: on", "symbolic_propagation: yes")).unwrap();
+        assert_eq!(rules[0].get_metadata("symbolic_propagation"), Some(&"true".to_string()));
+    }
+
+    #[test]
+    fn test_parse_equivalence_options() {
+        let yaml = r#"
+rules:
+  - id: null-check
+    message: Null comparison
+    severity: INFO
+    languages: [java]
+    pattern: $X == null
+    options:
+      commutative_matching: off
+      associative_matching: false
+"#;
+        let rules = RuleParser::new().parse_yaml(yaml).unwrap();
+        assert_eq!(rules[0].get_metadata("commutative_matching"), Some(&"false".to_string()));
+        assert_eq!(rules[0].get_metadata("associative_matching"), Some(&"false".to_string()));
+    }
+
+    #[test]
+    fn test_parse_ast_matching_option() {
+        let yaml = r#"
+rules:
+  - id: exec-call
+    message: Command execution
+    severity: WARNING
+    languages: [java]
+    pattern: Runtime.getRuntime().exec($CMD)
+    options:
+      ast_matching: off
+      import_aliasing: false
+"#;
+        let rules = RuleParser::new().parse_yaml(yaml).unwrap();
+        assert_eq!(rules[0].get_metadata("ast_matching"), Some(&"false".to_string()));
+        assert_eq!(rules[0].get_metadata("import_aliasing"), Some(&"false".to_string()));
+    }
 }
@@ -0,0 +1,131 @@
+//! Rule policies
+//!
+//! A policy file tunes rules without editing the packs that define them. It maps rule ids,
+//! or globs over them, to the fields an [override](crate::RuleOverride) may set:
+//!
+//! ```yaml
+//! rules:
+//!   vendor-*:
+//!     enabled: false
+//!   python-eval:
+//!     severity: INFO
+//!     confidence: HIGH
+//! ```
+//!
+//! A policy applies after every rule source is loaded, whatever the order of the sources,
+//! and its entries apply in file order, so a later entry wins over a broader earlier one.
+//! In globs, `*` matches any run of characters and `?` a single one.
+
+use crate::RuleOverride;
+use astgrep_core::{AnalysisError, Result};
+use regex::Regex;
+use serde_yaml::Value;
+use std::path::Path;
+
+/// Overrides applied to every rule whose id matches a glob
+#[derive(Debug, Clone)]
+pub struct RulePolicy {
+    /// File the policy was read from
+    pub origin: String,
+    entries: Vec<(Regex, RuleOverride)>,
+}
+
+impl RulePolicy {
+    /// Read a policy file
+    pub fn read(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| AnalysisError::parse_error(format!("Cannot read policy {}: {}", path.display(), e)))?;
+        Self::parse(&content, &path.display().to_string())
+    }
+
+    /// Parse a policy document; `origin` names it in conflicts and errors
+    pub fn parse(content: &str, origin: &str) -> Result<Self> {
+        let document: Value = serde_yaml::from_str(content)
+            .map_err(|e| AnalysisError::parse_error(format!("Invalid policy {}: {}", origin, e)))?;
+        let rules = document
+            .get("rules")
+            .ok_or_else(|| AnalysisError::parse_error(format!("Policy {} has no 'rules' mapping", origin)))?
+            .as_mapping()
+            .ok_or_else(|| AnalysisError::parse_error(format!("'rules' of policy {} must be a mapping", origin)))?;
+
+        let mut entries = Vec::with_capacity(rules.len());
+        for (glob, fields) in rules {
+            let glob = glob
+                .as_str()
+                .ok_or_else(|| AnalysisError::parse_error(format!("Policy {} has a non-string rule id", origin)))?;
+            entries.push((glob_regex(glob), RuleOverride::with_fields(glob, fields, origin)?));
+        }
+        Ok(Self { origin: origin.to_string(), entries })
+    }
+
+    /// The overrides, one per id or glob, in file order
+    pub fn overrides(&self) -> impl Iterator<Item = &RuleOverride> {
+        self.entries.iter().map(|(_, rule_override)| rule_override)
+    }
+
+    /// The overrides with the expressions their globs compile to
+    pub(crate) fn entries(&self) -> impl Iterator<Item = (&Regex, &RuleOverride)> {
+        self.entries.iter().map(|(regex, rule_override)| (regex, rule_override))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Anchored regex matching the ids a glob stands for
+fn glob_regex(glob: &str) -> Regex {
+    let mut pattern = String::from("^");
+    for c in glob.chars() {
+        match c {
+            '*' => pattern.push_str(".*"),
+            '?' => pattern.push('.'),
+            c => pattern.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    pattern.push('$');
+    Regex::new(&pattern).expect("escaped glob is a valid regex")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use astgrep_core::Severity;
+
+    #[test]
+    fn test_parse_globs_in_file_order() {
+        let policy = RulePolicy::parse(
+            "rules:\n  vendor-*:\n    enabled: false\n  vendor-sql-?:\n    enabled: true\n    severity: error\n  py.eval:\n    confidence: LOW\n",
+            "policy.yaml",
+        )
+        .unwrap();
+        assert_eq!(policy.len(), 3);
+        let ids: Vec<&str> = policy.overrides().map(|o| o.id.as_str()).collect();
+        assert_eq!(ids, vec!["vendor-*", "vendor-sql-?", "py.eval"]);
+        assert_eq!(policy.overrides().nth(1).unwrap().severity, Some(Severity::Error));
+        assert!(policy.overrides().all(|o| o.origin == "policy.yaml"));
+
+        let matches = |index: usize, id: &str| policy.entries().nth(index).unwrap().0.is_match(id);
+        assert!(matches(0, "vendor-xss"));
+        assert!(!matches(0, "my-vendor-xss"));
+        assert!(matches(1, "vendor-sql-1"));
+        assert!(!matches(1, "vendor-sql-12"));
+        // '.' is literal
+        assert!(matches(2, "py.eval"));
+        assert!(!matches(2, "py-eval"));
+    }
+
+    #[test]
+    fn test_rejects_invalid_policies() {
+        let error = |yaml: &str| RulePolicy::parse(yaml, "policy.yaml").unwrap_err().to_string();
+        assert!(error("overrides: []\n").contains("no 'rules'"));
+        assert!(error("rules:\n  - id: a\n").contains("must be a mapping"));
+        assert!(error("rules:\n  a:\n    pattern: eval(...)\n").contains("sets 'pattern'"));
+        assert!(error("rules:\n  a:\n    severity: LOUD\n").contains("invalid severity"));
+        assert!(RulePolicy::read(Path::new("/nonexistent/policy.yaml")).is_err());
+    }
+}
@@ -0,0 +1,665 @@
+//! Shared rule repository
+//!
+//! Loads rules once from YAML files, directories, rule packs and inline YAML, deduplicates
+//! them by id and indexes them by id, language and category. Sources are layered in the
+//! order they are added: a later source replaces or [overrides](crate::RuleOverride) the
+//! rules of earlier ones, and every such change is kept as a [`RuleConflict`]; a
+//! [policy](crate::RulePolicy) applies after all of them. Regex
+//! patterns are compiled when a rule is loaded. Parsed files are cached process-wide by path, modification
+//! time and size, so building a repository for an already-seen rule set only stats the files, and
+//! [`RuleRepository::refresh`] lets long-running frontends poll for on-disk changes cheaply.
+
+use crate::{Pattern, PatternType, Rule, RuleEngine, RuleOverride, RuleParser, RulePack, RulePolicy, RuleValidator};
+use astgrep_core::{AnalysisError, Language, Result};
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::time::SystemTime;
+
+/// Repository handle shared between threads
+pub type SharedRuleRepository = Arc<RwLock<RuleRepository>>;
+
+static GLOBAL_REPOSITORY: OnceLock<SharedRuleRepository> = OnceLock::new();
+
+/// Parsed rule files shared by every repository in the process
+static FILE_CACHE: OnceLock<Mutex<HashMap<PathBuf, CachedFile>>> = OnceLock::new();
+
+/// A rule held by the repository
+#[derive(Debug, Clone)]
+pub struct LoadedRule {
+    pub rule: Rule,
+    /// File, pack or inline source the rule was loaded from
+    pub origin: String,
+    /// Compiled `pattern-regex` and `pattern-not-regex` expressions, in pattern order
+    pub regexes: Vec<Regex>,
+}
+
+/// How a rule source changed the rules of the sources before it
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuleConflict {
+    /// The rule was defined again and the new definition replaced the one from `replaced`
+    Replaced { rule_id: String, origin: String, replaced: String },
+    /// An override changed `fields` of the rule
+    Overridden { rule_id: String, origin: String, fields: Vec<&'static str> },
+    /// An override names a rule no source before it defines
+    UnknownRule { rule_id: String, origin: String },
+}
+
+impl std::fmt::Display for RuleConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RuleConflict::Replaced { rule_id, origin, replaced } => {
+                write!(f, "rule '{}' from {} replaces the one from {}", rule_id, origin, replaced)
+            }
+            RuleConflict::Overridden { rule_id, origin, fields } if fields.is_empty() => {
+                write!(f, "override of rule '{}' in {} changes nothing", rule_id, origin)
+            }
+            RuleConflict::Overridden { rule_id, origin, fields } => {
+                write!(f, "{} overrides {} of rule '{}'", origin, fields.join(", "), rule_id)
+            }
+            RuleConflict::UnknownRule { rule_id, origin } => {
+                write!(f, "{} overrides rule '{}', which no earlier source defines", origin, rule_id)
+            }
+        }
+    }
+}
+
+/// Rules and overrides read from one file or inline source
+#[derive(Debug, Clone, Default)]
+struct ParsedSource {
+    rules: Vec<LoadedRule>,
+    overrides: Vec<RuleOverride>,
+}
+
+#[derive(Debug, Clone)]
+enum Source {
+    Path(PathBuf),
+    Inline { name: String, content: String, parsed: ParsedSource },
+}
+
+/// Modification time and size of a rule file; the size catches edits within one mtime tick
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileStamp {
+    modified: Option<SystemTime>,
+    len: Option<u64>,
+}
+
+impl FileStamp {
+    fn of(path: &Path) -> Self {
+        let metadata = std::fs::metadata(path).ok();
+        Self {
+            modified: metadata.as_ref().and_then(|m| m.modified().ok()),
+            len: metadata.map(|m| m.len()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct CachedFile {
+    stamp: FileStamp,
+    parsed: ParsedSource,
+}
+
+/// Deduplicated, indexed set of rules loaded from files, packs and inline YAML
+#[derive(Debug, Default)]
+pub struct RuleRepository {
+    sources: Vec<Source>,
+    rules: Vec<LoadedRule>,
+    by_id: HashMap<String, usize>,
+    /// Rules dropped because the same source already defined the id, as (rule id, origin)
+    duplicates: Vec<(String, String)>,
+    /// Changes later sources made to the rules of earlier ones, in load order
+    conflicts: Vec<RuleConflict>,
+    /// Policy applied after every source
+    policy: Option<RulePolicy>,
+    /// Rule files and their stamps as of the last load
+    snapshot: Vec<(PathBuf, FileStamp)>,
+}
+
+impl RuleRepository {
+    /// Create an empty repository
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Process-wide repository shared by long-running frontends
+    pub fn global() -> &'static SharedRuleRepository {
+        GLOBAL_REPOSITORY.get_or_init(|| Arc::new(RwLock::new(RuleRepository::new())))
+    }
+
+    /// Create a repository from rule files, directories or packs
+    pub fn from_paths(paths: &[PathBuf]) -> Self {
+        let mut repository = Self::new();
+        repository.load_paths(paths);
+        repository
+    }
+
+    /// Add rule files, directories or packs as sources; returns the number of rules available
+    pub fn load_paths(&mut self, paths: &[PathBuf]) -> usize {
+        let mut added = false;
+        for path in paths {
+            if !self.sources.iter().any(|s| matches!(s, Source::Path(p) if p == path)) {
+                self.sources.push(Source::Path(path.clone()));
+                added = true;
+            }
+        }
+        if added {
+            self.rebuild();
+        }
+        self.rules.len()
+    }
+
+    /// Add or replace the inline YAML source `name`; returns the number of rules it defines.
+    /// Unchanged content is not parsed again.
+    pub fn load_yaml(&mut self, name: &str, content: &str) -> Result<usize> {
+        let existing = self.sources.iter().position(|s| matches!(s, Source::Inline { name: n, .. } if n == name));
+        if let Some(Source::Inline { content: old, parsed, .. }) = existing.map(|i| &self.sources[i]) {
+            if old == content {
+                return Ok(parsed.rules.len());
+            }
+        }
+
+        let parsed = parse_rules(content, name)?;
+        let count = parsed.rules.len();
+        let source = Source::Inline { name: name.to_string(), content: content.to_string(), parsed };
+        match existing {
+            Some(index) => self.sources[index] = source,
+            None => self.sources.push(source),
+        }
+        self.rebuild();
+        Ok(count)
+    }
+
+    /// Set or clear the policy applied after every source
+    pub fn set_policy(&mut self, policy: Option<RulePolicy>) {
+        self.policy = policy;
+        self.rebuild();
+    }
+
+    /// Whether every path in `paths` is already a source
+    pub fn contains_paths(&self, paths: &[PathBuf]) -> bool {
+        paths.iter().all(|path| self.sources.iter().any(|s| matches!(s, Source::Path(p) if p == path)))
+    }
+
+    /// Whether any rule file was added, removed or modified since the last load; only reads
+    /// file metadata, so callers sharing the repository can check under a read lock
+    pub fn is_stale(&self) -> bool {
+        let files = self.rule_files();
+        files.len() != self.snapshot.len()
+            || files.iter().zip(&self.snapshot).any(|(file, (seen, stamp))| file != seen || FileStamp::of(file) != *stamp)
+    }
+
+    /// Reload when any rule file was added, removed or modified; returns true if rules changed
+    pub fn refresh(&mut self) -> bool {
+        if !self.is_stale() {
+            return false;
+        }
+        self.rebuild();
+        true
+    }
+
+    /// Number of distinct rules
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// All rules, in load order
+    pub fn rules(&self) -> impl Iterator<Item = &Rule> {
+        self.rules.iter().map(|r| &r.rule)
+    }
+
+    /// Look up a rule with its origin and compiled patterns
+    pub fn get(&self, id: &str) -> Option<&LoadedRule> {
+        self.by_id.get(id).map(|&i| &self.rules[i])
+    }
+
+    /// Look up a rule by id
+    pub fn rule(&self, id: &str) -> Option<&Rule> {
+        self.get(id).map(|r| &r.rule)
+    }
+
+    /// Enabled rules that apply to `language`
+    pub fn rules_for_language(&self, language: Language) -> Vec<&Rule> {
+        self.rules().filter(|rule| rule.applies_to(language)).collect()
+    }
+
+    /// Rules whose `category` metadata matches, ignoring case
+    pub fn rules_by_category(&self, category: &str) -> Vec<&Rule> {
+        self.rules()
+            .filter(|rule| rule.get_metadata("category").is_some_and(|c| c.eq_ignore_ascii_case(category)))
+            .collect()
+    }
+
+    /// Distinct categories, sorted
+    pub fn categories(&self) -> Vec<String> {
+        let mut categories: Vec<String> = self.rules()
+            .filter_map(|rule| rule.get_metadata("category").cloned())
+            .collect();
+        categories.sort();
+        categories.dedup();
+        categories
+    }
+
+    /// Rules dropped as duplicates within one source, as (rule id, origin)
+    pub fn duplicates(&self) -> &[(String, String)] {
+        &self.duplicates
+    }
+
+    /// Rules replaced or overridden by later sources, and overrides of unknown rules
+    pub fn conflicts(&self) -> &[RuleConflict] {
+        &self.conflicts
+    }
+
+    /// Build a rule engine holding every rule
+    pub fn engine(&self) -> RuleEngine {
+        populate(self.rules())
+    }
+
+    /// Build a rule engine holding the rules that apply to `language`
+    pub fn engine_for_language(&self, language: Language) -> RuleEngine {
+        populate(self.rules_for_language(language).into_iter())
+    }
+
+    /// Rule files reachable from the path sources, in load order
+    fn rule_files(&self) -> Vec<PathBuf> {
+        let mut files = Vec::new();
+        for source in &self.sources {
+            if let Source::Path(path) = source {
+                collect_rule_files(path, &mut files);
+            }
+        }
+        let mut seen = HashSet::new();
+        files.retain(|f| seen.insert(f.clone()));
+        files
+    }
+
+    /// Re-read changed files and rebuild the index in source order
+    fn rebuild(&mut self) {
+        let mut snapshot: Vec<(PathBuf, FileStamp)> = Vec::new();
+        let mut loaded: Vec<ParsedSource> = Vec::new();
+        let mut cache = FILE_CACHE.get_or_init(Default::default).lock().unwrap_or_else(|e| e.into_inner());
+
+        for source in &self.sources {
+            match source {
+                Source::Inline { parsed, .. } => loaded.push(parsed.clone()),
+                Source::Path(path) => {
+                    let mut files = Vec::new();
+                    collect_rule_files(path, &mut files);
+                    let mut parsed = ParsedSource::default();
+                    for file in files {
+                        if snapshot.iter().any(|(seen, _)| seen == &file) {
+                            continue;
+                        }
+                        let stamp = FileStamp::of(&file);
+                        let fresh = cache.get(&file).is_some_and(|c| stamp.modified.is_some() && c.stamp == stamp);
+                        if !fresh {
+                            cache.insert(file.clone(), CachedFile { stamp, parsed: load_rule_file(&file) });
+                        }
+                        parsed.rules.extend(cache[&file].parsed.rules.iter().cloned());
+                        parsed.overrides.extend(cache[&file].parsed.overrides.iter().cloned());
+                        snapshot.push((file, stamp));
+                    }
+                    loaded.push(parsed);
+                }
+            }
+        }
+        drop(cache);
+
+        self.snapshot = snapshot;
+        self.rules.clear();
+        self.by_id.clear();
+        self.duplicates.clear();
+        self.conflicts.clear();
+        for source in loaded {
+            // Ids defined by this source: another definition in it is a duplicate, while one
+            // from an earlier source is replaced
+            let mut defined = HashSet::new();
+            for rule in source.rules {
+                let id = rule.rule.id.clone();
+                match self.by_id.get(&id) {
+                    Some(_) if defined.contains(&id) => {
+                        tracing::warn!("Duplicate rule '{}' in {} ignored", id, rule.origin);
+                        self.duplicates.push((id, rule.origin));
+                    }
+                    Some(&index) => {
+                        let replaced = std::mem::replace(&mut self.rules[index], rule);
+                        let conflict = RuleConflict::Replaced { rule_id: id.clone(), origin: self.rules[index].origin.clone(), replaced: replaced.origin };
+                        tracing::debug!("{}", conflict);
+                        self.conflicts.push(conflict);
+                        defined.insert(id);
+                    }
+                    None => {
+                        self.by_id.insert(id.clone(), self.rules.len());
+                        self.rules.push(rule);
+                        defined.insert(id);
+                    }
+                }
+            }
+            // Overrides apply once the whole source is loaded, whatever the file order
+            for rule_override in source.overrides {
+                let conflict = match self.by_id.get(&rule_override.id) {
+                    Some(&index) => RuleConflict::Overridden {
+                        rule_id: rule_override.id.clone(),
+                        fields: rule_override.apply(&mut self.rules[index].rule),
+                        origin: rule_override.origin,
+                    },
+                    None => RuleConflict::UnknownRule { rule_id: rule_override.id, origin: rule_override.origin },
+                };
+                tracing::debug!("{}", conflict);
+                self.conflicts.push(conflict);
+            }
+        }
+
+        let Some(policy) = &self.policy else {
+            return;
+        };
+        for (ids, rule_override) in policy.entries() {
+            let mut matched = false;
+            for loaded in self.rules.iter_mut().filter(|loaded| ids.is_match(&loaded.rule.id)) {
+                matched = true;
+                let conflict = RuleConflict::Overridden {
+                    rule_id: loaded.rule.id.clone(),
+                    fields: rule_override.apply(&mut loaded.rule),
+                    origin: rule_override.origin.clone(),
+                };
+                tracing::debug!("{}", conflict);
+                self.conflicts.push(conflict);
+            }
+            if !matched {
+                let conflict = RuleConflict::UnknownRule { rule_id: rule_override.id.clone(), origin: rule_override.origin.clone() };
+                tracing::debug!("{}", conflict);
+                self.conflicts.push(conflict);
+            }
+        }
+    }
+}
+
+fn populate<'a>(rules: impl Iterator<Item = &'a Rule>) -> RuleEngine {
+    let mut engine = RuleEngine::new();
+    for rule in rules {
+        if let Err(e) = engine.add_rule(rule.clone()) {
+            tracing::warn!("Failed to add rule '{}': {}", rule.id, e);
+        }
+    }
+    engine
+}
+
+fn is_yaml(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "yaml" || ext == "yml")
+}
+
+/// Collect YAML rule files and packs under `path`, recursing into directories in name order
+fn collect_rule_files(path: &Path, files: &mut Vec<PathBuf>) {
+    if path.is_file() {
+        if is_yaml(path) || RulePack::is_pack_path(path) {
+            files.push(path.to_path_buf());
+        }
+        return;
+    }
+    if !path.exists() {
+        return;
+    }
+    let entries = match std::fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(e) => {
+            tracing::warn!("Cannot read rule path {:?}: {}", path, e);
+            return;
+        }
+    };
+    let mut children: Vec<PathBuf> = entries.filter_map(|e| e.ok().map(|e| e.path())).collect();
+    children.sort();
+    for child in children {
+        collect_rule_files(&child, files);
+    }
+}
+
+/// Load one rule file or pack, warning about (and skipping) anything unusable
+fn load_rule_file(file: &Path) -> ParsedSource {
+    let origin = file.display().to_string();
+
+    if RulePack::is_pack_path(file) {
+        let pack = match RulePack::read_from(file) {
+            Ok(pack) => pack,
+            Err(e) => {
+                tracing::warn!("Failed to load rule pack {:?}: {}", file, e);
+                return ParsedSource::default();
+            }
+        };
+        if !pack.manifest.is_compatible() {
+            tracing::warn!(
+                "Skipping rule pack {:?}: requires engine version {}",
+                file,
+                pack.manifest.min_engine_version.as_deref().unwrap_or_default()
+            );
+            return ParsedSource::default();
+        }
+        let mut loaded = ParsedSource::default();
+        for (name, content) in &pack.rules {
+            match parse_rules(content, &format!("{}!{}", origin, name)) {
+                Ok(parsed) => {
+                    loaded.rules.extend(parsed.rules);
+                    loaded.overrides.extend(parsed.overrides);
+                }
+                Err(e) => tracing::warn!("Failed to load rules from {} in pack {:?}: {}", name, file, e),
+            }
+        }
+        return loaded;
+    }
+
+    let parsed = std::fs::read_to_string(file)
+        .map_err(AnalysisError::from)
+        .and_then(|content| parse_rules(&content, &origin));
+    parsed.unwrap_or_else(|e| {
+        tracing::warn!("Failed to load rules from {:?}: {}", file, e);
+        ParsedSource::default()
+    })
+}
+
+/// Parse and validate a YAML rule document, with the same rules as [`RuleEngine::load_rules_from_yaml`],
+/// and its overrides. A document may hold only overrides.
+fn parse_rules(content: &str, origin: &str) -> Result<ParsedSource> {
+    let document: serde_yaml::Value = serde_yaml::from_str(content)
+        .map_err(|e| AnalysisError::parse_error(format!("YAML syntax error: {}", e)))?;
+    let overrides = RuleOverride::parse_all(&document, origin)?;
+    if document.get("rules").is_none() && !overrides.is_empty() {
+        return Ok(ParsedSource { rules: Vec::new(), overrides });
+    }
+
+    let parsed = RuleParser::new().parse_yaml(content)?;
+    if parsed.is_empty() {
+        return Err(AnalysisError::parse_error("No valid rules found"));
+    }
+    let validator = RuleValidator::new();
+    for rule in &parsed {
+        validator.validate_rule(rule)?;
+    }
+
+    let rules = parsed
+        .into_iter()
+        .map(|rule| {
+            let mut regexes = Vec::new();
+            compile_regexes(&rule.id, &rule.patterns, &mut regexes);
+            LoadedRule { rule, origin: origin.to_string(), regexes }
+        })
+        .collect();
+    Ok(ParsedSource { rules, overrides })
+}
+
+fn compile_regexes(rule_id: &str, patterns: &[Pattern], out: &mut Vec<Regex>) {
+    for pattern in patterns {
+        match &pattern.pattern_type {
+            PatternType::Regex(expr) | PatternType::NotRegex(expr) => match Regex::new(expr) {
+                Ok(regex) => out.push(regex),
+                Err(e) => tracing::warn!("Rule '{}' has an invalid regex '{}': {}", rule_id, expr, e),
+            },
+            PatternType::Either(nested) | PatternType::All(nested) | PatternType::Any(nested) => {
+                compile_regexes(rule_id, nested, out)
+            }
+            PatternType::Inside(inner) | PatternType::NotInside(inner) | PatternType::Not(inner) => {
+                compile_regexes(rule_id, std::slice::from_ref(inner.as_ref()), out)
+            }
+            PatternType::Simple(_) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule_yaml(id: &str, category: &str) -> String {
+        format!(
+            "rules:\n  - id: {}\n    message: test\n    severity: WARNING\n    languages: [python]\n    metadata:\n      category: {}\n    pattern-regex: eval\\(\n",
+            id, category
+        )
+    }
+
+    #[test]
+    fn test_load_paths_deduplicates_and_indexes() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.yaml"), rule_yaml("no-eval", "security")).unwrap();
+        std::fs::create_dir(dir.path().join("nested")).unwrap();
+        std::fs::write(dir.path().join("nested/b.yml"), rule_yaml("no-eval", "style")).unwrap();
+        std::fs::write(dir.path().join("nested/c.yaml"), rule_yaml("no-exec", "Security")).unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "not a rule").unwrap();
+
+        let repository = RuleRepository::from_paths(&[dir.path().to_path_buf()]);
+        assert_eq!(repository.len(), 2);
+        assert_eq!(repository.duplicates().len(), 1);
+        assert_eq!(repository.rules_by_category("security").len(), 2);
+        assert_eq!(repository.rules_for_language(Language::Python).len(), 2);
+        assert!(repository.rules_for_language(Language::Java).is_empty());
+
+        let loaded = repository.get("no-eval").unwrap();
+        assert!(loaded.origin.ends_with("a.yaml"));
+        assert_eq!(loaded.regexes.len(), 1);
+        assert_eq!(repository.engine_for_language(Language::Python).rule_count(), 2);
+    }
+
+    #[test]
+    fn test_refresh_picks_up_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.yaml"), rule_yaml("no-eval", "security")).unwrap();
+        let mut repository = RuleRepository::from_paths(&[dir.path().to_path_buf()]);
+        assert!(!repository.refresh());
+
+        std::fs::write(dir.path().join("b.yaml"), rule_yaml("no-exec", "security")).unwrap();
+        assert!(repository.refresh());
+        assert!(repository.rule("no-exec").is_some());
+
+        std::fs::remove_file(dir.path().join("a.yaml")).unwrap();
+        assert!(repository.refresh());
+        assert!(repository.rule("no-eval").is_none());
+    }
+
+    #[test]
+    fn test_refresh_sees_edits_within_one_mtime_tick() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("a.yaml");
+        std::fs::write(&file, rule_yaml("no-eval", "security")).unwrap();
+        let mut repository = RuleRepository::from_paths(&[dir.path().to_path_buf()]);
+        assert!(repository.contains_paths(&[dir.path().to_path_buf()]));
+        assert!(!repository.is_stale());
+
+        let modified = std::fs::metadata(&file).unwrap().modified().unwrap();
+        std::fs::write(&file, rule_yaml("no-eval", "reliability")).unwrap();
+        std::fs::File::options().write(true).open(&file).unwrap().set_modified(modified).unwrap();
+        assert!(repository.is_stale());
+        assert!(repository.refresh());
+        assert_eq!(repository.rule("no-eval").unwrap().get_metadata("category").map(String::as_str), Some("reliability"));
+        assert!(!repository.is_stale());
+    }
+
+    #[test]
+    fn test_inline_sources_replace_by_name() {
+        let mut repository = RuleRepository::new();
+        assert_eq!(repository.load_yaml("editor", &rule_yaml("first", "security")).unwrap(), 1);
+        assert_eq!(repository.load_yaml("editor", &rule_yaml("second", "security")).unwrap(), 1);
+        assert!(repository.rule("first").is_none());
+        assert!(repository.rule("second").is_some());
+        assert!(repository.load_yaml("broken", "rules: [").is_err());
+        assert_eq!(repository.len(), 1);
+    }
+
+    #[test]
+    fn test_later_sources_take_precedence() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path().join("base");
+        let team = dir.path().join("team");
+        std::fs::create_dir_all(&base).unwrap();
+        std::fs::create_dir_all(&team).unwrap();
+        std::fs::write(base.join("a.yaml"), rule_yaml("no-eval", "security")).unwrap();
+        std::fs::write(base.join("b.yaml"), rule_yaml("no-exec", "security")).unwrap();
+        std::fs::write(base.join("c.yaml"), rule_yaml("no-pickle", "security")).unwrap();
+        // Overrides apply after every rule of their source, so this file may sort first
+        std::fs::write(
+            team.join("0-overrides.yaml"),
+            "overrides:\n  - id: no-exec\n    enabled: false\n  - id: no-pickle\n    severity: ERROR\n  - id: no-yaml-load\n    severity: INFO\n",
+        )
+        .unwrap();
+        std::fs::write(team.join("1-rules.yaml"), rule_yaml("no-eval", "style")).unwrap();
+
+        let repository = RuleRepository::from_paths(&[base.clone(), team.clone()]);
+        assert_eq!(repository.len(), 3);
+        assert!(repository.duplicates().is_empty());
+
+        let no_eval = repository.get("no-eval").unwrap();
+        assert!(no_eval.origin.ends_with("1-rules.yaml"));
+        assert_eq!(no_eval.rule.get_metadata("category").map(String::as_str), Some("style"));
+        assert!(!repository.rule("no-exec").unwrap().enabled);
+        assert!(repository.rules_for_language(Language::Python).iter().all(|rule| rule.id != "no-exec"));
+        assert_eq!(repository.rule("no-pickle").unwrap().severity, astgrep_core::Severity::Error);
+
+        let conflicts = repository.conflicts();
+        assert_eq!(conflicts.len(), 4);
+        assert!(matches!(&conflicts[0], RuleConflict::Replaced { rule_id, replaced, .. } if rule_id == "no-eval" && replaced.ends_with("a.yaml")));
+        assert_eq!(conflicts[1], RuleConflict::Overridden { rule_id: "no-exec".into(), origin: team.join("0-overrides.yaml").display().to_string(), fields: vec!["enabled"] });
+        assert!(matches!(&conflicts[3], RuleConflict::UnknownRule { rule_id, .. } if rule_id == "no-yaml-load"));
+        assert!(conflicts[3].to_string().contains("no earlier source defines"));
+
+        // The same rules in the other order: the base pack wins
+        let reversed = RuleRepository::from_paths(&[team, base]);
+        assert!(reversed.get("no-eval").unwrap().origin.ends_with("a.yaml"));
+        assert!(reversed.rule("no-exec").unwrap().enabled);
+    }
+
+    #[test]
+    fn test_policy_applies_after_every_source() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.yaml"), rule_yaml("vendor-eval", "security")).unwrap();
+        std::fs::write(dir.path().join("b.yaml"), rule_yaml("vendor-exec", "security")).unwrap();
+        std::fs::write(dir.path().join("c.yaml"), rule_yaml("team-eval", "security")).unwrap();
+        let mut repository = RuleRepository::from_paths(&[dir.path().to_path_buf()]);
+
+        let policy = RulePolicy::parse(
+            "rules:\n  vendor-*:\n    enabled: false\n  vendor-exec:\n    enabled: true\n    severity: CRITICAL\n  legacy-*:\n    enabled: false\n",
+            "policy.yaml",
+        )
+        .unwrap();
+        repository.set_policy(Some(policy));
+        assert!(!repository.rule("vendor-eval").unwrap().enabled);
+        assert!(repository.rule("vendor-exec").unwrap().enabled);
+        assert_eq!(repository.rule("vendor-exec").unwrap().severity, astgrep_core::Severity::Critical);
+        assert!(repository.rule("team-eval").unwrap().enabled);
+
+        let conflicts = repository.conflicts();
+        assert_eq!(conflicts.len(), 4);
+        assert_eq!(conflicts[2], RuleConflict::Overridden { rule_id: "vendor-exec".into(), origin: "policy.yaml".into(), fields: vec!["enabled", "severity"] });
+        assert_eq!(conflicts[3], RuleConflict::UnknownRule { rule_id: "legacy-*".into(), origin: "policy.yaml".into() });
+
+        // Later sources do not escape the policy
+        let extra = dir.path().join("extra");
+        std::fs::create_dir_all(&extra).unwrap();
+        std::fs::write(extra.join("d.yaml"), rule_yaml("vendor-pickle", "security")).unwrap();
+        repository.load_paths(&[extra]);
+        assert!(!repository.rule("vendor-pickle").unwrap().enabled);
+
+        repository.set_policy(None);
+        assert!(repository.rule("vendor-eval").unwrap().enabled);
+    }
+}
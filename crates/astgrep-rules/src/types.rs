@@ -2,9 +2,11 @@
 //! 
 //! This module defines the core types used in the rule system.
 
-use astgrep_core::{Confidence, Finding, Language, Severity, MetavariableAnalysis, ComparisonOperator};
+use astgrep_core::{CancellationToken, Confidence, Finding, Language, Severity, MetavariableAnalysis, ComparisonOperator, NumericConstraint};
+use astgrep_dataflow::{ConstantAnalyzer, ImportAliases, PropagatedSource, SymbolicAssignments};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::OnceLock;
 
 /// A complete rule definition
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -20,6 +22,9 @@ pub struct Rule {
     pub fix: Option<String>,
     pub fix_regex: Option<FixRegex>,
     pub paths: Option<PathsFilter>,
+    /// Embedded `examples:` the rule must (or must not) match
+    #[serde(default)]
+    pub examples: Vec<RuleExample>,
     pub metadata: HashMap<String, String>,
     pub enabled: bool,
 }
@@ -46,6 +51,7 @@ impl Rule {
             fix: None,
             fix_regex: None,
             paths: None,
+            examples: Vec::new(),
             metadata: HashMap::new(),
             enabled: true,
         }
@@ -90,6 +96,117 @@ impl Rule {
     pub fn get_metadata(&self, key: &str) -> Option<&String> {
         self.metadata.get(key)
     }
+
+    /// Matching engine requested through the rule's `engine:` key, if any
+    pub fn engine_hint(&self) -> Option<EngineHint> {
+        let engine = MatchEngine::parse(self.get_metadata(ENGINE_METADATA_KEY)?)?;
+        let required = self.get_metadata(ENGINE_REQUIRED_METADATA_KEY).is_some_and(|v| v == "true");
+        Some(EngineHint { engine, required })
+    }
+
+    /// Severity and confidence to apply when taint analysis corroborates a pattern match.
+    /// Returns None when the rule does not opt into escalation.
+    pub fn escalation(&self) -> Option<(Severity, Confidence)> {
+        let severity = self.get_metadata("escalate_severity");
+        let confidence = self.get_metadata("escalate_confidence");
+        if severity.is_none() && confidence.is_none() {
+            return None;
+        }
+        let severity = match severity.map(|s| s.as_str()) {
+            Some("INFO") => Severity::Info,
+            Some("WARNING") => Severity::Warning,
+            Some("ERROR") => Severity::Error,
+            Some("CRITICAL") => Severity::Critical,
+            _ => self.severity,
+        };
+        let confidence = match confidence.map(|c| c.as_str()) {
+            Some("LOW") => Confidence::Low,
+            Some("MEDIUM") => Confidence::Medium,
+            Some("HIGH") => Confidence::High,
+            _ => self.confidence,
+        };
+        Some((severity.max(self.severity), confidence.max(self.confidence)))
+    }
+
+    /// Literal words at least one of which occurs (case-insensitively) in every file the
+    /// rule can match, or None when the rule can match without any (dataflow, regexes,
+    /// patterns made of metavariables only)
+    pub fn anchors(&self) -> Option<Vec<String>> {
+        if self.dataflow.is_some() || self.patterns.is_empty() {
+            return None;
+        }
+        self.patterns.iter().map(Pattern::anchors).collect::<Option<Vec<_>>>().map(|anchors| anchors.concat())
+    }
+
+    /// Languages that `metavariable-pattern`s of the rule parse bound code in, which the
+    /// matcher needs parsers for
+    pub fn embedded_languages(&self) -> Vec<Language> {
+        let mut languages = Vec::new();
+        for pattern in &self.patterns {
+            pattern.collect_embedded_languages(&mut languages);
+        }
+        languages
+    }
+}
+
+/// Rule metadata key holding the requested matching engine
+pub const ENGINE_METADATA_KEY: &str = "engine";
+/// Rule metadata key set to `true` when the requested engine is mandatory
+pub const ENGINE_REQUIRED_METADATA_KEY: &str = "engine.required";
+
+/// Matching engine a rule can ask for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MatchEngine {
+    /// Structural matching on the tree-sitter syntax tree
+    TreeSitter,
+    /// Token-based matching on the source text, available for every language
+    Generic,
+    /// Regular expressions over the source text; only `pattern-regex` patterns apply
+    Regex,
+}
+
+impl MatchEngine {
+    /// Parse an engine name as written in rule YAML
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "tree-sitter" | "treesitter" => Some(MatchEngine::TreeSitter),
+            "generic" => Some(MatchEngine::Generic),
+            "regex" => Some(MatchEngine::Regex),
+            _ => None,
+        }
+    }
+
+    /// Name of the engine as written in rule YAML
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MatchEngine::TreeSitter => "tree-sitter",
+            MatchEngine::Generic => "generic",
+            MatchEngine::Regex => "regex",
+        }
+    }
+
+    /// Whether the engine can evaluate rules for `language`
+    pub fn supports(&self, language: Language) -> bool {
+        match self {
+            MatchEngine::TreeSitter => astgrep_core::constants::languages::TREE_SITTER_LANGUAGES.contains(&language),
+            MatchEngine::Generic | MatchEngine::Regex => true,
+        }
+    }
+}
+
+impl std::fmt::Display for MatchEngine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A rule's engine selection: `engine: <name>` prefers an engine and falls back to the
+/// generic one where it is unavailable; `engine: {require: <name>}` makes it mandatory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EngineHint {
+    pub engine: MatchEngine,
+    pub required: bool,
 }
 
 /// Pattern matching specification
@@ -225,6 +342,86 @@ impl Pattern {
         }
     }
 
+    /// Whether the pattern consists of regexes only and can be evaluated by the regex engine
+    pub fn is_regex_only(&self) -> bool {
+        match &self.pattern_type {
+            PatternType::Regex(_) | PatternType::NotRegex(_) => true,
+            PatternType::Either(patterns) | PatternType::All(patterns) | PatternType::Any(patterns) => {
+                patterns.iter().all(Pattern::is_regex_only)
+            }
+            _ => false,
+        }
+    }
+
+    /// The rule key this pattern is written with
+    pub fn key(&self) -> &'static str {
+        match &self.pattern_type {
+            PatternType::Simple(_) => "pattern",
+            PatternType::Either(_) => "pattern-either",
+            PatternType::Inside(_) => "pattern-inside",
+            PatternType::NotInside(_) => "pattern-not-inside",
+            PatternType::Not(_) => "pattern-not",
+            PatternType::Regex(_) => "pattern-regex",
+            PatternType::NotRegex(_) => "pattern-not-regex",
+            PatternType::All(_) => "pattern-all",
+            PatternType::Any(_) => "pattern-any",
+        }
+    }
+
+    /// Convert to the matcher's pattern representation
+    pub fn to_semgrep_pattern(&self) -> astgrep_core::SemgrepPattern {
+        use astgrep_core::PatternType as CorePatternType;
+
+        let convert_all = |patterns: &[Pattern]| patterns.iter().map(Pattern::to_semgrep_pattern).collect();
+        let pattern_type = match &self.pattern_type {
+            PatternType::Simple(pattern) => CorePatternType::Simple(pattern.clone()),
+            PatternType::Either(patterns) => CorePatternType::Either(convert_all(patterns)),
+            PatternType::Inside(inner) => CorePatternType::Inside(Box::new(inner.to_semgrep_pattern())),
+            PatternType::NotInside(inner) => CorePatternType::NotInside(Box::new(inner.to_semgrep_pattern())),
+            PatternType::Not(inner) => CorePatternType::Not(Box::new(inner.to_semgrep_pattern())),
+            PatternType::Regex(regex) => CorePatternType::Regex(regex.clone()),
+            PatternType::NotRegex(regex) => CorePatternType::NotRegex(regex.clone()),
+            PatternType::All(patterns) => CorePatternType::All(convert_all(patterns)),
+            PatternType::Any(patterns) => CorePatternType::Any(convert_all(patterns)),
+        };
+
+        astgrep_core::SemgrepPattern {
+            pattern_type,
+            metavariable_pattern: self.metavariable_pattern.as_ref().map(MetavariablePattern::to_core),
+            conditions: Vec::new(), // TODO: Convert conditions
+            focus: self.focus.clone(),
+        }
+    }
+
+    fn collect_embedded_languages(&self, languages: &mut Vec<Language>) {
+        let language = self.metavariable_pattern.as_ref().and_then(|spec| spec.language.as_deref()).and_then(Language::from_str);
+        if let Some(language) = language {
+            if !languages.contains(&language) {
+                languages.push(language);
+            }
+        }
+        match &self.pattern_type {
+            PatternType::Either(patterns) | PatternType::All(patterns) | PatternType::Any(patterns) => {
+                patterns.iter().for_each(|pattern| pattern.collect_embedded_languages(languages));
+            }
+            PatternType::Inside(inner) | PatternType::NotInside(inner) | PatternType::Not(inner) => inner.collect_embedded_languages(languages),
+            PatternType::Simple(_) | PatternType::Regex(_) | PatternType::NotRegex(_) => {}
+        }
+    }
+
+    /// Literal words one of which must occur for the pattern to match; see [`Rule::anchors`]
+    pub fn anchors(&self) -> Option<Vec<String>> {
+        match &self.pattern_type {
+            PatternType::Simple(pattern) => longest_literal_word(pattern).map(|word| vec![word]),
+            PatternType::Inside(inner) => inner.anchors(),
+            PatternType::Either(patterns) | PatternType::Any(patterns) => {
+                patterns.iter().map(Pattern::anchors).collect::<Option<Vec<_>>>().map(|anchors| anchors.concat())
+            }
+            PatternType::All(patterns) => patterns.iter().find_map(Pattern::anchors),
+            PatternType::NotInside(_) | PatternType::Not(_) | PatternType::Regex(_) | PatternType::NotRegex(_) => None,
+        }
+    }
+
     /// Get the pattern string for simple patterns
     pub fn get_pattern_string(&self) -> Option<&String> {
         match &self.pattern_type {
@@ -251,6 +448,57 @@ impl Pattern {
         self.focus = Some(focus_vars);
         self
     }
+
+    /// Replace typed metavariables `($X : Type)` in the pattern strings with `$X` and a
+    /// `metavariable-type` condition on `$X`, in this pattern and the patterns it nests
+    pub fn expand_typed_metavariables(mut self) -> Self {
+        let expand_all = |patterns: Vec<Pattern>| patterns.into_iter().map(Pattern::expand_typed_metavariables).collect();
+        self.pattern_type = match self.pattern_type {
+            PatternType::Simple(pattern) => {
+                let (pattern, typed) = split_typed_metavariables(&pattern);
+                self.conditions.extend(typed.into_iter().map(Condition::MetavariableType));
+                PatternType::Simple(pattern)
+            }
+            PatternType::Either(patterns) => PatternType::Either(expand_all(patterns)),
+            PatternType::All(patterns) => PatternType::All(expand_all(patterns)),
+            PatternType::Any(patterns) => PatternType::Any(expand_all(patterns)),
+            PatternType::Inside(inner) => PatternType::Inside(Box::new(inner.expand_typed_metavariables())),
+            PatternType::NotInside(inner) => PatternType::NotInside(Box::new(inner.expand_typed_metavariables())),
+            PatternType::Not(inner) => PatternType::Not(Box::new(inner.expand_typed_metavariables())),
+            other @ (PatternType::Regex(_) | PatternType::NotRegex(_)) => other,
+        };
+        self
+    }
+}
+
+/// Split the typed metavariables `($X : Type)` out of a pattern string: the pattern with
+/// each replaced by its metavariable, and the declared types
+fn split_typed_metavariables(pattern: &str) -> (String, Vec<MetavariableType>) {
+    static TYPED: OnceLock<regex::Regex> = OnceLock::new();
+    let typed = TYPED.get_or_init(|| {
+        regex::Regex::new(r"\(\s*(\$[A-Z_][A-Z0-9_]*)\s*:\s*([A-Za-z_][\w.]*(?:<[^()]*>)?(?:\[\])*)\s*\)").unwrap()
+    });
+    let declared = typed
+        .captures_iter(pattern)
+        .map(|cap| MetavariableType::new(cap[1].to_string(), vec![cap[2].to_string()]))
+        .collect();
+    (typed.replace_all(pattern, "$1").into_owned(), declared)
+}
+
+/// Longest identifier of at least three characters in a pattern that is not a metavariable
+fn longest_literal_word(pattern: &str) -> Option<String> {
+    let mut best: Option<&str> = None;
+    let mut rest = pattern;
+    while let Some(start) = rest.find(|c: char| c.is_alphanumeric() || c == '_') {
+        let end = rest[start..].find(|c: char| !(c.is_alphanumeric() || c == '_')).map_or(rest.len(), |n| start + n);
+        let word = &rest[start..end];
+        let metavariable = rest[..start].ends_with('$');
+        if !metavariable && word.chars().count() >= 3 && best.is_none_or(|b| word.len() > b.len()) {
+            best = Some(word);
+        }
+        rest = &rest[end..];
+    }
+    best.map(str::to_lowercase)
 }
 
 /// Fix regex specification (Semgrep compatible)
@@ -267,6 +515,33 @@ pub struct PathsFilter {
     pub excludes: Vec<String>,
 }
 
+/// Code snippet embedded in a rule that documents what the rule does or does not match
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RuleExample {
+    pub code: String,
+    /// Language of the snippet; `None` means the rule's first language
+    pub language: Option<Language>,
+    /// Whether the rule must match the snippet (positive) or must not (negative)
+    pub should_match: bool,
+}
+
+impl RuleExample {
+    /// Example the rule must match
+    pub fn positive(code: String) -> Self {
+        Self { code, language: None, should_match: true }
+    }
+
+    /// Example the rule must not match
+    pub fn negative(code: String) -> Self {
+        Self { code, language: None, should_match: false }
+    }
+
+    pub fn with_language(mut self, language: Language) -> Self {
+        self.language = Some(language);
+        self
+    }
+}
+
 /// Metavariable pattern specification
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MetavariablePattern {
@@ -276,6 +551,9 @@ pub struct MetavariablePattern {
     pub type_constraint: Option<String>,
     pub name_constraint: Option<String>, // metavariable-name support
     pub analysis: Option<MetavariableAnalysis>, // metavariable-analysis support
+    /// Language to parse the bound code in, such as `sql` for a query string; `generic`
+    /// matches its text
+    pub language: Option<String>,
 }
 
 impl MetavariablePattern {
@@ -288,6 +566,7 @@ impl MetavariablePattern {
             type_constraint: None,
             name_constraint: None,
             analysis: None,
+            language: None,
         }
     }
 
@@ -307,6 +586,25 @@ impl MetavariablePattern {
         self.type_constraint = Some(type_constraint);
         self
     }
+
+    /// Parse the bound code in another language
+    pub fn with_language(mut self, language: String) -> Self {
+        self.language = Some(language);
+        self
+    }
+
+    /// Convert to the matcher's representation
+    pub fn to_core(&self) -> astgrep_core::MetavariablePattern {
+        astgrep_core::MetavariablePattern {
+            metavariable: self.metavariable.clone(),
+            patterns: self.patterns.clone(),
+            regex: self.regex.clone(),
+            type_constraint: self.type_constraint.clone(),
+            name_constraint: self.name_constraint.clone(),
+            analysis: self.analysis.clone(),
+            language: self.language.clone(),
+        }
+    }
 }
 
 /// Condition for pattern matching
@@ -316,6 +614,8 @@ pub enum Condition {
     MetavariableComparison(MetavariableComparison),
     MetavariableName(MetavariableName),
     MetavariableAnalysis(MetavariableAnalysisCondition),
+    MetavariableType(MetavariableType),
+    MetavariableNumeric(MetavariableNumeric),
     NodeType(String),
     NodeAttribute(String, String),
     Custom(String),
@@ -361,6 +661,37 @@ impl MetavariableName {
     }
 }
 
+/// Metavariable type constraint (e.g. the receiver of `$RT.exec(...)` must be a `Runtime`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetavariableType {
+    pub metavariable: String,
+    pub types: Vec<String>,
+}
+
+impl MetavariableType {
+    pub fn new(metavariable: String, types: Vec<String>) -> Self {
+        Self { metavariable, types }
+    }
+}
+
+/// Numeric constraint on the literal bound to a metavariable (e.g. `$MS > 5min`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetavariableNumeric {
+    pub metavariable: String,
+    pub constraint: NumericConstraint,
+}
+
+impl MetavariableNumeric {
+    pub fn new(metavariable: String, constraint: NumericConstraint) -> Self {
+        Self { metavariable, constraint }
+    }
+
+    /// Whether the text bound to the metavariable satisfies the constraint
+    pub fn holds(&self, value: &str, language: Language) -> bool {
+        self.constraint.holds(value, language)
+    }
+}
+
 /// Metavariable analysis condition
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MetavariableAnalysisCondition {
@@ -424,6 +755,15 @@ pub struct RuleContext {
     pub language: Language,
     pub source_code: String,
     pub custom_data: HashMap<String, String>,
+    /// Checked between rules; once cancelled, remaining rules are skipped
+    pub cancellation: CancellationToken,
+    /// Constants assigned in `source_code`, collected on first use and shared by all rules
+    constants: OnceLock<ConstantAnalyzer>,
+    /// Imports of `source_code`, collected on first use and shared by all rules
+    import_aliases: OnceLock<ImportAliases>,
+    /// `source_code` with single-assignment variables propagated, computed on first use;
+    /// None when no use is replaced
+    propagated_source: OnceLock<Option<PropagatedSource>>,
 }
 
 impl RuleContext {
@@ -434,9 +774,19 @@ impl RuleContext {
             language,
             source_code,
             custom_data: HashMap::new(),
+            cancellation: CancellationToken::new(),
+            constants: OnceLock::new(),
+            import_aliases: OnceLock::new(),
+            propagated_source: OnceLock::new(),
         }
     }
 
+    /// Share a cancellation token with the caller driving the analysis
+    pub fn with_cancellation(mut self, cancellation: CancellationToken) -> Self {
+        self.cancellation = cancellation;
+        self
+    }
+
     /// Add custom data
     pub fn add_data(mut self, key: String, value: String) -> Self {
         self.custom_data.insert(key, value);
@@ -447,6 +797,27 @@ impl RuleContext {
     pub fn get_data(&self, key: &str) -> Option<&String> {
         self.custom_data.get(key)
     }
+
+    /// The constants of the file, collected once per context
+    pub fn constants(&self) -> &ConstantAnalyzer {
+        self.constants.get_or_init(|| ConstantAnalyzer::from_source(&self.source_code, self.language))
+    }
+
+    /// The import aliases of the file, collected once per context
+    pub fn import_aliases(&self) -> &ImportAliases {
+        self.import_aliases.get_or_init(|| ImportAliases::from_source(&self.source_code, self.language))
+    }
+
+    /// The file with uses of single-assignment variables replaced by their expressions,
+    /// computed once per context; None when nothing would be replaced
+    pub fn propagated_source(&self) -> Option<&PropagatedSource> {
+        self.propagated_source
+            .get_or_init(|| {
+                let propagated = SymbolicAssignments::from_source(&self.source_code, self.language).propagate(&self.source_code);
+                (!propagated.is_unchanged()).then_some(propagated)
+            })
+            .as_ref()
+    }
 }
 
 /// Rule execution result
@@ -456,6 +827,8 @@ pub struct RuleResult {
     pub findings: Vec<Finding>,
     pub execution_time_ms: u64,
     pub error: Option<String>,
+    /// Engine the rule asked for when it ran on a fallback engine instead
+    pub downgraded_from: Option<MatchEngine>,
 }
 
 impl RuleResult {
@@ -466,6 +839,7 @@ impl RuleResult {
             findings,
             execution_time_ms,
             error: None,
+            downgraded_from: None,
         }
     }
 
@@ -476,6 +850,7 @@ impl RuleResult {
             findings: Vec::new(),
             execution_time_ms,
             error: Some(error),
+            downgraded_from: None,
         }
     }
 
@@ -563,6 +938,27 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_expand_typed_metavariables() {
+        let pattern = Pattern::either(vec![
+            Pattern::simple("foo(($X : String), ( $Y:List<Map<String, Integer>>[] ), $Z)".to_string()),
+            Pattern::simple("bar($X)".to_string()),
+        ])
+        .expand_typed_metavariables();
+
+        let PatternType::Either(patterns) = &pattern.pattern_type else { panic!("Expected Either pattern type") };
+        assert_eq!(patterns[0].get_pattern_string().unwrap(), "foo($X, $Y, $Z)");
+        let types: Vec<(String, Vec<String>)> = patterns[0].conditions.iter().map(|c| match c {
+            Condition::MetavariableType(t) => (t.metavariable.clone(), t.types.clone()),
+            other => panic!("unexpected condition: {:?}", other),
+        }).collect();
+        assert_eq!(types, vec![
+            ("$X".to_string(), vec!["String".to_string()]),
+            ("$Y".to_string(), vec!["List<Map<String, Integer>>[]".to_string()]),
+        ]);
+        assert!(patterns[1].conditions.is_empty());
+    }
+
     #[test]
     fn test_pattern_not_regex() {
         let pattern = Pattern::not_regex("test_.*".to_string());
@@ -613,6 +1009,26 @@ mod tests {
         assert!(dataflow.must_flow);
     }
 
+    #[test]
+    fn test_rule_escalation() {
+        let rule = Rule::new(
+            "sqli".to_string(),
+            "SQL Injection".to_string(),
+            "Query built from input".to_string(),
+            Severity::Warning,
+            Confidence::Medium,
+            vec![Language::Java],
+        );
+        assert!(rule.escalation().is_none());
+
+        let rule = rule.add_metadata("escalate_severity".to_string(), "CRITICAL".to_string());
+        assert_eq!(rule.escalation(), Some((Severity::Critical, Confidence::Medium)));
+
+        // Escalation never lowers the base level
+        let rule = rule.add_metadata("escalate_confidence".to_string(), "LOW".to_string());
+        assert_eq!(rule.escalation(), Some((Severity::Critical, Confidence::Medium)));
+    }
+
     #[test]
     fn test_rule_context() {
         let context = RuleContext::new(
@@ -627,6 +1043,21 @@ mod tests {
         assert_eq!(context.get_data("project"), Some(&"my-project".to_string()));
     }
 
+    #[test]
+    fn test_rule_anchors() {
+        let rule = Rule::new("r".into(), "r".into(), "r".into(), Severity::Error, Confidence::High, vec![Language::Java])
+            .add_pattern(Pattern::either(vec![
+                Pattern::simple("Runtime.getRuntime().exec($CMD)".to_string()),
+                Pattern::all(vec![Pattern::not(Pattern::simple("safe($X)".to_string())), Pattern::simple("$OBJ.executeQuery($Q)".to_string())]),
+            ]));
+        assert_eq!(rule.anchors(), Some(vec!["getruntime".to_string(), "executequery".to_string()]));
+
+        let bare = rule.clone().add_pattern(Pattern::simple("$F($X)".to_string()));
+        assert_eq!(bare.anchors(), None);
+        let regex = Rule { patterns: vec![Pattern::regex("exec\\(".to_string())], ..rule };
+        assert_eq!(regex.anchors(), None);
+    }
+
     #[test]
     fn test_rule_result() {
         let success_result = RuleResult::success(
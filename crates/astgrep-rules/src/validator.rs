@@ -2,10 +2,54 @@
 //!
 //! This module provides functionality to validate rules for correctness and consistency.
 
+use crate::pack::{version_at_least, ENGINE_VERSION};
 use crate::types::*;
 use astgrep_core::{AnalysisError, Result};
 use std::collections::{HashMap, HashSet};
 
+/// Rule features this engine implements, as named in a rule's `requires:` list
+pub const SUPPORTED_CAPABILITIES: &[&str] = &[
+    "pattern",
+    "pattern-either",
+    "pattern-inside",
+    "pattern-not",
+    "pattern-not-inside",
+    "pattern-regex",
+    "pattern-not-regex",
+    "metavariable-regex",
+    "metavariable-comparison",
+    "metavariable-pattern",
+    "metavariable-analysis",
+    "metavariable-type",
+    "metavariable-numeric",
+    "metavariable-name",
+    "focus",
+    "focus-metavariable",
+    "pattern-all",
+    "pattern-any",
+    "dataflow",
+    "fix",
+    "fix-regex",
+    "paths",
+    "examples",
+    "escalate_severity",
+    "escalate_confidence",
+    "sql_statement_boundary",
+    "engine",
+    "typed-metavariable",
+    "deep-expression",
+    "constant_propagation",
+    "commutative_matching",
+    "associative_matching",
+    "ast_matching",
+    "import_aliasing",
+    "symbolic_propagation",
+];
+
+/// Rule features this engine knows of but does not implement; rules requiring them are
+/// skipped rather than misread
+pub const UNSUPPORTED_CAPABILITIES: &[&str] = &["taint-labels", "ts-query"];
+
 /// Rule validator
 pub struct RuleValidator {
     strict_validation: bool,
@@ -69,6 +113,68 @@ impl RuleValidator {
         Ok(())
     }
 
+    /// Check that the running engine provides everything the rule declares via
+    /// `min_version:` and `requires:`. Such rules are well-formed, so this is kept
+    /// separate from `validate_rule` and the error explains what is missing.
+    pub fn check_capabilities(&self, rule: &Rule) -> Result<()> {
+        if let Some(min_version) = rule.get_metadata("min_version") {
+            if !version_at_least(ENGINE_VERSION, min_version) {
+                return Err(AnalysisError::rule_validation_error(format!(
+                    "Rule '{}' requires engine version {} (running {})",
+                    rule.id, min_version, ENGINE_VERSION
+                )));
+            }
+        }
+
+        if let Some(requires) = rule.get_metadata("requires") {
+            let missing: Vec<&str> = requires
+                .split(',')
+                .map(|c| c.trim())
+                .filter(|c| !c.is_empty() && !SUPPORTED_CAPABILITIES.contains(c))
+                .collect();
+            if !missing.is_empty() {
+                let (known, unknown): (Vec<&str>, Vec<&str>) = missing.iter().partition(|c| UNSUPPORTED_CAPABILITIES.contains(c));
+                let mut reasons = Vec::new();
+                if !known.is_empty() {
+                    reasons.push(format!("unsupported capabilities: {}", known.join(", ")));
+                }
+                if !unknown.is_empty() {
+                    reasons.push(format!("unknown capabilities: {}", unknown.join(", ")));
+                }
+                return Err(AnalysisError::rule_validation_error(format!(
+                    "Rule '{}' requires {}",
+                    rule.id,
+                    reasons.join("; ")
+                )));
+            }
+        }
+
+        if let Some(hint) = rule.engine_hint() {
+            if hint.engine == MatchEngine::Regex && !rule.patterns.iter().all(Pattern::is_regex_only) {
+                return Err(AnalysisError::rule_validation_error(format!(
+                    "Rule '{}' selects the regex engine but has non-regex patterns",
+                    rule.id
+                )));
+            }
+            let unavailable: Vec<&str> = rule
+                .languages
+                .iter()
+                .filter(|language| !hint.engine.supports(**language))
+                .map(|language| language.as_str())
+                .collect();
+            if hint.required && !unavailable.is_empty() {
+                return Err(AnalysisError::rule_validation_error(format!(
+                    "Rule '{}' requires the {} engine, which is unavailable for: {}",
+                    rule.id,
+                    hint.engine,
+                    unavailable.join(", ")
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
     /// Validate basic rule fields
     fn validate_basic_fields(&self, rule: &Rule) -> Result<()> {
         // Validate ID
@@ -165,7 +271,8 @@ impl RuleValidator {
                     }
                 }
 
-                if !metavar.is_empty() {
+                // The anonymous `$_` is exempt from naming rules
+                if !metavar.is_empty() && metavar != "_" {
                     metavars.insert(metavar);
                 }
             }
@@ -220,6 +327,15 @@ impl RuleValidator {
             }
         }
 
+        if let Some(ref language) = metavar_pattern.language {
+            if language != "generic" && astgrep_core::Language::from_str(language).is_none() {
+                return Err(AnalysisError::rule_validation_error(format!(
+                    "Pattern {} metavariable pattern language '{}' is not supported",
+                    pattern_index, language
+                )));
+            }
+        }
+
         Ok(())
     }
 
@@ -292,6 +408,34 @@ impl RuleValidator {
                     )));
                 }
             }
+            Condition::MetavariableType(metavar_type) => {
+                if metavar_type.metavariable.is_empty() {
+                    return Err(AnalysisError::rule_validation_error(format!(
+                        "Pattern {} condition {} metavariable cannot be empty",
+                        pattern_index, condition_index
+                    )));
+                }
+                if metavar_type.types.iter().all(|t| t.is_empty()) {
+                    return Err(AnalysisError::rule_validation_error(format!(
+                        "Pattern {} condition {} type cannot be empty",
+                        pattern_index, condition_index
+                    )));
+                }
+            }
+            Condition::MetavariableNumeric(metavar_numeric) => {
+                if metavar_numeric.metavariable.is_empty() {
+                    return Err(AnalysisError::rule_validation_error(format!(
+                        "Pattern {} condition {} metavariable cannot be empty",
+                        pattern_index, condition_index
+                    )));
+                }
+                if !metavar_numeric.constraint.bound.is_finite() {
+                    return Err(AnalysisError::rule_validation_error(format!(
+                        "Pattern {} condition {} numeric bound must be finite",
+                        pattern_index, condition_index
+                    )));
+                }
+            }
             Condition::MetavariableAnalysis(metavar_analysis) => {
                 // Validate metavariable analysis constraint
                 if metavar_analysis.metavariable.is_empty() {
@@ -520,6 +664,29 @@ mod tests {
         assert!(validator.validate_rule(&rule).is_err());
     }
 
+    #[test]
+    fn test_strict_validation_accepts_anonymous_metavariable() {
+        let mut rule = create_valid_rule();
+        rule.patterns = vec![Pattern::simple("$_.equals($X)".to_string())];
+        assert!(RuleValidator::strict().validate_rule(&rule).is_ok());
+
+        rule.patterns = vec![Pattern::simple("$x.equals($X)".to_string())];
+        assert!(RuleValidator::strict().validate_rule(&rule).is_err());
+    }
+
+    #[test]
+    fn test_validate_metavariable_pattern_language() {
+        let validator = RuleValidator::new();
+        let mut rule = create_valid_rule();
+
+        for (language, valid) in [("sql", true), ("generic", true), ("cobol", false)] {
+            let metavar_pattern = MetavariablePattern::new("$Q".to_string(), vec!["FROM users".to_string()])
+                .with_language(language.to_string());
+            rule.patterns = vec![Pattern::with_metavariable("execute($Q)".to_string(), metavar_pattern)];
+            assert_eq!(validator.validate_rule(&rule).is_ok(), valid, "{}", language);
+        }
+    }
+
     #[test]
     fn test_validate_duplicate_rule_ids() {
         let validator = RuleValidator::new();
@@ -589,6 +756,63 @@ mod tests {
         assert!(validator.validate_rule(&rule).is_ok());
     }
 
-}
 
+    #[test]
+    fn test_check_capabilities() {
+        let validator = RuleValidator::new();
 
+        let mut rule = create_valid_rule();
+        assert!(validator.check_capabilities(&rule).is_ok());
+
+        rule.metadata.insert("requires".to_string(), "pattern-inside,dataflow".to_string());
+        assert!(validator.check_capabilities(&rule).is_ok());
+
+        rule.metadata.insert("requires".to_string(), "ts-query".to_string());
+        let err = validator.check_capabilities(&rule).unwrap_err().to_string();
+        assert!(err.contains("unsupported capabilities: ts-query"), "{}", err);
+
+        rule.metadata.insert("requires".to_string(), "taint-labels,pattern-insde".to_string());
+        let err = validator.check_capabilities(&rule).unwrap_err().to_string();
+        assert!(err.contains("unsupported capabilities: taint-labels; unknown capabilities: pattern-insde"), "{}", err);
+        // Unsupported capabilities are not a structural problem
+        assert!(validator.validate_rule(&rule).is_ok());
+
+        let mut rule = create_valid_rule();
+        rule.metadata.insert("min_version".to_string(), "999.0".to_string());
+        assert!(validator.check_capabilities(&rule).is_err());
+    }
+
+    #[test]
+    fn test_every_parsed_key_is_a_capability() {
+        use crate::parser::{OPTION_KEYS, PATTERN_KIND_KEYS, PATTERN_MODIFIER_KEYS, RULE_PATTERN_KEYS};
+        let keys = RULE_PATTERN_KEYS.iter().chain(PATTERN_KIND_KEYS).chain(PATTERN_MODIFIER_KEYS).chain(OPTION_KEYS);
+        for key in keys.filter(|key| **key != "patterns") {
+            assert!(SUPPORTED_CAPABILITIES.contains(key), "{} has no capability", key);
+        }
+    }
+
+    #[test]
+    fn test_check_capabilities_engine_hint() {
+        let validator = RuleValidator::new();
+        let mut rule = Rule::new(
+            "engine-rule".to_string(),
+            "Engine rule".to_string(),
+            "Uses an engine hint".to_string(),
+            Severity::Warning,
+            Confidence::Medium,
+            vec![Language::Python, Language::Ruby],
+        )
+        .add_pattern(Pattern::simple("eval($X)".to_string()));
+
+        rule.metadata.insert(ENGINE_METADATA_KEY.to_string(), "tree-sitter".to_string());
+        rule.metadata.insert(ENGINE_REQUIRED_METADATA_KEY.to_string(), "false".to_string());
+        assert!(validator.check_capabilities(&rule).is_ok());
+
+        rule.metadata.insert(ENGINE_REQUIRED_METADATA_KEY.to_string(), "true".to_string());
+        let err = validator.check_capabilities(&rule).unwrap_err().to_string();
+        assert!(err.contains("ruby"), "{}", err);
+
+        rule.metadata.insert(ENGINE_METADATA_KEY.to_string(), "regex".to_string());
+        assert!(validator.check_capabilities(&rule).is_err());
+    }
+}
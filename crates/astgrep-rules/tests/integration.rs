@@ -35,6 +35,7 @@ fn test_complete_rule_execution() {
         fix: Some("Use prepared statements".to_string()),
         fix_regex: None,
         paths: None,
+        examples: Vec::new(),
         metadata: std::collections::HashMap::new(),
         enabled: true,
     };
@@ -88,6 +89,7 @@ fn test_basic_rule_execution() {
         fix: Some("Use prepared statements".to_string()),
         fix_regex: None,
         paths: None,
+        examples: Vec::new(),
         metadata: std::collections::HashMap::new(),
         enabled: true,
     };
@@ -136,6 +138,7 @@ fn test_rule_validation() {
         fix: None,
         fix_regex: None,
         paths: None,
+        examples: Vec::new(),
         metadata: std::collections::HashMap::new(),
         enabled: true,
     };
@@ -179,6 +182,7 @@ fn test_rule_engine_configuration() {
         fix: None,
         fix_regex: None,
         paths: None,
+        examples: Vec::new(),
         metadata: std::collections::HashMap::new(),
         enabled: true,
     };
@@ -225,6 +229,7 @@ fn test_simple_performance() {
         fix: None,
         fix_regex: None,
         paths: None,
+        examples: Vec::new(),
         metadata: std::collections::HashMap::new(),
         enabled: true,
     };
@@ -277,6 +282,7 @@ fn test_error_handling() {
         fix: None,
         fix_regex: None,
         paths: None,
+        examples: Vec::new(),
         metadata: std::collections::HashMap::new(),
         enabled: true,
     };
@@ -321,6 +327,7 @@ fn test_multiple_rules() {
             fix: None,
             fix_regex: None,
             paths: None,
+            examples: Vec::new(),
             metadata: std::collections::HashMap::new(),
             enabled: true,
         };
@@ -224,7 +224,7 @@ fn get_available_endpoints() -> Vec<EndpointInfo> {
         EndpointInfo {
             method: "POST".to_string(),
             path: "/api/v1/analyze/archive".to_string(),
-            description: "Analyze uploaded archive".to_string(),
+            description: "Start a background analysis of an uploaded archive".to_string(),
         },
         EndpointInfo {
             method: "GET".to_string(),
@@ -236,6 +236,26 @@ fn get_available_endpoints() -> Vec<EndpointInfo> {
             path: "/api/v1/jobs/{id}".to_string(),
             description: "Get job status".to_string(),
         },
+        EndpointInfo {
+            method: "POST".to_string(),
+            path: "/api/v1/jobs/{id}/cancel".to_string(),
+            description: "Cancel a running job".to_string(),
+        },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/v1/jobs/{id}/events".to_string(),
+            description: "Stream job progress (server-sent events)".to_string(),
+        },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/v1/jobs/{id}/results".to_string(),
+            description: "Get the results of a background job".to_string(),
+        },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/v1/jobs/events".to_string(),
+            description: "Stream progress of all jobs (server-sent events)".to_string(),
+        },
         EndpointInfo {
             method: "GET".to_string(),
             path: "/api/v1/rules".to_string(),
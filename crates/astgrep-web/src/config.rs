@@ -47,6 +47,9 @@ pub struct WebConfig {
     
     /// Logging configuration
     pub logging: LoggingConfig,
+
+    /// Default redaction of matched text in responses (mask, partial[:P:S], hash)
+    pub redact: Option<String>,
     
     /// Database configuration (optional)
     #[cfg(feature = "database")]
@@ -149,6 +152,7 @@ impl Default for WebConfig {
             rate_limit: RateLimitConfig::default(),
             cors: CorsConfig::default(),
             logging: LoggingConfig::default(),
+            redact: None,
             #[cfg(feature = "database")]
             database: None,
             #[cfg(feature = "metrics")]
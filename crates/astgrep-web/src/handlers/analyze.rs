@@ -2,6 +2,7 @@
 
 use axum::{
     extract::{Multipart, State},
+    http::StatusCode,
     response::Json,
 };
 use base64::{engine::general_purpose, Engine as _};
@@ -17,10 +18,10 @@ use crate::{
         MetavariableBinding, ConstraintMatch, TaintFlow, DataFlowInfo, SymbolInfo,
     },
     WebConfig, WebError, WebResult,
-    handlers::metrics::get_metrics_collector,
+    handlers::{jobs, metrics::get_metrics_collector},
 };
-use astgrep_core::{Language, Severity, Confidence};
-use astgrep_rules::{RuleEngine, RuleContext};
+use astgrep_core::{CancellationToken, Language, Redaction};
+use astgrep_rules::{RuleEngine, RuleContext, RuleRepository};
 
 /// Analyze code snippet
 pub async fn analyze_code(
@@ -409,11 +410,13 @@ pub async fn analyze_file_flexible(
 }
 
 
-/// Analyze uploaded archive
+/// Analyze uploaded archive. The analysis runs in the background: the response carries
+/// the job id with status `running`, and the job is followed through `/jobs/:id/events`,
+/// cancelled through `/jobs/:id/cancel` and its findings fetched from `/jobs/:id/results`.
 pub async fn analyze_archive(
     State(config): State<Arc<WebConfig>>,
     Json(request): Json<AnalyzeArchiveRequest>,
-) -> WebResult<Json<AnalysisResponse>> {
+) -> WebResult<(StatusCode, Json<AnalysisResponse>)> {
     info!("Analyzing archive, format: {}", request.format);
 
     // Validate request
@@ -431,23 +434,60 @@ pub async fn analyze_archive(
         .decode(&request.archive)
         .map_err(|e| WebError::bad_request(format!("Invalid base64 content: {}", e)))?;
 
-    // Generate job ID
-    let job_id = Uuid::new_v4();
+    // Track the analysis as a job so it can be followed and cancelled by its id
+    let created_at = chrono::Utc::now();
+    let mut metadata = std::collections::HashMap::new();
+    metadata.insert("format".to_string(), serde_json::Value::String(request.format.clone()));
+    let job_id = jobs::create_analysis_job("archive_analysis".to_string(), metadata).await?;
+    let cancellation = jobs::register_cancellation(job_id).await;
+    jobs::update_job_status(job_id, JobStatus::Running, 0, None).await?;
 
-    // Extract and analyze archive (simplified implementation)
-    let results = perform_archive_analysis(&archive_data, &request, &config).await?;
+    tokio::spawn(run_archive_job(job_id, archive_data, request, config, cancellation));
 
     let response = AnalysisResponse {
         job_id,
-        status: JobStatus::Completed,
-        results: Some(results),
+        status: JobStatus::Running,
+        results: None,
         error: None,
-        created_at: chrono::Utc::now(),
-        completed_at: Some(chrono::Utc::now()),
+        created_at,
+        completed_at: None,
     };
+    info!("Archive analysis started, job_id: {}", job_id);
+    Ok((StatusCode::ACCEPTED, Json(response)))
+}
 
-    info!("Archive analysis completed, job_id: {}", job_id);
-    Ok(Json(response))
+/// Extract and analyze an archive as job `job_id`, recording its final status and results
+async fn run_archive_job(
+    job_id: Uuid,
+    archive_data: Vec<u8>,
+    request: AnalyzeArchiveRequest,
+    config: Arc<WebConfig>,
+    cancellation: CancellationToken,
+) {
+    let outcome = perform_archive_analysis(job_id, &archive_data, &request, &config, &cancellation).await;
+    jobs::release_cancellation(job_id).await;
+
+    // A cancelled job keeps its status, which the cancel endpoint has already recorded,
+    // and the results gathered before the cancel request
+    let recorded = match outcome {
+        Ok(results) => {
+            jobs::store_results(job_id, results).await;
+            if cancellation.is_cancelled() {
+                Ok(())
+            } else {
+                jobs::update_job_status(job_id, JobStatus::Completed, 100, None).await
+            }
+        }
+        Err(_) if cancellation.is_cancelled() => Ok(()),
+        Err(e) => {
+            warn!("Archive analysis failed, job_id: {}: {}", job_id, e);
+            jobs::update_job_status(job_id, JobStatus::Failed, 0, Some(e.to_string())).await
+        }
+    };
+    if let Err(e) = recorded {
+        warn!("Cannot record the status of job {}: {}", job_id, e);
+    }
+    info!("Archive analysis finished, job_id: {}", job_id);
 }
 
 /// Analyze multipart file upload
@@ -530,6 +570,15 @@ pub async fn analyze_multipart(
 async fn perform_code_analysis(
     request: &AnalyzeRequest,
     config: &WebConfig,
+) -> WebResult<AnalysisResults> {
+    perform_code_analysis_cancellable(request, config, &CancellationToken::new()).await
+}
+
+/// Perform code analysis, skipping the remaining rules once `cancellation` is triggered
+async fn perform_code_analysis_cancellable(
+    request: &AnalyzeRequest,
+    config: &WebConfig,
+    cancellation: &CancellationToken,
 ) -> WebResult<AnalysisResults> {
     use std::collections::HashMap;
     use astgrep_parser::ParserFactory;
@@ -563,23 +612,31 @@ async fn perform_code_analysis(
             }
             eprintln!("🔍 Loaded {} rules from YAML", rule_engine.rule_count());
         } else if let Some(rule_ids) = rules_value.as_array() {
-            // Array of rule IDs
-            for rule_id in rule_ids {
-                if let Some(id_str) = rule_id.as_str() {
-                    // Load specific rule by ID (placeholder - implement as needed)
-                    warn!("Loading rule by ID not yet implemented: {}", id_str);
+            // Array of rule IDs, resolved against the shared rule repository
+            rule_engine = with_shared_rules(config, |repository| {
+                let mut engine = RuleEngine::new();
+                for id_str in rule_ids.iter().filter_map(|id| id.as_str()) {
+                    match repository.rule(id_str) {
+                        Some(rule) => {
+                            if let Err(e) = engine.add_rule(rule.clone()) {
+                                warn!("Failed to add rule {}: {}", id_str, e);
+                            }
+                        }
+                        None => warn!("Unknown rule id: {}", id_str),
+                    }
                 }
-            }
+                engine
+            });
             // If no rules loaded, use defaults
             if rule_engine.rule_count() == 0 {
-                load_default_rules_for_language(&mut rule_engine, language, config).await?;
+                load_default_rules_for_language(&mut rule_engine, language, config)?;
             }
         } else {
             return Err(WebError::bad_request("Invalid rules format"));
         }
     } else {
         // Load default rules for the language
-        load_default_rules_for_language(&mut rule_engine, language, config).await?;
+        load_default_rules_for_language(&mut rule_engine, language, config)?;
     }
 
     // Create rule context and pass CLI-level equivalent option from request if provided
@@ -587,7 +644,7 @@ async fn perform_code_analysis(
         dummy_path.to_string_lossy().to_string(),
         language,
         request.code.clone(),
-    );
+    ).with_cancellation(cancellation.clone());
     if let Some(ref options) = request.options {
         if let Some(flag) = options.sql_statement_boundary {
             context = context.add_data("sql_statement_boundary".to_string(), flag.to_string());
@@ -690,6 +747,24 @@ async fn perform_code_analysis(
 
     let duration = start_time.elapsed();
 
+    // Hide matched text of sensitive findings before it reaches the response
+    let redact = request.options.as_ref().and_then(|o| o.redact.as_deref()).or(config.redact.as_deref());
+    let default_redaction = match redact {
+        Some(spec) => Redaction::parse(spec).map_err(|e| WebError::bad_request(format!("Invalid redact option: {}", e)))?,
+        None => None,
+    };
+    for finding in &mut findings {
+        let rule = rule_engine.rules().iter().find(|r| r.id == finding.rule_id);
+        let redaction = match rule {
+            Some(rule) => Redaction::for_rule(&rule.metadata, default_redaction),
+            None => default_redaction,
+        };
+        if let (Some(redaction), Some(secret)) = (redaction, astgrep_core::matched_text(&request.code, &finding.location)) {
+            finding.message = redaction.redact_in(&finding.message, secret);
+            finding.fix_suggestion = finding.fix_suggestion.as_ref().map(|fix| redaction.redact_in(fix, secret));
+        }
+    }
+
     // Convert findings to web model format
     let web_findings: Vec<Finding> = findings.into_iter().map(|f| Finding {
         rule_id: f.rule_id,
@@ -785,60 +860,30 @@ fn parse_language(language_str: &str) -> WebResult<Language> {
     }
 }
 
+/// Run `f` against the process-wide rule repository over the configured rules directory,
+/// picking up any rule files added, removed or edited since the last request. Requests share
+/// the read lock; the write lock is only taken to reload changed rules.
+pub(crate) fn with_shared_rules<T>(config: &WebConfig, f: impl FnOnce(&RuleRepository) -> T) -> T {
+    let paths = std::slice::from_ref(&config.rules_directory);
+    {
+        let repository = RuleRepository::global().read().unwrap_or_else(|e| e.into_inner());
+        if repository.contains_paths(paths) && !repository.is_stale() {
+            return f(&repository);
+        }
+    }
+    let mut repository = RuleRepository::global().write().unwrap_or_else(|e| e.into_inner());
+    repository.load_paths(paths);
+    repository.refresh();
+    f(&repository)
+}
+
 /// Load default rules for a specific language
-async fn load_default_rules_for_language(
+fn load_default_rules_for_language(
     rule_engine: &mut RuleEngine,
     language: Language,
     config: &WebConfig,
 ) -> WebResult<()> {
-    use tokio::fs;
-
-    // Construct path to default rules for the language
-    let language_str = match language {
-        Language::Java => "java",
-        Language::JavaScript => "javascript",
-        Language::Python => "python",
-        Language::Sql => "sql",
-        Language::Bash => "bash",
-        Language::Php => "php",
-        Language::CSharp => "csharp",
-        Language::C => "c",
-        Language::Ruby => "ruby",
-        Language::Kotlin => "kotlin",
-        Language::Swift => "swift",
-        Language::Xml => "xml",
-    };
-
-    let rules_path = config.rules_directory.join(format!("{}.yaml", language_str));
-
-    // Try to load language-specific rules
-    if rules_path.exists() {
-        match fs::read_to_string(&rules_path).await {
-            Ok(rules_content) => {
-                if let Err(e) = rule_engine.load_rules_from_yaml(&rules_content) {
-                    warn!("Failed to load rules from {}: {}", rules_path.display(), e);
-                }
-            }
-            Err(e) => {
-                warn!("Failed to read rules file {}: {}", rules_path.display(), e);
-            }
-        }
-    }
-
-    // Also try to load general rules
-    let general_rules_path = config.rules_directory.join("general.yaml");
-    if general_rules_path.exists() {
-        match fs::read_to_string(&general_rules_path).await {
-            Ok(rules_content) => {
-                if let Err(e) = rule_engine.load_rules_from_yaml(&rules_content) {
-                    warn!("Failed to load general rules: {}", e);
-                }
-            }
-            Err(e) => {
-                warn!("Failed to read general rules file: {}", e);
-            }
-        }
-    }
+    *rule_engine = with_shared_rules(config, |repository| repository.engine_for_language(language));
 
     // If no rules were loaded, create some basic default rules
     if rule_engine.rule_count() == 0 {
@@ -1018,9 +1063,11 @@ rules:
 
 /// Perform archive analysis with real extraction and analysis
 async fn perform_archive_analysis(
+    job_id: Uuid,
     archive_data: &[u8],
     request: &AnalyzeArchiveRequest,
     config: &WebConfig,
+    cancellation: &CancellationToken,
 ) -> WebResult<AnalysisResults> {
     use std::collections::HashMap;
     use std::io::Cursor;
@@ -1028,6 +1075,7 @@ async fn perform_archive_analysis(
     let start_time = std::time::Instant::now();
 
     // Extract files from archive
+    jobs::report_progress(job_id, "extracting", 0, 0, 0).await;
     let extracted_files = extract_archive_files(archive_data, &request.format).await?;
 
     if extracted_files.is_empty() {
@@ -1037,14 +1085,22 @@ async fn perform_archive_analysis(
     let mut all_findings = Vec::new();
     let mut files_analyzed = 0;
     let mut total_rules_executed = 0;
+    let files_total = extracted_files.len();
+    jobs::report_progress(job_id, "analyzing", 0, files_total, 0).await;
 
     // Analyze each extracted file
-    for (file_path, file_content) in extracted_files {
+    for (index, (file_path, file_content)) in extracted_files.into_iter().enumerate() {
+        if cancellation.is_cancelled() {
+            info!("Archive analysis cancelled after {} file(s)", files_analyzed);
+            break;
+        }
+
         // Detect language from file extension
         let language = detect_language_from_filename(&file_path);
 
         // Skip unsupported languages
         if language == "text" {
+            jobs::report_progress(job_id, "analyzing", index + 1, files_total, all_findings.len()).await;
             continue;
         }
 
@@ -1057,7 +1113,7 @@ async fn perform_archive_analysis(
         };
 
         // Perform analysis on this file
-        match perform_code_analysis(&file_request, config).await {
+        match perform_code_analysis_cancellable(&file_request, config, cancellation).await {
             Ok(mut results) => {
                 // Update file paths in findings to include archive context
                 for finding in &mut results.findings {
@@ -1073,6 +1129,7 @@ async fn perform_archive_analysis(
                 // Continue with other files instead of failing the entire archive
             }
         }
+        jobs::report_progress(job_id, "analyzing", index + 1, files_total, all_findings.len()).await;
     }
 
     let duration = start_time.elapsed();
@@ -1705,4 +1762,53 @@ var square = number * number;
             .collect();
         assert_eq!(matches.len(), 1, "should return exactly 1 match, got {}", matches.len());
     }
+
+    fn archive_request(format: &str) -> AnalyzeArchiveRequest {
+        AnalyzeArchiveRequest {
+            archive: general_purpose::STANDARD.encode(vec![0u8; 200]),
+            format: format.to_string(),
+            languages: None,
+            rules: None,
+            include_patterns: None,
+            exclude_patterns: None,
+            options: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_archive_analysis_runs_as_background_job() {
+        let config = Arc::new(WebConfig::default());
+        let (status, Json(response)) = analyze_archive(State(config.clone()), Json(archive_request("zip"))).await.unwrap();
+        assert_eq!(status, StatusCode::ACCEPTED);
+        assert_eq!(response.status, JobStatus::Running);
+        assert!(response.results.is_none());
+
+        let mut finished = None;
+        for _ in 0..200 {
+            let Json(job) = jobs::get_job_results(State(config.clone()), axum::extract::Path(response.job_id)).await.unwrap();
+            if job.status.is_finished() {
+                finished = Some(job);
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        }
+        let job = finished.expect("archive job did not finish");
+        assert_eq!(job.status, JobStatus::Completed);
+        assert_eq!(job.results.unwrap().summary.files_analyzed, 2);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_archive_job_keeps_its_status() {
+        let job_id = jobs::create_analysis_job("archive_analysis".to_string(), Default::default()).await.unwrap();
+        let cancellation = jobs::register_cancellation(job_id).await;
+        jobs::update_job_status(job_id, JobStatus::Cancelled, 0, None).await.unwrap();
+        cancellation.cancel();
+
+        // The analysis fails after the cancel request, which must not turn the job into a failure
+        run_archive_job(job_id, vec![0u8; 200], archive_request("rar"), Arc::new(WebConfig::default()), cancellation).await;
+        let config = Arc::new(WebConfig::default());
+        let Json(job) = jobs::get_job_results(State(config), axum::extract::Path(job_id)).await.unwrap();
+        assert_eq!(job.status, JobStatus::Cancelled);
+        assert!(job.error.is_none());
+    }
 }
@@ -2,26 +2,42 @@
 
 use axum::{
     extract::{Path, Query, State},
-    response::Json,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        Json,
+    },
 };
+use futures_util::stream::{self, Stream};
 use serde::Deserialize;
 use std::collections::HashMap;
 use std::sync::Arc;
 use uuid::Uuid;
-use tokio::sync::RwLock;
+use tokio::sync::{broadcast, RwLock};
 use chrono::Utc;
 use std::sync::OnceLock;
+use astgrep_core::CancellationToken;
 
 use crate::{
-    models::{Job, JobStatus},
+    models::{AnalysisResponse, AnalysisResults, Job, JobProgress, JobStatus},
     api::PaginatedResponse,
     WebConfig, WebError, WebResult,
 };
 
+/// Progress events buffered per subscriber before slow subscribers start skipping
+const PROGRESS_EVENT_CAPACITY: usize = 256;
+
 /// In-memory job storage
 #[derive(Debug, Clone)]
 pub struct JobStorage {
     jobs: Arc<RwLock<HashMap<Uuid, Job>>>,
+    /// Cancellation tokens of jobs that are still running
+    cancellations: Arc<RwLock<HashMap<Uuid, CancellationToken>>>,
+    /// Latest progress of each job, sent first to new subscribers
+    progress: Arc<RwLock<HashMap<Uuid, JobProgress>>>,
+    /// Results of jobs that ran in the background, kept until the job is deleted
+    results: Arc<RwLock<HashMap<Uuid, AnalysisResults>>>,
+    /// Progress updates of all jobs
+    events: broadcast::Sender<JobProgress>,
 }
 
 impl JobStorage {
@@ -29,6 +45,10 @@ impl JobStorage {
     pub fn new() -> Self {
         Self {
             jobs: Arc::new(RwLock::new(HashMap::new())),
+            cancellations: Arc::new(RwLock::new(HashMap::new())),
+            progress: Arc::new(RwLock::new(HashMap::new())),
+            results: Arc::new(RwLock::new(HashMap::new())),
+            events: broadcast::channel(PROGRESS_EVENT_CAPACITY).0,
         }
     }
 
@@ -75,6 +95,7 @@ impl JobStorage {
 
     /// Delete a job
     pub async fn delete_job(&self, job_id: Uuid) -> WebResult<bool> {
+        self.results.write().await.remove(&job_id);
         let mut jobs = self.jobs.write().await;
         Ok(jobs.remove(&job_id).is_some())
     }
@@ -84,6 +105,56 @@ impl JobStorage {
         let jobs = self.jobs.read().await;
         jobs.len()
     }
+
+    /// Create the cancellation token a running job polls
+    pub async fn register_cancellation(&self, job_id: Uuid) -> CancellationToken {
+        let token = CancellationToken::new();
+        self.cancellations.write().await.insert(job_id, token.clone());
+        token
+    }
+
+    /// Request cancellation of a running job; false if the job is not running
+    pub async fn cancel(&self, job_id: Uuid) -> bool {
+        match self.cancellations.read().await.get(&job_id) {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Forget a job's cancellation token once it has finished
+    pub async fn release_cancellation(&self, job_id: Uuid) {
+        self.cancellations.write().await.remove(&job_id);
+    }
+
+    /// Record a job's progress and send it to subscribers
+    pub async fn publish_progress(&self, progress: JobProgress) {
+        self.progress.write().await.insert(progress.job_id, progress.clone());
+        // No subscribers is not an error
+        let _ = self.events.send(progress);
+    }
+
+    /// Latest recorded progress of a job
+    pub async fn latest_progress(&self, job_id: Uuid) -> Option<JobProgress> {
+        self.progress.read().await.get(&job_id).cloned()
+    }
+
+    /// Keep the results of a background job for `/jobs/:id/results`
+    pub async fn store_results(&self, job_id: Uuid, results: AnalysisResults) {
+        self.results.write().await.insert(job_id, results);
+    }
+
+    /// Results of a background job, once it has produced them
+    pub async fn results(&self, job_id: Uuid) -> Option<AnalysisResults> {
+        self.results.read().await.get(&job_id).cloned()
+    }
+
+    /// Receive progress updates of all jobs from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<JobProgress> {
+        self.events.subscribe()
+    }
 }
 
 /// Global job storage instance
@@ -257,6 +328,152 @@ pub async fn list_jobs(
     Ok(Json(response))
 }
 
+/// Cancel a running job. The job stops at its next checkpoint and keeps the results
+/// gathered so far.
+pub async fn cancel_job(
+    State(_config): State<Arc<WebConfig>>,
+    Path(job_id): Path<Uuid>,
+) -> WebResult<Json<Job>> {
+    let storage = get_job_storage();
+    let job = storage.get_job(job_id).await
+        .ok_or_else(|| WebError::not_found(format!("Job not found: {}", job_id)))?;
+
+    if job.status.is_finished() {
+        return Err(WebError::conflict(format!("Job {} has already finished", job_id)));
+    }
+
+    storage.cancel(job_id).await;
+    update_job_status(job_id, JobStatus::Cancelled, job.progress, None).await?;
+    tracing::info!("Cancelled job {}", job_id);
+
+    let job = storage.get_job(job_id).await
+        .ok_or_else(|| WebError::not_found(format!("Job not found: {}", job_id)))?;
+    Ok(Json(job))
+}
+
+/// Results of a job that runs in the background, such as an archive analysis. `results`
+/// stays empty until the job has finished; a cancelled job has the results gathered
+/// before the cancel request.
+pub async fn get_job_results(
+    State(_config): State<Arc<WebConfig>>,
+    Path(job_id): Path<Uuid>,
+) -> WebResult<Json<AnalysisResponse>> {
+    let storage = get_job_storage();
+    let job = storage.get_job(job_id).await
+        .ok_or_else(|| WebError::not_found(format!("Job not found: {}", job_id)))?;
+    let results = if job.status.is_finished() { storage.results(job_id).await } else { None };
+
+    Ok(Json(AnalysisResponse {
+        job_id,
+        status: job.status,
+        results,
+        error: job.error,
+        created_at: job.created_at,
+        completed_at: job.completed_at,
+    }))
+}
+
+/// Store the results of a background job
+pub async fn store_results(job_id: Uuid, results: AnalysisResults) {
+    get_job_storage().store_results(job_id, results).await
+}
+
+/// Stream progress events of one job as server-sent events. The current progress is
+/// sent first; the stream ends after the job's final status.
+pub async fn stream_job_events(
+    State(_config): State<Arc<WebConfig>>,
+    Path(job_id): Path<Uuid>,
+) -> WebResult<Sse<impl Stream<Item = Result<Event, axum::Error>>>> {
+    let storage = get_job_storage();
+    // Subscribe before taking the snapshot so no update falls in between
+    let receiver = storage.subscribe();
+    let snapshot = match storage.latest_progress(job_id).await {
+        Some(progress) => progress,
+        None => {
+            let job = get_job_from_storage(job_id).await
+                .ok_or_else(|| WebError::not_found(format!("Job not found: {}", job_id)))?;
+            JobProgress::from_job(&job)
+        }
+    };
+
+    let events = stream::unfold(Some((Some(snapshot), receiver)), move |state| async move {
+        let (pending, mut receiver) = state?;
+        let progress = match pending {
+            Some(progress) => progress,
+            None => next_progress(&mut receiver, Some(job_id)).await?,
+        };
+        let next = (!progress.status.is_finished()).then_some((None, receiver));
+        Some((progress_event(&progress), next))
+    });
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+/// Stream progress events of all jobs as server-sent events, for dashboards
+pub async fn stream_all_job_events(
+    State(_config): State<Arc<WebConfig>>,
+) -> Sse<impl Stream<Item = Result<Event, axum::Error>>> {
+    let receiver = get_job_storage().subscribe();
+    let events = stream::unfold(receiver, |mut receiver| async move {
+        let progress = next_progress(&mut receiver, None).await?;
+        Some((progress_event(&progress), receiver))
+    });
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
+
+/// Next progress update, optionally of one job only; skipped updates of a lagging
+/// subscriber are dropped since every update carries cumulative counters
+async fn next_progress(receiver: &mut broadcast::Receiver<JobProgress>, job_id: Option<Uuid>) -> Option<JobProgress> {
+    loop {
+        match receiver.recv().await {
+            Ok(progress) if job_id.is_none_or(|id| progress.job_id == id) => return Some(progress),
+            Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => return None,
+        }
+    }
+}
+
+fn progress_event(progress: &JobProgress) -> Result<Event, axum::Error> {
+    Event::default().event("progress").json_data(progress)
+}
+
+/// Report file-level progress of a running job
+pub async fn report_progress(job_id: Uuid, stage: &str, files_completed: usize, files_total: usize, findings: usize) {
+    let storage = get_job_storage();
+    let mut job = match storage.get_job(job_id).await {
+        Some(job) => job,
+        None => return,
+    };
+    // 100 is reserved for the final status
+    let percent = (files_completed * 100).checked_div(files_total).map_or(0, |p| p.min(99) as u8);
+    job.progress = percent;
+    let status = job.status.clone();
+    if storage.update_job(job).await.is_err() {
+        return;
+    }
+
+    storage.publish_progress(JobProgress {
+        job_id,
+        status,
+        stage: stage.to_string(),
+        progress: percent,
+        files_total,
+        files_completed,
+        findings,
+        timestamp: Utc::now(),
+    }).await;
+}
+
+/// Create the cancellation token for a job about to run
+pub async fn register_cancellation(job_id: Uuid) -> CancellationToken {
+    get_job_storage().register_cancellation(job_id).await
+}
+
+/// Drop a finished job's cancellation token
+pub async fn release_cancellation(job_id: Uuid) {
+    get_job_storage().release_cancellation(job_id).await
+}
+
 /// Get job from storage
 async fn get_job_from_storage(job_id: Uuid) -> Option<Job> {
     // Prefer persisted job
@@ -342,7 +559,17 @@ pub async fn update_job_status(
             _ => {}
         }
 
+        // Status changes keep the latest file counters
+        let mut progress = storage.latest_progress(job_id).await.unwrap_or_else(|| JobProgress::from_job(&job));
+        progress.status = status.clone();
+        progress.progress = job.progress;
+        progress.timestamp = Utc::now();
+        if status.is_finished() {
+            progress.stage = format!("{:?}", status).to_lowercase();
+        }
+
         storage.update_job(job).await?;
+        storage.publish_progress(progress).await;
         tracing::info!("Updated job {} status to {:?}", job_id, status);
     } else {
         return Err(WebError::not_found(format!("Job not found: {}", job_id)));
@@ -464,4 +691,46 @@ mod tests {
         assert_eq!(completed_job.progress, 100);
         assert!(completed_job.completed_at.is_some());
     }
+
+    #[tokio::test]
+    async fn test_cancel_running_job() {
+        let config = Arc::new(WebConfig::default());
+        let job_id = create_analysis_job("archive_analysis".to_string(), HashMap::new()).await.unwrap();
+        let token = register_cancellation(job_id).await;
+        update_job_status(job_id, JobStatus::Running, 10, None).await.unwrap();
+
+        let job = cancel_job(State(config.clone()), Path(job_id)).await.unwrap().0;
+        assert_eq!(job.status, JobStatus::Cancelled);
+        assert!(job.completed_at.is_some());
+        assert!(token.is_cancelled());
+
+        // A finished job cannot be cancelled again
+        let result = cancel_job(State(config), Path(job_id)).await;
+        assert!(matches!(result, Err(WebError::Conflict { .. })));
+        release_cancellation(job_id).await;
+    }
+
+    #[tokio::test]
+    async fn test_progress_events_reach_subscribers() {
+        let job_id = create_analysis_job("archive_analysis".to_string(), HashMap::new()).await.unwrap();
+        let mut receiver = get_job_storage().subscribe();
+
+        report_progress(job_id, "analyzing", 2, 4, 3).await;
+        let progress = next_progress(&mut receiver, Some(job_id)).await.unwrap();
+        assert_eq!((progress.files_completed, progress.files_total, progress.findings), (2, 4, 3));
+        assert_eq!(progress.progress, 50);
+
+        update_job_status(job_id, JobStatus::Completed, 100, None).await.unwrap();
+        let progress = next_progress(&mut receiver, Some(job_id)).await.unwrap();
+        assert_eq!(progress.status, JobStatus::Completed);
+        assert_eq!(progress.stage, "completed");
+        assert_eq!(progress.files_completed, 2);
+    }
+
+    #[tokio::test]
+    async fn test_stream_job_events_unknown_job() {
+        let config = Arc::new(WebConfig::default());
+        let result = stream_job_events(State(config), Path(Uuid::new_v4())).await;
+        assert!(matches!(result, Err(WebError::NotFound { .. })));
+    }
 }
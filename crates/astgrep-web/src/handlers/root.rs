@@ -41,7 +41,7 @@ pub async fn root() -> WebResult<Html<&'static str>> {
     
     <div class="endpoint">
         <span class="method">POST</span> <span class="path">/api/v1/analyze/archive</span>
-        <p>Analyze uploaded archive (zip, tar)</p>
+        <p>Start a background analysis of an uploaded archive (zip, tar)</p>
     </div>
     
     <div class="endpoint">
@@ -49,6 +49,21 @@ pub async fn root() -> WebResult<Html<&'static str>> {
         <p>Get analysis job status</p>
     </div>
     
+    <div class="endpoint">
+        <span class="method">POST</span> <span class="path">/api/v1/jobs/{id}/cancel</span>
+        <p>Cancel a running analysis job</p>
+    </div>
+    
+    <div class="endpoint">
+        <span class="method">GET</span> <span class="path">/api/v1/jobs/{id}/events</span>
+        <p>Stream job progress as server-sent events</p>
+    </div>
+    
+    <div class="endpoint">
+        <span class="method">GET</span> <span class="path">/api/v1/jobs/{id}/results</span>
+        <p>Get the results of a background analysis job</p>
+    </div>
+    
     <div class="endpoint">
         <span class="method">GET</span> <span class="path">/api/v1/rules</span>
         <p>List available analysis rules</p>
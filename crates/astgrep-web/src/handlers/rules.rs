@@ -8,6 +8,9 @@ use serde::Deserialize;
 use std::collections::HashMap;
 use std::sync::Arc;
 
+use astgrep_rules::Rule;
+
+use super::analyze::with_shared_rules;
 use crate::{
     models::{RuleInfo, ValidateRulesRequest, ValidateRulesResponse, RulePerformanceMetrics},
     WebConfig, WebError, WebResult,
@@ -35,8 +38,8 @@ pub async fn list_rules(
 ) -> WebResult<Json<Vec<RuleInfo>>> {
     tracing::info!("Listing rules with filters: {:?}", params);
     
-    // Load rules from the rules directory
-    let mut rules = load_rules_from_directory(&config.rules_directory).await?;
+    // Rules of the rules directory, as loaded for analysis requests
+    let mut rules = shared_rule_infos(&config);
     
     // Apply filters
     if let Some(language_filter) = &params.language {
@@ -82,7 +85,7 @@ pub async fn get_rule(
 ) -> WebResult<Json<RuleInfo>> {
     tracing::info!("Getting rule: {}", rule_id);
     
-    let rules = load_rules_from_directory(&config.rules_directory).await?;
+    let rules = shared_rule_infos(&config);
     
     let rule = rules
         .into_iter()
@@ -145,238 +148,45 @@ pub async fn validate_rules(
     Ok(Json(response))
 }
 
-/// Load rules from directory (real implementation)
-async fn load_rules_from_directory(
-    rules_dir: &std::path::Path,
-) -> WebResult<Vec<RuleInfo>> {
-    use tokio::fs;
-
-    let mut rules = Vec::new();
-
-    // Check if rules directory exists
-    if !rules_dir.exists() {
-        tracing::warn!("Rules directory does not exist: {}", rules_dir.display());
-        return Ok(get_fallback_rules());
-    }
-
-    // Read directory entries
-    let mut dir_entries = match fs::read_dir(rules_dir).await {
-        Ok(entries) => entries,
-        Err(e) => {
-            tracing::error!("Failed to read rules directory: {}", e);
-            return Ok(get_fallback_rules());
-        }
-    };
-
-    // Process each file in the directory
-    while let Some(entry) = dir_entries.next_entry().await.map_err(|e| {
-        WebError::internal_server_error(format!("Failed to read directory entry: {}", e))
-    })? {
-        let path = entry.path();
-
-        // Only process .yaml and .yml files
-        if let Some(extension) = path.extension() {
-            if extension == "yaml" || extension == "yml" {
-                match load_rule_file(&path).await {
-                    Ok(mut file_rules) => {
-                        rules.append(&mut file_rules);
-                    }
-                    Err(e) => {
-                        tracing::warn!("Failed to load rule file {}: {}", path.display(), e);
-                        // Continue processing other files
-                    }
-                }
-            }
-        }
-    }
+/// The rules of the rules directory from the rule repository shared with analysis requests,
+/// or the fallback rules when the directory has none
+fn shared_rule_infos(config: &WebConfig) -> Vec<RuleInfo> {
+    let rules: Vec<RuleInfo> = with_shared_rules(config, |repository| {
+        repository
+            .rules()
+            .filter_map(|rule| repository.get(&rule.id))
+            .filter(|loaded| std::path::Path::new(&loaded.origin).starts_with(&config.rules_directory))
+            .map(|loaded| rule_info(&loaded.rule))
+            .collect()
+    });
 
-    // If no rules were loaded, return fallback rules
     if rules.is_empty() {
-        tracing::info!("No rules loaded from directory, using fallback rules");
-        Ok(get_fallback_rules())
+        tracing::info!("No rules loaded from {}, using fallback rules", config.rules_directory.display());
+        get_fallback_rules()
     } else {
         tracing::info!("Loaded {} rules from directory", rules.len());
-        Ok(rules)
+        rules
     }
 }
 
-/// Load rules from a single YAML file
-async fn load_rule_file(file_path: &std::path::Path) -> WebResult<Vec<RuleInfo>> {
-    use tokio::fs;
-
-    let content = fs::read_to_string(file_path).await.map_err(|e| {
-        WebError::internal_server_error(format!("Failed to read rule file {}: {}", file_path.display(), e))
-    })?;
-
-    let yaml_value: serde_yaml::Value = serde_yaml::from_str(&content).map_err(|e| {
-        WebError::internal_server_error(format!("Failed to parse YAML in {}: {}", file_path.display(), e))
-    })?;
-
-    parse_rules_from_yaml(&yaml_value, file_path)
-}
-
-/// Parse rules from YAML value
-fn parse_rules_from_yaml(yaml_value: &serde_yaml::Value, file_path: &std::path::Path) -> WebResult<Vec<RuleInfo>> {
-    let mut rules = Vec::new();
-
-    match yaml_value {
-        serde_yaml::Value::Sequence(rule_list) => {
-            for (index, rule_yaml) in rule_list.iter().enumerate() {
-                match parse_single_rule(rule_yaml, file_path, index) {
-                    Ok(rule) => rules.push(rule),
-                    Err(e) => {
-                        tracing::warn!("Failed to parse rule {} in {}: {}", index, file_path.display(), e);
-                        // Continue parsing other rules
-                    }
-                }
-            }
-        }
-        serde_yaml::Value::Mapping(_) => {
-            // Single rule in file
-            match parse_single_rule(yaml_value, file_path, 0) {
-                Ok(rule) => rules.push(rule),
-                Err(e) => {
-                    tracing::warn!("Failed to parse rule in {}: {}", file_path.display(), e);
-                }
-            }
-        }
-        _ => {
-            return Err(WebError::internal_server_error(format!(
-                "Invalid YAML structure in {}: expected sequence or mapping",
-                file_path.display()
-            )));
-        }
-    }
-
-    Ok(rules)
-}
-
-/// Parse a single rule from YAML
-fn parse_single_rule(rule_yaml: &serde_yaml::Value, file_path: &std::path::Path, index: usize) -> WebResult<RuleInfo> {
-    let rule_map = rule_yaml.as_mapping().ok_or_else(|| {
-        WebError::internal_server_error(format!("Rule {} in {} is not a mapping", index, file_path.display()))
-    })?;
-
-    // Extract required fields
-    let id = extract_string_field(rule_map, "id", file_path, index)?;
-    let name = extract_string_field(rule_map, "name", file_path, index)?;
-    let description = extract_string_field(rule_map, "description", file_path, index)?;
-    let severity = extract_string_field(rule_map, "severity", file_path, index)?;
-
-    // Extract optional fields with defaults
-    let confidence = extract_string_field(rule_map, "confidence", file_path, index)
-        .unwrap_or_else(|_| "medium".to_string());
-    let enabled = extract_bool_field(rule_map, "enabled", file_path, index)
-        .unwrap_or(true);
-
-    // Extract arrays
-    let languages = extract_string_array(rule_map, "languages", file_path, index)?;
-    let tags = extract_string_array(rule_map, "tags", file_path, index)
-        .unwrap_or_else(|_| Vec::new());
-
-    // Extract optional fields
-    let category = extract_optional_string_field(rule_map, "category");
-    let metadata = extract_metadata(rule_map);
-
-    Ok(RuleInfo {
-        id,
-        name,
-        description,
-        languages,
-        severity,
-        confidence,
-        category,
+/// API view of a loaded rule; `category` and comma-separated `tags` come from its metadata
+fn rule_info(rule: &Rule) -> RuleInfo {
+    let tags = rule
+        .get_metadata("tags")
+        .map(|tags| tags.split(',').map(str::trim).filter(|tag| !tag.is_empty()).map(str::to_string).collect())
+        .unwrap_or_default();
+    RuleInfo {
+        id: rule.id.clone(),
+        name: rule.name.clone(),
+        description: rule.description.clone(),
+        languages: rule.languages.iter().map(|language| language.as_str().to_string()).collect(),
+        severity: rule.severity.as_str().to_lowercase(),
+        confidence: rule.confidence.as_str().to_lowercase(),
+        category: rule.get_metadata("category").cloned(),
         tags,
-        enabled,
-        metadata,
-    })
-}
-
-/// Extract string field from YAML mapping
-fn extract_string_field(
-    map: &serde_yaml::Mapping,
-    field: &str,
-    file_path: &std::path::Path,
-    index: usize,
-) -> WebResult<String> {
-    map.get(&serde_yaml::Value::String(field.to_string()))
-        .and_then(|v| v.as_str())
-        .map(|s| s.to_string())
-        .ok_or_else(|| {
-            WebError::internal_server_error(format!(
-                "Missing or invalid '{}' field in rule {} of {}",
-                field,
-                index,
-                file_path.display()
-            ))
-        })
-}
-
-/// Extract optional string field from YAML mapping
-fn extract_optional_string_field(map: &serde_yaml::Mapping, field: &str) -> Option<String> {
-    map.get(&serde_yaml::Value::String(field.to_string()))
-        .and_then(|v| v.as_str())
-        .map(|s| s.to_string())
-}
-
-/// Extract boolean field from YAML mapping
-fn extract_bool_field(
-    map: &serde_yaml::Mapping,
-    field: &str,
-    file_path: &std::path::Path,
-    index: usize,
-) -> WebResult<bool> {
-    map.get(&serde_yaml::Value::String(field.to_string()))
-        .and_then(|v| v.as_bool())
-        .ok_or_else(|| {
-            WebError::internal_server_error(format!(
-                "Missing or invalid '{}' field in rule {} of {}",
-                field,
-                index,
-                file_path.display()
-            ))
-        })
-}
-
-/// Extract string array from YAML mapping
-fn extract_string_array(
-    map: &serde_yaml::Mapping,
-    field: &str,
-    file_path: &std::path::Path,
-    index: usize,
-) -> WebResult<Vec<String>> {
-    map.get(&serde_yaml::Value::String(field.to_string()))
-        .and_then(|v| v.as_sequence())
-        .map(|seq| {
-            seq.iter()
-                .filter_map(|item| item.as_str().map(|s| s.to_string()))
-                .collect()
-        })
-        .ok_or_else(|| {
-            WebError::internal_server_error(format!(
-                "Missing or invalid '{}' field in rule {} of {}",
-                field,
-                index,
-                file_path.display()
-            ))
-        })
-}
-
-/// Extract metadata from YAML mapping
-fn extract_metadata(map: &serde_yaml::Mapping) -> HashMap<String, String> {
-    let mut metadata = HashMap::new();
-
-    if let Some(meta_value) = map.get(&serde_yaml::Value::String("metadata".to_string())) {
-        if let Some(meta_map) = meta_value.as_mapping() {
-            for (key, value) in meta_map {
-                if let (Some(key_str), Some(value_str)) = (key.as_str(), value.as_str()) {
-                    metadata.insert(key_str.to_string(), value_str.to_string());
-                }
-            }
-        }
+        enabled: rule.enabled,
+        metadata: rule.metadata.clone(),
     }
-
-    metadata
 }
 
 /// Get fallback rules when no rules can be loaded from files
@@ -613,6 +423,41 @@ mod tests {
         assert_eq!(rule.severity, "warning");
     }
 
+    #[tokio::test]
+    async fn test_list_rules_from_shared_repository() {
+        let temp_dir = tempdir().unwrap();
+        std::fs::write(
+            temp_dir.path().join("python.yaml"),
+            "rules:\n  - id: shared-no-eval\n    message: Avoid eval\n    severity: ERROR\n    languages: [python]\n    metadata:\n      category: security\n      tags: injection, eval\n    pattern: eval($X)\n",
+        )
+        .unwrap();
+        let config = Arc::new(WebConfig {
+            rules_directory: temp_dir.path().to_path_buf(),
+            ..Default::default()
+        });
+
+        let query = ListRulesQuery {
+            language: Some("python".to_string()),
+            category: Some("security".to_string()),
+            enabled: None,
+            limit: None,
+            offset: None,
+        };
+        let rules = list_rules(State(config.clone()), Query(query)).await.unwrap().0;
+        assert_eq!(rules.len(), 1);
+        assert_eq!(rules[0].id, "shared-no-eval");
+        assert_eq!(rules[0].severity, "error");
+        assert_eq!(rules[0].tags, vec!["injection".to_string(), "eval".to_string()]);
+
+        // Served from the repository analysis requests use
+        let repository = astgrep_rules::RuleRepository::global().read().unwrap();
+        assert!(repository.rule("shared-no-eval").is_some());
+        drop(repository);
+
+        let rule = get_rule(State(config), Path("shared-no-eval".to_string())).await.unwrap().0;
+        assert_eq!(rule.languages, vec!["python".to_string()]);
+    }
+
     #[tokio::test]
     async fn test_get_rule_not_found() {
         let temp_dir = tempdir().unwrap();
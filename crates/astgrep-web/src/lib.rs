@@ -53,7 +53,11 @@ pub fn create_app(config: Arc<WebConfig>) -> Router {
         .route("/analyze/sarif", post(handlers::analyze::analyze_code_sarif))
         .route("/analyze/file", post(handlers::analyze::analyze_file_flexible))
         .route("/analyze/archive", post(handlers::analyze::analyze_archive))
+        .route("/jobs/events", get(handlers::jobs::stream_all_job_events))
         .route("/jobs/:id", get(handlers::jobs::get_job_status))
+        .route("/jobs/:id/events", get(handlers::jobs::stream_job_events))
+        .route("/jobs/:id/results", get(handlers::jobs::get_job_results))
+        .route("/jobs/:id/cancel", post(handlers::jobs::cancel_job))
         .route("/jobs", get(handlers::jobs::list_jobs))
         .route("/rules", get(handlers::rules::list_rules))
         .route("/rules/:id", get(handlers::rules::get_rule))
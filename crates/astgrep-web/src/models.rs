@@ -106,6 +106,9 @@ pub struct AnalysisOptions {
     /// YAML 'options.sql_statement_boundary' in rules overrides this per-rule.
     pub sql_statement_boundary: Option<bool>,
 
+    /// Redaction of matched text: mask, partial[:PREFIX:SUFFIX], hash or none.
+    /// Overrides the server default; rules may override both via metadata.redact.
+    pub redact: Option<String>,
 }
 
 /// Analysis response
@@ -266,6 +269,13 @@ pub enum JobStatus {
     Cancelled,
 }
 
+impl JobStatus {
+    /// Whether the job has reached a final state
+    pub fn is_finished(&self) -> bool {
+        matches!(self, JobStatus::Completed | JobStatus::Failed | JobStatus::Cancelled)
+    }
+}
+
 /// Job information
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Job {
@@ -297,6 +307,50 @@ pub struct Job {
     pub metadata: HashMap<String, serde_json::Value>,
 }
 
+/// Progress update of a job, streamed as a server-sent event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobProgress {
+    /// Job identifier
+    pub job_id: Uuid,
+
+    /// Job status
+    pub status: JobStatus,
+
+    /// Current stage (e.g. "extracting", "analyzing", or the final status)
+    pub stage: String,
+
+    /// Progress percentage (0-100)
+    pub progress: u8,
+
+    /// Number of files to analyze (0 while unknown)
+    pub files_total: usize,
+
+    /// Number of files analyzed so far
+    pub files_completed: usize,
+
+    /// Number of findings so far
+    pub findings: usize,
+
+    /// Time of the update
+    pub timestamp: DateTime<Utc>,
+}
+
+impl JobProgress {
+    /// Progress snapshot of a job without detailed counters
+    pub fn from_job(job: &Job) -> Self {
+        Self {
+            job_id: job.id,
+            status: job.status.clone(),
+            stage: format!("{:?}", job.status).to_lowercase(),
+            progress: job.progress,
+            files_total: 0,
+            files_completed: 0,
+            findings: 0,
+            timestamp: Utc::now(),
+        }
+    }
+}
+
 /// Rule information
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RuleInfo {
@@ -458,6 +512,7 @@ impl Default for AnalysisOptions {
             mode: Some("normal".to_string()),
             // Default ON for SQL statement boundary; YAML can override per-rule
             sql_statement_boundary: Some(true),
+            redact: None,
         }
     }
 }
@@ -86,12 +86,11 @@ public class VulnerableApp {
         .expect("Failed to parse Java file");
 
     // Create rule context
-    let context = RuleContext {
-        file_path: java_file.to_string_lossy().to_string(),
-        language: Language::Java,
-        source_code: java_source.clone(),
-        custom_data: std::collections::HashMap::new(),
-    };
+    let context = RuleContext::new(
+        java_file.to_string_lossy().to_string(),
+        Language::Java,
+        java_source.clone(),
+    );
 
     // Run comprehensive analysis
     let findings = rule_engine.analyze(&*java_ast, &context)